@@ -0,0 +1,312 @@
+//! Aave V3 lending positions: every reserve a configured wallet supplies or borrows, read
+//! on-chain from the market's Pool and price oracle, as [`Position`]s carrying the account's
+//! health factor and the reserve's rate.
+
+use anyhow::{anyhow, Context, Result};
+use ethabi::{ParamType, Token as AbiToken};
+use ethereum_types::{Address, U256};
+use rust_decimal::Decimal;
+use tracing::{debug, info};
+
+use crate::config::AaveConfig;
+use crate::position::{LendingMetrics, LendingSide, Position};
+use crate::uniswap::{encode_call, UniswapClient};
+use crate::utils::{from_base_units, to_units};
+
+pub const PROTOCOL: &str = "aave_v3";
+
+/// Aave rates are per-year fractions scaled by 1e27 (ray)
+const RAY_DECIMALS: u8 = 27;
+/// Health factors are scaled by 1e18 (wad)
+const WAD_DECIMALS: u8 = 18;
+/// Oracle prices and account values are in USD with 8 decimals
+const BASE_CURRENCY_DECIMALS: u8 = 8;
+/// Liquidation thresholds are in basis points
+const BPS: f64 = 10_000.0;
+
+/// A reserve (listed asset) of the market and the tokens tracking its balances
+#[derive(Debug, Clone, PartialEq)]
+struct Reserve {
+    asset: String,
+    a_token: String,
+    variable_debt_token: String,
+    supply_apr: f64,
+    borrow_apr: f64,
+}
+
+/// Account-wide figures from `getUserAccountData`
+#[derive(Debug, Clone, PartialEq)]
+struct Account {
+    health_factor: Option<f64>,
+    liquidation_threshold: f64,
+}
+
+pub struct AaveAdapter {
+    client: UniswapClient,
+    rpc_url: String,
+    pool: String,
+    oracle: String,
+    wallets: Vec<String>,
+}
+
+impl AaveAdapter {
+    pub fn new(client: UniswapClient, rpc_url: &str, config: &AaveConfig) -> Self {
+        Self {
+            client,
+            rpc_url: rpc_url.to_string(),
+            pool: config.pool.clone(),
+            oracle: config.oracle.clone(),
+            wallets: config.wallets.iter().map(|w| w.to_lowercase()).collect(),
+        }
+    }
+
+    /// One position per wallet and reserve with a supply or borrow balance
+    pub async fn positions(&self) -> Result<Vec<Position>> {
+        let reserves = self.reserves().await?;
+        let assets: Vec<&str> = reserves.iter().map(|r| r.asset.as_str()).collect();
+        let decimals = self
+            .client
+            .tokens_decimals(&self.rpc_url, &assets)
+            .await
+            .into_iter()
+            .collect::<Result<Vec<u8>>>()
+            .context("reading Aave reserve decimals")?;
+        let prices = self.prices(&assets).await?;
+
+        // Per wallet: account data, then the aToken and variable debt balance of each reserve
+        let mut calls = Vec::with_capacity(self.wallets.len() * (1 + 2 * reserves.len()));
+        for wallet in &self.wallets {
+            let owner = AbiToken::Address(wallet.parse::<Address>().with_context(|| format!("invalid wallet {}", wallet))?);
+            calls.push((self.pool.clone(), encode_call("getUserAccountData(address)", std::slice::from_ref(&owner))));
+            for reserve in &reserves {
+                let balance_of = encode_call("balanceOf(address)", std::slice::from_ref(&owner));
+                calls.push((reserve.a_token.clone(), balance_of.clone()));
+                calls.push((reserve.variable_debt_token.clone(), balance_of));
+            }
+        }
+        let mut results = self.client.eth_call_batch(&self.rpc_url, &calls).await?.into_iter();
+
+        let mut positions = Vec::new();
+        for wallet in &self.wallets {
+            let account = results
+                .next()
+                .ok_or_else(|| anyhow!("missing getUserAccountData result"))?
+                .and_then(|bytes| decode_account(&bytes))
+                .with_context(|| format!("reading Aave account {}", wallet))?;
+            for (i, reserve) in reserves.iter().enumerate() {
+                for (side, apr) in [(LendingSide::Supply, reserve.supply_apr), (LendingSide::Borrow, reserve.borrow_apr)] {
+                    let balance = results
+                        .next()
+                        .ok_or_else(|| anyhow!("missing balanceOf result"))?
+                        .and_then(|bytes| word(&bytes, 0))
+                        .with_context(|| format!("reading {} balance of {}", reserve.asset, wallet))?;
+                    if balance.is_zero() {
+                        continue;
+                    }
+                    let amount = from_base_units(balance, decimals[i])?;
+                    let value_usd = amount * prices[i];
+                    let mut position = Position::new(
+                        position_id(wallet, &reserve.asset, side),
+                        wallet.clone(),
+                        reserve.asset.clone(),
+                        amount,
+                        value_usd.round_dp(2),
+                    );
+                    position.lending = Some(LendingMetrics {
+                        protocol: PROTOCOL.to_string(),
+                        side,
+                        health_factor: account.health_factor,
+                        apr,
+                        liquidation_threshold: account.liquidation_threshold,
+                    });
+                    debug!(target: "aave", wallet = %wallet, asset = %reserve.asset, side = side.as_str(), %amount, "lending position");
+                    positions.push(position);
+                }
+            }
+        }
+        info!(target: "aave", wallets = self.wallets.len(), positions = positions.len(), "fetched Aave positions");
+        Ok(positions)
+    }
+
+    /// Every reserve of the market with its token addresses and current rates
+    async fn reserves(&self) -> Result<Vec<Reserve>> {
+        let list = self
+            .client
+            .eth_call_batch(&self.rpc_url, &[(self.pool.clone(), encode_call("getReservesList()", &[]))])
+            .await?
+            .remove(0)
+            .context("reading the Aave reserves list")?;
+        let assets = decode_address_array(&list)?;
+        let calls: Vec<(String, Vec<u8>)> = assets
+            .iter()
+            .map(|asset| (self.pool.clone(), encode_call("getReserveData(address)", &[AbiToken::Address(*asset)])))
+            .collect();
+        self.client
+            .eth_call_batch(&self.rpc_url, &calls)
+            .await?
+            .into_iter()
+            .zip(&assets)
+            .map(|(result, asset)| {
+                result
+                    .and_then(|bytes| decode_reserve(asset, &bytes))
+                    .with_context(|| format!("reading Aave reserve {:?}", asset))
+            })
+            .collect()
+    }
+
+    /// USD price of each asset, in order
+    async fn prices(&self, assets: &[&str]) -> Result<Vec<Decimal>> {
+        let addresses = assets
+            .iter()
+            .map(|a| a.parse::<Address>().map(AbiToken::Address))
+            .collect::<Result<Vec<_>, _>>()?;
+        let call = encode_call("getAssetsPrices(address[])", &[AbiToken::Array(addresses)]);
+        let bytes = self
+            .client
+            .eth_call_batch(&self.rpc_url, &[(self.oracle.clone(), call)])
+            .await?
+            .remove(0)
+            .context("reading Aave oracle prices")?;
+        let tokens = ethabi::decode(&[ParamType::Array(Box::new(ParamType::Uint(256)))], &bytes)?;
+        let prices = match tokens.into_iter().next() {
+            Some(AbiToken::Array(prices)) => prices,
+            _ => return Err(anyhow!("unexpected getAssetsPrices result")),
+        };
+        if prices.len() != assets.len() {
+            return Err(anyhow!("oracle returned {} prices for {} assets", prices.len(), assets.len()));
+        }
+        prices
+            .into_iter()
+            .map(|price| match price {
+                AbiToken::Uint(raw) => from_base_units(raw, BASE_CURRENCY_DECIMALS),
+                other => Err(anyhow!("unexpected price {:?}", other)),
+            })
+            .collect()
+    }
+}
+
+/// Stable id of a wallet's supply or borrow position in one reserve
+pub fn position_id(wallet: &str, asset: &str, side: LendingSide) -> String {
+    format!("aave:{}:{}:{}", wallet.to_lowercase(), asset.to_lowercase(), side.as_str())
+}
+
+/// The `i`th 32-byte word of ABI-encoded return data
+fn word(bytes: &[u8], i: usize) -> Result<U256> {
+    bytes
+        .get(32 * i..32 * (i + 1))
+        .map(U256::from_big_endian)
+        .ok_or_else(|| anyhow!("return data too short for word {}", i))
+}
+
+fn word_address(bytes: &[u8], i: usize) -> Result<String> {
+    let mut raw = [0u8; 32];
+    word(bytes, i)?.to_big_endian(&mut raw);
+    Ok(format!("{:?}", Address::from_slice(&raw[12..])))
+}
+
+fn decode_address_array(bytes: &[u8]) -> Result<Vec<Address>> {
+    match ethabi::decode(&[ParamType::Array(Box::new(ParamType::Address))], bytes)?.into_iter().next() {
+        Some(AbiToken::Array(items)) => Ok(items.into_iter().filter_map(|t| t.into_address()).collect()),
+        _ => Err(anyhow!("expected an address array")),
+    }
+}
+
+/// `getReserveData` returns a static struct: configuration, liquidityIndex,
+/// currentLiquidityRate, variableBorrowIndex, currentVariableBorrowRate,
+/// currentStableBorrowRate, lastUpdateTimestamp, id, aTokenAddress,
+/// stableDebtTokenAddress, variableDebtTokenAddress, ...
+fn decode_reserve(asset: &Address, bytes: &[u8]) -> Result<Reserve> {
+    Ok(Reserve {
+        asset: format!("{:?}", asset),
+        a_token: word_address(bytes, 8)?,
+        variable_debt_token: word_address(bytes, 10)?,
+        supply_apr: to_units(word(bytes, 2)?, RAY_DECIMALS),
+        borrow_apr: to_units(word(bytes, 4)?, RAY_DECIMALS),
+    })
+}
+
+/// `getUserAccountData`: totalCollateralBase, totalDebtBase, availableBorrowsBase,
+/// currentLiquidationThreshold, ltv, healthFactor
+fn decode_account(bytes: &[u8]) -> Result<Account> {
+    let total_debt = word(bytes, 1)?;
+    Ok(Account {
+        // Without debt the health factor is uint256 max
+        health_factor: (!total_debt.is_zero()).then(|| word(bytes, 5)).transpose()?.map(|hf| to_units(hf, WAD_DECIMALS)),
+        liquidation_threshold: to_units(word(bytes, 3)?, 0) / BPS,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn words(values: &[U256]) -> Vec<u8> {
+        ethabi::encode(&values.iter().map(|v| AbiToken::Uint(*v)).collect::<Vec<_>>())
+    }
+
+    #[test]
+    fn test_decode_reserve_and_account() {
+        let asset: Address = "0xaf88d065e77c8cc2239327c5edb3a432268e5831".parse().unwrap();
+        let a_token = U256::from_big_endian(&[0x11; 20]);
+        let debt_token = U256::from_big_endian(&[0x22; 20]);
+        let ray = U256::exp10(27);
+        let mut data = vec![U256::zero(); 15];
+        data[2] = ray * 3 / 100;
+        data[4] = ray * 5 / 100;
+        data[8] = a_token;
+        data[10] = debt_token;
+        let reserve = decode_reserve(&asset, &words(&data)).unwrap();
+        assert_eq!(reserve.a_token, format!("0x{}", "11".repeat(20)));
+        assert_eq!(reserve.variable_debt_token, format!("0x{}", "22".repeat(20)));
+        assert!((reserve.supply_apr - 0.03).abs() < 1e-12);
+        assert!((reserve.borrow_apr - 0.05).abs() < 1e-12);
+
+        let wad = U256::exp10(18);
+        let account = decode_account(&words(&[
+            U256::from(10_000u64),
+            U256::from(6_000u64),
+            U256::zero(),
+            U256::from(8_250u64),
+            U256::from(8_000u64),
+            wad * 5 / 4,
+        ]))
+        .unwrap();
+        assert_eq!(account, Account { health_factor: Some(1.25), liquidation_threshold: 0.825 });
+
+        // No debt: the health factor is meaningless
+        let account = decode_account(&words(&[U256::from(10_000u64), U256::zero(), U256::zero(), U256::zero(), U256::zero(), U256::MAX])).unwrap();
+        assert_eq!(account.health_factor, None);
+        assert!(decode_account(&[0u8; 64]).is_err());
+    }
+
+    #[test]
+    fn test_liquidation_risk_drives_lending_position_risk() {
+        let mut position = Position::new(
+            position_id("0xAA", "0xBB", LendingSide::Borrow),
+            "0xaa".to_string(),
+            "0xbb".to_string(),
+            Decimal::ONE,
+            Decimal::ONE,
+        );
+        assert_eq!(position.id, "aave:0xaa:0xbb:borrow");
+        let market = crate::position::MarketData::new();
+        position.calculate_risk_score(&market);
+        let market_risk = position.risk_score;
+
+        position.lending = Some(LendingMetrics {
+            protocol: PROTOCOL.to_string(),
+            side: LendingSide::Borrow,
+            health_factor: Some(1.25),
+            apr: 0.05,
+            liquidation_threshold: 0.825,
+        });
+        position.calculate_risk_score(&market);
+        assert!((position.risk_score - 0.8).abs() < 1e-12);
+        assert!(position.risk_score > market_risk);
+
+        // Without debt only the market risk remains
+        position.lending.as_mut().unwrap().health_factor = None;
+        position.calculate_risk_score(&market);
+        assert_eq!(position.risk_score, market_risk);
+    }
+}
@@ -1,26 +1,205 @@
-use anyhow::Result;
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
 use rust_decimal::prelude::ToPrimitive;
+use serde::{Deserialize, Serialize};
 use smartcore::linalg::basic::matrix::DenseMatrix;
 use smartcore::linear::linear_regression::LinearRegression;
 use smartcore::ensemble::random_forest_regressor::RandomForestRegressor;
 use std::collections::HashMap;
-use tracing::{info, warn, error};
+use std::fs;
+use std::path::{Path, PathBuf};
+use tracing::{debug, info, warn, error};
 
 use crate::position::{Position, MarketData};
-use crate::config::Config;
+use crate::config::{AiConfig, Config};
+use crate::market_data::SharedMarketData;
+use crate::training_data::TrainingSample;
+
+/// Version of the model file layout and feature vector; files of another version are
+/// skipped at load, so bump it when either changes
+const MODEL_FORMAT: u32 = 2;
+
+/// Models trained on their own, which the ensemble combines
+const BASE_MODELS: [&str; 2] = ["random_forest", "linear_regression"];
+/// Ensemble weights until cross-validation has scored the base models
+const DEFAULT_ENSEMBLE_WEIGHTS: [(&str, f64); 2] = [("random_forest", 0.5), ("linear_regression", 0.3)];
+/// Cross-validation folds without `[ai]`
+const DEFAULT_CV_FOLDS: usize = 5;
+
+/// Length of the feature vector of [`features`]
+pub const FEATURE_COUNT: usize = 10;
+
+/// The features of `position` the models predict from
+pub fn features(market_data: &MarketData, position: &Position) -> Vec<f64> {
+    vec![
+        position.value_usd.to_f64().unwrap_or(0.0),
+        position.risk_score,
+        position.liquidity_score,
+        market_data.get_volatility(&position.token_address),
+        market_data.get_market_cap(&position.token_address),
+        market_data.get_volume(&position.token_address),
+        market_data.get_depth(&position.token_address),
+        position.timestamp as f64,
+        // Add more features as needed
+        AIPredictor::calculate_momentum_score(market_data, position),
+        AIPredictor::calculate_technical_indicators(market_data, position),
+    ]
+}
 
 /// AI-powered position predictor using multiple ML approaches
 pub struct AIPredictor {
     config: Config,
     models: HashMap<String, Box<dyn PredictionModel>>,
-    market_data: MarketData,
+    /// From the latest cross-validation, by model name
+    metrics: HashMap<String, ModelMetrics>,
+    market_data: SharedMarketData,
 }
 
 /// Trait for different prediction models
-pub trait PredictionModel {
+pub trait PredictionModel: Send + Sync {
     fn predict(&self, features: &[f64]) -> Result<f64>;
     fn train(&mut self, features: &[Vec<f64>], targets: &[f64]) -> Result<()>;
     fn model_name(&self) -> &str;
+    /// The trained model, for saving; `None` until trained
+    fn snapshot(&self) -> Result<Option<SavedModel>>;
+}
+
+/// A trained model as written to a model file; SmartCore models are kept bincode-encoded
+#[derive(Serialize, Deserialize)]
+pub enum SavedModel {
+    RandomForest(Vec<u8>),
+    LinearRegression(Vec<u8>),
+    /// Members and their weights
+    Ensemble(Vec<(SavedModel, f64)>),
+}
+
+impl SavedModel {
+    fn into_model(self) -> Result<Box<dyn PredictionModel>> {
+        Ok(match self {
+            SavedModel::RandomForest(bytes) => Box::new(RandomForestModel { model: Some(bincode::deserialize(&bytes)?) }),
+            SavedModel::LinearRegression(bytes) => Box::new(LinearRegressionModel { model: Some(bincode::deserialize(&bytes)?) }),
+            SavedModel::Ensemble(members) => {
+                let mut ensemble = EnsembleModel::new();
+                for (member, weight) in members {
+                    ensemble.add_model(member.into_model()?, weight);
+                }
+                Box::new(ensemble)
+            }
+        })
+    }
+}
+
+/// Contents of a `models-v<N>.bin` file
+#[derive(Serialize, Deserialize)]
+struct ModelFile {
+    format: u32,
+    trained_at: DateTime<Utc>,
+    models: HashMap<String, SavedModel>,
+    /// Cross-validation metrics of the training run, by model name
+    metrics: HashMap<String, ModelMetrics>,
+}
+
+/// Out-of-fold accuracy of a model from k-fold cross-validation
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ModelMetrics {
+    pub rmse: f64,
+    pub mae: f64,
+    /// Share of the targets' variance explained; 0 predicts no better than their mean
+    pub r2: f64,
+    pub folds: usize,
+}
+
+impl ModelMetrics {
+    /// Metrics of `predictions` against `targets`
+    pub fn from_predictions(predictions: &[f64], targets: &[f64], folds: usize) -> Self {
+        let n = targets.len().max(1) as f64;
+        let mean = targets.iter().sum::<f64>() / n;
+        let (mut squared, mut absolute, mut total) = (0.0, 0.0, 0.0);
+        for (prediction, target) in predictions.iter().zip(targets) {
+            squared += (target - prediction).powi(2);
+            absolute += (target - prediction).abs();
+            total += (target - mean).powi(2);
+        }
+        Self {
+            rmse: (squared / n).sqrt(),
+            mae: absolute / n,
+            r2: if total > 0.0 { 1.0 - squared / total } else { 0.0 },
+            folds,
+        }
+    }
+}
+
+/// An untrained base model by name
+fn new_model(name: &str) -> Option<Box<dyn PredictionModel>> {
+    match name {
+        "random_forest" => Some(Box::new(RandomForestModel::new())),
+        "linear_regression" => Some(Box::new(LinearRegressionModel::new())),
+        _ => None,
+    }
+}
+
+/// An untrained ensemble of base models with these weights
+fn new_ensemble(weights: &[(&str, f64)]) -> EnsembleModel {
+    let mut ensemble = EnsembleModel::new();
+    for (name, weight) in weights {
+        if let Some(model) = new_model(name) {
+            ensemble.add_model(model, *weight);
+        }
+    }
+    ensemble
+}
+
+/// Each sample's prediction by a fresh `name` model trained on the other folds; sample `i`
+/// is in fold `i % folds`
+fn out_of_fold(name: &str, features: &[Vec<f64>], targets: &[f64], folds: usize) -> Result<Vec<f64>> {
+    let mut predictions = vec![0.0; targets.len()];
+    for fold in 0..folds {
+        let (mut train_x, mut train_y) = (Vec::new(), Vec::new());
+        for (i, (x, y)) in features.iter().zip(targets).enumerate() {
+            if i % folds != fold {
+                train_x.push(x.clone());
+                train_y.push(*y);
+            }
+        }
+        let mut model = new_model(name).with_context(|| format!("unknown model {}", name))?;
+        model.train(&train_x, &train_y).with_context(|| format!("training fold {}", fold))?;
+        for i in (fold..targets.len()).step_by(folds) {
+            predictions[i] = model.predict(&features[i])?;
+        }
+    }
+    Ok(predictions)
+}
+
+/// Ensemble weights of the scored base models, by inverse out-of-fold MSE and summing to 1
+fn inverse_mse_weights(metrics: &HashMap<String, ModelMetrics>) -> Vec<(&'static str, f64)> {
+    let inverse: Vec<(&'static str, f64)> = BASE_MODELS
+        .iter()
+        .filter_map(|name| Some((*name, 1.0 / metrics.get(*name)?.rmse.powi(2).max(1e-12))))
+        .collect();
+    let total: f64 = inverse.iter().map(|(_, w)| w).sum();
+    inverse.into_iter().map(|(name, w)| (name, w / total)).collect()
+}
+
+/// Model files in `dir` by version, oldest first; none when `dir` doesn't exist
+fn model_versions(dir: &Path) -> Result<Vec<(u64, PathBuf)>> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e).with_context(|| format!("reading {}", dir.display())),
+    };
+    let mut versions = Vec::new();
+    for entry in entries {
+        let path = entry?.path();
+        let version = path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .and_then(|n| n.strip_prefix("models-v")?.strip_suffix(".bin")?.parse::<u64>().ok());
+        if let Some(version) = version {
+            versions.push((version, path));
+        }
+    }
+    versions.sort();
+    Ok(versions)
 }
 
 /// Random Forest Model using SmartCore
@@ -34,6 +213,12 @@ impl RandomForestModel {
     }
 }
 
+impl Default for RandomForestModel {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl PredictionModel for RandomForestModel {
     fn predict(&self, features: &[f64]) -> Result<f64> {
         if let Some(ref model) = self.model {
@@ -57,6 +242,10 @@ impl PredictionModel for RandomForestModel {
     fn model_name(&self) -> &str {
         "RandomForest"
     }
+
+    fn snapshot(&self) -> Result<Option<SavedModel>> {
+        Ok(self.model.as_ref().map(bincode::serialize).transpose()?.map(SavedModel::RandomForest))
+    }
 }
 
 /// Linear Regression Model using SmartCore
@@ -70,6 +259,12 @@ impl LinearRegressionModel {
     }
 }
 
+impl Default for LinearRegressionModel {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl PredictionModel for LinearRegressionModel {
     fn predict(&self, features: &[f64]) -> Result<f64> {
         if let Some(ref model) = self.model {
@@ -93,6 +288,10 @@ impl PredictionModel for LinearRegressionModel {
     fn model_name(&self) -> &str {
         "LinearRegression"
     }
+
+    fn snapshot(&self) -> Result<Option<SavedModel>> {
+        Ok(self.model.as_ref().map(bincode::serialize).transpose()?.map(SavedModel::LinearRegression))
+    }
 }
 
 /// Ensemble Model that combines multiple predictions
@@ -115,6 +314,12 @@ impl EnsembleModel {
     }
 }
 
+impl Default for EnsembleModel {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl PredictionModel for EnsembleModel {
     fn predict(&self, features: &[f64]) -> Result<f64> {
         let mut weighted_sum = 0.0;
@@ -151,6 +356,18 @@ impl PredictionModel for EnsembleModel {
     fn model_name(&self) -> &str {
         "Ensemble"
     }
+
+    /// `None` unless every member is trained, so a restored ensemble keeps all its weights
+    fn snapshot(&self) -> Result<Option<SavedModel>> {
+        let mut members = Vec::new();
+        for (model, weight) in self.models.iter().zip(self.weights.iter()) {
+            match model.snapshot()? {
+                Some(member) => members.push((member, *weight)),
+                None => return Ok(None),
+            }
+        }
+        Ok(Some(SavedModel::Ensemble(members)))
+    }
 }
 
 impl AIPredictor {
@@ -158,88 +375,92 @@ impl AIPredictor {
         let mut predictor = Self {
             config,
             models: HashMap::new(),
-            market_data: MarketData::new(),
+            metrics: HashMap::new(),
+            market_data: SharedMarketData::default(),
         };
 
-        // Initialize models
+        // Initialize models, then replace them with the latest saved ones
         predictor.initialize_models();
+        if predictor.config.ai.is_some() {
+            if let Err(e) = predictor.load_latest() {
+                warn!("Failed to load saved AI models, starting untrained: {:#}", e);
+            }
+        }
         predictor
     }
 
     fn initialize_models(&mut self) {
-        // Add linear regression model
-        let lr_model = Box::new(LinearRegressionModel::new());
-        self.models.insert("linear_regression".to_string(), lr_model);
-
-        // Add random forest model
-        let rf_model = Box::new(RandomForestModel::new());
-        self.models.insert("random_forest".to_string(), rf_model);
+        // Add the base models: random forest and linear regression
+        for name in BASE_MODELS {
+            if let Some(model) = new_model(name) {
+                self.models.insert(name.to_string(), model);
+            }
+        }
 
-        // Add ensemble model
-        let mut ensemble = EnsembleModel::new();
-        ensemble.add_model(Box::new(RandomForestModel::new()), 0.5);
-        ensemble.add_model(Box::new(LinearRegressionModel::new()), 0.3);
-        self.models.insert("ensemble".to_string(), Box::new(ensemble));
+        // Add ensemble model; cross-validation replaces the default weights
+        self.models.insert("ensemble".to_string(), Box::new(new_ensemble(&DEFAULT_ENSEMBLE_WEIGHTS)));
 
         info!("Initialized {} AI models", self.models.len());
     }
 
+    /// Read features from `market_data`, kept current by the market data refresher
+    pub fn with_market_data(mut self, market_data: SharedMarketData) -> Self {
+        self.market_data = market_data;
+        self
+    }
+
     /// Extract features from a position for ML prediction
     pub fn extract_features(&self, position: &Position) -> Vec<f64> {
-        vec![
-            position.value_usd.to_f64().unwrap_or(0.0),
-            position.risk_score,
-            position.liquidity_score,
-            self.market_data.get_volatility(&position.token_address),
-            self.market_data.get_market_cap(&position.token_address),
-            self.market_data.get_volume(&position.token_address),
-            self.market_data.get_depth(&position.token_address),
-            position.timestamp as f64,
-            // Add more features as needed
-            self.calculate_momentum_score(position),
-            self.calculate_technical_indicators(position),
-        ]
+        features(&self.market_data.read().expect("market data lock poisoned"), position)
     }
 
     /// Calculate momentum score for a position
-    fn calculate_momentum_score(&self, position: &Position) -> f64 {
+    fn calculate_momentum_score(market_data: &MarketData, position: &Position) -> f64 {
         // Simple momentum calculation based on recent performance
-        let volatility = self.market_data.get_volatility(&position.token_address);
-        let volume = self.market_data.get_volume(&position.token_address);
+        let volatility = market_data.get_volatility(&position.token_address);
+        let volume = market_data.get_volume(&position.token_address);
         
         // Higher volume and lower volatility = better momentum
         volume / (volatility + 0.1) // Add small constant to avoid division by zero
     }
 
     /// Calculate technical indicators
-    fn calculate_technical_indicators(&self, position: &Position) -> f64 {
+    fn calculate_technical_indicators(market_data: &MarketData, position: &Position) -> f64 {
         // Simple RSI-like calculation
-        let market_cap = self.market_data.get_market_cap(&position.token_address);
-        let volume = self.market_data.get_volume(&position.token_address);
+        let market_cap = market_data.get_market_cap(&position.token_address);
+        let volume = market_data.get_volume(&position.token_address);
         
         // Normalize to 0-1 range
         (volume / market_cap).min(1.0)
     }
 
-    /// Train all models with historical data
-    pub async fn train_models(&mut self, training_data: &[(Position, f64)]) -> Result<()> {
-        if training_data.is_empty() {
+    /// Train all models on labeled samples (see `training_data`); samples recorded with
+    /// another feature layout are left out
+    pub async fn train_models(&mut self, training_data: &[TrainingSample]) -> Result<()> {
+        let usable: Vec<&TrainingSample> = training_data.iter().filter(|s| s.features.len() == FEATURE_COUNT).collect();
+        if usable.is_empty() {
             warn!("No training data provided, using default models");
             return Ok(());
         }
 
-        info!("Training AI models with {} data points", training_data.len());
+        info!("Training AI models with {} data points", usable.len());
 
-        // Extract features and targets
-        let features: Vec<Vec<f64>> = training_data
-            .iter()
-            .map(|(position, _)| self.extract_features(position))
-            .collect();
-        
-        let targets: Vec<f64> = training_data
-            .iter()
-            .map(|(_, target)| *target)
-            .collect();
+        // Features as recorded, targets from the realized returns
+        let features: Vec<Vec<f64>> = usable.iter().map(|s| s.features.clone()).collect();
+        let targets: Vec<f64> = usable.iter().map(|s| s.target()).collect();
+
+        // Score the models on held-out folds and weight the ensemble by those scores
+        let folds = self.config.ai.as_ref().map_or(DEFAULT_CV_FOLDS, |a| a.cv_folds).max(2);
+        if features.len() >= 2 * folds {
+            self.metrics = Self::cross_validate(&features, &targets, folds);
+            let weights = inverse_mse_weights(&self.metrics);
+            if !weights.is_empty() {
+                info!("Ensemble weights from cross-validation: {:?}", weights);
+                self.models.insert("ensemble".to_string(), Box::new(new_ensemble(&weights)));
+            }
+        } else {
+            debug!(samples = features.len(), folds, "Too few samples to cross-validate; keeping ensemble weights");
+        }
 
         // Train each model
         for (name, model) in self.models.iter_mut() {
@@ -249,9 +470,97 @@ impl AIPredictor {
             }
         }
 
+        if self.config.ai.is_some() {
+            if let Err(e) = self.save_models() {
+                warn!("Failed to save trained AI models: {:#}", e);
+            }
+        }
+
         Ok(())
     }
 
+    /// Write the trained models to the next `models-v<N>.bin` in `ai.model_dir`, deleting
+    /// versions beyond `ai.keep_models`; `None` without `[ai]` or with nothing trained
+    pub fn save_models(&self) -> Result<Option<PathBuf>> {
+        let Some(ai) = &self.config.ai else {
+            return Ok(None);
+        };
+        let mut models = HashMap::new();
+        for (name, model) in &self.models {
+            if let Some(saved) = model.snapshot().with_context(|| format!("serializing model {}", name))? {
+                models.insert(name.clone(), saved);
+            }
+        }
+        if models.is_empty() {
+            return Ok(None);
+        }
+        let dir = Path::new(&ai.model_dir);
+        fs::create_dir_all(dir).with_context(|| format!("creating {}", dir.display()))?;
+        let versions = model_versions(dir)?;
+        let version = versions.last().map_or(1, |(v, _)| v + 1);
+        let file = ModelFile { format: MODEL_FORMAT, trained_at: Utc::now(), models, metrics: self.metrics.clone() };
+        let bytes = bincode::serialize(&file).context("serializing AI models")?;
+
+        // Written aside and renamed, so a crash never leaves a partial latest version
+        let path = dir.join(format!("models-v{}.bin", version));
+        let tmp = path.with_extension("bin.tmp");
+        fs::write(&tmp, bytes).with_context(|| format!("writing {}", tmp.display()))?;
+        fs::rename(&tmp, &path).with_context(|| format!("renaming {}", tmp.display()))?;
+        info!(path = %path.display(), models = file.models.len(), "Saved AI models");
+
+        Self::prune(&versions, ai);
+        Ok(Some(path))
+    }
+
+    /// Delete the oldest of `versions` (not counting the one just saved) so `ai.keep_models` remain
+    fn prune(versions: &[(u64, PathBuf)], ai: &AiConfig) {
+        let excess = (versions.len() + 1).saturating_sub(ai.keep_models);
+        for (_, path) in versions.iter().take(excess) {
+            if let Err(e) = fs::remove_file(path) {
+                warn!(path = %path.display(), "Failed to delete old AI model file: {}", e);
+            }
+        }
+    }
+
+    /// Replace the models with those of the newest readable model file in `ai.model_dir`,
+    /// skipping files of another format; `None` when there is none (or no `[ai]`)
+    pub fn load_latest(&mut self) -> Result<Option<PathBuf>> {
+        let Some(ai) = &self.config.ai else {
+            return Ok(None);
+        };
+        for (_, path) in model_versions(Path::new(&ai.model_dir))?.into_iter().rev() {
+            let file: ModelFile = match fs::read(&path).map_err(anyhow::Error::from).and_then(|b| Ok(bincode::deserialize(&b)?)) {
+                Ok(file) => file,
+                Err(e) => {
+                    warn!(path = %path.display(), "Skipping unreadable AI model file: {:#}", e);
+                    continue;
+                }
+            };
+            if file.format != MODEL_FORMAT {
+                warn!(path = %path.display(), format = file.format, "Skipping AI model file of another format");
+                continue;
+            }
+            let trained_at = file.trained_at;
+            let models = match file
+                .models
+                .into_iter()
+                .map(|(name, model)| Ok((name, model.into_model()?)))
+                .collect::<Result<Vec<_>>>()
+            {
+                Ok(models) => models,
+                Err(e) => {
+                    warn!(path = %path.display(), "Skipping AI model file with undecodable models: {:#}", e);
+                    continue;
+                }
+            };
+            info!(path = %path.display(), models = models.len(), trained_at = %trained_at, "Loaded saved AI models");
+            self.models.extend(models);
+            self.metrics = file.metrics;
+            return Ok(Some(path));
+        }
+        Ok(None)
+    }
+
     /// Predict the recommendation score for a position
     pub async fn predict_recommendation_score(&self, position: &Position) -> Result<f64> {
         let features = self.extract_features(position);
@@ -284,22 +593,41 @@ impl AIPredictor {
         Ok(score)
     }
 
-    /// Get model performance metrics
-    pub fn get_model_performance(&self) -> HashMap<String, f64> {
-        let mut performance = HashMap::new();
-        
-        for (name, model) in &self.models {
-            // In a real implementation, you'd calculate actual performance metrics
-            // For now, return placeholder values
-            performance.insert(name.clone(), 0.85); // 85% accuracy placeholder
+    /// Cross-validation metrics of each base model and the ensemble from the latest
+    /// training run (or the loaded model file); empty until then
+    pub fn get_model_performance(&self) -> &HashMap<String, ModelMetrics> {
+        &self.metrics
+    }
+
+    /// k-fold cross-validation of each base model, and of the ensemble they make with
+    /// inverse-MSE weights; a model that fails on any fold is left out
+    pub fn cross_validate(features: &[Vec<f64>], targets: &[f64], folds: usize) -> HashMap<String, ModelMetrics> {
+        let mut metrics = HashMap::new();
+        let mut predictions = HashMap::new();
+        for name in BASE_MODELS {
+            match out_of_fold(name, features, targets, folds) {
+                Ok(p) => {
+                    let m = ModelMetrics::from_predictions(&p, targets, folds);
+                    info!(model = name, rmse = m.rmse, mae = m.mae, r2 = m.r2, "Cross-validated AI model");
+                    metrics.insert(name.to_string(), m);
+                    predictions.insert(name, p);
+                }
+                Err(e) => warn!(model = name, "Cross-validation failed: {:#}", e),
+            }
         }
-        
-        performance
+        let weights = inverse_mse_weights(&metrics);
+        if !weights.is_empty() {
+            let ensemble: Vec<f64> = (0..targets.len())
+                .map(|i| weights.iter().map(|(name, w)| predictions[name][i] * w).sum())
+                .collect();
+            metrics.insert("ensemble".to_string(), ModelMetrics::from_predictions(&ensemble, targets, folds));
+        }
+        metrics
     }
 
-    /// Update market data for better predictions
-    pub fn update_market_data(&mut self, new_market_data: MarketData) {
-        self.market_data = new_market_data;
+    /// Update market data for better predictions; everyone sharing it sees the update
+    pub fn update_market_data(&self, new_market_data: MarketData) {
+        *self.market_data.write().expect("market data lock poisoned") = new_market_data;
         info!("Updated market data for AI predictions");
     }
 }
@@ -343,4 +671,86 @@ mod tests {
         let result = tokio_test::block_on(predictor.predict_recommendation_score(&position));
         assert!(result.is_ok());
     }
+
+    #[test]
+    fn test_models_persist_across_restarts() {
+        let dir = std::env::temp_dir().join(format!("origins-models-test-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let config = Config {
+            ai: Some(AiConfig { model_dir: dir.to_string_lossy().into_owned(), keep_models: 2, ..AiConfig::default() }),
+            ..Config::default()
+        };
+        let mut predictor = AIPredictor::new(config.clone());
+        let training: Vec<TrainingSample> = (0..30)
+            .map(|i| {
+                let mut position = Position::new(
+                    format!("p{}", i),
+                    "0x123".to_string(),
+                    "0x456".to_string(),
+                    Decimal::from(i + 1),
+                    Decimal::from(100 * (i + 1)),
+                );
+                position.risk_score = (i % 7) as f64 / 7.0;
+                position.liquidity_score = (i % 5) as f64 / 5.0;
+                TrainingSample {
+                    features: predictor.extract_features(&position),
+                    position_id: position.id,
+                    token_address: position.token_address,
+                    recorded_at: Utc::now(),
+                    realized_return: 0.1 - (i % 7) as f64 / 35.0,
+                }
+            })
+            .collect();
+
+        tokio_test::block_on(predictor.train_models(&training)).unwrap();
+        tokio_test::block_on(predictor.train_models(&training)).unwrap();
+        tokio_test::block_on(predictor.train_models(&training)).unwrap();
+        let versions: Vec<u64> = model_versions(&dir).unwrap().into_iter().map(|(v, _)| v).collect();
+        assert_eq!(versions, vec![2, 3]);
+
+        // A restart loads the latest version instead of starting untrained
+        let restarted = AIPredictor::new(config);
+        let features = &training[3].features;
+        let trained = predictor.models["random_forest"].predict(features).unwrap();
+        assert_eq!(restarted.models["random_forest"].predict(features).unwrap(), trained);
+        assert!(predictor.get_model_performance().contains_key("random_forest"));
+        assert_eq!(restarted.get_model_performance(), predictor.get_model_performance());
+        assert!(AIPredictor::new(Config::default()).models["random_forest"].predict(features).is_err());
+
+        // An unreadable newer file falls back to the one before it
+        fs::write(dir.join("models-v4.bin"), b"not a model").unwrap();
+        let mut fallback = AIPredictor::new(Config::default());
+        fallback.config.ai = restarted.config.ai.clone();
+        assert_eq!(fallback.load_latest().unwrap(), Some(dir.join("models-v3.bin")));
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_model_metrics() {
+        let m = ModelMetrics::from_predictions(&[1.0, 2.0, 3.0, 5.0], &[1.0, 2.0, 3.0, 4.0], 2);
+        assert!((m.rmse - 0.5).abs() < 1e-12);
+        assert!((m.mae - 0.25).abs() < 1e-12);
+        // Residual sum of squares 1 over a total of 5
+        assert!((m.r2 - 0.8).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_cross_validation_weights_ensemble() {
+        // A linear target the regression recovers exactly and the forest only approximates
+        let features: Vec<Vec<f64>> = (0..40).map(|i| vec![(i % 10) as f64, ((i * 7) % 13) as f64]).collect();
+        let targets: Vec<f64> = features.iter().map(|x| 0.2 + 0.03 * x[0] - 0.01 * x[1]).collect();
+
+        let metrics = AIPredictor::cross_validate(&features, &targets, 4);
+        let lr = metrics["linear_regression"];
+        let rf = metrics["random_forest"];
+        assert_eq!(lr.folds, 4);
+        assert!(lr.rmse < 1e-6 && lr.r2 > 0.999);
+        assert!(rf.rmse > lr.rmse);
+        assert!(metrics.contains_key("ensemble"));
+
+        let weights: HashMap<&str, f64> = inverse_mse_weights(&metrics).into_iter().collect();
+        assert!((weights.values().sum::<f64>() - 1.0).abs() < 1e-9);
+        assert!(weights["linear_regression"] > 0.99);
+    }
 }
@@ -0,0 +1,150 @@
+use anyhow::Result;
+use chrono::Utc;
+use std::collections::HashMap;
+use std::time::Duration;
+use tokio::time::{interval, MissedTickBehavior};
+use tracing::{debug, info, warn};
+
+use super::{AlertState, PositionInfo, PositionMutes};
+use crate::config::FeeAlertConfig;
+use crate::events::{Event, EventBus, UncollectedFees};
+use crate::uniswap::UniswapClient;
+use crate::utils::to_units;
+
+/// Polls uncollected fees of tracked positions and publishes an event once they are worth
+/// collecting.
+pub struct FeeMonitor {
+    client: UniswapClient,
+    rpc_url: String,
+    position_ids: Vec<String>,
+    config: FeeAlertConfig,
+    mutes: PositionMutes,
+    /// Marks alerted positions; cleared when fees fall back below the threshold (i.e. were
+    /// collected)
+    state: AlertState,
+    bus: EventBus,
+    tracked: HashMap<String, PositionInfo>,
+}
+
+impl FeeMonitor {
+    pub fn new(
+        client: UniswapClient,
+        rpc_url: &str,
+        position_ids: Vec<String>,
+        config: &FeeAlertConfig,
+        mutes: PositionMutes,
+        state: AlertState,
+        bus: EventBus,
+    ) -> Self {
+        Self {
+            client,
+            rpc_url: rpc_url.to_string(),
+            position_ids,
+            config: config.clone(),
+            mutes,
+            state,
+            bus,
+            tracked: HashMap::new(),
+        }
+    }
+
+    pub async fn run(mut self) {
+        info!(target: "alerts", positions = self.position_ids.len(), "fee monitor started");
+        let mut ticker = interval(Duration::from_secs(self.config.check_interval_secs.max(1)));
+        ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        loop {
+            ticker.tick().await;
+            for id in self.position_ids.clone() {
+                if let Err(e) = self.check(&id).await {
+                    warn!(target: "alerts", position = %id, "fee check failed: {:#}", e);
+                }
+            }
+        }
+    }
+
+    async fn check(&mut self, position_id: &str) -> Result<()> {
+        if !self.tracked.contains_key(position_id) {
+            let info = PositionInfo::resolve(&self.client, &self.rpc_url, position_id).await?;
+            self.tracked.insert(position_id.to_string(), info);
+        }
+        let Some(info) = self.tracked.get(position_id) else {
+            return Ok(());
+        };
+        let (raw0, raw1) = self.client.uncollected_fees(&self.rpc_url, position_id, &info.owner).await?;
+        let prices = self.client.token_prices_usd(&[&info.token0, &info.token1]).await?;
+        let amount0 = to_units(raw0, info.token0_decimals);
+        let amount1 = to_units(raw1, info.token1_decimals);
+        let value_usd = amount0 * prices.usd(&info.token0).unwrap_or(0.0) + amount1 * prices.usd(&info.token1).unwrap_or(0.0);
+
+        // Gas is only estimated when a multiple is configured (and when there is something to collect)
+        let collect_gas_usd = match self.config.gas_multiple {
+            Some(_) if value_usd > 0.0 => {
+                match self.client.collect_gas_cost_wei(&self.rpc_url, position_id, &info.owner).await {
+                    Ok(wei) => Some(to_units(wei, 18) * prices.eth_usd),
+                    Err(e) => {
+                        warn!(target: "alerts", position = position_id, "collect gas estimate failed: {:#}", e);
+                        None
+                    }
+                }
+            }
+            _ => None,
+        };
+        debug!(target: "alerts", position = position_id, value_usd, ?collect_gas_usd, "fees checked");
+
+        let worth = worth_collecting(value_usd, collect_gas_usd, &self.config);
+        let newly = worth && !self.state.fees_alerted(position_id);
+        self.state.set_fees_alerted(position_id, worth);
+        if !newly {
+            return Ok(());
+        }
+        if self.mutes.is_muted(position_id) {
+            info!(target: "alerts", position = position_id, value_usd, "fee alert for muted position not published");
+            return Ok(());
+        }
+        info!(target: "alerts", position = position_id, value_usd, "fees worth collecting");
+        self.bus.publish(Event::FeesCollectable {
+            timestamp: Utc::now(),
+            fees: UncollectedFees {
+                position_id: position_id.to_string(),
+                owner: info.owner.clone(),
+                token0_symbol: info.token0_symbol.clone(),
+                token1_symbol: info.token1_symbol.clone(),
+                amount0,
+                amount1,
+                value_usd,
+                collect_gas_usd,
+            },
+        });
+        Ok(())
+    }
+}
+
+/// Whether fees meet the USD floor or the gas multiple (either suffices)
+fn worth_collecting(value_usd: f64, collect_gas_usd: Option<f64>, config: &FeeAlertConfig) -> bool {
+    let above_floor = config.min_usd.is_some_and(|min| value_usd >= min);
+    let above_gas = match (config.gas_multiple, collect_gas_usd) {
+        (Some(multiple), Some(gas)) => value_usd > 0.0 && value_usd >= gas * multiple,
+        _ => false,
+    };
+    above_floor || above_gas
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethereum_types::U256;
+
+    #[test]
+    fn test_thresholds() {
+        let config = FeeAlertConfig { check_interval_secs: 300, min_usd: Some(50.0), gas_multiple: Some(10.0) };
+        assert!(worth_collecting(50.0, None, &config));
+        assert!(!worth_collecting(20.0, Some(2.5), &config));
+        assert!(worth_collecting(25.0, Some(2.5), &config));
+
+        let gas_only = FeeAlertConfig { min_usd: None, ..config };
+        assert!(!worth_collecting(100.0, None, &gas_only));
+        assert!(!worth_collecting(0.0, Some(0.0), &gas_only));
+
+        assert_eq!(to_units(U256::from(1_500_000u64), 6), 1.5);
+    }
+}
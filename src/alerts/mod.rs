@@ -0,0 +1,137 @@
+//! Position alerts published on the event bus, alongside recommendation changes.
+
+mod fees;
+mod range;
+
+pub use fees::FeeMonitor;
+pub use range::RangeMonitor;
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::{Arc, RwLock};
+
+use crate::uniswap::UniswapClient;
+
+/// Positions whose alerts are suppressed; shared between the monitors and the admin API
+#[derive(Clone, Default)]
+pub struct PositionMutes {
+    inner: Arc<RwLock<BTreeSet<String>>>,
+}
+
+impl PositionMutes {
+    pub fn new(positions: impl IntoIterator<Item = String>) -> Self {
+        Self {
+            inner: Arc::new(RwLock::new(positions.into_iter().collect())),
+        }
+    }
+
+    /// Returns false if the position was already muted
+    pub fn mute(&self, position_id: &str) -> bool {
+        self.inner.write().unwrap().insert(position_id.to_string())
+    }
+
+    /// Returns false if the position wasn't muted
+    pub fn unmute(&self, position_id: &str) -> bool {
+        self.inner.write().unwrap().remove(position_id)
+    }
+
+    pub fn is_muted(&self, position_id: &str) -> bool {
+        self.inner.read().unwrap().contains(position_id)
+    }
+
+    pub fn list(&self) -> Vec<String> {
+        self.inner.read().unwrap().iter().cloned().collect()
+    }
+}
+
+/// What the monitors last alerted on, so a restart doesn't repeat alerts
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct AlertMarks {
+    /// Range state per position as of the last check
+    #[serde(default)]
+    pub in_range: BTreeMap<String, bool>,
+    /// Positions whose fee alert fired and whose fees haven't been collected since
+    #[serde(default)]
+    pub fees_alerted: BTreeSet<String>,
+}
+
+/// Alert marks shared between the monitors and state snapshots
+#[derive(Clone, Default)]
+pub struct AlertState {
+    inner: Arc<RwLock<AlertMarks>>,
+}
+
+impl AlertState {
+    pub fn new(marks: AlertMarks) -> Self {
+        Self {
+            inner: Arc::new(RwLock::new(marks)),
+        }
+    }
+
+    pub fn in_range(&self, position_id: &str) -> Option<bool> {
+        self.inner.read().unwrap().in_range.get(position_id).copied()
+    }
+
+    pub fn set_in_range(&self, position_id: &str, in_range: bool) {
+        self.inner.write().unwrap().in_range.insert(position_id.to_string(), in_range);
+    }
+
+    pub fn fees_alerted(&self, position_id: &str) -> bool {
+        self.inner.read().unwrap().fees_alerted.contains(position_id)
+    }
+
+    pub fn set_fees_alerted(&self, position_id: &str, alerted: bool) {
+        let mut marks = self.inner.write().unwrap();
+        if alerted {
+            marks.fees_alerted.insert(position_id.to_string());
+        } else {
+            marks.fees_alerted.remove(position_id);
+        }
+    }
+
+    pub fn marks(&self) -> AlertMarks {
+        self.inner.read().unwrap().clone()
+    }
+}
+
+/// Details of a tracked position that don't change between checks, resolved once
+pub(crate) struct PositionInfo {
+    pub pool: String,
+    pub owner: String,
+    pub token0: String,
+    pub token1: String,
+    pub token0_symbol: String,
+    pub token1_symbol: String,
+    pub token0_decimals: u8,
+    pub token1_decimals: u8,
+    pub tick_lower: i32,
+    pub tick_upper: i32,
+}
+
+impl PositionInfo {
+    pub(crate) async fn resolve(client: &UniswapClient, rpc_url: &str, position_id: &str) -> Result<Self> {
+        let position = client.get_onchain_position(rpc_url, position_id).await?;
+        let pool = client
+            .pool_address(rpc_url, &position.token0, &position.token1, position.fee)
+            .await?;
+        let owner = client.position_owner(rpc_url, position_id).await?;
+        Ok(Self {
+            pool,
+            owner,
+            token0: position.token0,
+            token1: position.token1,
+            token0_symbol: position.token0_symbol,
+            token1_symbol: position.token1_symbol,
+            token0_decimals: position.token0_decimals,
+            token1_decimals: position.token1_decimals,
+            tick_lower: position.tick_lower,
+            tick_upper: position.tick_upper,
+        })
+    }
+
+    /// e.g. `WETH/USDC`
+    pub(crate) fn pair(&self) -> String {
+        format!("{}/{}", self.token0_symbol, self.token1_symbol)
+    }
+}
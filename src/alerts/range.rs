@@ -0,0 +1,165 @@
+use anyhow::Result;
+use chrono::Utc;
+use std::collections::HashMap;
+use std::time::Duration;
+use tokio::time::{interval, MissedTickBehavior};
+use tracing::{debug, info, warn};
+
+use super::{AlertState, PositionInfo, PositionMutes};
+use crate::config::RangeAlertConfig;
+use crate::events::{Event, EventBus, RangeStatus};
+use crate::uniswap::UniswapClient;
+
+/// Polls the pool tick of tracked positions and publishes an event when one leaves its
+/// range and again when it returns.
+pub struct RangeMonitor {
+    client: UniswapClient,
+    rpc_url: String,
+    position_ids: Vec<String>,
+    hysteresis_ticks: i32,
+    check_interval: Duration,
+    mutes: PositionMutes,
+    /// Range state per position; unknown until the first check unless restored
+    state: AlertState,
+    bus: EventBus,
+    tracked: HashMap<String, PositionInfo>,
+}
+
+impl RangeMonitor {
+    pub fn new(
+        client: UniswapClient,
+        rpc_url: &str,
+        position_ids: Vec<String>,
+        config: &RangeAlertConfig,
+        mutes: PositionMutes,
+        state: AlertState,
+        bus: EventBus,
+    ) -> Self {
+        Self {
+            client,
+            rpc_url: rpc_url.to_string(),
+            position_ids,
+            hysteresis_ticks: config.hysteresis_ticks.max(0),
+            check_interval: Duration::from_secs(config.check_interval_secs.max(1)),
+            mutes,
+            state,
+            bus,
+            tracked: HashMap::new(),
+        }
+    }
+
+    pub async fn run(mut self) {
+        info!(target: "alerts", positions = self.position_ids.len(), "range monitor started");
+        let mut ticker = interval(self.check_interval);
+        ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        loop {
+            ticker.tick().await;
+            self.check_all().await;
+        }
+    }
+
+    /// Read every tracked position's pool tick in one RPC batch and check each against it
+    async fn check_all(&mut self) {
+        let mut ready = Vec::new();
+        for id in &self.position_ids {
+            if !self.tracked.contains_key(id) {
+                match PositionInfo::resolve(&self.client, &self.rpc_url, id).await {
+                    Ok(info) => {
+                        self.tracked.insert(id.clone(), info);
+                    }
+                    Err(e) => {
+                        warn!(target: "alerts", position = %id, "range check failed: {:#}", e);
+                        continue;
+                    }
+                }
+            }
+            ready.push(id.clone());
+        }
+        let pools: Vec<&str> = ready.iter().map(|id| self.tracked[id].pool.as_str()).collect();
+        let ticks = match self.client.pool_ticks(&self.rpc_url, &pools).await {
+            Ok(ticks) => ticks,
+            Err(e) => {
+                warn!(target: "alerts", positions = ready.len(), "reading pool ticks failed: {:#}", e);
+                return;
+            }
+        };
+        for (id, tick) in ready.iter().zip(ticks) {
+            if let Err(e) = tick.and_then(|tick| self.check(id, tick)) {
+                warn!(target: "alerts", position = %id, "range check failed: {:#}", e);
+            }
+        }
+    }
+
+    fn check(&mut self, position_id: &str, tick: i32) -> Result<()> {
+        let Some(info) = self.tracked.get(position_id) else {
+            return Ok(());
+        };
+        let previous = self.state.in_range(position_id);
+        let in_range = next_in_range(previous, tick, info.tick_lower, info.tick_upper, self.hysteresis_ticks);
+        self.state.set_in_range(position_id, in_range);
+        debug!(target: "alerts", position = position_id, tick, in_range, "range checked");
+
+        // Alert on transitions; at startup only an out-of-range position is worth a message
+        if previous.map_or(in_range, |p| p == in_range) {
+            return Ok(());
+        }
+        if self.mutes.is_muted(position_id) {
+            info!(target: "alerts", position = position_id, in_range, "range change for muted position not published");
+            return Ok(());
+        }
+        let range = RangeStatus {
+            position_id: position_id.to_string(),
+            owner: info.owner.clone(),
+            pool: info.pool.clone(),
+            pair: info.pair(),
+            tick_lower: info.tick_lower,
+            tick_upper: info.tick_upper,
+            current_tick: tick,
+        };
+        info!(target: "alerts", position = position_id, tick, in_range, "position range changed");
+        let timestamp = Utc::now();
+        self.bus.publish(if in_range {
+            Event::PositionBackInRange { timestamp, range }
+        } else {
+            Event::PositionOutOfRange { timestamp, range }
+        });
+        Ok(())
+    }
+}
+
+/// Whether a position counts as in range at `tick`.
+///
+/// Leaving the range requires moving `hysteresis` ticks past an edge and returning requires
+/// moving `hysteresis` ticks back inside, so a price hovering at the edge doesn't flap. The
+/// buffer is capped at a quarter of the range width for narrow positions.
+fn next_in_range(previous: Option<bool>, tick: i32, lower: i32, upper: i32, hysteresis: i32) -> bool {
+    let h = hysteresis.min((upper - lower) / 4).max(0);
+    match previous {
+        Some(true) => tick >= lower - h && tick < upper + h,
+        Some(false) => tick >= lower + h && tick < upper - h,
+        None => tick >= lower && tick < upper,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hysteresis() {
+        // Range [-100, 100) with a 10 tick buffer
+        assert!(next_in_range(None, 0, -100, 100, 10));
+        assert!(!next_in_range(None, 100, -100, 100, 10));
+
+        assert!(next_in_range(Some(true), 105, -100, 100, 10));
+        assert!(!next_in_range(Some(true), 110, -100, 100, 10));
+        assert!(!next_in_range(Some(true), -111, -100, 100, 10));
+
+        assert!(!next_in_range(Some(false), 95, -100, 100, 10));
+        assert!(next_in_range(Some(false), 89, -100, 100, 10));
+
+        // Narrow range: buffer capped at a quarter of the width
+        assert!(!next_in_range(Some(false), 3, 0, 20, 10));
+        assert!(next_in_range(Some(false), 10, 0, 20, 10));
+    }
+}
@@ -0,0 +1,295 @@
+use anyhow::Result;
+use axum::extract::{Path, Request, State};
+use axum::http::header::AUTHORIZATION;
+use axum::http::StatusCode;
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use chrono::{DateTime, Utc};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use tracing::info;
+use utoipa::ToSchema;
+
+pub use crate::config::Scope;
+use crate::config::ApiKeyConfig;
+use crate::rate_limit::RateLimiter;
+use crate::server::AppState;
+
+/// Header carrying the API key
+pub const API_KEY_HEADER: &str = "X-Api-Key";
+
+/// An API key as seen by the server (the plaintext key is never stored).
+/// JWT bearer tokens are mapped onto the same type.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct ApiKey {
+    pub id: String,
+    pub name: String,
+    pub scopes: Vec<Scope>,
+    /// Requests per minute; 0 disables the limit
+    pub rate_limit_per_minute: u32,
+    /// Wallets whose positions this key may see; empty means all wallets
+    pub wallets: Vec<String>,
+    pub created_at: DateTime<Utc>,
+    pub revoked_at: Option<DateTime<Utc>>,
+}
+
+impl ApiKey {
+    pub fn has_scope(&self, scope: Scope) -> bool {
+        self.scopes.contains(&Scope::Admin) || self.scopes.contains(&scope)
+    }
+
+    /// Whether positions owned by `wallet` are visible to this key
+    pub fn allows_wallet(&self, wallet: &str) -> bool {
+        self.wallets.is_empty() || self.wallets.iter().any(|w| w.eq_ignore_ascii_case(wallet))
+    }
+}
+
+/// Request body for creating a key
+#[derive(Debug, Clone, Deserialize, ToSchema)]
+pub struct CreateApiKey {
+    pub name: String,
+    pub scopes: Vec<Scope>,
+    #[serde(default)]
+    pub rate_limit_per_minute: u32,
+    #[serde(default)]
+    pub wallets: Vec<String>,
+}
+
+/// Response for a created key; the plaintext key is only ever returned here
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct CreatedApiKey {
+    pub key: String,
+    #[serde(flatten)]
+    pub api_key: ApiKey,
+}
+
+/// In-memory registry of API keys indexed by the SHA-256 of the plaintext key
+#[derive(Clone, Default)]
+pub struct ApiKeyStore {
+    keys: Arc<RwLock<HashMap<String, ApiKey>>>,
+    limiter: RateLimiter,
+    enforced: bool,
+}
+
+fn hash_key(key: &str) -> String {
+    hex::encode(Sha256::digest(key.as_bytes()))
+}
+
+impl ApiKeyStore {
+    /// Seed the store with the keys from config; authentication is enforced when `enforced`
+    pub fn from_config(configured: &[ApiKeyConfig], enforced: bool) -> Result<Self> {
+        let mut keys = HashMap::new();
+        for cfg in configured {
+            let hash = cfg.key_sha256.to_lowercase();
+            if hash.len() != 64 || !hash.chars().all(|c| c.is_ascii_hexdigit()) {
+                return Err(anyhow::anyhow!("api key '{}': key_sha256 must be 64 hex characters", cfg.name));
+            }
+            let key = ApiKey {
+                id: format!("key_{}", &hash[..12]),
+                name: cfg.name.clone(),
+                scopes: cfg.scopes.clone(),
+                rate_limit_per_minute: cfg.rate_limit_per_minute,
+                wallets: cfg.wallets.clone(),
+                created_at: Utc::now(),
+                revoked_at: None,
+            };
+            keys.insert(hash, key);
+        }
+        Ok(Self {
+            keys: Arc::new(RwLock::new(keys)),
+            limiter: RateLimiter::new(),
+            enforced,
+        })
+    }
+
+    pub fn enforced(&self) -> bool {
+        self.enforced
+    }
+
+    /// Create a new random key and return it with its plaintext (shown once)
+    pub async fn create(&self, req: CreateApiKey) -> CreatedApiKey {
+        let mut raw = [0u8; 32];
+        rand::thread_rng().fill_bytes(&mut raw);
+        let plaintext = format!("ork_{}", hex::encode(raw));
+        let hash = hash_key(&plaintext);
+        let api_key = ApiKey {
+            id: format!("key_{}", &hash[..12]),
+            name: req.name,
+            scopes: req.scopes,
+            rate_limit_per_minute: req.rate_limit_per_minute,
+            wallets: req.wallets,
+            created_at: Utc::now(),
+            revoked_at: None,
+        };
+        self.keys.write().await.insert(hash, api_key.clone());
+        info!(target: "api_keys", id = %api_key.id, name = %api_key.name, "created API key");
+        CreatedApiKey { key: plaintext, api_key }
+    }
+
+    /// Revoke a key by id; returns false when no active key has that id
+    pub async fn revoke(&self, id: &str) -> bool {
+        let mut keys = self.keys.write().await;
+        match keys.values_mut().find(|k| k.id == id && k.revoked_at.is_none()) {
+            Some(key) => {
+                key.revoked_at = Some(Utc::now());
+                info!(target: "api_keys", id, "revoked API key");
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub async fn list(&self) -> Vec<ApiKey> {
+        let mut keys: Vec<ApiKey> = self.keys.read().await.values().cloned().collect();
+        keys.sort_by_key(|k| k.created_at);
+        keys
+    }
+
+    /// Look up an active key by its plaintext
+    pub async fn authenticate(&self, plaintext: &str) -> Option<ApiKey> {
+        self.keys
+            .read()
+            .await
+            .get(&hash_key(plaintext))
+            .filter(|k| k.revoked_at.is_none())
+            .cloned()
+    }
+}
+
+/// Middleware for read endpoints
+pub async fn require_read(State(state): State<AppState>, req: Request, next: Next) -> Response {
+    authorize(&state, Scope::Read, req, next).await
+}
+
+/// Middleware for admin endpoints
+pub async fn require_admin(State(state): State<AppState>, req: Request, next: Next) -> Response {
+    authorize(&state, Scope::Admin, req, next).await
+}
+
+/// Authenticate the caller by JWT bearer token or API key, then check scope and rate limit.
+/// Without API keys or JWT configured reads are open, but admin endpoints (which manage
+/// keys and send transactions) are refused. Each scheme is only accepted when enabled, so
+/// with JWT alone a valid token is the only way in.
+async fn authorize(state: &AppState, scope: Scope, mut req: Request, next: Next) -> Response {
+    let store = &state.api_keys;
+    if !store.enforced() && state.jwt.is_none() {
+        if scope == Scope::Admin {
+            return (
+                StatusCode::SERVICE_UNAVAILABLE,
+                "admin endpoints need server.require_api_key or [server.jwt]",
+            )
+                .into_response();
+        }
+        return next.run(req).await;
+    }
+    let bearer = req
+        .headers()
+        .get(AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "));
+    let key = if let Some(token) = bearer {
+        let Some(verifier) = &state.jwt else {
+            return (StatusCode::UNAUTHORIZED, "bearer tokens are not enabled").into_response();
+        };
+        match verifier.verify(token) {
+            Ok(key) => key,
+            Err(_) => return (StatusCode::UNAUTHORIZED, "invalid or expired bearer token").into_response(),
+        }
+    } else {
+        let Some(plaintext) = req.headers().get(API_KEY_HEADER).and_then(|v| v.to_str().ok()) else {
+            return (StatusCode::UNAUTHORIZED, "missing X-Api-Key header or bearer token").into_response();
+        };
+        if !store.enforced() {
+            return (StatusCode::UNAUTHORIZED, "API keys are not enabled").into_response();
+        }
+        match store.authenticate(plaintext).await {
+            Some(key) => key,
+            None => return (StatusCode::UNAUTHORIZED, "invalid or revoked API key").into_response(),
+        }
+    };
+    if !key.has_scope(scope) {
+        return (StatusCode::FORBIDDEN, "credentials lack the required scope").into_response();
+    }
+    if !store.limiter.check(&key.id, key.rate_limit_per_minute) {
+        return (StatusCode::TOO_MANY_REQUESTS, "rate limit exceeded").into_response();
+    }
+    req.extensions_mut().insert(key);
+    next.run(req).await
+}
+
+/// Create an API key; the plaintext key is only returned in this response
+#[utoipa::path(
+    post,
+    path = "/admin/api-keys",
+    tag = "admin",
+    request_body = CreateApiKey,
+    responses((status = 201, description = "Key created", body = CreatedApiKey)),
+    security(("api_key" = []), ("bearer" = []))
+)]
+pub async fn create_key(State(state): State<AppState>, Json(body): Json<CreateApiKey>) -> impl IntoResponse {
+    (StatusCode::CREATED, Json(state.api_keys.create(body).await))
+}
+
+/// List API keys, including revoked ones
+#[utoipa::path(
+    get,
+    path = "/admin/api-keys",
+    tag = "admin",
+    responses((status = 200, description = "All known keys", body = [ApiKey])),
+    security(("api_key" = []), ("bearer" = []))
+)]
+pub async fn list_keys(State(state): State<AppState>) -> impl IntoResponse {
+    Json(state.api_keys.list().await)
+}
+
+/// Revoke an API key by id
+#[utoipa::path(
+    delete,
+    path = "/admin/api-keys/{id}",
+    tag = "admin",
+    params(("id" = String, Path, description = "Key id, e.g. key_0123456789ab")),
+    responses(
+        (status = 204, description = "Key revoked"),
+        (status = 404, description = "No active key with that id")
+    ),
+    security(("api_key" = []), ("bearer" = []))
+)]
+pub async fn revoke_key(State(state): State<AppState>, Path(id): Path<String>) -> impl IntoResponse {
+    if state.api_keys.revoke(&id).await {
+        StatusCode::NO_CONTENT
+    } else {
+        StatusCode::NOT_FOUND
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_create_authenticate_revoke() {
+        let store = ApiKeyStore::from_config(&[], true).unwrap();
+        let created = store
+            .create(CreateApiKey {
+                name: "desk-a".to_string(),
+                scopes: vec![Scope::Read],
+                rate_limit_per_minute: 10,
+                wallets: vec!["0xAbC".to_string()],
+            })
+            .await;
+        let key = store.authenticate(&created.key).await.unwrap();
+        assert!(key.has_scope(Scope::Read));
+        assert!(!key.has_scope(Scope::Admin));
+        assert!(key.allows_wallet("0xabc"));
+        assert!(!key.allows_wallet("0xdef"));
+
+        assert!(store.revoke(&created.api_key.id).await);
+        assert!(store.authenticate(&created.key).await.is_none());
+        assert!(!store.revoke(&created.api_key.id).await);
+    }
+}
@@ -0,0 +1,224 @@
+//! Append-only audit log of what the recommender advised and did: recommendation changes,
+//! alerts and sent transactions. Each record commits to its predecessor's hash, so
+//! editing, removing or reordering stored records breaks the chain and `verify` finds it.
+
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::sync::Arc;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::Mutex;
+use tracing::{debug, warn};
+
+use crate::events::EventBus;
+use crate::storage::Storage;
+
+/// `prev_hash` of the first record
+pub const GENESIS_HASH: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+/// Attempts to append when another instance extended the chain first
+const APPEND_ATTEMPTS: usize = 3;
+/// Records read per page when verifying
+const VERIFY_PAGE: usize = 1_000;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "server", derive(utoipa::ToSchema))]
+pub struct AuditRecord {
+    /// 1-based position in the chain
+    pub seq: u64,
+    /// Millisecond precision, so it survives every storage backend unchanged
+    pub timestamp: DateTime<Utc>,
+    /// Event type (e.g. `recommendation_changed`) or `transaction_sent` /
+    /// `transaction_status`
+    pub kind: String,
+    pub position_id: Option<String>,
+    pub payload: Value,
+    /// Hex SHA-256 of the previous record
+    pub prev_hash: String,
+    /// Hex SHA-256 over this record's fields and `prev_hash`
+    pub hash: String,
+}
+
+impl AuditRecord {
+    fn new(prev: Option<&AuditRecord>, kind: &str, position_id: Option<&str>, payload: Value) -> Self {
+        let now = Utc::now();
+        let mut record = Self {
+            seq: prev.map_or(1, |p| p.seq + 1),
+            timestamp: DateTime::from_timestamp_millis(now.timestamp_millis()).unwrap_or(now),
+            kind: kind.to_string(),
+            position_id: position_id.map(str::to_string),
+            payload,
+            prev_hash: prev.map_or_else(|| GENESIS_HASH.to_string(), |p| p.hash.clone()),
+            hash: String::new(),
+        };
+        record.hash = record.compute_hash();
+        record
+    }
+
+    /// The hash this record should carry, from its other fields
+    pub fn compute_hash(&self) -> String {
+        let mut hasher = Sha256::new();
+        for field in [
+            self.seq.to_string(),
+            self.timestamp.to_rfc3339_opts(SecondsFormat::Millis, true),
+            self.kind.clone(),
+            self.position_id.clone().unwrap_or_default(),
+            canonical_json(&self.payload),
+            self.prev_hash.clone(),
+        ] {
+            hasher.update(field.as_bytes());
+            hasher.update([0u8]);
+        }
+        hex::encode(hasher.finalize())
+    }
+}
+
+/// JSON with object keys sorted at every level, so the hash doesn't depend on how a
+/// backend (e.g. Postgres JSONB) orders keys
+fn canonical_json(value: &Value) -> String {
+    match value {
+        Value::Object(map) => {
+            let mut keys: Vec<&String> = map.keys().collect();
+            keys.sort();
+            let fields: Vec<String> = keys
+                .into_iter()
+                .map(|k| format!("{}:{}", Value::String(k.clone()), canonical_json(&map[k])))
+                .collect();
+            format!("{{{}}}", fields.join(","))
+        }
+        Value::Array(items) => format!("[{}]", items.iter().map(canonical_json).collect::<Vec<_>>().join(",")),
+        other => other.to_string(),
+    }
+}
+
+/// Check that `records` (consecutive, oldest first) each carry the right hash and link
+/// to the one before; `prev` is the record preceding the first, if any
+pub fn verify(prev: Option<&AuditRecord>, records: &[AuditRecord]) -> Result<()> {
+    let mut expected_prev = prev.map_or(GENESIS_HASH, |p| p.hash.as_str());
+    for (expected_seq, record) in (prev.map_or(1, |p| p.seq + 1)..).zip(records) {
+        if record.seq != expected_seq {
+            bail!("audit record {} found where {} was expected (records missing or reordered)", record.seq, expected_seq);
+        }
+        if record.prev_hash != expected_prev {
+            bail!("audit record {} does not link to record {}", record.seq, expected_seq - 1);
+        }
+        if record.compute_hash() != record.hash {
+            bail!("audit record {} was modified (hash mismatch)", record.seq);
+        }
+        expected_prev = &record.hash;
+    }
+    Ok(())
+}
+
+/// Verify the whole stored chain page by page; returns the number of records and the
+/// head hash
+pub async fn verify_storage(storage: &dyn Storage) -> Result<(u64, String)> {
+    let mut prev: Option<AuditRecord> = None;
+    loop {
+        let page = storage.audit_records(prev.as_ref().map_or(0, |p| p.seq), VERIFY_PAGE).await?;
+        verify(prev.as_ref(), &page)?;
+        match page.into_iter().last() {
+            Some(last) => prev = Some(last),
+            None => break,
+        }
+    }
+    Ok(prev.map_or((0, GENESIS_HASH.to_string()), |p| (p.seq, p.hash)))
+}
+
+/// Appends records to the stored chain
+#[derive(Clone)]
+pub struct AuditLog {
+    storage: Arc<dyn Storage>,
+    /// Last appended record; loaded from storage on first use
+    head: Arc<Mutex<Option<AuditRecord>>>,
+}
+
+impl AuditLog {
+    pub fn new(storage: Arc<dyn Storage>) -> Self {
+        Self { storage, head: Arc::new(Mutex::new(None)) }
+    }
+
+    pub async fn append<T: Serialize>(&self, kind: &str, position_id: Option<&str>, payload: &T) -> Result<AuditRecord> {
+        let payload = serde_json::to_value(payload).context("serializing audit payload")?;
+        let mut head = self.head.lock().await;
+        let mut attempt = 0;
+        loop {
+            if head.is_none() {
+                *head = self.storage.latest_audit().await?;
+            }
+            let record = AuditRecord::new(head.as_ref(), kind, position_id, payload.clone());
+            match self.storage.append_audit(&record).await {
+                Ok(()) => {
+                    debug!(target: "audit", seq = record.seq, kind, "audit record appended");
+                    *head = Some(record.clone());
+                    return Ok(record);
+                }
+                // Another instance may have appended; re-read the head and try again
+                Err(e) if attempt + 1 < APPEND_ATTEMPTS => {
+                    debug!(target: "audit", seq = record.seq, "audit append conflicted, retrying: {:#}", e);
+                    *head = None;
+                    attempt += 1;
+                }
+                Err(e) => return Err(e.context("appending audit record")),
+            }
+        }
+    }
+}
+
+/// Append every event published on the bus until it closes
+pub async fn record_events(audit: AuditLog, bus: EventBus) {
+    let mut rx = bus.subscribe();
+    loop {
+        match rx.recv().await {
+            Ok(event) => {
+                if let Err(e) = audit.append(event.kind(), Some(event.position_id()), &event).await {
+                    warn!(target: "audit", kind = event.kind(), position = event.position_id(), "event not audited: {:#}", e);
+                }
+            }
+            Err(RecvError::Lagged(skipped)) => {
+                warn!(target: "audit", skipped, "audit recorder lagged, events not audited");
+            }
+            Err(RecvError::Closed) => break,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::MemoryStorage;
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn test_chain_detects_tampering() {
+        let audit = AuditLog::new(Arc::new(MemoryStorage::new()));
+        let mut records = Vec::new();
+        for i in 0..4 {
+            records.push(audit.append("recommendation_changed", Some("1"), &json!({ "score": i, "b": [1, { "z": 1, "a": 2 }] })).await.unwrap());
+        }
+        assert_eq!(records[0].prev_hash, GENESIS_HASH);
+        assert_eq!(records[3].seq, 4);
+        verify(None, &records).unwrap();
+        verify(Some(&records[1]), &records[2..]).unwrap();
+
+        // Key order doesn't matter, as after a round trip through JSONB
+        let mut reordered = records.clone();
+        reordered[0].payload = serde_json::from_str(r#"{"b":[1,{"a":2,"z":1}],"score":0}"#).unwrap();
+        verify(None, &reordered).unwrap();
+
+        let mut edited = records.clone();
+        edited[1].payload = json!({ "score": 99 });
+        assert!(verify(None, &edited).unwrap_err().to_string().contains("record 2 was modified"));
+
+        let mut removed = records.clone();
+        removed.remove(2);
+        assert!(verify(None, &removed).unwrap_err().to_string().contains("missing or reordered"));
+
+        // Re-hashing an edited record still breaks the link from its successor
+        let mut rehashed = records.clone();
+        rehashed[1].payload = json!({ "score": 99 });
+        rehashed[1].hash = rehashed[1].compute_hash();
+        assert!(verify(None, &rehashed).unwrap_err().to_string().contains("record 3 does not link"));
+    }
+}
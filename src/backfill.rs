@@ -0,0 +1,323 @@
+//! Historical backfill: pulls a pool's hourly candles, swaps and position snapshots from
+//! the subgraph into storage, so backtests and indicators have history on day one.
+//! Progress is stored after every page; a re-run resumes where the last one stopped.
+
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::sync::Arc;
+use tracing::{info, warn};
+
+use crate::archive::SwapRecord;
+use crate::storage::{Candle, Storage};
+use crate::uniswap::{PoolDayData, PoolHourData, PositionSnapshot, UniswapClient};
+
+/// Candle interval of the subgraph's `poolHourData`
+pub const HOURLY: u32 = 3600;
+/// Candle interval of the subgraph's `poolDayData`
+pub const DAILY: u32 = 86_400;
+/// Largest page the subgraph serves
+const PAGE_SIZE: usize = 1000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Dataset {
+    Candles,
+    Swaps,
+    PositionSnapshots,
+}
+
+impl Dataset {
+    pub const ALL: [Dataset; 3] = [Dataset::Candles, Dataset::Swaps, Dataset::PositionSnapshots];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Dataset::Candles => "candles",
+            Dataset::Swaps => "swaps",
+            Dataset::PositionSnapshots => "position_snapshots",
+        }
+    }
+}
+
+/// A position's cumulative deposits, withdrawals and collected fees (token units) after
+/// one of its transactions
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PositionSnapshotRecord {
+    pub id: String,
+    pub pool_id: String,
+    pub position_id: String,
+    pub owner: String,
+    pub timestamp: DateTime<Utc>,
+    pub block_number: u64,
+    pub liquidity: f64,
+    pub deposited0: f64,
+    pub deposited1: f64,
+    pub withdrawn0: f64,
+    pub withdrawn1: f64,
+    pub collected_fees0: f64,
+    pub collected_fees1: f64,
+    pub tx_hash: String,
+}
+
+impl PositionSnapshotRecord {
+    pub fn from_snapshot(pool_id: &str, snapshot: &PositionSnapshot) -> Result<Self> {
+        let secs: i64 = snapshot.timestamp.parse().context("invalid snapshot timestamp")?;
+        let amount = |field: &str, value: &str| value.parse::<f64>().with_context(|| format!("invalid snapshot {}", field));
+        Ok(Self {
+            id: snapshot.id.clone(),
+            pool_id: pool_id.to_lowercase(),
+            position_id: snapshot.position.id.clone(),
+            owner: snapshot.owner.to_lowercase(),
+            timestamp: DateTime::from_timestamp(secs, 0).context("snapshot timestamp out of range")?,
+            block_number: snapshot.block_number.parse().context("invalid snapshot block number")?,
+            liquidity: amount("liquidity", &snapshot.liquidity)?,
+            deposited0: amount("depositedToken0", &snapshot.deposited_token0)?,
+            deposited1: amount("depositedToken1", &snapshot.deposited_token1)?,
+            withdrawn0: amount("withdrawnToken0", &snapshot.withdrawn_token0)?,
+            withdrawn1: amount("withdrawnToken1", &snapshot.withdrawn_token1)?,
+            collected_fees0: amount("collectedFeesToken0", &snapshot.collected_fees_token0)?,
+            collected_fees1: amount("collectedFeesToken1", &snapshot.collected_fees_token1)?,
+            tx_hash: snapshot.transaction.id.clone(),
+        })
+    }
+}
+
+pub(crate) fn candle_from_hour(pool_id: &str, hour: &PoolHourData) -> Result<Candle> {
+    let price = |field: &str, value: &str| value.parse::<f64>().with_context(|| format!("invalid hour data {}", field));
+    Ok(Candle {
+        pool_id: pool_id.to_lowercase(),
+        interval_secs: HOURLY,
+        start: DateTime::from_timestamp(hour.period_start_unix, 0).context("hour data start out of range")?,
+        open: price("open", &hour.open)?,
+        high: price("high", &hour.high)?,
+        low: price("low", &hour.low)?,
+        close: price("close", &hour.close)?,
+        volume: hour.volume_usd.parse().unwrap_or(0.0),
+    })
+}
+
+pub(crate) fn candle_from_day(pool_id: &str, day: &PoolDayData) -> Result<Candle> {
+    let price = |field: &str, value: &str| value.parse::<f64>().with_context(|| format!("invalid day data {}", field));
+    Ok(Candle {
+        pool_id: pool_id.to_lowercase(),
+        interval_secs: DAILY,
+        start: DateTime::from_timestamp(day.date, 0).context("day data start out of range")?,
+        open: price("open", &day.open)?,
+        high: price("high", &day.high)?,
+        low: price("low", &day.low)?,
+        close: price("close", &day.close)?,
+        volume: day.volume_usd.parse().unwrap_or(0.0),
+    })
+}
+
+/// How far a pool's dataset has been backfilled
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BackfillProgress {
+    pub pool_id: String,
+    pub dataset: String,
+    /// Start of the backfilled history
+    pub from: DateTime<Utc>,
+    /// Time of the latest stored row; a resumed run continues from here
+    pub cursor: DateTime<Utc>,
+    /// Rows stored since `from`
+    pub rows: u64,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl BackfillProgress {
+    /// Resume `existing` if it covers `from` without a gap, else start over at `from`
+    fn resume(existing: Option<BackfillProgress>, pool_id: &str, dataset: Dataset, from: DateTime<Utc>) -> Self {
+        match existing {
+            Some(progress) if progress.from <= from && from <= progress.cursor => progress,
+            _ => Self {
+                pool_id: pool_id.to_string(),
+                dataset: dataset.as_str().to_string(),
+                from,
+                cursor: from,
+                rows: 0,
+                updated_at: Utc::now(),
+            },
+        }
+    }
+}
+
+/// One page of fetched rows
+enum Page {
+    Candles(Vec<Candle>),
+    Swaps(Vec<SwapRecord>),
+    Snapshots(Vec<PositionSnapshotRecord>),
+}
+
+impl Page {
+    fn len(&self) -> usize {
+        match self {
+            Page::Candles(rows) => rows.len(),
+            Page::Swaps(rows) => rows.len(),
+            Page::Snapshots(rows) => rows.len(),
+        }
+    }
+
+    /// Drop rows already stored at or before the cursor and advance it past the rest
+    fn advance(self, cursor: &mut PageCursor) -> Page {
+        match self {
+            Page::Candles(rows) => Page::Candles(rows.into_iter().filter(|c| cursor.take(&c.start.timestamp().to_string(), c.start)).collect()),
+            Page::Swaps(rows) => Page::Swaps(rows.into_iter().filter(|s| cursor.take(&s.id, s.timestamp)).collect()),
+            Page::Snapshots(rows) => Page::Snapshots(rows.into_iter().filter(|s| cursor.take(&s.id, s.timestamp)).collect()),
+        }
+    }
+}
+
+/// The latest time read and the ids seen at exactly that time (subgraph filters are
+/// inclusive, so the next page starts with them again)
+struct PageCursor {
+    at: DateTime<Utc>,
+    seen: HashSet<String>,
+}
+
+impl PageCursor {
+    /// Whether the row is new, marking it read
+    fn take(&mut self, id: &str, timestamp: DateTime<Utc>) -> bool {
+        if timestamp < self.at || (timestamp == self.at && self.seen.contains(id)) {
+            return false;
+        }
+        if timestamp > self.at {
+            self.at = timestamp;
+            self.seen.clear();
+        }
+        self.seen.insert(id.to_string());
+        true
+    }
+}
+
+/// Rows stored per dataset by one run
+#[derive(Debug, Clone, Default)]
+pub struct BackfillSummary {
+    pub rows: Vec<(Dataset, u64, DateTime<Utc>)>,
+}
+
+pub struct Backfill {
+    client: UniswapClient,
+    storage: Arc<dyn Storage>,
+}
+
+impl Backfill {
+    pub fn new(client: UniswapClient, storage: Arc<dyn Storage>) -> Self {
+        Self { client, storage }
+    }
+
+    /// Backfill every dataset of `pool_id` from `from` up to now
+    pub async fn run(&self, pool_id: &str, from: DateTime<Utc>) -> Result<BackfillSummary> {
+        let pool_id = pool_id.to_lowercase();
+        let mut summary = BackfillSummary::default();
+        for dataset in Dataset::ALL {
+            let (rows, cursor) = self.dataset(&pool_id, dataset, from).await.with_context(|| format!("backfilling {}", dataset.as_str()))?;
+            summary.rows.push((dataset, rows, cursor));
+        }
+        Ok(summary)
+    }
+
+    async fn dataset(&self, pool_id: &str, dataset: Dataset, from: DateTime<Utc>) -> Result<(u64, DateTime<Utc>)> {
+        let existing = self.storage.backfill_progress(pool_id).await?.into_iter().find(|p| p.dataset == dataset.as_str());
+        let mut progress = BackfillProgress::resume(existing, pool_id, dataset, from);
+        if progress.cursor > from {
+            info!(target: "backfill", pool = pool_id, dataset = dataset.as_str(), cursor = %progress.cursor, "resuming backfill");
+        }
+        let mut cursor = PageCursor { at: progress.cursor, seen: HashSet::new() };
+        let mut stored = 0;
+        loop {
+            let page = self.fetch(pool_id, dataset, cursor.at.timestamp()).await?;
+            let full = page.len() >= PAGE_SIZE;
+            let fresh = page.advance(&mut cursor);
+            let count = fresh.len() as u64;
+            self.save(fresh).await?;
+            progress.cursor = cursor.at;
+            progress.rows += count;
+            progress.updated_at = Utc::now();
+            self.storage.save_backfill_progress(&progress).await?;
+            stored += count;
+            info!(target: "backfill", pool = pool_id, dataset = dataset.as_str(), rows = count, cursor = %progress.cursor, "backfilled page");
+            if !full {
+                break;
+            }
+            if count == 0 {
+                // A full page within one second that was already read cannot be paged past
+                warn!(target: "backfill", pool = pool_id, dataset = dataset.as_str(), cursor = %progress.cursor, "backfill stuck on one timestamp, stopping");
+                break;
+            }
+        }
+        Ok((stored, progress.cursor))
+    }
+
+    async fn fetch(&self, pool_id: &str, dataset: Dataset, since: i64) -> Result<Page> {
+        Ok(match dataset {
+            Dataset::Candles => {
+                let hours = self.client.pool_hour_data_since(pool_id, since, PAGE_SIZE).await?;
+                Page::Candles(hours.iter().map(|h| candle_from_hour(pool_id, h)).collect::<Result<_>>()?)
+            }
+            Dataset::Swaps => {
+                let swaps = self.client.swaps_since(pool_id, since, PAGE_SIZE).await?;
+                Page::Swaps(swaps.iter().map(|s| SwapRecord::from_swap(pool_id, s)).collect::<Result<_>>()?)
+            }
+            Dataset::PositionSnapshots => {
+                let snapshots = self.client.position_snapshots_since(pool_id, since, PAGE_SIZE).await?;
+                Page::Snapshots(snapshots.iter().map(|s| PositionSnapshotRecord::from_snapshot(pool_id, s)).collect::<Result<_>>()?)
+            }
+        })
+    }
+
+    async fn save(&self, page: Page) -> Result<()> {
+        match page {
+            Page::Candles(rows) => self.storage.save_candles(&rows).await,
+            Page::Swaps(rows) => self.storage.save_swaps(&rows).await,
+            Page::Snapshots(rows) => self.storage.save_position_snapshots(&rows).await,
+        }
+    }
+}
+
+/// Refuse storage that would drop the backfilled history on exit
+pub fn require_persistent(storage: &dyn Storage) -> Result<()> {
+    if storage.backend_name() == "memory" {
+        bail!("backfill needs a persistent [storage] backend (sqlite or postgres)");
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn at(secs: i64) -> DateTime<Utc> {
+        Utc.timestamp_opt(secs, 0).unwrap()
+    }
+
+    #[test]
+    fn test_resume() {
+        let existing = BackfillProgress {
+            pool_id: "0xpool".to_string(),
+            dataset: "swaps".to_string(),
+            from: at(1_000),
+            cursor: at(5_000),
+            rows: 42,
+            updated_at: at(5_000),
+        };
+        let resume = |from| BackfillProgress::resume(Some(existing.clone()), "0xpool", Dataset::Swaps, at(from));
+        assert_eq!(resume(1_000).cursor, at(5_000));
+        assert_eq!(resume(3_000).rows, 42);
+        // Earlier than what was backfilled, or past a gap: start over
+        assert_eq!((resume(500).cursor, resume(500).rows), (at(500), 0));
+        assert_eq!(resume(9_000).from, at(9_000));
+    }
+
+    #[test]
+    fn test_page_cursor_skips_rows_already_read() {
+        let mut cursor = PageCursor { at: at(100), seen: HashSet::new() };
+        assert!(cursor.take("a", at(100)));
+        assert!(cursor.take("b", at(100)));
+        assert!(!cursor.take("a", at(100)));
+        assert!(!cursor.take("z", at(99)));
+        assert!(cursor.take("c", at(101)));
+        assert!(cursor.take("a", at(101)));
+        assert_eq!(cursor.at, at(101));
+    }
+}
@@ -0,0 +1,333 @@
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use redis::aio::ConnectionManager;
+use redis::AsyncCommands;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use chrono::Utc;
+use sqlx::sqlite::{SqliteConnectOptions, SqliteJournalMode, SqlitePool, SqlitePoolOptions};
+use std::time::{Duration, Instant};
+use tokio::sync::OnceCell;
+use tracing::{debug, warn};
+
+use crate::config::{CacheBackendKind, CacheConfig};
+
+/// Key/value store with per-entry expiry
+#[async_trait]
+pub trait CacheBackend: Send + Sync {
+    async fn get(&self, key: &str) -> Result<Option<Vec<u8>>>;
+    async fn set(&self, key: &str, value: Vec<u8>, ttl: Duration) -> Result<()>;
+}
+
+/// Process-local cache; lost on restart and not shared between instances
+#[derive(Default)]
+pub struct MemoryCache {
+    entries: Mutex<HashMap<String, (Vec<u8>, Instant)>>,
+}
+
+impl MemoryCache {
+    fn get_at(&self, key: &str, now: Instant) -> Option<Vec<u8>> {
+        let mut entries = self.entries.lock().expect("memory cache lock poisoned");
+        match entries.get(key) {
+            Some((value, expires_at)) if *expires_at > now => Some(value.clone()),
+            Some(_) => {
+                entries.remove(key);
+                None
+            }
+            None => None,
+        }
+    }
+
+    fn set_at(&self, key: &str, value: Vec<u8>, ttl: Duration, now: Instant) {
+        let mut entries = self.entries.lock().expect("memory cache lock poisoned");
+        entries.retain(|_, (_, expires_at)| *expires_at > now);
+        entries.insert(key.to_string(), (value, now + ttl));
+    }
+}
+
+#[async_trait]
+impl CacheBackend for MemoryCache {
+    async fn get(&self, key: &str) -> Result<Option<Vec<u8>>> {
+        Ok(self.get_at(key, Instant::now()))
+    }
+
+    async fn set(&self, key: &str, value: Vec<u8>, ttl: Duration) -> Result<()> {
+        self.set_at(key, value, ttl, Instant::now());
+        Ok(())
+    }
+}
+
+/// Redis-backed cache shared by every instance pointing at the same server
+pub struct RedisCache {
+    client: redis::Client,
+    conn: OnceCell<ConnectionManager>,
+}
+
+impl RedisCache {
+    /// Validate the URL; the connection is opened lazily on first use
+    pub fn new(url: &str) -> Result<Self> {
+        let client = redis::Client::open(url).with_context(|| format!("invalid redis url '{}'", url))?;
+        Ok(Self {
+            client,
+            conn: OnceCell::new(),
+        })
+    }
+
+    async fn connection(&self) -> Result<ConnectionManager> {
+        let conn = self
+            .conn
+            .get_or_try_init(|| ConnectionManager::new(self.client.clone()))
+            .await
+            .context("connecting to redis")?;
+        Ok(conn.clone())
+    }
+}
+
+#[async_trait]
+impl CacheBackend for RedisCache {
+    async fn get(&self, key: &str) -> Result<Option<Vec<u8>>> {
+        let mut conn = self.connection().await?;
+        Ok(conn.get(key).await?)
+    }
+
+    async fn set(&self, key: &str, value: Vec<u8>, ttl: Duration) -> Result<()> {
+        let mut conn = self.connection().await?;
+        let _: () = conn.set_ex(key, value, ttl.as_secs().max(1)).await?;
+        Ok(())
+    }
+}
+
+/// Entries in a local SQLite file, so restarts and separate CLI runs reuse them
+pub struct SqliteCache {
+    path: String,
+    pool: OnceCell<SqlitePool>,
+}
+
+impl SqliteCache {
+    /// The file is opened (and created) lazily on first use
+    pub fn new(path: &str) -> Self {
+        Self {
+            path: path.to_string(),
+            pool: OnceCell::new(),
+        }
+    }
+
+    async fn pool(&self) -> Result<&SqlitePool> {
+        self.pool
+            .get_or_try_init(|| async {
+                let options = SqliteConnectOptions::new()
+                    .filename(&self.path)
+                    .create_if_missing(true)
+                    .journal_mode(SqliteJournalMode::Wal)
+                    .busy_timeout(Duration::from_secs(5));
+                let pool = SqlitePoolOptions::new()
+                    .max_connections(2)
+                    .connect_with(options)
+                    .await
+                    .with_context(|| format!("opening cache database {}", self.path))?;
+                sqlx::query(
+                    "CREATE TABLE IF NOT EXISTS cache_entries (key TEXT PRIMARY KEY, value BLOB NOT NULL, expires_at INTEGER NOT NULL)",
+                )
+                .execute(&pool)
+                .await?;
+                // Expired entries are skipped on read; clear what earlier runs left behind
+                sqlx::query("DELETE FROM cache_entries WHERE expires_at <= ?")
+                    .bind(Utc::now().timestamp())
+                    .execute(&pool)
+                    .await?;
+                Ok::<_, anyhow::Error>(pool)
+            })
+            .await
+    }
+}
+
+#[async_trait]
+impl CacheBackend for SqliteCache {
+    async fn get(&self, key: &str) -> Result<Option<Vec<u8>>> {
+        let row: Option<(Vec<u8>,)> = sqlx::query_as("SELECT value FROM cache_entries WHERE key = ? AND expires_at > ?")
+            .bind(key)
+            .bind(Utc::now().timestamp())
+            .fetch_optional(self.pool().await?)
+            .await?;
+        Ok(row.map(|(value,)| value))
+    }
+
+    async fn set(&self, key: &str, value: Vec<u8>, ttl: Duration) -> Result<()> {
+        let expires_at = Utc::now().timestamp() + ttl.as_secs().max(1) as i64;
+        sqlx::query(
+            "INSERT INTO cache_entries (key, value, expires_at) VALUES (?, ?, ?) \
+             ON CONFLICT (key) DO UPDATE SET value = excluded.value, expires_at = excluded.expires_at",
+        )
+        .bind(key)
+        .bind(value)
+        .bind(expires_at)
+        .execute(self.pool().await?)
+        .await?;
+        Ok(())
+    }
+}
+
+/// Best-effort typed cache over a backend; errors are logged and treated as misses.
+/// A disabled cache never stores anything.
+#[derive(Clone, Default)]
+pub struct Cache {
+    backend: Option<Arc<dyn CacheBackend>>,
+    key_prefix: String,
+    graph_ttl: Duration,
+    /// Overrides of `graph_ttl` by GraphQL operation name
+    graph_query_ttls: Arc<HashMap<String, Duration>>,
+    token_ttl: Duration,
+}
+
+impl Cache {
+    pub fn from_config(config: Option<&CacheConfig>) -> Result<Self> {
+        let Some(cfg) = config else {
+            return Ok(Self::default());
+        };
+        let backend: Arc<dyn CacheBackend> = match cfg.backend {
+            CacheBackendKind::Memory => Arc::new(MemoryCache::default()),
+            CacheBackendKind::Redis => {
+                let url = cfg
+                    .redis_url
+                    .as_deref()
+                    .ok_or_else(|| anyhow::anyhow!("cache backend 'redis' requires redis_url"))?;
+                Arc::new(RedisCache::new(url)?)
+            }
+            CacheBackendKind::Sqlite => {
+                let path = cfg
+                    .sqlite_path
+                    .as_deref()
+                    .ok_or_else(|| anyhow::anyhow!("cache backend 'sqlite' requires sqlite_path"))?;
+                Arc::new(SqliteCache::new(path))
+            }
+        };
+        Ok(Self {
+            backend: Some(backend),
+            key_prefix: cfg.key_prefix.clone(),
+            graph_ttl: Duration::from_secs(cfg.graph_ttl_secs),
+            graph_query_ttls: Arc::new(
+                cfg.graph_query_ttl_secs
+                    .iter()
+                    .map(|(name, secs)| (name.clone(), Duration::from_secs(*secs)))
+                    .collect(),
+            ),
+            token_ttl: Duration::from_secs(cfg.token_ttl_secs),
+        })
+    }
+
+    /// TTL for The Graph query responses
+    pub fn graph_ttl(&self) -> Duration {
+        self.graph_ttl
+    }
+
+    /// TTL for the response to `query`: its operation name's override, else `graph_ttl`
+    pub fn graph_ttl_for(&self, query: &str) -> Duration {
+        operation_name(query)
+            .and_then(|name| self.graph_query_ttls.get(name).copied())
+            .unwrap_or(self.graph_ttl)
+    }
+
+    /// TTL for ERC-20 token metadata (symbol, decimals)
+    pub fn token_ttl(&self) -> Duration {
+        self.token_ttl
+    }
+
+    fn full_key(&self, key: &str) -> String {
+        format!("{}:{}", self.key_prefix, key)
+    }
+
+    pub async fn get_json<T: DeserializeOwned>(&self, key: &str) -> Option<T> {
+        let backend = self.backend.as_ref()?;
+        let key = self.full_key(key);
+        match backend.get(&key).await {
+            Ok(Some(bytes)) => match serde_json::from_slice(&bytes) {
+                Ok(value) => {
+                    debug!(target: "cache", %key, "cache hit");
+                    Some(value)
+                }
+                Err(e) => {
+                    warn!(target: "cache", %key, "discarding undecodable cache entry: {}", e);
+                    None
+                }
+            },
+            Ok(None) => None,
+            Err(e) => {
+                warn!(target: "cache", %key, "cache read failed: {:#}", e);
+                None
+            }
+        }
+    }
+
+    pub async fn set_json<T: Serialize + ?Sized>(&self, key: &str, value: &T, ttl: Duration) {
+        let Some(backend) = self.backend.as_ref() else {
+            return;
+        };
+        if ttl.is_zero() {
+            return;
+        }
+        let key = self.full_key(key);
+        let bytes = match serde_json::to_vec(value) {
+            Ok(bytes) => bytes,
+            Err(e) => {
+                warn!(target: "cache", %key, "failed to encode cache entry: {}", e);
+                return;
+            }
+        };
+        if let Err(e) = backend.set(&key, bytes, ttl).await {
+            warn!(target: "cache", %key, "cache write failed: {:#}", e);
+        }
+    }
+}
+
+/// Name of a GraphQL operation, e.g. `TopPools` in `query TopPools($first: Int!) { ... }`
+fn operation_name(query: &str) -> Option<&str> {
+    let rest = query.trim_start().strip_prefix("query")?.trim_start();
+    let end = rest.find(|c: char| !(c.is_alphanumeric() || c == '_')).unwrap_or(rest.len());
+    (end > 0).then(|| &rest[..end])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_memory_cache_expiry() {
+        let cache = MemoryCache::default();
+        let start = Instant::now();
+        cache.set_at("k", b"v".to_vec(), Duration::from_secs(10), start);
+        assert_eq!(cache.get_at("k", start + Duration::from_secs(9)), Some(b"v".to_vec()));
+        assert_eq!(cache.get_at("k", start + Duration::from_secs(10)), None);
+        assert_eq!(cache.get_at("missing", start), None);
+    }
+
+    #[test]
+    fn test_graph_ttl_by_operation_name() {
+        let config: CacheConfig = toml::from_str(
+            "backend = \"memory\"\nkey_prefix = \"t\"\ngraph_ttl_secs = 60\ntoken_ttl_secs = 600\n[graph_query_ttl_secs]\nTopPools = 300\n",
+        )
+        .unwrap();
+        let cache = Cache::from_config(Some(&config)).unwrap();
+        assert_eq!(cache.graph_ttl_for("\n  query TopPools($first: Int!) { pools { id } }"), Duration::from_secs(300));
+        assert_eq!(cache.graph_ttl_for("query Pool($id: ID!) { pool(id: $id) { id } }"), Duration::from_secs(60));
+        assert_eq!(cache.graph_ttl_for("{ pools { id } }"), Duration::from_secs(60));
+    }
+
+    #[tokio::test]
+    async fn test_sqlite_cache_survives_reopening() {
+        let path = std::env::temp_dir().join(format!("origins-cache-test-{}.db", std::process::id()));
+        let path = path.to_str().unwrap().to_string();
+        let _ = std::fs::remove_file(&path);
+
+        let cache = SqliteCache::new(&path);
+        cache.set("graph:a", b"one".to_vec(), Duration::from_secs(60)).await.unwrap();
+        cache.set("graph:a", b"two".to_vec(), Duration::from_secs(60)).await.unwrap();
+        drop(cache);
+
+        let reopened = SqliteCache::new(&path);
+        assert_eq!(reopened.get("graph:a").await.unwrap(), Some(b"two".to_vec()));
+        assert_eq!(reopened.get("graph:b").await.unwrap(), None);
+        let _ = std::fs::remove_file(&path);
+    }
+}
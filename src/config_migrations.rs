@@ -0,0 +1,178 @@
+//! Upgrades of older config file layouts, applied in memory on load.
+//!
+//! A file's `config_version` says which layout it uses (0 when absent). Every migration
+//! above it runs in order on the parsed TOML before it is deserialized, and each change is
+//! reported so the file can be updated by hand. Like schema migrations these are
+//! append-only: never edit one that has shipped, add a new version instead.
+
+use anyhow::{bail, Result};
+use toml::value::Table;
+use toml::Value;
+
+struct ConfigMigration {
+    version: u32,
+    description: &'static str,
+    /// Rewrites the document, describing each change in the returned notes
+    apply: fn(&mut Table) -> Vec<String>,
+}
+
+const MIGRATIONS: &[ConfigMigration] = &[ConfigMigration {
+    version: 1,
+    description: "move flat section keys into their [section] tables",
+    apply: nest_flat_keys,
+}];
+
+/// Layout this build writes and expects
+pub const CURRENT_CONFIG_VERSION: u32 = 1;
+
+/// Upgrade `document` to [`CURRENT_CONFIG_VERSION`]; returns one note per change. Fails for a
+/// file written for a newer build.
+pub fn migrate(document: &mut Value) -> Result<Vec<String>> {
+    let Some(root) = document.as_table_mut() else {
+        bail!("configuration is not a TOML table");
+    };
+    let version = match root.get("config_version") {
+        None => 0,
+        Some(Value::Integer(v)) if *v >= 0 => *v as u32,
+        Some(other) => bail!("config_version must be a non-negative integer, got {}", other),
+    };
+    if version > CURRENT_CONFIG_VERSION {
+        bail!(
+            "config_version {} is newer than this build supports (latest known is {}); upgrade the binary",
+            version,
+            CURRENT_CONFIG_VERSION
+        );
+    }
+    let mut notes = Vec::new();
+    for migration in MIGRATIONS.iter().filter(|m| m.version > version) {
+        for change in (migration.apply)(root) {
+            notes.push(format!("v{} ({}): {}", migration.version, migration.description, change));
+        }
+    }
+    root.insert("config_version".to_string(), Value::Integer(CURRENT_CONFIG_VERSION as i64));
+    Ok(notes)
+}
+
+/// Keys of the v0 flat layout and the section each belongs to. Headerless keys after a
+/// `[table]` end up inside that table, so they are looked for one table down as well.
+const FLAT_KEYS: &[(&str, &str)] = &[
+    ("coingecko_api_url", "api"),
+    ("coinmarketcap_api_url", "api"),
+    ("coinmarketcap_api_key", "api"),
+    ("defipulse_api_url", "api"),
+    ("thegraph_api_url", "api"),
+    ("thegraph_api_key", "api"),
+    ("max_risk_score", "risk_assessment"),
+    ("min_liquidity_score", "risk_assessment"),
+    ("volatility_threshold", "risk_assessment"),
+    ("recommendation_interval", "recommendations"),
+    ("recommendation_types", "recommendations"),
+    ("log_level", "logging"),
+    ("detailed_logging", "logging"),
+    ("performance_logging", "logging"),
+    ("enable_transaction_signing", "security"),
+    ("gas_settings", "security"),
+    ("market_data_refresh_interval", "market_data"),
+    ("real_time_prices", "market_data"),
+    ("price_sources", "market_data"),
+    ("notifications_enabled", "notifications"),
+    ("notification_channels", "notifications"),
+    ("test_mode", "development"),
+    ("mock_data", "development"),
+];
+
+fn section_of(key: &str) -> Option<&'static str> {
+    FLAT_KEYS.iter().find(|(k, _)| *k == key).map(|(_, section)| *section)
+}
+
+fn nest_flat_keys(root: &mut Table) -> Vec<String> {
+    // Stray keys inside the wrong table first, so a moved table arrives without them
+    let mut found: Vec<(String, String, Value)> = Vec::new();
+    let tables: Vec<String> = root.iter().filter(|(_, v)| v.is_table()).map(|(k, _)| k.clone()).collect();
+    for table_name in tables {
+        let Some(Value::Table(table)) = root.get_mut(&table_name) else {
+            continue;
+        };
+        let stray: Vec<String> = table
+            .keys()
+            .filter(|key| section_of(key).is_some_and(|section| section != table_name))
+            .cloned()
+            .collect();
+        for key in stray {
+            let value = table.remove(&key).expect("key listed above");
+            found.push((format!("{}.{}", table_name, key), key, value));
+        }
+    }
+    let top_level: Vec<String> = root.keys().filter(|key| section_of(key).is_some()).cloned().collect();
+    for key in top_level {
+        let value = root.remove(&key).expect("key listed above");
+        found.push((key.clone(), key, value));
+    }
+
+    let mut notes = Vec::new();
+    for (from, key, value) in found {
+        let section = section_of(&key).expect("only flat keys are collected");
+        let target = root
+            .entry(section.to_string())
+            .or_insert_with(|| Value::Table(Table::new()));
+        let Some(target) = target.as_table_mut() else {
+            notes.push(format!("dropped {} because {} is not a table", from, section));
+            continue;
+        };
+        if target.contains_key(&key) {
+            notes.push(format!("dropped {} because {}.{} is already set", from, section, key));
+            continue;
+        }
+        target.insert(key.clone(), value);
+        notes.push(format!("moved {} to {}.{}", from, section, key));
+    }
+    notes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_flat_layout_is_nested() {
+        // As written by hand before sections were tables: headerless keys fall into the
+        // table declared above them
+        let mut document: Value = toml::from_str(
+            r#"
+rpc_url = "http://localhost:8545"
+coingecko_api_url = "https://api.coingecko.com/api/v3"
+
+[risk_assessment]
+max_risk_score = 0.8
+recommendation_interval = 300
+
+[recommendation_types]
+hold_recommendations = true
+log_level = "debug"
+"#,
+        )
+        .unwrap();
+
+        let notes = migrate(&mut document).unwrap();
+        assert_eq!(notes.len(), 4, "{:?}", notes);
+        assert!(notes.iter().any(|n| n.contains("moved risk_assessment.recommendation_interval to recommendations.recommendation_interval")));
+        assert_eq!(document["config_version"].as_integer(), Some(1));
+        assert_eq!(document["api"]["coingecko_api_url"].as_str(), Some("https://api.coingecko.com/api/v3"));
+        assert_eq!(document["risk_assessment"]["max_risk_score"].as_float(), Some(0.8));
+        assert_eq!(document["recommendations"]["recommendation_interval"].as_integer(), Some(300));
+        assert_eq!(document["recommendations"]["recommendation_types"]["hold_recommendations"].as_bool(), Some(true));
+        assert_eq!(document["logging"]["log_level"].as_str(), Some("debug"));
+        assert!(document.get("recommendation_types").is_none());
+    }
+
+    #[test]
+    fn test_current_files_are_untouched_and_newer_ones_refused() {
+        let mut document: Value = toml::from_str("config_version = 1\n[logging]\nlog_level = \"info\"\n").unwrap();
+        let before = document.clone();
+        assert!(migrate(&mut document).unwrap().is_empty());
+        assert_eq!(document, before);
+
+        let mut newer: Value = toml::from_str("config_version = 2\n").unwrap();
+        assert!(migrate(&mut newer).unwrap_err().to_string().contains("upgrade the binary"));
+    }
+}
@@ -0,0 +1,40 @@
+//! Credentials kept in the OS keyring (macOS Keychain, Windows Credential Manager, Linux
+//! kernel keyutils) for running the CLI on a desktop without secrets in files or env vars.
+//! Config fields reference an entry as `keyring:<name>`.
+
+use anyhow::{Context, Result};
+use keyring::{Entry, Error};
+
+/// Service every entry is stored under
+pub const SERVICE: &str = "origins-position-recommender";
+
+/// Entry holding the keystore passphrase when `security.keystore_password_keyring` is set
+pub const KEYSTORE_PASSPHRASE: &str = "keystore-passphrase";
+
+fn entry(name: &str) -> Result<Entry> {
+    Entry::new(SERVICE, name).with_context(|| format!("opening keyring entry '{}'", name))
+}
+
+/// The stored credential, or `None` if there is no such entry
+pub fn get(name: &str) -> Result<Option<String>> {
+    match entry(name)?.get_password() {
+        Ok(secret) => Ok(Some(secret)),
+        Err(Error::NoEntry) => Ok(None),
+        Err(e) => Err(e).with_context(|| format!("reading keyring entry '{}'", name)),
+    }
+}
+
+pub fn set(name: &str, secret: &str) -> Result<()> {
+    entry(name)?
+        .set_password(secret)
+        .with_context(|| format!("writing keyring entry '{}'", name))
+}
+
+/// Remove an entry; false if it did not exist
+pub fn delete(name: &str) -> Result<bool> {
+    match entry(name)?.delete_credential() {
+        Ok(()) => Ok(true),
+        Err(Error::NoEntry) => Ok(false),
+        Err(e) => Err(e).with_context(|| format!("deleting keyring entry '{}'", name)),
+    }
+}
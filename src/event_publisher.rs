@@ -0,0 +1,285 @@
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use rskafka::client::partition::{Compression, PartitionClient, UnknownTopicHandling};
+use rskafka::client::{Client as KafkaClient, ClientBuilder};
+use rskafka::record::Record;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::broadcast::{self, error::RecvError};
+use tokio::sync::Mutex;
+use tracing::{info, warn};
+
+use crate::config::{EventBusBackend, EventBusConfig};
+use crate::events::{Event, EventBus};
+use crate::quotes::{QuoteBus, QuoteEvent};
+
+/// Version of the published envelope; bump on breaking payload changes
+pub const EVENT_SCHEMA_VERSION: u32 = 1;
+
+/// How long a topic's partition count is trusted before it's read again (Kafka's own
+/// producers refresh metadata on the same schedule)
+const TOPIC_METADATA_TTL: Duration = Duration::from_secs(300);
+
+/// Envelope wrapping every published event
+#[derive(Debug, Serialize)]
+pub struct EventEnvelope<'a> {
+    pub schema_version: u32,
+    pub source: &'static str,
+    pub published_at: chrono::DateTime<chrono::Utc>,
+    #[serde(flatten)]
+    pub event: &'a Event,
+}
+
+/// A message broker that events can be published to
+#[async_trait]
+pub trait EventPublisher: Send + Sync {
+    async fn publish(&self, topic: &str, key: Option<&str>, payload: Vec<u8>) -> Result<()>;
+    fn backend_name(&self) -> &str;
+}
+
+/// Publishes to NATS subjects
+pub struct NatsPublisher {
+    client: async_nats::Client,
+}
+
+impl NatsPublisher {
+    pub async fn connect(servers: &[String]) -> Result<Self> {
+        let client = async_nats::connect(servers.join(","))
+            .await
+            .with_context(|| format!("connecting to NATS at {:?}", servers))?;
+        Ok(Self { client })
+    }
+}
+
+#[async_trait]
+impl EventPublisher for NatsPublisher {
+    async fn publish(&self, topic: &str, _key: Option<&str>, payload: Vec<u8>) -> Result<()> {
+        self.client.publish(topic.to_string(), payload.into()).await?;
+        self.client.flush().await?;
+        Ok(())
+    }
+
+    fn backend_name(&self) -> &str {
+        "nats"
+    }
+}
+
+/// Publishes to Kafka topics, partitioning by event key the way Kafka's default partitioner
+/// does, so keyed consumers see the same layout as from any other producer
+pub struct KafkaPublisher {
+    client: KafkaClient,
+    partitions: Mutex<HashMap<(String, i32), Arc<PartitionClient>>>,
+    /// Topic -> partition count and when it was read
+    partition_counts: Mutex<HashMap<String, (usize, Instant)>>,
+}
+
+impl KafkaPublisher {
+    pub async fn connect(brokers: &[String]) -> Result<Self> {
+        let client = ClientBuilder::new(brokers.to_vec())
+            .build()
+            .await
+            .with_context(|| format!("connecting to Kafka at {:?}", brokers))?;
+        Ok(Self {
+            client,
+            partitions: Mutex::new(HashMap::new()),
+            partition_counts: Mutex::new(HashMap::new()),
+        })
+    }
+
+    /// Partitions of `topic`, read from the cluster at most every [`TOPIC_METADATA_TTL`]
+    async fn partition_count(&self, topic: &str) -> Result<usize> {
+        let mut counts = self.partition_counts.lock().await;
+        if let Some((count, read_at)) = counts.get(topic) {
+            if read_at.elapsed() < TOPIC_METADATA_TTL {
+                return Ok(*count);
+            }
+        }
+        let topics = self.client.list_topics().await?;
+        let count = topics
+            .iter()
+            .find(|t| t.name == topic)
+            .map(|t| t.partitions.len().max(1))
+            .ok_or_else(|| anyhow::anyhow!("Kafka topic '{}' does not exist", topic))?;
+        counts.insert(topic.to_string(), (count, Instant::now()));
+        Ok(count)
+    }
+
+    async fn partition_for(&self, topic: &str, key: Option<&str>) -> Result<Arc<PartitionClient>> {
+        let partition = match key {
+            Some(k) => partition_of(k, self.partition_count(topic).await?),
+            None => 0,
+        };
+
+        let mut cache = self.partitions.lock().await;
+        if let Some(client) = cache.get(&(topic.to_string(), partition)) {
+            return Ok(client.clone());
+        }
+        let client = Arc::new(
+            self.client
+                .partition_client(topic, partition, UnknownTopicHandling::Retry)
+                .await?,
+        );
+        cache.insert((topic.to_string(), partition), client.clone());
+        Ok(client)
+    }
+}
+
+#[async_trait]
+impl EventPublisher for KafkaPublisher {
+    async fn publish(&self, topic: &str, key: Option<&str>, payload: Vec<u8>) -> Result<()> {
+        let partition = self.partition_for(topic, key).await?;
+        let mut headers = BTreeMap::new();
+        headers.insert("schema_version".to_string(), EVENT_SCHEMA_VERSION.to_string().into_bytes());
+        let record = Record {
+            key: key.map(|k| k.as_bytes().to_vec()),
+            value: Some(payload),
+            headers,
+            timestamp: chrono::Utc::now(),
+        };
+        partition.produce(vec![record], Compression::NoCompression).await?;
+        Ok(())
+    }
+
+    fn backend_name(&self) -> &str {
+        "kafka"
+    }
+}
+
+/// Partition Kafka's default partitioner assigns `key`: positive murmur2 modulo the count
+fn partition_of(key: &str, count: usize) -> i32 {
+    ((murmur2(key.as_bytes()) & 0x7fff_ffff) as usize % count.max(1)) as i32
+}
+
+/// Kafka's murmur2 (seed `0x9747b28c`), stable across releases unlike `std`'s hasher
+fn murmur2(data: &[u8]) -> u32 {
+    const M: u32 = 0x5bd1_e995;
+    const R: u32 = 24;
+    let mut h: u32 = 0x9747_b28c ^ data.len() as u32;
+    let chunks = data.chunks_exact(4);
+    let tail = chunks.remainder();
+    for chunk in chunks {
+        let mut k = u32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]);
+        k = k.wrapping_mul(M);
+        k ^= k >> R;
+        k = k.wrapping_mul(M);
+        h = h.wrapping_mul(M);
+        h ^= k;
+    }
+    if tail.len() >= 3 {
+        h ^= (tail[2] as u32) << 16;
+    }
+    if tail.len() >= 2 {
+        h ^= (tail[1] as u32) << 8;
+    }
+    if let Some(first) = tail.first() {
+        h ^= *first as u32;
+        h = h.wrapping_mul(M);
+    }
+    h ^= h >> 13;
+    h = h.wrapping_mul(M);
+    h ^= h >> 15;
+    h
+}
+
+/// Connect the publisher selected in config
+pub async fn connect(config: &EventBusConfig) -> Result<Box<dyn EventPublisher>> {
+    Ok(match config.backend {
+        EventBusBackend::Nats => Box::new(NatsPublisher::connect(&config.servers).await?),
+        EventBusBackend::Kafka => Box::new(KafkaPublisher::connect(&config.servers).await?),
+    })
+}
+
+/// Topic/subject for an event: `{prefix}.{event type}`
+pub fn topic_for(prefix: &str, event: &Event) -> String {
+    format!("{}.{}", prefix, event.kind())
+}
+
+/// Next quote, or never once the quote bus has closed
+async fn next_quote(rx: &mut Option<broadcast::Receiver<QuoteEvent>>) -> Result<QuoteEvent, RecvError> {
+    match rx {
+        Some(rx) => rx.recv().await,
+        None => std::future::pending().await,
+    }
+}
+
+/// Forward every event from the bus, and a price update for every quote, to the broker until
+/// the event bus closes
+pub async fn run(publisher: Box<dyn EventPublisher>, config: EventBusConfig, bus: EventBus, quotes: QuoteBus) {
+    let mut rx = bus.subscribe();
+    let mut quote_rx = Some(quotes.subscribe());
+    drop(quotes);
+    info!(target: "event_publisher", backend = publisher.backend_name(), prefix = %config.topic_prefix, "publishing events");
+    loop {
+        let event = tokio::select! {
+            event = rx.recv() => match event {
+                Ok(event) => event,
+                Err(RecvError::Lagged(skipped)) => {
+                    warn!(target: "event_publisher", skipped, "event publisher lagged, events dropped");
+                    continue;
+                }
+                Err(RecvError::Closed) => break,
+            },
+            quote = next_quote(&mut quote_rx) => match quote {
+                Ok(quote) => match quote.price_update() {
+                    Some(event) => event,
+                    None => continue,
+                },
+                Err(RecvError::Lagged(skipped)) => {
+                    warn!(target: "event_publisher", skipped, "event publisher lagged, price updates dropped");
+                    continue;
+                }
+                Err(RecvError::Closed) => {
+                    quote_rx = None;
+                    continue;
+                }
+            },
+        };
+        let envelope = EventEnvelope {
+            schema_version: EVENT_SCHEMA_VERSION,
+            source: "origins-position-recommender",
+            published_at: chrono::Utc::now(),
+            event: &event,
+        };
+        let payload = match serde_json::to_vec(&envelope) {
+            Ok(payload) => payload,
+            Err(e) => {
+                warn!(target: "event_publisher", "failed to encode event: {}", e);
+                continue;
+            }
+        };
+        let topic = topic_for(&config.topic_prefix, &event);
+        // Price updates have no wallet; keyed by pool, each pool's prices stay in order
+        let key = match &event {
+            Event::PriceUpdated { price, .. } => Some(price.pool.as_str()),
+            _ => event.wallet(),
+        };
+        if let Err(e) = publisher.publish(&topic, key, payload).await {
+            warn!(target: "event_publisher", %topic, "failed to publish event: {:#}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_murmur2_matches_kafka() {
+        // Vectors from Kafka's own tests of `Utils.murmur2`
+        let cases = [
+            ("21", -973_932_308i32),
+            ("foobar", -790_332_482),
+            ("a-little-bit-long-string", -985_981_536),
+            ("a-little-bit-longer-string", -1_486_304_829),
+            ("lkjh234lh9fiuh90y23oiuhsafujhadof229phr9h19h89h8", -58_897_971),
+            ("abc", 479_470_107),
+        ];
+        for (key, expected) in cases {
+            assert_eq!(murmur2(key.as_bytes()) as i32, expected, "{}", key);
+        }
+        assert_eq!(partition_of("abc", 4), 479_470_107 % 4);
+        assert!((0..7).contains(&partition_of("foobar", 7)));
+    }
+}
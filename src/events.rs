@@ -0,0 +1,268 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast;
+
+use crate::position::{Action, PositionRecommendation};
+
+/// Capacity of the broadcast channel; slow subscribers lag rather than block the loop
+const EVENT_BUS_CAPACITY: usize = 1024;
+
+/// How urgently an event needs attention; drives formatting and routing in notifiers
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "server", derive(utoipa::ToSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    #[default]
+    Info,
+    Warning,
+    Critical,
+}
+
+/// Events emitted by the recommender for downstream sinks
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "server", derive(utoipa::ToSchema))]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Event {
+    /// A position received its first recommendation, or its suggested action changed
+    RecommendationChanged {
+        timestamp: DateTime<Utc>,
+        previous_action: Option<Action>,
+        recommendation: PositionRecommendation,
+    },
+    /// The pool price of a tracked position moved outside its tick range; it stops earning fees
+    PositionOutOfRange {
+        timestamp: DateTime<Utc>,
+        #[serde(flatten)]
+        range: RangeStatus,
+    },
+    /// A previously out-of-range position is back within its tick range
+    PositionBackInRange {
+        timestamp: DateTime<Utc>,
+        #[serde(flatten)]
+        range: RangeStatus,
+    },
+    /// A position's uncollected fees crossed the configured threshold; collecting now pays off
+    FeesCollectable {
+        timestamp: DateTime<Utc>,
+        #[serde(flatten)]
+        fees: UncollectedFees,
+    },
+    /// The executor collected a position's fees on-chain
+    FeesCollected {
+        timestamp: DateTime<Utc>,
+        #[serde(flatten)]
+        collection: FeeCollection,
+    },
+    /// A rebalance workflow finished: liquidity moved from the old position into a new range
+    PositionRebalanced {
+        timestamp: DateTime<Utc>,
+        #[serde(flatten)]
+        rebalance: RebalanceSummary,
+    },
+    /// A rebalance workflow stopped at a failed step; funds may sit in the wallet until it is
+    /// retried
+    RebalanceFailed {
+        timestamp: DateTime<Utc>,
+        #[serde(flatten)]
+        failure: RebalanceFailure,
+    },
+    /// A quoted pool's latest price. Published to the event bus (`[event_bus]`) straight from
+    /// the quote pipeline, never to the notifiers, webhooks or alert store
+    PriceUpdated {
+        timestamp: DateTime<Utc>,
+        #[serde(flatten)]
+        price: PriceUpdate,
+    },
+}
+
+/// Fees a position could collect, in token units and USD
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "server", derive(utoipa::ToSchema))]
+pub struct UncollectedFees {
+    pub position_id: String,
+    pub owner: String,
+    pub token0_symbol: String,
+    pub token1_symbol: String,
+    pub amount0: f64,
+    pub amount1: f64,
+    pub value_usd: f64,
+    /// Estimated gas cost of the `collect` transaction, when known
+    pub collect_gas_usd: Option<f64>,
+}
+
+/// Fees realized by a `collect` transaction, read from its receipt
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "server", derive(utoipa::ToSchema))]
+pub struct FeeCollection {
+    pub position_id: String,
+    pub owner: String,
+    pub token0_symbol: String,
+    pub token1_symbol: String,
+    pub amount0: f64,
+    pub amount1: f64,
+    pub tx_hash: String,
+    pub block_number: u64,
+    /// Gas paid for the transaction, in ETH
+    pub gas_cost_eth: f64,
+}
+
+/// Outcome of a completed rebalance workflow
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "server", derive(utoipa::ToSchema))]
+pub struct RebalanceSummary {
+    pub workflow_id: String,
+    pub position_id: String,
+    /// Position minted at the new range
+    pub new_position_id: Option<String>,
+    pub owner: String,
+    pub pair: String,
+    pub old_tick_lower: i32,
+    pub old_tick_upper: i32,
+    pub tick_lower: i32,
+    pub tick_upper: i32,
+    pub tx_hashes: Vec<String>,
+    /// Gas paid across all steps, in ETH
+    pub gas_cost_eth: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "server", derive(utoipa::ToSchema))]
+pub struct RebalanceFailure {
+    pub workflow_id: String,
+    pub position_id: String,
+    pub owner: String,
+    pub pair: String,
+    /// Step that failed, e.g. `mint`
+    pub step: String,
+    pub error: String,
+}
+
+/// A pool quote's price, with the TVL and cumulative volume quoted alongside it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "server", derive(utoipa::ToSchema))]
+pub struct PriceUpdate {
+    pub pool: String,
+    /// e.g. `WETH/USDC`
+    pub pair: String,
+    /// Hundredths of a basis point
+    pub fee_tier: u32,
+    /// token1 per token0
+    pub price: f64,
+    pub tvl_usd: f64,
+    pub volume_usd: f64,
+    /// Tracked position the pool was quoted for, if any
+    pub position_id: Option<String>,
+}
+
+/// A position's tick range against its pool's current tick
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "server", derive(utoipa::ToSchema))]
+pub struct RangeStatus {
+    pub position_id: String,
+    pub owner: String,
+    pub pool: String,
+    /// e.g. `WETH/USDC`
+    pub pair: String,
+    pub tick_lower: i32,
+    pub tick_upper: i32,
+    pub current_tick: i32,
+}
+
+impl Event {
+    /// Stable event name, matching the serialized `type` tag
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Event::RecommendationChanged { .. } => "recommendation_changed",
+            Event::PositionOutOfRange { .. } => "position_out_of_range",
+            Event::PositionBackInRange { .. } => "position_back_in_range",
+            Event::FeesCollectable { .. } => "fees_collectable",
+            Event::FeesCollected { .. } => "fees_collected",
+            Event::PositionRebalanced { .. } => "position_rebalanced",
+            Event::RebalanceFailed { .. } => "rebalance_failed",
+            Event::PriceUpdated { .. } => "price_updated",
+        }
+    }
+
+    pub fn timestamp(&self) -> DateTime<Utc> {
+        match self {
+            Event::RecommendationChanged { timestamp, .. }
+            | Event::PositionOutOfRange { timestamp, .. }
+            | Event::PositionBackInRange { timestamp, .. }
+            | Event::FeesCollectable { timestamp, .. }
+            | Event::FeesCollected { timestamp, .. }
+            | Event::PositionRebalanced { timestamp, .. }
+            | Event::RebalanceFailed { timestamp, .. }
+            | Event::PriceUpdated { timestamp, .. } => *timestamp,
+        }
+    }
+
+    /// Position the event concerns; for a price update of a pool no position is tracked in,
+    /// the pool
+    pub fn position_id(&self) -> &str {
+        match self {
+            Event::RecommendationChanged { recommendation, .. } => &recommendation.position.id,
+            Event::PositionOutOfRange { range, .. } | Event::PositionBackInRange { range, .. } => &range.position_id,
+            Event::FeesCollectable { fees, .. } => &fees.position_id,
+            Event::FeesCollected { collection, .. } => &collection.position_id,
+            Event::PositionRebalanced { rebalance, .. } => &rebalance.position_id,
+            Event::RebalanceFailed { failure, .. } => &failure.position_id,
+            Event::PriceUpdated { price, .. } => price.position_id.as_deref().unwrap_or(&price.pool),
+        }
+    }
+
+    pub fn severity(&self) -> Severity {
+        match self {
+            Event::RecommendationChanged { recommendation, .. } => match recommendation.suggested_action {
+                Action::Exit => Severity::Critical,
+                Action::Decrease | Action::Rebalance { .. } => Severity::Warning,
+                Action::Increase | Action::Hold => Severity::Info,
+            },
+            Event::PositionOutOfRange { .. } => Severity::Critical,
+            Event::PositionBackInRange { .. } => Severity::Info,
+            Event::FeesCollectable { .. } => Severity::Warning,
+            Event::FeesCollected { .. } | Event::PositionRebalanced { .. } | Event::PriceUpdated { .. } => Severity::Info,
+            Event::RebalanceFailed { .. } => Severity::Critical,
+        }
+    }
+
+    /// Wallet the event concerns, used for per-tenant filtering
+    pub fn wallet(&self) -> Option<&str> {
+        match self {
+            Event::RecommendationChanged { recommendation, .. } => Some(&recommendation.position.user_address),
+            Event::PositionOutOfRange { range, .. } | Event::PositionBackInRange { range, .. } => Some(&range.owner),
+            Event::FeesCollectable { fees, .. } => Some(&fees.owner),
+            Event::FeesCollected { collection, .. } => Some(&collection.owner),
+            Event::PositionRebalanced { rebalance, .. } => Some(&rebalance.owner),
+            Event::RebalanceFailed { failure, .. } => Some(&failure.owner),
+            Event::PriceUpdated { .. } => None,
+        }
+    }
+}
+
+/// Fan-out channel for `Event`s; cheap to clone, one receiver per subscriber
+#[derive(Clone)]
+pub struct EventBus {
+    sender: broadcast::Sender<Event>,
+}
+
+impl Default for EventBus {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl EventBus {
+    pub fn new() -> Self {
+        let (sender, _) = broadcast::channel(EVENT_BUS_CAPACITY);
+        Self { sender }
+    }
+
+    /// Publish an event; dropped silently when nobody is subscribed
+    pub fn publish(&self, event: Event) {
+        let _ = self.sender.send(event);
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<Event> {
+        self.sender.subscribe()
+    }
+}
@@ -0,0 +1,92 @@
+use anyhow::{bail, Result};
+use ethereum_types::{Address, U256};
+use std::sync::Arc;
+use tracing::info;
+
+use super::tx::{Receipt, TxRequest, TxSender};
+use crate::config::{ApprovalConfig, ApprovalPolicy};
+use crate::uniswap::{self, UniswapClient};
+
+/// Allowance a transaction needs from the signer
+#[derive(Debug, Clone)]
+pub struct Approval {
+    pub token: String,
+    pub spender: Address,
+    pub amount: U256,
+}
+
+/// Checks allowances before spending transactions and, when enabled, approves the shortfall
+pub struct AllowanceManager {
+    sender: Arc<TxSender>,
+    client: UniswapClient,
+    rpc_url: String,
+    config: ApprovalConfig,
+}
+
+impl AllowanceManager {
+    pub fn new(sender: Arc<TxSender>, client: UniswapClient, rpc_url: &str, config: &ApprovalConfig) -> Self {
+        Self {
+            sender,
+            client,
+            rpc_url: rpc_url.to_string(),
+            config: config.clone(),
+        }
+    }
+
+    /// Make sure `spender` may move `amount` of `token`; returns the approval receipt if one
+    /// was sent
+    pub async fn ensure(&self, approval: &Approval) -> Result<Option<Receipt>> {
+        if approval.amount.is_zero() {
+            return Ok(None);
+        }
+        let owner = format!("{:?}", self.sender.address());
+        let current = self
+            .client
+            .token_allowance(&self.rpc_url, &approval.token, &owner, approval.spender)
+            .await?;
+        if current >= approval.amount {
+            return Ok(None);
+        }
+        if !self.config.auto_approve {
+            bail!(
+                "allowance of {} for spender {:?} is {}, need {}; approve it or enable execution.approvals.auto_approve",
+                approval.token,
+                approval.spender,
+                current,
+                approval.amount
+            );
+        }
+        let amount = approval_amount(self.config.policy, approval.amount);
+        info!(target: "executor", token = %approval.token, spender = ?approval.spender, %amount, "approving token");
+        let request = TxRequest {
+            to: approval.token.parse()?,
+            data: uniswap::approve_call(approval.spender, amount),
+            value: U256::zero(),
+            label: "approve",
+            position_id: None,
+        };
+        self.sender.send(&request).await.map(Some)
+    }
+}
+
+fn approval_amount(policy: ApprovalPolicy, needed: U256) -> U256 {
+    match policy {
+        ApprovalPolicy::Exact => needed,
+        ApprovalPolicy::Unlimited => U256::MAX,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_approval_policy() {
+        let needed = U256::from(1_000u64);
+        assert_eq!(approval_amount(ApprovalPolicy::Exact, needed), needed);
+        assert_eq!(approval_amount(ApprovalPolicy::Unlimited, needed), U256::MAX);
+        let config: ApprovalConfig = toml::from_str("auto_approve = true\npolicy = \"unlimited\"").unwrap();
+        assert_eq!(config.policy, ApprovalPolicy::Unlimited);
+        assert_eq!(ApprovalConfig::default().policy, ApprovalPolicy::Exact);
+    }
+}
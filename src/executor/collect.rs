@@ -0,0 +1,147 @@
+use anyhow::{anyhow, Result};
+use chrono::Utc;
+use ethereum_types::{Address, H256, U256};
+use sha3::{Digest, Keccak256};
+use std::sync::Arc;
+use tokio::sync::broadcast::error::RecvError;
+use tracing::{info, warn};
+
+use super::tx::{Receipt, TxRequest, TxSender};
+use crate::events::{Event, EventBus, FeeCollection, UncollectedFees};
+use crate::uniswap::UniswapClient;
+use crate::utils::to_units;
+
+/// Sends `collect()` for positions whose fee alert fired and reports the realized amounts
+pub struct FeeCollector {
+    sender: Arc<TxSender>,
+    client: UniswapClient,
+    rpc_url: String,
+    bus: EventBus,
+}
+
+impl FeeCollector {
+    pub fn new(sender: Arc<TxSender>, client: UniswapClient, rpc_url: &str, bus: EventBus) -> Self {
+        Self {
+            sender,
+            client,
+            rpc_url: rpc_url.to_string(),
+            bus,
+        }
+    }
+
+    /// Collect on each `fees_collectable` event until the bus closes; one transaction at a time
+    pub async fn run(self) {
+        info!(target: "executor", account = ?self.sender.address(), "automatic fee collection enabled");
+        let mut rx = self.bus.subscribe();
+        loop {
+            match rx.recv().await {
+                Ok(Event::FeesCollectable { fees, .. }) => {
+                    if let Err(e) = self.collect(&fees).await {
+                        warn!(target: "executor", position = %fees.position_id, "fee collection failed: {:#}", e);
+                    }
+                }
+                Ok(_) => {}
+                Err(RecvError::Lagged(skipped)) => {
+                    warn!(target: "executor", skipped, "fee collector lagged, events dropped");
+                }
+                Err(RecvError::Closed) => break,
+            }
+        }
+    }
+
+    async fn collect(&self, fees: &UncollectedFees) -> Result<()> {
+        let owner: Address = fees.owner.parse().map_err(|_| anyhow!("invalid owner address {}", fees.owner))?;
+        if owner != self.sender.address() {
+            warn!(target: "executor", position = %fees.position_id, owner = %fees.owner, "position not owned by the signer, not collecting");
+            return Ok(());
+        }
+        info!(target: "executor", position = %fees.position_id, value_usd = fees.value_usd, "collecting fees");
+        let request = TxRequest {
+            to: self.client.contracts().position_manager,
+            data: UniswapClient::collect_call(&fees.position_id, &fees.owner)?,
+            value: U256::zero(),
+            label: "collect",
+            position_id: Some(fees.position_id.clone()),
+        };
+        let receipt = self.sender.send(&request).await?;
+        let token_id = U256::from_dec_str(&fees.position_id)?;
+        let (raw0, raw1) = collected_amounts(&receipt, self.client.contracts().position_manager, token_id)
+            .ok_or_else(|| anyhow!("no Collect event in receipt {:?}", receipt.tx_hash))?;
+        let position = self.client.get_onchain_position(&self.rpc_url, &fees.position_id).await?;
+        let collection = FeeCollection {
+            position_id: fees.position_id.clone(),
+            owner: fees.owner.clone(),
+            token0_symbol: fees.token0_symbol.clone(),
+            token1_symbol: fees.token1_symbol.clone(),
+            amount0: to_units(raw0, position.token0_decimals),
+            amount1: to_units(raw1, position.token1_decimals),
+            tx_hash: format!("{:?}", receipt.tx_hash),
+            block_number: receipt.block_number,
+            gas_cost_eth: to_units(receipt.gas_cost_wei(), 18),
+        };
+        info!(
+            target: "executor",
+            position = %fees.position_id,
+            tx = %collection.tx_hash,
+            amount0 = collection.amount0,
+            amount1 = collection.amount1,
+            gas_cost_eth = collection.gas_cost_eth,
+            "fees collected"
+        );
+        self.bus.publish(Event::FeesCollected {
+            timestamp: Utc::now(),
+            collection,
+        });
+        Ok(())
+    }
+}
+
+/// Amounts from the position manager's `Collect(uint256 indexed tokenId, address recipient,
+/// uint256 amount0, uint256 amount1)` log for `token_id`
+pub(super) fn collected_amounts(receipt: &Receipt, manager: Address, token_id: U256) -> Option<(U256, U256)> {
+    let topic = H256::from_slice(&Keccak256::digest(b"Collect(uint256,address,uint256,uint256)"));
+    let mut id_topic = [0u8; 32];
+    token_id.to_big_endian(&mut id_topic);
+    receipt
+        .logs
+        .iter()
+        .find(|log| {
+            log.address == manager && log.topics.first() == Some(&topic) && log.topics.get(1) == Some(&H256(id_topic))
+        })
+        .filter(|log| log.data.len() >= 96)
+        .map(|log| (U256::from_big_endian(&log.data[32..64]), U256::from_big_endian(&log.data[64..96])))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::executor::Log;
+    use crate::uniswap::POSITION_MANAGER;
+
+    #[test]
+    fn test_collected_amounts_from_receipt() {
+        let manager: Address = POSITION_MANAGER.parse().unwrap();
+        let topic = H256::from_slice(&Keccak256::digest(b"Collect(uint256,address,uint256,uint256)"));
+        let mut data = vec![0u8; 96];
+        U256::from(1_500_000u64).to_big_endian(&mut data[32..64]);
+        U256::from(42u64).to_big_endian(&mut data[64..96]);
+        let log = |id: u64| Log {
+            address: manager,
+            topics: vec![topic, H256::from_low_u64_be(id)],
+            data: data.clone(),
+        };
+        let receipt = Receipt {
+            tx_hash: H256::zero(),
+            block_number: 1,
+            gas_used: U256::from(100_000u64),
+            effective_gas_price: U256::from(10u64),
+            logs: vec![log(7), log(12345)],
+        };
+        assert_eq!(
+            collected_amounts(&receipt, manager, U256::from(12345u64)),
+            Some((U256::from(1_500_000u64), U256::from(42u64)))
+        );
+        assert_eq!(collected_amounts(&receipt, manager, U256::from(1u64)), None);
+        assert_eq!(receipt.gas_cost_wei(), U256::from(1_000_000u64));
+    }
+}
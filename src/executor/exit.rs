@@ -0,0 +1,281 @@
+use anyhow::{Context, Result};
+use chrono::Utc;
+use ethereum_types::{Address, U256};
+use std::fmt::Write;
+use std::sync::Arc;
+use tracing::{info, warn};
+
+use super::slippage::{Slippage, SlippageCall};
+use super::tx::{TxRequest, TxSender};
+use super::workflow::{RebalanceQuery, WorkflowStatus};
+use crate::alerts::PositionInfo;
+use crate::config::ExecutionConfig;
+use crate::storage::Storage;
+use crate::uniswap::{self, positions, UniswapClient};
+use crate::utils::{format_thousands, to_units};
+
+/// Deadline for the exit transactions when no rebalance deadline is configured
+const DEFAULT_DEADLINE_SECS: u64 = 600;
+/// Most recent completed rebalances whose minted positions count as tracked
+const REBALANCE_LOOKBACK: usize = 1_000;
+
+/// Every tracked Uniswap position id, each once: `configured` (`uniswap.position_ids`), the
+/// Uniswap positions of the latest recorded cycle, and the positions rebalances minted
+pub async fn tracked_position_ids(storage: &dyn Storage, configured: &[String]) -> Result<Vec<String>> {
+    let mut ids = configured.to_vec();
+    let recorded = storage.positions().await.context("loading the latest cycle's positions")?;
+    ids.extend(
+        recorded
+            .into_iter()
+            .filter(|p| p.liquidity.as_ref().is_some_and(|l| l.protocol == positions::PROTOCOL))
+            .map(|p| p.id),
+    );
+    let completed = RebalanceQuery {
+        status: Some(WorkflowStatus::Completed),
+        limit: REBALANCE_LOOKBACK,
+        ..Default::default()
+    };
+    let rebalances = storage.rebalances(&completed).await.context("loading completed rebalances")?;
+    ids.extend(rebalances.into_iter().filter_map(|w| w.new_position_id));
+    let mut seen = std::collections::HashSet::new();
+    ids.retain(|id| seen.insert(id.clone()));
+    Ok(ids)
+}
+
+/// One position's exit: what it releases and the transactions that do it
+#[derive(Debug, Clone)]
+pub struct ExitPosition {
+    pub position_id: String,
+    /// e.g. `WETH/USDC`
+    pub pair: String,
+    pub liquidity: u128,
+    /// Released by withdrawing the liquidity
+    pub amount0: f64,
+    pub amount1: f64,
+    pub fees0: f64,
+    pub fees1: f64,
+    pub value_usd: f64,
+    pub fees_usd: f64,
+    /// `decreaseLiquidity` (when there is liquidity) then `collect`
+    pub requests: Vec<TxRequest>,
+    /// Why simulating the transactions failed
+    pub simulation_error: Option<String>,
+}
+
+/// Withdraws all liquidity and collects fees from an owner's tracked positions
+pub struct EmergencyExit {
+    sender: Arc<TxSender>,
+    client: UniswapClient,
+    rpc_url: String,
+    slippage: Slippage,
+    deadline_secs: u64,
+}
+
+impl EmergencyExit {
+    pub fn new(sender: Arc<TxSender>, client: UniswapClient, rpc_url: &str, config: &ExecutionConfig) -> Result<Self> {
+        Ok(Self {
+            sender,
+            client,
+            rpc_url: rpc_url.to_string(),
+            slippage: Slippage::new(&config.slippage)?,
+            deadline_secs: config.rebalance.as_ref().map_or(DEFAULT_DEADLINE_SECS, |r| r.deadline_secs),
+        })
+    }
+
+    /// The account the transactions act for
+    pub fn account(&self) -> Address {
+        self.sender.address()
+    }
+
+    /// Build and simulate the exit of each of `position_ids` owned by `owner` that holds
+    /// liquidity or fees; positions of other owners are skipped
+    pub async fn plan(&self, owner: Address, position_ids: &[String]) -> Result<Vec<ExitPosition>> {
+        let owner_hex = format!("{:?}", owner);
+        let deadline = Utc::now().timestamp() as u64 + self.deadline_secs;
+        let manager = self.client.contracts().position_manager;
+        let mut plan = Vec::new();
+        for id in position_ids {
+            let info = PositionInfo::resolve(&self.client, &self.rpc_url, id)
+                .await
+                .with_context(|| format!("resolving position {}", id))?;
+            if info.owner.parse::<Address>().ok() != Some(owner) {
+                info!(target: "executor", position = %id, owner = %info.owner, "position has another owner, not exiting");
+                continue;
+            }
+            let position = self.client.get_onchain_position(&self.rpc_url, id).await?;
+            let liquidity: u128 = position.liquidity.parse().context("invalid position liquidity")?;
+            let (raw0, raw1) = if liquidity > 0 {
+                self.client.decrease_liquidity_amounts(&self.rpc_url, id, &owner_hex, liquidity).await?
+            } else {
+                (U256::zero(), U256::zero())
+            };
+            let (fees0, fees1) = self.client.uncollected_fees(&self.rpc_url, id, &owner_hex).await?;
+            if liquidity == 0 && fees0.is_zero() && fees1.is_zero() {
+                continue;
+            }
+            let prices = self.client.token_prices_usd(&[&info.token0, &info.token1]).await?;
+            let (price0, price1) = (prices.usd(&info.token0).unwrap_or(0.0), prices.usd(&info.token1).unwrap_or(0.0));
+            let amount0 = to_units(raw0, info.token0_decimals);
+            let amount1 = to_units(raw1, info.token1_decimals);
+            let fees0 = to_units(fees0, info.token0_decimals);
+            let fees1 = to_units(fees1, info.token1_decimals);
+
+            let mut requests = Vec::new();
+            if liquidity > 0 {
+                let data = uniswap::decrease_liquidity_call(
+                    id,
+                    liquidity,
+                    self.slippage.min_amount(SlippageCall::DecreaseLiquidity, raw0),
+                    self.slippage.min_amount(SlippageCall::DecreaseLiquidity, raw1),
+                    deadline,
+                )?;
+                requests.push(manager_call(manager, id, "decrease_liquidity", data));
+            }
+            requests.push(manager_call(manager, id, "collect", UniswapClient::collect_call(id, &owner_hex)?));
+
+            let mut simulation_error = None;
+            for request in &requests {
+                if let Err(e) = self.sender.simulate_exit(request).await {
+                    simulation_error = Some(format!("{}: {:#}", request.label, e));
+                    break;
+                }
+            }
+            plan.push(ExitPosition {
+                position_id: id.clone(),
+                pair: info.pair(),
+                liquidity,
+                amount0,
+                amount1,
+                fees0,
+                fees1,
+                value_usd: amount0 * price0 + amount1 * price1,
+                fees_usd: fees0 * price0 + fees1 * price1,
+                requests,
+                simulation_error,
+            });
+        }
+        Ok(plan)
+    }
+
+    /// Send each position's transactions in turn, continuing past failed positions;
+    /// returns each position's outcome
+    pub async fn execute(&self, plan: &[ExitPosition]) -> Vec<(String, Result<()>)> {
+        let mut outcomes = Vec::new();
+        for exit in plan {
+            let mut outcome = Ok(());
+            for request in &exit.requests {
+                match self.sender.send_exit(request).await {
+                    Ok(receipt) => {
+                        info!(target: "executor", position = %exit.position_id, step = request.label, tx = ?receipt.tx_hash, "exit transaction confirmed");
+                    }
+                    Err(e) => {
+                        warn!(target: "executor", position = %exit.position_id, step = request.label, "exit transaction failed: {:#}", e);
+                        outcome = Err(e.context(request.label));
+                        break;
+                    }
+                }
+            }
+            outcomes.push((exit.position_id.clone(), outcome));
+        }
+        outcomes
+    }
+}
+
+fn manager_call(manager: Address, position_id: &str, label: &'static str, data: Vec<u8>) -> TxRequest {
+    TxRequest {
+        to: manager,
+        data,
+        value: U256::zero(),
+        label,
+        position_id: Some(position_id.to_string()),
+    }
+}
+
+/// Table of the planned exits with totals, for the confirmation prompt
+pub fn summary(plan: &[ExitPosition]) -> String {
+    let mut out = String::new();
+    let _ = writeln!(out, "{:<12} {:<14} {:>18} {:>18} {:>14} {:>12}  simulation", "position", "pair", "amount0", "amount1", "value USD", "fees USD");
+    for exit in plan {
+        let simulation = exit.simulation_error.as_deref().unwrap_or("ok");
+        let _ = writeln!(
+            out,
+            "{:<12} {:<14} {:>18.6} {:>18.6} {:>14.2} {:>12.2}  {}",
+            exit.position_id,
+            exit.pair,
+            exit.amount0 + exit.fees0,
+            exit.amount1 + exit.fees1,
+            exit.value_usd,
+            exit.fees_usd,
+            simulation
+        );
+    }
+    let value: f64 = plan.iter().map(|e| e.value_usd).sum();
+    let fees: f64 = plan.iter().map(|e| e.fees_usd).sum();
+    let failing = plan.iter().filter(|e| e.simulation_error.is_some()).count();
+    let _ = write!(
+        out,
+        "{} positions, {} USD of liquidity and {} USD of fees; {} failed simulation",
+        plan.len(),
+        format_thousands(value, 2),
+        format_thousands(fees, 2),
+        failing
+    );
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_summary() {
+        let exit = |id: &str, value_usd: f64, simulation_error: Option<&str>| ExitPosition {
+            position_id: id.to_string(),
+            pair: "WETH/USDC".to_string(),
+            liquidity: 1,
+            amount0: 1.5,
+            amount1: 3000.0,
+            fees0: 0.01,
+            fees1: 20.0,
+            value_usd,
+            fees_usd: 40.0,
+            requests: Vec::new(),
+            simulation_error: simulation_error.map(str::to_string),
+        };
+        let text = summary(&[exit("1", 6000.0, None), exit("2", 1000.5, Some("collect: transaction would revert: STF"))]);
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines.len(), 4);
+        assert!(lines[1].contains("1.510000") && lines[1].contains("3020.000000") && lines[1].ends_with("ok"));
+        assert!(lines[2].ends_with("collect: transaction would revert: STF"));
+        assert_eq!(lines[3], "2 positions, 7,000.50 USD of liquidity and 80.00 USD of fees; 1 failed simulation");
+    }
+
+    #[tokio::test]
+    async fn test_tracked_position_ids() {
+        use crate::position::{LiquidityMetrics, Position};
+        use rust_decimal::Decimal;
+
+        let storage = crate::storage::MemoryStorage::default();
+        let nft = |id: &str| {
+            let mut position = Position::new(id.to_string(), "0xowner".to_string(), "0xweth".to_string(), Decimal::ONE, Decimal::ONE);
+            position.liquidity = Some(Box::new(LiquidityMetrics {
+                protocol: positions::PROTOCOL.to_string(),
+                pool: "0xpool".to_string(),
+                token0: "0xweth".to_string(),
+                token1: "0xusdc".to_string(),
+                token0_symbol: "WETH".to_string(),
+                token1_symbol: "USDC".to_string(),
+                fee: 500,
+                tick_lower: -100,
+                tick_upper: 100,
+                current_tick: 0,
+                liquidity: "1".to_string(),
+            }));
+            position
+        };
+        let lending = Position::new("aave-1".to_string(), "0xowner".to_string(), "0xusdc".to_string(), Decimal::ONE, Decimal::ONE);
+        storage.record_cycle(&[nft("7"), nft("42"), lending], &[]).await.unwrap();
+        let ids = tracked_position_ids(&storage, &["42".to_string(), "9".to_string()]).await.unwrap();
+        assert_eq!(ids, vec!["42", "9", "7"]);
+    }
+}
@@ -0,0 +1,130 @@
+use anyhow::{anyhow, bail, Result};
+use ethereum_types::U256;
+use serde_json::Value;
+
+use crate::config::GasSettings;
+
+const GWEI: u64 = 1_000_000_000;
+/// Blocks of fee history sampled for the priority fee
+pub const FEE_HISTORY_BLOCKS: u64 = 10;
+/// maxFeePerGas allows the base fee to double before the transaction is priced out
+const BASE_FEE_HEADROOM: u64 = 2;
+
+/// EIP-1559 fee parameters for the next block
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FeeQuote {
+    pub max_fee_per_gas: U256,
+    pub max_priority_fee_per_gas: U256,
+    /// Base fee of the next block
+    pub base_fee: U256,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum GasDecision {
+    Send(FeeQuote),
+    /// Inclusion needs `needed` wei per gas but the cap is `cap`
+    TooHigh { needed: U256, cap: U256 },
+}
+
+/// Quote from an `eth_feeHistory` result: the next block's base fee and the median of the
+/// sampled priority-fee percentile
+pub fn quote_from_history(history: &Value) -> Result<FeeQuote> {
+    let base_fee = history["baseFeePerGas"]
+        .as_array()
+        .and_then(|fees| fees.last())
+        .and_then(Value::as_str)
+        .ok_or_else(|| anyhow!("fee history has no baseFeePerGas"))?;
+    let base_fee = parse_hex(base_fee)?;
+    let mut rewards: Vec<U256> = history["reward"]
+        .as_array()
+        .map(Vec::as_slice)
+        .unwrap_or_default()
+        .iter()
+        .filter_map(|block| block.get(0).and_then(Value::as_str))
+        .map(parse_hex)
+        .collect::<Result<_>>()?;
+    rewards.sort();
+    let tip = rewards.get(rewards.len() / 2).copied().unwrap_or_default();
+    Ok(FeeQuote {
+        max_fee_per_gas: base_fee * BASE_FEE_HEADROOM + tip,
+        max_priority_fee_per_gas: tip,
+        base_fee,
+    })
+}
+
+/// Clamp a quote to `max_gas_price` (gwei, 0 = uncapped), or report that inclusion costs more
+pub fn apply_cap(quote: FeeQuote, settings: &GasSettings) -> GasDecision {
+    if settings.max_gas_price == 0 {
+        return GasDecision::Send(quote);
+    }
+    let cap = U256::from(settings.max_gas_price) * U256::from(GWEI);
+    let needed = quote.base_fee + quote.max_priority_fee_per_gas;
+    if needed > cap {
+        return GasDecision::TooHigh { needed, cap };
+    }
+    let max_fee_per_gas = quote.max_fee_per_gas.min(cap);
+    GasDecision::Send(FeeQuote {
+        max_fee_per_gas,
+        max_priority_fee_per_gas: quote.max_priority_fee_per_gas.min(max_fee_per_gas),
+        base_fee: quote.base_fee,
+    })
+}
+
+/// Gas limit from an estimate plus `margin_percent`, capped by `gas_limit` (0 = uncapped)
+pub fn gas_limit(estimate: U256, margin_percent: u64, settings: &GasSettings) -> Result<U256> {
+    let padded = estimate + estimate * margin_percent / 100;
+    if settings.gas_limit == 0 {
+        return Ok(padded);
+    }
+    let cap = U256::from(settings.gas_limit);
+    if estimate > cap {
+        bail!("estimated gas {} exceeds gas_settings.gas_limit {}", estimate, settings.gas_limit);
+    }
+    Ok(padded.min(cap))
+}
+
+fn parse_hex(value: &str) -> Result<U256> {
+    Ok(U256::from_str_radix(value.trim_start_matches("0x"), 16)?)
+}
+
+/// Wei per gas as gwei, for logs and errors
+pub fn gwei(wei: U256) -> f64 {
+    wei.to_string().parse::<f64>().unwrap_or(0.0) / GWEI as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::HighGasPolicy;
+    use serde_json::json;
+
+    #[test]
+    fn test_quote_and_caps() {
+        let history = json!({
+            "baseFeePerGas": ["0x3b9aca00", "0x4a817c800"],
+            "reward": [["0x3b9aca00"], ["0x77359400"], ["0x0"]],
+        });
+        let quote = quote_from_history(&history).unwrap();
+        assert_eq!(quote.base_fee, U256::from(20 * GWEI));
+        assert_eq!(quote.max_priority_fee_per_gas, U256::from(GWEI));
+        assert_eq!(quote.max_fee_per_gas, U256::from(41 * GWEI));
+
+        let settings = GasSettings {
+            max_gas_price: 30,
+            gas_limit: 200_000,
+            on_high_gas: HighGasPolicy::Defer,
+            max_defer_secs: 600,
+            priority_fee_percentile: 50.0,
+        };
+        match apply_cap(quote, &settings) {
+            GasDecision::Send(capped) => assert_eq!(capped.max_fee_per_gas, U256::from(30 * GWEI)),
+            other => panic!("expected send, got {:?}", other),
+        }
+        let low = GasSettings { max_gas_price: 20, ..settings.clone() };
+        assert!(matches!(apply_cap(quote, &low), GasDecision::TooHigh { .. }));
+
+        assert_eq!(gas_limit(U256::from(150_000), 20, &settings).unwrap(), U256::from(180_000));
+        assert_eq!(gas_limit(U256::from(190_000), 20, &settings).unwrap(), U256::from(200_000));
+        assert!(gas_limit(U256::from(250_000), 20, &settings).is_err());
+    }
+}
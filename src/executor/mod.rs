@@ -0,0 +1,32 @@
+//! On-chain execution: signs and sends transactions for actions the monitors flag.
+
+mod approvals;
+mod collect;
+mod exit;
+mod gas;
+mod paper;
+mod policy;
+mod rebalance;
+mod relay;
+mod safe;
+mod simulate;
+mod slippage;
+mod tracker;
+mod tx;
+mod workflow;
+
+pub use approvals::{AllowanceManager, Approval};
+pub use collect::FeeCollector;
+pub use exit::{summary as exit_summary, tracked_position_ids, EmergencyExit, ExitPosition};
+pub(crate) use rebalance::centered_range;
+pub use rebalance::{RebalanceHandle, Rebalancer};
+pub use paper::{PaperHolding, PaperTrade, PaperTrader, PaperWallet};
+pub(crate) use paper::Snapshot;
+pub use policy::{ExecutionPolicy, PolicyState};
+pub use relay::PrivateRelay;
+pub use safe::{SafeProposer, SafeTransaction};
+pub use simulate::{decode_revert, Simulator};
+pub use slippage::{Slippage, SlippageCall};
+pub use tracker::{ExecutionQuery, ExecutionStatus, TrackedTransaction, TxTracker};
+pub use tx::{Eip1559Transaction, Log, Receipt, TxRequest, TxSender};
+pub use workflow::{RebalanceQuery, RebalanceStep, RebalanceWorkflow, StepRecord, StepStatus, WorkflowStatus};
@@ -0,0 +1,466 @@
+use anyhow::{bail, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::time::{interval, MissedTickBehavior};
+use tracing::{debug, info, warn};
+
+use crate::alerts::PositionInfo;
+use crate::config::PaperConfig;
+use crate::events::{Event, EventBus};
+use crate::position::{Action, PositionRecommendation};
+use crate::storage::Storage;
+use crate::uniswap::UniswapClient;
+use crate::utils::to_units;
+
+/// Trades kept in the wallet's log
+const TRADE_LOG: usize = 1_000;
+/// Holdings smaller than this fraction of their deposit are closed out
+const DUST: f64 = 1e-9;
+
+/// A virtual stake in a real position, marked against it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "server", derive(utoipa::ToSchema))]
+pub struct PaperHolding {
+    pub position_id: String,
+    /// e.g. `WETH/USDC`
+    pub pair: String,
+    pub token0: String,
+    pub token1: String,
+    /// Virtual liquidity, in the same units as the mirrored position's
+    pub liquidity: f64,
+    /// Tokens the holding would withdraw at the last mark
+    pub amount0: f64,
+    pub amount1: f64,
+    /// Tokens deposited (less withdrawals); what holding them instead is compared against
+    pub hodl0: f64,
+    pub hodl1: f64,
+    pub value_usd: f64,
+    /// LP value minus the value of just holding the deposited tokens; negative is a loss
+    pub impermanent_loss_usd: f64,
+    pub fees_usd: f64,
+    pub gas_usd: f64,
+    /// The mirrored position's uncollected fees per unit of liquidity at the last mark
+    pub fee_mark0: f64,
+    pub fee_mark1: f64,
+    pub opened_at: DateTime<Utc>,
+    pub marked_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "server", derive(utoipa::ToSchema))]
+pub struct PaperTrade {
+    pub timestamp: DateTime<Utc>,
+    pub position_id: String,
+    pub action: Action,
+    /// Deposited (Increase) or withdrawn (Decrease, Exit), before gas
+    pub usd: f64,
+    pub gas_usd: f64,
+}
+
+/// Simulated wallet following the recommendations
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "server", derive(utoipa::ToSchema))]
+pub struct PaperWallet {
+    pub name: String,
+    pub starting_cash_usd: f64,
+    /// Includes accrued fees, which are collected as they are earned
+    pub cash_usd: f64,
+    pub holdings: Vec<PaperHolding>,
+    /// Cash plus holdings at their last mark
+    pub equity_usd: f64,
+    pub pnl_usd: f64,
+    /// Across open holdings
+    pub impermanent_loss_usd: f64,
+    /// Over the wallet's lifetime, including closed holdings
+    pub fees_usd: f64,
+    pub gas_usd: f64,
+    /// Newest last
+    pub trades: Vec<PaperTrade>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// The mirrored position at one point in time, in token units and USD prices
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Snapshot {
+    pub liquidity: f64,
+    /// Withdrawable amounts for the whole position
+    pub amount0: f64,
+    pub amount1: f64,
+    /// Uncollected fees of the whole position
+    pub fees0: f64,
+    pub fees1: f64,
+    pub price0: f64,
+    pub price1: f64,
+}
+
+impl Snapshot {
+    /// Read the position's liquidity, withdrawable amounts and fees on-chain, with current prices
+    pub(crate) async fn fetch(client: &UniswapClient, rpc_url: &str, position_id: &str, info: &PositionInfo) -> Result<Self> {
+        let position = client.get_onchain_position(rpc_url, position_id).await?;
+        let liquidity: u128 = position.liquidity.parse()?;
+        let (amount0, amount1) = if liquidity > 0 {
+            client.decrease_liquidity_amounts(rpc_url, position_id, &info.owner, liquidity).await?
+        } else {
+            Default::default()
+        };
+        let (fees0, fees1) = client.uncollected_fees(rpc_url, position_id, &info.owner).await?;
+        let prices = client.token_prices_usd(&[&info.token0, &info.token1]).await?;
+        Ok(Self {
+            liquidity: liquidity as f64,
+            amount0: to_units(amount0, info.token0_decimals),
+            amount1: to_units(amount1, info.token1_decimals),
+            fees0: to_units(fees0, info.token0_decimals),
+            fees1: to_units(fees1, info.token1_decimals),
+            price0: prices.usd(&info.token0).unwrap_or(0.0),
+            price1: prices.usd(&info.token1).unwrap_or(0.0),
+        })
+    }
+
+    pub(crate) fn usd(&self, amount0: f64, amount1: f64) -> f64 {
+        amount0 * self.price0 + amount1 * self.price1
+    }
+}
+
+impl PaperWallet {
+    pub fn new(name: &str, starting_cash_usd: f64) -> Self {
+        let now = Utc::now();
+        Self {
+            name: name.to_string(),
+            starting_cash_usd,
+            cash_usd: starting_cash_usd,
+            holdings: Vec::new(),
+            equity_usd: starting_cash_usd,
+            pnl_usd: 0.0,
+            impermanent_loss_usd: 0.0,
+            fees_usd: 0.0,
+            gas_usd: 0.0,
+            trades: Vec::new(),
+            created_at: now,
+            updated_at: now,
+        }
+    }
+
+    pub fn holding(&self, position_id: &str) -> Option<&PaperHolding> {
+        self.holdings.iter().find(|h| h.position_id == position_id)
+    }
+
+    /// Accrue fees earned since the last mark and revalue the holding
+    pub(crate) fn mark(&mut self, position_id: &str, snapshot: &Snapshot, now: DateTime<Utc>) {
+        let Some(holding) = self.holdings.iter_mut().find(|h| h.position_id == position_id) else {
+            return;
+        };
+        if snapshot.liquidity > 0.0 {
+            let growth0 = snapshot.fees0 / snapshot.liquidity;
+            let growth1 = snapshot.fees1 / snapshot.liquidity;
+            // Uncollected fees drop when the owner collects; everything after that is new
+            let earned0 = if growth0 >= holding.fee_mark0 { growth0 - holding.fee_mark0 } else { growth0 };
+            let earned1 = if growth1 >= holding.fee_mark1 { growth1 - holding.fee_mark1 } else { growth1 };
+            let fees = holding.liquidity * snapshot.usd(earned0, earned1);
+            holding.fees_usd += fees;
+            holding.fee_mark0 = growth0;
+            holding.fee_mark1 = growth1;
+            holding.amount0 = holding.liquidity * snapshot.amount0 / snapshot.liquidity;
+            holding.amount1 = holding.liquidity * snapshot.amount1 / snapshot.liquidity;
+            self.cash_usd += fees;
+            self.fees_usd += fees;
+        }
+        holding.value_usd = snapshot.usd(holding.amount0, holding.amount1);
+        holding.impermanent_loss_usd = holding.value_usd - snapshot.usd(holding.hodl0, holding.hodl1);
+        holding.marked_at = now;
+        self.refresh(now);
+    }
+
+    /// Put up to `usd` of cash (after gas) into the position; returns the amount deposited
+    pub(crate) fn deposit(
+        &mut self,
+        info: &PositionInfo,
+        position_id: &str,
+        usd: f64,
+        gas_usd: f64,
+        snapshot: &Snapshot,
+        now: DateTime<Utc>,
+    ) -> Result<f64> {
+        let position_value = snapshot.usd(snapshot.amount0, snapshot.amount1);
+        if snapshot.liquidity <= 0.0 || position_value <= 0.0 {
+            bail!("mirrored position has no liquidity");
+        }
+        let usd = usd.min(self.cash_usd - gas_usd);
+        if usd <= 0.0 {
+            bail!("not enough cash: {:.2} USD", self.cash_usd);
+        }
+        self.mark(position_id, snapshot, now);
+        if self.holding(position_id).is_none() {
+            self.holdings.push(PaperHolding {
+                position_id: position_id.to_string(),
+                pair: info.pair(),
+                token0: info.token0.clone(),
+                token1: info.token1.clone(),
+                liquidity: 0.0,
+                amount0: 0.0,
+                amount1: 0.0,
+                hodl0: 0.0,
+                hodl1: 0.0,
+                value_usd: 0.0,
+                impermanent_loss_usd: 0.0,
+                fees_usd: 0.0,
+                gas_usd: 0.0,
+                fee_mark0: snapshot.fees0 / snapshot.liquidity,
+                fee_mark1: snapshot.fees1 / snapshot.liquidity,
+                opened_at: now,
+                marked_at: now,
+            });
+        }
+        let share = usd / position_value;
+        let holding = self.holdings.iter_mut().find(|h| h.position_id == position_id).expect("holding exists");
+        holding.liquidity += share * snapshot.liquidity;
+        holding.amount0 += share * snapshot.amount0;
+        holding.amount1 += share * snapshot.amount1;
+        holding.hodl0 += share * snapshot.amount0;
+        holding.hodl1 += share * snapshot.amount1;
+        holding.value_usd = snapshot.usd(holding.amount0, holding.amount1);
+        holding.impermanent_loss_usd = holding.value_usd - snapshot.usd(holding.hodl0, holding.hodl1);
+        holding.gas_usd += gas_usd;
+        self.cash_usd -= usd + gas_usd;
+        self.gas_usd += gas_usd;
+        self.log(position_id, Action::Increase, usd, gas_usd, now);
+        Ok(usd)
+    }
+
+    /// Withdraw `fraction` of a holding into cash, less gas; returns the amount withdrawn
+    pub(crate) fn withdraw(
+        &mut self,
+        position_id: &str,
+        fraction: f64,
+        gas_usd: f64,
+        snapshot: &Snapshot,
+        now: DateTime<Utc>,
+    ) -> Option<f64> {
+        self.mark(position_id, snapshot, now);
+        let index = self.holdings.iter().position(|h| h.position_id == position_id)?;
+        let fraction = fraction.clamp(0.0, 1.0);
+        let holding = &mut self.holdings[index];
+        let usd = holding.value_usd * fraction;
+        let keep = 1.0 - fraction;
+        holding.liquidity *= keep;
+        holding.amount0 *= keep;
+        holding.amount1 *= keep;
+        holding.hodl0 *= keep;
+        holding.hodl1 *= keep;
+        holding.value_usd *= keep;
+        holding.impermanent_loss_usd *= keep;
+        holding.gas_usd += gas_usd;
+        if keep < DUST {
+            self.holdings.remove(index);
+        }
+        self.cash_usd += usd - gas_usd;
+        self.gas_usd += gas_usd;
+        let action = if keep < DUST { Action::Exit } else { Action::Decrease };
+        self.log(position_id, action, usd, gas_usd, now);
+        Some(usd)
+    }
+
+    fn log(&mut self, position_id: &str, action: Action, usd: f64, gas_usd: f64, now: DateTime<Utc>) {
+        if self.trades.len() == TRADE_LOG {
+            self.trades.remove(0);
+        }
+        self.trades.push(PaperTrade {
+            timestamp: now,
+            position_id: position_id.to_string(),
+            action,
+            usd,
+            gas_usd,
+        });
+        self.refresh(now);
+    }
+
+    fn refresh(&mut self, now: DateTime<Utc>) {
+        self.equity_usd = self.cash_usd + self.holdings.iter().map(|h| h.value_usd).sum::<f64>();
+        self.pnl_usd = self.equity_usd - self.starting_cash_usd;
+        self.impermanent_loss_usd = self.holdings.iter().map(|h| h.impermanent_loss_usd).sum();
+        self.updated_at = now;
+    }
+}
+
+/// Follows recommendation changes with a simulated wallet, marked against the real positions
+pub struct PaperTrader {
+    client: UniswapClient,
+    rpc_url: String,
+    storage: Arc<dyn Storage>,
+    config: PaperConfig,
+    bus: EventBus,
+    positions: HashMap<String, PositionInfo>,
+}
+
+impl PaperTrader {
+    pub fn new(client: UniswapClient, rpc_url: &str, storage: Arc<dyn Storage>, config: &PaperConfig, bus: EventBus) -> Self {
+        Self {
+            client,
+            rpc_url: rpc_url.to_string(),
+            storage,
+            config: config.clone(),
+            bus,
+            positions: HashMap::new(),
+        }
+    }
+
+    /// Trade on recommendation changes and mark holdings until the bus closes
+    pub async fn run(mut self) {
+        let mut wallet = match self.storage.paper_wallet(&self.config.wallet).await {
+            Ok(Some(wallet)) => wallet,
+            Ok(None) => PaperWallet::new(&self.config.wallet, self.config.starting_cash_usd),
+            Err(e) => {
+                warn!(target: "executor", wallet = %self.config.wallet, "paper wallet not loaded, trading disabled: {:#}", e);
+                return;
+            }
+        };
+        info!(target: "executor", wallet = %wallet.name, equity_usd = wallet.equity_usd, holdings = wallet.holdings.len(), "paper trading enabled");
+        let mut rx = self.bus.subscribe();
+        let mut ticker = interval(Duration::from_secs(self.config.mark_interval_secs.max(1)));
+        ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        loop {
+            tokio::select! {
+                _ = ticker.tick() => self.mark_all(&mut wallet).await,
+                event = rx.recv() => match event {
+                    Ok(Event::RecommendationChanged { recommendation, .. }) => {
+                        if let Err(e) = self.trade(&mut wallet, &recommendation).await {
+                            warn!(target: "executor", position = %recommendation.position.id, "paper trade skipped: {:#}", e);
+                        }
+                    }
+                    Ok(_) => continue,
+                    Err(RecvError::Lagged(skipped)) => {
+                        warn!(target: "executor", skipped, "paper trader lagged, events dropped");
+                        continue;
+                    }
+                    Err(RecvError::Closed) => break,
+                },
+            }
+            if let Err(e) = self.storage.save_paper_wallet(&wallet).await {
+                warn!(target: "executor", wallet = %wallet.name, "paper wallet not saved: {:#}", e);
+            }
+        }
+    }
+
+    async fn trade(&mut self, wallet: &mut PaperWallet, recommendation: &PositionRecommendation) -> Result<()> {
+        let position_id = &recommendation.position.id;
+        let (fraction, gas) = match recommendation.suggested_action {
+            // Holdings mirror the live position, whose range only changes when it's rebalanced
+            Action::Hold | Action::Rebalance { .. } => return Ok(()),
+            Action::Increase => (0.0, self.config.enter_gas),
+            Action::Decrease => (self.config.decrease_fraction, self.config.exit_gas),
+            Action::Exit => (1.0, self.config.exit_gas),
+        };
+        if fraction > 0.0 && wallet.holding(position_id).is_none() {
+            return Ok(());
+        }
+        let snapshot = self.snapshot(position_id).await?;
+        let gas_usd = self.gas_usd(gas).await?;
+        let now = Utc::now();
+        if fraction > 0.0 {
+            let usd = wallet.withdraw(position_id, fraction, gas_usd, &snapshot, now).unwrap_or_default();
+            info!(target: "executor", position = %position_id, usd, gas_usd, cash_usd = wallet.cash_usd, "paper withdrawal");
+        } else {
+            let info = &self.positions[position_id];
+            let usd = wallet.deposit(info, position_id, self.config.trade_usd, gas_usd, &snapshot, now)?;
+            info!(target: "executor", position = %position_id, usd, gas_usd, cash_usd = wallet.cash_usd, "paper deposit");
+        }
+        Ok(())
+    }
+
+    async fn mark_all(&mut self, wallet: &mut PaperWallet) {
+        let ids: Vec<String> = wallet.holdings.iter().map(|h| h.position_id.clone()).collect();
+        for id in ids {
+            match self.snapshot(&id).await {
+                Ok(snapshot) => wallet.mark(&id, &snapshot, Utc::now()),
+                Err(e) => warn!(target: "executor", position = %id, "paper holding not marked: {:#}", e),
+            }
+        }
+        debug!(target: "executor", equity_usd = wallet.equity_usd, pnl_usd = wallet.pnl_usd, "paper wallet marked");
+    }
+
+    async fn snapshot(&mut self, position_id: &str) -> Result<Snapshot> {
+        if !self.positions.contains_key(position_id) {
+            let info = PositionInfo::resolve(&self.client, &self.rpc_url, position_id).await?;
+            self.positions.insert(position_id.to_string(), info);
+        }
+        Snapshot::fetch(&self.client, &self.rpc_url, position_id, &self.positions[position_id]).await
+    }
+
+    async fn gas_usd(&self, gas: u64) -> Result<f64> {
+        let gas_price = self.client.gas_price_wei(&self.rpc_url).await?;
+        let eth_usd = self.client.token_prices_usd(&[]).await?.eth_usd;
+        Ok(to_units(gas_price.saturating_mul(gas.into()), 18) * eth_usd)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn info() -> PositionInfo {
+        PositionInfo {
+            pool: "0xpool".to_string(),
+            owner: "0xowner".to_string(),
+            token0: "0xweth".to_string(),
+            token1: "0xusdc".to_string(),
+            token0_symbol: "WETH".to_string(),
+            token1_symbol: "USDC".to_string(),
+            token0_decimals: 18,
+            token1_decimals: 6,
+            tick_lower: -100,
+            tick_upper: 100,
+        }
+    }
+
+    fn snapshot(price0: f64, amount0: f64, amount1: f64, fees1: f64) -> Snapshot {
+        Snapshot { liquidity: 1e12, amount0, amount1, fees0: 0.0, fees1, price0, price1: 1.0 }
+    }
+
+    #[test]
+    fn test_paper_accounting() {
+        let now = Utc::now();
+        let mut wallet = PaperWallet::new("paper", 10_000.0);
+
+        // A 20k position (5 WETH at 2000 + 10k USDC); 1000 buys 5% of it
+        let entry = snapshot(2000.0, 5.0, 10_000.0, 100.0);
+        assert_eq!(wallet.deposit(&info(), "1", 1_000.0, 10.0, &entry, now).unwrap(), 1_000.0);
+        assert!((wallet.cash_usd - 8_990.0).abs() < 1e-9);
+        let holding = wallet.holding("1").unwrap();
+        assert!((holding.amount0 - 0.25).abs() < 1e-9);
+        assert!((holding.value_usd - 1_000.0).abs() < 1e-9);
+        assert_eq!(holding.fees_usd, 0.0);
+
+        // ETH falls to 1600 and the position buys ETH on the way down; 200 USDC of new fees
+        let later = snapshot(1600.0, 5.5, 9_000.0, 300.0);
+        wallet.mark("1", &later, now);
+        let holding = wallet.holding("1").unwrap();
+        assert!((holding.value_usd - (0.275 * 1600.0 + 450.0)).abs() < 1e-9);
+        assert!((holding.impermanent_loss_usd - -10.0).abs() < 1e-9);
+        assert!((holding.fees_usd - 10.0).abs() < 1e-9);
+        assert!((wallet.equity_usd - (8_990.0 + 10.0 + 890.0)).abs() < 1e-9);
+
+        // Fees were collected on-chain: the new uncollected amount is all new
+        let collected = snapshot(1600.0, 5.5, 9_000.0, 40.0);
+        wallet.mark("1", &collected, now);
+        assert!((wallet.fees_usd - 12.0).abs() < 1e-9);
+
+        // Half then the rest
+        let half = wallet.withdraw("1", 0.5, 5.0, &collected, now).unwrap();
+        assert!((half - 445.0).abs() < 1e-9);
+        assert!((wallet.holding("1").unwrap().hodl0 - 0.125).abs() < 1e-9);
+        wallet.withdraw("1", 1.0, 5.0, &collected, now).unwrap();
+        assert!(wallet.holding("1").is_none());
+        assert_eq!(wallet.trades.iter().map(|t| t.action.clone()).collect::<Vec<_>>(), vec![Action::Increase, Action::Decrease, Action::Exit]);
+        assert!((wallet.cash_usd - (8_990.0 + 12.0 + 890.0 - 10.0)).abs() < 1e-9);
+        assert!((wallet.gas_usd - 20.0).abs() < 1e-9);
+        assert!((wallet.pnl_usd - (wallet.cash_usd - 10_000.0)).abs() < 1e-9);
+
+        // Can't spend more than the cash left after gas
+        let mut poor = PaperWallet::new("poor", 5.0);
+        assert!(poor.deposit(&info(), "1", 1_000.0, 10.0, &entry, now).is_err());
+    }
+}
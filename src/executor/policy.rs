@@ -0,0 +1,428 @@
+use anyhow::{anyhow, bail, Context, Result};
+use chrono::{DateTime, Duration as ChronoDuration, Utc};
+use ethereum_types::{Address, U256};
+use sha3::{Digest, Keccak256};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use tracing::{info, warn};
+
+use super::tx::TxRequest;
+use crate::config::PolicyConfig;
+use crate::storage::Storage;
+use crate::uniswap::UniswapClient;
+use crate::utils::to_units;
+
+const MINT: &str = "mint((address,address,uint24,int24,int24,uint256,uint256,uint256,uint256,address,uint256))";
+const DECREASE_LIQUIDITY: &str = "decreaseLiquidity((uint256,uint128,uint256,uint256,uint256))";
+const COLLECT: &str = "collect((uint256,address,uint128,uint128))";
+const EXACT_INPUT_SINGLE: &str = "exactInputSingle((address,address,uint24,address,uint256,uint256,uint160))";
+const APPROVE: &str = "approve(address,uint256)";
+
+/// Functions the policy knows how to inspect; allowed selectors must be among these
+const KNOWN: [&str; 5] = [MINT, DECREASE_LIQUIDITY, COLLECT, EXACT_INPUT_SINGLE, APPROVE];
+
+/// What the policy must remember across restarts
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PolicyState {
+    /// When the executor first started; the dry-run window runs from here
+    pub started_at: DateTime<Utc>,
+    /// USD spent by sent transactions in the last 24h, oldest first
+    pub spent: VecDeque<(DateTime<Utc>, f64)>,
+}
+
+/// Guards every executor transaction: only allowed contracts and functions, proceeds paid to
+/// the executing account, a rolling 24h USD cap on tokens spent, and a dry-run window after
+/// the first start during which transactions are simulated but never sent. With storage
+/// attached (`restore`), the spend log and first-start time survive restarts
+pub struct ExecutionPolicy {
+    config: PolicyConfig,
+    contracts: Vec<Address>,
+    selectors: Vec<[u8; 4]>,
+    client: UniswapClient,
+    rpc_url: String,
+    state: Mutex<PolicyState>,
+    storage: Option<Arc<dyn Storage>>,
+}
+
+impl ExecutionPolicy {
+    pub fn new(config: &PolicyConfig, client: UniswapClient, rpc_url: &str) -> Result<Self> {
+        let contracts = if config.allowed_contracts.is_empty() {
+            let contracts = client.contracts();
+            vec![contracts.position_manager, contracts.swap_router]
+        } else {
+            config
+                .allowed_contracts
+                .iter()
+                .map(|c| c.parse().with_context(|| format!("invalid allowed contract {}", c)))
+                .collect::<Result<_>>()?
+        };
+        let selectors = if config.allowed_selectors.is_empty() {
+            [MINT, DECREASE_LIQUIDITY, COLLECT, EXACT_INPUT_SINGLE, APPROVE].map(selector).to_vec()
+        } else {
+            config.allowed_selectors.iter().map(|s| parse_selector(s)).collect::<Result<_>>()?
+        };
+        // Recipients and spends are only checked for known functions, so nothing else may pass
+        if let Some(unknown) = selectors.iter().find(|s| !KNOWN.map(selector).contains(s)) {
+            bail!(
+                "execution policy: allowed selector 0x{} is not one the policy can inspect (mint, decreaseLiquidity, collect, exactInputSingle, approve)",
+                hex::encode(unknown)
+            );
+        }
+        Ok(Self {
+            config: config.clone(),
+            contracts,
+            selectors,
+            client,
+            rpc_url: rpc_url.to_string(),
+            state: Mutex::new(PolicyState { started_at: Utc::now(), spent: VecDeque::new() }),
+            storage: None,
+        })
+    }
+
+    /// Persist the policy state in `storage`, restoring the spend log and first-start time a
+    /// previous run saved there
+    pub async fn restore(&mut self, storage: Arc<dyn Storage>) -> Result<()> {
+        let state = self.state.get_mut();
+        if let Some(saved) = storage.policy_state().await.context("loading execution policy state")? {
+            state.started_at = saved.started_at.min(state.started_at);
+            state.spent = saved.spent;
+        }
+        // Runs without a dry-run window (e.g. exit-all) don't count as the first start
+        if self.config.dry_run_secs > 0 {
+            storage.save_policy_state(state).await.context("saving execution policy state")?;
+        }
+        self.storage = Some(storage);
+        Ok(())
+    }
+
+    /// Refuse `request` unless it passes the policy; returns the USD it spends
+    pub async fn check(&self, account: Address, request: &TxRequest) -> Result<f64> {
+        let spends = self.inspect(account, request)?;
+        let Some(limit) = self.config.daily_usd_limit else {
+            return Ok(0.0);
+        };
+        let usd = self.value_usd(&spends).await?;
+        let spent = self.spent_today().await;
+        if spent + usd > limit {
+            bail!(
+                "execution policy: spending ${:.2} would exceed the daily limit of ${:.2} (${:.2} spent in the last 24h)",
+                usd,
+                limit,
+                spent
+            );
+        }
+        Ok(usd)
+    }
+
+    /// Refuse exit `request` unless it passes the contract, function and recipient checks and
+    /// only withdraws; exits aren't held to the daily limit, so de-risking works on a day
+    /// the limit is used up
+    pub fn check_exit(&self, account: Address, request: &TxRequest) -> Result<()> {
+        if !self.inspect(account, request)?.is_empty() {
+            bail!("execution policy: exits may only withdraw liquidity and collect fees, not spend tokens");
+        }
+        Ok(())
+    }
+
+    /// Error while the dry-run window is open
+    pub async fn dry_run_gate(&self, request: &TxRequest) -> Result<()> {
+        let window = ChronoDuration::seconds(i64::try_from(self.config.dry_run_secs).unwrap_or(i64::MAX));
+        let elapsed = Utc::now() - self.state.lock().await.started_at;
+        if elapsed < window {
+            let remaining = (window - elapsed).num_seconds();
+            info!(target: "executor", to = ?request.to, selector = %hex::encode(request.data.get(..4).unwrap_or_default()), remaining, "dry run: transaction simulated, not sent");
+            bail!("execution policy: dry-run window open for another {}s; transaction simulated but not sent", remaining);
+        }
+        Ok(())
+    }
+
+    /// Count a sent transaction's spend against the daily limit
+    pub async fn record(&self, usd: f64) {
+        if usd <= 0.0 {
+            return;
+        }
+        let mut state = self.state.lock().await;
+        state.spent.push_back((Utc::now(), usd));
+        if let Some(storage) = &self.storage {
+            // The transaction is already sent; a failed save must not fail it
+            if let Err(e) = storage.save_policy_state(&state).await {
+                warn!(target: "executor", "saving execution policy state failed: {:#}", e);
+            }
+        }
+    }
+
+    async fn spent_today(&self) -> f64 {
+        let mut state = self.state.lock().await;
+        let cutoff = Utc::now() - ChronoDuration::hours(24);
+        while state.spent.front().is_some_and(|(at, _)| *at < cutoff) {
+            state.spent.pop_front();
+        }
+        state.spent.iter().map(|(_, usd)| usd).sum()
+    }
+
+    /// Check target, function and recipient; returns the tokens the call spends
+    fn inspect(&self, account: Address, request: &TxRequest) -> Result<Vec<(Address, U256)>> {
+        let data = &request.data;
+        let call: [u8; 4] = data
+            .get(..4)
+            .and_then(|s| s.try_into().ok())
+            .ok_or_else(|| anyhow!("execution policy: call data has no function selector"))?;
+        if !self.selectors.contains(&call) {
+            bail!("execution policy: function 0x{} is not allowed", hex::encode(call));
+        }
+        if !request.value.is_zero() {
+            bail!("execution policy: transactions may not send ETH");
+        }
+        let args = &data[4..];
+        // approve() goes to a token; the spender must be an allowed contract instead
+        if call == selector(APPROVE) {
+            let spender = address_arg(args, 0)?;
+            if !self.contracts.contains(&spender) {
+                bail!("execution policy: approval for {:?}, which is not an allowed contract", spender);
+            }
+            return Ok(Vec::new());
+        }
+        if !self.contracts.contains(&request.to) {
+            bail!("execution policy: contract {:?} is not allowed", request.to);
+        }
+        let (recipient, spends) = if call == selector(MINT) {
+            (
+                Some(address_arg(args, 9)?),
+                vec![(address_arg(args, 0)?, word(args, 5)?), (address_arg(args, 1)?, word(args, 6)?)],
+            )
+        } else if call == selector(EXACT_INPUT_SINGLE) {
+            (Some(address_arg(args, 3)?), vec![(address_arg(args, 0)?, word(args, 4)?)])
+        } else if call == selector(COLLECT) {
+            (Some(address_arg(args, 1)?), Vec::new())
+        } else if call == selector(DECREASE_LIQUIDITY) {
+            // Credits the position's owed tokens; nothing leaves until collect()
+            (None, Vec::new())
+        } else {
+            bail!("execution policy: function 0x{} cannot be inspected", hex::encode(call));
+        };
+        if let Some(recipient) = recipient.filter(|r| *r != account) {
+            bail!("execution policy: proceeds would go to {:?}, not the executing account", recipient);
+        }
+        Ok(spends)
+    }
+
+    /// USD value of `spends`; any token whose decimals or price can't be read is an error,
+    /// so the policy refuses a spend it can't value rather than undercount it
+    async fn value_usd(&self, spends: &[(Address, U256)]) -> Result<f64> {
+        let spends: Vec<(String, U256)> = spends
+            .iter()
+            .filter(|(_, amount)| !amount.is_zero())
+            .map(|(token, amount)| (format!("{:?}", token), *amount))
+            .collect();
+        if spends.is_empty() {
+            return Ok(0.0);
+        }
+        let tokens: Vec<&str> = spends.iter().map(|(token, _)| token.as_str()).collect();
+        let decimals = self
+            .client
+            .tokens_decimals(&self.rpc_url, &tokens)
+            .await
+            .into_iter()
+            .collect::<Result<Vec<u8>>>()
+            .context("execution policy: cannot read token decimals to enforce the daily limit")?;
+        let prices = self.client.token_prices_usd(&tokens).await.context("pricing spend for the daily limit")?;
+        let mut usd = 0.0;
+        for ((token, amount), decimals) in spends.iter().zip(decimals) {
+            let price = prices
+                .usd(token)
+                .ok_or_else(|| anyhow!("execution policy: no USD price for {}, cannot enforce the daily limit", token))?;
+            usd += to_units(*amount, decimals) * price;
+        }
+        Ok(usd)
+    }
+}
+
+fn selector(signature: &str) -> [u8; 4] {
+    let hash = Keccak256::digest(signature.as_bytes());
+    [hash[0], hash[1], hash[2], hash[3]]
+}
+
+/// A `0x`-prefixed 4-byte selector or a function signature
+fn parse_selector(value: &str) -> Result<[u8; 4]> {
+    if value.starts_with("0x") && value.len() == 10 {
+        let bytes = hex::decode(&value[2..]).with_context(|| format!("invalid selector {}", value))?;
+        return Ok([bytes[0], bytes[1], bytes[2], bytes[3]]);
+    }
+    if !value.contains('(') {
+        bail!("allowed selector {} is neither 0x-hex nor a function signature", value);
+    }
+    Ok(selector(value))
+}
+
+fn word(args: &[u8], index: usize) -> Result<U256> {
+    args.get(index * 32..(index + 1) * 32)
+        .map(U256::from_big_endian)
+        .ok_or_else(|| anyhow!("execution policy: call data too short"))
+}
+
+fn address_arg(args: &[u8], index: usize) -> Result<Address> {
+    let word = args
+        .get(index * 32..(index + 1) * 32)
+        .ok_or_else(|| anyhow!("execution policy: call data too short"))?;
+    Ok(Address::from_slice(&word[12..]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::uniswap::{self, MintParams, POSITION_MANAGER, SWAP_ROUTER};
+
+    #[test]
+    fn test_policy_inspection() {
+        let policy = ExecutionPolicy::new(
+            &PolicyConfig::default(),
+            UniswapClient::from_config(&Config::default()),
+            "http://localhost:8545",
+        )
+        .unwrap();
+        let account: Address = "0x008aeeda4d805471df9b2a5b0f38a0c3bcba786b".parse().unwrap();
+        let router: Address = SWAP_ROUTER.parse().unwrap();
+        let weth: Address = "0x82aF49447D8a07e3bd95BD0d56f35241523fBab1".parse().unwrap();
+        let usdc: Address = "0xaf88d065e77c8cC2239327C5EDb3A432268e5831".parse().unwrap();
+        let swap = |recipient| TxRequest {
+            to: router,
+            data: uniswap::exact_input_single_call(weth, usdc, 500, recipient, U256::from(10u64.pow(18)), U256::one()),
+            value: U256::zero(),
+            label: "swap",
+            position_id: None,
+        };
+        assert_eq!(policy.inspect(account, &swap(account)).unwrap(), vec![(weth, U256::from(10u64.pow(18)))]);
+        assert!(policy.inspect(account, &swap(Address::repeat_byte(0x66))).is_err());
+
+        let mint = TxRequest {
+            to: POSITION_MANAGER.parse().unwrap(),
+            data: uniswap::mint_call(&MintParams {
+                token0: weth,
+                token1: usdc,
+                fee: 500,
+                tick_lower: -200_000,
+                tick_upper: -190_000,
+                amount0_desired: U256::from(5),
+                amount1_desired: U256::from(7),
+                amount0_min: U256::zero(),
+                amount1_min: U256::zero(),
+                recipient: account,
+                deadline: 1,
+            }),
+            value: U256::zero(),
+            label: "mint",
+            position_id: None,
+        };
+        assert_eq!(policy.inspect(account, &mint).unwrap(), vec![(weth, U256::from(5)), (usdc, U256::from(7))]);
+
+        // Approvals must name an allowed spender; other contracts and functions are refused
+        let approve = |spender| TxRequest {
+            to: weth,
+            data: uniswap::approve_call(spender, U256::MAX),
+            value: U256::zero(),
+            label: "approve",
+            position_id: None,
+        };
+        assert!(policy.inspect(account, &approve(router)).is_ok());
+        assert!(policy.inspect(account, &approve(Address::repeat_byte(0x66))).is_err());
+        assert!(policy.inspect(account, &TxRequest { to: weth, ..swap(account) }).is_err());
+        let transfer = [&selector("transfer(address,uint256)")[..], &[0u8; 64]].concat();
+        assert!(policy.inspect(account, &TxRequest { to: weth, data: transfer, ..approve(router) }).is_err());
+
+        assert_eq!(parse_selector("0xfc6f7865").unwrap(), selector(COLLECT));
+    }
+
+    #[test]
+    fn test_allowed_selectors_must_be_inspectable() {
+        let client = UniswapClient::from_config(&Config::default());
+        let allow = |selectors: &[&str]| PolicyConfig {
+            allowed_selectors: selectors.iter().map(|s| s.to_string()).collect(),
+            ..Default::default()
+        };
+        assert!(ExecutionPolicy::new(&allow(&["0x0c49ccbe", COLLECT]), client.clone(), "http://localhost:8545").is_ok());
+        let err = ExecutionPolicy::new(&allow(&["multicall(bytes[])"]), client, "http://localhost:8545").err().unwrap();
+        assert!(err.to_string().contains("not one the policy can inspect"));
+    }
+
+    #[tokio::test]
+    async fn test_state_survives_restart() {
+        let storage: Arc<dyn Storage> = Arc::new(crate::storage::MemoryStorage::default());
+        let new_policy = || {
+            let config = PolicyConfig { daily_usd_limit: Some(100.0), ..Default::default() };
+            ExecutionPolicy::new(&config, UniswapClient::from_config(&Config::default()), "http://localhost:8545").unwrap()
+        };
+        let mut first = new_policy();
+        first.restore(storage.clone()).await.unwrap();
+        first.record(40.0).await;
+        let started_at = first.state.lock().await.started_at;
+
+        let mut second = new_policy();
+        second.restore(storage.clone()).await.unwrap();
+        assert_eq!(second.state.lock().await.started_at, started_at);
+        assert_eq!(second.spent_today().await, 40.0);
+        let request = TxRequest {
+            to: Address::zero(),
+            data: Vec::new(),
+            value: U256::zero(),
+            label: "collect",
+            position_id: None,
+        };
+        assert!(second.dry_run_gate(&request).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_unreadable_decimals_refuse_the_spend() {
+        let config = PolicyConfig { daily_usd_limit: Some(100.0), ..Default::default() };
+        // Nothing answers at the RPC endpoint
+        let policy = ExecutionPolicy::new(&config, UniswapClient::from_config(&Config::default()), "http://127.0.0.1:9").unwrap();
+        let account: Address = "0x008aeeda4d805471df9b2a5b0f38a0c3bcba786b".parse().unwrap();
+        let usdc: Address = "0xaf88d065e77c8cC2239327C5EDb3A432268e5831".parse().unwrap();
+        let weth: Address = "0x82aF49447D8a07e3bd95BD0d56f35241523fBab1".parse().unwrap();
+        let swap = TxRequest {
+            to: SWAP_ROUTER.parse().unwrap(),
+            data: uniswap::exact_input_single_call(usdc, weth, 500, account, U256::from(5_000_000_000u64), U256::one()),
+            value: U256::zero(),
+            label: "swap",
+            position_id: None,
+        };
+        let err = policy.check(account, &swap).await.unwrap_err();
+        assert!(format!("{:#}", err).contains("cannot read token decimals"), "{:#}", err);
+    }
+
+    #[tokio::test]
+    async fn test_exits_bypass_the_daily_limit() {
+        let config = PolicyConfig { daily_usd_limit: Some(100.0), ..Default::default() };
+        let policy = ExecutionPolicy::new(&config, UniswapClient::from_config(&Config::default()), "http://127.0.0.1:9").unwrap();
+        // Over the limit, e.g. after it was lowered
+        policy.record(150.0).await;
+        let account: Address = "0x008aeeda4d805471df9b2a5b0f38a0c3bcba786b".parse().unwrap();
+        let collect = TxRequest {
+            to: POSITION_MANAGER.parse().unwrap(),
+            data: UniswapClient::collect_call("42", &format!("{:?}", account)).unwrap(),
+            value: U256::zero(),
+            label: "collect",
+            position_id: Some("42".to_string()),
+        };
+        assert!(policy.check(account, &collect).await.is_err());
+        policy.check_exit(account, &collect).unwrap();
+
+        // Recipient checks still apply, and exits may not spend
+        let stranger = format!("{:?}", Address::repeat_byte(0x66));
+        let elsewhere = TxRequest { data: UniswapClient::collect_call("42", &stranger).unwrap(), ..collect.clone() };
+        assert!(policy.check_exit(account, &elsewhere).is_err());
+        let usdc: Address = "0xaf88d065e77c8cC2239327C5EDb3A432268e5831".parse().unwrap();
+        let weth: Address = "0x82aF49447D8a07e3bd95BD0d56f35241523fBab1".parse().unwrap();
+        let swap = TxRequest {
+            to: SWAP_ROUTER.parse().unwrap(),
+            data: uniswap::exact_input_single_call(usdc, weth, 500, account, U256::from(5_000_000u64), U256::one()),
+            value: U256::zero(),
+            label: "swap",
+            position_id: None,
+        };
+        let err = policy.check_exit(account, &swap).unwrap_err();
+        assert!(err.to_string().contains("exits may only withdraw"), "{}", err);
+    }
+}
@@ -0,0 +1,600 @@
+use anyhow::{anyhow, bail, Context, Result};
+use chrono::Utc;
+use ethereum_types::{Address, H256, U256};
+use sha3::{Digest, Keccak256};
+use std::sync::Arc;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::mpsc;
+use tracing::{info, warn};
+
+use super::approvals::{AllowanceManager, Approval};
+use super::collect::collected_amounts;
+use super::slippage::{Slippage, SlippageCall};
+use super::tx::{Receipt, TxRequest, TxSender};
+use super::workflow::{RebalanceQuery, RebalanceStep, RebalanceWorkflow, StepRecord, StepStatus, WorkflowStatus};
+use crate::config::{ExecutionConfig, RebalanceConfig};
+use crate::events::{Event, EventBus, RebalanceFailure, RebalanceSummary};
+use crate::storage::Storage;
+use crate::uniswap::{self, MintParams, UniswapClient};
+use crate::utils::to_units;
+use crate::utils::uniswap_v3::{
+    max_usable_tick, min_usable_tick, sqrt_ratio_at_tick, sqrt_ratio_to_f64, tick_spacing, MAX_TICK, MIN_TICK,
+};
+
+const REQUEST_QUEUE: usize = 32;
+/// Swaps moving less than this share of the position's value are skipped
+const MIN_SWAP_SHARE: f64 = 0.005;
+
+/// Queues rebalances for the executor; held by the admin API
+#[derive(Clone)]
+pub struct RebalanceHandle {
+    sender: mpsc::Sender<String>,
+}
+
+impl RebalanceHandle {
+    /// Returns false if the queue is full
+    pub fn request(&self, position_id: &str) -> bool {
+        self.sender.try_send(position_id.to_string()).is_ok()
+    }
+}
+
+enum StepAction {
+    /// Send `request` once the approvals are in place
+    Send { request: TxRequest, approvals: Vec<Approval> },
+    Skip(&'static str),
+}
+
+impl StepAction {
+    fn send(request: TxRequest) -> Self {
+        StepAction::Send { request, approvals: Vec::new() }
+    }
+}
+
+/// Moves a position's liquidity into a new range centered on the current price:
+/// `decreaseLiquidity`, `collect`, an optional swap to the new range's token ratio, then
+/// `mint`. Each step waits for its receipt and the workflow is persisted after every step, so
+/// an interrupted rebalance resumes where it stopped.
+pub struct Rebalancer {
+    sender: Arc<TxSender>,
+    client: UniswapClient,
+    rpc_url: String,
+    storage: Arc<dyn Storage>,
+    config: RebalanceConfig,
+    slippage: Slippage,
+    bus: EventBus,
+    allowances: AllowanceManager,
+    requests: mpsc::Receiver<String>,
+}
+
+impl Rebalancer {
+    pub fn new(
+        sender: Arc<TxSender>,
+        client: UniswapClient,
+        rpc_url: &str,
+        storage: Arc<dyn Storage>,
+        config: &ExecutionConfig,
+        bus: EventBus,
+        allowances: AllowanceManager,
+    ) -> Result<(Self, RebalanceHandle)> {
+        let rebalance = config.rebalance.clone().ok_or_else(|| anyhow!("[execution.rebalance] is not configured"))?;
+        let slippage = Slippage::new(&config.slippage)?;
+        let (tx, requests) = mpsc::channel(REQUEST_QUEUE);
+        let rebalancer = Self {
+            sender,
+            client,
+            rpc_url: rpc_url.to_string(),
+            storage,
+            config: rebalance,
+            slippage,
+            bus,
+            allowances,
+            requests,
+        };
+        Ok((rebalancer, RebalanceHandle { sender: tx }))
+    }
+
+    /// Resume interrupted workflows, then handle requests (and out-of-range events when
+    /// enabled) one at a time
+    pub async fn run(mut self) {
+        info!(target: "executor", account = ?self.sender.address(), auto = self.config.auto_on_out_of_range, "rebalance executor enabled");
+        let running = RebalanceQuery {
+            status: Some(WorkflowStatus::Running),
+            limit: 100,
+            ..Default::default()
+        };
+        match self.storage.rebalances(&running).await {
+            Ok(workflows) => {
+                for workflow in workflows {
+                    info!(target: "executor", workflow = %workflow.id, "resuming interrupted rebalance");
+                    self.execute(workflow).await;
+                }
+            }
+            Err(e) => warn!(target: "executor", "loading interrupted rebalances failed: {:#}", e),
+        }
+
+        let mut events = self.bus.subscribe();
+        loop {
+            let position_id = tokio::select! {
+                request = self.requests.recv() => match request {
+                    Some(position_id) => position_id,
+                    None => break,
+                },
+                event = events.recv() => match event {
+                    Ok(Event::PositionOutOfRange { range, .. }) if self.config.auto_on_out_of_range => range.position_id,
+                    Ok(_) => continue,
+                    Err(RecvError::Lagged(skipped)) => {
+                        warn!(target: "executor", skipped, "rebalancer lagged, events dropped");
+                        continue;
+                    }
+                    Err(RecvError::Closed) => break,
+                },
+            };
+            if let Err(e) = self.start(&position_id).await {
+                warn!(target: "executor", position = %position_id, "rebalance not started: {:#}", e);
+            }
+        }
+    }
+
+    /// Resume the position's failed workflow if it got partway, else plan a new one
+    async fn start(&self, position_id: &str) -> Result<()> {
+        let latest = RebalanceQuery {
+            position_id: Some(position_id.to_string()),
+            limit: 1,
+            ..Default::default()
+        };
+        let workflow = match self.storage.rebalances(&latest).await?.into_iter().next() {
+            Some(workflow) if workflow.status == WorkflowStatus::Running => {
+                info!(target: "executor", workflow = %workflow.id, "rebalance already in progress");
+                return Ok(());
+            }
+            Some(mut workflow) if workflow.status == WorkflowStatus::Failed && workflow.started() => {
+                info!(target: "executor", workflow = %workflow.id, "retrying failed rebalance");
+                workflow.status = WorkflowStatus::Running;
+                workflow.error = None;
+                // Steps re-read on-chain state, so a transaction that landed after all is not repeated
+                for step in workflow.steps.iter_mut().filter(|s| s.status == StepStatus::Failed) {
+                    step.status = StepStatus::Pending;
+                    step.tx_hash = None;
+                }
+                workflow
+            }
+            _ => self.plan(position_id).await?,
+        };
+        self.storage.save_rebalance(&workflow).await?;
+        self.execute(workflow).await;
+        Ok(())
+    }
+
+    async fn plan(&self, position_id: &str) -> Result<RebalanceWorkflow> {
+        let rpc = &self.rpc_url;
+        let position = self.client.get_onchain_position(rpc, position_id).await?;
+        let owner = self.client.position_owner(rpc, position_id).await?;
+        if owner.parse::<Address>()? != self.sender.address() {
+            bail!("position is owned by {}, not the signer", owner);
+        }
+        let pool = self.client.pool_address(rpc, &position.token0, &position.token1, position.fee).await?;
+        let current_tick = self.client.pool_tick(rpc, &pool).await?;
+        let width = self.config.width_ticks.unwrap_or(position.tick_upper - position.tick_lower);
+        let (tick_lower, tick_upper) = centered_range(current_tick, width, tick_spacing(position.fee).unwrap_or(60));
+        let balance0 = self.client.token_balance(rpc, &position.token0, &owner).await?;
+        let balance1 = self.client.token_balance(rpc, &position.token1, &owner).await?;
+        let now = Utc::now();
+        let workflow = RebalanceWorkflow {
+            id: format!("rb-{}-{}", position_id, now.timestamp_millis()),
+            position_id: position_id.to_string(),
+            owner,
+            token0: position.token0,
+            token1: position.token1,
+            pair: format!("{}/{}", position.token0_symbol, position.token1_symbol),
+            pool,
+            fee: position.fee,
+            old_tick_lower: position.tick_lower,
+            old_tick_upper: position.tick_upper,
+            tick_lower,
+            tick_upper,
+            balance0_before: balance0.to_string(),
+            balance1_before: balance1.to_string(),
+            new_position_id: None,
+            status: WorkflowStatus::Running,
+            error: None,
+            steps: RebalanceStep::ALL
+                .iter()
+                .map(|&step| StepRecord {
+                    step,
+                    status: StepStatus::Pending,
+                    tx_hash: None,
+                    approval_tx_hashes: Vec::new(),
+                    block_number: None,
+                    gas_cost_eth: None,
+                    detail: None,
+                    updated_at: now,
+                })
+                .collect(),
+            created_at: now,
+            updated_at: now,
+        };
+        info!(
+            target: "executor",
+            workflow = %workflow.id,
+            current_tick,
+            old_range = ?(workflow.old_tick_lower, workflow.old_tick_upper),
+            new_range = ?(tick_lower, tick_upper),
+            "rebalance planned"
+        );
+        Ok(workflow)
+    }
+
+    async fn execute(&self, mut workflow: RebalanceWorkflow) {
+        while let Some(index) = workflow.next_step() {
+            if let Err(e) = self.run_step(&mut workflow, index).await {
+                let step = workflow.steps[index].step;
+                let error = format!("{:#}", e);
+                warn!(target: "executor", workflow = %workflow.id, ?step, "rebalance step failed: {}", error);
+                let now = Utc::now();
+                let record = &mut workflow.steps[index];
+                record.status = StepStatus::Failed;
+                record.detail = Some(error.clone());
+                record.updated_at = now;
+                workflow.status = WorkflowStatus::Failed;
+                workflow.error = Some(error.clone());
+                workflow.updated_at = now;
+                self.persist(&workflow).await;
+                self.bus.publish(Event::RebalanceFailed {
+                    timestamp: now,
+                    failure: RebalanceFailure {
+                        workflow_id: workflow.id.clone(),
+                        position_id: workflow.position_id.clone(),
+                        owner: workflow.owner.clone(),
+                        pair: workflow.pair.clone(),
+                        step: serde_json::to_value(step).ok().and_then(|v| v.as_str().map(str::to_string)).unwrap_or_default(),
+                        error,
+                    },
+                });
+                return;
+            }
+        }
+        workflow.status = WorkflowStatus::Completed;
+        workflow.updated_at = Utc::now();
+        self.persist(&workflow).await;
+        info!(target: "executor", workflow = %workflow.id, new_position = ?workflow.new_position_id, "rebalance completed");
+        self.bus.publish(Event::PositionRebalanced {
+            timestamp: workflow.updated_at,
+            rebalance: RebalanceSummary {
+                workflow_id: workflow.id.clone(),
+                position_id: workflow.position_id.clone(),
+                new_position_id: workflow.new_position_id.clone(),
+                owner: workflow.owner.clone(),
+                pair: workflow.pair.clone(),
+                old_tick_lower: workflow.old_tick_lower,
+                old_tick_upper: workflow.old_tick_upper,
+                tick_lower: workflow.tick_lower,
+                tick_upper: workflow.tick_upper,
+                tx_hashes: workflow.tx_hashes(),
+                gas_cost_eth: workflow.gas_cost_eth(),
+            },
+        });
+    }
+
+    async fn persist(&self, workflow: &RebalanceWorkflow) {
+        if let Err(e) = self.storage.save_rebalance(workflow).await {
+            warn!(target: "executor", workflow = %workflow.id, "saving rebalance failed: {:#}", e);
+        }
+    }
+
+    /// Send (or, after a restart, keep waiting for) one step's transaction and record the result
+    async fn run_step(&self, workflow: &mut RebalanceWorkflow, index: usize) -> Result<()> {
+        let step = workflow.steps[index].step;
+        let pending = match (workflow.steps[index].status, workflow.steps[index].tx_hash.as_deref()) {
+            (StepStatus::Sent, Some(hash)) => hash.trim_start_matches("0x").parse::<H256>().ok(),
+            _ => None,
+        };
+        let tx_hash = match pending {
+            Some(hash) => hash,
+            None => {
+                let request = match self.build_step(workflow, step).await? {
+                    StepAction::Send { request, approvals } => {
+                        for approval in &approvals {
+                            if let Some(receipt) = self.allowances.ensure(approval).await? {
+                                let record = &mut workflow.steps[index];
+                                record.approval_tx_hashes.push(format!("{:?}", receipt.tx_hash));
+                                *record.gas_cost_eth.get_or_insert(0.0) += to_units(receipt.gas_cost_wei(), 18);
+                                self.storage.save_rebalance(workflow).await?;
+                            }
+                        }
+                        request
+                    }
+                    StepAction::Skip(reason) => {
+                        info!(target: "executor", workflow = %workflow.id, ?step, reason, "rebalance step skipped");
+                        update_step(workflow, index, StepStatus::Skipped, Some(reason.to_string()));
+                        self.storage.save_rebalance(workflow).await?;
+                        return Ok(());
+                    }
+                };
+                let hash = self.sender.submit(&request).await?;
+                update_step(workflow, index, StepStatus::Sent, None);
+                workflow.steps[index].tx_hash = Some(format!("{:?}", hash));
+                self.storage.save_rebalance(workflow).await?;
+                hash
+            }
+        };
+        let receipt = self.sender.confirm(tx_hash).await?;
+        let detail = self.after_step(workflow, step, &receipt)?;
+        update_step(workflow, index, StepStatus::Confirmed, detail);
+        let record = &mut workflow.steps[index];
+        record.block_number = Some(receipt.block_number);
+        *record.gas_cost_eth.get_or_insert(0.0) += to_units(receipt.gas_cost_wei(), 18);
+        info!(target: "executor", workflow = %workflow.id, ?step, tx = ?tx_hash, "rebalance step confirmed");
+        self.storage.save_rebalance(workflow).await
+    }
+
+    async fn build_step(&self, workflow: &RebalanceWorkflow, step: RebalanceStep) -> Result<StepAction> {
+        let rpc = &self.rpc_url;
+        let owner: Address = workflow.owner.parse()?;
+        let deadline = Utc::now().timestamp() as u64 + self.config.deadline_secs;
+        match step {
+            RebalanceStep::DecreaseLiquidity => {
+                let position = self.client.get_onchain_position(rpc, &workflow.position_id).await?;
+                let liquidity: u128 = position.liquidity.parse().context("invalid position liquidity")?;
+                if liquidity == 0 {
+                    return Ok(StepAction::Skip("position has no liquidity"));
+                }
+                let (amount0, amount1) = self
+                    .client
+                    .decrease_liquidity_amounts(rpc, &workflow.position_id, &workflow.owner, liquidity)
+                    .await?;
+                let data = uniswap::decrease_liquidity_call(
+                    &workflow.position_id,
+                    liquidity,
+                    self.slippage.min_amount(SlippageCall::DecreaseLiquidity, amount0),
+                    self.slippage.min_amount(SlippageCall::DecreaseLiquidity, amount1),
+                    deadline,
+                )?;
+                Ok(StepAction::send(self.manager_call(workflow, step, data)))
+            }
+            RebalanceStep::Collect => Ok(StepAction::send(self.manager_call(
+                workflow,
+                step,
+                UniswapClient::collect_call(&workflow.position_id, &workflow.owner)?,
+            ))),
+            RebalanceStep::Swap => {
+                if !self.config.swap {
+                    return Ok(StepAction::Skip("swapping disabled"));
+                }
+                let (amount0, amount1) = self.freed_balances(workflow).await?;
+                let tick = self.client.pool_tick(rpc, &workflow.pool).await?;
+                let Some((zero_for_one, amount_in)) =
+                    swap_plan(to_f64(amount0), to_f64(amount1), tick, workflow.tick_lower, workflow.tick_upper)
+                else {
+                    return Ok(StepAction::Skip("already at the target ratio"));
+                };
+                let price = sqrt_price(tick).powi(2);
+                let after_fee = 1.0 - workflow.fee as f64 / 1_000_000.0;
+                let (token_in, token_out, available, expected_out) = if zero_for_one {
+                    (&workflow.token0, &workflow.token1, amount0, amount_in * price * after_fee)
+                } else {
+                    (&workflow.token1, &workflow.token0, amount1, amount_in / price * after_fee)
+                };
+                let amount_in = from_f64(amount_in).min(available);
+                let router = self.client.contracts().swap_router;
+                let data = uniswap::exact_input_single_call(
+                    token_in.parse()?,
+                    token_out.parse()?,
+                    workflow.fee,
+                    owner,
+                    amount_in,
+                    self.slippage.min_amount(SlippageCall::Swap, from_f64(expected_out)),
+                );
+                Ok(StepAction::Send {
+                    request: TxRequest {
+                        to: router,
+                        data,
+                        value: U256::zero(),
+                        label: step.as_str(),
+                        position_id: Some(workflow.position_id.clone()),
+                    },
+                    approvals: vec![Approval {
+                        token: token_in.clone(),
+                        spender: router,
+                        amount: amount_in,
+                    }],
+                })
+            }
+            RebalanceStep::Mint => {
+                let (amount0, amount1) = self.freed_balances(workflow).await?;
+                if amount0.is_zero() && amount1.is_zero() {
+                    bail!("no freed tokens to mint with");
+                }
+                let tick = self.client.pool_tick(rpc, &workflow.pool).await?;
+                let (min0, min1) = mint_minimums(
+                    to_f64(amount0),
+                    to_f64(amount1),
+                    tick,
+                    workflow.tick_lower,
+                    workflow.tick_upper,
+                    self.slippage.bps(SlippageCall::Mint),
+                );
+                let params = MintParams {
+                    token0: workflow.token0.parse()?,
+                    token1: workflow.token1.parse()?,
+                    fee: workflow.fee,
+                    tick_lower: workflow.tick_lower,
+                    tick_upper: workflow.tick_upper,
+                    amount0_desired: amount0,
+                    amount1_desired: amount1,
+                    amount0_min: from_f64(min0).min(amount0),
+                    amount1_min: from_f64(min1).min(amount1),
+                    recipient: owner,
+                    deadline,
+                };
+                let manager = self.client.contracts().position_manager;
+                let approvals = vec![
+                    Approval {
+                        token: workflow.token0.clone(),
+                        spender: manager,
+                        amount: amount0,
+                    },
+                    Approval {
+                        token: workflow.token1.clone(),
+                        spender: manager,
+                        amount: amount1,
+                    },
+                ];
+                Ok(StepAction::Send {
+                    request: self.manager_call(workflow, step, uniswap::mint_call(&params)),
+                    approvals,
+                })
+            }
+        }
+    }
+
+    /// A call to the position manager for this workflow's step
+    fn manager_call(&self, workflow: &RebalanceWorkflow, step: RebalanceStep, data: Vec<u8>) -> TxRequest {
+        TxRequest {
+            to: self.client.contracts().position_manager,
+            data,
+            value: U256::zero(),
+            label: step.as_str(),
+            position_id: Some(workflow.position_id.clone()),
+        }
+    }
+
+    /// Record what a confirmed step produced
+    fn after_step(&self, workflow: &mut RebalanceWorkflow, step: RebalanceStep, receipt: &Receipt) -> Result<Option<String>> {
+        match step {
+            RebalanceStep::Collect => {
+                let token_id = U256::from_dec_str(&workflow.position_id)?;
+                let manager = self.client.contracts().position_manager;
+                Ok(collected_amounts(receipt, manager, token_id).map(|(a0, a1)| format!("collected amount0={} amount1={}", a0, a1)))
+            }
+            RebalanceStep::Mint => {
+                let token_id = minted_position(receipt, self.client.contracts().position_manager)
+                    .ok_or_else(|| anyhow!("no IncreaseLiquidity event in mint receipt"))?;
+                workflow.new_position_id = Some(token_id.to_string());
+                Ok(Some(format!("minted position {}", token_id)))
+            }
+            RebalanceStep::DecreaseLiquidity | RebalanceStep::Swap => Ok(None),
+        }
+    }
+
+    /// Owner balances above the pre-rebalance snapshot: what the rebalance freed
+    async fn freed_balances(&self, workflow: &RebalanceWorkflow) -> Result<(U256, U256)> {
+        let balance0 = self.client.token_balance(&self.rpc_url, &workflow.token0, &workflow.owner).await?;
+        let balance1 = self.client.token_balance(&self.rpc_url, &workflow.token1, &workflow.owner).await?;
+        let before0 = U256::from_dec_str(&workflow.balance0_before)?;
+        let before1 = U256::from_dec_str(&workflow.balance1_before)?;
+        Ok((balance0.saturating_sub(before0), balance1.saturating_sub(before1)))
+    }
+}
+
+fn update_step(workflow: &mut RebalanceWorkflow, index: usize, status: StepStatus, detail: Option<String>) {
+    let now = Utc::now();
+    let record = &mut workflow.steps[index];
+    record.status = status;
+    if detail.is_some() {
+        record.detail = detail;
+    }
+    record.updated_at = now;
+    workflow.updated_at = now;
+}
+
+
+/// Token id from the position manager's `IncreaseLiquidity` log
+fn minted_position(receipt: &Receipt, manager: Address) -> Option<U256> {
+    let topic = H256::from_slice(&Keccak256::digest(b"IncreaseLiquidity(uint256,uint128,uint256,uint256)"));
+    receipt
+        .logs
+        .iter()
+        .find(|log| log.address == manager && log.topics.first() == Some(&topic))
+        .and_then(|log| log.topics.get(1))
+        .map(|id| U256::from_big_endian(id.as_bytes()))
+}
+
+/// Range of about `width` ticks around `current_tick`, aligned to the pool's tick spacing
+pub(crate) fn centered_range(current_tick: i32, width: i32, spacing: i32) -> (i32, i32) {
+    let half_steps = ((width.max(spacing) / spacing + 1) / 2).max(1);
+    let center = current_tick.div_euclid(spacing) * spacing;
+    let lower = (center - half_steps * spacing).max(min_usable_tick(spacing));
+    let upper = (center + half_steps * spacing).min(max_usable_tick(spacing));
+    (lower, upper)
+}
+
+fn sqrt_price(tick: i32) -> f64 {
+    sqrt_ratio_at_tick(tick.clamp(MIN_TICK, MAX_TICK)).map(sqrt_ratio_to_f64).unwrap_or(1.0)
+}
+
+/// Token amounts per unit of liquidity for a range at `tick`
+fn unit_amounts(tick: i32, tick_lower: i32, tick_upper: i32) -> (f64, f64) {
+    let (lower, upper) = (sqrt_price(tick_lower), sqrt_price(tick_upper));
+    let current = sqrt_price(tick).clamp(lower, upper);
+    (1.0 / current - 1.0 / upper, current - lower)
+}
+
+/// Swap bringing raw balances to the range's token ratio at `tick`: `(zero_for_one, amount_in)`,
+/// or `None` when the imbalance is negligible
+fn swap_plan(amount0: f64, amount1: f64, tick: i32, tick_lower: i32, tick_upper: i32) -> Option<(bool, f64)> {
+    let price = sqrt_price(tick).powi(2);
+    let (unit0, unit1) = unit_amounts(tick, tick_lower, tick_upper);
+    let total = amount0 * price + amount1;
+    let denominator = unit0 * price + unit1;
+    if total <= 0.0 || denominator <= 0.0 {
+        return None;
+    }
+    let share0 = unit0 * price / denominator;
+    let target0 = share0 * total / price;
+    let (zero_for_one, amount_in, value_in) = if amount0 > target0 {
+        (true, amount0 - target0, (amount0 - target0) * price)
+    } else {
+        let excess1 = amount1 - (1.0 - share0) * total;
+        (false, excess1, excess1)
+    };
+    (value_in >= total * MIN_SWAP_SHARE).then_some((zero_for_one, amount_in))
+}
+
+/// Minimum amounts for `mint`: what the liquidity fitting the balances uses, less slippage
+fn mint_minimums(amount0: f64, amount1: f64, tick: i32, tick_lower: i32, tick_upper: i32, slippage_bps: u32) -> (f64, f64) {
+    let (unit0, unit1) = unit_amounts(tick, tick_lower, tick_upper);
+    let liquidity = [(amount0, unit0), (amount1, unit1)]
+        .iter()
+        .filter(|(_, unit)| *unit > 0.0)
+        .map(|(amount, unit)| amount / unit)
+        .fold(f64::INFINITY, f64::min);
+    if !liquidity.is_finite() {
+        return (0.0, 0.0);
+    }
+    let factor = 1.0 - slippage_bps.min(10_000) as f64 / 10_000.0;
+    (liquidity * unit0 * factor, liquidity * unit1 * factor)
+}
+
+fn to_f64(amount: U256) -> f64 {
+    amount.to_string().parse().unwrap_or(0.0)
+}
+
+fn from_f64(amount: f64) -> U256 {
+    U256::from_dec_str(&format!("{:.0}", amount.max(0.0).floor())).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_range_and_ratio_math() {
+        let (lower, upper) = centered_range(-201_234, 1_200, 60);
+        assert_eq!((lower, upper), (-201_840, -200_640));
+        assert!(lower <= -201_234 && -201_234 < upper);
+        assert_eq!(centered_range(5, 1, 10), (-10, 10));
+
+        // Symmetric range around the price: the target is an equal split by value
+        let tick = 0;
+        let (lower, upper) = centered_range(tick, 2_000, 10);
+        let (zero_for_one, amount_in) = swap_plan(1_000.0, 0.0, tick, lower, upper).unwrap();
+        assert!(zero_for_one);
+        assert!((amount_in - 500.0).abs() < 5.0);
+        assert!(swap_plan(500.0, 500.0, tick, lower, upper).is_none());
+
+        let (min0, min1) = mint_minimums(500.0, 800.0, tick, lower, upper, 50);
+        assert!((min0 - 497.5).abs() < 5.0);
+        assert!(min1 < 500.0);
+        assert_eq!(from_f64(1234.7), U256::from(1234u64));
+    }
+}
@@ -0,0 +1,119 @@
+use anyhow::{bail, Result};
+use reqwest::Client;
+use serde_json::{json, Value};
+use sha3::{Digest, Keccak256};
+
+use crate::config::{PrivateRelayConfig, RelayKind};
+use crate::signer::Signer;
+
+/// Submits signed transactions privately, keeping them out of the public mempool
+pub struct PrivateRelay {
+    http: Client,
+    config: PrivateRelayConfig,
+    /// Signs Flashbots request bodies; identifies the searcher, holds no funds
+    auth: Option<Box<dyn Signer>>,
+}
+
+impl PrivateRelay {
+    pub fn new(config: &PrivateRelayConfig) -> Result<Self> {
+        let auth = match config.kind {
+            RelayKind::Rpc => None,
+            RelayKind::Flashbots => Some(flashbots_auth(config)?),
+        };
+        Ok(Self {
+            http: crate::http::shared(),
+            config: config.clone(),
+            auth,
+        })
+    }
+
+    pub fn chain_id(&self) -> u64 {
+        self.config.chain_id
+    }
+
+    pub fn url(&self) -> &str {
+        &self.config.url
+    }
+
+    /// Whether a relay failure may fall back to the public RPC
+    pub fn fallback_public(&self) -> bool {
+        self.config.fallback_public
+    }
+
+    /// Submit a raw signed transaction
+    pub async fn send(&self, raw: &[u8]) -> Result<()> {
+        let raw = format!("0x{}", hex::encode(raw));
+        let (method, params) = match self.config.kind {
+            RelayKind::Rpc => ("eth_sendRawTransaction", json!([raw])),
+            RelayKind::Flashbots => ("eth_sendPrivateTransaction", json!([{ "tx": raw }])),
+        };
+        let body = json!({ "jsonrpc": "2.0", "id": 1, "method": method, "params": params }).to_string();
+        let mut request = self.http.post(&self.config.url).header("Content-Type", "application/json");
+        if let Some(auth) = &self.auth {
+            request = request.header("X-Flashbots-Signature", flashbots_signature(auth.as_ref(), &body).await?);
+        }
+        let resp = request.body(body).send().await?.error_for_status()?;
+        let json: Value = resp.json().await?;
+        if let Some(error) = json.get("error") {
+            bail!("{} to {} failed: {}", method, self.config.url, error);
+        }
+        Ok(())
+    }
+}
+
+/// Flashbots searcher key: from `auth_key_env`, else a fresh random one
+#[cfg(feature = "execution")]
+fn flashbots_auth(config: &PrivateRelayConfig) -> Result<Box<dyn Signer>> {
+    use crate::signer::LocalSigner;
+    use anyhow::Context;
+
+    let signer = match config.auth_key_env.as_deref() {
+        Some(var) => {
+            let key = std::env::var(var).with_context(|| format!("relay auth key env var {} not set", var))?;
+            LocalSigner::from_hex(&key)?
+        }
+        None => LocalSigner::from_bytes(&rand::random::<[u8; 32]>())?,
+    };
+    Ok(Box::new(signer))
+}
+
+#[cfg(not(feature = "execution"))]
+fn flashbots_auth(config: &PrivateRelayConfig) -> Result<Box<dyn Signer>> {
+    bail!("Flashbots relay {} signs requests with a local key, which needs the `execution` feature", config.url)
+}
+
+/// `X-Flashbots-Signature` value: `address:signature`, an EIP-191 signature of the hex
+/// keccak256 of the request body
+async fn flashbots_signature(auth: &dyn Signer, body: &str) -> Result<String> {
+    let digest = format!("0x{}", hex::encode(Keccak256::digest(body.as_bytes())));
+    let message = format!("\x19Ethereum Signed Message:\n{}{}", digest.len(), digest);
+    let signature = auth.sign_transaction(message.as_bytes()).await?;
+    let bytes = [signature.r.as_bytes(), signature.s.as_bytes(), &[27 + signature.y_parity]].concat();
+    Ok(format!("{:?}:0x{}", auth.address(), hex::encode(bytes)))
+}
+
+#[cfg(all(test, feature = "execution"))]
+mod tests {
+    use super::*;
+    use crate::signer::LocalSigner;
+    use ethereum_types::Address;
+    use k256::ecdsa::{RecoveryId, Signature as EcdsaSignature, VerifyingKey};
+
+    #[tokio::test]
+    async fn test_flashbots_signature_recovers_auth_address() {
+        let auth = LocalSigner::from_hex("0x7a28b5ba57c53603b0b07b56bba752f7784bf506fa95edc395f5cf6c7514fe9d").unwrap();
+        let body = r#"{"jsonrpc":"2.0","id":1,"method":"eth_sendPrivateTransaction","params":[{"tx":"0x02"}]}"#;
+        let header = flashbots_signature(&auth, body).await.unwrap();
+        let (address, signature) = header.split_once(':').unwrap();
+        assert_eq!(address.parse::<Address>().unwrap(), auth.address());
+
+        let bytes = hex::decode(signature.trim_start_matches("0x")).unwrap();
+        assert_eq!(bytes.len(), 65);
+        let digest = format!("0x{}", hex::encode(Keccak256::digest(body.as_bytes())));
+        let hash = Keccak256::digest(format!("\x19Ethereum Signed Message:\n66{}", digest));
+        let ecdsa = EcdsaSignature::from_slice(&bytes[..64]).unwrap();
+        let key = VerifyingKey::recover_from_prehash(&hash, &ecdsa, RecoveryId::from_byte(bytes[64] - 27).unwrap()).unwrap();
+        let point = key.to_encoded_point(false);
+        assert_eq!(Address::from_slice(&Keccak256::digest(&point.as_bytes()[1..])[12..]), auth.address());
+    }
+}
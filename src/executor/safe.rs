@@ -0,0 +1,260 @@
+use anyhow::{anyhow, bail, Context, Result};
+use ethabi::Token;
+use ethereum_types::{Address, H256, U256};
+use reqwest::Client;
+use serde_json::{json, Value};
+use sha3::{Digest, Keccak256};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::time::{sleep, Instant};
+use tracing::{debug, info};
+
+use super::tx::TxRequest;
+use crate::config::SafeConfig;
+use crate::signer::Signer;
+use crate::utils::to_checksum_address;
+
+const DOMAIN_TYPE: &str = "EIP712Domain(uint256 chainId,address verifyingContract)";
+const SAFE_TX_TYPE: &str = "SafeTx(address to,uint256 value,bytes data,uint8 operation,uint256 safeTxGas,uint256 baseGas,uint256 gasPrice,address gasToken,address refundReceiver,uint256 nonce)";
+const EXECUTION_POLL_INTERVAL: Duration = Duration::from_secs(15);
+/// Pending proposals scanned for a duplicate and the next free nonce
+const PENDING_PAGE: usize = 100;
+
+/// A `CALL` from the Safe with no gas refund, as owners sign it
+#[derive(Debug, Clone, PartialEq)]
+pub struct SafeTransaction {
+    pub to: Address,
+    pub value: U256,
+    pub data: Vec<u8>,
+    pub nonce: u64,
+}
+
+impl SafeTransaction {
+    fn struct_hash(&self) -> H256 {
+        keccak(&ethabi::encode(&[
+            Token::FixedBytes(keccak(SAFE_TX_TYPE.as_bytes()).as_bytes().to_vec()),
+            Token::Address(self.to),
+            Token::Uint(self.value),
+            Token::FixedBytes(keccak(&self.data).as_bytes().to_vec()),
+            Token::Uint(U256::zero()),
+            Token::Uint(U256::zero()),
+            Token::Uint(U256::zero()),
+            Token::Uint(U256::zero()),
+            Token::Address(Address::zero()),
+            Token::Address(Address::zero()),
+            Token::Uint(U256::from(self.nonce)),
+        ]))
+    }
+}
+
+/// EIP-712 domain separator of a Safe (v1.3+)
+pub fn domain_separator(chain_id: u64, safe: Address) -> H256 {
+    keccak(&ethabi::encode(&[
+        Token::FixedBytes(keccak(DOMAIN_TYPE.as_bytes()).as_bytes().to_vec()),
+        Token::Uint(U256::from(chain_id)),
+        Token::Address(safe),
+    ]))
+}
+
+/// The `safeTxHash` owners confirm
+pub fn safe_tx_hash(chain_id: u64, safe: Address, tx: &SafeTransaction) -> H256 {
+    keccak(&[&[0x19, 0x01][..], domain_separator(chain_id, safe).as_bytes(), tx.struct_hash().as_bytes()].concat())
+}
+
+/// Proposes transactions to a Safe through the Safe Transaction Service and waits for the
+/// owners to execute them
+pub struct SafeProposer {
+    http: Client,
+    config: SafeConfig,
+    safe: Address,
+    signer: Arc<dyn Signer>,
+}
+
+impl SafeProposer {
+    pub fn new(config: &SafeConfig, signer: Arc<dyn Signer>) -> Result<Self> {
+        info!(target: "executor", safe = %config.address, proposer = ?signer.address(), "Safe mode: transactions are proposed to the Safe");
+        Ok(Self {
+            http: crate::http::shared(),
+            safe: config.address.parse().context("invalid execution.safe.address")?,
+            config: config.clone(),
+            signer,
+        })
+    }
+
+    pub fn address(&self) -> Address {
+        self.safe
+    }
+
+    /// Propose `request` (or find an identical pending proposal) and wait until it is
+    /// executed; returns the execution's transaction hash
+    pub async fn propose_and_wait(&self, chain_id: u64, request: &TxRequest) -> Result<H256> {
+        let safe_nonce = self.safe_nonce().await?;
+        let pending = self.pending(safe_nonce).await?;
+        let existing = pending.iter().find(|p| {
+            p["to"].as_str().is_some_and(|to| to.eq_ignore_ascii_case(&format!("{:?}", request.to)))
+                && p["value"].as_str() == Some(request.value.to_string().as_str())
+                && p["data"].as_str().unwrap_or("0x").eq_ignore_ascii_case(&format!("0x{}", hex::encode(&request.data)))
+        });
+        let (hash, nonce) = match existing {
+            Some(proposal) => {
+                let hash: H256 = proposal["safeTxHash"].as_str().unwrap_or_default().parse().context("invalid safeTxHash")?;
+                let nonce = as_u64(&proposal["nonce"])?;
+                info!(target: "executor", safe_tx = ?hash, nonce, "matching Safe proposal already pending");
+                (hash, nonce)
+            }
+            None => {
+                let nonce = pending
+                    .iter()
+                    .filter_map(|p| as_u64(&p["nonce"]).ok())
+                    .max()
+                    .map_or(safe_nonce, |n| n + 1);
+                let tx = SafeTransaction {
+                    to: request.to,
+                    value: request.value,
+                    data: request.data.clone(),
+                    nonce,
+                };
+                (self.propose(chain_id, &tx).await?, nonce)
+            }
+        };
+        self.wait_executed(hash, nonce).await
+    }
+
+    async fn propose(&self, chain_id: u64, tx: &SafeTransaction) -> Result<H256> {
+        let hash = safe_tx_hash(chain_id, self.safe, tx);
+        let signature = self
+            .signer
+            .sign_typed_data(domain_separator(chain_id, self.safe), tx.struct_hash())
+            .await?;
+        let signature = [signature.r.as_bytes(), signature.s.as_bytes(), &[27 + signature.y_parity]].concat();
+        let zero = to_checksum_address(&Address::zero());
+        let body = json!({
+            "to": to_checksum_address(&tx.to),
+            "value": tx.value.to_string(),
+            "data": format!("0x{}", hex::encode(&tx.data)),
+            "operation": 0,
+            "safeTxGas": "0",
+            "baseGas": "0",
+            "gasPrice": "0",
+            "gasToken": zero,
+            "refundReceiver": zero,
+            "nonce": tx.nonce,
+            "contractTransactionHash": format!("{:?}", hash),
+            "sender": to_checksum_address(&self.signer.address()),
+            "signature": format!("0x{}", hex::encode(signature)),
+            "origin": self.config.origin,
+        });
+        let resp = self
+            .http
+            .post(self.url(&format!("safes/{}/multisig-transactions/", to_checksum_address(&self.safe))))
+            .json(&body)
+            .send()
+            .await?;
+        if !resp.status().is_success() {
+            let status = resp.status();
+            bail!("Safe Transaction Service rejected the proposal ({}): {}", status, resp.text().await.unwrap_or_default());
+        }
+        info!(target: "executor", safe = ?self.safe, safe_tx = ?hash, nonce = tx.nonce, "transaction proposed to Safe");
+        Ok(hash)
+    }
+
+    async fn wait_executed(&self, hash: H256, nonce: u64) -> Result<H256> {
+        let deadline = Instant::now() + Duration::from_secs(self.config.execution_timeout_secs);
+        loop {
+            let proposal = self.get(&format!("multisig-transactions/{:?}/", hash)).await?;
+            if proposal["isExecuted"].as_bool().unwrap_or(false) {
+                if proposal["isSuccessful"].as_bool() == Some(false) {
+                    bail!("Safe transaction {:?} was executed but failed", hash);
+                }
+                let tx_hash = proposal["transactionHash"]
+                    .as_str()
+                    .ok_or_else(|| anyhow!("executed Safe transaction {:?} has no transactionHash", hash))?;
+                return Ok(tx_hash.parse()?);
+            }
+            if self.safe_nonce().await? > nonce {
+                bail!("Safe nonce {} was used by another transaction; proposal {:?} was replaced", nonce, hash);
+            }
+            if Instant::now() >= deadline {
+                bail!("Safe transaction {:?} not executed after {}s", hash, self.config.execution_timeout_secs);
+            }
+            debug!(target: "executor", safe_tx = ?hash, "waiting for Safe owners to execute");
+            sleep(EXECUTION_POLL_INTERVAL).await;
+        }
+    }
+
+    async fn safe_nonce(&self) -> Result<u64> {
+        as_u64(&self.get(&format!("safes/{}/", to_checksum_address(&self.safe))).await?["nonce"])
+    }
+
+    async fn pending(&self, safe_nonce: u64) -> Result<Vec<Value>> {
+        let path = format!(
+            "safes/{}/multisig-transactions/?executed=false&nonce__gte={}&ordering=-nonce&limit={}",
+            to_checksum_address(&self.safe),
+            safe_nonce,
+            PENDING_PAGE
+        );
+        let mut page = self.get(&path).await?;
+        Ok(match page["results"].take() {
+            Value::Array(results) => results,
+            _ => Vec::new(),
+        })
+    }
+
+    async fn get(&self, path: &str) -> Result<Value> {
+        Ok(self.http.get(self.url(path)).send().await?.error_for_status()?.json().await?)
+    }
+
+    fn url(&self, path: &str) -> String {
+        format!("{}/api/v1/{}", self.config.service_url.trim_end_matches('/'), path)
+    }
+}
+
+fn keccak(data: &[u8]) -> H256 {
+    H256::from_slice(&Keccak256::digest(data))
+}
+
+/// The service returns nonces as numbers or decimal strings depending on the version
+fn as_u64(value: &Value) -> Result<u64> {
+    value
+        .as_u64()
+        .or_else(|| value.as_str().and_then(|s| s.parse().ok()))
+        .ok_or_else(|| anyhow!("expected a nonce, got {}", value))
+}
+
+#[cfg(all(test, feature = "execution"))]
+mod tests {
+    use super::*;
+    use crate::signer::LocalSigner;
+    use k256::ecdsa::{RecoveryId, Signature as EcdsaSignature, VerifyingKey};
+
+    #[tokio::test]
+    async fn test_safe_tx_hash_and_signature() {
+        // Type hashes from the Safe contracts
+        assert_eq!(
+            format!("{:?}", keccak(SAFE_TX_TYPE.as_bytes())),
+            "0xbb8310d486368db6bd6f849402fdd73ad53d316b5a4b2644ad6efe0f941286d8"
+        );
+        assert_eq!(
+            format!("{:?}", keccak(DOMAIN_TYPE.as_bytes())),
+            "0x47e79534a245952e8b16893a336b85a3d9ea9fa8c573f3d803afb92a79469218"
+        );
+
+        let safe: Address = "0x1111111111111111111111111111111111111111".parse().unwrap();
+        let tx = SafeTransaction {
+            to: "0xC36442b4a4522E871399CD717aBDD847Ab11FE88".parse().unwrap(),
+            value: U256::zero(),
+            data: vec![0xfc, 0x6f, 0x78, 0x65],
+            nonce: 3,
+        };
+        let hash = safe_tx_hash(1, safe, &tx);
+        assert_ne!(hash, safe_tx_hash(1, safe, &SafeTransaction { nonce: 4, ..tx.clone() }));
+
+        // Owners sign the safeTxHash itself
+        let signer = LocalSigner::from_hex("0x7a28b5ba57c53603b0b07b56bba752f7784bf506fa95edc395f5cf6c7514fe9d").unwrap();
+        let signature = signer.sign_typed_data(domain_separator(1, safe), tx.struct_hash()).await.unwrap();
+        let ecdsa = EcdsaSignature::from_scalars(signature.r.0, signature.s.0).unwrap();
+        let key = VerifyingKey::recover_from_prehash(hash.as_bytes(), &ecdsa, RecoveryId::from_byte(signature.y_parity).unwrap()).unwrap();
+        let point = key.to_encoded_point(false);
+        assert_eq!(Address::from_slice(&Keccak256::digest(&point.as_bytes()[1..])[12..]), signer.address());
+    }
+}
@@ -0,0 +1,194 @@
+use anyhow::{anyhow, bail, Context, Result};
+use ethabi::{ParamType, Token};
+use ethereum_types::{Address, U256};
+use reqwest::Client;
+use serde_json::{json, Value};
+use std::time::Duration;
+use tracing::debug;
+
+use super::tx::TxRequest;
+use crate::config::{SimulationConfig, SimulationMethod, TenderlyConfig};
+
+/// `Error(string)`
+const ERROR_SELECTOR: [u8; 4] = [0x08, 0xc3, 0x79, 0xa0];
+/// `Panic(uint256)`
+const PANIC_SELECTOR: [u8; 4] = [0x4e, 0x48, 0x7b, 0x71];
+const TENDERLY_API: &str = "https://api.tenderly.co/api/v1";
+/// Simulations (tracing calls, Tenderly) run longer than the shared client's default timeout
+const SIMULATION_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Runs a transaction against current state before it is signed, so one that would revert
+/// fails with its reason instead of burning gas
+pub struct Simulator {
+    http: Client,
+    rpc_url: String,
+    config: SimulationConfig,
+}
+
+impl Simulator {
+    pub fn new(rpc_url: &str, config: &SimulationConfig) -> Self {
+        Self {
+            http: crate::http::shared(),
+            rpc_url: rpc_url.to_string(),
+            config: config.clone(),
+        }
+    }
+
+    /// Errors with the decoded revert reason if `request` from `from` would revert
+    pub async fn check(&self, chain_id: u64, from: Address, request: &TxRequest) -> Result<()> {
+        if !self.config.enabled {
+            return Ok(());
+        }
+        let outcome = match &self.config.tenderly {
+            Some(tenderly) => self.tenderly(tenderly, chain_id, from, request).await,
+            None => match self.config.method {
+                SimulationMethod::Call => self.eth_call(from, request).await,
+                SimulationMethod::Trace => self.trace_call(from, request).await,
+            },
+        }
+        .context("simulating transaction")?;
+        if let Some(reason) = outcome {
+            bail!("transaction would revert: {}", reason);
+        }
+        debug!(target: "executor", to = ?request.to, "simulation passed");
+        Ok(())
+    }
+
+    /// `Some(reason)` if the call reverts
+    async fn eth_call(&self, from: Address, request: &TxRequest) -> Result<Option<String>> {
+        let resp = self.rpc("eth_call", json!([call_object(from, request), "pending"])).await?;
+        let Some(error) = resp.get("error") else {
+            return Ok(None);
+        };
+        // Nodes put the revert data in `error.data`; anything else (bad params, rate
+        // limits) is a failed simulation rather than a revert
+        match error.get("data").and_then(Value::as_str) {
+            Some(data) => Ok(Some(decode_revert(&hex::decode(data.trim_start_matches("0x"))?))),
+            None if is_revert_message(error) => Ok(Some(error["message"].as_str().unwrap_or("reverted").to_string())),
+            None => bail!("eth_call failed: {}", error),
+        }
+    }
+
+    async fn trace_call(&self, from: Address, request: &TxRequest) -> Result<Option<String>> {
+        let resp = self
+            .rpc(
+                "debug_traceCall",
+                json!([call_object(from, request), "pending", { "tracer": "callTracer" }]),
+            )
+            .await?;
+        if let Some(error) = resp.get("error") {
+            bail!("debug_traceCall failed: {}", error);
+        }
+        let frame = &resp["result"];
+        let Some(error) = frame["error"].as_str() else {
+            return Ok(None);
+        };
+        let output = frame["output"].as_str().unwrap_or("0x");
+        let data = hex::decode(output.trim_start_matches("0x"))?;
+        Ok(Some(if data.is_empty() { error.to_string() } else { decode_revert(&data) }))
+    }
+
+    async fn tenderly(
+        &self,
+        tenderly: &TenderlyConfig,
+        chain_id: u64,
+        from: Address,
+        request: &TxRequest,
+    ) -> Result<Option<String>> {
+        let access_key = std::env::var(&tenderly.access_key_env)
+            .with_context(|| format!("Tenderly access key env var {} not set", tenderly.access_key_env))?;
+        let url = format!("{}/account/{}/project/{}/simulate", TENDERLY_API, tenderly.account, tenderly.project);
+        let body = json!({
+            "network_id": chain_id.to_string(),
+            "from": from,
+            "to": request.to,
+            "input": format!("0x{}", hex::encode(&request.data)),
+            "value": request.value.to_string(),
+            "save": false,
+            "simulation_type": "quick",
+        });
+        let resp: Value = self
+            .http
+            .post(&url)
+            .timeout(SIMULATION_TIMEOUT)
+            .header("X-Access-Key", access_key)
+            .json(&body)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        let tx = &resp["transaction"];
+        if tx["status"].as_bool().ok_or_else(|| anyhow!("unexpected Tenderly response: {}", resp))? {
+            return Ok(None);
+        }
+        Ok(Some(tx["error_message"].as_str().unwrap_or("reverted").to_string()))
+    }
+
+    async fn rpc(&self, method: &str, params: Value) -> Result<Value> {
+        let body = json!({ "jsonrpc": "2.0", "id": 1, "method": method, "params": params });
+        Ok(self.http.post(&self.rpc_url).timeout(SIMULATION_TIMEOUT).json(&body).send().await?.error_for_status()?.json().await?)
+    }
+}
+
+fn call_object(from: Address, request: &TxRequest) -> Value {
+    json!({
+        "from": from,
+        "to": request.to,
+        "data": format!("0x{}", hex::encode(&request.data)),
+        "value": format!("{:#x}", request.value),
+    })
+}
+
+fn is_revert_message(error: &Value) -> bool {
+    error["message"].as_str().is_some_and(|m| m.contains("revert"))
+}
+
+/// Human-readable reason from revert data: `Error(string)`, `Panic(uint256)` or a custom
+/// error's selector
+pub fn decode_revert(data: &[u8]) -> String {
+    if data.len() < 4 {
+        return "reverted without a reason".to_string();
+    }
+    let (selector, args) = data.split_at(4);
+    if selector == ERROR_SELECTOR {
+        if let Ok(Token::String(reason)) = ethabi::decode(&[ParamType::String], args).map(|mut t| t.remove(0)) {
+            return reason;
+        }
+    }
+    if selector == PANIC_SELECTOR && args.len() >= 32 {
+        return format!("panic 0x{:02x} ({})", U256::from_big_endian(&args[..32]), panic_reason(U256::from_big_endian(&args[..32])));
+    }
+    format!("custom error 0x{}", hex::encode(selector))
+}
+
+fn panic_reason(code: U256) -> &'static str {
+    match code.low_u64() {
+        0x01 => "assertion failed",
+        0x11 => "arithmetic overflow",
+        0x12 => "division by zero",
+        0x21 => "invalid enum value",
+        0x31 => "pop on empty array",
+        0x32 => "array index out of bounds",
+        0x41 => "out of memory",
+        0x51 => "uninitialized function pointer",
+        _ => "unknown panic",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_revert() {
+        let error = [&ERROR_SELECTOR[..], &ethabi::encode(&[Token::String("Too little received".into())])].concat();
+        assert_eq!(decode_revert(&error), "Too little received");
+
+        let panic = [&PANIC_SELECTOR[..], &ethabi::encode(&[Token::Uint(U256::from(0x11))])].concat();
+        assert_eq!(decode_revert(&panic), "panic 0x11 (arithmetic overflow)");
+
+        assert_eq!(decode_revert(&[0xde, 0xad, 0xbe, 0xef]), "custom error 0xdeadbeef");
+        assert_eq!(decode_revert(&[]), "reverted without a reason");
+    }
+}
@@ -0,0 +1,79 @@
+use anyhow::{bail, Result};
+use ethereum_types::U256;
+
+use crate::config::SlippageConfig;
+
+const BPS: u32 = 10_000;
+
+/// Calls whose minimum amounts carry a slippage tolerance
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SlippageCall {
+    DecreaseLiquidity,
+    Swap,
+    Mint,
+}
+
+/// Validated slippage tolerances; construction refuses any above `max_bps`
+#[derive(Debug, Clone)]
+pub struct Slippage {
+    config: SlippageConfig,
+}
+
+impl Slippage {
+    pub fn new(config: &SlippageConfig) -> Result<Self> {
+        if config.max_bps > BPS {
+            bail!("execution.slippage.max_bps {} is above 10000 (100%)", config.max_bps);
+        }
+        let slippage = Self { config: config.clone() };
+        for call in [SlippageCall::DecreaseLiquidity, SlippageCall::Swap, SlippageCall::Mint] {
+            let bps = slippage.bps(call);
+            if bps > config.max_bps {
+                bail!(
+                    "slippage tolerance for {:?} is {} bps, above execution.slippage.max_bps {}",
+                    call,
+                    bps,
+                    config.max_bps
+                );
+            }
+        }
+        Ok(slippage)
+    }
+
+    /// Tolerance for `call`: its override, else `tolerance_bps`
+    pub fn bps(&self, call: SlippageCall) -> u32 {
+        let specific = match call {
+            SlippageCall::DecreaseLiquidity => self.config.decrease_bps,
+            SlippageCall::Swap => self.config.swap_bps,
+            SlippageCall::Mint => self.config.mint_bps,
+        };
+        specific.unwrap_or(self.config.tolerance_bps)
+    }
+
+    /// `amount` less `call`'s tolerance, for an amountMin parameter
+    pub fn min_amount(&self, call: SlippageCall, amount: U256) -> U256 {
+        amount * U256::from(BPS - self.bps(call)) / U256::from(BPS)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tolerances_and_bound() {
+        let config = SlippageConfig {
+            tolerance_bps: 50,
+            decrease_bps: None,
+            swap_bps: Some(100),
+            mint_bps: None,
+            max_bps: 300,
+        };
+        let slippage = Slippage::new(&config).unwrap();
+        assert_eq!(slippage.bps(SlippageCall::Mint), 50);
+        assert_eq!(slippage.min_amount(SlippageCall::DecreaseLiquidity, U256::from(10_000)), U256::from(9_950));
+        assert_eq!(slippage.min_amount(SlippageCall::Swap, U256::from(10_000)), U256::from(9_900));
+
+        assert!(Slippage::new(&SlippageConfig { swap_bps: Some(500), ..config.clone() }).is_err());
+        assert!(Slippage::new(&SlippageConfig { max_bps: 20_000, ..config }).is_err());
+    }
+}
@@ -0,0 +1,317 @@
+use anyhow::{anyhow, bail, Result};
+use chrono::{DateTime, Utc};
+use ethereum_types::{Address, H256};
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::time::interval;
+use tracing::{info, warn};
+
+use super::tx::{parse_quantity, parse_receipt, TxRequest};
+use crate::audit::AuditLog;
+use crate::storage::{HistoryQuery, Storage};
+use crate::utils::to_units;
+
+const POLL_INTERVAL: Duration = Duration::from_secs(15);
+/// Unsettled transactions re-checked per poll
+const BATCH: usize = 200;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "server", derive(utoipa::ToSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum ExecutionStatus {
+    /// Broadcast, not (or no longer) in a block
+    Pending,
+    Executed,
+    /// Included but reverted
+    Failed,
+    /// Its nonce was used by another transaction
+    Replaced,
+}
+
+impl ExecutionStatus {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ExecutionStatus::Pending => "pending",
+            ExecutionStatus::Executed => "executed",
+            ExecutionStatus::Failed => "failed",
+            ExecutionStatus::Replaced => "replaced",
+        }
+    }
+}
+
+/// Lifecycle of a sent transaction, attached to the recommendation it acted on
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "server", derive(utoipa::ToSchema))]
+pub struct TrackedTransaction {
+    pub tx_hash: String,
+    pub account: String,
+    /// Absent for Safe executions, which another account sends
+    pub nonce: Option<u64>,
+    pub label: String,
+    pub position_id: Option<String>,
+    /// Cycle of the position's recommendation when the transaction was sent
+    pub cycle: Option<u64>,
+    pub status: ExecutionStatus,
+    pub block_number: Option<u64>,
+    pub block_hash: Option<String>,
+    pub gas_used: Option<u64>,
+    pub effective_gas_price_gwei: Option<f64>,
+    pub gas_cost_eth: Option<f64>,
+    /// Times the transaction left or changed its block
+    #[serde(default)]
+    pub reorgs: u32,
+    /// Deep enough that it is no longer re-checked
+    #[serde(default)]
+    pub finalized: bool,
+    pub submitted_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Filters for tracked transactions, newest first
+#[derive(Debug, Clone, Default)]
+pub struct ExecutionQuery {
+    pub position_id: Option<String>,
+    /// Only transactions still being watched
+    pub unsettled: bool,
+    pub limit: usize,
+}
+
+impl ExecutionQuery {
+    pub fn matches(&self, tx: &TrackedTransaction) -> bool {
+        self.position_id.as_ref().is_none_or(|id| tx.position_id.as_ref() == Some(id)) && (!self.unsettled || !tx.finalized)
+    }
+}
+
+/// Polls receipts of sent transactions until they are final: records gas used and the
+/// effective price, notices reorgs and replacements, and persists each change so the
+/// recommendation history shows what was executed
+#[derive(Clone)]
+pub struct TxTracker {
+    http: Client,
+    rpc_url: String,
+    storage: Arc<dyn Storage>,
+    finality_blocks: u64,
+    audit: Option<AuditLog>,
+}
+
+impl TxTracker {
+    pub fn new(rpc_url: &str, storage: Arc<dyn Storage>, finality_blocks: u64) -> Self {
+        Self {
+            http: crate::http::shared(),
+            rpc_url: rpc_url.to_string(),
+            storage,
+            finality_blocks,
+            audit: None,
+        }
+    }
+
+    /// Also append sends and settlement changes to the audit log
+    pub fn with_audit(mut self, audit: AuditLog) -> Self {
+        self.audit = Some(audit);
+        self
+    }
+
+    /// Start tracking a broadcast transaction
+    pub async fn track(&self, tx_hash: H256, account: Address, nonce: Option<u64>, request: &TxRequest) -> Result<()> {
+        let cycle = match &request.position_id {
+            Some(position_id) => {
+                let query = HistoryQuery {
+                    position_id: Some(position_id.clone()),
+                    limit: 1,
+                    ..Default::default()
+                };
+                self.storage.history(&query).await?.first().map(|r| r.cycle)
+            }
+            None => None,
+        };
+        let now = Utc::now();
+        let tx = TrackedTransaction {
+            tx_hash: format!("{:?}", tx_hash),
+            account: format!("{:?}", account),
+            nonce,
+            label: request.label.to_string(),
+            position_id: request.position_id.clone(),
+            cycle,
+            status: ExecutionStatus::Pending,
+            block_number: None,
+            block_hash: None,
+            gas_used: None,
+            effective_gas_price_gwei: None,
+            gas_cost_eth: None,
+            reorgs: 0,
+            finalized: false,
+            submitted_at: now,
+            updated_at: now,
+        };
+        self.storage.save_execution(&tx).await?;
+        self.audit("transaction_sent", &tx).await;
+        Ok(())
+    }
+
+    pub async fn run(self) {
+        let mut ticker = interval(POLL_INTERVAL);
+        loop {
+            ticker.tick().await;
+            if let Err(e) = self.poll().await {
+                warn!(target: "executor", "transaction tracking failed: {:#}", e);
+            }
+        }
+    }
+
+    async fn poll(&self) -> Result<()> {
+        let query = ExecutionQuery {
+            unsettled: true,
+            limit: BATCH,
+            ..Default::default()
+        };
+        let unsettled = self.storage.executions(&query).await?;
+        if unsettled.is_empty() {
+            return Ok(());
+        }
+        let head = parse_quantity(&self.rpc("eth_blockNumber", json!([])).await?)?.low_u64();
+        for mut tx in unsettled {
+            let receipt = self.rpc("eth_getTransactionReceipt", json!([tx.tx_hash])).await?;
+            let mined_nonce = match (&tx.nonce, receipt.is_null()) {
+                (Some(_), true) => Some(parse_quantity(&self.rpc("eth_getTransactionCount", json!([tx.account, "latest"])).await?)?.low_u64()),
+                _ => None,
+            };
+            let before = tx.clone();
+            update(&mut tx, (!receipt.is_null()).then_some(&receipt), head, mined_nonce, self.finality_blocks)?;
+            if tx != before {
+                if tx.status != before.status || tx.reorgs != before.reorgs {
+                    info!(target: "executor", tx = %tx.tx_hash, status = tx.status.as_str(), reorgs = tx.reorgs, "transaction status changed");
+                }
+                tx.updated_at = Utc::now();
+                self.storage.save_execution(&tx).await?;
+                if tx.status != before.status || tx.reorgs != before.reorgs || tx.finalized != before.finalized {
+                    self.audit("transaction_status", &tx).await;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Tracking carries on if the audit log can't be written
+    async fn audit(&self, kind: &str, tx: &TrackedTransaction) {
+        if let Some(audit) = &self.audit {
+            if let Err(e) = audit.append(kind, tx.position_id.as_deref(), tx).await {
+                warn!(target: "executor", tx = %tx.tx_hash, "transaction not audited: {:#}", e);
+            }
+        }
+    }
+
+    async fn rpc(&self, method: &str, params: Value) -> Result<Value> {
+        let body = json!({ "jsonrpc": "2.0", "id": 1, "method": method, "params": params });
+        let mut resp: Value = self.http.post(&self.rpc_url).json(&body).send().await?.error_for_status()?.json().await?;
+        if let Some(error) = resp.get("error") {
+            bail!("{} failed: {}", method, error);
+        }
+        Ok(resp["result"].take())
+    }
+}
+
+/// Apply the latest receipt (or its absence) to `tx`. `mined_nonce` is the account's
+/// confirmed transaction count, checked when there is no receipt.
+fn update(tx: &mut TrackedTransaction, receipt: Option<&Value>, head: u64, mined_nonce: Option<u64>, finality_blocks: u64) -> Result<()> {
+    let Some(receipt) = receipt else {
+        if tx.block_number.is_some() {
+            // It was in a block that is no longer canonical
+            warn!(target: "executor", tx = %tx.tx_hash, block = tx.block_number, "transaction reorged out, pending again");
+            tx.status = ExecutionStatus::Pending;
+            tx.block_number = None;
+            tx.block_hash = None;
+            tx.reorgs += 1;
+        } else if let (Some(nonce), Some(mined)) = (tx.nonce, mined_nonce) {
+            if mined > nonce {
+                tx.status = ExecutionStatus::Replaced;
+                tx.finalized = true;
+            }
+        }
+        return Ok(());
+    };
+    let hash: H256 = tx.tx_hash.parse().map_err(|_| anyhow!("invalid tracked hash {}", tx.tx_hash))?;
+    let parsed = parse_receipt(hash, receipt)?;
+    let block_hash = receipt["blockHash"].as_str().map(str::to_lowercase);
+    if tx.block_hash.is_some() && tx.block_hash != block_hash {
+        warn!(target: "executor", tx = %tx.tx_hash, "transaction re-included in a different block");
+        tx.reorgs += 1;
+    }
+    tx.status = if parse_quantity(&receipt["status"])?.is_zero() {
+        ExecutionStatus::Failed
+    } else {
+        ExecutionStatus::Executed
+    };
+    tx.block_number = Some(parsed.block_number);
+    tx.block_hash = block_hash;
+    tx.gas_used = Some(parsed.gas_used.low_u64());
+    tx.effective_gas_price_gwei = Some(to_units(parsed.effective_gas_price, 9));
+    tx.gas_cost_eth = Some(to_units(parsed.gas_cost_wei(), 18));
+    tx.finalized = head >= parsed.block_number + finality_blocks;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pending() -> TrackedTransaction {
+        TrackedTransaction {
+            tx_hash: format!("{:?}", H256::repeat_byte(0xab)),
+            account: format!("{:?}", Address::repeat_byte(0x01)),
+            nonce: Some(7),
+            label: "collect".to_string(),
+            position_id: Some("42".to_string()),
+            cycle: Some(3),
+            status: ExecutionStatus::Pending,
+            block_number: None,
+            block_hash: None,
+            gas_used: None,
+            effective_gas_price_gwei: None,
+            gas_cost_eth: None,
+            reorgs: 0,
+            finalized: false,
+            submitted_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    fn receipt(block: &str, block_hash: &str, status: &str) -> Value {
+        json!({
+            "blockNumber": block,
+            "blockHash": block_hash,
+            "status": status,
+            "gasUsed": "0x249f0",
+            "effectiveGasPrice": "0x3b9aca00",
+            "logs": [],
+        })
+    }
+
+    #[test]
+    fn test_lifecycle() {
+        let mut tx = pending();
+        update(&mut tx, None, 100, Some(7), 12).unwrap();
+        assert_eq!(tx.status, ExecutionStatus::Pending);
+
+        update(&mut tx, Some(&receipt("0x64", "0xaa", "0x1")), 100, None, 12).unwrap();
+        assert_eq!(tx.status, ExecutionStatus::Executed);
+        assert_eq!(tx.gas_used, Some(150_000));
+        assert_eq!(tx.effective_gas_price_gwei, Some(1.0));
+        assert!(!tx.finalized);
+
+        // Dropped from the chain, then re-mined elsewhere and buried
+        update(&mut tx, None, 101, Some(7), 12).unwrap();
+        assert_eq!((tx.status, tx.reorgs, tx.block_number), (ExecutionStatus::Pending, 1, None));
+        update(&mut tx, Some(&receipt("0x65", "0xbb", "0x0")), 113, None, 12).unwrap();
+        assert_eq!(tx.status, ExecutionStatus::Failed);
+        assert!(tx.finalized);
+
+        // Nonce consumed by another transaction
+        let mut replaced = pending();
+        update(&mut replaced, None, 100, Some(8), 12).unwrap();
+        assert_eq!(replaced.status, ExecutionStatus::Replaced);
+        assert!(replaced.finalized);
+    }
+}
@@ -0,0 +1,457 @@
+use anyhow::{anyhow, bail, Context, Result};
+use ethereum_types::{Address, H256, U256};
+use reqwest::Client;
+use rlp::RlpStream;
+use serde_json::{json, Value};
+use sha3::{Digest, Keccak256};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Mutex;
+use tokio::time::{sleep, Instant};
+use tracing::{debug, info, instrument, warn};
+
+use super::gas::{self, GasDecision};
+use super::policy::ExecutionPolicy;
+use super::relay::PrivateRelay;
+use super::safe::SafeProposer;
+use super::simulate::Simulator;
+use super::tracker::TxTracker;
+use crate::config::{ExecutionConfig, GasSettings, HighGasPolicy};
+use crate::signer::{Signature, Signer};
+use crate::storage::Storage;
+use crate::uniswap::UniswapClient;
+
+const EIP1559_TX_TYPE: u8 = 0x02;
+const RECEIPT_POLL_INTERVAL: Duration = Duration::from_secs(2);
+/// How often a deferred transaction re-checks fees
+const GAS_DEFER_POLL_INTERVAL: Duration = Duration::from_secs(30);
+/// Headroom over `eth_estimateGas`, in percent
+const GAS_LIMIT_MARGIN_PERCENT: u64 = 20;
+
+/// A contract call to send
+#[derive(Debug, Clone)]
+pub struct TxRequest {
+    pub to: Address,
+    pub data: Vec<u8>,
+    pub value: U256,
+    /// What the transaction does (`collect`, `mint`, `approve`, ...), for the tracker
+    pub label: &'static str,
+    /// Position acted on; links the transaction to its latest recommendation
+    pub position_id: Option<String>,
+}
+
+/// EIP-1559 (type 2) transaction without an access list
+#[derive(Debug, Clone, PartialEq)]
+pub struct Eip1559Transaction {
+    pub chain_id: u64,
+    pub nonce: U256,
+    pub max_priority_fee_per_gas: U256,
+    pub max_fee_per_gas: U256,
+    pub gas_limit: U256,
+    pub to: Address,
+    pub value: U256,
+    pub data: Vec<u8>,
+}
+
+impl Eip1559Transaction {
+    fn append_fields(&self, stream: &mut RlpStream) {
+        stream.append(&self.chain_id);
+        stream.append(&self.nonce);
+        stream.append(&self.max_priority_fee_per_gas);
+        stream.append(&self.max_fee_per_gas);
+        stream.append(&self.gas_limit);
+        stream.append(&self.to);
+        stream.append(&self.value);
+        stream.append(&self.data.as_slice());
+        stream.begin_list(0);
+    }
+
+    /// `0x02 || rlp(fields)`, the payload the signature covers
+    pub fn signing_payload(&self) -> Vec<u8> {
+        let mut stream = RlpStream::new_list(9);
+        self.append_fields(&mut stream);
+        [&[EIP1559_TX_TYPE][..], &stream.out()].concat()
+    }
+
+    /// Raw transaction for `eth_sendRawTransaction`
+    pub fn encode_signed(&self, signature: &Signature) -> Vec<u8> {
+        let mut stream = RlpStream::new_list(12);
+        self.append_fields(&mut stream);
+        stream.append(&signature.y_parity);
+        stream.append(&U256::from_big_endian(signature.r.as_bytes()));
+        stream.append(&U256::from_big_endian(signature.s.as_bytes()));
+        [&[EIP1559_TX_TYPE][..], &stream.out()].concat()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Log {
+    pub address: Address,
+    pub topics: Vec<H256>,
+    pub data: Vec<u8>,
+}
+
+/// Receipt of a successful transaction
+#[derive(Debug, Clone)]
+pub struct Receipt {
+    pub tx_hash: H256,
+    pub block_number: u64,
+    pub gas_used: U256,
+    pub effective_gas_price: U256,
+    pub logs: Vec<Log>,
+}
+
+impl Receipt {
+    /// Fee paid, in wei
+    pub fn gas_cost_wei(&self) -> U256 {
+        self.gas_used.saturating_mul(self.effective_gas_price)
+    }
+}
+
+/// Builds, signs and broadcasts transactions from the signer's account and waits for them
+/// to confirm
+pub struct TxSender {
+    http: Client,
+    rpc_url: String,
+    signer: Arc<dyn Signer>,
+    config: ExecutionConfig,
+    gas: GasSettings,
+    relays: Vec<PrivateRelay>,
+    simulator: Simulator,
+    policy: ExecutionPolicy,
+    /// Set in Safe mode: transactions are proposed to the Safe instead of sent
+    safe: Option<SafeProposer>,
+    tracker: Option<TxTracker>,
+    /// Held from the policy check to broadcast so concurrent callers get distinct nonces and
+    /// spend limits see each other's sends; never held while waiting for gas
+    submit_lock: Mutex<()>,
+}
+
+impl TxSender {
+    pub fn new(
+        rpc_url: &str,
+        signer: Arc<dyn Signer>,
+        client: UniswapClient,
+        config: &ExecutionConfig,
+        gas: &GasSettings,
+    ) -> Result<Self> {
+        let safe = config.safe.as_ref().map(|safe| SafeProposer::new(safe, signer.clone())).transpose()?;
+        Ok(Self {
+            http: crate::http::shared(),
+            rpc_url: rpc_url.to_string(),
+            signer,
+            config: config.clone(),
+            gas: gas.clone(),
+            relays: config.private_relays.iter().map(PrivateRelay::new).collect::<Result<_>>()?,
+            simulator: Simulator::new(rpc_url, &config.simulation),
+            policy: ExecutionPolicy::new(&config.policy, client, rpc_url)?,
+            safe,
+            tracker: None,
+            submit_lock: Mutex::new(()),
+        })
+    }
+
+    /// Record every sent transaction's lifecycle with `tracker`
+    pub fn with_tracker(mut self, tracker: TxTracker) -> Self {
+        self.tracker = Some(tracker);
+        self
+    }
+
+    /// Keep the execution policy's spend log and first-start time in `storage`, restoring
+    /// what a previous run saved
+    pub async fn with_storage(mut self, storage: Arc<dyn Storage>) -> Result<Self> {
+        self.policy.restore(storage).await?;
+        Ok(self)
+    }
+
+    /// The account transactions act for: the Safe in Safe mode, else the signer
+    pub fn address(&self) -> Address {
+        match &self.safe {
+            Some(safe) => safe.address(),
+            None => self.signer.address(),
+        }
+    }
+
+    /// Send `request` and wait for it to confirm; errors if it reverts
+    pub async fn send(&self, request: &TxRequest) -> Result<Receipt> {
+        let tx_hash = self.submit(request).await?;
+        self.confirm(tx_hash).await
+    }
+
+    /// Send exit `request` (withdrawing liquidity or collecting fees) and wait for it to
+    /// confirm; unlike `send`, it isn't held to the policy's daily USD limit
+    pub async fn send_exit(&self, request: &TxRequest) -> Result<Receipt> {
+        let tx_hash = self.submit_checked(request, true).await?;
+        self.confirm(tx_hash).await
+    }
+
+    /// Sign and broadcast `request`, returning its hash without waiting for inclusion
+    pub async fn submit(&self, request: &TxRequest) -> Result<H256> {
+        self.submit_checked(request, false).await
+    }
+
+    /// `submit`, checking `request` as an exit when `exit` is set
+    #[instrument(name = "send_transaction", skip(self, request), fields(to = ?request.to))]
+    async fn submit_checked(&self, request: &TxRequest, exit: bool) -> Result<H256> {
+        // Resolve fees first: under the defer policy this can wait for gas to drop, and the
+        // nonce must not be taken (nor the lock held) across that wait
+        let fees = match self.safe {
+            Some(_) => None,
+            None => Some(self.fees().await?),
+        };
+        let _guard = self.submit_lock.lock().await;
+        let from = self.address();
+        let chain_id = self.chain_id().await?;
+        let spend_usd = self.policy_check(from, request, exit).await?;
+        self.simulator.check(chain_id, from, request).await?;
+        self.policy.dry_run_gate(request).await?;
+        if let Some(safe) = &self.safe {
+            let tx_hash = safe.propose_and_wait(chain_id, request).await?;
+            self.policy.record(spend_usd).await;
+            self.track(tx_hash, None, request).await;
+            return Ok(tx_hash);
+        }
+        let fees = fees.context("fees are resolved for direct sends")?;
+        let nonce = self.quantity("eth_getTransactionCount", json!([from, "pending"])).await?;
+        let call = json!({
+            "from": from,
+            "to": request.to,
+            "data": format!("0x{}", hex::encode(&request.data)),
+            "value": format!("{:#x}", request.value),
+        });
+        let estimate = self.quantity("eth_estimateGas", json!([call])).await.context("estimating gas (the call would likely revert)")?;
+        let gas_limit = gas::gas_limit(estimate, GAS_LIMIT_MARGIN_PERCENT, &self.gas)?;
+        let (max_fee_per_gas, max_priority_fee_per_gas) = (fees.max_fee_per_gas, fees.max_priority_fee_per_gas);
+
+        let tx = Eip1559Transaction {
+            chain_id,
+            nonce,
+            max_priority_fee_per_gas,
+            max_fee_per_gas,
+            gas_limit,
+            to: request.to,
+            value: request.value,
+            data: request.data.clone(),
+        };
+        let signature = self.signer.sign_transaction(&tx.signing_payload()).await?;
+        let raw = tx.encode_signed(&signature);
+        let tx_hash = H256::from_slice(&Keccak256::digest(&raw));
+        self.broadcast(chain_id, &raw).await?;
+        self.policy.record(spend_usd).await;
+        self.track(tx_hash, Some(nonce.low_u64()), request).await;
+        info!(target: "executor", tx = ?tx_hash, nonce = %nonce, gas_limit = %gas_limit, "transaction sent");
+        Ok(tx_hash)
+    }
+
+    /// Run the policy checks and simulation `submit` would, without sending
+    pub async fn simulate(&self, request: &TxRequest) -> Result<()> {
+        let from = self.address();
+        let chain_id = self.chain_id().await?;
+        self.policy.check(from, request).await?;
+        self.simulator.check(chain_id, from, request).await
+    }
+
+    /// `simulate` for an exit: the policy checks `send_exit` would, then the simulation
+    pub async fn simulate_exit(&self, request: &TxRequest) -> Result<()> {
+        let from = self.address();
+        let chain_id = self.chain_id().await?;
+        self.policy.check_exit(from, request)?;
+        self.simulator.check(chain_id, from, request).await
+    }
+
+    /// USD `request` spends against the daily limit; exits spend none and skip the limit
+    async fn policy_check(&self, from: Address, request: &TxRequest, exit: bool) -> Result<f64> {
+        if exit {
+            self.policy.check_exit(from, request)?;
+            return Ok(0.0);
+        }
+        self.policy.check(from, request).await
+    }
+
+    async fn chain_id(&self) -> Result<u64> {
+        match self.config.chain_id {
+            Some(id) => Ok(id),
+            None => Ok(self.quantity("eth_chainId", json!([])).await?.low_u64()),
+        }
+    }
+
+    /// Hand a sent transaction to the tracker; a failure here must not fail the send
+    async fn track(&self, tx_hash: H256, nonce: Option<u64>, request: &TxRequest) {
+        if let Some(tracker) = &self.tracker {
+            if let Err(e) = tracker.track(tx_hash, self.address(), nonce, request).await {
+                warn!(target: "executor", tx = ?tx_hash, "tracking transaction failed: {:#}", e);
+            }
+        }
+    }
+
+    /// Send through the chain's private relay when one is configured, else the public RPC
+    async fn broadcast(&self, chain_id: u64, raw: &[u8]) -> Result<()> {
+        if let Some(relay) = self.relays.iter().find(|r| r.chain_id() == chain_id) {
+            match relay.send(raw).await {
+                Ok(()) => {
+                    debug!(target: "executor", relay = relay.url(), "transaction sent privately");
+                    return Ok(());
+                }
+                Err(e) if relay.fallback_public() => {
+                    warn!(target: "executor", relay = relay.url(), error = %format!("{:#}", e), "private relay failed, broadcasting publicly");
+                }
+                Err(e) => return Err(e.context("sending transaction to private relay")),
+            }
+        }
+        self.rpc("eth_sendRawTransaction", json!([format!("0x{}", hex::encode(raw))]))
+            .await
+            .context("broadcasting transaction")?;
+        Ok(())
+    }
+
+    /// Fees for the next block from recent fee history, capped by `max_gas_price`. Above the
+    /// cap this aborts or, under the defer policy, waits for fees to drop
+    async fn fees(&self) -> Result<gas::FeeQuote> {
+        let deadline = Instant::now() + Duration::from_secs(self.gas.max_defer_secs);
+        loop {
+            let history = self
+                .rpc(
+                    "eth_feeHistory",
+                    json!([format!("{:#x}", gas::FEE_HISTORY_BLOCKS), "latest", [self.gas.priority_fee_percentile]]),
+                )
+                .await?;
+            let quote = gas::quote_from_history(&history)?;
+            match gas::apply_cap(quote, &self.gas) {
+                GasDecision::Send(quote) => return Ok(quote),
+                GasDecision::TooHigh { needed, cap } => {
+                    if self.gas.on_high_gas == HighGasPolicy::Abort || Instant::now() >= deadline {
+                        bail!(
+                            "gas price {:.2} gwei is above max_gas_price {:.2} gwei",
+                            gas::gwei(needed),
+                            gas::gwei(cap)
+                        );
+                    }
+                    warn!(
+                        target: "executor",
+                        needed_gwei = gas::gwei(needed),
+                        cap_gwei = gas::gwei(cap),
+                        "gas above max_gas_price, deferring transaction"
+                    );
+                    sleep(GAS_DEFER_POLL_INTERVAL).await;
+                }
+            }
+        }
+    }
+
+    /// Wait for a sent transaction to confirm; errors if it reverts or times out
+    pub async fn confirm(&self, tx_hash: H256) -> Result<Receipt> {
+        let deadline = Instant::now() + Duration::from_secs(self.config.receipt_timeout_secs);
+        loop {
+            let value = self.rpc("eth_getTransactionReceipt", json!([tx_hash])).await?;
+            if !value.is_null() {
+                let block_number = parse_quantity(&value["blockNumber"])?.low_u64();
+                let head = self.quantity("eth_blockNumber", json!([])).await?.low_u64();
+                if head + 1 >= block_number + self.config.confirmations.max(1) {
+                    if parse_quantity(&value["status"])?.is_zero() {
+                        bail!("transaction {:?} reverted in block {}", tx_hash, block_number);
+                    }
+                    return parse_receipt(tx_hash, &value);
+                }
+                debug!(target: "executor", tx = ?tx_hash, block_number, head, "waiting for confirmations");
+            }
+            if Instant::now() >= deadline {
+                bail!("transaction {:?} not confirmed after {}s", tx_hash, self.config.receipt_timeout_secs);
+            }
+            sleep(RECEIPT_POLL_INTERVAL).await;
+        }
+    }
+
+    async fn rpc(&self, method: &str, params: Value) -> Result<Value> {
+        let body = json!({ "jsonrpc": "2.0", "id": 1, "method": method, "params": params });
+        let resp = self.http.post(&self.rpc_url).json(&body).send().await?.error_for_status()?;
+        let mut json: Value = resp.json().await?;
+        if let Some(error) = json.get("error") {
+            bail!("{} failed: {}", method, error);
+        }
+        Ok(json["result"].take())
+    }
+
+    async fn quantity(&self, method: &str, params: Value) -> Result<U256> {
+        parse_quantity(&self.rpc(method, params).await?).with_context(|| format!("parsing {} result", method))
+    }
+}
+
+pub(super) fn parse_quantity(value: &Value) -> Result<U256> {
+    let hex = value.as_str().ok_or_else(|| anyhow!("expected a hex quantity, got {}", value))?;
+    Ok(U256::from_str_radix(hex.trim_start_matches("0x"), 16)?)
+}
+
+fn parse_bytes(value: &Value) -> Result<Vec<u8>> {
+    let hex = value.as_str().ok_or_else(|| anyhow!("expected hex data, got {}", value))?;
+    Ok(hex::decode(hex.trim_start_matches("0x"))?)
+}
+
+pub(super) fn parse_receipt(tx_hash: H256, value: &Value) -> Result<Receipt> {
+    let logs = value["logs"]
+        .as_array()
+        .map(Vec::as_slice)
+        .unwrap_or_default()
+        .iter()
+        .map(|log| {
+            Ok(Log {
+                address: Address::from_slice(&parse_bytes(&log["address"])?),
+                topics: log["topics"]
+                    .as_array()
+                    .map(Vec::as_slice)
+                    .unwrap_or_default()
+                    .iter()
+                    .map(|t| parse_bytes(t).map(|b| H256::from_slice(&b)))
+                    .collect::<Result<_>>()?,
+                data: parse_bytes(&log["data"])?,
+            })
+        })
+        .collect::<Result<_>>()?;
+    Ok(Receipt {
+        tx_hash,
+        block_number: parse_quantity(&value["blockNumber"])?.low_u64(),
+        gas_used: parse_quantity(&value["gasUsed"])?,
+        // Pre-London nodes omit it; the fee is then unknown
+        effective_gas_price: parse_quantity(&value["effectiveGasPrice"]).unwrap_or_default(),
+        logs,
+    })
+}
+
+#[cfg(all(test, feature = "execution"))]
+mod tests {
+    use super::*;
+    use crate::signer::LocalSigner;
+
+    #[tokio::test]
+    async fn test_signed_transaction_encoding() {
+        let tx = Eip1559Transaction {
+            chain_id: 42161,
+            nonce: U256::from(7),
+            max_priority_fee_per_gas: U256::from(1_000_000u64),
+            max_fee_per_gas: U256::from(200_000_000u64),
+            gas_limit: U256::from(150_000u64),
+            to: "0xC36442b4a4522E871399CD717aBDD847Ab11FE88".parse().unwrap(),
+            value: U256::zero(),
+            data: vec![0xfc, 0x6f, 0x78, 0x65],
+        };
+        let signer = LocalSigner::from_hex("0x7a28b5ba57c53603b0b07b56bba752f7784bf506fa95edc395f5cf6c7514fe9d").unwrap();
+        let signature = signer.sign_transaction(&tx.signing_payload()).await.unwrap();
+        let raw = tx.encode_signed(&signature);
+        assert_eq!(raw[0], EIP1559_TX_TYPE);
+
+        let fields = rlp::Rlp::new(&raw[1..]);
+        assert_eq!(fields.item_count().unwrap(), 12);
+        assert_eq!(fields.val_at::<u64>(0).unwrap(), 42161);
+        assert_eq!(fields.val_at::<Address>(5).unwrap(), tx.to);
+        assert_eq!(fields.val_at::<Vec<u8>>(7).unwrap(), tx.data);
+        assert_eq!(fields.at(8).unwrap().item_count().unwrap(), 0);
+        assert_eq!(fields.val_at::<U256>(10).unwrap(), U256::from_big_endian(signature.r.as_bytes()));
+
+        // The signature recovers to the signer's address
+        use k256::ecdsa::{RecoveryId, Signature as EcdsaSignature, VerifyingKey};
+        let ecdsa = EcdsaSignature::from_scalars(signature.r.0, signature.s.0).unwrap();
+        let hash = Keccak256::digest(tx.signing_payload());
+        let key = VerifyingKey::recover_from_prehash(&hash, &ecdsa, RecoveryId::from_byte(signature.y_parity).unwrap()).unwrap();
+        let point = key.to_encoded_point(false);
+        assert_eq!(Address::from_slice(&Keccak256::digest(&point.as_bytes()[1..])[12..]), signer.address());
+    }
+}
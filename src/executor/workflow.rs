@@ -0,0 +1,150 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Steps of a rebalance, executed in order
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "server", derive(utoipa::ToSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum RebalanceStep {
+    DecreaseLiquidity,
+    Collect,
+    Swap,
+    Mint,
+}
+
+impl RebalanceStep {
+    pub const ALL: [RebalanceStep; 4] = [
+        RebalanceStep::DecreaseLiquidity,
+        RebalanceStep::Collect,
+        RebalanceStep::Swap,
+        RebalanceStep::Mint,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            RebalanceStep::DecreaseLiquidity => "decrease_liquidity",
+            RebalanceStep::Collect => "collect",
+            RebalanceStep::Swap => "swap",
+            RebalanceStep::Mint => "mint",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "server", derive(utoipa::ToSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum StepStatus {
+    Pending,
+    /// Broadcast; `tx_hash` is set and the receipt is awaited
+    Sent,
+    Confirmed,
+    /// Nothing to do (e.g. no liquidity left, already at the target ratio)
+    Skipped,
+    Failed,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "server", derive(utoipa::ToSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum WorkflowStatus {
+    Running,
+    Completed,
+    Failed,
+}
+
+impl WorkflowStatus {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            WorkflowStatus::Running => "running",
+            WorkflowStatus::Completed => "completed",
+            WorkflowStatus::Failed => "failed",
+        }
+    }
+}
+
+/// Audit record of one step
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "server", derive(utoipa::ToSchema))]
+pub struct StepRecord {
+    pub step: RebalanceStep,
+    pub status: StepStatus,
+    pub tx_hash: Option<String>,
+    /// `approve` transactions sent before the step
+    #[serde(default)]
+    pub approval_tx_hashes: Vec<String>,
+    pub block_number: Option<u64>,
+    pub gas_cost_eth: Option<f64>,
+    /// Amounts, skip reason or error
+    pub detail: Option<String>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// A rebalance of one position into a new range, persisted after every step so it can resume
+/// after a restart
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "server", derive(utoipa::ToSchema))]
+pub struct RebalanceWorkflow {
+    pub id: String,
+    pub position_id: String,
+    pub owner: String,
+    pub token0: String,
+    pub token1: String,
+    /// e.g. `WETH/USDC`
+    pub pair: String,
+    pub pool: String,
+    pub fee: u32,
+    pub old_tick_lower: i32,
+    pub old_tick_upper: i32,
+    pub tick_lower: i32,
+    pub tick_upper: i32,
+    /// Owner balances before the first step (raw units), so the mint only uses what the
+    /// rebalance freed
+    pub balance0_before: String,
+    pub balance1_before: String,
+    pub new_position_id: Option<String>,
+    pub status: WorkflowStatus,
+    pub error: Option<String>,
+    pub steps: Vec<StepRecord>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl RebalanceWorkflow {
+    /// First step that hasn't confirmed or been skipped
+    pub fn next_step(&self) -> Option<usize> {
+        self.steps
+            .iter()
+            .position(|s| !matches!(s.status, StepStatus::Confirmed | StepStatus::Skipped))
+    }
+
+    /// Whether any transaction has landed, i.e. retrying must resume rather than start over
+    pub fn started(&self) -> bool {
+        self.steps.iter().any(|s| s.status == StepStatus::Confirmed)
+    }
+
+    pub fn gas_cost_eth(&self) -> f64 {
+        self.steps.iter().filter_map(|s| s.gas_cost_eth).sum()
+    }
+
+    pub fn tx_hashes(&self) -> Vec<String> {
+        self.steps
+            .iter()
+            .flat_map(|s| s.approval_tx_hashes.iter().cloned().chain(s.tx_hash.clone()))
+            .collect()
+    }
+}
+
+/// Filters for stored rebalance workflows
+#[derive(Debug, Clone, Default)]
+pub struct RebalanceQuery {
+    pub position_id: Option<String>,
+    pub status: Option<WorkflowStatus>,
+    pub limit: usize,
+}
+
+impl RebalanceQuery {
+    pub fn matches(&self, workflow: &RebalanceWorkflow) -> bool {
+        self.position_id.as_ref().is_none_or(|id| id == &workflow.position_id)
+            && self.status.is_none_or(|status| status == workflow.status)
+    }
+}
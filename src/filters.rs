@@ -0,0 +1,103 @@
+//! Token and pool allow/deny lists (`[filters]`), applied to top-pool listings, pool quoting,
+//! position ingestion and scoring. A denied entry always loses; a non-empty allowlist admits
+//! only what it names.
+
+use std::collections::HashSet;
+
+use crate::config::FiltersConfig;
+use crate::position::Position;
+use crate::uniswap::Pool;
+
+#[derive(Debug, Clone, Default)]
+pub struct AssetFilter {
+    allowed_tokens: HashSet<String>,
+    denied_tokens: HashSet<String>,
+    allowed_pools: HashSet<String>,
+    denied_pools: HashSet<String>,
+}
+
+fn lowercase(addresses: &[String]) -> HashSet<String> {
+    addresses.iter().map(|a| a.to_lowercase()).collect()
+}
+
+impl AssetFilter {
+    /// Allows everything when `config` is unset
+    pub fn from_config(config: Option<&FiltersConfig>) -> Self {
+        let Some(config) = config else {
+            return Self::default();
+        };
+        Self {
+            allowed_tokens: lowercase(&config.allowed_tokens),
+            denied_tokens: lowercase(&config.denied_tokens),
+            allowed_pools: lowercase(&config.allowed_pools),
+            denied_pools: lowercase(&config.denied_pools),
+        }
+    }
+
+    pub fn allows_token(&self, token: &str) -> bool {
+        let token = token.to_lowercase();
+        !self.denied_tokens.contains(&token) && (self.allowed_tokens.is_empty() || self.allowed_tokens.contains(&token))
+    }
+
+    /// Pool address check only; see [`Self::allows_pool`] for its tokens too
+    pub fn allows_pool_id(&self, pool: &str) -> bool {
+        let pool = pool.to_lowercase();
+        !self.denied_pools.contains(&pool) && (self.allowed_pools.is_empty() || self.allowed_pools.contains(&pool))
+    }
+
+    /// The pool itself and both of its tokens pass
+    pub fn allows_pool(&self, pool: &Pool) -> bool {
+        self.allows_pool_id(&pool.id) && self.allows_token(&pool.token0.id) && self.allows_token(&pool.token1.id)
+    }
+
+    pub fn allows_position(&self, position: &Position) -> bool {
+        self.allows_token(&position.token_address)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::uniswap::Token;
+
+    const USDC: &str = "0xaf88d065e77c8cc2239327c5edb3a432268e5831";
+    const WETH: &str = "0x82af49447d8a07e3bd95bd0d56f35241523fbab1";
+    const SCAM: &str = "0x000000000000000000000000000000000000dead";
+
+    fn token(id: &str) -> Token {
+        Token { id: id.to_string(), symbol: "T".to_string(), name: "T".to_string(), decimals: "18".to_string() }
+    }
+
+    fn pool(id: &str, token0: &str, token1: &str) -> Pool {
+        Pool {
+            id: id.to_string(),
+            token0: token(token0),
+            token1: token(token1),
+            fee_tier: "500".to_string(),
+            liquidity: "0".to_string(),
+            volume_usd: "0".to_string(),
+            total_value_locked_usd: "0".to_string(),
+            total_value_locked_token0: None,
+            total_value_locked_token1: None,
+            token1_price: None,
+        }
+    }
+
+    #[test]
+    fn test_deny_beats_allow_and_allowlists_restrict() {
+        assert!(AssetFilter::default().allows_pool(&pool("0x01", USDC, SCAM)));
+
+        let filter = AssetFilter::from_config(Some(&FiltersConfig {
+            allowed_tokens: vec![USDC.to_uppercase().replace("0X", "0x"), WETH.to_string()],
+            denied_tokens: vec![WETH.to_string()],
+            allowed_pools: Vec::new(),
+            denied_pools: vec!["0x02".to_string()],
+        }));
+        assert!(filter.allows_token(USDC));
+        assert!(!filter.allows_token(WETH), "denied even though allowed");
+        assert!(!filter.allows_token(SCAM), "not on the allowlist");
+        assert!(filter.allows_pool(&pool("0x01", USDC, USDC)));
+        assert!(!filter.allows_pool(&pool("0x01", USDC, SCAM)));
+        assert!(!filter.allows_pool(&pool("0x02", USDC, USDC)));
+    }
+}
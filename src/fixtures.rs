@@ -0,0 +1,119 @@
+//! Record/replay of upstream responses for offline development. In record mode every Graph
+//! and RPC response is saved to a JSON file named after the request; in replay mode those
+//! files answer instead of the network, so tests and demos run without access or API keys.
+//!
+//! Files are keyed by the chain and request body only, never by endpoint URL (which often
+//! carries an API key), so a recording replays against any endpoint configuration.
+
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use tracing::{debug, warn};
+
+use crate::config::{FixtureMode, FixturesConfig};
+
+/// Fixture directory and mode; clones share the configuration
+#[derive(Clone)]
+pub struct Fixtures {
+    dir: Arc<PathBuf>,
+    mode: FixtureMode,
+}
+
+impl Fixtures {
+    pub fn new(dir: impl Into<PathBuf>, mode: FixtureMode) -> Self {
+        Self {
+            dir: Arc::new(dir.into()),
+            mode,
+        }
+    }
+
+    pub fn from_config(config: &FixturesConfig) -> Self {
+        Self::new(&config.dir, config.mode)
+    }
+
+    pub fn mode(&self) -> FixtureMode {
+        self.mode
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// File holding the response to `request` (a `kind` of call on `chain`)
+    pub fn path(&self, kind: &str, chain: &str, request: &Value) -> PathBuf {
+        let mut hasher = Sha256::new();
+        hasher.update(chain.as_bytes());
+        hasher.update(request.to_string().as_bytes());
+        let digest = hex::encode(hasher.finalize());
+        self.dir.join(format!("{}-{}.json", kind, &digest[..16]))
+    }
+
+    /// Answer `request` from its fixture (replay), or run `fetch` and save its response
+    /// (record). `fetch` is never polled in replay mode.
+    pub async fn through<F>(&self, kind: &str, chain: &str, request: &Value, fetch: F) -> Result<Value>
+    where
+        F: Future<Output = Result<Value>>,
+    {
+        let path = self.path(kind, chain, request);
+        match self.mode {
+            FixtureMode::Replay => {
+                debug!(target: "fixtures", path = %path.display(), "replaying {} response", kind);
+                let text = tokio::fs::read_to_string(&path).await.with_context(|| {
+                    format!("no {} fixture at {} (record it with development.fixtures.mode = \"record\")", kind, path.display())
+                })?;
+                let fixture: Value = serde_json::from_str(&text).with_context(|| format!("parsing fixture {}", path.display()))?;
+                Ok(fixture.get("response").cloned().unwrap_or(Value::Null))
+            }
+            FixtureMode::Record => {
+                let response = fetch.await?;
+                if let Err(e) = self.save(&path, chain, request, &response).await {
+                    warn!(target: "fixtures", path = %path.display(), "recording fixture failed: {:#}", e);
+                }
+                Ok(response)
+            }
+        }
+    }
+
+    async fn save(&self, path: &Path, chain: &str, request: &Value, response: &Value) -> Result<()> {
+        tokio::fs::create_dir_all(self.dir.as_path()).await?;
+        // The request is kept alongside for readability; replay matches on the file name
+        let fixture = json!({ "chain": chain, "request": request, "response": response });
+        tokio::fs::write(path, serde_json::to_vec_pretty(&fixture)?).await?;
+        debug!(target: "fixtures", path = %path.display(), "recorded response");
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_record_then_replay_offline() {
+        let dir = std::env::temp_dir().join(format!("fixtures-test-{}", std::process::id()));
+        let request = json!({ "jsonrpc": "2.0", "id": 1, "method": "eth_blockNumber", "params": [] });
+        let response = json!({ "jsonrpc": "2.0", "id": 1, "result": "0x10" });
+
+        let recorder = Fixtures::new(&dir, FixtureMode::Record);
+        let recorded = recorder.through("rpc", "arbitrum", &request, async { Ok(response.clone()) }).await.unwrap();
+        assert_eq!(recorded, response);
+
+        // Replay never runs the fetch
+        let replayer = Fixtures::new(&dir, FixtureMode::Replay);
+        let replayed = replayer
+            .through("rpc", "arbitrum", &request, async { panic!("replay must not hit the network") })
+            .await
+            .unwrap();
+        assert_eq!(replayed, response);
+
+        // Another chain or request has no fixture
+        let missing = replayer.through("rpc", "ethereum", &request, async { Ok(Value::Null) }).await;
+        assert!(missing.unwrap_err().to_string().contains("no rpc fixture"));
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,457 @@
+//! GMX V2 positions: each configured wallet's open perps, read on-chain through the
+//! synthetics Reader and priced with GMX's oracle tickers, plus its GM market token and GLP
+//! holdings. Perps carry their side and size in index tokens, so the recommender can net an
+//! existing hedge against the rest of the portfolio.
+
+use std::collections::HashMap;
+
+use anyhow::{anyhow, Context, Result};
+use ethabi::{ParamType, Token as AbiToken};
+use ethereum_types::{Address, U256};
+use reqwest::Client;
+use serde::Deserialize;
+use sha3::{Digest, Keccak256};
+use tracing::{debug, info, warn};
+
+use crate::config::GmxConfig;
+use crate::position::{PerpMetrics, PerpSide, Position};
+use crate::uniswap::{encode_call, UniswapClient};
+use crate::utils::{from_base_units, to_decimal, to_units};
+
+pub const PROTOCOL: &str = "gmx_v2";
+
+/// GMX prices and USD amounts carry 30 decimals
+const PRICE_DECIMALS: u8 = 30;
+/// GM and GLP tokens have 18 decimals
+const LP_TOKEN_DECIMALS: u8 = 18;
+/// Upper bound on the markets and positions listed per Reader call
+const MAX_LISTED: u64 = 1000;
+
+/// One token's oracle price per raw unit, scaled by 1e30
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Price {
+    min: U256,
+    max: U256,
+}
+
+impl Price {
+    /// USD value of a raw token amount at the mid price
+    fn usd(&self, raw: U256) -> f64 {
+        let mid = (to_units(self.min, 0) + to_units(self.max, 0)) / 2.0;
+        to_units(raw, 0) * mid / 10f64.powi(PRICE_DECIMALS as i32)
+    }
+
+    fn token(&self) -> AbiToken {
+        AbiToken::Tuple(vec![AbiToken::Uint(self.min), AbiToken::Uint(self.max)])
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Ticker {
+    token_address: String,
+    min_price: String,
+    max_price: String,
+}
+
+/// A GMX market: its GM token and the tokens it trades and pools
+#[derive(Debug, Clone, PartialEq)]
+struct Market {
+    market_token: Address,
+    index_token: Address,
+    long_token: Address,
+    short_token: Address,
+}
+
+impl Market {
+    fn token(&self) -> AbiToken {
+        AbiToken::Tuple(vec![
+            AbiToken::Address(self.market_token),
+            AbiToken::Address(self.index_token),
+            AbiToken::Address(self.long_token),
+            AbiToken::Address(self.short_token),
+        ])
+    }
+}
+
+/// The fields of a `Position.Props` the adapter reads
+#[derive(Debug, Clone, PartialEq)]
+struct PerpPosition {
+    market: Address,
+    collateral_token: Address,
+    size_in_usd: U256,
+    size_in_tokens: U256,
+    collateral_amount: U256,
+    is_long: bool,
+}
+
+pub struct GmxAdapter {
+    client: UniswapClient,
+    http: Client,
+    rpc_url: String,
+    config: GmxConfig,
+}
+
+impl GmxAdapter {
+    pub fn new(client: UniswapClient, rpc_url: &str, config: &GmxConfig) -> Self {
+        Self {
+            client,
+            http: crate::http::shared(),
+            rpc_url: rpc_url.to_string(),
+            config: config.clone(),
+        }
+    }
+
+    /// Every wallet's open perps, GM holdings and (when configured) GLP holding
+    pub async fn positions(&self) -> Result<Vec<Position>> {
+        let prices = self.prices().await?;
+        let markets = self.markets().await?;
+        let data_store = AbiToken::Address(self.config.data_store.parse().context("gmx.data_store")?);
+        let wallets = self
+            .config
+            .wallets
+            .iter()
+            .map(|w| w.parse::<Address>().with_context(|| format!("invalid wallet {}", w)))
+            .collect::<Result<Vec<_>>>()?;
+        let glp = self.config.glp_token.as_ref().zip(self.config.glp_manager.as_ref());
+
+        // Per wallet: its positions, a balance per GM token and its GLP balance; then the GLP price
+        let mut calls = Vec::new();
+        for wallet in &wallets {
+            let range = [AbiToken::Uint(U256::zero()), AbiToken::Uint(U256::from(MAX_LISTED))];
+            let args = [data_store.clone(), AbiToken::Address(*wallet), range[0].clone(), range[1].clone()];
+            calls.push((self.config.reader.clone(), encode_call("getAccountPositions(address,address,uint256,uint256)", &args)));
+            let balance_of = encode_call("balanceOf(address)", &[AbiToken::Address(*wallet)]);
+            for market in &markets {
+                calls.push((format!("{:?}", market.market_token), balance_of.clone()));
+            }
+            if let Some((glp_token, _)) = glp {
+                calls.push((glp_token.clone(), balance_of));
+            }
+        }
+        if let Some((_, glp_manager)) = glp {
+            calls.push((glp_manager.clone(), encode_call("getPrice(bool)", &[AbiToken::Bool(false)])));
+        }
+        let mut results = self.client.eth_call_batch(&self.rpc_url, &calls).await?.into_iter();
+        let mut next = |what: &str| results.next().ok_or_else(|| anyhow!("missing {} result", what))?;
+
+        let mut perps: Vec<(Address, PerpPosition)> = Vec::new();
+        let mut gm_balances: Vec<(Address, &Market, U256)> = Vec::new();
+        let mut glp_balances: Vec<(Address, U256)> = Vec::new();
+        for wallet in &wallets {
+            let listed = next("getAccountPositions")
+                .and_then(|bytes| decode_positions(&bytes))
+                .with_context(|| format!("reading GMX positions of {:?}", wallet))?;
+            perps.extend(listed.into_iter().map(|p| (*wallet, p)));
+            for market in &markets {
+                let balance = next("balanceOf").and_then(|bytes| word(&bytes, 0))?;
+                if !balance.is_zero() {
+                    gm_balances.push((*wallet, market, balance));
+                }
+            }
+            if glp.is_some() {
+                let balance = next("balanceOf").and_then(|bytes| word(&bytes, 0))?;
+                if !balance.is_zero() {
+                    glp_balances.push((*wallet, balance));
+                }
+            }
+        }
+        let glp_price = match glp {
+            Some(_) => Some(next("getPrice").and_then(|bytes| word(&bytes, 0)).context("reading the GLP price")?),
+            None => None,
+        };
+
+        let mut positions = self.perp_positions(&markets, &prices, &perps).await?;
+        positions.extend(self.gm_positions(&prices, &gm_balances).await);
+        if let (Some((glp_token, _)), Some(price)) = (glp, glp_price) {
+            for (wallet, balance) in glp_balances {
+                let amount = from_base_units(balance, LP_TOKEN_DECIMALS)?;
+                let value = to_units(balance, LP_TOKEN_DECIMALS) * to_units(price, PRICE_DECIMALS);
+                let wallet = format!("{:?}", wallet);
+                positions.push(Position::new(format!("gmx:{}:glp", wallet), wallet, glp_token.to_lowercase(), amount, to_decimal(value)));
+            }
+        }
+        info!(target: "gmx", wallets = wallets.len(), positions = positions.len(), "fetched GMX positions");
+        Ok(positions)
+    }
+
+    async fn perp_positions(&self, markets: &[Market], prices: &HashMap<Address, Price>, perps: &[(Address, PerpPosition)]) -> Result<Vec<Position>> {
+        let index_of: HashMap<Address, Address> = markets.iter().map(|m| (m.market_token, m.index_token)).collect();
+        let tokens: Vec<String> = perps
+            .iter()
+            .flat_map(|(_, p)| [index_of.get(&p.market).copied().unwrap_or_default(), p.collateral_token])
+            .map(|a| format!("{:?}", a))
+            .collect();
+        let token_refs: Vec<&str> = tokens.iter().map(String::as_str).collect();
+        let decimals = self
+            .client
+            .tokens_decimals(&self.rpc_url, &token_refs)
+            .await
+            .into_iter()
+            .collect::<Result<Vec<u8>>>()
+            .context("reading GMX token decimals")?;
+
+        let mut positions = Vec::with_capacity(perps.len());
+        for (i, (wallet, perp)) in perps.iter().enumerate() {
+            let index_token = index_of.get(&perp.market).copied().ok_or_else(|| anyhow!("position in unknown market {:?}", perp.market))?;
+            let (index_decimals, collateral_decimals) = (decimals[2 * i], decimals[2 * i + 1]);
+            let side = if perp.is_long { PerpSide::Long } else { PerpSide::Short };
+            let size_usd = to_units(perp.size_in_usd, PRICE_DECIMALS);
+            let collateral_usd = prices.get(&perp.collateral_token).map(|p| p.usd(perp.collateral_amount));
+            let pnl_usd = prices.get(&index_token).map(|p| {
+                let mark_value = p.usd(perp.size_in_tokens);
+                match side {
+                    PerpSide::Long => mark_value - size_usd,
+                    PerpSide::Short => size_usd - mark_value,
+                }
+            });
+            let metrics = PerpMetrics {
+                protocol: PROTOCOL.to_string(),
+                market: format!("{:?}", perp.market),
+                side,
+                size_usd,
+                size_in_tokens: to_units(perp.size_in_tokens, index_decimals),
+                collateral_token: format!("{:?}", perp.collateral_token),
+                collateral_amount: to_units(perp.collateral_amount, collateral_decimals),
+                leverage: collateral_usd.filter(|c| *c > 0.0).map(|c| size_usd / c),
+                pnl_usd,
+            };
+            // Equity when both legs are priced, else the notional
+            let value_usd = collateral_usd.zip(pnl_usd).map_or(size_usd, |(c, pnl)| c + pnl);
+            let wallet = format!("{:?}", wallet);
+            let id = format!("gmx:{}:{}:{}:{}", wallet, metrics.market, metrics.collateral_token, side.as_str());
+            debug!(target: "gmx", wallet = %wallet, market = %metrics.market, side = side.as_str(), size_usd, "perp position");
+            let mut position = Position::new(
+                id,
+                wallet,
+                format!("{:?}", index_token),
+                from_base_units(perp.size_in_tokens, index_decimals)?,
+                to_decimal(value_usd),
+            );
+            position.perp = Some(Box::new(metrics));
+            positions.push(position);
+        }
+        Ok(positions)
+    }
+
+    /// GM holdings valued at each market's token price; holdings that can't be priced are
+    /// skipped with a warning
+    async fn gm_positions(&self, prices: &HashMap<Address, Price>, balances: &[(Address, &Market, U256)]) -> Vec<Position> {
+        let Ok(data_store) = self.config.data_store.parse::<Address>() else {
+            return Vec::new();
+        };
+        let pnl_factor = Keccak256::digest(ethabi::encode(&[AbiToken::String("MAX_PNL_FACTOR_FOR_TRADERS".to_string())]));
+        let unpriced = Price { min: U256::zero(), max: U256::zero() };
+        let calls: Vec<(String, Vec<u8>)> = balances
+            .iter()
+            .map(|(_, market, _)| {
+                let price = |token: &Address| prices.get(token).copied().unwrap_or(unpriced).token();
+                let args = [
+                    AbiToken::Address(data_store),
+                    market.token(),
+                    price(&market.index_token),
+                    price(&market.long_token),
+                    price(&market.short_token),
+                    AbiToken::FixedBytes(pnl_factor.to_vec()),
+                    AbiToken::Bool(false),
+                ];
+                let signature = "getMarketTokenPrice(address,(address,address,address,address),(uint256,uint256),(uint256,uint256),(uint256,uint256),bytes32,bool)";
+                (self.config.reader.clone(), encode_call(signature, &args))
+            })
+            .collect();
+        let results = match self.client.eth_call_batch(&self.rpc_url, &calls).await {
+            Ok(results) => results,
+            Err(e) => {
+                warn!(target: "gmx", "GM token prices unavailable: {:#}", e);
+                return Vec::new();
+            }
+        };
+
+        let mut positions = Vec::new();
+        for ((wallet, market, balance), result) in balances.iter().zip(results) {
+            // The price is an int256; a negative pool value prices the token at zero
+            let price = match result.and_then(|bytes| word(&bytes, 0)) {
+                Ok(price) if price.bit(255) => U256::zero(),
+                Ok(price) => price,
+                Err(e) => {
+                    warn!(target: "gmx", market = ?market.market_token, "GM token price unavailable: {:#}", e);
+                    continue;
+                }
+            };
+            let Ok(amount) = from_base_units(*balance, LP_TOKEN_DECIMALS) else {
+                continue;
+            };
+            let value = to_units(*balance, LP_TOKEN_DECIMALS) * to_units(price, PRICE_DECIMALS);
+            let (wallet, market_token) = (format!("{:?}", wallet), format!("{:?}", market.market_token));
+            positions.push(Position::new(format!("gmx:{}:{}:gm", wallet, market_token), wallet, market_token, amount, to_decimal(value)));
+        }
+        positions
+    }
+
+    /// Oracle prices by token, from the tickers API
+    async fn prices(&self) -> Result<HashMap<Address, Price>> {
+        let tickers: Vec<Ticker> = self
+            .http
+            .get(&self.config.tickers_url)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await
+            .context("reading GMX tickers")?;
+        Ok(tickers
+            .into_iter()
+            .filter_map(|t| {
+                let price = Price {
+                    min: U256::from_dec_str(&t.min_price).ok()?,
+                    max: U256::from_dec_str(&t.max_price).ok()?,
+                };
+                Some((t.token_address.parse().ok()?, price))
+            })
+            .collect())
+    }
+
+    /// Every market the DataStore lists
+    async fn markets(&self) -> Result<Vec<Market>> {
+        let args = [
+            AbiToken::Address(self.config.data_store.parse().context("gmx.data_store")?),
+            AbiToken::Uint(U256::zero()),
+            AbiToken::Uint(U256::from(MAX_LISTED)),
+        ];
+        let bytes = self
+            .client
+            .eth_call_batch(&self.rpc_url, &[(self.config.reader.clone(), encode_call("getMarkets(address,uint256,uint256)", &args))])
+            .await?
+            .remove(0)
+            .context("reading GMX markets")?;
+        decode_markets(&bytes)
+    }
+}
+
+/// The `i`th 32-byte word of ABI-encoded data
+fn word(bytes: &[u8], i: usize) -> Result<U256> {
+    bytes
+        .get(32 * i..32 * (i + 1))
+        .map(U256::from_big_endian)
+        .ok_or_else(|| anyhow!("return data too short for word {}", i))
+}
+
+fn word_address(bytes: &[u8], i: usize) -> Result<Address> {
+    let word = bytes.get(32 * i..32 * (i + 1)).ok_or_else(|| anyhow!("return data too short for word {}", i))?;
+    Ok(Address::from_slice(&word[12..]))
+}
+
+fn decode_markets(bytes: &[u8]) -> Result<Vec<Market>> {
+    let market = ParamType::Tuple(vec![ParamType::Address; 4]);
+    let Some(AbiToken::Array(items)) = ethabi::decode(&[ParamType::Array(Box::new(market))], bytes)?.into_iter().next() else {
+        return Err(anyhow!("expected a market array"));
+    };
+    items
+        .into_iter()
+        .map(|item| {
+            let addresses: Vec<Address> = item.into_tuple().unwrap_or_default().into_iter().filter_map(AbiToken::into_address).collect();
+            match addresses[..] {
+                [market_token, index_token, long_token, short_token] => Ok(Market { market_token, index_token, long_token, short_token }),
+                _ => Err(anyhow!("malformed market")),
+            }
+        })
+        .collect()
+}
+
+/// `getAccountPositions` returns `Position.Props[]`, static structs whose width differs
+/// between GMX releases; the fields read here lead the struct (account, market, collateral
+/// token, size in USD, size in tokens, collateral amount) or end it (isLong)
+fn decode_positions(bytes: &[u8]) -> Result<Vec<PerpPosition>> {
+    let offset = word(bytes, 0)?.low_u64() as usize;
+    let body = bytes.get(offset..).ok_or_else(|| anyhow!("array offset out of range"))?;
+    let len = word(body, 0)?.low_u64() as usize;
+    let items = &body[32..];
+    if len == 0 {
+        return Ok(Vec::new());
+    }
+    let width = items.len() / 32 / len;
+    if width < 7 || items.len() != width * 32 * len {
+        return Err(anyhow!("{} bytes don't hold {} positions", items.len(), len));
+    }
+    (0..len)
+        .map(|i| {
+            let item = &items[i * width * 32..(i + 1) * width * 32];
+            Ok(PerpPosition {
+                market: word_address(item, 1)?,
+                collateral_token: word_address(item, 2)?,
+                size_in_usd: word(item, 3)?,
+                size_in_tokens: word(item, 4)?,
+                collateral_amount: word(item, 5)?,
+                is_long: !word(item, width - 1)?.is_zero(),
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal::Decimal;
+
+    #[test]
+    fn test_decode_positions_of_any_struct_width() {
+        let (account, market, collateral) = (Address::repeat_byte(0xaa), Address::repeat_byte(0xbb), Address::repeat_byte(0xcc));
+        let props = |width: usize, is_long: bool| {
+            let mut fields = vec![
+                AbiToken::Address(account),
+                AbiToken::Address(market),
+                AbiToken::Address(collateral),
+                AbiToken::Uint(U256::exp10(30) * 5000),
+                AbiToken::Uint(U256::exp10(18) * 2),
+                AbiToken::Uint(U256::from(1_000_000_000u64)),
+            ];
+            fields.resize(width - 1, AbiToken::Uint(U256::zero()));
+            fields.push(AbiToken::Bool(is_long));
+            AbiToken::Tuple(fields)
+        };
+        for width in [13, 14] {
+            let bytes = ethabi::encode(&[AbiToken::Array(vec![props(width, true), props(width, false)])]);
+            let positions = decode_positions(&bytes).unwrap();
+            assert_eq!(positions.len(), 2);
+            assert_eq!((positions[0].market, positions[0].collateral_token), (market, collateral));
+            assert_eq!(positions[0].size_in_tokens, U256::exp10(18) * 2);
+            assert!(positions[0].is_long && !positions[1].is_long);
+        }
+        assert!(decode_positions(&ethabi::encode(&[AbiToken::Array(vec![])])).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_decode_markets_and_price_usd() {
+        let market = |b: u8| AbiToken::Tuple((0..4).map(|i| AbiToken::Address(Address::repeat_byte(b + i))).collect());
+        let markets = decode_markets(&ethabi::encode(&[AbiToken::Array(vec![market(0x10), market(0x20)])])).unwrap();
+        assert_eq!(markets[1].market_token, Address::repeat_byte(0x20));
+        assert_eq!(markets[1].short_token, Address::repeat_byte(0x23));
+
+        // ETH at $3000: 3000 * 1e30 / 1e18 per wei
+        let eth = U256::exp10(12) * 3000;
+        let price = Price { min: eth, max: eth };
+        assert!((price.usd(U256::exp10(18) / 2) - 1500.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_short_perp_offsets_exposure_and_leverage_drives_risk() {
+        let mut short = Position::new("gmx:short".into(), "0xaa".into(), "0xeth".into(), Decimal::ONE, Decimal::new(1000, 0));
+        short.perp = Some(Box::new(PerpMetrics {
+            protocol: PROTOCOL.to_string(),
+            market: "0xmarket".to_string(),
+            side: PerpSide::Short,
+            size_usd: 3000.0,
+            size_in_tokens: 1.0,
+            collateral_token: "0xusdc".to_string(),
+            collateral_amount: 1000.0,
+            leverage: Some(3.0),
+            pnl_usd: Some(0.0),
+        }));
+        let spot = Position::new("spot".into(), "0xaa".into(), "0xeth".into(), Decimal::new(15, 1), Decimal::new(4500, 0));
+        assert_eq!(short.exposure() + spot.exposure(), 0.5);
+
+        short.calculate_risk_score(&crate::position::MarketData::new());
+        // Default volatility 0.1 at 3x leverage
+        assert!((short.risk_score - 0.3).abs() < 1e-12);
+    }
+}
@@ -0,0 +1,287 @@
+use async_graphql::{Context, EmptyMutation, EmptySubscription, Enum, Object, Schema, SimpleObject};
+use chrono::{DateTime, Utc};
+
+use crate::api_keys::ApiKey;
+use crate::position::{Action, Position, PositionRecommendation};
+use crate::rest::MAX_PAGE_SIZE;
+use crate::state::{RecommendationRecord, RecommenderState};
+use crate::storage::HistoryQuery;
+use crate::uniswap::{Pool, UniswapClient};
+
+pub type ApiSchema = Schema<QueryRoot, EmptyMutation, EmptySubscription>;
+
+/// Build the GraphQL schema over the recommender's shared state and the Uniswap subgraph
+pub fn build_schema(state: RecommenderState, uniswap: UniswapClient) -> ApiSchema {
+    Schema::build(QueryRoot, EmptyMutation, EmptySubscription)
+        .data(state)
+        .data(uniswap)
+        .finish()
+}
+
+#[derive(Enum, Copy, Clone, Eq, PartialEq)]
+#[graphql(name = "Action")]
+pub enum ActionKind {
+    Hold,
+    Increase,
+    Decrease,
+    Exit,
+    Rebalance,
+}
+
+impl From<ActionKind> for Action {
+    fn from(kind: ActionKind) -> Self {
+        match kind {
+            ActionKind::Hold => Action::Hold,
+            ActionKind::Increase => Action::Increase,
+            ActionKind::Decrease => Action::Decrease,
+            ActionKind::Exit => Action::Exit,
+            // Filters compare action names only
+            ActionKind::Rebalance => Action::Rebalance { new_tick_lower: 0, new_tick_upper: 0 },
+        }
+    }
+}
+
+impl From<&Action> for ActionKind {
+    fn from(action: &Action) -> Self {
+        match action {
+            Action::Hold => ActionKind::Hold,
+            Action::Increase => ActionKind::Increase,
+            Action::Decrease => ActionKind::Decrease,
+            Action::Exit => ActionKind::Exit,
+            Action::Rebalance { .. } => ActionKind::Rebalance,
+        }
+    }
+}
+
+#[derive(SimpleObject)]
+#[graphql(name = "Position")]
+pub struct PositionObject {
+    id: String,
+    user_address: String,
+    token_address: String,
+    /// Decimal amount, as a string to preserve precision
+    amount: String,
+    /// Decimal USD value, as a string to preserve precision
+    value_usd: String,
+    risk_score: f64,
+    liquidity_score: f64,
+    timestamp: u64,
+}
+
+impl From<&Position> for PositionObject {
+    fn from(p: &Position) -> Self {
+        Self {
+            id: p.id.clone(),
+            user_address: p.user_address.clone(),
+            token_address: p.token_address.clone(),
+            amount: p.amount.to_string(),
+            value_usd: p.value_usd.to_string(),
+            risk_score: p.risk_score,
+            liquidity_score: p.liquidity_score,
+            timestamp: p.timestamp,
+        }
+    }
+}
+
+#[derive(SimpleObject)]
+#[graphql(name = "Recommendation")]
+pub struct RecommendationObject {
+    position: PositionObject,
+    recommendation_score: f64,
+    reasoning: String,
+    suggested_action: ActionKind,
+    /// Target range of a `REBALANCE`
+    new_tick_lower: Option<i32>,
+    new_tick_upper: Option<i32>,
+    fee_apr: Option<f64>,
+    projected_il: Option<f64>,
+}
+
+impl From<&PositionRecommendation> for RecommendationObject {
+    fn from(r: &PositionRecommendation) -> Self {
+        Self {
+            position: PositionObject::from(&r.position),
+            recommendation_score: r.recommendation_score,
+            reasoning: r.reasoning.clone(),
+            suggested_action: ActionKind::from(&r.suggested_action),
+            new_tick_lower: match r.suggested_action {
+                Action::Rebalance { new_tick_lower, .. } => Some(new_tick_lower),
+                _ => None,
+            },
+            new_tick_upper: match r.suggested_action {
+                Action::Rebalance { new_tick_upper, .. } => Some(new_tick_upper),
+                _ => None,
+            },
+            fee_apr: r.fee_apr,
+            projected_il: r.projected_il,
+        }
+    }
+}
+
+#[derive(SimpleObject)]
+#[graphql(name = "HistoryEntry")]
+pub struct HistoryObject {
+    cycle: u64,
+    timestamp: DateTime<Utc>,
+    recommendation: RecommendationObject,
+}
+
+impl From<&RecommendationRecord> for HistoryObject {
+    fn from(r: &RecommendationRecord) -> Self {
+        Self {
+            cycle: r.cycle,
+            timestamp: r.timestamp,
+            recommendation: RecommendationObject::from(&r.recommendation),
+        }
+    }
+}
+
+#[derive(SimpleObject)]
+#[graphql(name = "Token")]
+pub struct TokenObject {
+    id: String,
+    symbol: String,
+    name: String,
+    decimals: String,
+}
+
+#[derive(SimpleObject)]
+#[graphql(name = "Pool")]
+pub struct PoolObject {
+    id: String,
+    token0: TokenObject,
+    token1: TokenObject,
+    fee_tier: String,
+    liquidity: String,
+    volume_usd: String,
+    total_value_locked_usd: String,
+}
+
+impl From<Pool> for PoolObject {
+    fn from(p: Pool) -> Self {
+        let token = |t: crate::uniswap::Token| TokenObject {
+            id: t.id,
+            symbol: t.symbol,
+            name: t.name,
+            decimals: t.decimals,
+        };
+        Self {
+            id: p.id,
+            token0: token(p.token0),
+            token1: token(p.token1),
+            fee_tier: p.fee_tier,
+            liquidity: p.liquidity,
+            volume_usd: p.volume_usd,
+            total_value_locked_usd: p.total_value_locked_usd,
+        }
+    }
+}
+
+pub struct QueryRoot;
+
+/// Whether the calling API key (if any) may see positions owned by `wallet`
+fn visible(ctx: &Context<'_>, wallet: &str) -> bool {
+    ctx.data_opt::<ApiKey>().is_none_or(|key| key.allows_wallet(wallet))
+}
+
+/// Wallets the calling API key is restricted to (empty means all)
+fn caller_wallets(ctx: &Context<'_>) -> Vec<String> {
+    ctx.data_opt::<ApiKey>().map(|k| k.wallets.clone()).unwrap_or_default()
+}
+
+#[Object]
+impl QueryRoot {
+    /// Positions analyzed in the latest cycle
+    async fn positions(&self, ctx: &Context<'_>) -> async_graphql::Result<Vec<PositionObject>> {
+        let state = ctx.data_unchecked::<RecommenderState>();
+        Ok(state
+            .positions()
+            .await?
+            .iter()
+            .filter(|p| visible(ctx, &p.user_address))
+            .map(PositionObject::from)
+            .collect())
+    }
+
+    async fn position(&self, ctx: &Context<'_>, id: String) -> async_graphql::Result<Option<PositionObject>> {
+        let state = ctx.data_unchecked::<RecommenderState>();
+        Ok(state
+            .positions()
+            .await?
+            .iter()
+            .find(|p| p.id == id && visible(ctx, &p.user_address))
+            .map(PositionObject::from))
+    }
+
+    /// Recommendations from the latest cycle, optionally filtered and paginated; `limit` is capped
+    /// like the REST endpoints
+    #[allow(clippy::too_many_arguments)]
+    async fn recommendations(
+        &self,
+        ctx: &Context<'_>,
+        action: Option<ActionKind>,
+        wallet: Option<String>,
+        token_address: Option<String>,
+        #[graphql(default = 100)] limit: usize,
+        #[graphql(default = 0)] offset: usize,
+    ) -> async_graphql::Result<Vec<RecommendationObject>> {
+        let state = ctx.data_unchecked::<RecommenderState>();
+        let query = HistoryQuery {
+            wallet,
+            wallets: caller_wallets(ctx),
+            action: action.map(Action::from),
+            token_address,
+            ..Default::default()
+        };
+        Ok(state
+            .latest()
+            .await?
+            .iter()
+            .filter(|r| query.matches_recommendation(r))
+            .skip(offset)
+            .take(limit.min(MAX_PAGE_SIZE))
+            .map(RecommendationObject::from)
+            .collect())
+    }
+
+    /// Past recommendations, newest first; `since` is inclusive, `until` exclusive
+    #[allow(clippy::too_many_arguments)]
+    async fn history(
+        &self,
+        ctx: &Context<'_>,
+        position_id: Option<String>,
+        wallet: Option<String>,
+        action: Option<ActionKind>,
+        token_address: Option<String>,
+        since: Option<DateTime<Utc>>,
+        until: Option<DateTime<Utc>>,
+        #[graphql(default = 100)] limit: usize,
+        #[graphql(default = 0)] offset: usize,
+    ) -> async_graphql::Result<Vec<HistoryObject>> {
+        let state = ctx.data_unchecked::<RecommenderState>();
+        let query = HistoryQuery {
+            position_id,
+            wallet,
+            wallets: caller_wallets(ctx),
+            action: action.map(Action::from),
+            token_address,
+            since,
+            until,
+            limit: limit.min(MAX_PAGE_SIZE),
+            offset,
+        };
+        Ok(state.history(&query).await?.iter().map(HistoryObject::from).collect())
+    }
+
+    /// Pool data from the Uniswap subgraph
+    async fn pool(&self, ctx: &Context<'_>, id: String) -> async_graphql::Result<Option<PoolObject>> {
+        let uniswap = ctx.data_unchecked::<UniswapClient>();
+        Ok(uniswap.get_pool_by_id(&id).await?.map(PoolObject::from))
+    }
+
+    /// Top pools by TVL from the Uniswap subgraph
+    async fn top_pools(&self, ctx: &Context<'_>, #[graphql(default = 10)] first: usize) -> async_graphql::Result<Vec<PoolObject>> {
+        let uniswap = ctx.data_unchecked::<UniswapClient>();
+        Ok(uniswap.top_pools(first).await?.into_iter().map(PoolObject::from).collect())
+    }
+}
@@ -0,0 +1,54 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+
+/// Liveness/readiness signals shared between the recommender loop and the HTTP probes
+#[derive(Clone, Default)]
+pub struct HealthState {
+    inner: Arc<RwLock<HealthInner>>,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct HealthInner {
+    pub started_at: Option<DateTime<Utc>>,
+    pub last_successful_cycle: Option<DateTime<Utc>>,
+    pub last_cycle_error: Option<String>,
+    pub consecutive_cycle_failures: u32,
+    pub models_loaded: bool,
+}
+
+impl HealthState {
+    pub fn new() -> Self {
+        Self {
+            inner: Arc::new(RwLock::new(HealthInner {
+                started_at: Some(Utc::now()),
+                ..Default::default()
+            })),
+        }
+    }
+
+    /// Record a completed recommendation cycle
+    pub async fn record_cycle_success(&self) {
+        let mut inner = self.inner.write().await;
+        inner.last_successful_cycle = Some(Utc::now());
+        inner.last_cycle_error = None;
+        inner.consecutive_cycle_failures = 0;
+    }
+
+    /// Record a failed recommendation cycle
+    pub async fn record_cycle_failure(&self, error: &str) {
+        let mut inner = self.inner.write().await;
+        inner.last_cycle_error = Some(error.to_string());
+        inner.consecutive_cycle_failures += 1;
+    }
+
+    /// Mark whether the AI models are trained/loaded
+    pub async fn set_models_loaded(&self, loaded: bool) {
+        self.inner.write().await.models_loaded = loaded;
+    }
+
+    pub async fn snapshot(&self) -> HealthInner {
+        self.inner.read().await.clone()
+    }
+}
@@ -0,0 +1,68 @@
+//! The HTTP client shared by The Graph, RPC and price requests. One pool means connections
+//! are kept alive and reused (multiplexed over HTTP/2 where the server offers it) instead of
+//! every component opening its own sockets.
+
+use std::sync::OnceLock;
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use reqwest::Client;
+use tracing::warn;
+
+use crate::config::HttpConfig;
+
+const USER_AGENT: &str = concat!("origins-onchain-position-recommender/", env!("CARGO_PKG_VERSION"));
+
+static SHARED: OnceLock<Client> = OnceLock::new();
+
+/// Build a client tuned by `config`
+pub fn build(config: &HttpConfig) -> Result<Client> {
+    Client::builder()
+        .user_agent(USER_AGENT)
+        .pool_max_idle_per_host(config.pool_max_idle_per_host)
+        .pool_idle_timeout(Duration::from_secs(config.pool_idle_timeout_secs))
+        .tcp_keepalive(Duration::from_secs(config.tcp_keepalive_secs))
+        .tcp_nodelay(true)
+        .http2_keep_alive_interval(Duration::from_secs(config.http2_keep_alive_interval_secs))
+        .http2_keep_alive_while_idle(true)
+        .http2_adaptive_window(true)
+        .connect_timeout(Duration::from_secs(config.connect_timeout_secs))
+        .timeout(Duration::from_secs(config.request_timeout_secs))
+        .build()
+        .context("building the HTTP client")
+}
+
+/// Set up the shared client from `[http]`; call once at startup, before any client is
+/// handed out. Later calls keep the client already in place.
+pub fn init(config: Option<&HttpConfig>) -> Result<()> {
+    let client = build(&config.cloned().unwrap_or_default())?;
+    if SHARED.set(client).is_err() {
+        warn!(target: "http", "shared HTTP client already initialized; [http] not applied");
+    }
+    Ok(())
+}
+
+/// Handle to the shared client (cheap: clones share the pool); defaults when [`init`]
+/// wasn't called
+pub fn shared() -> Client {
+    SHARED
+        .get_or_init(|| build(&HttpConfig::default()).expect("default HTTP client settings are valid"))
+        .clone()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_config_defaults_and_repeated_init() {
+        let config: HttpConfig = toml::from_str("pool_max_idle_per_host = 4\nrequest_timeout_secs = 30").unwrap();
+        assert_eq!((config.pool_max_idle_per_host, config.pool_idle_timeout_secs), (4, 90));
+        assert!(build(&config).is_ok());
+
+        // Other tests may already have created the shared client; init then keeps it
+        init(Some(&config)).unwrap();
+        init(None).unwrap();
+        assert!(SHARED.get().is_some());
+    }
+}
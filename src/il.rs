@@ -0,0 +1,114 @@
+//! Impermanent loss: what providing liquidity costs against holding the tokens deposited,
+//! as a share of the holding's value (zero or negative). Prices are token1 per token0.
+
+use crate::utils::uniswap_v3::{sqrt_ratio_at_tick, sqrt_ratio_to_f64};
+
+/// Loss of a full-range (constant product) position after the price moved by `price_ratio`
+/// (current / entry): `2 * sqrt(r) / (1 + r) - 1`. `None` for a non-positive ratio.
+pub fn full_range_il(price_ratio: f64) -> Option<f64> {
+    if !(price_ratio.is_finite() && price_ratio > 0.0) {
+        return None;
+    }
+    Some(2.0 * price_ratio.sqrt() / (1.0 + price_ratio) - 1.0)
+}
+
+/// Loss of a position concentrated in `[price_lower, price_upper]`, entered at `entry_price`,
+/// now at `current_price`. Concentration magnifies the loss of a full-range position for
+/// the same move; outside the range the position is all one token. `None` for non-positive
+/// prices or an empty range.
+pub fn concentrated_il(entry_price: f64, current_price: f64, price_lower: f64, price_upper: f64) -> Option<f64> {
+    let positive = |p: f64| p.is_finite() && p > 0.0;
+    if !(positive(entry_price) && positive(current_price) && positive(price_lower) && price_lower < price_upper) {
+        return None;
+    }
+    let (sqrt_lower, sqrt_upper) = (price_lower.sqrt(), price_upper.sqrt());
+    // Token amounts of one unit of liquidity at `price`
+    let amounts = |price: f64| {
+        let sqrt_price = price.sqrt().clamp(sqrt_lower, sqrt_upper);
+        (1.0 / sqrt_price - 1.0 / sqrt_upper, sqrt_price - sqrt_lower)
+    };
+    let (held0, held1) = amounts(entry_price);
+    let (lp0, lp1) = amounts(current_price);
+    let hodl = held0 * current_price + held1;
+    if hodl <= 0.0 {
+        return None;
+    }
+    Some((lp0 * current_price + lp1) / hodl - 1.0)
+}
+
+/// Loss a full-range position takes from a one-`volatility` move of its price (either way:
+/// the loss is symmetric in log price). With annualized volatility, a year's typical loss.
+pub fn projected_il(volatility: f64) -> f64 {
+    full_range_il(volatility.max(0.0).exp()).unwrap_or(0.0)
+}
+
+/// Loss a position in ticks `[tick_lower, tick_upper]`, at `current_tick` now, takes from a
+/// one-`volatility` move of its price: the worse of a move up and down, since a concentrated
+/// range isn't symmetric about the current price. `None` for an empty range or a tick
+/// outside the tick space.
+pub fn projected_range_il(volatility: f64, current_tick: i32, tick_lower: i32, tick_upper: i32) -> Option<f64> {
+    let sqrt_price = |tick: i32| sqrt_ratio_at_tick(tick).ok().map(sqrt_ratio_to_f64);
+    // Prices relative to the current one, which is all the loss depends on
+    let current = sqrt_price(current_tick)?;
+    let price = |tick: i32| sqrt_price(tick).map(|s| (s / current).powi(2));
+    let (lower, upper) = (price(tick_lower)?, price(tick_upper)?);
+    let moved = volatility.max(0.0).exp();
+    let up = concentrated_il(1.0, moved, lower, upper)?;
+    let down = concentrated_il(1.0, 1.0 / moved, lower, upper)?;
+    Some(up.min(down))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_full_range_il() {
+        assert_eq!(full_range_il(1.0), Some(0.0));
+        // Price doubles: 2 * sqrt(2) / 3 - 1
+        let doubled = full_range_il(2.0).unwrap();
+        assert!((doubled - -0.057190958).abs() < 1e-9, "{}", doubled);
+        // Symmetric in log price
+        assert!((full_range_il(0.5).unwrap() - doubled).abs() < 1e-12);
+        assert_eq!(full_range_il(0.0), None);
+        assert_eq!(full_range_il(f64::NAN), None);
+    }
+
+    #[test]
+    fn test_concentrated_il() {
+        // A very wide range approaches the full-range loss
+        let wide = concentrated_il(2_000.0, 4_000.0, 1e-9, 1e15).unwrap();
+        assert!((wide - full_range_il(2.0).unwrap()).abs() < 1e-4, "{}", wide);
+        // A ±20% range loses more on a 10% move
+        let narrow = concentrated_il(2_000.0, 2_200.0, 1_600.0, 2_400.0).unwrap();
+        assert!(narrow < full_range_il(1.1).unwrap(), "{}", narrow);
+        assert_eq!(concentrated_il(2_000.0, 2_000.0, 1_600.0, 2_400.0), Some(0.0));
+        // Below the range the position is all token0, and further drops keep widening the gap
+        // to the holding's token1
+        let below = concentrated_il(2_000.0, 1_000.0, 1_600.0, 2_400.0).unwrap();
+        let further = concentrated_il(2_000.0, 500.0, 1_600.0, 2_400.0).unwrap();
+        assert!(below < 0.0 && further < below);
+        assert_eq!(concentrated_il(2_000.0, 2_200.0, 2_400.0, 1_600.0), None);
+    }
+
+    #[test]
+    fn test_projected_il() {
+        assert_eq!(projected_il(0.0), 0.0);
+        // For small moves the loss is about volatility^2 / 8
+        assert!((projected_il(0.1) - -0.01 / 8.0).abs() < 1e-5);
+        assert!(projected_il(0.8) < projected_il(0.4));
+    }
+
+    #[test]
+    fn test_projected_range_il() {
+        assert_eq!(projected_range_il(0.0, 0, -600, 600), Some(0.0));
+        // A ±6% range loses more than full range to the same move
+        let narrow = projected_range_il(0.1, 0, -600, 600).unwrap();
+        assert!(narrow < projected_il(0.1), "{}", narrow);
+        // A range as wide as the tick space is about full range
+        let wide = projected_range_il(0.1, 0, -887_220, 887_220).unwrap();
+        assert!((wide - projected_il(0.1)).abs() < 1e-6, "{}", wide);
+        assert_eq!(projected_range_il(0.1, 0, 600, -600), None);
+        assert_eq!(projected_range_il(0.1, 0, -600, 1_000_000), None);
+    }
+}
@@ -0,0 +1,98 @@
+use anyhow::Result;
+use chrono::Utc;
+use jsonwebtoken::{decode, Algorithm, DecodingKey, Validation};
+use serde::{Deserialize, Serialize};
+
+use crate::api_keys::{ApiKey, Scope};
+use crate::config::JwtConfig;
+
+/// Claims expected in bearer tokens
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Claims {
+    pub sub: String,
+    pub exp: u64,
+    /// `read` for read-only endpoints, `admin` for admin endpoints as well
+    pub role: Scope,
+    /// Wallets whose positions the token may see; empty means all wallets
+    #[serde(default)]
+    pub wallets: Vec<String>,
+}
+
+/// Verifies HS256 bearer tokens and maps them onto the same principal as API keys
+#[derive(Clone)]
+pub struct JwtVerifier {
+    key: DecodingKey,
+    validation: Validation,
+    rate_limit_per_minute: u32,
+}
+
+impl JwtVerifier {
+    pub fn new(config: &JwtConfig) -> Self {
+        let mut validation = Validation::new(Algorithm::HS256);
+        if let Some(issuer) = &config.issuer {
+            validation.set_issuer(&[issuer]);
+        }
+        if let Some(audience) = &config.audience {
+            validation.set_audience(&[audience]);
+        }
+        Self {
+            key: DecodingKey::from_secret(config.secret.as_bytes()),
+            validation,
+            rate_limit_per_minute: config.rate_limit_per_minute,
+        }
+    }
+
+    /// Validate the token's signature, expiry, issuer and audience
+    pub fn verify(&self, token: &str) -> Result<ApiKey> {
+        let claims = decode::<Claims>(token, &self.key, &self.validation)?.claims;
+        Ok(ApiKey {
+            id: format!("jwt:{}", claims.sub),
+            name: claims.sub,
+            scopes: vec![claims.role],
+            rate_limit_per_minute: self.rate_limit_per_minute,
+            wallets: claims.wallets,
+            created_at: Utc::now(),
+            revoked_at: None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use jsonwebtoken::{encode, EncodingKey, Header};
+
+    fn config() -> JwtConfig {
+        JwtConfig {
+            secret: "secret".to_string(),
+            issuer: None,
+            audience: None,
+            rate_limit_per_minute: 0,
+        }
+    }
+
+    fn token(secret: &str, exp: i64) -> String {
+        let claims = Claims {
+            sub: "desk-a".to_string(),
+            exp: exp as u64,
+            role: Scope::Read,
+            wallets: vec!["0xabc".to_string()],
+        };
+        encode(&Header::default(), &claims, &EncodingKey::from_secret(secret.as_bytes())).unwrap()
+    }
+
+    #[test]
+    fn test_verify_token() {
+        let verifier = JwtVerifier::new(&config());
+        let exp = Utc::now().timestamp() + 300;
+
+        let principal = verifier.verify(&token("secret", exp)).unwrap();
+        assert_eq!(principal.id, "jwt:desk-a");
+        assert!(principal.has_scope(Scope::Read));
+        assert!(!principal.has_scope(Scope::Admin));
+        assert!(principal.allows_wallet("0xABC"));
+
+        assert!(verifier.verify(&token("other", exp)).is_err());
+        assert!(verifier.verify(&token("secret", Utc::now().timestamp() - 3600)).is_err());
+    }
+}
@@ -0,0 +1,62 @@
+//! Origins onchain position recommender library.
+
+pub mod aave;
+#[cfg(feature = "ml")]
+pub mod ai_predictor;
+pub mod alerts;
+pub mod archive;
+pub mod audit;
+pub mod aws;
+pub mod backfill;
+#[cfg(feature = "server")]
+pub mod api_keys;
+pub mod cache;
+pub mod config;
+pub mod config_migrations;
+pub mod credentials;
+pub mod event_publisher;
+pub mod events;
+pub mod executor;
+pub mod filters;
+pub mod fixtures;
+pub mod gmx;
+#[cfg(feature = "server")]
+pub mod graphql;
+pub mod health;
+pub mod http;
+pub mod il;
+#[cfg(feature = "server")]
+pub mod jwt;
+pub mod market_data;
+pub mod metrics;
+pub mod notifier;
+#[cfg(feature = "server")]
+pub mod openapi;
+pub mod ops_alerts;
+pub mod origins;
+pub mod pnl;
+pub mod position;
+pub mod quotes;
+pub mod range_optimizer;
+#[cfg(feature = "server")]
+pub mod rate_limit;
+pub mod rebalance_advice;
+pub mod recommender;
+pub mod reports;
+#[cfg(feature = "server")]
+pub mod rest;
+pub mod rpc_health;
+pub mod scheduler;
+pub mod secrets;
+#[cfg(feature = "server")]
+pub mod server;
+pub mod signer;
+pub mod singleflight;
+pub mod state;
+pub mod storage;
+pub mod telemetry;
+#[cfg(feature = "ml")]
+pub mod training_data;
+pub mod uniswap;
+pub mod utils;
+pub mod webhook;
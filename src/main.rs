@@ -1,18 +1,49 @@
 use anyhow::Result;
-use clap::Parser;
-use tracing::{info, Level};
-use tracing_subscriber;
+use anyhow::{bail, Context};
+use chrono::Utc;
+use clap::{Parser, Subcommand};
+use ethereum_types::U256;
+use std::io::{BufRead, IsTerminal, Write};
+use std::sync::Arc;
+use tracing::{error, info, warn, Level};
 
-mod config;
-mod position;
-mod recommender;
-mod utils;
-mod ai_predictor;
-mod uniswap;
-
-use config::Config;
-use recommender::PositionRecommender;
-use uniswap::UniswapClient;
+#[cfg(feature = "ml")]
+use origins_onchain_position_recommender::ai_predictor::AIPredictor;
+use origins_onchain_position_recommender::alerts::{FeeMonitor, PositionMutes, RangeMonitor};
+use origins_onchain_position_recommender::archive::ArchiveWriter;
+use origins_onchain_position_recommender::audit::{self, AuditLog};
+use origins_onchain_position_recommender::backfill::{self, Backfill};
+use origins_onchain_position_recommender::cache::Cache;
+use origins_onchain_position_recommender::config::Config;
+use origins_onchain_position_recommender::credentials;
+use origins_onchain_position_recommender::event_publisher;
+use origins_onchain_position_recommender::executor::{
+    exit_summary, tracked_position_ids, AllowanceManager, EmergencyExit, FeeCollector, PaperTrader, Rebalancer, TxSender,
+    TxTracker,
+};
+use origins_onchain_position_recommender::http;
+use origins_onchain_position_recommender::market_data::{CoinGeckoProvider, MarketDataRefresher};
+use origins_onchain_position_recommender::metrics::{self, MetricKind, MetricQuery, MetricsRecorder};
+use origins_onchain_position_recommender::notifier;
+use origins_onchain_position_recommender::ops_alerts::{self, OpsMonitor};
+use origins_onchain_position_recommender::pnl::{self, PnlRecorder};
+use origins_onchain_position_recommender::position::Action;
+use origins_onchain_position_recommender::quotes::{self, QuoteBus, QuoteFetcher};
+use origins_onchain_position_recommender::range_optimizer::{self, RangeRecommendation};
+use origins_onchain_position_recommender::recommender::PositionRecommender;
+use origins_onchain_position_recommender::reports::ReportExporter;
+use origins_onchain_position_recommender::rpc_health::{RpcEndpoints, RpcHealthMonitor};
+use origins_onchain_position_recommender::secrets;
+#[cfg(feature = "server")]
+use origins_onchain_position_recommender::server::{self, AppState};
+use origins_onchain_position_recommender::signer;
+use origins_onchain_position_recommender::storage::{self, HistoryQuery};
+use origins_onchain_position_recommender::telemetry;
+#[cfg(feature = "ml")]
+use origins_onchain_position_recommender::training_data::{Retrainer, TrainingCollector};
+use origins_onchain_position_recommender::uniswap::{OnchainPosition, UniswapClient};
+use origins_onchain_position_recommender::utils;
+use origins_onchain_position_recommender::webhook::WebhookSink;
 
 #[derive(Parser)]
 #[command(name = "origins-onchain-position-recommender")]
@@ -21,6 +52,14 @@ struct Cli {
     /// Configuration file path
     #[arg(short, long, default_value = "config.toml")]
     config: String,
+
+    /// Override file deep-merged over the configuration (repeatable; later files win)
+    #[arg(long = "override", value_name = "FILE")]
+    overrides: Vec<String>,
+
+    /// Fail on unknown configuration keys instead of ignoring them
+    #[arg(long)]
+    strict: bool,
     
     /// Enable verbose logging
     #[arg(short, long)]
@@ -33,124 +72,550 @@ struct Cli {
     /// Fetch a Uniswap V3 position by tokenId and exit
     #[arg(long)]
     position_id: Option<String>,
+
+    /// Fetch every Uniswap V3 position held by this wallet and exit
+    #[arg(long)]
+    owner: Option<String>,
+
+    /// Work on this `[chains.<name>]` network instead of the configured `chain`
+    #[arg(long, global = true)]
+    chain: Option<String>,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Withdraw all liquidity and collect fees from every tracked position of an owner
+    ExitAll {
+        /// Owner of the positions: the signer, or the Safe in Safe mode
+        #[arg(long)]
+        owner: String,
+        /// Send the transactions after a confirmation prompt; without it the exits are only
+        /// simulated and summarized
+        #[arg(long)]
+        confirm: bool,
+    },
+    /// Check the audit log's hash chain from the first record to the head
+    VerifyAudit,
+    /// Export the [reports] portfolio report for the last complete period now
+    ExportReport,
+    /// Pull a pool's hourly candles, swaps and position snapshots into storage; re-running
+    /// resumes where the previous run stopped
+    Backfill {
+        /// Pool address
+        #[arg(long)]
+        pool: String,
+        /// First day to fetch (YYYY-MM-DD, UTC)
+        #[arg(long)]
+        from: chrono::NaiveDate,
+    },
+    /// Print a stored metric time series, oldest first
+    Metrics {
+        /// pool_fee_apr, realized_vol, position_value, in_range_pct or rpc_score
+        #[arg(long)]
+        metric: MetricKind,
+        /// Pool address or position id; all subjects when omitted
+        #[arg(long)]
+        subject: Option<String>,
+        /// First day to show (YYYY-MM-DD, UTC)
+        #[arg(long)]
+        since: Option<chrono::NaiveDate>,
+        #[arg(long, default_value_t = 100)]
+        limit: usize,
+    },
+    /// Print stored recommendations, newest first
+    History {
+        #[arg(long)]
+        position_id: Option<String>,
+        /// Only this wallet's positions
+        #[arg(long)]
+        wallet: Option<String>,
+        /// hold, increase, decrease or exit
+        #[arg(long)]
+        action: Option<Action>,
+        /// First day to show (YYYY-MM-DD, UTC)
+        #[arg(long)]
+        since: Option<chrono::NaiveDate>,
+        #[arg(long, default_value_t = 50)]
+        limit: usize,
+    },
+    /// Suggest how to enter a new position
+    Recommend {
+        #[command(subcommand)]
+        target: RecommendTarget,
+    },
+    /// Manage credentials in the OS keyring, referenced from config as `keyring:<name>`
+    Keyring {
+        #[command(subcommand)]
+        action: KeyringAction,
+    },
+}
+
+#[derive(Subcommand)]
+enum RecommendTarget {
+    /// Tick range for a new position in a pool, from its volatility and fee yield
+    Range {
+        /// Pool address
+        pool_id: String,
+        /// How long the position is meant to be held
+        #[arg(long, default_value_t = 7)]
+        horizon_days: u64,
+    },
+}
+
+#[derive(Subcommand)]
+enum KeyringAction {
+    /// Store a credential, read from a hidden prompt (or stdin when piped)
+    Set {
+        /// Entry name, e.g. `graph-key` or `keystore-passphrase`
+        name: String,
+    },
+    /// Remove a stored credential
+    Delete { name: String },
 }
 
 #[tokio::main]
 async fn main() -> Result<()> {
     let cli = Cli::parse();
     
-    // Initialize logging
+    // Load configuration
+    let mut config = Config::load_layered(&cli.config, &cli.overrides, cli.strict)?;
+    if let Some(chain) = &cli.chain {
+        config.select_chain(chain)?;
+    }
+
+    // Initialize logging (and OTLP span export when configured)
     let level = if cli.verbose { Level::DEBUG } else { Level::INFO };
-    tracing_subscriber::fmt()
-        .with_max_level(level)
-        .init();
+    let _telemetry = telemetry::init(level, config.get_telemetry_config())?;
+    http::init(config.http.as_ref())?;
+    if let Some(fixtures) = config.fixtures() {
+        info!(mode = ?fixtures.mode, dir = %fixtures.dir, "Graph and RPC responses go through fixtures");
+    }
+
+    // Before secret references are resolved, so a missing keyring entry can be stored
+    if let Some(Command::Keyring { action }) = &cli.command {
+        return manage_keyring(action);
+    }
     
     info!("Starting Origins Onchain Position Recommender");
-    
-    // Load configuration
-    let config = Config::load(&cli.config)?;
     info!("Configuration loaded from {}", cli.config);
+    for path in &cli.overrides {
+        info!("Configuration overridden by {}", path);
+    }
+    if !config.migration_notes.is_empty() {
+        warn!(
+            "Configuration uses an older layout and was migrated to config_version {} in memory; update the file:",
+            config.config_version
+        );
+        for note in &config.migration_notes {
+            warn!("  {}", note);
+        }
+    }
+    secrets::resolve_config(&mut config).await?;
+    let chain = config.active_chain();
+    info!("Using chain {}", chain.name);
+
+    // Shared cache for Graph responses and token metadata
+    let cache = Cache::from_config(config.cache.as_ref())?;
+
+    match &cli.command {
+        Some(Command::VerifyAudit) => return verify_audit(&config).await,
+        Some(Command::ExportReport) => return export_report(&config).await,
+        Some(Command::Backfill { pool, from }) => return run_backfill(&config, cache, pool, *from).await,
+        Some(Command::Metrics { metric, subject, since, limit }) => {
+            return print_metrics(&config, *metric, subject.clone(), *since, *limit).await
+        }
+        Some(Command::History { position_id, wallet, action, since, limit }) => {
+            let query = HistoryQuery {
+                position_id: position_id.clone(),
+                wallet: wallet.clone(),
+                action: action.clone(),
+                since: since.map(|day| day.and_time(chrono::NaiveTime::MIN).and_utc()),
+                limit: *limit,
+                ..HistoryQuery::default()
+            };
+            return print_history(&config, &query).await;
+        }
+        Some(Command::Recommend { target: RecommendTarget::Range { pool_id, horizon_days } }) => {
+            let client = UniswapClient::from_config(&config).with_cache(cache.clone());
+            return print_range(&client, &chain.rpc_url, pool_id, *horizon_days).await;
+        }
+        _ => {}
+    }
 
     // If a position id is requested, fetch on-chain and exit
     if let Some(token_id) = cli.position_id.as_deref() {
-        let client = UniswapClient::from_config(&config);
-        let rpc = config.rpc_url.as_str();
-        let pos = client.get_onchain_position(rpc, token_id).await?;
-        println!(
-            "[UNISWAP ONCHAIN] tokenId={} {}({})-{}({}) fee={} tickRange=[{},{}] priceRange[{} per {}]=[{}, {}] midPrice={} liquidity={} owed0={} owed1={}",
-            pos.token_id,
-            pos.token0_symbol,
-            pos.token0,
-            pos.token1_symbol,
-            pos.token1,
-            pos.fee,
-            pos.tick_lower,
-            pos.tick_upper,
-            pos.token1_symbol,
-            pos.token0_symbol,
-            pos.price_lower_quote_per_base,
-            pos.price_upper_quote_per_base,
-            pos.mid_price_quote_per_base,
-            pos.liquidity,
-            pos.tokens_owed0,
-            pos.tokens_owed1
-        );
+        let client = UniswapClient::from_config(&config).with_cache(cache.clone());
+        let pos = client.get_onchain_position(&chain.rpc_url, token_id).await?;
+        print_onchain_position(&pos);
+        return Ok(());
+    }
+
+    // Every position NFT of a wallet, fetched on-chain
+    if let Some(owner) = cli.owner.as_deref() {
+        let client = UniswapClient::from_config(&config).with_cache(cache.clone());
+        let positions = client.get_positions_by_owner(&chain.rpc_url, owner).await?;
+        info!("{} holds {} Uniswap V3 positions", owner, positions.len());
+        for pos in &positions {
+            print_onchain_position(pos);
+        }
         return Ok(());
     }
 
     // Optional: List top Uniswap pools and exit
     if cli.list_top_pools > 0 {
-        let client = UniswapClient::from_config(&config);
+        let client = UniswapClient::from_config(&config).with_cache(cache.clone());
         let pools = client.top_pools(cli.list_top_pools).await?;
         info!("Fetched {} pools", pools.len());
+        let ids: Vec<&str> = pools.iter().map(|p| p.id.as_str()).collect();
+        // The APR column is best-effort; the listing doesn't depend on it
+        let volumes = client.volumes_24h(&ids).await.unwrap_or_else(|e| {
+            warn!("24h volumes unavailable: {:#}", e);
+            Default::default()
+        });
         for (i, p) in pools.iter().enumerate() {
+            let apr = volumes.get(&p.id.to_lowercase()).and_then(|volume| {
+                metrics::pool_fee_apr(p.fee_tier.parse().ok()?, *volume, p.total_value_locked_usd.parse().ok()?)
+            });
             info!(
-                "{}. {} | {}-{} | TVL(USD): {} | Volume(USD): {}",
+                "{}. {} | {}-{} | TVL: {} | Volume: {} | Fee APR: {}",
                 i + 1,
                 p.id,
                 p.token0.symbol,
                 p.token1.symbol,
-                p.total_value_locked_usd,
-                p.volume_usd
+                utils::format_usd_compact_str(&p.total_value_locked_usd),
+                utils::format_usd_compact_str(&p.volume_usd),
+                apr.map_or_else(|| "n/a".to_string(), |apr| format!("{:.2}%", apr * 100.0))
             );
         }
         return Ok(());
     }
 
-    // Background task: quote configured Uniswap pools periodically
-    if let Some(uniswap_cfg) = &config.uniswap {
-        let client = UniswapClient::from_config(&config);
+    // Transaction signer (keystore passphrase may be prompted for here)
+    let signer = signer::from_config(&config).await?;
+    if let Some(signer) = &signer {
+        info!(signer = signer.name(), address = ?signer.address(), "Transaction signing enabled");
+    }
+
+    if let Some(Command::ExitAll { owner, confirm }) = &cli.command {
+        return exit_all(&config, signer, cache, owner, *confirm).await;
+    }
+
+    // Initialize position recommender
+    let server_cfg = config.get_server_config().cloned();
+    let shared_config = Arc::new(config.clone());
+    let mut recommender = PositionRecommender::new(config).await?;
+
+    // Keep alerts (range, fees, executor outcomes) in storage across restarts
+    tokio::spawn(storage::record_alerts(recommender.shared_state().storage(), recommender.event_bus()));
+
+    // Hash-chained audit log of recommendations, alerts and sent transactions
+    let audit_log = match &shared_config.audit {
+        Some(audit_cfg) => {
+            let storage = recommender.shared_state().storage();
+            if audit_cfg.verify_on_startup {
+                match audit::verify_storage(storage.as_ref()).await {
+                    Ok((records, head)) => info!(records, head = %head, "Audit log verified"),
+                    Err(e) => error!("Audit log verification FAILED: {:#}", e),
+                }
+            }
+            let audit_log = AuditLog::new(storage);
+            tokio::spawn(audit::record_events(audit_log.clone(), recommender.event_bus()));
+            Some(audit_log)
+        }
+        None => None,
+    };
+
+    // Quote pipeline: one fetcher publishes pool quotes; logging, storage and the
+    // recommender's market data subscribe here, the notifier and API further down
+    let quote_bus = QuoteBus::new();
+    if let Some(uniswap_cfg) = &shared_config.uniswap {
         let pool_ids = uniswap_cfg.pool_ids.clone();
         let position_ids = uniswap_cfg.position_ids.clone();
-        let interval = uniswap_cfg.quote_interval_secs;
         if !pool_ids.is_empty() || !position_ids.is_empty() {
-            tokio::spawn(async move {
-                loop {
-                    // Quote pools by id
-                    for pid in &pool_ids {
-                        match client.get_pool_by_id(pid).await {
-                            Ok(Some(pool)) => {
-                                println!(
-                                    "[UNISWAP] Pool {} | {}-{} | TVL(USD): {} | Volume(USD): {}",
-                                    pool.id,
-                                    pool.token0.symbol,
-                                    pool.token1.symbol,
-                                    pool.total_value_locked_usd,
-                                    pool.volume_usd
-                                );
-                            }
-                            Ok(None) => println!("[UNISWAP] Pool {} not found", pid),
-                            Err(e) => println!("[UNISWAP] Error fetching pool {}: {}", pid, e),
-                        }
-                    }
+            tokio::spawn(quotes::log_quotes(quote_bus.clone()));
+            tokio::spawn(quotes::store_quotes(recommender.shared_state().storage(), quote_bus.clone()));
+            tokio::spawn(quotes::track_latest(recommender.latest_quotes(), quote_bus.clone()));
+            let client = UniswapClient::from_config(&shared_config).with_cache(cache.clone());
+            let fetcher = QuoteFetcher::new(client, pool_ids, position_ids, shared_config.uniswap_quote_schedule()?, quote_bus.clone());
+            tokio::spawn(fetcher.run());
+        }
+    }
 
-                    // Quote pools by position id (resolve to pool)
-                    for pos_id in &position_ids {
-                        match client.get_pool_by_position_id(pos_id).await {
-                            Ok(Some(pool)) => {
-                                println!(
-                                    "[UNISWAP] Position {} -> Pool {} | {}-{} | TVL(USD): {} | Volume(USD): {}",
-                                    pos_id,
-                                    pool.id,
-                                    pool.token0.symbol,
-                                    pool.token1.symbol,
-                                    pool.total_value_locked_usd,
-                                    pool.volume_usd
-                                );
-                            }
-                            Ok(None) => println!("[UNISWAP] Position {} not found", pos_id),
-                            Err(e) => println!("[UNISWAP] Error fetching position {}: {}", pos_id, e),
-                        }
-                    }
+    // Background task: token prices, market caps, volumes and volatility into the market
+    // data the recommender (and the predictor, given the same handle) reads
+    if let Some(provider) = CoinGeckoProvider::from_config(&shared_config) {
+        let refresher = MarketDataRefresher::new(provider, recommender.market_data(), shared_config.market_refresh_schedule()?);
+        tokio::spawn(refresher.run());
+    }
+
+    // Background task: archive pool quotes, token prices and swaps as Parquet
+    if let Some(archive_cfg) = &shared_config.archive {
+        let (pool_ids, position_ids) = shared_config
+            .uniswap
+            .as_ref()
+            .map(|u| (u.pool_ids.clone(), u.position_ids.clone()))
+            .unwrap_or_default();
+        if pool_ids.is_empty() && position_ids.is_empty() {
+            warn!("[archive] is set but [uniswap] lists no pools or positions to archive");
+        } else {
+            let client = UniswapClient::from_config(&shared_config).with_cache(cache.clone());
+            info!(dir = %archive_cfg.dir, "Archiving market history to Parquet");
+            tokio::spawn(ArchiveWriter::new(client, archive_cfg, pool_ids, position_ids).run());
+        }
+    }
+
+    // Outgoing webhooks for new/changed recommendations
+    if let Some(webhook_cfg) = shared_config.webhooks.clone().filter(|w| !w.urls.is_empty()) {
+        let sink = WebhookSink::new(webhook_cfg);
+        tokio::spawn(sink.run(recommender.event_bus()));
+    }
+
+    // Human-facing notifications (Discord, ...)
+    if let Some(notification_cfg) = shared_config.notifications.as_ref().filter(|_| shared_config.notifications_enabled()) {
+        let notifiers = notifier::from_config(notification_cfg);
+        if !notifiers.is_empty() {
+            let mut dispatcher = notifier::Dispatcher::new(
+                notifiers,
+                notifier::Templates::from_config(notification_cfg, chain.explorer_url.as_deref()),
+                notifier::Router::from_config(notification_cfg)?,
+            );
+            if let Some(digest_cfg) = &notification_cfg.digest {
+                let schedule = shared_config.notification_digest_schedule()?;
+                dispatcher = dispatcher.with_digest(notifier::Digest::new(digest_cfg.max_severity, schedule));
+            }
+            if let Some(pct) = notification_cfg.tvl_drop_alert_pct {
+                dispatcher = dispatcher.with_quotes(quote_bus.clone(), pct);
+            }
+            tokio::spawn(dispatcher.run(recommender.event_bus()));
+        }
+    }
+
+    // Publish recommender events to Kafka/NATS
+    if let Some(bus_cfg) = shared_config.get_event_bus_config().cloned() {
+        match event_publisher::connect(&bus_cfg).await {
+            Ok(publisher) => {
+                tokio::spawn(event_publisher::run(publisher, bus_cfg, recommender.event_bus(), quote_bus.clone()));
+            }
+            Err(e) => error!("Event bus publisher disabled: {:#}", e),
+        }
+    }
+
+    // Position alerts (out of range, fees); mutes are shared with the admin API
+    let alerts_cfg = shared_config.alerts.clone().unwrap_or_default();
+    let mutes = PositionMutes::new(alerts_cfg.muted_positions.clone());
+    let position_ids = shared_config.uniswap.as_ref().map(|u| u.position_ids.clone()).unwrap_or_default();
+    if (alerts_cfg.range.is_some() || alerts_cfg.fees.is_some()) && position_ids.is_empty() {
+        warn!("Position alerts enabled but uniswap.position_ids is empty");
+    } else {
+        let client = UniswapClient::from_config(&shared_config).with_cache(cache.clone());
+        if let Some(range_cfg) = &alerts_cfg.range {
+            let monitor = RangeMonitor::new(
+                client.clone(),
+                &chain.rpc_url,
+                position_ids.clone(),
+                range_cfg,
+                mutes.clone(),
+                recommender.alert_state(),
+                recommender.event_bus(),
+            );
+            tokio::spawn(monitor.run());
+        }
+        if let Some(fee_cfg) = &alerts_cfg.fees {
+            if fee_cfg.min_usd.is_none() && fee_cfg.gas_multiple.is_none() {
+                warn!("Fee alerts need min_usd or gas_multiple; disabled");
+            } else {
+                let monitor = FeeMonitor::new(
+                    client,
+                    &chain.rpc_url,
+                    position_ids,
+                    fee_cfg,
+                    mutes.clone(),
+                    recommender.alert_state(),
+                    recommender.event_bus(),
+                );
+                tokio::spawn(monitor.run());
+            }
+        }
+    }
+
+    // PnL ledger: value tracked positions and record fee collections and rebalances
+    if let Some(pnl_cfg) = &shared_config.pnl {
+        let position_ids = shared_config.uniswap.as_ref().map(|u| u.position_ids.clone()).unwrap_or_default();
+        let recorder = PnlRecorder::new(
+            UniswapClient::from_config(&shared_config).with_cache(cache.clone()),
+            &chain.rpc_url,
+            recommender.shared_state().storage(),
+            pnl_cfg,
+            position_ids,
+            recommender.event_bus(),
+        );
+        tokio::spawn(recorder.run());
+    }
 
-                    tokio::time::sleep(std::time::Duration::from_secs(interval)).await;
+    // Derived metric time series for the configured pools and positions
+    if let Some(metrics_cfg) = &shared_config.metrics {
+        let (pool_ids, position_ids) = shared_config
+            .uniswap
+            .as_ref()
+            .map(|u| (u.pool_ids.clone(), u.position_ids.clone()))
+            .unwrap_or_default();
+        let recorder = MetricsRecorder::new(
+            recommender.shared_state().storage(),
+            recommender.alert_state(),
+            metrics_cfg,
+            pool_ids,
+            position_ids,
+        )
+        .with_rpc_endpoints(RpcEndpoints::for_chain(&chain));
+        tokio::spawn(recorder.run());
+    }
+
+    // Scheduled portfolio reports (JSON/CSV, local directory and/or S3)
+    if let Some(reports_cfg) = &shared_config.reports {
+        let exporter = ReportExporter::new(recommender.shared_state().storage(), reports_cfg, shared_config.report_schedule()?);
+        tokio::spawn(exporter.run());
+    }
+
+    // AI training data: label recorded positions with realized returns, retrain on them
+    #[cfg(feature = "ml")]
+    if let Some(ai_cfg) = &shared_config.ai {
+        let collector = TrainingCollector::new(recommender.shared_state().storage(), recommender.market_data(), ai_cfg);
+        tokio::spawn(collector.run());
+        let predictor = AIPredictor::new((*shared_config).clone()).with_market_data(recommender.market_data());
+        tokio::spawn(Retrainer::new(predictor, ai_cfg, shared_config.retraining_schedule()?).run());
+    }
+    #[cfg(not(feature = "ml"))]
+    if shared_config.ai.is_some() {
+        warn!("[ai] is set but this build lacks the `ml` feature; no training data or retraining");
+    }
+
+    // Paper trading: follow recommendations with a simulated wallet, no signer needed
+    if let Some(paper_cfg) = shared_config.execution.as_ref().and_then(|e| e.paper.as_ref()) {
+        let trader = PaperTrader::new(
+            UniswapClient::from_config(&shared_config).with_cache(cache.clone()),
+            &chain.rpc_url,
+            recommender.shared_state().storage(),
+            paper_cfg,
+            recommender.event_bus(),
+        );
+        tokio::spawn(trader.run());
+    }
+
+    // On-chain execution: fee collection and rebalancing with the configured signer
+    let mut rebalance_handle = None;
+    if let Some(execution_cfg) = &shared_config.execution {
+        let wanted = execution_cfg.auto_collect_fees || execution_cfg.rebalance.is_some();
+        match (&signer, &shared_config.security) {
+            (Some(signer), Some(security)) if wanted => {
+                let mut tracker = TxTracker::new(
+                    &chain.rpc_url,
+                    recommender.shared_state().storage(),
+                    execution_cfg.finality_blocks,
+                );
+                if let Some(audit_log) = &audit_log {
+                    tracker = tracker.with_audit(audit_log.clone());
                 }
-            });
+                tokio::spawn(tracker.clone().run());
+                let sender = Arc::new(
+                    TxSender::new(
+                        &chain.rpc_url,
+                        signer.clone(),
+                        UniswapClient::from_config(&shared_config).with_cache(cache.clone()),
+                        execution_cfg,
+                        &security.gas_settings,
+                    )?
+                    .with_tracker(tracker)
+                    .with_storage(recommender.shared_state().storage())
+                    .await?,
+                );
+                if execution_cfg.auto_collect_fees {
+                    if alerts_cfg.fees.is_some() {
+                        let collector = FeeCollector::new(
+                            sender.clone(),
+                            UniswapClient::from_config(&shared_config).with_cache(cache.clone()),
+                            &chain.rpc_url,
+                            recommender.event_bus(),
+                        );
+                        tokio::spawn(collector.run());
+                    } else {
+                        warn!("execution.auto_collect_fees needs [alerts.fees]; disabled");
+                    }
+                }
+                if execution_cfg.rebalance.is_some() {
+                    let client = UniswapClient::from_config(&shared_config).with_cache(cache.clone());
+                    let allowances = AllowanceManager::new(
+                        sender.clone(),
+                        client.clone(),
+                        &chain.rpc_url,
+                        &execution_cfg.approvals,
+                    );
+                    let (rebalancer, handle) = Rebalancer::new(
+                        sender,
+                        client,
+                        &chain.rpc_url,
+                        recommender.shared_state().storage(),
+                        execution_cfg,
+                        recommender.event_bus(),
+                        allowances,
+                    )?;
+                    tokio::spawn(rebalancer.run());
+                    rebalance_handle = Some(handle);
+                }
+            }
+            _ if wanted => warn!("[execution] needs transaction signing enabled in [security]; disabled"),
+            _ => {}
         }
     }
-    
-    // Initialize position recommender
-    let mut recommender = PositionRecommender::new(config).await?;
+
+    // Probe the RPC endpoints' head blocks for their health scores
+    if let Some(rpc_health_cfg) = &shared_config.rpc_health {
+        let endpoints = RpcEndpoints::for_chain(&chain);
+        info!("Scoring {} RPC endpoint(s) every {}s", endpoints.len(), rpc_health_cfg.probe_interval_secs);
+        tokio::spawn(RpcHealthMonitor::new(endpoints, rpc_health_cfg).run());
+    }
+
+    // Page on-call for system failures (separate from trading notifications)
+    if let Some(ops_cfg) = &shared_config.ops_alerts {
+        let monitor = OpsMonitor::new(
+            ops_alerts::pager_from_config(ops_cfg),
+            UniswapClient::from_config(&shared_config),
+            &chain.rpc_url,
+            recommender.health_state(),
+            ops_cfg,
+        );
+        tokio::spawn(monitor.run());
+    }
+
+    // Server mode: expose health/readiness probes alongside the loop
+    #[cfg(feature = "server")]
+    if let Some(server_cfg) = server_cfg {
+        let mut state = AppState::new(
+            shared_config,
+            recommender.health_state(),
+            recommender.shared_state(),
+            recommender.event_bus(),
+            cache,
+            recommender.cycle_trigger(),
+            mutes,
+        )?
+        .with_quotes(recommender.latest_quotes());
+        if let Some(handle) = rebalance_handle {
+            state = state.with_rebalancer(handle);
+        }
+        tokio::spawn(async move {
+            if let Err(e) = server::serve(&server_cfg, state).await {
+                error!("HTTP server stopped: {}", e);
+            }
+        });
+    }
+    #[cfg(not(feature = "server"))]
+    if server_cfg.is_some() {
+        let _ = (mutes, rebalance_handle);
+        warn!("[server] is set but this build lacks the `server` feature; no HTTP server");
+    }
     
     // Run the recommender
     recommender.run().await?;
@@ -158,3 +623,251 @@ async fn main() -> Result<()> {
     info!("Position recommender completed successfully");
     Ok(())
 }
+
+/// One `--position-id` / `--owner` result line; raw token amounts in whole units
+fn print_onchain_position(pos: &OnchainPosition) {
+    let whole = |raw: &str, decimals: u8| {
+        U256::from_dec_str(raw)
+            .ok()
+            .and_then(|raw| utils::from_base_units(raw, decimals).ok())
+            .map_or_else(|| raw.to_string(), |amount| amount.normalize().to_string())
+    };
+    println!(
+        "[UNISWAP ONCHAIN] tokenId={} {}({})-{}({}) fee={} pool={} tickRange=[{},{}] currentTick={} inRange={} priceRange[{} per {}]=[{}, {}] midPrice={} currentPrice={} liquidity={} amount0={} amount1={} owed0={} owed1={} uncollectedFees0={} uncollectedFees1={}",
+        pos.token_id,
+        pos.token0_symbol,
+        pos.token0,
+        pos.token1_symbol,
+        pos.token1,
+        pos.fee,
+        pos.pool,
+        pos.tick_lower,
+        pos.tick_upper,
+        pos.current_tick,
+        pos.in_range,
+        pos.token1_symbol,
+        pos.token0_symbol,
+        pos.price_lower_quote_per_base,
+        pos.price_upper_quote_per_base,
+        pos.mid_price_quote_per_base,
+        pos.current_price,
+        pos.liquidity,
+        whole(&pos.amount0, pos.token0_decimals),
+        whole(&pos.amount1, pos.token1_decimals),
+        whole(&pos.tokens_owed0, pos.token0_decimals),
+        whole(&pos.tokens_owed1, pos.token1_decimals),
+        whole(&pos.uncollected_fees0, pos.token0_decimals),
+        whole(&pos.uncollected_fees1, pos.token1_decimals)
+    );
+}
+
+/// `verify-audit`: walk the stored audit chain and fail on the first broken link
+async fn verify_audit(config: &Config) -> Result<()> {
+    let storage = storage::connect(config.storage.as_ref()).await?;
+    let (records, head) = audit::verify_storage(storage.as_ref()).await.context("audit log verification failed")?;
+    println!("Audit log intact: {} records, head {}", records, head);
+    Ok(())
+}
+
+/// `export-report`: write the report for the last complete period without waiting for the schedule
+async fn export_report(config: &Config) -> Result<()> {
+    let reports_cfg = config.reports.as_ref().context("export-report needs a [reports] section")?;
+    let storage = storage::connect(config.storage.as_ref()).await?;
+    let exporter = ReportExporter::new(storage, reports_cfg, config.report_schedule()?);
+    println!("Exported {}", exporter.export(Utc::now()).await?);
+    Ok(())
+}
+
+/// `backfill`: fetch a pool's history into storage from `from` up to now
+async fn run_backfill(config: &Config, cache: Cache, pool: &str, from: chrono::NaiveDate) -> Result<()> {
+    let storage = storage::connect(config.storage.as_ref()).await?;
+    backfill::require_persistent(storage.as_ref())?;
+    let client = UniswapClient::from_config(config).with_cache(cache);
+    let from = from.and_time(chrono::NaiveTime::MIN).and_utc();
+    let summary = Backfill::new(client, storage).run(pool, from).await?;
+    for (dataset, rows, cursor) in &summary.rows {
+        println!("{}: {} rows stored, up to {}", dataset.as_str(), rows, cursor);
+    }
+    Ok(())
+}
+
+/// `metrics`: print one metric's stored points
+async fn print_metrics(
+    config: &Config,
+    metric: MetricKind,
+    subject: Option<String>,
+    since: Option<chrono::NaiveDate>,
+    limit: usize,
+) -> Result<()> {
+    let storage = storage::connect(config.storage.as_ref()).await?;
+    let query = MetricQuery {
+        metric: Some(metric),
+        subject,
+        since: since.map(|day| day.and_time(chrono::NaiveTime::MIN).and_utc()),
+        until: None,
+        limit,
+    };
+    let points = storage.metrics(&query).await?;
+    if points.is_empty() {
+        println!("No {} points stored", metric.as_str());
+    }
+    for point in &points {
+        println!("{} {} {}", point.timestamp.to_rfc3339(), point.subject, point.value);
+    }
+    Ok(())
+}
+
+/// `history`: print stored recommendations matching `query`
+async fn print_history(config: &Config, query: &HistoryQuery) -> Result<()> {
+    let storage = storage::connect(config.storage.as_ref()).await?;
+    let records = storage.history(query).await?;
+    if records.is_empty() {
+        println!("No recommendations stored");
+    }
+    for record in &records {
+        let rec = &record.recommendation;
+        println!(
+            "{} cycle {} {} {:?} score={:.2} value={} {}",
+            record.timestamp.to_rfc3339(),
+            record.cycle,
+            rec.position.id,
+            rec.suggested_action,
+            rec.recommendation_score,
+            utils::format_usd(&rec.position.value_usd),
+            rec.reasoning
+        );
+    }
+    Ok(())
+}
+
+/// `recommend range`: print the tick range suggested for a new position in `pool_id`
+async fn print_range(client: &UniswapClient, rpc_url: &str, pool_id: &str, horizon_days: u64) -> Result<()> {
+    let horizon = std::time::Duration::from_secs(horizon_days * 86_400);
+    let RangeRecommendation { pool, inputs, suggestion } = range_optimizer::recommend_range(client, rpc_url, pool_id, horizon).await?;
+    let (decimals0, decimals1) = (pool.token0.decimals.parse()?, pool.token1.decimals.parse()?);
+    let price = |tick: i32| {
+        utils::uniswap_v3::price_at_tick(tick, decimals0, decimals1).map_or_else(|_| "n/a".to_string(), |p| p.round_sf(6).unwrap_or(p).to_string())
+    };
+    let percent = |share: f64| format!("{:.2}%", share * 100.0);
+    println!("{} {}/{} ({} bps), {} day horizon", pool.id, pool.token0.symbol, pool.token1.symbol, inputs.fee_tier / 100, horizon_days);
+    println!("Current tick {} ({} {} per {})", inputs.current_tick, price(inputs.current_tick), pool.token1.symbol, pool.token0.symbol);
+    println!(
+        "Suggested range: ticks {} to {} ({} to {})",
+        suggestion.tick_lower,
+        suggestion.tick_upper,
+        price(suggestion.tick_lower),
+        price(suggestion.tick_upper)
+    );
+    println!(
+        "Volatility {} a year, {} over the horizon; {} of the horizon expected in range",
+        percent(inputs.volatility),
+        percent(suggestion.horizon_volatility),
+        percent(suggestion.in_range_share)
+    );
+    println!(
+        "Expected fees {} - impermanent loss {} = {} of the deposit",
+        percent(suggestion.expected_fees),
+        percent(-suggestion.expected_il),
+        percent(suggestion.net_return())
+    );
+    Ok(())
+}
+
+/// `keyring set|delete`: store or remove an OS keyring credential
+fn manage_keyring(action: &KeyringAction) -> Result<()> {
+    match action {
+        KeyringAction::Set { name } => {
+            let secret = if std::io::stdin().is_terminal() {
+                rpassword::prompt_password(format!("Value for '{}': ", name)).context("reading the credential")?
+            } else {
+                let mut line = String::new();
+                std::io::stdin().lock().read_line(&mut line)?;
+                line.trim_end_matches(['\r', '\n']).to_string()
+            };
+            if secret.is_empty() {
+                bail!("refusing to store an empty credential");
+            }
+            credentials::set(name, &secret)?;
+            println!("Stored '{}' in the OS keyring; reference it as keyring:{}", name, name);
+        }
+        KeyringAction::Delete { name } => {
+            if credentials::delete(name)? {
+                println!("Deleted '{}' from the OS keyring", name);
+            } else {
+                println!("No keyring entry '{}'", name);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// `exit-all`: simulate and summarize the exits, then send them once confirmed
+async fn exit_all(config: &Config, signer: Option<Arc<dyn signer::Signer>>, cache: Cache, owner: &str, confirm: bool) -> Result<()> {
+    let owner = owner.parse().with_context(|| format!("invalid owner address {}", owner))?;
+    let (Some(signer), Some(security)) = (signer, config.security.as_ref()) else {
+        bail!("exit-all needs transaction signing enabled in [security]");
+    };
+    // Exits always simulate first and, once confirmed, skip the startup dry-run window
+    let mut execution_cfg = config.execution.clone().context("exit-all needs an [execution] section")?;
+    execution_cfg.simulation.enabled = true;
+    execution_cfg.policy.dry_run_secs = 0;
+    let client = UniswapClient::from_config(config).with_cache(cache);
+    let rpc_url = config.active_chain().rpc_url;
+    let storage = storage::connect(config.storage.as_ref()).await?;
+    let sender = TxSender::new(&rpc_url, signer, client.clone(), &execution_cfg, &security.gas_settings)?;
+    let sender = Arc::new(sender.with_storage(storage.clone()).await?);
+    let exit = EmergencyExit::new(sender, client, &rpc_url, &execution_cfg)?;
+    if exit.account() != owner {
+        bail!("exit-all: owner {:?} is not the executing account {:?}", owner, exit.account());
+    }
+    let configured = config.uniswap.as_ref().map(|u| u.position_ids.clone()).unwrap_or_default();
+    let position_ids = tracked_position_ids(storage.as_ref(), &configured).await?;
+    let plan = exit.plan(owner, &position_ids).await?;
+    if plan.is_empty() {
+        println!("No tracked positions of {:?} hold liquidity or fees", owner);
+        return Ok(());
+    }
+    println!("{}", exit_summary(&plan));
+    if !confirm {
+        println!("Simulation only; re-run with --confirm to send the transactions");
+        return Ok(());
+    }
+    if std::io::stdin().is_terminal() {
+        print!("Withdraw everything from these {} positions? Type 'exit' to continue: ", plan.len());
+        std::io::stdout().flush()?;
+        let mut answer = String::new();
+        std::io::stdin().lock().read_line(&mut answer)?;
+        if answer.trim() != "exit" {
+            println!("Aborted");
+            return Ok(());
+        }
+    }
+    let audit_log = config.audit.as_ref().map(|_| AuditLog::new(storage.clone()));
+    let outcomes = exit.execute(&plan).await;
+    let failed = outcomes.iter().filter(|(_, outcome)| outcome.is_err()).count();
+    for ((position_id, outcome), position) in outcomes.iter().zip(&plan) {
+        if let Some(audit_log) = &audit_log {
+            let payload = serde_json::json!({
+                "owner": format!("{:?}", owner),
+                "exited": outcome.is_ok(),
+                "error": outcome.as_ref().err().map(|e| format!("{:#}", e)),
+            });
+            if let Err(e) = audit_log.append("emergency_exit", Some(position_id), &payload).await {
+                warn!("Exit of {} not recorded in the audit log: {:#}", position_id, e);
+            }
+        }
+        match outcome {
+            Ok(()) => {
+                println!("{}: exited", position_id);
+                if let Err(e) = pnl::record_exit(storage.as_ref(), &format!("{:?}", owner), position).await {
+                    warn!("Exit of {} not recorded in the PnL ledger: {:#}", position_id, e);
+                }
+            }
+            Err(e) => println!("{}: FAILED {:#}", position_id, e),
+        }
+    }
+    if failed > 0 {
+        bail!("exit-all: {} of {} positions failed", failed, outcomes.len());
+    }
+    Ok(())
+}
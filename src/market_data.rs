@@ -0,0 +1,285 @@
+//! Token market data from CoinGecko: price, market cap and 24h volume by contract address,
+//! and annualized volatility from each token's recent daily closes. A background
+//! [`MarketDataRefresher`] keeps the [`SharedMarketData`] the recommender's risk and
+//! liquidity scores and the predictor's features read from up to date.
+
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+
+use anyhow::{Context, Result};
+use reqwest::{Client, RequestBuilder};
+use serde::Deserialize;
+use tracing::{debug, info, warn};
+
+use crate::config::Config;
+use crate::position::{MarketData, TokenData};
+use crate::scheduler::Schedule;
+
+/// Market data written by the refresher and read by the recommender and predictor
+pub type SharedMarketData = Arc<RwLock<MarketData>>;
+
+/// Contract addresses per `simple/token_price` request
+const PRICE_BATCH: usize = 50;
+
+/// One token's entry in a `simple/token_price` response
+#[derive(Debug, Clone, Default, Deserialize)]
+struct TokenPrice {
+    usd: Option<f64>,
+    usd_market_cap: Option<f64>,
+    usd_24h_vol: Option<f64>,
+}
+
+#[derive(Debug, Deserialize)]
+struct MarketChart {
+    /// `[timestamp_ms, price]` pairs
+    prices: Vec<(f64, f64)>,
+}
+
+/// One token's latest CoinGecko data; what it didn't report is `None`
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TokenMarket {
+    pub price_usd: Option<f64>,
+    pub market_cap: Option<f64>,
+    pub volume_24h: Option<f64>,
+    /// Annualized, from daily closes
+    pub volatility: Option<f64>,
+}
+
+pub struct CoinGeckoProvider {
+    http: Client,
+    base_url: String,
+    api_key: Option<String>,
+    platform: String,
+    tokens: Vec<String>,
+    volatility_days: u32,
+}
+
+impl CoinGeckoProvider {
+    /// The provider for `[market_data]`; `None` unless its `price_sources` list "coingecko"
+    pub fn from_config(config: &Config) -> Option<Self> {
+        let market = config.market_data.as_ref()?;
+        if !market.price_sources.iter().any(|s| s.eq_ignore_ascii_case("coingecko")) {
+            return None;
+        }
+        let api = config.api.as_ref();
+        Some(Self {
+            http: crate::http::shared(),
+            base_url: api
+                .map_or("https://api.coingecko.com/api/v3", |a| a.coingecko_api_url.as_str())
+                .trim_end_matches('/')
+                .to_string(),
+            api_key: api.and_then(|a| a.coingecko_api_key.clone()),
+            platform: market.coingecko_platform.clone(),
+            tokens: market.tokens.iter().map(|t| t.to_lowercase()).collect(),
+            volatility_days: market.volatility_days,
+        })
+    }
+
+    /// The configured tokens, or `fallback` (the tracked positions' tokens) when none are
+    pub fn tokens<'a>(&'a self, fallback: &'a [String]) -> &'a [String] {
+        if self.tokens.is_empty() {
+            fallback
+        } else {
+            &self.tokens
+        }
+    }
+
+    /// The latest data of each of `tokens` CoinGecko lists, keyed by lowercase address
+    pub async fn fetch(&self, tokens: &[String]) -> Result<HashMap<String, TokenMarket>> {
+        let mut tokens: Vec<String> = tokens.iter().map(|t| t.to_lowercase()).collect();
+        tokens.sort();
+        tokens.dedup();
+        let prices = self.token_prices(&tokens).await?;
+        let mut fetched = HashMap::with_capacity(prices.len());
+        for (token, price) in prices {
+            let volatility = match self.daily_closes(&token).await {
+                Ok(closes) => annualized_volatility(&closes),
+                Err(e) => {
+                    warn!(target: "market_data", token = %token, "reading CoinGecko price history failed: {:#}", e);
+                    None
+                }
+            };
+            let market = TokenMarket {
+                price_usd: price.usd,
+                market_cap: price.usd_market_cap,
+                volume_24h: price.usd_24h_vol,
+                volatility,
+            };
+            debug!(target: "market_data", token = %token, ?market, "CoinGecko market data");
+            fetched.insert(token, market);
+        }
+        info!(target: "market_data", requested = tokens.len(), listed = fetched.len(), "fetched CoinGecko market data");
+        Ok(fetched)
+    }
+
+    /// Price, market cap and 24h volume of each listed token, keyed by lowercase address
+    async fn token_prices(&self, tokens: &[String]) -> Result<HashMap<String, TokenPrice>> {
+        let mut prices = HashMap::new();
+        for chunk in tokens.chunks(PRICE_BATCH) {
+            let url = format!("{}/simple/token_price/{}", self.base_url, self.platform);
+            let query = [
+                ("contract_addresses", chunk.join(",")),
+                ("vs_currencies", "usd".to_string()),
+                ("include_market_cap", "true".to_string()),
+                ("include_24hr_vol", "true".to_string()),
+            ];
+            let listed: HashMap<String, TokenPrice> = self
+                .get(&url)
+                .query(&query)
+                .send()
+                .await?
+                .error_for_status()?
+                .json()
+                .await
+                .context("reading CoinGecko token prices")?;
+            prices.extend(listed.into_iter().map(|(token, price)| (token.to_lowercase(), price)));
+        }
+        Ok(prices)
+    }
+
+    /// Daily USD closes of `token` over the last `volatility_days`, oldest first
+    async fn daily_closes(&self, token: &str) -> Result<Vec<f64>> {
+        let url = format!("{}/coins/{}/contract/{}/market_chart", self.base_url, self.platform, token);
+        let query = [
+            ("vs_currency", "usd".to_string()),
+            ("days", self.volatility_days.to_string()),
+            ("interval", "daily".to_string()),
+        ];
+        let chart: MarketChart = self
+            .get(&url)
+            .query(&query)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await
+            .context("reading CoinGecko market chart")?;
+        Ok(chart.prices.into_iter().map(|(_, price)| price).collect())
+    }
+
+    fn get(&self, url: &str) -> RequestBuilder {
+        let request = self.http.get(url);
+        match &self.api_key {
+            Some(key) if self.base_url.contains("pro-api") => request.header("x-cg-pro-api-key", key),
+            Some(key) => request.header("x-cg-demo-api-key", key),
+            None => request,
+        }
+    }
+}
+
+/// Refreshes the shared market data on the `market_refresh` schedule (every
+/// `market_data_refresh_interval` seconds by default)
+pub struct MarketDataRefresher {
+    provider: CoinGeckoProvider,
+    market_data: SharedMarketData,
+    schedule: Schedule,
+}
+
+impl MarketDataRefresher {
+    pub fn new(provider: CoinGeckoProvider, market_data: SharedMarketData, schedule: Schedule) -> Self {
+        Self {
+            provider,
+            market_data,
+            schedule,
+        }
+    }
+
+    pub async fn run(self) {
+        info!(target: "market_data", "refreshing market data");
+        loop {
+            self.refresh().await;
+            self.schedule.tick().await;
+        }
+    }
+
+    async fn refresh(&self) {
+        let tracked: Vec<String> = {
+            let market_data = self.market_data.read().expect("market data lock poisoned");
+            market_data.tracked_tokens.iter().cloned().collect()
+        };
+        let tokens = self.provider.tokens(&tracked);
+        if tokens.is_empty() {
+            debug!(target: "market_data", "no tokens to refresh yet");
+            return;
+        }
+        match self.provider.fetch(tokens).await {
+            Ok(fetched) => apply(&mut self.market_data.write().expect("market data lock poisoned"), fetched),
+            Err(e) => warn!(target: "market_data", "refreshing CoinGecko market data failed: {:#}", e),
+        }
+    }
+}
+
+/// Merge `fetched` into `market_data`: what CoinGecko didn't report, and the pool depth it
+/// never does, keeps its current value
+fn apply(market_data: &mut MarketData, fetched: HashMap<String, TokenMarket>) {
+    for (token, market) in fetched {
+        let data = TokenData {
+            volatility: market.volatility.unwrap_or_else(|| market_data.get_volatility(&token)),
+            market_cap: market.market_cap.unwrap_or_else(|| market_data.get_market_cap(&token)),
+            volume: market.volume_24h.unwrap_or_else(|| market_data.get_volume(&token)),
+            depth: market_data.get_depth(&token),
+            price_usd: market.price_usd.or_else(|| market_data.get_price(&token)),
+        };
+        market_data.token_data.insert(token, data);
+    }
+}
+
+/// Standard deviation of daily log returns scaled to a year; `None` with fewer than three
+/// positive closes
+fn annualized_volatility(closes: &[f64]) -> Option<f64> {
+    let returns: Vec<f64> = closes
+        .windows(2)
+        .filter(|w| w[0] > 0.0 && w[1] > 0.0)
+        .map(|w| (w[1] / w[0]).ln())
+        .collect();
+    if returns.len() < 2 {
+        return None;
+    }
+    let n = returns.len() as f64;
+    let mean = returns.iter().sum::<f64>() / n;
+    let variance = returns.iter().map(|r| (r - mean).powi(2)).sum::<f64>() / (n - 1.0);
+    Some(variance.sqrt() * 365f64.sqrt())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_annualized_volatility() {
+        assert_eq!(annualized_volatility(&[100.0; 10]), Some(0.0));
+        // Alternating ±ln(1.01) daily moves: a sample deviation of about 1% a day, 19% a year
+        let closes: Vec<f64> = (0..31).map(|i| 100.0 * if i % 2 == 0 { 1.0 } else { 1.01f64 }).collect();
+        let volatility = annualized_volatility(&closes).unwrap();
+        assert!((volatility - 0.0099503 * (30.0f64 / 29.0).sqrt() * 365f64.sqrt()).abs() < 1e-4, "{}", volatility);
+        assert_eq!(annualized_volatility(&[100.0, 101.0]), None);
+        assert_eq!(annualized_volatility(&[]), None);
+    }
+
+    #[test]
+    fn test_responses_parse() {
+        let prices: HashMap<String, TokenPrice> = serde_json::from_str(
+            r#"{"0xa0b86991c6218b36c1d19d4a2e9eb0ce3606eb48": {"usd": 0.9998, "usd_market_cap": 33000000000.5, "usd_24h_vol": 4100000000.0}}"#,
+        )
+        .unwrap();
+        let usdc = &prices["0xa0b86991c6218b36c1d19d4a2e9eb0ce3606eb48"];
+        assert_eq!((usdc.usd, usdc.usd_24h_vol), (Some(0.9998), Some(4_100_000_000.0)));
+        let chart: MarketChart = serde_json::from_str(r#"{"prices": [[1700000000000, 2000.5], [1700086400000, 2010.0]], "market_caps": []}"#).unwrap();
+        assert_eq!(chart.prices[1], (1_700_086_400_000.0, 2010.0));
+    }
+
+    #[test]
+    fn test_apply_keeps_unreported_fields() {
+        let mut market_data = MarketData::new();
+        let token = "0xa0b86991c6218b36c1d19d4a2e9eb0ce3606eb48".to_string();
+        let volume_only = TokenMarket { volume_24h: Some(5_000.0), ..Default::default() };
+        apply(&mut market_data, HashMap::from([(token.clone(), volume_only)]));
+        assert_eq!(market_data.get_volume(&token), 5_000.0);
+        assert_eq!(market_data.get_volatility(&token), MarketData::new().get_volatility(&token));
+
+        let priced = TokenMarket { price_usd: Some(1.0), volatility: Some(0.02), ..Default::default() };
+        apply(&mut market_data, HashMap::from([(token.clone(), priced)]));
+        assert_eq!((market_data.get_price(&token), market_data.get_volatility(&token)), (Some(1.0), 0.02));
+        assert_eq!(market_data.get_volume(&token), 5_000.0);
+    }
+}
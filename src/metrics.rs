@@ -0,0 +1,437 @@
+//! Derived metrics stored as time series: pool fee APR and realized volatility, position
+//! value and the share of time a position spent in range. Each is derived from quotes,
+//! candles, the PnL ledger and range alerts already in storage, so trends can be read back
+//! without re-deriving them from raw data.
+
+use anyhow::{bail, Result};
+use chrono::{DateTime, Duration as ChronoDuration, Utc};
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::time::{interval, MissedTickBehavior};
+use tracing::{debug, warn};
+
+use crate::alerts::AlertState;
+use crate::backfill::HOURLY;
+use crate::config::MetricsConfig;
+use crate::events::Event;
+use crate::pnl::{LedgerQuery, PositionPnl};
+use crate::rpc_health::RpcEndpoints;
+use crate::storage::{AlertQuery, Candle, PoolQuote, Storage};
+use crate::uniswap::PoolDayData;
+use crate::utils::{calculate_volatility, periodic_yield_to_apr, SECS_PER_YEAR};
+
+/// Range alerts read back per position when deriving its in-range share
+pub(crate) const MAX_RANGE_ALERTS: usize = 1000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "server", derive(utoipa::ToSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum MetricKind {
+    /// Annualized fees earned by the pool's liquidity over the window (0.12 = 12%)
+    PoolFeeApr,
+    /// Annualized standard deviation of the pool's hourly log returns over the window
+    RealizedVol,
+    /// Latest ledger valuation of a position, in USD
+    PositionValue,
+    /// Share of the window the position spent in range (0 to 1)
+    InRangePct,
+    /// Health score of an RPC endpoint (see `rpc_health`); the subject is its scheme and host
+    RpcScore,
+}
+
+impl MetricKind {
+    pub const ALL: [MetricKind; 5] = [
+        MetricKind::PoolFeeApr,
+        MetricKind::RealizedVol,
+        MetricKind::PositionValue,
+        MetricKind::InRangePct,
+        MetricKind::RpcScore,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MetricKind::PoolFeeApr => "pool_fee_apr",
+            MetricKind::RealizedVol => "realized_vol",
+            MetricKind::PositionValue => "position_value",
+            MetricKind::InRangePct => "in_range_pct",
+            MetricKind::RpcScore => "rpc_score",
+        }
+    }
+
+    /// Whether the subject is a position (else a pool or endpoint)
+    pub fn per_position(&self) -> bool {
+        matches!(self, MetricKind::PositionValue | MetricKind::InRangePct)
+    }
+}
+
+impl FromStr for MetricKind {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match MetricKind::ALL.into_iter().find(|m| m.as_str() == s) {
+            Some(metric) => Ok(metric),
+            None => bail!("unknown metric '{}' (expected one of pool_fee_apr, realized_vol, position_value, in_range_pct, rpc_score)", s),
+        }
+    }
+}
+
+/// One value of a metric for a pool or position
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "server", derive(utoipa::ToSchema))]
+pub struct MetricPoint {
+    pub metric: MetricKind,
+    /// Pool address, position id or RPC endpoint
+    pub subject: String,
+    pub timestamp: DateTime<Utc>,
+    pub value: f64,
+}
+
+/// Filters for stored metric points
+#[derive(Debug, Clone, Default)]
+pub struct MetricQuery {
+    pub metric: Option<MetricKind>,
+    pub subject: Option<String>,
+    /// Inclusive
+    pub since: Option<DateTime<Utc>>,
+    /// Exclusive
+    pub until: Option<DateTime<Utc>>,
+    pub limit: usize,
+}
+
+impl MetricQuery {
+    pub fn matches(&self, point: &MetricPoint) -> bool {
+        self.metric.is_none_or(|metric| point.metric == metric)
+            && self.subject.as_deref().is_none_or(|subject| point.subject.eq_ignore_ascii_case(subject))
+            && self.since.is_none_or(|since| point.timestamp >= since)
+            && self.until.is_none_or(|until| point.timestamp < until)
+    }
+}
+
+/// Annualized fee yield from the cumulative volume growth between the first and last quote
+/// (fee tier in hundredths of a basis point), against the latest TVL
+pub fn fee_apr(quotes: &[PoolQuote]) -> Option<f64> {
+    let (first, last) = (quotes.first()?, quotes.last()?);
+    let elapsed = (last.timestamp - first.timestamp).num_seconds() as f64;
+    if elapsed <= 0.0 || last.tvl_usd <= 0.0 {
+        return None;
+    }
+    let fees = (last.volume_usd - first.volume_usd).max(0.0) * last.fee_tier as f64 / 1_000_000.0;
+    Some(periodic_yield_to_apr(fees / last.tvl_usd, elapsed))
+}
+
+/// Estimated fee APR of a pool: a day's fees (fee tier times 24h volume) over its TVL,
+/// annualized. The subgraph's TVL includes liquidity out of range, so this understates what
+/// in-range liquidity earns.
+pub fn pool_fee_apr(fee_tier: u32, volume_24h_usd: f64, tvl_usd: f64) -> Option<f64> {
+    if tvl_usd <= 0.0 || volume_24h_usd < 0.0 {
+        return None;
+    }
+    let daily_fees = volume_24h_usd * fee_tier as f64 / 1_000_000.0;
+    Some(periodic_yield_to_apr(daily_fees / tvl_usd, 86_400.0))
+}
+
+/// Fee APR of a pool over its daily history: each day's fees over that day's TVL, averaged
+/// and annualized; days without TVL are skipped
+pub fn day_data_fee_apr(days: &[PoolDayData]) -> Option<f64> {
+    let yields: Vec<f64> = days
+        .iter()
+        .filter_map(|day| {
+            let (fees, tvl) = (day.fees_usd.parse::<f64>().ok()?, day.tvl_usd.parse::<f64>().ok()?);
+            (tvl > 0.0 && fees >= 0.0).then(|| fees / tvl)
+        })
+        .collect();
+    if yields.is_empty() {
+        return None;
+    }
+    let daily = yields.iter().sum::<f64>() / yields.len() as f64;
+    Some(periodic_yield_to_apr(daily, 86_400.0))
+}
+
+/// Estimated fee APR of a position: while in range it earns its share of the pool's active
+/// liquidity of a day's fees; out of range it earns nothing
+pub fn position_fee_apr(
+    fee_tier: u32,
+    volume_24h_usd: f64,
+    liquidity: u128,
+    active_liquidity: u128,
+    value_usd: f64,
+    in_range: bool,
+) -> Option<f64> {
+    if value_usd <= 0.0 {
+        return None;
+    }
+    if !in_range || liquidity == 0 {
+        return Some(0.0);
+    }
+    // The position's own liquidity is part of the active liquidity while it is in range
+    let share = liquidity as f64 / active_liquidity.max(liquidity) as f64;
+    let daily_fees = volume_24h_usd * fee_tier as f64 / 1_000_000.0 * share;
+    Some(periodic_yield_to_apr(daily_fees / value_usd, 86_400.0))
+}
+
+/// Annualized volatility of the candles' close-to-close log returns
+pub fn realized_vol(candles: &[Candle]) -> Option<f64> {
+    let interval_secs = candles.first()?.interval_secs;
+    let returns: Vec<f64> = candles
+        .windows(2)
+        .filter(|w| w[0].close > 0.0 && w[1].close > 0.0)
+        .map(|w| (w[1].close / w[0].close).ln())
+        .collect();
+    if returns.len() < 2 || interval_secs == 0 {
+        return None;
+    }
+    Some(calculate_volatility(&returns) * (SECS_PER_YEAR / interval_secs as f64).sqrt())
+}
+
+/// Share of `[since, now]` spent in range, walking back from the current state through
+/// the range alerts (any order); before the earliest alert the state is the opposite of
+/// what it switched to
+pub fn in_range_share(in_range_now: bool, alerts: &[Event], since: DateTime<Utc>, now: DateTime<Utc>) -> f64 {
+    let window = (now - since).num_seconds();
+    if window <= 0 {
+        return if in_range_now { 1.0 } else { 0.0 };
+    }
+    let mut changes: Vec<(DateTime<Utc>, bool)> = alerts
+        .iter()
+        .filter_map(|event| match event {
+            Event::PositionOutOfRange { timestamp, .. } => Some((*timestamp, false)),
+            Event::PositionBackInRange { timestamp, .. } => Some((*timestamp, true)),
+            _ => None,
+        })
+        .filter(|(at, _)| *at > since && *at <= now)
+        .collect();
+    changes.sort_by_key(|(at, _)| std::cmp::Reverse(*at));
+    let (mut end, mut state, mut in_range) = (now, in_range_now, 0);
+    for (at, became) in changes {
+        if state {
+            in_range += (end - at).num_seconds();
+        }
+        end = at;
+        state = !became;
+    }
+    if state {
+        in_range += (end - since).num_seconds();
+    }
+    in_range as f64 / window as f64
+}
+
+/// Derives and stores the metrics of the configured pools and positions on an interval
+pub struct MetricsRecorder {
+    storage: Arc<dyn Storage>,
+    alerts: AlertState,
+    interval: Duration,
+    window: ChronoDuration,
+    pool_ids: Vec<String>,
+    position_ids: Vec<String>,
+    rpc: Option<RpcEndpoints>,
+}
+
+impl MetricsRecorder {
+    pub fn new(
+        storage: Arc<dyn Storage>,
+        alerts: AlertState,
+        config: &MetricsConfig,
+        pool_ids: Vec<String>,
+        position_ids: Vec<String>,
+    ) -> Self {
+        Self {
+            storage,
+            alerts,
+            interval: Duration::from_secs(config.interval_secs.max(1)),
+            window: ChronoDuration::seconds(config.window_secs.max(1) as i64),
+            pool_ids: pool_ids.into_iter().map(|id| id.to_lowercase()).collect(),
+            position_ids,
+            rpc: None,
+        }
+    }
+
+    /// Also record the health score of each of these RPC endpoints
+    pub fn with_rpc_endpoints(mut self, endpoints: RpcEndpoints) -> Self {
+        self.rpc = Some(endpoints);
+        self
+    }
+
+    pub async fn run(self) {
+        let mut ticker = interval(self.interval);
+        ticker.set_missed_tick_behavior(MissedTickBehavior::Skip);
+        loop {
+            ticker.tick().await;
+            match self.derive(Utc::now()).await {
+                Ok(points) => {
+                    debug!(target: "metrics", points = points.len(), "derived metrics");
+                    if let Err(e) = self.storage.save_metrics(&points).await {
+                        warn!(target: "metrics", "metrics not saved: {:#}", e);
+                    }
+                }
+                Err(e) => warn!(target: "metrics", "metrics not derived: {:#}", e),
+            }
+        }
+    }
+
+    /// Every metric that has enough data as of `now`
+    pub async fn derive(&self, now: DateTime<Utc>) -> Result<Vec<MetricPoint>> {
+        let since = now - self.window;
+        let mut points = Vec::new();
+        let mut point = |metric: MetricKind, subject: &str, value: Option<f64>| {
+            if let Some(value) = value.filter(|v| v.is_finite()) {
+                points.push(MetricPoint { metric, subject: subject.to_string(), timestamp: now, value });
+            }
+        };
+        for pool_id in &self.pool_ids {
+            let quotes = self.storage.quotes(pool_id, since).await?;
+            point(MetricKind::PoolFeeApr, pool_id, fee_apr(&quotes));
+            let candles = self.storage.candles(pool_id, HOURLY, since).await?;
+            point(MetricKind::RealizedVol, pool_id, realized_vol(&candles));
+        }
+        for position_id in &self.position_ids {
+            let entries = self
+                .storage
+                .ledger(&LedgerQuery { position_id: Some(position_id.clone()), ..Default::default() })
+                .await?;
+            if !entries.is_empty() {
+                point(MetricKind::PositionValue, position_id, Some(PositionPnl::from_entries(&entries).value_usd));
+            }
+            // Range state is only known while the range monitor runs
+            if let Some(in_range_now) = self.alerts.in_range(position_id) {
+                let query = AlertQuery {
+                    position_id: Some(position_id.clone()),
+                    since: Some(since),
+                    limit: MAX_RANGE_ALERTS,
+                    ..Default::default()
+                };
+                let alerts = self.storage.alerts(&query).await?;
+                point(MetricKind::InRangePct, position_id, Some(in_range_share(in_range_now, &alerts, since, now)));
+            }
+        }
+        for score in self.rpc.iter().flat_map(RpcEndpoints::scores) {
+            point(MetricKind::RpcScore, &score.endpoint, Some(score.score));
+        }
+        Ok(points)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::events::RangeStatus;
+    use chrono::TimeZone;
+
+    fn at(secs: i64) -> DateTime<Utc> {
+        Utc.timestamp_opt(1_700_000_000 + secs, 0).unwrap()
+    }
+
+    fn quote(secs: i64, volume_usd: f64, tvl_usd: f64) -> PoolQuote {
+        PoolQuote {
+            pool_id: "0xpool".to_string(),
+            timestamp: at(secs),
+            token0_symbol: "WETH".to_string(),
+            token1_symbol: "USDC".to_string(),
+            fee_tier: 3000,
+            tvl_usd,
+            volume_usd,
+        }
+    }
+
+    fn range_event(secs: i64, back_in_range: bool) -> Event {
+        let range = RangeStatus {
+            position_id: "1".to_string(),
+            owner: "0xowner".to_string(),
+            pool: "0xpool".to_string(),
+            pair: "WETH/USDC".to_string(),
+            tick_lower: -10,
+            tick_upper: 10,
+            current_tick: 0,
+        };
+        if back_in_range {
+            Event::PositionBackInRange { timestamp: at(secs), range }
+        } else {
+            Event::PositionOutOfRange { timestamp: at(secs), range }
+        }
+    }
+
+    #[test]
+    fn test_fee_apr() {
+        // 1M volume in a day at 0.3% on 1M TVL: 3k a day
+        let apr = fee_apr(&[quote(0, 5_000_000.0, 900_000.0), quote(86_400, 6_000_000.0, 1_000_000.0)]).unwrap();
+        assert!((apr - 0.003 * 365.0).abs() < 1e-9, "{}", apr);
+        assert_eq!(fee_apr(&[quote(0, 5_000_000.0, 1_000_000.0)]), None);
+        assert_eq!(fee_apr(&[quote(0, 1.0, 0.0), quote(60, 2.0, 0.0)]), None);
+    }
+
+    #[test]
+    fn test_pool_and_position_fee_apr() {
+        let apr = pool_fee_apr(3000, 1_000_000.0, 1_000_000.0).unwrap();
+        assert!((apr - 0.003 * 365.0).abs() < 1e-9, "{}", apr);
+        assert_eq!(pool_fee_apr(3000, 1_000_000.0, 0.0), None);
+
+        // A tenth of the active liquidity earns a tenth of 3k a day on 10k
+        let apr = position_fee_apr(3000, 1_000_000.0, 100, 1_000, 10_000.0, true).unwrap();
+        assert!((apr - 300.0 * 365.0 / 10_000.0).abs() < 1e-9, "{}", apr);
+        assert_eq!(position_fee_apr(3000, 1_000_000.0, 100, 1_000, 10_000.0, false), Some(0.0));
+        // Stale active liquidity below the position's own never gives it more than all fees
+        let all = position_fee_apr(3000, 1_000_000.0, 100, 50, 10_000.0, true).unwrap();
+        assert!((all - 3_000.0 * 365.0 / 10_000.0).abs() < 1e-9, "{}", all);
+        assert_eq!(position_fee_apr(3000, 1_000_000.0, 100, 1_000, 0.0, true), None);
+    }
+
+    #[test]
+    fn test_day_data_fee_apr() {
+        let day = |date: i64, fees: &str, tvl: &str| PoolDayData {
+            date,
+            open: "2000".to_string(),
+            high: "2000".to_string(),
+            low: "2000".to_string(),
+            close: "2000".to_string(),
+            volume_usd: "0".to_string(),
+            tvl_usd: tvl.to_string(),
+            fees_usd: fees.to_string(),
+        };
+        // 0.2% and 0.4% days average to 0.3% a day; the day without TVL is skipped
+        let days = [day(0, "2000", "1000000"), day(86_400, "4000", "1000000"), day(172_800, "50", "0")];
+        let apr = day_data_fee_apr(&days).unwrap();
+        assert!((apr - 0.003 * 365.0).abs() < 1e-9, "{}", apr);
+        assert_eq!(day_data_fee_apr(&days[2..]), None);
+        assert_eq!(day_data_fee_apr(&[]), None);
+    }
+
+    #[test]
+    fn test_realized_vol() {
+        let candle = |hour: i64, close: f64| Candle {
+            pool_id: "0xpool".to_string(),
+            interval_secs: HOURLY,
+            start: at(hour * 3600),
+            open: close,
+            high: close,
+            low: close,
+            close,
+            volume: 0.0,
+        };
+        let flat: Vec<Candle> = (0..24).map(|h| candle(h, 2000.0)).collect();
+        assert_eq!(realized_vol(&flat), Some(0.0));
+        let zigzag: Vec<Candle> = (0..24).map(|h| candle(h, if h % 2 == 0 { 2000.0 } else { 2020.0 })).collect();
+        assert!(realized_vol(&zigzag).unwrap() > 0.5);
+        assert_eq!(realized_vol(&flat[..2]), None);
+    }
+
+    #[test]
+    fn test_in_range_share() {
+        let (since, now) = (at(0), at(100));
+        assert_eq!(in_range_share(true, &[], since, now), 1.0);
+        assert_eq!(in_range_share(false, &[], since, now), 0.0);
+        // Out of range at 20, back at 70: in range 0-20 and 70-100
+        let alerts = [range_event(70, true), range_event(20, false)];
+        assert!((in_range_share(true, &alerts, since, now) - 0.5).abs() < 1e-9);
+        // Alerts before the window only set the starting state
+        assert_eq!(in_range_share(false, &[range_event(-5, false)], since, now), 0.0);
+    }
+
+    #[test]
+    fn test_metric_kind_round_trip() {
+        for metric in MetricKind::ALL {
+            assert_eq!(metric.as_str().parse::<MetricKind>().unwrap(), metric);
+        }
+        assert!("apr".parse::<MetricKind>().is_err());
+    }
+}
@@ -0,0 +1,111 @@
+use chrono::{DateTime, Utc};
+use std::collections::BTreeMap;
+
+use super::Message;
+use crate::events::Severity;
+use crate::scheduler::Schedule;
+
+/// Lines listed in a digest before the rest is summarized as a count
+const MAX_DIGEST_LINES: usize = 25;
+
+/// Holds low-severity messages until the next scheduled summary
+pub struct Digest {
+    max_severity: Severity,
+    pub(super) schedule: Schedule,
+    pending: Vec<Message>,
+    since: DateTime<Utc>,
+}
+
+impl Digest {
+    pub fn new(max_severity: Severity, schedule: Schedule) -> Self {
+        Self {
+            max_severity,
+            schedule,
+            pending: Vec::new(),
+            since: Utc::now(),
+        }
+    }
+
+    /// Keep the message for the digest if it is low-severity enough; returns it otherwise
+    pub(super) fn hold(&mut self, message: Message) -> Option<Message> {
+        if message.severity <= self.max_severity {
+            self.pending.push(message);
+            None
+        } else {
+            Some(message)
+        }
+    }
+
+    /// Held messages and the start of the period they cover; starts a new period
+    pub(super) fn take(&mut self, now: DateTime<Utc>) -> (Vec<Message>, DateTime<Utc>) {
+        let since = std::mem::replace(&mut self.since, now);
+        (std::mem::take(&mut self.pending), since)
+    }
+}
+
+/// One summary message for a batch of held messages
+pub fn render_digest(messages: &[Message], since: DateTime<Utc>, now: DateTime<Utc>) -> Message {
+    let mut counts: BTreeMap<&str, usize> = BTreeMap::new();
+    for message in messages {
+        *counts.entry(message.event_type).or_default() += 1;
+    }
+    let mut lines: Vec<String> = messages.iter().take(MAX_DIGEST_LINES).map(|m| format!("• {}", m.title)).collect();
+    if messages.len() > MAX_DIGEST_LINES {
+        lines.push(format!("…and {} more", messages.len() - MAX_DIGEST_LINES));
+    }
+    Message {
+        event_type: "digest",
+        severity: messages.iter().map(|m| m.severity).max().unwrap_or_default(),
+        title: match messages.len() {
+            1 => "Digest: 1 update".to_string(),
+            n => format!("Digest: {} updates", n),
+        },
+        body: lines.join("\n"),
+        fields: counts
+            .into_iter()
+            .map(|(event_type, count)| (event_type.replace('_', " "), count.to_string()))
+            .chain(std::iter::once((
+                "Period".to_string(),
+                format!("{} – {} UTC", since.format("%b %d %H:%M"), now.format("%b %d %H:%M")),
+            )))
+            .collect(),
+        links: Vec::new(),
+        thread_key: None,
+        timestamp: now,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::notifier::template::tests::exit_event;
+    use crate::notifier::Templates;
+    use chrono::TimeZone;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_hold_and_render() {
+        let mut digest = Digest::new(Severity::Warning, Schedule::every_secs(3600));
+        let templates = Templates::new(HashMap::new(), "https://arbiscan.io");
+        let critical = templates.render(&exit_event());
+        let mut routine = critical.clone();
+        routine.severity = Severity::Info;
+        routine.title = "Hold · 0x912c…6548".to_string();
+
+        assert!(digest.hold(critical).is_some());
+        for _ in 0..30 {
+            assert!(digest.hold(routine.clone()).is_none());
+        }
+
+        let since = Utc.with_ymd_and_hms(2024, 5, 1, 9, 0, 0).unwrap();
+        let now = Utc.with_ymd_and_hms(2024, 5, 1, 10, 0, 0).unwrap();
+        let (held, _) = digest.take(now);
+        let message = render_digest(&held, since, now);
+        assert_eq!(message.title, "Digest: 30 updates");
+        assert_eq!(message.severity, Severity::Info);
+        assert!(message.body.ends_with("…and 5 more"));
+        assert_eq!(message.fields[0], ("recommendation changed".to_string(), "30".to_string()));
+        assert_eq!(message.fields[1].1, "May 01 09:00 – May 01 10:00 UTC");
+        assert!(digest.take(now).0.is_empty());
+    }
+}
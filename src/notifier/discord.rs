@@ -0,0 +1,88 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use reqwest::Client;
+use serde_json::{json, Value};
+
+use super::{http_client, post_json, Message, Notifier};
+use crate::events::Severity;
+
+/// Posts messages as embeds to a Discord incoming webhook
+pub struct DiscordNotifier {
+    http: Client,
+    webhook_url: String,
+}
+
+impl DiscordNotifier {
+    pub fn new(webhook_url: &str) -> Self {
+        Self {
+            http: http_client(),
+            webhook_url: webhook_url.to_string(),
+        }
+    }
+
+    fn severity_color(severity: Severity) -> u32 {
+        match severity {
+            Severity::Info => 0x3498db,
+            Severity::Warning => 0xe67e22,
+            Severity::Critical => 0xe74c3c,
+        }
+    }
+
+    /// Discord webhook payload for a message
+    pub fn payload(&self, message: &Message) -> Value {
+        let mut fields: Vec<Value> = message
+            .fields
+            .iter()
+            .map(|(name, value)| json!({ "name": name, "value": value, "inline": true }))
+            .collect();
+        if !message.links.is_empty() {
+            let links: Vec<String> = message.links.iter().map(|l| format!("[{}]({})", l.label, l.url)).collect();
+            fields.push(json!({ "name": "Links", "value": links.join(" · "), "inline": false }));
+        }
+        json!({
+            "username": "Origins Recommender",
+            "embeds": [{
+                "title": message.headline(),
+                "url": message.links.first().map(|l| l.url.as_str()),
+                "description": message.body,
+                "color": Self::severity_color(message.severity),
+                "fields": fields,
+                "timestamp": message.timestamp.to_rfc3339(),
+            }]
+        })
+    }
+}
+
+#[async_trait]
+impl Notifier for DiscordNotifier {
+    fn name(&self) -> &'static str {
+        "discord"
+    }
+
+    async fn notify(&self, message: &Message) -> Result<()> {
+        post_json(&self.http, &self.webhook_url, &self.payload(message)).await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::notifier::template::tests::exit_event;
+    use crate::notifier::Templates;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_embed_payload() {
+        let notifier = DiscordNotifier::new("https://discord.test/hook");
+        let message = Templates::new(HashMap::new(), "https://arbiscan.io").render(&exit_event());
+        let payload = notifier.payload(&message);
+        let embed = &payload["embeds"][0];
+        assert_eq!(embed["title"], "🚨 Exit · 0x912c…6548");
+        assert_eq!(embed["color"], 0xe74c3c);
+        assert_eq!(embed["fields"][0]["value"], "Exit (was Hold)");
+        assert_eq!(embed["fields"][2]["value"], "$123.45");
+        assert!(embed["fields"][5]["value"].as_str().unwrap().starts_with("[Token](https://arbiscan.io/token/"));
+        assert_eq!(embed["url"], "https://arbiscan.io/token/0x912ce59144191c1204e64559fe8253a0e49e6548");
+    }
+}
@@ -0,0 +1,193 @@
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use lettre::message::{Mailbox, MultiPart};
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message as Email, Tokio1Executor};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Mutex;
+use tokio::time::sleep;
+use tracing::{info, warn};
+
+use super::{Message, Notifier};
+use crate::config::EmailConfig;
+use crate::events::Severity;
+
+/// Send attempts per digest; only transient SMTP failures are retried
+const MAX_ATTEMPTS: u32 = 3;
+
+/// Sends HTML digests of messages over SMTP.
+///
+/// Messages arriving within `digest_window_secs` of the first buffered one (a recommendation
+/// cycle publishes its changes back-to-back) are batched into a single email.
+pub struct EmailNotifier {
+    inner: Arc<EmailInner>,
+}
+
+struct EmailInner {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+    from: Mailbox,
+    to: Mailbox,
+    window: Duration,
+    pending: Mutex<Vec<Message>>,
+}
+
+impl EmailNotifier {
+    pub fn new(config: &EmailConfig) -> Result<Self> {
+        // Port 465 is implicit TLS; anything else upgrades with STARTTLS
+        let builder = if config.smtp_port == 465 {
+            AsyncSmtpTransport::<Tokio1Executor>::relay(&config.smtp_server)
+        } else {
+            AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&config.smtp_server)
+        }
+        .with_context(|| format!("configuring SMTP relay {}", config.smtp_server))?;
+        let transport = builder
+            .port(config.smtp_port)
+            .credentials(Credentials::new(config.username.clone(), config.password.clone()))
+            .build();
+        let from = config.from_address.as_deref().unwrap_or(&config.username);
+        Ok(Self {
+            inner: Arc::new(EmailInner {
+                transport,
+                from: from.parse().with_context(|| format!("invalid from address '{}'", from))?,
+                to: config
+                    .to_address
+                    .parse()
+                    .with_context(|| format!("invalid to address '{}'", config.to_address))?,
+                window: Duration::from_secs(config.digest_window_secs),
+                pending: Mutex::new(Vec::new()),
+            }),
+        })
+    }
+}
+
+impl EmailInner {
+    async fn flush(&self) {
+        let messages = std::mem::take(&mut *self.pending.lock().await);
+        if messages.is_empty() {
+            return;
+        }
+        let (subject, text, html) = render_digest(&messages);
+        if let Err(e) = self.send(&subject, text, html).await {
+            warn!(target: "notifier", channel = "email", count = messages.len(), "digest email failed: {:#}", e);
+        } else {
+            info!(target: "notifier", channel = "email", count = messages.len(), "digest email sent");
+        }
+    }
+
+    async fn send(&self, subject: &str, text: String, html: String) -> Result<()> {
+        let email = Email::builder()
+            .from(self.from.clone())
+            .to(self.to.clone())
+            .subject(subject)
+            .multipart(MultiPart::alternative_plain_html(text, html))?;
+        let mut attempt: u32 = 0;
+        loop {
+            attempt += 1;
+            match self.transport.send(email.clone()).await {
+                Ok(_) => return Ok(()),
+                Err(e) if e.is_transient() && attempt < MAX_ATTEMPTS => {
+                    let backoff = Duration::from_secs(2u64.pow(attempt));
+                    warn!(target: "notifier", channel = "email", attempt, "transient SMTP failure, retrying in {:?}: {}", backoff, e);
+                    sleep(backoff).await;
+                }
+                Err(e) => return Err(e.into()),
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl Notifier for EmailNotifier {
+    fn name(&self) -> &'static str {
+        "email"
+    }
+
+    async fn notify(&self, message: &Message) -> Result<()> {
+        let mut pending = self.inner.pending.lock().await;
+        pending.push(message.clone());
+        // The first message of a batch schedules the flush
+        if pending.len() == 1 {
+            let inner = self.inner.clone();
+            tokio::spawn(async move {
+                sleep(inner.window).await;
+                inner.flush().await;
+            });
+        }
+        Ok(())
+    }
+}
+
+fn escape_html(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Subject, plain-text and HTML bodies for a digest of messages.
+///
+/// The subject is prefixed with the highest severity when it is above info.
+pub fn render_digest(messages: &[Message]) -> (String, String, String) {
+    let mut subject = match messages.len() {
+        1 => "Origins: 1 update".to_string(),
+        n => format!("Origins: {} updates", n),
+    };
+    match messages.iter().map(|m| m.severity).max() {
+        Some(Severity::Critical) => subject.insert_str(0, "[CRITICAL] "),
+        Some(Severity::Warning) => subject.insert_str(0, "[WARNING] "),
+        _ => {}
+    }
+    let mut text = String::new();
+    let mut rows = String::new();
+    for message in messages {
+        let fields: Vec<String> = message.fields.iter().map(|(name, value)| format!("{}: {}", name, value)).collect();
+        text.push_str(&format!("- {}. {}. {}\n", message.title, fields.join(", "), message.body));
+        let links: Vec<String> = message
+            .links
+            .iter()
+            .map(|l| format!("<a href=\"{}\">{}</a>", escape_html(&l.url), escape_html(&l.label)))
+            .collect();
+        let fields: Vec<String> = message
+            .fields
+            .iter()
+            .map(|(name, value)| format!("{}: <strong>{}</strong>", escape_html(name), escape_html(value)))
+            .collect();
+        rows.push_str(&format!(
+            "<tr><td>{}</td><td><strong>{}</strong></td><td>{}</td><td>{}</td><td>{}</td></tr>",
+            super::severity_emoji(message.severity),
+            escape_html(&message.title),
+            fields.join("<br>"),
+            escape_html(&message.body),
+            links.join(" · ")
+        ));
+    }
+    let html = format!(
+        "<!doctype html><html><body style=\"font-family: sans-serif\">\
+         <h2>{}</h2>\
+         <table cellpadding=\"6\" style=\"border-collapse: collapse\" border=\"1\">\
+         <tr><th></th><th>Update</th><th>Details</th><th>Reasoning</th><th>Links</th></tr>\
+         {}</table></body></html>",
+        escape_html(&subject),
+        rows
+    );
+    (subject, text, html)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::notifier::template::tests::exit_event;
+    use crate::notifier::Templates;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_render_digest_escapes_html() {
+        let message = Templates::new(HashMap::new(), "https://arbiscan.io").render(&exit_event());
+        let (subject, text, html) = render_digest(&[message.clone(), message]);
+        assert_eq!(subject, "[CRITICAL] Origins: 2 updates");
+        assert_eq!(text.lines().count(), 2);
+        assert!(html.contains("High risk &lt;volatile&gt;"));
+        assert!(html.contains("Exit (was Hold)"));
+    }
+}
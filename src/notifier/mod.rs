@@ -0,0 +1,336 @@
+//! Human-facing notification channels (Discord, Slack, email, Telegram) fed from the event bus.
+
+mod digest;
+mod discord;
+mod email;
+mod routing;
+mod slack;
+mod telegram;
+mod template;
+
+pub use digest::{render_digest, Digest};
+pub use discord::DiscordNotifier;
+pub use email::EmailNotifier;
+pub use routing::Router;
+pub use slack::SlackNotifier;
+pub use telegram::TelegramNotifier;
+pub use template::{severity_emoji, Link, Message, Templates};
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use reqwest::{Client, StatusCode};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::time::{sleep, Instant};
+use tracing::{debug, info, warn};
+
+use crate::config::NotificationConfig;
+use crate::events::{EventBus, Severity};
+use crate::quotes::{QuoteBus, QuoteEvent};
+use crate::utils::format_usd_compact;
+
+/// Block explorer used for links when none is configured (the tracked pools live on Arbitrum)
+pub const DEFAULT_EXPLORER_URL: &str = "https://arbiscan.io";
+
+/// Delivery attempts per message before giving up
+const MAX_ATTEMPTS: u32 = 3;
+
+/// A channel delivering rendered messages to humans
+#[async_trait]
+pub trait Notifier: Send + Sync {
+    fn name(&self) -> &'static str;
+    async fn notify(&self, message: &Message) -> Result<()>;
+}
+
+/// Build the notifiers enabled in config
+pub fn from_config(config: &NotificationConfig) -> Vec<Arc<dyn Notifier>> {
+    let mut notifiers: Vec<Arc<dyn Notifier>> = Vec::new();
+    if let Some(channels) = &config.notification_channels {
+        if let Some(url) = channels.discord_webhook.as_deref().filter(|u| !u.is_empty()) {
+            notifiers.push(Arc::new(DiscordNotifier::new(url)));
+        }
+        // The Web API (bot token) supports threads, so it wins over the incoming webhook
+        if let Some(slack) = &channels.slack {
+            notifiers.push(Arc::new(SlackNotifier::api(slack)));
+        } else if let Some(url) = channels.slack_webhook.as_deref().filter(|u| !u.is_empty()) {
+            notifiers.push(Arc::new(SlackNotifier::webhook(url)));
+        }
+        if let Some(telegram) = &channels.telegram {
+            notifiers.push(Arc::new(TelegramNotifier::new(telegram)));
+        }
+        if let Some(email) = &channels.email {
+            match EmailNotifier::new(email) {
+                Ok(notifier) => notifiers.push(Arc::new(notifier)),
+                Err(e) => warn!(target: "notifier", "email notifications disabled: {:#}", e),
+            }
+        }
+    }
+    notifiers
+}
+
+/// Renders events from the bus, routes them and delivers them to the notifiers
+pub struct Dispatcher {
+    notifiers: Vec<Arc<dyn Notifier>>,
+    templates: Templates,
+    router: Router,
+    digest: Option<Digest>,
+    /// Quote bus and the TVL drop (percent) that triggers a warning
+    quotes: Option<(QuoteBus, f64)>,
+    /// Last quoted TVL per pool
+    tvl_marks: HashMap<String, f64>,
+}
+
+impl Dispatcher {
+    pub fn new(notifiers: Vec<Arc<dyn Notifier>>, templates: Templates, router: Router) -> Self {
+        Self {
+            notifiers,
+            templates,
+            router,
+            digest: None,
+            quotes: None,
+            tvl_marks: HashMap::new(),
+        }
+    }
+
+    /// Warn when a quoted pool's TVL falls by at least `drop_pct` percent between two quotes
+    pub fn with_quotes(mut self, bus: QuoteBus, drop_pct: f64) -> Self {
+        self.quotes = Some((bus, drop_pct));
+        self
+    }
+
+    /// Batch low-severity messages into periodic summaries
+    pub fn with_digest(mut self, digest: Digest) -> Self {
+        self.digest = Some(digest);
+        self
+    }
+
+    /// Deliver events until the bus closes
+    pub async fn run(mut self, bus: EventBus) {
+        let names: Vec<&str> = self.notifiers.iter().map(|n| n.name()).collect();
+        info!(target: "notifier", channels = ?names, digest = self.digest.is_some(), "notifications enabled");
+        let mut rx = bus.subscribe();
+        let mut quote_rx = self.quotes.as_ref().map(|(bus, _)| bus.subscribe());
+        let flush = sleep(self.next_flush_delay());
+        tokio::pin!(flush);
+        loop {
+            tokio::select! {
+                received = rx.recv() => match received {
+                    Ok(event) => {
+                        let message = self.templates.render(&event);
+                        if let Some(message) = self.hold_for_digest(message) {
+                            self.deliver(&message, Utc::now()).await;
+                        }
+                    }
+                    Err(RecvError::Lagged(skipped)) => {
+                        warn!(target: "notifier", skipped, "notifier lagged, events dropped");
+                    }
+                    Err(RecvError::Closed) => break,
+                },
+                received = async { quote_rx.as_mut().expect("branch requires a receiver").recv().await }, if quote_rx.is_some() => match received {
+                    Ok(quote) => {
+                        if let Some(message) = self.check_tvl(&quote) {
+                            if let Some(message) = self.hold_for_digest(message) {
+                                self.deliver(&message, Utc::now()).await;
+                            }
+                        }
+                    }
+                    Err(RecvError::Lagged(skipped)) => {
+                        warn!(target: "notifier", skipped, "notifier lagged, quotes dropped");
+                    }
+                    Err(RecvError::Closed) => quote_rx = None,
+                },
+                _ = &mut flush, if self.digest.is_some() => {
+                    self.flush_digest().await;
+                    flush.as_mut().reset(Instant::now() + self.next_flush_delay());
+                }
+            }
+        }
+        self.flush_digest().await;
+    }
+
+    /// Record the quote's TVL; a warning when it fell past the threshold since the last quote
+    fn check_tvl(&mut self, event: &QuoteEvent) -> Option<Message> {
+        let drop_pct = self.quotes.as_ref()?.1;
+        let quote = &event.quote;
+        let previous = self.tvl_marks.insert(quote.pool_id.clone(), quote.tvl_usd)?;
+        if previous <= 0.0 {
+            return None;
+        }
+        let change_pct = (previous - quote.tvl_usd) / previous * 100.0;
+        if change_pct < drop_pct {
+            return None;
+        }
+        let pair = format!("{}/{}", quote.token0_symbol, quote.token1_symbol);
+        Some(Message {
+            event_type: "pool_tvl_drop",
+            severity: Severity::Warning,
+            title: format!("TVL drop · {}", pair),
+            body: format!(
+                "{} pool TVL fell {:.1}% from {} to {}",
+                pair,
+                change_pct,
+                format_usd_compact(previous),
+                format_usd_compact(quote.tvl_usd)
+            ),
+            fields: vec![
+                ("Pool".to_string(), short_address(&quote.pool_id)),
+                ("Fee tier".to_string(), quote.fee_tier.to_string()),
+            ],
+            links: vec![],
+            thread_key: Some(quote.pool_id.clone()),
+            timestamp: quote.timestamp,
+        })
+    }
+
+    fn next_flush_delay(&self) -> Duration {
+        match &self.digest {
+            Some(digest) => digest.schedule.delay_after(Utc::now()),
+            // Never fires: the select branch is disabled without a digest
+            None => Duration::from_secs(86_400),
+        }
+    }
+
+    fn hold_for_digest(&mut self, message: Message) -> Option<Message> {
+        match self.digest.as_mut() {
+            Some(digest) => digest.hold(message),
+            None => Some(message),
+        }
+    }
+
+    async fn deliver(&self, message: &Message, now: DateTime<Utc>) {
+        for notifier in &self.notifiers {
+            if !self.router.allows(notifier.name(), message, now) {
+                debug!(target: "notifier", channel = notifier.name(), event_type = message.event_type, "message not routed to channel");
+                continue;
+            }
+            self.send(notifier.as_ref(), message).await;
+        }
+    }
+
+    async fn send(&self, notifier: &dyn Notifier, message: &Message) {
+        if let Err(e) = notifier.notify(message).await {
+            warn!(target: "notifier", channel = notifier.name(), "notification failed: {:#}", e);
+        }
+    }
+
+    /// Send each channel a summary of the held messages routed to it
+    async fn flush_digest(&mut self) {
+        let now = Utc::now();
+        let Some(digest) = self.digest.as_mut() else {
+            return;
+        };
+        let (held, since) = digest.take(now);
+        if held.is_empty() {
+            return;
+        }
+        info!(target: "notifier", count = held.len(), "sending notification digest");
+        for notifier in &self.notifiers {
+            let routed: Vec<Message> = held
+                .iter()
+                .filter(|m| self.router.allows(notifier.name(), m, now))
+                .cloned()
+                .collect();
+            if !routed.is_empty() {
+                self.send(notifier.as_ref(), &render_digest(&routed, since, now)).await;
+            }
+        }
+    }
+}
+
+pub(crate) fn http_client() -> Client {
+    Client::builder()
+        .user_agent("origins-notifier/0.1")
+        .timeout(Duration::from_secs(10))
+        .build()
+        .expect("failed to build reqwest client")
+}
+
+/// POST a JSON body, retrying on 5xx/network errors and honouring 429 Retry-After
+pub(crate) async fn post_json(http: &Client, url: &str, body: &serde_json::Value) -> Result<reqwest::Response> {
+    let mut attempt: u32 = 0;
+    loop {
+        attempt += 1;
+        let result = http.post(url).json(body).send().await.context("sending notification");
+        let retry_in = match result {
+            Ok(resp) if resp.status().is_success() => return Ok(resp),
+            Ok(resp) if resp.status() == StatusCode::TOO_MANY_REQUESTS => {
+                let secs = resp
+                    .headers()
+                    .get("retry-after")
+                    .and_then(|v| v.to_str().ok())
+                    .and_then(|v| v.parse::<f64>().ok())
+                    .unwrap_or(1.0);
+                Duration::from_secs_f64(secs.clamp(0.1, 30.0))
+            }
+            Ok(resp) if resp.status().is_client_error() => {
+                let status = resp.status();
+                let text = resp.text().await.unwrap_or_default();
+                return Err(anyhow::anyhow!("rejected, status={} body={}", status, text));
+            }
+            Ok(resp) if attempt >= MAX_ATTEMPTS => {
+                return Err(anyhow::anyhow!("failed after {} attempts, status={}", attempt, resp.status()));
+            }
+            Err(e) if attempt >= MAX_ATTEMPTS => return Err(e),
+            _ => Duration::from_millis(500 * 3u64.pow(attempt - 1)),
+        };
+        if attempt >= MAX_ATTEMPTS {
+            return Err(anyhow::anyhow!("still rate limited after {} attempts", attempt));
+        }
+        sleep(retry_in).await;
+    }
+}
+
+/// Shorten a 0x address to `0x1234…abcd` for display
+pub(crate) fn short_address(address: &str) -> String {
+    if address.len() > 12 && address.starts_with("0x") {
+        format!("{}…{}", &address[..6], &address[address.len() - 4..])
+    } else {
+        address.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::storage::PoolQuote;
+    use std::collections::HashMap;
+
+    fn quote(tvl: f64) -> QuoteEvent {
+        QuoteEvent {
+            quote: PoolQuote {
+                pool_id: "0xpool".to_string(),
+                timestamp: Utc::now(),
+                token0_symbol: "WETH".to_string(),
+                token1_symbol: "USDC".to_string(),
+                fee_tier: 500,
+                tvl_usd: tvl,
+                volume_usd: 0.0,
+            },
+            token0: "0xaaa".to_string(),
+            token1: "0xbbb".to_string(),
+            position_id: None,
+            active_liquidity: None,
+            price: None,
+        }
+    }
+
+    #[test]
+    fn test_tvl_drop_past_threshold_warns() {
+        let config = Config::default().notifications.unwrap();
+        let mut dispatcher = Dispatcher::new(vec![], Templates::new(HashMap::new(), DEFAULT_EXPLORER_URL), Router::from_config(&config).unwrap())
+            .with_quotes(QuoteBus::new(), 20.0);
+
+        assert!(dispatcher.check_tvl(&quote(1_000_000.0)).is_none());
+        // 15% is under the threshold, and becomes the new reference
+        assert!(dispatcher.check_tvl(&quote(850_000.0)).is_none());
+        let message = dispatcher.check_tvl(&quote(680_000.0)).unwrap();
+        assert_eq!(message.event_type, "pool_tvl_drop");
+        assert_eq!(message.severity, Severity::Warning);
+        assert!(message.body.contains("fell 20.0%"), "{}", message.body);
+        assert!(dispatcher.check_tvl(&quote(700_000.0)).is_none());
+    }
+}
@@ -0,0 +1,142 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Duration, NaiveTime, Utc};
+use std::collections::HashMap;
+use tracing::warn;
+
+use super::Message;
+use crate::config::{NotificationConfig, RouteConfig};
+use crate::events::Severity;
+
+const CHANNELS: [&str; 4] = ["discord", "slack", "telegram", "email"];
+
+struct QuietHours {
+    start: NaiveTime,
+    end: NaiveTime,
+    utc_offset: Duration,
+    allow_critical: bool,
+}
+
+impl QuietHours {
+    fn contains(&self, now: DateTime<Utc>) -> bool {
+        let local = (now + self.utc_offset).time();
+        if self.start <= self.end {
+            local >= self.start && local < self.end
+        } else {
+            local >= self.start || local < self.end
+        }
+    }
+}
+
+struct Route {
+    enabled: bool,
+    event_types: Vec<String>,
+    min_severity: Severity,
+    quiet_hours: Option<QuietHours>,
+}
+
+impl Route {
+    fn from_config(config: &RouteConfig) -> Result<Self> {
+        let quiet_hours = match &config.quiet_hours {
+            Some(q) => Some(QuietHours {
+                start: parse_time(&q.start)?,
+                end: parse_time(&q.end)?,
+                utc_offset: Duration::minutes(q.utc_offset_minutes as i64),
+                allow_critical: q.allow_critical,
+            }),
+            None => None,
+        };
+        Ok(Self {
+            enabled: config.enabled,
+            event_types: config.event_types.clone(),
+            min_severity: config.min_severity,
+            quiet_hours,
+        })
+    }
+
+    fn allows(&self, message: &Message, now: DateTime<Utc>) -> bool {
+        self.enabled
+            && (self.event_types.is_empty() || self.event_types.iter().any(|t| t == message.event_type))
+            && message.severity >= self.min_severity
+            && self
+                .quiet_hours
+                .as_ref()
+                .is_none_or(|q| !q.contains(now) || (q.allow_critical && message.severity == Severity::Critical))
+    }
+}
+
+fn parse_time(value: &str) -> Result<NaiveTime> {
+    NaiveTime::parse_from_str(value, "%H:%M").with_context(|| format!("invalid quiet hours time '{}', expected HH:MM", value))
+}
+
+/// Decides which channels receive a message
+#[derive(Default)]
+pub struct Router {
+    routes: HashMap<String, Route>,
+}
+
+impl Router {
+    pub fn from_config(config: &NotificationConfig) -> Result<Self> {
+        let mut routes = HashMap::new();
+        for (channel, route) in &config.routing {
+            if !CHANNELS.contains(&channel.as_str()) {
+                warn!(target: "notifier", channel = %channel, "routing rule for unknown channel ignored");
+                continue;
+            }
+            let route = Route::from_config(route).with_context(|| format!("routing rule for '{}'", channel))?;
+            routes.insert(channel.clone(), route);
+        }
+        Ok(Self { routes })
+    }
+
+    /// Whether `channel` should deliver `message` at `now`
+    pub fn allows(&self, channel: &str, message: &Message, now: DateTime<Utc>) -> bool {
+        self.routes.get(channel).is_none_or(|route| route.allows(message, now))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::QuietHoursConfig;
+    use crate::notifier::template::tests::exit_event;
+    use crate::notifier::Templates;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_routing_rules() {
+        let mut config: NotificationConfig = toml::from_str("notifications_enabled = true").unwrap();
+        config.routing.insert(
+            "email".to_string(),
+            RouteConfig { enabled: true, event_types: vec![], min_severity: Severity::Critical, quiet_hours: None },
+        );
+        config.routing.insert(
+            "telegram".to_string(),
+            RouteConfig {
+                enabled: true,
+                event_types: vec!["recommendation_changed".to_string()],
+                min_severity: Severity::Info,
+                quiet_hours: Some(QuietHoursConfig {
+                    start: "22:00".to_string(),
+                    end: "07:00".to_string(),
+                    utc_offset_minutes: 120,
+                    allow_critical: false,
+                }),
+            },
+        );
+        let router = Router::from_config(&config).unwrap();
+        let templates = Templates::new(HashMap::new(), "https://arbiscan.io");
+        let mut message = templates.render(&exit_event());
+        let noon = Utc.with_ymd_and_hms(2024, 5, 1, 12, 0, 0).unwrap();
+        // 21:30 UTC is 23:30 local with the +2h offset
+        let night = Utc.with_ymd_and_hms(2024, 5, 1, 21, 30, 0).unwrap();
+
+        assert!(router.allows("discord", &message, noon));
+        assert!(router.allows("email", &message, noon));
+        assert!(router.allows("telegram", &message, noon));
+        assert!(!router.allows("telegram", &message, night));
+
+        message.severity = Severity::Warning;
+        assert!(!router.allows("email", &message, noon));
+        assert!(router.allows("discord", &message, night));
+    }
+}
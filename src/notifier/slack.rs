@@ -0,0 +1,192 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use reqwest::Client;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+
+use super::{http_client, post_json, Message, Notifier};
+use crate::config::SlackConfig;
+
+const POST_MESSAGE_URL: &str = "https://slack.com/api/chat.postMessage";
+
+/// How long a position's thread is followed up after its last message; quieter positions
+/// start a new thread and are dropped from the cache
+const THREAD_TTL: Duration = Duration::from_secs(7 * 24 * 3600);
+
+enum Delivery {
+    /// Incoming webhook: fixed channel, no threading
+    Webhook(String),
+    /// Web API with a bot token; follow-ups about a position go into one thread
+    Api {
+        bot_token: String,
+        channel: String,
+        thread_by_position: bool,
+        threads: Mutex<Threads>,
+    },
+}
+
+/// Position id -> ts of the message that started its thread, and when it was last posted to
+#[derive(Default)]
+struct Threads {
+    by_key: HashMap<String, (String, Instant)>,
+}
+
+impl Threads {
+    /// The live thread for `key`, marking it used at `now`
+    fn get(&mut self, key: &str, now: Instant) -> Option<String> {
+        let (ts, last_used) = self.by_key.get_mut(key)?;
+        if now.duration_since(*last_used) >= THREAD_TTL {
+            return None;
+        }
+        *last_used = now;
+        Some(ts.clone())
+    }
+
+    /// Start a thread for `key`, dropping the ones that went quiet
+    fn insert(&mut self, key: String, ts: String, now: Instant) {
+        self.by_key.retain(|_, (_, last_used)| now.duration_since(*last_used) < THREAD_TTL);
+        self.by_key.insert(key, (ts, now));
+    }
+}
+
+/// Posts messages to Slack using Block Kit
+pub struct SlackNotifier {
+    http: Client,
+    delivery: Delivery,
+}
+
+impl SlackNotifier {
+    pub fn webhook(url: &str) -> Self {
+        Self {
+            http: http_client(),
+            delivery: Delivery::Webhook(url.to_string()),
+        }
+    }
+
+    pub fn api(config: &SlackConfig) -> Self {
+        Self {
+            http: http_client(),
+            delivery: Delivery::Api {
+                bot_token: config.bot_token.clone(),
+                channel: config.channel.clone(),
+                thread_by_position: config.thread_by_position,
+                threads: Mutex::new(Threads::default()),
+            },
+        }
+    }
+
+    /// Block Kit message (`text` fallback plus `blocks`)
+    pub fn message(&self, message: &Message) -> Value {
+        let headline = message.headline();
+        let fields: Vec<Value> = message
+            .fields
+            .iter()
+            .map(|(name, value)| json!({ "type": "mrkdwn", "text": format!("*{}*\n{}", name, value) }))
+            .collect();
+        let mut blocks = vec![
+            json!({ "type": "header", "text": { "type": "plain_text", "text": headline } }),
+            json!({ "type": "section", "fields": fields }),
+        ];
+        if !message.body.is_empty() {
+            blocks.push(json!({ "type": "section", "text": { "type": "mrkdwn", "text": message.body } }));
+        }
+        let mut context: Vec<Value> = message
+            .links
+            .iter()
+            .map(|l| json!({ "type": "mrkdwn", "text": format!("<{}|{}>", l.url, l.label) }))
+            .collect();
+        let ts = message.timestamp;
+        context.push(json!({
+            "type": "mrkdwn",
+            "text": format!("<!date^{}^{{date_short_pretty}} {{time}}|{}>", ts.timestamp(), ts.to_rfc3339())
+        }));
+        blocks.push(json!({ "type": "context", "elements": context }));
+        json!({
+            "text": format!("{}: {}", headline, message.body),
+            "blocks": blocks,
+        })
+    }
+}
+
+#[async_trait]
+impl Notifier for SlackNotifier {
+    fn name(&self) -> &'static str {
+        "slack"
+    }
+
+    async fn notify(&self, message: &Message) -> Result<()> {
+        let thread_key = message.thread_key.clone();
+        let mut message = self.message(message);
+        match &self.delivery {
+            Delivery::Webhook(url) => {
+                post_json(&self.http, url, &message).await?;
+            }
+            Delivery::Api { bot_token, channel, thread_by_position, threads } => {
+                let key = thread_key.filter(|_| *thread_by_position);
+                let thread_ts = match &key {
+                    Some(key) => threads.lock().await.get(key, Instant::now()),
+                    None => None,
+                };
+                message["channel"] = json!(channel);
+                if let Some(ts) = &thread_ts {
+                    message["thread_ts"] = json!(ts);
+                }
+                let resp = self
+                    .http
+                    .post(POST_MESSAGE_URL)
+                    .bearer_auth(bot_token)
+                    .json(&message)
+                    .send()
+                    .await?
+                    .error_for_status()?;
+                // The Web API reports failures in the body with HTTP 200
+                let body: Value = resp.json().await?;
+                if body["ok"] != json!(true) {
+                    return Err(anyhow::anyhow!("slack chat.postMessage failed: {}", body["error"]));
+                }
+                if let (Some(key), None, Some(ts)) = (key, thread_ts, body["ts"].as_str()) {
+                    threads.lock().await.insert(key, ts.to_string(), Instant::now());
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::notifier::template::tests::exit_event;
+    use crate::notifier::Templates;
+
+    #[test]
+    fn test_block_kit_message() {
+        let notifier = SlackNotifier::webhook("https://hooks.slack.test/x");
+        let rendered = Templates::new(HashMap::new(), "https://arbiscan.io").render(&exit_event());
+        let message = notifier.message(&rendered);
+        assert_eq!(message["blocks"][0]["text"]["text"], "🚨 Exit · 0x912c…6548");
+        assert_eq!(message["blocks"][1]["fields"][0]["text"], "*Action*\nExit (was Hold)");
+        assert_eq!(message["blocks"][3]["elements"][2]["text"], "<https://app.uniswap.org/positions/v3/arbitrum/42|Position>");
+        assert_eq!(rendered.thread_key.as_deref(), Some("42"));
+    }
+
+    #[test]
+    fn test_quiet_threads_expire() {
+        let start = Instant::now();
+        let mut threads = Threads::default();
+        threads.insert("pos-1".to_string(), "1700000000.000100".to_string(), start);
+        threads.insert("pos-2".to_string(), "1700000000.000200".to_string(), start);
+        // Posting keeps a thread alive
+        let later = start + THREAD_TTL / 2;
+        assert_eq!(threads.get("pos-1", later).as_deref(), Some("1700000000.000100"));
+        let expired = start + THREAD_TTL;
+        assert_eq!(threads.get("pos-2", expired), None);
+        assert!(threads.get("pos-1", expired).is_some());
+        // Starting another thread evicts the quiet one
+        threads.insert("pos-3".to_string(), "1700000000.000300".to_string(), expired);
+        assert!(!threads.by_key.contains_key("pos-2"));
+        assert_eq!(threads.by_key.len(), 2);
+    }
+}
@@ -0,0 +1,82 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use reqwest::Client;
+use serde_json::{json, Value};
+
+use super::{http_client, post_json, Message, Notifier};
+use crate::config::TelegramConfig;
+
+/// Sends messages to a Telegram chat via the Bot API
+pub struct TelegramNotifier {
+    http: Client,
+    bot_token: String,
+    chat_id: String,
+}
+
+fn escape_html(s: &str) -> String {
+    s.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
+
+impl TelegramNotifier {
+    pub fn new(config: &TelegramConfig) -> Self {
+        Self {
+            http: http_client(),
+            bot_token: config.bot_token.clone(),
+            chat_id: config.chat_id.clone(),
+        }
+    }
+
+    /// `sendMessage` body: HTML text plus inline buttons for the message links
+    pub fn message(&self, message: &Message) -> Value {
+        let mut text = format!("<b>{}</b>\n", escape_html(&message.headline()));
+        for (name, value) in &message.fields {
+            text.push_str(&format!("{}: <code>{}</code>\n", escape_html(name), escape_html(value)));
+        }
+        if !message.body.is_empty() {
+            text.push_str(&format!("\n{}", escape_html(&message.body)));
+        }
+        let buttons: Vec<Value> = message.links.iter().map(|l| json!({ "text": l.label, "url": l.url })).collect();
+        json!({
+            "chat_id": self.chat_id,
+            "text": text,
+            "parse_mode": "HTML",
+            "disable_web_page_preview": true,
+            "reply_markup": { "inline_keyboard": [buttons] },
+        })
+    }
+}
+
+#[async_trait]
+impl Notifier for TelegramNotifier {
+    fn name(&self) -> &'static str {
+        "telegram"
+    }
+
+    async fn notify(&self, message: &Message) -> Result<()> {
+        let url = format!("https://api.telegram.org/bot{}/sendMessage", self.bot_token);
+        post_json(&self.http, &url, &self.message(message)).await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::notifier::template::tests::exit_event;
+    use crate::notifier::Templates;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_message_buttons() {
+        let notifier = TelegramNotifier::new(&TelegramConfig { bot_token: "t".to_string(), chat_id: "-100".to_string() });
+        let rendered = Templates::new(HashMap::new(), "https://arbiscan.io").render(&exit_event());
+        let message = notifier.message(&rendered);
+        let text = message["text"].as_str().unwrap();
+        assert!(text.starts_with("<b>🚨 Exit"));
+        assert!(text.ends_with("High risk &lt;volatile&gt;"));
+        let buttons = message["reply_markup"]["inline_keyboard"][0].as_array().unwrap();
+        assert_eq!(buttons.len(), 3);
+        assert_eq!(buttons[1]["url"], "https://arbiscan.io/address/0x1111111111111111111111111111111111111111");
+        assert_eq!(buttons[2]["url"], "https://app.uniswap.org/positions/v3/arbitrum/42");
+    }
+}
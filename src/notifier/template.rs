@@ -0,0 +1,450 @@
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+
+use super::{short_address, DEFAULT_EXPLORER_URL};
+use crate::config::{NotificationConfig, TemplateConfig};
+use crate::events::{Event, Severity};
+use crate::position::LiquidityMetrics;
+
+/// Title and body for recommendation changes
+const DEFAULT_TITLE: &str = "{action} · {pair}";
+const DEFAULT_BODY: &str = "{reasoning}";
+
+/// A link rendered as a button or hyperlink depending on the channel
+#[derive(Debug, Clone, PartialEq)]
+pub struct Link {
+    pub label: String,
+    pub url: String,
+}
+
+/// Channel-independent rendering of an event
+#[derive(Debug, Clone)]
+pub struct Message {
+    pub event_type: &'static str,
+    pub severity: Severity,
+    pub title: String,
+    pub body: String,
+    /// Short labelled values (action, score, ...)
+    pub fields: Vec<(String, String)>,
+    pub links: Vec<Link>,
+    /// Messages sharing a key belong to the same conversation thread
+    pub thread_key: Option<String>,
+    pub timestamp: DateTime<Utc>,
+}
+
+impl Message {
+    /// Title prefixed with the severity marker
+    pub fn headline(&self) -> String {
+        format!("{} {}", severity_emoji(self.severity), self.title)
+    }
+}
+
+pub fn severity_emoji(severity: Severity) -> &'static str {
+    match severity {
+        Severity::Info => "ℹ️",
+        Severity::Warning => "⚠️",
+        Severity::Critical => "🚨",
+    }
+}
+
+/// Renders events into `Message`s using per-event-type title/body templates.
+///
+/// Templates use `{placeholder}` substitution; unknown placeholders are left as-is.
+pub struct Templates {
+    overrides: HashMap<String, TemplateConfig>,
+    explorer_url: String,
+}
+
+impl Templates {
+    pub fn new(overrides: HashMap<String, TemplateConfig>, explorer_url: &str) -> Self {
+        Self {
+            overrides,
+            explorer_url: explorer_url.trim_end_matches('/').to_string(),
+        }
+    }
+
+    /// Templates linking to `explorer_url` (the chain's), else `notifications.explorer_url`
+    pub fn from_config(config: &NotificationConfig, explorer_url: Option<&str>) -> Self {
+        Self::new(
+            config.templates.clone(),
+            explorer_url.or(config.explorer_url.as_deref()).unwrap_or(DEFAULT_EXPLORER_URL),
+        )
+    }
+
+    pub fn render(&self, event: &Event) -> Message {
+        let vars = self.vars(event);
+        let (default_title, default_body) = default_template(event.kind());
+        let template = self.overrides.get(event.kind());
+        let title = template.and_then(|t| t.title.as_deref()).unwrap_or(default_title);
+        let body = template.and_then(|t| t.body.as_deref()).unwrap_or(default_body);
+        let (fields, links) = match event {
+            Event::RecommendationChanged { recommendation, .. } => {
+                let position = &recommendation.position;
+                let links = vec![
+                    self.explorer_link("Token", "token", &position.token_address),
+                    self.explorer_link("Wallet", "address", &position.user_address),
+                ];
+                let fields = vec![
+                    ("Action".to_string(), vars["action_change"].clone()),
+                    ("Score".to_string(), vars["score"].clone()),
+                    ("Value".to_string(), format!("${}", vars["value_usd"])),
+                    ("Position".to_string(), position.id.clone()),
+                    ("Token".to_string(), vars["pair"].clone()),
+                ];
+                (fields, links)
+            }
+            Event::PositionOutOfRange { range, .. } | Event::PositionBackInRange { range, .. } => {
+                let links = vec![
+                    self.explorer_link("Pool", "address", &range.pool),
+                    self.explorer_link("Wallet", "address", &range.owner),
+                ];
+                let fields = vec![
+                    ("Position".to_string(), range.position_id.clone()),
+                    ("Pair".to_string(), range.pair.clone()),
+                    ("Range".to_string(), format!("[{}, {}]", range.tick_lower, range.tick_upper)),
+                    ("Current tick".to_string(), range.current_tick.to_string()),
+                ];
+                (fields, links)
+            }
+            Event::FeesCollectable { fees, .. } => {
+                let links = vec![self.explorer_link("Wallet", "address", &fees.owner)];
+                let fields = vec![
+                    ("Position".to_string(), fees.position_id.clone()),
+                    ("Fees".to_string(), format!("${}", vars["value_usd"])),
+                    ("Collect gas".to_string(), vars["collect_cost"].clone()),
+                ];
+                (fields, links)
+            }
+            Event::FeesCollected { collection, .. } => {
+                let links = vec![
+                    self.explorer_link("Transaction", "tx", &collection.tx_hash),
+                    self.explorer_link("Wallet", "address", &collection.owner),
+                ];
+                let fields = vec![
+                    ("Position".to_string(), collection.position_id.clone()),
+                    ("Collected".to_string(), vars["collected"].clone()),
+                    ("Gas".to_string(), format!("{} ETH", vars["gas_cost_eth"])),
+                ];
+                (fields, links)
+            }
+            Event::PositionRebalanced { rebalance, .. } => {
+                let mut links: Vec<Link> = rebalance
+                    .tx_hashes
+                    .iter()
+                    .map(|hash| self.explorer_link("Transaction", "tx", hash))
+                    .collect();
+                links.push(self.explorer_link("Wallet", "address", &rebalance.owner));
+                let fields = vec![
+                    ("Position".to_string(), rebalance.position_id.clone()),
+                    ("New position".to_string(), vars["new_position_id"].clone()),
+                    ("Range".to_string(), format!("{} → {}", vars["old_range"], vars["new_range"])),
+                    ("Gas".to_string(), format!("{} ETH", vars["gas_cost_eth"])),
+                ];
+                (fields, links)
+            }
+            Event::RebalanceFailed { failure, .. } => {
+                let links = vec![self.explorer_link("Wallet", "address", &failure.owner)];
+                let fields = vec![
+                    ("Position".to_string(), failure.position_id.clone()),
+                    ("Step".to_string(), failure.step.clone()),
+                    ("Workflow".to_string(), failure.workflow_id.clone()),
+                ];
+                (fields, links)
+            }
+            Event::PriceUpdated { price, .. } => {
+                let links = vec![self.explorer_link("Pool", "address", &price.pool)];
+                let fields = vec![
+                    ("Pair".to_string(), price.pair.clone()),
+                    ("Price".to_string(), vars["price"].clone()),
+                    ("TVL".to_string(), format!("${}", vars["tvl_usd"])),
+                ];
+                (fields, links)
+            }
+        };
+        let position_id = event.position_id();
+        let mut links = links;
+        // Uniswap v3 positions are NFTs with numeric ids
+        if position_id.chars().all(|c| c.is_ascii_digit()) {
+            links.push(Link {
+                label: "Position".to_string(),
+                url: format!("https://app.uniswap.org/positions/v3/arbitrum/{}", position_id),
+            });
+        }
+        Message {
+            event_type: event.kind(),
+            severity: event.severity(),
+            title: substitute(title, &vars),
+            body: substitute(body, &vars),
+            fields,
+            links,
+            thread_key: Some(position_id.to_string()),
+            timestamp: event.timestamp(),
+        }
+    }
+
+    fn explorer_link(&self, label: &str, kind: &str, address: &str) -> Link {
+        Link {
+            label: label.to_string(),
+            url: format!("{}/{}/{}", self.explorer_url, kind, address),
+        }
+    }
+
+    /// Placeholder values available to templates for an event
+    fn vars(&self, event: &Event) -> HashMap<&'static str, String> {
+        let mut vars = HashMap::new();
+        vars.insert("event_type", event.kind().to_string());
+        vars.insert("severity", format!("{:?}", event.severity()).to_uppercase());
+        vars.insert("position_id", event.position_id().to_string());
+        match event {
+            Event::RecommendationChanged { previous_action, recommendation, .. } => {
+                let position = &recommendation.position;
+                let action = recommendation.suggested_action.to_string();
+                let previous = previous_action.as_ref().map(|a| a.name().to_string());
+                vars.insert(
+                    "action_change",
+                    match &previous {
+                        Some(prev) => format!("{} (was {})", action, prev),
+                        None => action.clone(),
+                    },
+                );
+                vars.insert("action", action);
+                vars.insert("previous_action", previous.unwrap_or_else(|| "none".to_string()));
+                vars.insert("token", position.token_address.clone());
+                vars.insert("token_short", short_address(&position.token_address));
+                // Liquidity positions name their pair; single-token positions fall back to the address
+                let pair = position.liquidity.as_deref().map_or_else(|| short_address(&position.token_address), LiquidityMetrics::pair);
+                vars.insert("pair", pair);
+                vars.insert("wallet", position.user_address.clone());
+                vars.insert("wallet_short", short_address(&position.user_address));
+                vars.insert("score", format!("{:.2}", recommendation.recommendation_score));
+                vars.insert("value_usd", format!("{:.2}", position.value_usd));
+                vars.insert("reasoning", recommendation.reasoning.clone());
+            }
+            Event::PositionOutOfRange { range, .. } | Event::PositionBackInRange { range, .. } => {
+                vars.insert("wallet", range.owner.clone());
+                vars.insert("wallet_short", short_address(&range.owner));
+                vars.insert("pool", range.pool.clone());
+                vars.insert("pair", range.pair.clone());
+                vars.insert("tick_lower", range.tick_lower.to_string());
+                vars.insert("tick_upper", range.tick_upper.to_string());
+                vars.insert("current_tick", range.current_tick.to_string());
+            }
+            Event::FeesCollectable { fees, .. } => {
+                vars.insert("wallet", fees.owner.clone());
+                vars.insert("wallet_short", short_address(&fees.owner));
+                vars.insert("pair", format!("{}/{}", fees.token0_symbol, fees.token1_symbol));
+                vars.insert("token0_symbol", fees.token0_symbol.clone());
+                vars.insert("token1_symbol", fees.token1_symbol.clone());
+                vars.insert("amount0", format!("{:.6}", fees.amount0));
+                vars.insert("amount1", format!("{:.6}", fees.amount1));
+                vars.insert("value_usd", format!("{:.2}", fees.value_usd));
+                vars.insert(
+                    "collect_cost",
+                    fees.collect_gas_usd.map_or_else(|| "unknown".to_string(), |gas| format!("${:.2}", gas)),
+                );
+            }
+            Event::FeesCollected { collection, .. } => {
+                vars.insert("wallet", collection.owner.clone());
+                vars.insert("wallet_short", short_address(&collection.owner));
+                vars.insert("pair", format!("{}/{}", collection.token0_symbol, collection.token1_symbol));
+                vars.insert("token0_symbol", collection.token0_symbol.clone());
+                vars.insert("token1_symbol", collection.token1_symbol.clone());
+                vars.insert("amount0", format!("{:.6}", collection.amount0));
+                vars.insert("amount1", format!("{:.6}", collection.amount1));
+                vars.insert(
+                    "collected",
+                    format!(
+                        "{:.6} {} + {:.6} {}",
+                        collection.amount0, collection.token0_symbol, collection.amount1, collection.token1_symbol
+                    ),
+                );
+                vars.insert("tx_hash", collection.tx_hash.clone());
+                vars.insert("tx_hash_short", short_address(&collection.tx_hash));
+                vars.insert("block_number", collection.block_number.to_string());
+                vars.insert("gas_cost_eth", format!("{:.6}", collection.gas_cost_eth));
+            }
+            Event::PositionRebalanced { rebalance, .. } => {
+                vars.insert("wallet", rebalance.owner.clone());
+                vars.insert("wallet_short", short_address(&rebalance.owner));
+                vars.insert("pair", rebalance.pair.clone());
+                vars.insert("workflow_id", rebalance.workflow_id.clone());
+                vars.insert(
+                    "new_position_id",
+                    rebalance.new_position_id.clone().unwrap_or_else(|| "unknown".to_string()),
+                );
+                vars.insert("old_range", format!("[{}, {}]", rebalance.old_tick_lower, rebalance.old_tick_upper));
+                vars.insert("new_range", format!("[{}, {}]", rebalance.tick_lower, rebalance.tick_upper));
+                vars.insert("gas_cost_eth", format!("{:.6}", rebalance.gas_cost_eth));
+            }
+            Event::RebalanceFailed { failure, .. } => {
+                vars.insert("wallet", failure.owner.clone());
+                vars.insert("wallet_short", short_address(&failure.owner));
+                vars.insert("pair", failure.pair.clone());
+                vars.insert("workflow_id", failure.workflow_id.clone());
+                vars.insert("step", failure.step.clone());
+                vars.insert("error", failure.error.clone());
+            }
+            Event::PriceUpdated { price, .. } => {
+                vars.insert("pool", price.pool.clone());
+                vars.insert("pair", price.pair.clone());
+                vars.insert("price", format!("{:.6}", price.price));
+                vars.insert("tvl_usd", format!("{:.2}", price.tvl_usd));
+            }
+        }
+        vars
+    }
+}
+
+/// Built-in title and body for an event type
+fn default_template(event_type: &str) -> (&'static str, &'static str) {
+    match event_type {
+        "position_out_of_range" => (
+            "Out of range · {pair} #{position_id}",
+            "Pool tick {current_tick} is outside the position's range [{tick_lower}, {tick_upper}]; it is no longer earning fees.",
+        ),
+        "position_back_in_range" => (
+            "Back in range · {pair} #{position_id}",
+            "Pool tick {current_tick} is back inside [{tick_lower}, {tick_upper}].",
+        ),
+        "fees_collectable" => (
+            "Fees ready to collect · {pair} #{position_id}",
+            "${value_usd} in uncollected fees ({amount0} {token0_symbol} + {amount1} {token1_symbol}); collecting costs {collect_cost} in gas.",
+        ),
+        "fees_collected" => (
+            "Fees collected · {pair} #{position_id}",
+            "Collected {collected} in tx {tx_hash_short} (block {block_number}, gas {gas_cost_eth} ETH).",
+        ),
+        "position_rebalanced" => (
+            "Rebalanced · {pair} #{position_id}",
+            "Moved liquidity from {old_range} to {new_range} (new position #{new_position_id}, gas {gas_cost_eth} ETH).",
+        ),
+        "rebalance_failed" => (
+            "Rebalance failed · {pair} #{position_id}",
+            "Workflow {workflow_id} stopped at step {step}: {error}. Funds freed so far stay in the wallet until it is retried.",
+        ),
+        _ => (DEFAULT_TITLE, DEFAULT_BODY),
+    }
+}
+
+fn substitute(template: &str, vars: &HashMap<&'static str, String>) -> String {
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        out.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+        match after.find('}') {
+            Some(end) if vars.contains_key(&after[..end]) => {
+                out.push_str(&vars[&after[..end]]);
+                rest = &after[end + 1..];
+            }
+            _ => {
+                out.push('{');
+                rest = after;
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::position::{Action, Position, PositionRecommendation};
+    use rust_decimal::Decimal;
+
+    pub(crate) fn exit_event() -> Event {
+        Event::RecommendationChanged {
+            timestamp: Utc::now(),
+            previous_action: Some(Action::Hold),
+            recommendation: PositionRecommendation {
+                position: Position::new(
+                    "42".to_string(),
+                    "0x1111111111111111111111111111111111111111".to_string(),
+                    "0x912ce59144191c1204e64559fe8253a0e49e6548".to_string(),
+                    Decimal::ONE,
+                    Decimal::new(12345, 2),
+                ),
+                recommendation_score: 0.256,
+                reasoning: "High risk <volatile>".to_string(),
+                suggested_action: Action::Exit,
+                fee_apr: None,
+                projected_il: None,
+                rebalance: None,
+            },
+        }
+    }
+
+    #[test]
+    fn test_default_rendering() {
+        let message = Templates::new(HashMap::new(), "https://arbiscan.io/").render(&exit_event());
+        assert_eq!(message.severity, Severity::Critical);
+        assert_eq!(message.title, "Exit · 0x912c…6548");
+        assert_eq!(message.headline(), "🚨 Exit · 0x912c…6548");
+        assert_eq!(message.fields[0].1, "Exit (was Hold)");
+        assert_eq!(message.fields[1].1, "0.26");
+        assert_eq!(message.fields[2].1, "$123.45");
+        assert_eq!(message.fields[4].1, "0x912c…6548");
+        assert_eq!(message.links[0].url, "https://arbiscan.io/token/0x912ce59144191c1204e64559fe8253a0e49e6548");
+        assert_eq!(message.links.len(), 3);
+
+        // A liquidity position shows its pair instead of the token address
+        let Event::RecommendationChanged { timestamp, previous_action, mut recommendation } = exit_event() else {
+            unreachable!()
+        };
+        recommendation.position.liquidity = Some(Box::new(LiquidityMetrics {
+            protocol: "uniswap_v3".to_string(),
+            pool: "0xc6962004f452be9203591991d15f6b388e09e8d0".to_string(),
+            token0: "0x82af49447d8a07e3bd95bd0d56f35241523fbab1".to_string(),
+            token1: "0xaf88d065e77c8cc2239327c5edb3a432268e5831".to_string(),
+            token0_symbol: "WETH".to_string(),
+            token1_symbol: "USDC".to_string(),
+            fee: 500,
+            tick_lower: -200_000,
+            tick_upper: -190_000,
+            current_tick: -195_000,
+            liquidity: "1000".to_string(),
+        }));
+        let event = Event::RecommendationChanged { timestamp, previous_action, recommendation };
+        let message = Templates::new(HashMap::new(), "https://arbiscan.io/").render(&event);
+        assert_eq!(message.title, "Exit · WETH/USDC");
+        assert_eq!(message.fields[4], ("Token".to_string(), "WETH/USDC".to_string()));
+    }
+
+    #[test]
+    fn test_range_rendering() {
+        let event = Event::PositionOutOfRange {
+            timestamp: Utc::now(),
+            range: crate::events::RangeStatus {
+                position_id: "7".to_string(),
+                owner: "0x1111111111111111111111111111111111111111".to_string(),
+                pool: "0xc6962004f452be9203591991d15f6b388e09e8d0".to_string(),
+                pair: "WETH/USDC".to_string(),
+                tick_lower: -200,
+                tick_upper: 200,
+                current_tick: 250,
+            },
+        };
+        let message = Templates::new(HashMap::new(), "https://arbiscan.io").render(&event);
+        assert_eq!(message.severity, Severity::Critical);
+        assert_eq!(message.title, "Out of range · WETH/USDC #7");
+        assert!(message.body.starts_with("Pool tick 250 is outside the position's range [-200, 200]"));
+        assert_eq!(message.links[0].label, "Pool");
+        assert_eq!(message.thread_key.as_deref(), Some("7"));
+    }
+
+    #[test]
+    fn test_template_override() {
+        let mut overrides = HashMap::new();
+        overrides.insert(
+            "recommendation_changed".to_string(),
+            TemplateConfig {
+                title: Some("[{severity}] {action} {position_id} {unknown}".to_string()),
+                body: None,
+            },
+        );
+        let message = Templates::new(overrides, "https://arbiscan.io").render(&exit_event());
+        assert_eq!(message.title, "[CRITICAL] Exit 42 {unknown}");
+        assert_eq!(message.body, "High risk <volatile>");
+    }
+}
@@ -0,0 +1,101 @@
+use axum::response::{Html, IntoResponse};
+use axum::Json;
+use utoipa::openapi::security::{ApiKey, ApiKeyValue, HttpAuthScheme, HttpBuilder, SecurityScheme};
+use utoipa::{Modify, OpenApi};
+
+use crate::{api_keys, rest, server};
+
+/// OpenAPI description of the server-mode REST API
+#[derive(OpenApi)]
+#[openapi(
+    info(title = "Origins Onchain Position Recommender API"),
+    paths(
+        server::healthz,
+        server::readyz,
+        server::graphql_handler,
+        rest::recommendations,
+        rest::history,
+        rest::pnl,
+        rest::tax_report,
+        rest::metrics,
+        rest::quotes,
+        server::events_sse,
+        server::trigger_cycle,
+        server::list_mutes,
+        server::mute_position,
+        server::unmute_position,
+        server::list_rebalances,
+        server::request_rebalance,
+        server::paper_wallet,
+        server::list_audit,
+        api_keys::list_keys,
+        api_keys::create_key,
+        api_keys::revoke_key,
+    ),
+    modifiers(&SecuritySchemes),
+    tags(
+        (name = "probes", description = "Kubernetes liveness/readiness probes"),
+        (name = "query", description = "Positions, recommendations and pool data"),
+        (name = "events", description = "Recommendation and alert event stream"),
+        (name = "admin", description = "Key management and operations (admin scope)")
+    )
+)]
+pub struct ApiDoc;
+
+struct SecuritySchemes;
+
+impl Modify for SecuritySchemes {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let components = openapi.components.get_or_insert_with(Default::default);
+        components.add_security_scheme(
+            "api_key",
+            SecurityScheme::ApiKey(ApiKey::Header(ApiKeyValue::new(api_keys::API_KEY_HEADER))),
+        );
+        components.add_security_scheme(
+            "bearer",
+            SecurityScheme::Http(HttpBuilder::new().scheme(HttpAuthScheme::Bearer).bearer_format("JWT").build()),
+        );
+    }
+}
+
+/// GET /openapi.json
+pub async fn openapi_json() -> impl IntoResponse {
+    Json(ApiDoc::openapi())
+}
+
+/// GET /docs: Swagger UI (assets from the unpkg CDN) pointed at /openapi.json
+pub async fn swagger_ui() -> impl IntoResponse {
+    Html(
+        r##"<!doctype html>
+<html>
+  <head>
+    <meta charset="utf-8" />
+    <title>Origins Recommender API</title>
+    <link rel="stylesheet" href="https://unpkg.com/swagger-ui-dist@5/swagger-ui.css" />
+  </head>
+  <body>
+    <div id="swagger-ui"></div>
+    <script src="https://unpkg.com/swagger-ui-dist@5/swagger-ui-bundle.js"></script>
+    <script>
+      window.ui = SwaggerUIBundle({ url: "/openapi.json", dom_id: "#swagger-ui" });
+    </script>
+  </body>
+</html>"##,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_spec_lists_routes_and_schemes() {
+        let spec = ApiDoc::openapi();
+        for path in ["/healthz", "/readyz", "/graphql", "/events", "/history", "/admin/api-keys", "/admin/api-keys/{id}"] {
+            assert!(spec.paths.paths.contains_key(path), "missing {}", path);
+        }
+        let components = spec.components.expect("components");
+        assert!(components.security_schemes.contains_key("bearer"));
+        assert!(components.schemas.contains_key("CreatedApiKey"));
+    }
+}
@@ -0,0 +1,355 @@
+//! Pages on-call engineers (PagerDuty or Opsgenie) for system failures, separately from the
+//! trading notifications: RPC down, subgraph stale, recommendation cycles failing.
+
+use anyhow::Result;
+use async_trait::async_trait;
+use chrono::Utc;
+use reqwest::Client;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::time::Duration;
+use tokio::time::{interval, timeout, MissedTickBehavior};
+use tracing::{info, warn};
+
+use crate::config::{OpsAlertConfig, OpsAlertProvider};
+use crate::health::HealthState;
+use crate::notifier::{http_client, post_json};
+use crate::uniswap::UniswapClient;
+
+const SOURCE: &str = "origins-position-recommender";
+const PAGERDUTY_EVENTS_URL: &str = "https://events.pagerduty.com/v2/enqueue";
+const OPSGENIE_API_URL: &str = "https://api.opsgenie.com";
+const PROBE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// A system condition that can page
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum OpsCheck {
+    Rpc,
+    Subgraph,
+    Cycles,
+}
+
+impl OpsCheck {
+    pub fn name(self) -> &'static str {
+        match self {
+            OpsCheck::Rpc => "rpc",
+            OpsCheck::Subgraph => "subgraph",
+            OpsCheck::Cycles => "recommendation_cycles",
+        }
+    }
+
+    /// Stable key so repeated triggers update one incident and the resolve closes it
+    pub fn dedup_key(self) -> String {
+        format!("{}:{}", SOURCE, self.name())
+    }
+
+    /// Stale data degrades recommendations; the others stop the service outright
+    fn critical(self) -> bool {
+        !matches!(self, OpsCheck::Subgraph)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Incident {
+    pub check: OpsCheck,
+    pub summary: String,
+    pub details: String,
+}
+
+#[async_trait]
+pub trait Pager: Send + Sync {
+    fn name(&self) -> &'static str;
+    async fn trigger(&self, incident: &Incident) -> Result<()>;
+    async fn resolve(&self, check: OpsCheck) -> Result<()>;
+}
+
+/// PagerDuty Events API v2
+pub struct PagerDuty {
+    http: Client,
+    url: String,
+    routing_key: String,
+}
+
+impl PagerDuty {
+    pub fn new(routing_key: &str, url: Option<&str>) -> Self {
+        Self {
+            http: http_client(),
+            url: url.unwrap_or(PAGERDUTY_EVENTS_URL).to_string(),
+            routing_key: routing_key.to_string(),
+        }
+    }
+
+    pub fn trigger_payload(&self, incident: &Incident) -> Value {
+        json!({
+            "routing_key": self.routing_key,
+            "event_action": "trigger",
+            "dedup_key": incident.check.dedup_key(),
+            "payload": {
+                "summary": incident.summary,
+                "source": SOURCE,
+                "severity": if incident.check.critical() { "critical" } else { "error" },
+                "component": incident.check.name(),
+                "timestamp": Utc::now().to_rfc3339(),
+                "custom_details": { "detail": incident.details },
+            }
+        })
+    }
+}
+
+#[async_trait]
+impl Pager for PagerDuty {
+    fn name(&self) -> &'static str {
+        "pagerduty"
+    }
+
+    async fn trigger(&self, incident: &Incident) -> Result<()> {
+        post_json(&self.http, &self.url, &self.trigger_payload(incident)).await?;
+        Ok(())
+    }
+
+    async fn resolve(&self, check: OpsCheck) -> Result<()> {
+        let body = json!({
+            "routing_key": self.routing_key,
+            "event_action": "resolve",
+            "dedup_key": check.dedup_key(),
+        });
+        post_json(&self.http, &self.url, &body).await?;
+        Ok(())
+    }
+}
+
+/// Opsgenie Alert API; incidents are keyed by alias
+pub struct Opsgenie {
+    http: Client,
+    base_url: String,
+    api_key: String,
+}
+
+impl Opsgenie {
+    pub fn new(api_key: &str, base_url: Option<&str>) -> Self {
+        Self {
+            http: http_client(),
+            base_url: base_url.unwrap_or(OPSGENIE_API_URL).trim_end_matches('/').to_string(),
+            api_key: api_key.to_string(),
+        }
+    }
+
+    pub fn create_payload(incident: &Incident) -> Value {
+        json!({
+            "message": incident.summary,
+            "alias": incident.check.dedup_key(),
+            "description": incident.details,
+            "priority": if incident.check.critical() { "P1" } else { "P2" },
+            "source": SOURCE,
+            "tags": ["origins", incident.check.name()],
+        })
+    }
+
+    async fn post(&self, url: &str, body: &Value) -> Result<()> {
+        self.http
+            .post(url)
+            .header("Authorization", format!("GenieKey {}", self.api_key))
+            .json(body)
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Pager for Opsgenie {
+    fn name(&self) -> &'static str {
+        "opsgenie"
+    }
+
+    async fn trigger(&self, incident: &Incident) -> Result<()> {
+        let url = format!("{}/v2/alerts", self.base_url);
+        self.post(&url, &Self::create_payload(incident)).await
+    }
+
+    async fn resolve(&self, check: OpsCheck) -> Result<()> {
+        let url = format!("{}/v2/alerts/{}/close?identifierType=alias", self.base_url, check.dedup_key());
+        self.post(&url, &json!({ "source": SOURCE })).await
+    }
+}
+
+pub fn pager_from_config(config: &OpsAlertConfig) -> Box<dyn Pager> {
+    match config.provider {
+        OpsAlertProvider::PagerDuty => Box::new(PagerDuty::new(&config.api_key, config.api_url.as_deref())),
+        OpsAlertProvider::Opsgenie => Box::new(Opsgenie::new(&config.api_key, config.api_url.as_deref())),
+    }
+}
+
+/// What to tell the pager after a check
+#[derive(Debug, PartialEq)]
+enum Transition {
+    Trigger,
+    Resolve,
+}
+
+/// Per-check failure streaks and whether an incident is open
+#[derive(Default)]
+struct CheckStates {
+    streaks: HashMap<OpsCheck, u32>,
+    open: HashMap<OpsCheck, bool>,
+}
+
+impl CheckStates {
+    /// Record a check result; an incident opens after `required` failures in a row and
+    /// resolves on the first success
+    fn record(&mut self, check: OpsCheck, failing: bool, required: u32) -> Option<Transition> {
+        let streak = self.streaks.entry(check).or_default();
+        *streak = if failing { *streak + 1 } else { 0 };
+        let open = self.open.entry(check).or_default();
+        match (failing && *streak >= required.max(1), *open) {
+            (true, false) => {
+                *open = true;
+                Some(Transition::Trigger)
+            }
+            (false, true) if !failing => {
+                *open = false;
+                Some(Transition::Resolve)
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Periodically probes the RPC, the subgraph and the recommendation loop and pages on failure
+pub struct OpsMonitor {
+    pager: Box<dyn Pager>,
+    uniswap: UniswapClient,
+    rpc_url: String,
+    health: HealthState,
+    config: OpsAlertConfig,
+    states: CheckStates,
+}
+
+impl OpsMonitor {
+    pub fn new(pager: Box<dyn Pager>, uniswap: UniswapClient, rpc_url: &str, health: HealthState, config: &OpsAlertConfig) -> Self {
+        Self {
+            pager,
+            uniswap,
+            rpc_url: rpc_url.to_string(),
+            health,
+            config: config.clone(),
+            states: CheckStates::default(),
+        }
+    }
+
+    pub async fn run(mut self) {
+        info!(target: "ops_alerts", provider = self.pager.name(), "ops alerting enabled");
+        let mut ticker = interval(Duration::from_secs(self.config.check_interval_secs.max(1)));
+        ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        loop {
+            ticker.tick().await;
+            let probe_failures = self.config.probe_failures_before_alert;
+            let rpc = self.check_rpc().await;
+            self.apply(OpsCheck::Rpc, rpc, probe_failures).await;
+            let subgraph = self.check_subgraph().await;
+            self.apply(OpsCheck::Subgraph, subgraph, probe_failures).await;
+            // The health state already counts consecutive failures
+            let cycles = self.check_cycles().await;
+            self.apply(OpsCheck::Cycles, cycles, 1).await;
+        }
+    }
+
+    /// `Err(detail)` when the check fails
+    async fn check_rpc(&self) -> std::result::Result<(), String> {
+        match timeout(PROBE_TIMEOUT, self.uniswap.block_number(&self.rpc_url)).await {
+            Ok(Ok(_)) => Ok(()),
+            Ok(Err(e)) => Err(format!("RPC error: {:#}", e)),
+            Err(_) => Err(format!("RPC probe timed out after {:?}", PROBE_TIMEOUT)),
+        }
+    }
+
+    async fn check_subgraph(&self) -> std::result::Result<(), String> {
+        match timeout(PROBE_TIMEOUT, self.uniswap.subgraph_meta()).await {
+            Ok(Ok(meta)) => {
+                let lag = meta.timestamp.map(|ts| (Utc::now().timestamp() as u64).saturating_sub(ts));
+                match lag {
+                    Some(lag) if lag > self.config.max_subgraph_lag_secs => Err(format!(
+                        "indexed block {} is {}s behind (threshold {}s)",
+                        meta.number, lag, self.config.max_subgraph_lag_secs
+                    )),
+                    _ => Ok(()),
+                }
+            }
+            Ok(Err(e)) => Err(format!("subgraph error: {:#}", e)),
+            Err(_) => Err(format!("subgraph probe timed out after {:?}", PROBE_TIMEOUT)),
+        }
+    }
+
+    async fn check_cycles(&self) -> std::result::Result<(), String> {
+        let health = self.health.snapshot().await;
+        if health.consecutive_cycle_failures >= self.config.max_cycle_failures.max(1) {
+            Err(format!(
+                "{} consecutive recommendation cycles failed; last error: {}",
+                health.consecutive_cycle_failures,
+                health.last_cycle_error.unwrap_or_default()
+            ))
+        } else {
+            Ok(())
+        }
+    }
+
+    async fn apply(&mut self, check: OpsCheck, result: std::result::Result<(), String>, required: u32) {
+        let transition = self.states.record(check, result.is_err(), required);
+        let outcome = match (transition, result) {
+            (Some(Transition::Trigger), Err(details)) => {
+                let incident = Incident {
+                    check,
+                    summary: summary(check),
+                    details,
+                };
+                warn!(target: "ops_alerts", check = check.name(), detail = %incident.details, "paging on-call");
+                self.pager.trigger(&incident).await
+            }
+            (Some(Transition::Resolve), _) => {
+                info!(target: "ops_alerts", check = check.name(), "check recovered, resolving incident");
+                self.pager.resolve(check).await
+            }
+            _ => return,
+        };
+        if let Err(e) = outcome {
+            warn!(target: "ops_alerts", provider = self.pager.name(), check = check.name(), "pager request failed: {:#}", e);
+        }
+    }
+}
+
+fn summary(check: OpsCheck) -> String {
+    match check {
+        OpsCheck::Rpc => "Origins recommender: RPC endpoint unreachable".to_string(),
+        OpsCheck::Subgraph => "Origins recommender: Uniswap subgraph stale or unreachable".to_string(),
+        OpsCheck::Cycles => "Origins recommender: recommendation cycles failing".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_incident_lifecycle() {
+        let mut states = CheckStates::default();
+        assert_eq!(states.record(OpsCheck::Rpc, true, 3), None);
+        assert_eq!(states.record(OpsCheck::Rpc, true, 3), None);
+        assert_eq!(states.record(OpsCheck::Rpc, true, 3), Some(Transition::Trigger));
+        assert_eq!(states.record(OpsCheck::Rpc, true, 3), None);
+        assert_eq!(states.record(OpsCheck::Rpc, false, 3), Some(Transition::Resolve));
+        assert_eq!(states.record(OpsCheck::Rpc, false, 3), None);
+        assert_eq!(states.record(OpsCheck::Cycles, true, 1), Some(Transition::Trigger));
+
+        let pagerduty = PagerDuty::new("key", None);
+        let incident = Incident {
+            check: OpsCheck::Subgraph,
+            summary: summary(OpsCheck::Subgraph),
+            details: "behind".to_string(),
+        };
+        let payload = pagerduty.trigger_payload(&incident);
+        assert_eq!(payload["dedup_key"], "origins-position-recommender:subgraph");
+        assert_eq!(payload["payload"]["severity"], "error");
+        assert_eq!(Opsgenie::create_payload(&incident)["priority"], "P2");
+    }
+}
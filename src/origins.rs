@@ -0,0 +1,282 @@
+//! Positions held in the Origins protocol, read live from the Origins contract with the ABI
+//! at `blockchain.origins_abi_path`.
+//!
+//! The contract's `blockchain.origins_positions_function` (default `getPositions`) must be
+//! a view returning an array of structs, and take no arguments or a single wallet address
+//! (called once per `blockchain.origins_wallets` entry). Struct fields are matched by name,
+//! so their order doesn't matter: `id` / `positionId` / `tokenId`, `owner` / `user` /
+//! `account`, `token` / `asset`, `amount` / `balance` (token base units), and optionally
+//! `valueUsd` / `value` (USD with 18 decimals). Without a value field positions are priced
+//! from the subgraph.
+
+use std::fs::File;
+
+use anyhow::{anyhow, bail, Context, Result};
+use ethabi::{Contract, Function, ParamType, Token as AbiToken};
+use ethereum_types::{Address, U256};
+use tracing::{debug, info, warn};
+
+use crate::config::Config;
+use crate::position::Position;
+use crate::uniswap::UniswapClient;
+use crate::utils::{from_base_units, to_decimal, to_units};
+
+/// Decimals of the optional USD value field
+const VALUE_DECIMALS: u8 = 18;
+
+/// Names a struct field may have for each position attribute, lowercase without underscores
+const ID_FIELDS: &[&str] = &["id", "positionid", "tokenid"];
+const OWNER_FIELDS: &[&str] = &["owner", "user", "account"];
+const TOKEN_FIELDS: &[&str] = &["token", "asset"];
+const AMOUNT_FIELDS: &[&str] = &["amount", "balance"];
+const VALUE_FIELDS: &[&str] = &["valueusd", "value", "usdvalue"];
+
+/// Where each attribute sits in the returned struct
+#[derive(Debug, Clone, PartialEq)]
+struct Layout {
+    id: usize,
+    owner: usize,
+    token: usize,
+    amount: usize,
+    value: Option<usize>,
+}
+
+impl Layout {
+    fn from_fields(fields: &[String]) -> Result<Self> {
+        let find = |names: &[&str]| {
+            fields
+                .iter()
+                .position(|field| names.contains(&field.trim_start_matches('_').replace('_', "").to_lowercase().as_str()))
+        };
+        let require = |names: &[&str]| find(names).ok_or_else(|| anyhow!("positions struct has no {} field (fields: {})", names[0], fields.join(", ")));
+        Ok(Self {
+            id: require(ID_FIELDS)?,
+            owner: require(OWNER_FIELDS)?,
+            token: require(TOKEN_FIELDS)?,
+            amount: require(AMOUNT_FIELDS)?,
+            value: find(VALUE_FIELDS),
+        })
+    }
+}
+
+/// One position as returned by the contract
+#[derive(Debug, Clone, PartialEq)]
+struct RawPosition {
+    id: U256,
+    owner: Address,
+    token: Address,
+    amount: U256,
+    value_usd: Option<U256>,
+}
+
+pub struct OriginsAdapter {
+    client: UniswapClient,
+    rpc_url: String,
+    contract: String,
+    function: Function,
+    layout: Layout,
+    wallets: Vec<Address>,
+}
+
+impl OriginsAdapter {
+    /// The adapter for the configured contract; `None` without `blockchain.origins_abi_path`
+    /// or with the zero contract address
+    pub fn from_config(client: UniswapClient, config: &Config) -> Result<Option<Self>> {
+        let Some(blockchain) = &config.blockchain else {
+            return Ok(None);
+        };
+        let Some(abi_path) = &blockchain.origins_abi_path else {
+            return Ok(None);
+        };
+        // The section's address, or the top-level one when the section leaves it unset
+        let contract: Address = [&blockchain.origins_contract_address, &config.origins_contract_address]
+            .into_iter()
+            .filter_map(|a| a.parse::<Address>().ok())
+            .find(|a| !a.is_zero())
+            .ok_or_else(|| anyhow!("origins_abi_path is set but no origins_contract_address is"))?;
+        let name = &blockchain.origins_positions_function;
+        let file = File::open(abi_path).with_context(|| format!("opening Origins ABI {}", abi_path))?;
+        let abi = Contract::load(file).with_context(|| format!("parsing Origins ABI {}", abi_path))?;
+        let function = abi.function(name).with_context(|| format!("Origins ABI has no {} function", name))?.clone();
+        let json: serde_json::Value = serde_json::from_reader(File::open(abi_path)?)?;
+        let layout = Layout::from_fields(&struct_fields(&json, name)?).with_context(|| format!("Origins {} output", name))?;
+        let wallets = blockchain
+            .origins_wallets
+            .iter()
+            .map(|w| w.parse::<Address>().with_context(|| format!("invalid Origins wallet {}", w)))
+            .collect::<Result<Vec<_>>>()?;
+        match function.inputs.iter().map(|p| &p.kind).collect::<Vec<_>>().as_slice() {
+            [] => {}
+            [ParamType::Address] if !wallets.is_empty() => {}
+            [ParamType::Address] => bail!("Origins {} takes a wallet; set blockchain.origins_wallets", name),
+            _ => bail!("Origins {} must take no arguments or a single address", name),
+        }
+        Ok(Some(Self {
+            client,
+            rpc_url: config.active_chain().rpc_url,
+            contract: format!("{:?}", contract),
+            function,
+            layout,
+            wallets,
+        }))
+    }
+
+    /// Every position the contract lists, valued in USD
+    pub async fn positions(&self) -> Result<Vec<Position>> {
+        let calls: Vec<(String, Vec<u8>)> = if self.function.inputs.is_empty() {
+            vec![(self.contract.clone(), self.function.encode_input(&[])?)]
+        } else {
+            self.wallets
+                .iter()
+                .map(|w| Ok((self.contract.clone(), self.function.encode_input(&[AbiToken::Address(*w)])?)))
+                .collect::<Result<_>>()?
+        };
+        let mut raw = Vec::new();
+        for result in self.client.eth_call_batch(&self.rpc_url, &calls).await? {
+            let output = self.function.decode_output(&result?).context("decoding Origins positions")?;
+            raw.extend(decode_positions(&output, &self.layout)?);
+        }
+
+        let tokens: Vec<String> = raw.iter().map(|p| format!("{:?}", p.token)).collect();
+        let token_refs: Vec<&str> = tokens.iter().map(String::as_str).collect();
+        let decimals = self
+            .client
+            .tokens_decimals(&self.rpc_url, &token_refs)
+            .await
+            .into_iter()
+            .collect::<Result<Vec<u8>>>()
+            .context("reading Origins token decimals")?;
+        let prices = if self.layout.value.is_none() && !raw.is_empty() {
+            match self.client.token_prices_usd(&token_refs).await {
+                Ok(prices) => Some(prices),
+                Err(e) => {
+                    warn!(target: "origins", "pricing Origins positions failed: {:#}", e);
+                    None
+                }
+            }
+        } else {
+            None
+        };
+
+        let mut positions = Vec::with_capacity(raw.len());
+        for ((position, token), decimals) in raw.iter().zip(&tokens).zip(decimals) {
+            let value_usd = match position.value_usd {
+                Some(value) => from_base_units(value, VALUE_DECIMALS)?,
+                None => {
+                    let price = prices.as_ref().and_then(|p| p.usd(token)).unwrap_or(0.0);
+                    to_decimal(to_units(position.amount, decimals) * price)
+                }
+            };
+            debug!(target: "origins", id = %position.id, token = %token, %value_usd, "Origins position");
+            positions.push(Position::new(
+                format!("origins:{}", position.id),
+                format!("{:?}", position.owner),
+                token.clone(),
+                from_base_units(position.amount, decimals)?,
+                value_usd,
+            ));
+        }
+        info!(target: "origins", contract = %self.contract, positions = positions.len(), "fetched Origins positions");
+        Ok(positions)
+    }
+}
+
+/// Field names of the struct array `function` returns, from the raw ABI JSON (ethabi keeps
+/// tuple component types but drops their names)
+fn struct_fields(abi: &serde_json::Value, function: &str) -> Result<Vec<String>> {
+    let entry = abi
+        .as_array()
+        .into_iter()
+        .flatten()
+        .find(|e| e["type"] == "function" && e["name"] == function)
+        .ok_or_else(|| anyhow!("no {} function", function))?;
+    let output = match entry["outputs"].as_array().map(Vec::as_slice) {
+        Some([output]) if output["type"] == "tuple[]" => output,
+        _ => bail!("{} must return a single struct array", function),
+    };
+    Ok(output["components"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .map(|c| c["name"].as_str().unwrap_or_default().to_string())
+        .collect())
+}
+
+fn decode_positions(output: &[AbiToken], layout: &Layout) -> Result<Vec<RawPosition>> {
+    let Some(AbiToken::Array(items)) = output.first() else {
+        bail!("expected a struct array");
+    };
+    items
+        .iter()
+        .map(|item| {
+            let AbiToken::Tuple(fields) = item else {
+                bail!("expected a struct");
+            };
+            let field = |i: usize| fields.get(i).cloned().ok_or_else(|| anyhow!("struct field {} missing", i));
+            let uint = |i: usize| field(i)?.into_uint().ok_or_else(|| anyhow!("struct field {} is not a uint", i));
+            let address = |i: usize| field(i)?.into_address().ok_or_else(|| anyhow!("struct field {} is not an address", i));
+            Ok(RawPosition {
+                id: uint(layout.id)?,
+                owner: address(layout.owner)?,
+                token: address(layout.token)?,
+                amount: uint(layout.amount)?,
+                value_usd: layout.value.map(uint).transpose()?,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal::Decimal;
+
+    const ABI: &str = r#"[{
+        "type": "function",
+        "name": "getPositions",
+        "stateMutability": "view",
+        "inputs": [{ "name": "user", "type": "address" }],
+        "outputs": [{
+            "name": "",
+            "type": "tuple[]",
+            "components": [
+                { "name": "token", "type": "address" },
+                { "name": "_positionId", "type": "uint256" },
+                { "name": "amount", "type": "uint256" },
+                { "name": "owner", "type": "address" }
+            ]
+        }]
+    }]"#;
+
+    #[test]
+    fn test_struct_fields_are_matched_by_name() {
+        let json: serde_json::Value = serde_json::from_str(ABI).unwrap();
+        let fields = struct_fields(&json, "getPositions").unwrap();
+        let layout = Layout::from_fields(&fields).unwrap();
+        assert_eq!(layout, Layout { id: 1, owner: 3, token: 0, amount: 2, value: None });
+        assert!(struct_fields(&json, "positions").is_err());
+        let missing = Layout::from_fields(&["token".to_string(), "amount".to_string()]).unwrap_err();
+        assert!(missing.to_string().contains("no id field"), "{}", missing);
+    }
+
+    #[test]
+    fn test_decode_positions() {
+        let abi = Contract::load(ABI.as_bytes()).unwrap();
+        let function = abi.function("getPositions").unwrap();
+        let (token, owner): (Address, Address) = ("0x00000000000000000000000000000000000000aa".parse().unwrap(), Address::repeat_byte(0x11));
+        let encoded = ethabi::encode(&[AbiToken::Array(vec![AbiToken::Tuple(vec![
+            AbiToken::Address(token),
+            AbiToken::Uint(U256::from(7)),
+            AbiToken::Uint(U256::exp10(18)),
+            AbiToken::Address(owner),
+        ])])]);
+        let output = function.decode_output(&encoded).unwrap();
+        let layout = Layout { id: 1, owner: 3, token: 0, amount: 2, value: None };
+        let positions = decode_positions(&output, &layout).unwrap();
+        assert_eq!(
+            positions,
+            vec![RawPosition { id: U256::from(7), owner, token, amount: U256::exp10(18), value_usd: None }]
+        );
+        assert_eq!(from_base_units(positions[0].amount, 18).unwrap(), Decimal::ONE);
+    }
+}
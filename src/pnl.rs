@@ -0,0 +1,617 @@
+//! Per-position PnL ledger: deposits, withdrawals, fee collections and valuations are
+//! appended as they happen, and PnL and tax figures are replayed from the entries.
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Datelike, Utc};
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::time::{interval, MissedTickBehavior};
+use tracing::{debug, info, warn};
+
+use crate::alerts::PositionInfo;
+use crate::config::PnlConfig;
+use crate::events::{Event, EventBus, FeeCollection, RebalanceSummary};
+use crate::executor::{ExitPosition, Snapshot};
+use crate::storage::Storage;
+use crate::uniswap::UniswapClient;
+use crate::utils::{sum_decimal, to_decimal};
+
+/// Relative liquidity change a valuation must show before it counts as a deposit or
+/// withdrawal made outside this process
+const LIQUIDITY_TOLERANCE: f64 = 1e-6;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "server", derive(utoipa::ToSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum LedgerKind {
+    Deposit,
+    Withdrawal,
+    FeeCollection,
+    Valuation,
+}
+
+impl LedgerKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LedgerKind::Deposit => "deposit",
+            LedgerKind::Withdrawal => "withdrawal",
+            LedgerKind::FeeCollection => "fee_collection",
+            LedgerKind::Valuation => "valuation",
+        }
+    }
+}
+
+/// One ledger line; USD values are taken at `timestamp`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "server", derive(utoipa::ToSchema))]
+pub struct LedgerEntry {
+    pub position_id: String,
+    pub owner: String,
+    pub kind: LedgerKind,
+    pub timestamp: DateTime<Utc>,
+    /// Token amounts moved, collected, or (valuations) held
+    pub amount0: f64,
+    pub amount1: f64,
+    pub value_usd: f64,
+    /// Liquidity moved, or (valuations) held; pro-rates the cost basis on withdrawals
+    pub liquidity: f64,
+    /// Valuations only: fees earned but not yet collected
+    #[serde(default)]
+    pub uncollected_fees_usd: f64,
+    #[serde(default)]
+    pub gas_usd: f64,
+    pub tx_hash: Option<String>,
+    /// e.g. `rebalance <workflow id>`, `exit-all`, `opening balance`
+    pub note: Option<String>,
+}
+
+impl LedgerEntry {
+    fn new(kind: LedgerKind, position_id: &str, owner: &str, timestamp: DateTime<Utc>) -> Self {
+        Self {
+            position_id: position_id.to_string(),
+            owner: owner.to_string(),
+            kind,
+            timestamp,
+            amount0: 0.0,
+            amount1: 0.0,
+            value_usd: 0.0,
+            liquidity: 0.0,
+            uncollected_fees_usd: 0.0,
+            gas_usd: 0.0,
+            tx_hash: None,
+            note: None,
+        }
+    }
+
+    fn valuation(position_id: &str, owner: &str, snapshot: &Snapshot, timestamp: DateTime<Utc>) -> Self {
+        Self {
+            amount0: snapshot.amount0,
+            amount1: snapshot.amount1,
+            value_usd: snapshot.usd(snapshot.amount0, snapshot.amount1),
+            liquidity: snapshot.liquidity,
+            uncollected_fees_usd: snapshot.usd(snapshot.fees0, snapshot.fees1),
+            ..Self::new(LedgerKind::Valuation, position_id, owner, timestamp)
+        }
+    }
+}
+
+/// Filters for ledger entries
+#[derive(Debug, Clone, Default)]
+pub struct LedgerQuery {
+    pub position_id: Option<String>,
+    pub owner: Option<String>,
+    pub kind: Option<LedgerKind>,
+    /// Inclusive
+    pub since: Option<DateTime<Utc>>,
+    /// Exclusive
+    pub until: Option<DateTime<Utc>>,
+}
+
+impl LedgerQuery {
+    pub fn matches(&self, entry: &LedgerEntry) -> bool {
+        self.position_id.as_deref().is_none_or(|id| entry.position_id == id)
+            && self.owner.as_deref().is_none_or(|owner| entry.owner.eq_ignore_ascii_case(owner))
+            && self.kind.is_none_or(|kind| entry.kind == kind)
+            && self.since.is_none_or(|since| entry.timestamp >= since)
+            && self.until.is_none_or(|until| entry.timestamp < until)
+    }
+}
+
+/// A position's PnL as replayed from its ledger
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "server", derive(utoipa::ToSchema))]
+pub struct PositionPnl {
+    pub position_id: String,
+    pub owner: String,
+    /// Liquidity still held
+    pub liquidity: f64,
+    pub deposited_usd: f64,
+    pub withdrawn_usd: f64,
+    /// Cost basis of the liquidity still held
+    pub cost_basis_usd: f64,
+    /// Latest valuation, adjusted for later deposits and withdrawals
+    pub value_usd: f64,
+    pub uncollected_fees_usd: f64,
+    pub fees_collected_usd: f64,
+    pub gas_usd: f64,
+    /// Withdrawal proceeds less the cost basis they released
+    pub realized_usd: f64,
+    /// `value_usd` less `cost_basis_usd`
+    pub unrealized_usd: f64,
+    /// Realized and unrealized gains plus collected and uncollected fees, less gas
+    pub pnl_usd: f64,
+    pub valued_at: Option<DateTime<Utc>>,
+}
+
+/// A withdrawal with the cost basis it released
+struct Disposal {
+    timestamp: DateTime<Utc>,
+    proceeds_usd: Decimal,
+    cost_basis_usd: Decimal,
+}
+
+impl PositionPnl {
+    /// Replay one position's entries, oldest first
+    pub fn from_entries(entries: &[LedgerEntry]) -> Self {
+        Self::replay(entries).0
+    }
+
+    /// USD figures are accumulated in Decimal and only converted back at the end, so long
+    /// ledgers don't drift
+    fn replay(entries: &[LedgerEntry]) -> (Self, Vec<Disposal>) {
+        let mut pnl = PositionPnl::default();
+        let mut disposals = Vec::new();
+        let (mut deposited, mut withdrawn, mut cost_basis, mut value) = (Decimal::ZERO, Decimal::ZERO, Decimal::ZERO, Decimal::ZERO);
+        let (mut uncollected, mut collected, mut gas, mut realized) = (Decimal::ZERO, Decimal::ZERO, Decimal::ZERO, Decimal::ZERO);
+        for entry in entries {
+            pnl.position_id.clone_from(&entry.position_id);
+            pnl.owner.clone_from(&entry.owner);
+            gas += to_decimal(entry.gas_usd);
+            let entry_value = to_decimal(entry.value_usd);
+            match entry.kind {
+                LedgerKind::Deposit => {
+                    deposited += entry_value;
+                    cost_basis += entry_value;
+                    value += entry_value;
+                    pnl.liquidity += entry.liquidity;
+                }
+                LedgerKind::Withdrawal => {
+                    let fraction = if pnl.liquidity > 0.0 { (entry.liquidity / pnl.liquidity).min(1.0) } else { 1.0 };
+                    let fraction = to_decimal(fraction);
+                    let released = cost_basis * fraction;
+                    withdrawn += entry_value;
+                    cost_basis -= released;
+                    realized += entry_value - released;
+                    value *= Decimal::ONE - fraction;
+                    pnl.liquidity = (pnl.liquidity - entry.liquidity).max(0.0);
+                    disposals.push(Disposal { timestamp: entry.timestamp, proceeds_usd: entry_value, cost_basis_usd: released });
+                }
+                LedgerKind::FeeCollection => {
+                    collected += entry_value;
+                }
+                LedgerKind::Valuation => {
+                    value = entry_value;
+                    uncollected = to_decimal(entry.uncollected_fees_usd);
+                    pnl.valued_at = Some(entry.timestamp);
+                }
+            }
+        }
+        let unrealized = value - cost_basis;
+        let usd = |amount: Decimal| amount.to_f64().unwrap_or(0.0);
+        pnl.deposited_usd = usd(deposited);
+        pnl.withdrawn_usd = usd(withdrawn);
+        pnl.cost_basis_usd = usd(cost_basis);
+        pnl.value_usd = usd(value);
+        pnl.uncollected_fees_usd = usd(uncollected);
+        pnl.fees_collected_usd = usd(collected);
+        pnl.gas_usd = usd(gas);
+        pnl.realized_usd = usd(realized);
+        pnl.unrealized_usd = usd(unrealized);
+        pnl.pnl_usd = usd(realized + unrealized + collected + uncollected - gas);
+        (pnl, disposals)
+    }
+}
+
+/// Group entries by position, keeping their order
+pub fn by_position(entries: Vec<LedgerEntry>) -> BTreeMap<String, Vec<LedgerEntry>> {
+    let mut grouped: BTreeMap<String, Vec<LedgerEntry>> = BTreeMap::new();
+    for entry in entries {
+        grouped.entry(entry.position_id.clone()).or_default().push(entry);
+    }
+    grouped
+}
+
+/// One position's taxable events in a year
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "server", derive(utoipa::ToSchema))]
+pub struct TaxLine {
+    pub position_id: String,
+    pub owner: String,
+    /// Fees collected, valued when received
+    pub fee_income_usd: f64,
+    /// Value withdrawn
+    pub proceeds_usd: f64,
+    /// Average cost of the liquidity withdrawn
+    pub cost_basis_usd: f64,
+    pub realized_gain_usd: f64,
+    pub gas_usd: f64,
+}
+
+impl TaxLine {
+    fn add(&mut self, other: &TaxLine) {
+        self.fee_income_usd = sum_decimal([self.fee_income_usd, other.fee_income_usd]);
+        self.proceeds_usd = sum_decimal([self.proceeds_usd, other.proceeds_usd]);
+        self.cost_basis_usd = sum_decimal([self.cost_basis_usd, other.cost_basis_usd]);
+        self.realized_gain_usd = sum_decimal([self.realized_gain_usd, other.realized_gain_usd]);
+        self.gas_usd = sum_decimal([self.gas_usd, other.gas_usd]);
+    }
+}
+
+/// Fee income and realized gains for a calendar year (UTC), per position and in total
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "server", derive(utoipa::ToSchema))]
+pub struct TaxReport {
+    pub year: i32,
+    pub positions: Vec<TaxLine>,
+    pub total: TaxLine,
+}
+
+impl TaxReport {
+    /// `entries` must hold each position's full history (cost basis carries over
+    /// from earlier years), oldest first
+    pub fn from_entries(year: i32, entries: Vec<LedgerEntry>) -> Self {
+        let mut positions = Vec::new();
+        let mut total = TaxLine::default();
+        for (position_id, entries) in by_position(entries) {
+            let (pnl, disposals) = PositionPnl::replay(&entries);
+            let in_year: Vec<&LedgerEntry> = entries.iter().filter(|e| e.timestamp.year() == year).collect();
+            let disposed: Vec<&Disposal> = disposals.iter().filter(|d| d.timestamp.year() == year).collect();
+            let proceeds: Decimal = disposed.iter().map(|d| d.proceeds_usd).sum();
+            let cost_basis: Decimal = disposed.iter().map(|d| d.cost_basis_usd).sum();
+            let line = TaxLine {
+                position_id,
+                owner: pnl.owner,
+                fee_income_usd: sum_decimal(in_year.iter().filter(|e| e.kind == LedgerKind::FeeCollection).map(|e| e.value_usd)),
+                proceeds_usd: proceeds.to_f64().unwrap_or(0.0),
+                cost_basis_usd: cost_basis.to_f64().unwrap_or(0.0),
+                realized_gain_usd: (proceeds - cost_basis).to_f64().unwrap_or(0.0),
+                gas_usd: sum_decimal(in_year.iter().map(|e| e.gas_usd)),
+            };
+            if line.fee_income_usd != 0.0 || line.proceeds_usd != 0.0 || line.gas_usd != 0.0 {
+                total.add(&line);
+                positions.push(line);
+            }
+        }
+        TaxReport { year, positions, total }
+    }
+}
+
+/// The deposit or withdrawal explaining a valuation's liquidity differing from the
+/// ledger's, e.g. liquidity added outside this process or a position tracked for the
+/// first time (its opening balance)
+fn reconcile(pnl: &PositionPnl, position_id: &str, owner: &str, snapshot: &Snapshot, now: DateTime<Utc>) -> Option<LedgerEntry> {
+    let delta = snapshot.liquidity - pnl.liquidity;
+    if delta.abs() <= LIQUIDITY_TOLERANCE * snapshot.liquidity.max(pnl.liquidity) {
+        return None;
+    }
+    // Value per unit of liquidity: from the position now, or its last valuation once emptied
+    let unit_value = if snapshot.liquidity > 0.0 {
+        snapshot.usd(snapshot.amount0, snapshot.amount1) / snapshot.liquidity
+    } else {
+        pnl.value_usd / pnl.liquidity
+    };
+    let (kind, note) = match (delta > 0.0, pnl.liquidity > 0.0) {
+        (true, false) => (LedgerKind::Deposit, "opening balance"),
+        (true, true) => (LedgerKind::Deposit, "detected deposit"),
+        (false, _) => (LedgerKind::Withdrawal, "detected withdrawal"),
+    };
+    let share = if snapshot.liquidity > 0.0 { delta.abs() / snapshot.liquidity } else { 0.0 };
+    Some(LedgerEntry {
+        amount0: snapshot.amount0 * share,
+        amount1: snapshot.amount1 * share,
+        value_usd: unit_value * delta.abs(),
+        liquidity: delta.abs(),
+        note: Some(note.to_string()),
+        ..LedgerEntry::new(kind, position_id, owner, now)
+    })
+}
+
+/// Record the withdrawal and fee collection of a completed emergency exit, unless the
+/// ledger already shows the position empty
+pub async fn record_exit(storage: &dyn Storage, owner: &str, exit: &ExitPosition) -> Result<()> {
+    let now = Utc::now();
+    let entries = storage
+        .ledger(&LedgerQuery { position_id: Some(exit.position_id.clone()), ..Default::default() })
+        .await?;
+    let held = PositionPnl::from_entries(&entries).liquidity;
+    if held > 0.0 && exit.liquidity > 0 {
+        let withdrawal = LedgerEntry {
+            amount0: exit.amount0,
+            amount1: exit.amount1,
+            value_usd: exit.value_usd,
+            liquidity: held,
+            note: Some("exit-all".to_string()),
+            ..LedgerEntry::new(LedgerKind::Withdrawal, &exit.position_id, owner, now)
+        };
+        storage.save_ledger_entry(&withdrawal).await?;
+    }
+    if exit.fees_usd > 0.0 {
+        let fees = LedgerEntry {
+            amount0: exit.fees0,
+            amount1: exit.fees1,
+            value_usd: exit.fees_usd,
+            note: Some("exit-all".to_string()),
+            ..LedgerEntry::new(LedgerKind::FeeCollection, &exit.position_id, owner, now)
+        };
+        storage.save_ledger_entry(&fees).await?;
+    }
+    Ok(())
+}
+
+/// Appends ledger entries: valuations of tracked positions on an interval, fee
+/// collections and rebalances from the event bus
+pub struct PnlRecorder {
+    client: UniswapClient,
+    rpc_url: String,
+    storage: Arc<dyn Storage>,
+    bus: EventBus,
+    interval: Duration,
+    /// Configured positions, valued even when empty
+    configured: BTreeSet<String>,
+    /// Configured positions plus those opened by rebalances
+    tracked: BTreeSet<String>,
+    positions: HashMap<String, PositionInfo>,
+}
+
+impl PnlRecorder {
+    pub fn new(
+        client: UniswapClient,
+        rpc_url: &str,
+        storage: Arc<dyn Storage>,
+        config: &PnlConfig,
+        position_ids: Vec<String>,
+        bus: EventBus,
+    ) -> Self {
+        let configured: BTreeSet<String> = position_ids.into_iter().collect();
+        Self {
+            client,
+            rpc_url: rpc_url.to_string(),
+            storage,
+            bus,
+            interval: Duration::from_secs(config.valuation_interval_secs.max(1)),
+            tracked: configured.clone(),
+            configured,
+            positions: HashMap::new(),
+        }
+    }
+
+    pub async fn run(mut self) {
+        let mut rx = self.bus.subscribe();
+        // Positions opened by earlier rebalances are still ours to value
+        match self.storage.ledger(&LedgerQuery { kind: Some(LedgerKind::Deposit), ..Default::default() }).await {
+            Ok(deposits) => self.tracked.extend(deposits.into_iter().map(|e| e.position_id)),
+            Err(e) => warn!(target: "pnl", "could not load ledger deposits: {:#}", e),
+        }
+        let mut ticker = interval(self.interval);
+        ticker.set_missed_tick_behavior(MissedTickBehavior::Skip);
+        loop {
+            tokio::select! {
+                _ = ticker.tick() => self.value_all().await,
+                event = rx.recv() => match event {
+                    Ok(Event::FeesCollected { timestamp, collection }) => {
+                        if let Err(e) = self.record_fees(&collection, timestamp).await {
+                            warn!(target: "pnl", position = %collection.position_id, "fee collection not recorded: {:#}", e);
+                        }
+                    }
+                    Ok(Event::PositionRebalanced { timestamp, rebalance }) => {
+                        if let Err(e) = self.record_rebalance(&rebalance, timestamp).await {
+                            warn!(target: "pnl", position = %rebalance.position_id, "rebalance not recorded: {:#}", e);
+                        }
+                    }
+                    Ok(_) => {}
+                    Err(RecvError::Lagged(skipped)) => {
+                        warn!(target: "pnl", skipped, "PnL recorder lagged, events dropped");
+                    }
+                    Err(RecvError::Closed) => break,
+                },
+            }
+        }
+    }
+
+    /// Resolve a position's static details once
+    async fn resolve(&mut self, position_id: &str) -> Result<()> {
+        if !self.positions.contains_key(position_id) {
+            let info = PositionInfo::resolve(&self.client, &self.rpc_url, position_id).await?;
+            self.positions.insert(position_id.to_string(), info);
+        }
+        Ok(())
+    }
+
+    async fn pnl(&self, position_id: &str) -> Result<PositionPnl> {
+        let entries = self
+            .storage
+            .ledger(&LedgerQuery { position_id: Some(position_id.to_string()), ..Default::default() })
+            .await?;
+        Ok(PositionPnl::from_entries(&entries))
+    }
+
+    async fn value_all(&mut self) {
+        for id in self.tracked.clone() {
+            if let Err(e) = self.value(&id).await {
+                warn!(target: "pnl", position = %id, "valuation failed: {:#}", e);
+            }
+        }
+    }
+
+    async fn value(&mut self, position_id: &str) -> Result<()> {
+        self.resolve(position_id).await?;
+        let info = &self.positions[position_id];
+        let owner = info.owner.clone();
+        let snapshot = Snapshot::fetch(&self.client, &self.rpc_url, position_id, info).await?;
+        let now = Utc::now();
+        let pnl = self.pnl(position_id).await?;
+        if let Some(entry) = reconcile(&pnl, position_id, &owner, &snapshot, now) {
+            info!(target: "pnl", position = %position_id, kind = entry.kind.as_str(), value_usd = entry.value_usd, "recorded liquidity change found by valuation");
+            self.storage.save_ledger_entry(&entry).await?;
+        }
+        let valuation = LedgerEntry::valuation(position_id, &owner, &snapshot, now);
+        debug!(target: "pnl", position = %position_id, value_usd = valuation.value_usd, "position valued");
+        self.storage.save_ledger_entry(&valuation).await?;
+        if snapshot.liquidity == 0.0 && !self.configured.contains(position_id) {
+            self.tracked.remove(position_id);
+        }
+        Ok(())
+    }
+
+    async fn record_fees(&mut self, collection: &FeeCollection, timestamp: DateTime<Utc>) -> Result<()> {
+        self.resolve(&collection.position_id).await?;
+        let info = &self.positions[&collection.position_id];
+        let prices = self.client.token_prices_usd(&[&info.token0, &info.token1]).await?;
+        let (price0, price1) = (prices.usd(&info.token0).unwrap_or(0.0), prices.usd(&info.token1).unwrap_or(0.0));
+        let entry = LedgerEntry {
+            amount0: collection.amount0,
+            amount1: collection.amount1,
+            value_usd: collection.amount0 * price0 + collection.amount1 * price1,
+            gas_usd: collection.gas_cost_eth * prices.eth_usd,
+            tx_hash: Some(collection.tx_hash.clone()),
+            ..LedgerEntry::new(LedgerKind::FeeCollection, &collection.position_id, &collection.owner, timestamp)
+        };
+        info!(target: "pnl", position = %collection.position_id, value_usd = entry.value_usd, "recorded fee collection");
+        self.storage.save_ledger_entry(&entry).await
+    }
+
+    /// A rebalance disposes of the old position and deposits the proceeds into the new
+    /// one, both at the new position's value
+    async fn record_rebalance(&mut self, rebalance: &RebalanceSummary, timestamp: DateTime<Utc>) -> Result<()> {
+        let new_id = rebalance.new_position_id.clone().context("rebalance minted no position")?;
+        self.resolve(&new_id).await?;
+        let snapshot = Snapshot::fetch(&self.client, &self.rpc_url, &new_id, &self.positions[&new_id]).await?;
+        let eth_usd = self.client.token_prices_usd(&[]).await?.eth_usd;
+        let value_usd = snapshot.usd(snapshot.amount0, snapshot.amount1);
+        let note = Some(format!("rebalance {}", rebalance.workflow_id));
+        let tx_hash = rebalance.tx_hashes.last().cloned();
+
+        // A valuation may have already seen the old position emptied
+        let held = self.pnl(&rebalance.position_id).await?.liquidity;
+        if held > 0.0 {
+            let withdrawal = LedgerEntry {
+                amount0: snapshot.amount0,
+                amount1: snapshot.amount1,
+                value_usd,
+                liquidity: held,
+                tx_hash: tx_hash.clone(),
+                note: note.clone(),
+                ..LedgerEntry::new(LedgerKind::Withdrawal, &rebalance.position_id, &rebalance.owner, timestamp)
+            };
+            self.storage.save_ledger_entry(&withdrawal).await?;
+        }
+        let deposit = LedgerEntry {
+            amount0: snapshot.amount0,
+            amount1: snapshot.amount1,
+            value_usd,
+            liquidity: snapshot.liquidity,
+            gas_usd: rebalance.gas_cost_eth * eth_usd,
+            tx_hash,
+            note,
+            ..LedgerEntry::new(LedgerKind::Deposit, &new_id, &rebalance.owner, timestamp)
+        };
+        self.storage.save_ledger_entry(&deposit).await?;
+        info!(target: "pnl", from = %rebalance.position_id, to = %new_id, value_usd, "recorded rebalance");
+        if !self.configured.contains(&rebalance.position_id) {
+            self.tracked.remove(&rebalance.position_id);
+        }
+        self.tracked.insert(new_id);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn entry(kind: LedgerKind, (year, month): (i32, u32), value_usd: f64, liquidity: f64) -> LedgerEntry {
+        LedgerEntry {
+            value_usd,
+            liquidity,
+            ..LedgerEntry::new(kind, "1", "0xowner", Utc.with_ymd_and_hms(year, month, 1, 0, 0, 0).unwrap())
+        }
+    }
+
+    fn snapshot(liquidity: f64, amount0: f64) -> Snapshot {
+        Snapshot { liquidity, amount0, amount1: 0.0, fees0: 0.0, fees1: 0.0, price0: 2.0, price1: 1.0 }
+    }
+
+    #[test]
+    fn test_replay() {
+        let mut valuation = entry(LedgerKind::Valuation, (2025, 3), 1_200.0, 100.0);
+        valuation.uncollected_fees_usd = 15.0;
+        let mut fees = entry(LedgerKind::FeeCollection, (2025, 4), 30.0, 0.0);
+        fees.gas_usd = 5.0;
+        let entries = vec![
+            entry(LedgerKind::Deposit, (2025, 1), 1_000.0, 100.0),
+            valuation,
+            fees,
+            // Half the liquidity out at 650: half the 1000 basis released
+            entry(LedgerKind::Withdrawal, (2025, 5), 650.0, 50.0),
+        ];
+        let pnl = PositionPnl::from_entries(&entries);
+        assert_eq!(pnl.liquidity, 50.0);
+        assert_eq!(pnl.cost_basis_usd, 500.0);
+        assert_eq!(pnl.realized_usd, 150.0);
+        assert_eq!(pnl.value_usd, 600.0);
+        assert_eq!(pnl.unrealized_usd, 100.0);
+        assert_eq!(pnl.pnl_usd, 150.0 + 100.0 + 30.0 + 15.0 - 5.0);
+    }
+
+    #[test]
+    fn test_replay_sums_without_float_drift() {
+        // Ten 0.1 collections are 0.9999999999999999 when summed as f64
+        let entries: Vec<LedgerEntry> = (0..10).map(|_| entry(LedgerKind::FeeCollection, (2025, 1), 0.1, 0.0)).collect();
+        let pnl = PositionPnl::from_entries(&entries);
+        assert_eq!(pnl.fees_collected_usd, 1.0);
+        assert_eq!(pnl.pnl_usd, 1.0);
+    }
+
+    #[test]
+    fn test_tax_report_carries_basis_across_years() {
+        let mut fees = entry(LedgerKind::FeeCollection, (2026, 2), 40.0, 0.0);
+        fees.gas_usd = 2.0;
+        let entries = vec![
+            entry(LedgerKind::Deposit, (2025, 1), 1_000.0, 100.0),
+            entry(LedgerKind::Withdrawal, (2025, 6), 300.0, 25.0),
+            fees,
+            entry(LedgerKind::Withdrawal, (2026, 3), 900.0, 75.0),
+        ];
+        let report = TaxReport::from_entries(2026, entries.clone());
+        assert_eq!(report.positions.len(), 1);
+        assert_eq!(report.total.fee_income_usd, 40.0);
+        assert_eq!(report.total.proceeds_usd, 900.0);
+        assert_eq!(report.total.cost_basis_usd, 750.0);
+        assert_eq!(report.total.realized_gain_usd, 150.0);
+        assert_eq!(report.total.gas_usd, 2.0);
+        assert_eq!(TaxReport::from_entries(2025, entries).total.realized_gain_usd, 50.0);
+        assert!(TaxReport::from_entries(2024, Vec::new()).positions.is_empty());
+    }
+
+    #[test]
+    fn test_reconcile() {
+        let now = Utc::now();
+        let empty = PositionPnl::default();
+        let opening = reconcile(&empty, "1", "0xowner", &snapshot(100.0, 500.0), now).unwrap();
+        assert_eq!((opening.kind, opening.value_usd, opening.liquidity), (LedgerKind::Deposit, 1_000.0, 100.0));
+        assert_eq!(opening.note.as_deref(), Some("opening balance"));
+
+        let held = PositionPnl::from_entries(&[opening]);
+        assert!(reconcile(&held, "1", "0xowner", &snapshot(100.0, 520.0), now).is_none());
+        let added = reconcile(&held, "1", "0xowner", &snapshot(150.0, 750.0), now).unwrap();
+        assert_eq!((added.kind, added.value_usd, added.liquidity), (LedgerKind::Deposit, 500.0, 50.0));
+        let emptied = reconcile(&held, "1", "0xowner", &snapshot(0.0, 0.0), now).unwrap();
+        assert_eq!((emptied.kind, emptied.value_usd, emptied.liquidity), (LedgerKind::Withdrawal, 1_000.0, 100.0));
+    }
+}
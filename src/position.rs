@@ -1,8 +1,10 @@
+use rust_decimal::prelude::ToPrimitive;
 use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "server", derive(utoipa::ToSchema))]
 pub struct Position {
     pub id: String,
     pub user_address: String,
@@ -12,22 +14,248 @@ pub struct Position {
     pub risk_score: f64,
     pub liquidity_score: f64,
     pub timestamp: u64,
+    /// Set for lending-market positions, which are scored on liquidation risk
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lending: Option<LendingMetrics>,
+    /// Set for perpetual futures positions, which are scored on leverage (boxed: most positions
+    /// have none)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub perp: Option<Box<PerpMetrics>>,
+    /// Set for concentrated liquidity positions, e.g. a Uniswap V3 NFT; the position's token
+    /// is the pool's token0 (boxed: most positions have none)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub liquidity: Option<Box<LiquidityMetrics>>,
+}
+
+/// Supplied (collateral) or borrowed (debt) side of a lending position
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "server", derive(utoipa::ToSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum LendingSide {
+    Supply,
+    Borrow,
+}
+
+/// Lending-market state of a position, e.g. one Aave reserve of a wallet
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "server", derive(utoipa::ToSchema))]
+pub struct LendingMetrics {
+    /// e.g. `aave_v3`
+    pub protocol: String,
+    pub side: LendingSide,
+    /// Health factor of the whole account; `None` while it has no debt
+    pub health_factor: Option<f64>,
+    /// Annual rate earned (supply) or paid (borrow)
+    pub apr: f64,
+    /// Weighted liquidation threshold of the account's collateral (0 to 1)
+    pub liquidation_threshold: f64,
+}
+
+impl LendingSide {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LendingSide::Supply => "supply",
+            LendingSide::Borrow => "borrow",
+        }
+    }
+}
+
+impl LendingMetrics {
+    /// 1 at a health factor of 1 (liquidatable), halving each time it doubles; 0 without debt
+    pub fn liquidation_risk(&self) -> f64 {
+        self.health_factor.map_or(0.0, |hf| if hf <= 1.0 { 1.0 } else { 1.0 / hf })
+    }
+}
+
+/// Pool and range of a concentrated liquidity position when it was read
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "server", derive(utoipa::ToSchema))]
+pub struct LiquidityMetrics {
+    /// e.g. `uniswap_v3`
+    pub protocol: String,
+    pub pool: String,
+    pub token0: String,
+    pub token1: String,
+    pub token0_symbol: String,
+    pub token1_symbol: String,
+    /// Pool fee tier, in hundredths of a basis point
+    pub fee: u32,
+    pub tick_lower: i32,
+    pub tick_upper: i32,
+    /// Pool tick when the position was read
+    pub current_tick: i32,
+    /// The position's liquidity (L), as a decimal string
+    pub liquidity: String,
+}
+
+impl LiquidityMetrics {
+    /// Whether the range contains the current tick (lower inclusive, upper exclusive), i.e.
+    /// the position earns fees
+    pub fn in_range(&self) -> bool {
+        self.tick_lower <= self.current_tick && self.current_tick < self.tick_upper
+    }
+
+    /// e.g. `WETH/USDC`
+    pub fn pair(&self) -> String {
+        format!("{}/{}", self.token0_symbol, self.token1_symbol)
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "server", derive(utoipa::ToSchema))]
 pub struct PositionRecommendation {
     pub position: Position,
     pub recommendation_score: f64,
     pub reasoning: String,
     pub suggested_action: Action,
+    /// Estimated fee APR of a liquidity position whose pool is quoted: its share of the
+    /// pool's active liquidity of the daily fees, over the share of the last day it spent in
+    /// range
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fee_apr: Option<f64>,
+    /// Impermanent loss a one-volatility price move would cause (a negative share of the
+    /// value), over the position's tick range where known; not set for lending and perp
+    /// positions. The score weighs it against `fee_apr`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub projected_il: Option<f64>,
+    /// Cost and payoff of moving a tracked Uniswap position that is out of range, whether or
+    /// not `Rebalance` was suggested
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rebalance: Option<RebalanceEstimate>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "server", derive(utoipa::ToSchema))]
 pub enum Action {
     Hold,
     Increase,
     Decrease,
     Exit,
+    /// Move the liquidity to a new range around the current price
+    Rebalance { new_tick_lower: i32, new_tick_upper: i32 },
+}
+
+impl Action {
+    /// Variant name, e.g. `Rebalance`; what storage records and filters on
+    pub fn name(&self) -> &'static str {
+        match self {
+            Action::Hold => "Hold",
+            Action::Increase => "Increase",
+            Action::Decrease => "Decrease",
+            Action::Exit => "Exit",
+            Action::Rebalance { .. } => "Rebalance",
+        }
+    }
+}
+
+impl std::fmt::Display for Action {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Action::Rebalance { new_tick_lower, new_tick_upper } => write!(f, "Rebalance to ticks {} to {}", new_tick_lower, new_tick_upper),
+            action => f.write_str(action.name()),
+        }
+    }
+}
+
+impl std::str::FromStr for Action {
+    type Err = anyhow::Error;
+
+    /// Case-insensitive action name, e.g. `exit`. `rebalance` parses with a zero range, for
+    /// filters, which compare names only.
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "hold" => Ok(Action::Hold),
+            "increase" => Ok(Action::Increase),
+            "decrease" => Ok(Action::Decrease),
+            "exit" => Ok(Action::Exit),
+            "rebalance" => Ok(Action::Rebalance { new_tick_lower: 0, new_tick_upper: 0 }),
+            _ => anyhow::bail!("unknown action '{}' (expected hold, increase, decrease, exit or rebalance)", s),
+        }
+    }
+}
+
+/// What moving an out-of-range Uniswap position to a new range would cost and earn
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "server", derive(utoipa::ToSchema))]
+pub struct RebalanceEstimate {
+    pub tick_lower: i32,
+    pub tick_upper: i32,
+    pub current_tick: i32,
+    pub new_tick_lower: i32,
+    pub new_tick_upper: i32,
+    /// Gas for decreasing, collecting, swapping and minting, at the current gas price
+    pub gas_usd: f64,
+    /// Pool fee on swapping half the position into the other token
+    pub swap_usd: f64,
+    /// Fees the new range would earn over `horizon_days` at the position's in-range fee APR; the
+    /// out-of-range position earns none
+    pub extra_fees_usd: f64,
+    pub horizon_days: f64,
+}
+
+impl RebalanceEstimate {
+    pub fn cost_usd(&self) -> f64 {
+        self.gas_usd + self.swap_usd
+    }
+
+    /// Extra fees net of the cost; positive when rebalancing pays
+    pub fn net_usd(&self) -> f64 {
+        self.extra_fees_usd - self.cost_usd()
+    }
+}
+
+/// Long or short side of a perpetual futures position
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "server", derive(utoipa::ToSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum PerpSide {
+    Long,
+    Short,
+}
+
+impl PerpSide {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PerpSide::Long => "long",
+            PerpSide::Short => "short",
+        }
+    }
+}
+
+/// Perpetual futures state of a position, e.g. a GMX V2 position; the position's token is
+/// the market's index token
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "server", derive(utoipa::ToSchema))]
+pub struct PerpMetrics {
+    /// e.g. `gmx_v2`
+    pub protocol: String,
+    pub market: String,
+    pub side: PerpSide,
+    /// Notional size in USD
+    pub size_usd: f64,
+    /// Notional size in index tokens
+    pub size_in_tokens: f64,
+    pub collateral_token: String,
+    pub collateral_amount: f64,
+    /// Notional over collateral value; `None` when the collateral couldn't be priced
+    pub leverage: Option<f64>,
+    /// Unrealized PnL at the mark price; `None` when the index token couldn't be priced
+    pub pnl_usd: Option<f64>,
+}
+
+impl PerpMetrics {
+    /// Exposure to the index token: positive when long, negative when short
+    pub fn delta(&self) -> f64 {
+        match self.side {
+            PerpSide::Long => self.size_in_tokens,
+            PerpSide::Short => -self.size_in_tokens,
+        }
+    }
+
+    /// Share of the collateral a typical move (`volatility`) wipes out, capped at 1
+    pub fn leverage_risk(&self, volatility: f64) -> f64 {
+        (volatility * self.leverage.unwrap_or(1.0)).min(1.0)
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -36,6 +264,8 @@ pub struct PositionMetrics {
     pub risk_distribution: HashMap<String, f64>,
     pub liquidity_distribution: HashMap<String, f64>,
     pub concentration_risk: f64,
+    /// Token address -> net exposure in tokens, with debt and short perps counted against it
+    pub net_exposure: HashMap<String, f64>,
 }
 
 impl Position {
@@ -55,6 +285,18 @@ impl Position {
             risk_score: 0.0,
             liquidity_score: 0.0,
             timestamp: chrono::Utc::now().timestamp() as u64,
+            lending: None,
+            perp: None,
+            liquidity: None,
+        }
+    }
+
+    /// Signed exposure to `token_address`, in tokens: borrowed and shorted amounts are negative
+    pub fn exposure(&self) -> f64 {
+        match (&self.lending, &self.perp) {
+            (_, Some(perp)) => perp.delta(),
+            (Some(lending), _) if lending.side == LendingSide::Borrow => -self.amount.to_f64().unwrap_or(0.0),
+            _ => self.amount.to_f64().unwrap_or(0.0),
         }
     }
     
@@ -63,7 +305,13 @@ impl Position {
         let volatility = market_data.get_volatility(&self.token_address);
         let market_cap = market_data.get_market_cap(&self.token_address);
         
-        self.risk_score = volatility * (1.0 / market_cap.sqrt());
+        let market_risk = volatility * (1.0 / market_cap.sqrt());
+        // Lending positions also carry the account's liquidation risk, perps their leverage
+        self.risk_score = match (&self.lending, &self.perp) {
+            (Some(lending), _) => market_risk.max(lending.liquidation_risk()),
+            (_, Some(perp)) => market_risk.max(perp.leverage_risk(volatility)),
+            _ => market_risk,
+        };
     }
     
     pub fn calculate_liquidity_score(&mut self, market_data: &MarketData) {
@@ -78,6 +326,8 @@ impl Position {
 #[derive(Debug, Clone)]
 pub struct MarketData {
     pub token_data: HashMap<String, TokenData>,
+    /// Tokens of the recommender's positions, refreshed when `[market_data]` names none
+    pub tracked_tokens: HashSet<String>,
 }
 
 #[derive(Debug, Clone)]
@@ -86,12 +336,26 @@ pub struct TokenData {
     pub market_cap: f64,
     pub volume: f64,
     pub depth: f64,
+    pub price_usd: Option<f64>,
+}
+
+// Fallbacks for tokens no market data source covers
+const DEFAULT_VOLATILITY: f64 = 0.1;
+const DEFAULT_MARKET_CAP: f64 = 1_000_000.0;
+const DEFAULT_VOLUME: f64 = 100_000.0;
+const DEFAULT_DEPTH: f64 = 0.5;
+
+impl Default for MarketData {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl MarketData {
     pub fn new() -> Self {
         Self {
             token_data: HashMap::new(),
+            tracked_tokens: HashSet::new(),
         }
     }
     
@@ -99,27 +363,50 @@ impl MarketData {
         self.token_data
             .get(token_address)
             .map(|data| data.volatility)
-            .unwrap_or(0.1) // Default volatility
+            .unwrap_or(DEFAULT_VOLATILITY)
     }
     
     pub fn get_market_cap(&self, token_address: &str) -> f64 {
         self.token_data
             .get(token_address)
             .map(|data| data.market_cap)
-            .unwrap_or(1_000_000.0) // Default market cap
+            .unwrap_or(DEFAULT_MARKET_CAP)
     }
     
     pub fn get_volume(&self, token_address: &str) -> f64 {
         self.token_data
             .get(token_address)
             .map(|data| data.volume)
-            .unwrap_or(100_000.0) // Default volume
+            .unwrap_or(DEFAULT_VOLUME)
     }
     
     pub fn get_depth(&self, token_address: &str) -> f64 {
         self.token_data
             .get(token_address)
             .map(|data| data.depth)
-            .unwrap_or(0.5) // Default depth
+            .unwrap_or(DEFAULT_DEPTH)
+    }
+    
+    pub fn get_price(&self, token_address: &str) -> Option<f64> {
+        self.token_data.get(token_address).and_then(|data| data.price_usd)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_action_names() {
+        let rebalance = Action::Rebalance { new_tick_lower: -600, new_tick_upper: 600 };
+        assert_eq!(rebalance.to_string(), "Rebalance to ticks -600 to 600");
+        assert_eq!(Action::Exit.to_string(), "Exit");
+        assert_eq!("REBALANCE".parse::<Action>().unwrap().name(), rebalance.name());
+        assert!("close".parse::<Action>().is_err());
+
+        // Stored recommendations keep reading back: unit variants stay plain strings
+        assert_eq!(serde_json::to_string(&Action::Hold).unwrap(), "\"Hold\"");
+        let json = serde_json::to_string(&rebalance).unwrap();
+        assert_eq!(serde_json::from_str::<Action>(&json).unwrap(), rebalance);
     }
 }
@@ -0,0 +1,285 @@
+//! Pool quoting pipeline: one fetcher polls the configured pools and publishes each quote on
+//! a broadcast channel; the logger, storage, recommender, notifier and API subscribe to it.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use chrono::Utc;
+use tokio::sync::broadcast::{self, error::RecvError};
+use tokio::sync::RwLock;
+use tracing::{info, warn};
+
+use crate::events::{Event, PriceUpdate};
+use crate::scheduler::Schedule;
+use crate::storage::{PoolQuote, Storage};
+use crate::uniswap::{Pool, UniswapClient};
+use crate::utils::format_usd_compact;
+
+/// Capacity of the quote channel; slow subscribers lag rather than block the fetcher
+const QUOTE_BUS_CAPACITY: usize = 256;
+
+/// A fresh quote with the pool's token addresses
+#[derive(Debug, Clone, PartialEq)]
+pub struct QuoteEvent {
+    pub quote: PoolQuote,
+    pub token0: String,
+    pub token1: String,
+    /// Tracked position the pool was resolved from, if any
+    pub position_id: Option<String>,
+    /// Liquidity in range at the pool's current tick, which in-range positions share fees with
+    pub active_liquidity: Option<u128>,
+    /// Pool price in token1 per token0
+    pub price: Option<f64>,
+}
+
+impl QuoteEvent {
+    fn new(pool: &Pool, position_id: Option<&str>) -> Self {
+        Self {
+            quote: PoolQuote::from_pool(pool, Utc::now()),
+            token0: pool.token0.id.to_lowercase(),
+            token1: pool.token1.id.to_lowercase(),
+            position_id: position_id.map(str::to_string),
+            active_liquidity: pool.liquidity.parse().ok(),
+            price: pool.token1_price.as_deref().and_then(|p| p.parse().ok()),
+        }
+    }
+
+    /// The quote as a price-update event; `None` when the subgraph gave no price
+    pub fn price_update(&self) -> Option<Event> {
+        Some(Event::PriceUpdated {
+            timestamp: self.quote.timestamp,
+            price: PriceUpdate {
+                pool: self.quote.pool_id.to_lowercase(),
+                pair: format!("{}/{}", self.quote.token0_symbol, self.quote.token1_symbol),
+                fee_tier: self.quote.fee_tier,
+                price: self.price?,
+                tvl_usd: self.quote.tvl_usd,
+                volume_usd: self.quote.volume_usd,
+                position_id: self.position_id.clone(),
+            },
+        })
+    }
+}
+
+/// Fan-out channel for quotes; cheap to clone, one receiver per subscriber
+#[derive(Clone)]
+pub struct QuoteBus {
+    sender: broadcast::Sender<QuoteEvent>,
+}
+
+impl Default for QuoteBus {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl QuoteBus {
+    pub fn new() -> Self {
+        let (sender, _) = broadcast::channel(QUOTE_BUS_CAPACITY);
+        Self { sender }
+    }
+
+    /// Publish a quote; dropped silently when nobody is subscribed
+    pub fn publish(&self, quote: QuoteEvent) {
+        let _ = self.sender.send(quote);
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<QuoteEvent> {
+        self.sender.subscribe()
+    }
+}
+
+/// Latest quote per pool, kept current by [`track_latest`]
+#[derive(Clone, Default)]
+pub struct LatestQuotes {
+    inner: Arc<RwLock<HashMap<String, QuoteEvent>>>,
+}
+
+impl LatestQuotes {
+    pub async fn insert(&self, quote: QuoteEvent) {
+        self.inner.write().await.insert(quote.quote.pool_id.clone(), quote);
+    }
+
+    /// Every pool's latest quote, ordered by pool id
+    pub async fn all(&self) -> Vec<QuoteEvent> {
+        let mut quotes: Vec<QuoteEvent> = self.inner.read().await.values().cloned().collect();
+        quotes.sort_by(|a, b| a.quote.pool_id.cmp(&b.quote.pool_id));
+        quotes
+    }
+}
+
+/// Polls the configured pools, and the pools of the configured positions, on a schedule
+pub struct QuoteFetcher {
+    client: UniswapClient,
+    pool_ids: Vec<String>,
+    position_ids: Vec<String>,
+    schedule: Schedule,
+    bus: QuoteBus,
+}
+
+impl QuoteFetcher {
+    pub fn new(client: UniswapClient, pool_ids: Vec<String>, position_ids: Vec<String>, schedule: Schedule, bus: QuoteBus) -> Self {
+        Self { client, pool_ids, position_ids, schedule, bus }
+    }
+
+    pub async fn run(self) {
+        info!(target: "quotes", pools = self.pool_ids.len(), positions = self.position_ids.len(), "quoting pools");
+        loop {
+            self.fetch_all().await;
+            self.schedule.tick().await;
+        }
+    }
+
+    async fn fetch_all(&self) {
+        for pool_id in &self.pool_ids {
+            match self.client.get_pool_by_id(pool_id).await {
+                Ok(Some(pool)) => self.publish(&pool, None),
+                Ok(None) => warn!(target: "quotes", pool = %pool_id, "pool not found"),
+                Err(e) => warn!(target: "quotes", pool = %pool_id, "fetching pool failed: {:#}", e),
+            }
+        }
+        for position_id in &self.position_ids {
+            match self.client.get_pool_by_position_id(position_id).await {
+                Ok(Some(pool)) => self.publish(&pool, Some(position_id)),
+                Ok(None) => warn!(target: "quotes", position = %position_id, "position not found"),
+                Err(e) => warn!(target: "quotes", position = %position_id, "fetching position's pool failed: {:#}", e),
+            }
+        }
+    }
+
+    fn publish(&self, pool: &Pool, position_id: Option<&str>) {
+        if !self.client.filter().allows_pool(pool) {
+            info!(target: "quotes", pool = %pool.id, position = ?position_id, "pool skipped by [filters]");
+            return;
+        }
+        self.bus.publish(QuoteEvent::new(pool, position_id));
+    }
+}
+
+/// Run `handle` on every quote until the bus closes
+async fn consume<F, Fut>(bus: QuoteBus, subscriber: &'static str, mut handle: F)
+where
+    F: FnMut(QuoteEvent) -> Fut,
+    Fut: std::future::Future<Output = ()>,
+{
+    // Only the receiver is kept, so the bus closes once every publisher is gone
+    let mut rx = bus.subscribe();
+    drop(bus);
+    loop {
+        match rx.recv().await {
+            Ok(quote) => handle(quote).await,
+            Err(RecvError::Lagged(skipped)) => warn!(target: "quotes", subscriber, skipped, "quote subscriber lagged"),
+            Err(RecvError::Closed) => break,
+        }
+    }
+}
+
+/// Log each quote
+pub async fn log_quotes(bus: QuoteBus) {
+    consume(bus, "logger", |event| async move {
+        let quote = &event.quote;
+        info!(
+            target: "quotes",
+            pool = %quote.pool_id,
+            position = ?event.position_id,
+            "{}-{} | TVL: {} | Volume: {}",
+            quote.token0_symbol,
+            quote.token1_symbol,
+            format_usd_compact(quote.tvl_usd),
+            format_usd_compact(quote.volume_usd)
+        );
+    })
+    .await
+}
+
+/// Persist each quote
+pub async fn store_quotes(storage: Arc<dyn Storage>, bus: QuoteBus) {
+    consume(bus, "storage", |event| {
+        let storage = storage.clone();
+        async move {
+            if let Err(e) = storage.save_quote(&event.quote).await {
+                warn!(target: "quotes", pool = %event.quote.pool_id, "storing quote failed: {:#}", e);
+            }
+        }
+    })
+    .await
+}
+
+/// Keep `latest` up to date with the bus
+pub async fn track_latest(latest: LatestQuotes, bus: QuoteBus) {
+    consume(bus, "latest", |event| {
+        let latest = latest.clone();
+        async move { latest.insert(event).await }
+    })
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::MemoryStorage;
+    use crate::uniswap::Token;
+
+    fn pool(id: &str, tvl: &str) -> Pool {
+        let token = |id: &str, symbol: &str| Token { id: id.to_string(), symbol: symbol.to_string(), name: symbol.to_string(), decimals: "18".to_string() };
+        Pool {
+            id: id.to_string(),
+            token0: token("0xAAA", "WETH"),
+            token1: token("0xBBB", "USDC"),
+            fee_tier: "500".to_string(),
+            liquidity: "0".to_string(),
+            volume_usd: "1000".to_string(),
+            total_value_locked_usd: tvl.to_string(),
+            total_value_locked_token0: None,
+            total_value_locked_token1: None,
+            token1_price: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_subscribers_receive_published_quotes() {
+        let bus = QuoteBus::new();
+        let latest = LatestQuotes::default();
+        let storage: Arc<dyn Storage> = Arc::new(MemoryStorage::new());
+        let tracker = tokio::spawn(track_latest(latest.clone(), bus.clone()));
+        let recorder = tokio::spawn(store_quotes(storage.clone(), bus.clone()));
+        while bus.sender.receiver_count() < 2 {
+            tokio::task::yield_now().await;
+        }
+
+        let first = QuoteEvent::new(&pool("0x02", "5000"), None);
+        assert_eq!((first.token0.as_str(), first.token1.as_str()), ("0xaaa", "0xbbb"));
+        bus.publish(first);
+        bus.publish(QuoteEvent::new(&pool("0x01", "100"), Some("42")));
+        bus.publish(QuoteEvent::new(&pool("0x02", "6000"), None));
+        drop(bus);
+        tracker.await.unwrap();
+        recorder.await.unwrap();
+
+        let quotes = latest.all().await;
+        assert_eq!(quotes.len(), 2);
+        assert_eq!(quotes[0].position_id.as_deref(), Some("42"));
+        assert_eq!(quotes[1].quote.tvl_usd, 6000.0);
+        let since = Utc::now() - chrono::Duration::hours(1);
+        assert_eq!(storage.quotes("0x02", since).await.unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_quote_price_update() {
+        let mut quoted = pool("0xPOOL", "5000000");
+        assert_eq!(QuoteEvent::new(&quoted, None).price_update().map(|e| e.kind()), None);
+
+        quoted.token1_price = Some("2512.5".to_string());
+        let event = QuoteEvent::new(&quoted, Some("42")).price_update().unwrap();
+        let Event::PriceUpdated { price, .. } = &event else {
+            panic!("expected a price update, got {:?}", event);
+        };
+        assert_eq!(price.pool, "0xpool");
+        assert_eq!(price.pair, "WETH/USDC");
+        assert_eq!(price.price, 2512.5);
+        assert_eq!(price.tvl_usd, 5_000_000.0);
+        assert_eq!(event.position_id(), "42");
+        assert_eq!(event.wallet(), None);
+        assert_eq!(serde_json::to_value(&event).unwrap()["type"], "price_updated");
+    }
+}
@@ -0,0 +1,251 @@
+//! Tick range suggestions for new liquidity positions. Candidate ranges around the current
+//! tick are scored over a holding horizon on the fees they'd capture (what full-range
+//! liquidity earns in the pool, scaled by the range's capital efficiency and the share of
+//! the horizon the price is expected to stay inside it) net of the impermanent loss of a
+//! one-volatility move, with the price taken as a driftless random walk at the pool's
+//! realized volatility.
+
+use anyhow::{bail, Context, Result};
+use ethereum_types::U256;
+use std::time::Duration;
+
+use crate::backfill::candle_from_hour;
+use crate::il::concentrated_il;
+use crate::metrics::{day_data_fee_apr, realized_vol};
+use crate::storage::Candle;
+use crate::uniswap::{Pool, Token, UniswapClient};
+use crate::utils::uniswap_v3::{align_tick, sqrt_ratio_to_f64, tick_spacing, TickRounding};
+use crate::utils::{apr_to_periodic_yield, SECS_PER_YEAR};
+
+/// Hourly candles the pool's volatility is measured over
+const VOLATILITY_HOURS: usize = 14 * 24;
+/// Complete days the pool's fee yield is averaged over
+const FEE_DAYS: usize = 7;
+/// Candidate half-widths are steps of this share of the horizon's volatility...
+const WIDTH_STEP: f64 = 0.05;
+/// ...up to this many volatilities either side of the current price
+const MAX_WIDTH_SIGMAS: f64 = 5.0;
+/// Points the horizon is sampled at for the expected share of time in range
+const TIME_STEPS: usize = 24;
+
+/// What a range is optimized for
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RangeInputs {
+    pub current_tick: i32,
+    /// In hundredths of a basis point; sets the tick spacing
+    pub fee_tier: u32,
+    /// Annualized volatility of the pool price
+    pub volatility: f64,
+    /// A day's fees earned by full-range liquidity, over its value
+    pub daily_fee_yield: f64,
+    pub horizon: Duration,
+}
+
+/// Suggested range and its expected outcome over the horizon, as shares of the deposit
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RangeSuggestion {
+    pub tick_lower: i32,
+    pub tick_upper: i32,
+    /// Standard deviation of the log price over the horizon
+    pub horizon_volatility: f64,
+    /// Expected share of the horizon the price spends in range
+    pub in_range_share: f64,
+    pub expected_fees: f64,
+    /// Zero or negative
+    pub expected_il: f64,
+}
+
+impl RangeSuggestion {
+    /// Expected fees net of impermanent loss
+    pub fn net_return(&self) -> f64 {
+        self.expected_fees + self.expected_il
+    }
+}
+
+/// A pool and the range suggested for it
+#[derive(Debug, Clone)]
+pub struct RangeRecommendation {
+    pub pool: Pool,
+    pub inputs: RangeInputs,
+    pub suggestion: RangeSuggestion,
+}
+
+/// Error function (Abramowitz and Stegun 7.1.26, accurate to 1.5e-7)
+fn erf(x: f64) -> f64 {
+    let t = 1.0 / (1.0 + 0.3275911 * x.abs());
+    let poly = t * (0.254829592 + t * (-0.284496736 + t * (1.421413741 + t * (-1.453152027 + t * 1.061405429))));
+    (1.0 - poly * (-x * x).exp()).copysign(x)
+}
+
+/// Expected share of the horizon a random walk with horizon standard deviation `sigma`
+/// spends within `below` under and `above` over its start (both in log price)
+fn in_range_share(below: f64, above: f64, sigma: f64) -> f64 {
+    (0..TIME_STEPS)
+        .map(|i| {
+            // Standard deviation at the middle of the step, scaled by sqrt(2) for erf
+            let spread = sigma * (2.0 * (i as f64 + 0.5) / TIME_STEPS as f64).sqrt();
+            (erf(below / spread) + erf(above / spread)) / 2.0
+        })
+        .sum::<f64>()
+        / TIME_STEPS as f64
+}
+
+/// Fees a range `below`/`above` the current price (in log price) earns per unit earned by
+/// full-range liquidity of the same value
+fn capital_efficiency(below: f64, above: f64) -> f64 {
+    1.0 / (1.0 - (-(below + above) / 4.0).exp())
+}
+
+fn evaluate(tick_lower: i32, tick_upper: i32, inputs: &RangeInputs, sigma: f64) -> RangeSuggestion {
+    let tick_log = 1.0001f64.ln();
+    let below = (inputs.current_tick - tick_lower) as f64 * tick_log;
+    let above = (tick_upper - inputs.current_tick) as f64 * tick_log;
+    let share = in_range_share(below, above, sigma);
+    let horizon_days = inputs.horizon.as_secs_f64() / 86_400.0;
+    let expected_fees = inputs.daily_fee_yield * horizon_days * capital_efficiency(below, above) * share;
+    // Prices relative to the current one; the loss is averaged over a move either way
+    let (lower, upper) = ((-below).exp(), above.exp());
+    let il = |moved: f64| concentrated_il(1.0, moved, lower, upper).unwrap_or(0.0);
+    RangeSuggestion {
+        tick_lower,
+        tick_upper,
+        horizon_volatility: sigma,
+        in_range_share: share,
+        expected_fees,
+        expected_il: (il(sigma.exp()) + il((-sigma).exp())) / 2.0,
+    }
+}
+
+/// The candidate range with the best expected fees net of impermanent loss
+pub fn optimize(inputs: &RangeInputs) -> Result<RangeSuggestion> {
+    let spacing = tick_spacing(inputs.fee_tier).with_context(|| format!("unknown fee tier {}", inputs.fee_tier))?;
+    let sigma = inputs.volatility * (inputs.horizon.as_secs_f64() / SECS_PER_YEAR).sqrt();
+    if !(sigma.is_finite() && sigma > 0.0) {
+        bail!("no price volatility to size a range by");
+    }
+    let ticks_per_sigma = sigma / 1.0001f64.ln();
+    let steps = (MAX_WIDTH_SIGMAS / WIDTH_STEP) as usize;
+    let mut best: Option<RangeSuggestion> = None;
+    for step in 1..=steps {
+        let half_width = (ticks_per_sigma * WIDTH_STEP * step as f64).round() as i32;
+        let tick_lower = align_tick(inputs.current_tick.saturating_sub(half_width), spacing, TickRounding::Down);
+        let tick_upper = align_tick(inputs.current_tick.saturating_add(half_width), spacing, TickRounding::Up);
+        if tick_lower >= tick_upper || best.is_some_and(|b| (b.tick_lower, b.tick_upper) == (tick_lower, tick_upper)) {
+            continue;
+        }
+        let candidate = evaluate(tick_lower, tick_upper, inputs, sigma);
+        if best.is_none_or(|b| candidate.net_return() > b.net_return()) {
+            best = Some(candidate);
+        }
+    }
+    best.context("no candidate range")
+}
+
+/// Value of a full-range position with the pool's active liquidity over the pool's TVL:
+/// how many times more fees the average dollar in the pool earns than a full-range one
+fn pool_capital_efficiency(pool: &Pool, sqrt_price_x96: U256) -> Result<f64> {
+    let decimals = |token: &Token| token.decimals.parse::<i32>().with_context(|| format!("invalid {} decimals", token.symbol));
+    let (decimals0, decimals1) = (decimals(&pool.token0)?, decimals(&pool.token1)?);
+    let locked = |amount: &Option<String>| amount.as_deref().and_then(|a| a.parse::<f64>().ok()).context("pool token TVL unavailable");
+    let (locked0, locked1) = (locked(&pool.total_value_locked_token0)?, locked(&pool.total_value_locked_token1)?);
+    let liquidity: f64 = pool.liquidity.parse().context("invalid pool liquidity")?;
+    let sqrt_price = sqrt_ratio_to_f64(sqrt_price_x96);
+    // In whole token1: a full-range position holds L / sqrt(P) token0 and L * sqrt(P) token1 (raw)
+    let full_range_value = 2.0 * liquidity * sqrt_price / 10f64.powi(decimals1);
+    let tvl = locked0 * sqrt_price.powi(2) * 10f64.powi(decimals0 - decimals1) + locked1;
+    if !(full_range_value > 0.0 && tvl > 0.0) {
+        bail!("pool has no active liquidity");
+    }
+    Ok((full_range_value / tvl).max(1.0))
+}
+
+/// Suggest a range in `pool_id` for holding over `horizon`, from its hourly volatility over
+/// the last two weeks and its fee yield over the last week
+pub async fn recommend_range(client: &UniswapClient, rpc_url: &str, pool_id: &str, horizon: Duration) -> Result<RangeRecommendation> {
+    let pool = client.get_pool_by_id(pool_id).await?.with_context(|| format!("pool {} not found", pool_id))?;
+    let fee_tier: u32 = pool.fee_tier.parse().context("invalid pool fee tier")?;
+    let slot0 = client.get_slot0(rpc_url, &pool.id).await?;
+    let candles: Vec<Candle> = client
+        .pool_hour_data(&pool.id, VOLATILITY_HOURS)
+        .await?
+        .iter()
+        .map(|hour| candle_from_hour(&pool.id, hour))
+        .collect::<Result<_>>()?;
+    let volatility = realized_vol(&candles).context("too few hourly candles to measure volatility")?;
+    let fee_apr = day_data_fee_apr(&client.pool_day_data(&pool.id, FEE_DAYS).await?).context("no daily fee history")?;
+    // The pool's fees over TVL are what its average, concentrated, dollar earns
+    let pool_yield = apr_to_periodic_yield(fee_apr, 86_400.0);
+    let inputs = RangeInputs {
+        current_tick: slot0.tick,
+        fee_tier,
+        volatility,
+        daily_fee_yield: pool_yield / pool_capital_efficiency(&pool, slot0.sqrt_price_x96)?,
+        horizon,
+    };
+    let suggestion = optimize(&inputs)?;
+    Ok(RangeRecommendation { pool, inputs, suggestion })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn inputs(volatility: f64, daily_fee_yield: f64) -> RangeInputs {
+        RangeInputs {
+            current_tick: 200_030,
+            fee_tier: 3000,
+            volatility,
+            daily_fee_yield,
+            horizon: Duration::from_secs(7 * 86_400),
+        }
+    }
+
+    #[test]
+    fn test_erf_and_in_range_share() {
+        assert!((erf(1.0) - 0.8427007929).abs() < 1e-6);
+        assert!((erf(-1.0) + erf(1.0)).abs() < 1e-12);
+        // A range of ±2 sigma holds the price for most of the horizon, ±0.1 sigma for little
+        assert!(in_range_share(0.2, 0.2, 0.1) > 0.95);
+        assert!(in_range_share(0.01, 0.01, 0.1) < 0.2);
+    }
+
+    #[test]
+    fn test_pool_capital_efficiency() {
+        let token = |symbol: &str| Token { id: symbol.to_string(), symbol: symbol.to_string(), name: symbol.to_string(), decimals: "18".to_string() };
+        let mut pool = Pool {
+            id: "0xpool".to_string(),
+            token0: token("A"),
+            token1: token("B"),
+            fee_tier: "3000".to_string(),
+            liquidity: "5000000000000000000".to_string(),
+            volume_usd: "0".to_string(),
+            total_value_locked_usd: "0".to_string(),
+            total_value_locked_token0: Some("1".to_string()),
+            total_value_locked_token1: Some("1".to_string()),
+            token1_price: None,
+        };
+        // At a price of 1, liquidity 5e18 would need 5 of each token over the full range; 1 of
+        // each is locked
+        let efficiency = pool_capital_efficiency(&pool, U256::one() << 96).unwrap();
+        assert!((efficiency - 5.0).abs() < 1e-9, "{}", efficiency);
+        pool.total_value_locked_token1 = None;
+        assert!(pool_capital_efficiency(&pool, U256::one() << 96).is_err());
+    }
+
+    #[test]
+    fn test_optimize_range() {
+        let calm = optimize(&inputs(0.5, 0.001)).unwrap();
+        assert!(calm.tick_lower < 200_030 && 200_030 < calm.tick_upper, "{:?}", calm);
+        assert_eq!((calm.tick_lower % 60, calm.tick_upper % 60), (0, 0));
+        assert!(calm.net_return() > 0.0 && calm.expected_il < 0.0);
+
+        // More volatility widens the range; fees alone never pay for narrowing it further
+        let volatile = optimize(&inputs(1.5, 0.001)).unwrap();
+        assert!(volatile.tick_upper - volatile.tick_lower > calm.tick_upper - calm.tick_lower);
+        let feeless = optimize(&inputs(0.5, 0.0)).unwrap();
+        assert!(feeless.tick_upper - feeless.tick_lower > calm.tick_upper - calm.tick_lower);
+
+        assert!(optimize(&inputs(0.0, 0.001)).is_err());
+        assert!(optimize(&RangeInputs { fee_tier: 2500, ..inputs(0.5, 0.001) }).is_err());
+    }
+}
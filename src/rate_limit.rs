@@ -0,0 +1,155 @@
+use axum::extract::{ConnectInfo, Request, State};
+use axum::http::StatusCode;
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::server::AppState;
+
+/// How often buckets that have refilled are dropped
+const SWEEP_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Per-key token-bucket rate limiter
+#[derive(Clone, Default)]
+pub struct RateLimiter {
+    buckets: Arc<Mutex<Buckets>>,
+}
+
+#[derive(Default)]
+struct Buckets {
+    by_key: HashMap<String, Bucket>,
+    last_sweep: Option<Instant>,
+}
+
+#[derive(Debug, Clone)]
+struct Bucket {
+    tokens: f64,
+    capacity: f64,
+    last_refill: Instant,
+}
+
+impl Bucket {
+    fn refilled(&self, now: Instant) -> f64 {
+        let elapsed = now.saturating_duration_since(self.last_refill).as_secs_f64();
+        (self.tokens + elapsed * self.capacity / 60.0).min(self.capacity)
+    }
+}
+
+impl RateLimiter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Take one token from `key`'s bucket, refilled at `per_minute` tokens per minute
+    /// with a burst capacity of `per_minute`. Returns false when the bucket is empty.
+    pub fn check(&self, key: &str, per_minute: u32) -> bool {
+        self.check_at(key, per_minute, Instant::now())
+    }
+
+    fn check_at(&self, key: &str, per_minute: u32, now: Instant) -> bool {
+        if per_minute == 0 {
+            return true;
+        }
+        let capacity = per_minute as f64;
+        let mut buckets = self.buckets.lock().expect("rate limiter lock poisoned");
+        // A full bucket is no different from a missing one, so idle keys don't pile up
+        if buckets.last_sweep.is_none_or(|at| now.saturating_duration_since(at) >= SWEEP_INTERVAL) {
+            buckets.by_key.retain(|_, bucket| bucket.refilled(now) < bucket.capacity);
+            buckets.last_sweep = Some(now);
+        }
+        let bucket = buckets.by_key.entry(key.to_string()).or_insert(Bucket {
+            tokens: capacity,
+            capacity,
+            last_refill: now,
+        });
+        bucket.capacity = capacity;
+        bucket.tokens = bucket.refilled(now);
+        bucket.last_refill = now;
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+/// Middleware limiting requests per client IP, independent of credentials
+pub async fn limit_clients(State(state): State<AppState>, req: Request, next: Next) -> Response {
+    let Some(server) = state.config.server.as_ref().filter(|s| s.client_rate_limit_per_minute > 0) else {
+        return next.run(req).await;
+    };
+    let forwarded = server
+        .trust_forwarded_for
+        .then(|| req.headers().get("x-forwarded-for").and_then(|v| v.to_str().ok()))
+        .flatten()
+        .and_then(forwarded_client);
+    let client = forwarded.or_else(|| {
+        req.extensions()
+            .get::<ConnectInfo<SocketAddr>>()
+            .map(|ConnectInfo(addr)| addr.ip().to_string())
+    });
+    if let Some(client) = client {
+        if !state.client_limiter.check(&client, server.client_rate_limit_per_minute) {
+            return (StatusCode::TOO_MANY_REQUESTS, "client rate limit exceeded").into_response();
+        }
+    }
+    next.run(req).await
+}
+
+/// The client address the trusted proxy appended to `X-Forwarded-For`: the rightmost entry,
+/// since everything before it came from the client and can be anything
+fn forwarded_client(header: &str) -> Option<String> {
+    header.rsplit(',').map(str::trim).find(|ip| !ip.is_empty()).map(str::to_string)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bucket_exhausts_and_refills() {
+        let limiter = RateLimiter::new();
+        let start = Instant::now();
+        for _ in 0..3 {
+            assert!(limiter.check_at("a", 3, start));
+        }
+        assert!(!limiter.check_at("a", 3, start));
+        // Other keys have their own bucket
+        assert!(limiter.check_at("b", 3, start));
+        // 3/min refills one token every 20s
+        assert!(limiter.check_at("a", 3, start + Duration::from_secs(20)));
+        assert!(!limiter.check_at("a", 3, start + Duration::from_secs(21)));
+    }
+
+    #[test]
+    fn test_spoofed_forwarded_for_is_ignored() {
+        let limiter = RateLimiter::new();
+        let start = Instant::now();
+        // Rotating the client-supplied entries leaves the proxy's entry, and the limit, in place
+        for spoofed in ["1.1.1.1", "2.2.2.2", "3.3.3.3"] {
+            let client = forwarded_client(&format!("{}, 203.0.113.7", spoofed)).unwrap();
+            assert_eq!(client, "203.0.113.7");
+            assert!(limiter.check_at(&client, 3, start));
+        }
+        let client = forwarded_client("4.4.4.4, 203.0.113.7 ").unwrap();
+        assert!(!limiter.check_at(&client, 3, start));
+        assert_eq!(forwarded_client(""), None);
+    }
+
+    #[test]
+    fn test_idle_buckets_are_evicted() {
+        let limiter = RateLimiter::new();
+        let start = Instant::now();
+        for i in 0..100 {
+            assert!(limiter.check_at(&format!("10.0.0.{}", i), 60, start));
+        }
+        assert_eq!(limiter.buckets.lock().unwrap().by_key.len(), 100);
+        // A minute on every bucket has refilled, so the next sweep drops them
+        assert!(limiter.check_at("10.0.1.1", 60, start + SWEEP_INTERVAL));
+        assert_eq!(limiter.buckets.lock().unwrap().by_key.len(), 1);
+    }
+}
@@ -0,0 +1,129 @@
+//! Rebalance advice for tracked Uniswap positions that have drifted out of range: a new
+//! range centered on the current tick (of `[execution.rebalance] width_ticks`, else the old
+//! width), and whether the fees it would earn over a horizon pay for the gas and the swap
+//! back to the range's token ratio.
+
+use anyhow::Result;
+use rust_decimal::prelude::ToPrimitive;
+use std::collections::HashMap;
+use tracing::{debug, warn};
+
+use crate::config::Config;
+use crate::executor::centered_range;
+use crate::position::{LiquidityMetrics, Position, RebalanceEstimate};
+use crate::uniswap::UniswapClient;
+use crate::utils::to_units;
+use crate::utils::uniswap_v3::tick_spacing;
+
+/// Gas of a rebalance: decrease liquidity, collect, swap and mint
+const REBALANCE_GAS: u64 = 700_000;
+/// Days of fee income a rebalance has to pay for itself in
+pub const HORIZON_DAYS: f64 = 30.0;
+
+/// Cost and payoff of rebalancing a position in `range`, worth `value_usd`; an unknown
+/// `fee_apr` earns nothing
+fn estimate(range: &LiquidityMetrics, value_usd: f64, fee_apr: Option<f64>, gas_usd: f64, width: Option<i32>) -> RebalanceEstimate {
+    let width = width.unwrap_or(range.tick_upper - range.tick_lower);
+    let (new_tick_lower, new_tick_upper) = centered_range(range.current_tick, width, tick_spacing(range.fee).unwrap_or(60));
+    RebalanceEstimate {
+        tick_lower: range.tick_lower,
+        tick_upper: range.tick_upper,
+        current_tick: range.current_tick,
+        new_tick_lower,
+        new_tick_upper,
+        gas_usd,
+        // Out of range the position is all one token; the new range needs about half of each
+        swap_usd: value_usd / 2.0 * range.fee as f64 / 1_000_000.0,
+        extra_fees_usd: value_usd * fee_apr.unwrap_or(0.0).max(0.0) * HORIZON_DAYS / 365.0,
+        horizon_days: HORIZON_DAYS,
+    }
+}
+
+/// Prices moving the tracked Uniswap positions (`uniswap.position_ids`) that are out of range
+pub struct RebalanceAdvisor {
+    client: UniswapClient,
+    rpc_url: String,
+    width_ticks: Option<i32>,
+}
+
+impl RebalanceAdvisor {
+    /// `None` without `uniswap.position_ids`
+    pub fn from_config(client: UniswapClient, config: &Config) -> Option<Self> {
+        if config.uniswap.as_ref()?.position_ids.is_empty() {
+            return None;
+        }
+        Some(Self {
+            client,
+            rpc_url: config.active_chain().rpc_url.clone(),
+            width_ticks: config.execution.as_ref().and_then(|e| e.rebalance.as_ref()).and_then(|r| r.width_ticks),
+        })
+    }
+
+    /// An estimate for each of `positions` that is a liquidity position out of range, by
+    /// position id; `fee_aprs` are the positions' estimated fee APRs while in range, by
+    /// position id
+    pub async fn advise(&self, positions: &[Position], fee_aprs: &HashMap<String, f64>) -> HashMap<String, RebalanceEstimate> {
+        let out_of_range: Vec<(&Position, &LiquidityMetrics)> = positions
+            .iter()
+            .filter_map(|p| p.liquidity.as_deref().filter(|range| !range.in_range()).map(|range| (p, range)))
+            .collect();
+        if out_of_range.is_empty() {
+            return HashMap::new();
+        }
+        let gas_usd = match self.gas_usd().await {
+            Ok(gas_usd) => gas_usd,
+            Err(e) => {
+                warn!("Failed to price rebalance gas: {:#}", e);
+                return HashMap::new();
+            }
+        };
+        out_of_range
+            .into_iter()
+            .map(|(position, range)| {
+                let value_usd = position.value_usd.to_f64().unwrap_or(0.0);
+                let estimate = estimate(range, value_usd, fee_aprs.get(&position.id).copied(), gas_usd, self.width_ticks);
+                debug!(position = %position.id, tick = range.current_tick, net_usd = estimate.net_usd(), "Priced rebalance of out-of-range position");
+                (position.id.clone(), estimate)
+            })
+            .collect()
+    }
+
+    async fn gas_usd(&self) -> Result<f64> {
+        let gas_price = self.client.gas_price_wei(&self.rpc_url).await?;
+        let eth_usd = self.client.token_prices_usd(&[]).await?.eth_usd;
+        Ok(to_units(gas_price.saturating_mul(REBALANCE_GAS.into()), 18) * eth_usd)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rebalance_estimate() {
+        let range = LiquidityMetrics {
+            protocol: "uniswap_v3".to_string(),
+            pool: "0xpool".to_string(),
+            token0: "0xweth".to_string(),
+            token1: "0xusdc".to_string(),
+            token0_symbol: "WETH".to_string(),
+            token1_symbol: "USDC".to_string(),
+            fee: 3000,
+            tick_lower: -600,
+            tick_upper: 600,
+            current_tick: 1_234,
+            liquidity: "1000000".to_string(),
+        };
+        // $10k at 36.5% APR earns $300 over 30 days; the swap of $5k at 0.3% costs $15
+        let paying = estimate(&range, 10_000.0, Some(0.365), 20.0, None);
+        assert_eq!((paying.new_tick_lower, paying.new_tick_upper), (600, 1_800));
+        assert!((paying.swap_usd - 15.0).abs() < 1e-9);
+        assert!((paying.extra_fees_usd - 300.0).abs() < 1e-9);
+        assert!((paying.net_usd() - 265.0).abs() < 1e-9);
+
+        // Without a fee estimate nothing pays for the move
+        let unknown = estimate(&range, 10_000.0, None, 20.0, Some(240));
+        assert_eq!((unknown.new_tick_lower, unknown.new_tick_upper), (1_080, 1_320));
+        assert!(unknown.net_usd() < 0.0);
+    }
+}
@@ -1,70 +1,442 @@
-use anyhow::Result;
-use tracing::{info, error};
+use anyhow::{Context, Result};
+use tracing::{info, error, warn, instrument};
 use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Notify;
+use tokio::time::{interval_at, Instant, Interval, MissedTickBehavior};
 use rust_decimal::Decimal;
 use rust_decimal::prelude::ToPrimitive;
 
+use crate::aave::AaveAdapter;
+use crate::alerts::AlertState;
+use crate::backfill;
 use crate::config::Config;
-use crate::position::{Position, PositionRecommendation, PositionMetrics, MarketData, Action};
+use crate::events::{Event, EventBus};
+use crate::filters::AssetFilter;
+use crate::gmx::GmxAdapter;
+use crate::health::HealthState;
+use crate::il;
+use crate::market_data::SharedMarketData;
+use crate::metrics;
+use crate::origins::OriginsAdapter;
+use crate::scheduler::Schedule;
+use crate::state::RecommenderState;
+use crate::storage::{self, AlertQuery, Candle, PoolQuote, RecommendationMark, StateSnapshot};
+use crate::position::{Position, PositionRecommendation, PositionMetrics, TokenData, Action, LendingSide, PerpSide, RebalanceEstimate};
+use crate::quotes::{LatestQuotes, QuoteEvent};
+use crate::rebalance_advice::RebalanceAdvisor;
+use crate::uniswap::positions::UniswapAdapter;
+use crate::uniswap::UniswapClient;
+use crate::utils::{format_usd, format_usd_compact};
+
+/// Window of range alerts a liquidity position's fee APR is scaled by
+const IN_RANGE_WINDOW_HOURS: i64 = 24;
+
+/// A quoted pool's fee inputs, from the change between its quotes or its daily history
+#[derive(Debug, Clone, Default)]
+struct PoolStats {
+    /// Hundredths of a basis point
+    fee_tier: u32,
+    daily_volume_usd: Option<f64>,
+    /// Liquidity in range at the pool's tick, as of the latest quote
+    active_liquidity: Option<u128>,
+    /// Fee APR of the pool's whole TVL, for positions whose share of the liquidity is unknown
+    fee_apr: Option<f64>,
+}
 
 pub struct PositionRecommender {
     config: Config,
-    market_data: MarketData,
+    schedule: Schedule,
+    /// Kept current by the market data refresher; pool volumes from quotes land here too
+    market_data: SharedMarketData,
     positions: Vec<Position>,
+    health: HealthState,
+    events: EventBus,
+    state: RecommenderState,
+    last_actions: HashMap<String, Action>,
+    last_scores: HashMap<String, f64>,
+    /// Range and fee alert marks, shared with the monitors
+    alerts: AlertState,
+    /// Set when snapshots are taken between cycles too
+    snapshot_interval: Option<Duration>,
+    /// `[filters]`: positions in denied (or not allowed) tokens are neither added nor scored
+    filter: AssetFilter,
+    trigger: Arc<Notify>,
+    /// Latest pool quotes, fed by the quote pipeline
+    quotes: LatestQuotes,
+    /// Quotes seen by the previous cycle, to turn cumulative pool volume into a rate
+    quote_marks: HashMap<String, PoolQuote>,
+    /// Lowercase pool id -> its fee inputs, from the quotes' daily volume (seeded from the
+    /// pool's daily history until two quotes are in); liquidity positions' fee APRs derive
+    /// from their pool's
+    pool_stats: HashMap<String, PoolStats>,
+    /// Reads the daily history of newly quoted pools
+    uniswap: UniswapClient,
+    /// `uniswap.position_ids`: the tracked NFT positions, re-read at the start of every cycle
+    uniswap_positions: Option<UniswapAdapter>,
+    /// `uniswap.position_ids`: out-of-range positions are priced for a rebalance every cycle
+    rebalance: Option<RebalanceAdvisor>,
+    /// `[aave]`: lending positions re-read at the start of every cycle
+    aave: Option<AaveAdapter>,
+    /// `[gmx]`: perp positions and GM/GLP holdings re-read at the start of every cycle
+    gmx: Option<GmxAdapter>,
+    /// `blockchain.origins_abi_path`: positions in the Origins contract, re-read every cycle
+    origins: Option<OriginsAdapter>,
 }
 
 impl PositionRecommender {
     pub async fn new(config: Config) -> Result<Self> {
         info!("Initializing position recommender");
         
-        // Initialize market data (in a real implementation, this would fetch from APIs)
-        let market_data = MarketData::new();
+        let market_data = SharedMarketData::default();
+        let schedule = config.recommendation_schedule()?;
+        let state = RecommenderState::with_storage(storage::connect(config.storage.as_ref()).await?);
+        
+        // Resume from the last persisted cycle so a restart doesn't re-announce every recommendation
+        let mut last_actions: HashMap<String, Action> = state
+            .latest()
+            .await?
+            .into_iter()
+            .map(|r| (r.position.id, r.suggested_action))
+            .collect();
+        let mut last_scores = HashMap::new();
+        let mut positions = Vec::new();
+        let mut alerts = AlertState::default();
+        // A snapshot also restores positions, scores and alert marks, possibly from mid-cycle
+        match state.storage().latest_snapshot().await {
+            Ok(Some(snapshot)) => {
+                info!(taken_at = %snapshot.taken_at, positions = snapshot.positions.len(), "Restored recommender state snapshot");
+                positions = snapshot.positions;
+                for (id, mark) in snapshot.recommendations {
+                    last_actions.insert(id.clone(), mark.action);
+                    last_scores.insert(id, mark.score);
+                }
+                alerts = AlertState::new(snapshot.alerts);
+            }
+            Ok(None) => {}
+            Err(e) => warn!("Failed to load state snapshot: {:#}", e),
+        }
+        let snapshot_interval = config
+            .storage
+            .as_ref()
+            .map(|s| s.snapshot_interval_secs)
+            .filter(|secs| *secs > 0)
+            .map(Duration::from_secs);
+        let filter = AssetFilter::from_config(config.filters.as_ref());
+        let aave = config.aave.as_ref().map(|aave_cfg| {
+            info!(wallets = aave_cfg.wallets.len(), "Tracking Aave positions");
+            AaveAdapter::new(UniswapClient::from_config(&config), &config.active_chain().rpc_url, aave_cfg)
+        });
+        let gmx = config.gmx.as_ref().map(|gmx_cfg| {
+            info!(wallets = gmx_cfg.wallets.len(), "Tracking GMX positions");
+            GmxAdapter::new(UniswapClient::from_config(&config), &config.active_chain().rpc_url, gmx_cfg)
+        });
+        let origins = OriginsAdapter::from_config(UniswapClient::from_config(&config), &config)
+            .context("loading the Origins contract ABI")?;
+        if origins.is_some() {
+            info!("Tracking Origins contract positions");
+        }
+        let uniswap = UniswapClient::from_config(&config);
+        let uniswap_positions = config
+            .uniswap
+            .as_ref()
+            .map(|u| &u.position_ids)
+            .filter(|ids| !ids.is_empty())
+            .map(|ids| {
+                info!(positions = ids.len(), "Tracking Uniswap positions");
+                UniswapAdapter::new(uniswap.clone(), &config.active_chain().rpc_url, ids)
+            });
+        let rebalance = RebalanceAdvisor::from_config(uniswap.clone(), &config);
         
         Ok(Self {
             config,
+            schedule,
             market_data,
-            positions: Vec::new(),
+            positions,
+            health: HealthState::new(),
+            events: EventBus::new(),
+            state,
+            last_actions,
+            last_scores,
+            alerts,
+            snapshot_interval,
+            filter,
+            trigger: Arc::new(Notify::new()),
+            quotes: LatestQuotes::default(),
+            quote_marks: HashMap::new(),
+            pool_stats: HashMap::new(),
+            uniswap,
+            uniswap_positions,
+            rebalance,
+            aave,
+            gmx,
+            origins,
         })
     }
     
+    /// Handle to the health signals updated by each cycle (shared with the HTTP probes)
+    pub fn health_state(&self) -> HealthState {
+        self.health.clone()
+    }
+    
+    /// Handle to the positions/recommendations/history exposed by the API
+    pub fn shared_state(&self) -> RecommenderState {
+        self.state.clone()
+    }
+    
+    /// Notifying this starts the next cycle immediately (used by the admin API)
+    pub fn cycle_trigger(&self) -> Arc<Notify> {
+        self.trigger.clone()
+    }
+    
+    /// Bus carrying recommendation events to downstream sinks
+    pub fn event_bus(&self) -> EventBus {
+        self.events.clone()
+    }
+    
+    /// Latest pool quotes; subscribe it to the quote bus to feed market data
+    pub fn latest_quotes(&self) -> LatestQuotes {
+        self.quotes.clone()
+    }
+    
+    /// Market data the recommender scores with; hand it to the market data refresher and
+    /// the predictor
+    pub fn market_data(&self) -> SharedMarketData {
+        self.market_data.clone()
+    }
+    
+    /// Alert marks restored from the last snapshot, for the range and fee monitors
+    pub fn alert_state(&self) -> AlertState {
+        self.alerts.clone()
+    }
+    
     pub async fn run(&mut self) -> Result<()> {
         info!("Starting position recommendation process");
+        let mut snapshots = self.snapshot_interval.map(|every| {
+            let mut ticker = interval_at(Instant::now() + every, every);
+            ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+            ticker
+        });
         
         loop {
             match self.recommend_positions().await {
                 Ok(recommendations) => {
                     info!("Generated {} position recommendations", recommendations.len());
                     self.display_recommendations(&recommendations);
+                    self.publish_changes(&recommendations);
+                    if let Err(e) = self.state.record_cycle(&self.positions, &recommendations).await {
+                        error!("Failed to persist recommendation cycle: {:#}", e);
+                    }
+                    self.save_snapshot().await;
+                    self.health.record_cycle_success().await;
                 }
                 Err(e) => {
                     error!("Error generating recommendations: {}", e);
+                    self.health.record_cycle_failure(&e.to_string()).await;
                 }
             }
             
-            // Wait for the configured interval
-            tokio::time::sleep(tokio::time::Duration::from_secs(self.config.get_recommendation_interval())).await;
+            // Wait for the next scheduled cycle, or an on-demand trigger, snapshotting meanwhile
+            loop {
+                tokio::select! {
+                    _ = self.schedule.tick() => break,
+                    _ = self.trigger.notified() => {
+                        info!("Recommendation cycle triggered on demand");
+                        break;
+                    }
+                    _ = next_snapshot(&mut snapshots) => self.save_snapshot().await,
+                }
+            }
+        }
+    }
+    
+    /// Current positions, scores and alert marks
+    pub fn snapshot(&self) -> StateSnapshot {
+        StateSnapshot {
+            taken_at: chrono::Utc::now(),
+            positions: self.positions.clone(),
+            recommendations: self
+                .last_actions
+                .iter()
+                .map(|(id, action)| {
+                    let score = self.last_scores.get(id).copied().unwrap_or_default();
+                    (id.clone(), RecommendationMark { action: action.clone(), score })
+                })
+                .collect(),
+            alerts: self.alerts.marks(),
+        }
+    }
+    
+    async fn save_snapshot(&self) {
+        if let Err(e) = self.state.storage().save_snapshot(&self.snapshot()).await {
+            warn!("Failed to save state snapshot: {:#}", e);
+        }
+    }
+    
+    /// Update each quoted token's daily volume from the change in its pools' cumulative
+    /// volume since the previous cycle; tokens without two quotes keep their current data
+    async fn refresh_market_data(&mut self) {
+        let mut volumes: HashMap<String, f64> = HashMap::new();
+        for event in self.quotes.all().await {
+            if event.position_id.is_some() && !self.quote_marks.contains_key(&event.quote.pool_id) {
+                self.seed_from_history(&event).await;
+            }
+            let quote = event.quote;
+            let stats = self.pool_stats.entry(quote.pool_id.to_lowercase()).or_default();
+            stats.fee_tier = quote.fee_tier;
+            stats.active_liquidity = event.active_liquidity.or(stats.active_liquidity);
+            if let Some(mark) = self.quote_marks.get(&quote.pool_id) {
+                let elapsed = (quote.timestamp - mark.timestamp).num_seconds();
+                if elapsed > 0 {
+                    let daily = (quote.volume_usd - mark.volume_usd).max(0.0) * 86_400.0 / elapsed as f64;
+                    for token in [&event.token0, &event.token1] {
+                        *volumes.entry(token.clone()).or_default() += daily;
+                    }
+                    stats.daily_volume_usd = Some(daily);
+                    stats.fee_apr = metrics::pool_fee_apr(quote.fee_tier, daily, quote.tvl_usd).or(stats.fee_apr);
+                }
+            }
+            if self.quote_marks.get(&quote.pool_id).is_none_or(|mark| mark.timestamp < quote.timestamp) {
+                self.quote_marks.insert(quote.pool_id.clone(), quote);
+            }
+        }
+        let mut market_data = self.market_data.write().expect("market data lock poisoned");
+        // Tokens CoinGecko prices keep its market-wide volume over that of the quoted pools
+        for (token, volume) in volumes {
+            if market_data.get_price(&token).is_some() {
+                continue;
+            }
+            let data = TokenData {
+                volatility: market_data.get_volatility(&token),
+                market_cap: market_data.get_market_cap(&token),
+                volume,
+                depth: market_data.get_depth(&token),
+                price_usd: None,
+            };
+            market_data.token_data.insert(token, data);
         }
     }
     
+    /// Seed a tracked position's pool's daily volume and fee APR from its recent daily
+    /// history, and the pool's tokens' volatility from its daily closes where no market data
+    /// covers them (the pair's relative volatility, which is what drives its impermanent
+    /// loss). The days are stored as daily candles.
+    async fn seed_from_history(&mut self, event: &QuoteEvent) {
+        let pool_id = &event.quote.pool_id;
+        let days = self.config.market_data.as_ref().map_or(30, |m| m.volatility_days) as usize;
+        let day_data = match self.uniswap.pool_day_data(pool_id, days).await {
+            Ok(day_data) => day_data,
+            Err(e) => {
+                warn!(pool = %pool_id, "Failed to read pool day data: {:#}", e);
+                return;
+            }
+        };
+        let candles: Vec<Candle> = day_data.iter().filter_map(|day| backfill::candle_from_day(pool_id, day).ok()).collect();
+        if let Err(e) = self.state.storage().save_candles(&candles).await {
+            warn!(pool = %pool_id, "Failed to store daily candles: {:#}", e);
+        }
+        let volumes: Vec<f64> = day_data.iter().filter_map(|day| day.volume_usd.parse().ok()).collect();
+        let stats = self.pool_stats.entry(pool_id.to_lowercase()).or_default();
+        if !volumes.is_empty() {
+            stats.daily_volume_usd = Some(volumes.iter().sum::<f64>() / volumes.len() as f64);
+        }
+        stats.fee_apr = metrics::day_data_fee_apr(&day_data).or(stats.fee_apr);
+        if let Some(volatility) = metrics::realized_vol(&candles) {
+            let mut market_data = self.market_data.write().expect("market data lock poisoned");
+            for token in [&event.token0, &event.token1] {
+                if market_data.token_data.contains_key(token) {
+                    continue;
+                }
+                let data = TokenData {
+                    volatility,
+                    market_cap: market_data.get_market_cap(token),
+                    volume: market_data.get_volume(token),
+                    depth: market_data.get_depth(token),
+                    price_usd: None,
+                };
+                market_data.token_data.insert(token.clone(), data);
+            }
+        }
+    }
+    
+    /// Replace the Uniswap, Origins, Aave and GMX positions with the current ones; a protocol
+    /// whose read fails has its previous positions scored again
+    async fn refresh_protocol_positions(&mut self) {
+        if let Some(fetched) = match &self.uniswap_positions {
+            Some(uniswap) => Some(uniswap.positions().await),
+            None => None,
+        } {
+            // Uniswap positions are keyed by bare token id, so they're told apart by their range
+            self.replace_positions(|p| p.liquidity.is_some(), "Uniswap", fetched);
+        }
+        if let Some(fetched) = match &self.origins {
+            Some(origins) => Some(origins.positions().await),
+            None => None,
+        } {
+            self.replace_positions(|p| p.id.starts_with("origins:"), "Origins", fetched);
+        }
+        if let Some(fetched) = match &self.aave {
+            Some(aave) => Some(aave.positions().await),
+            None => None,
+        } {
+            self.replace_positions(|p| p.id.starts_with("aave:"), "Aave", fetched);
+        }
+        if let Some(fetched) = match &self.gmx {
+            Some(gmx) => Some(gmx.positions().await),
+            None => None,
+        } {
+            self.replace_positions(|p| p.id.starts_with("gmx:"), "GMX", fetched);
+        }
+    }
+    
+    /// Swap the positions `owned` says the protocol read for `fetched`
+    fn replace_positions(&mut self, owned: impl Fn(&Position) -> bool, protocol: &str, fetched: Result<Vec<Position>>) {
+        match fetched {
+            Ok(positions) => {
+                self.positions.retain(|p| !owned(p));
+                let filter = &self.filter;
+                self.positions.extend(positions.into_iter().filter(|p| filter.allows_position(p)));
+            }
+            Err(e) => warn!("Failed to read {} positions: {:#}", protocol, e),
+        }
+    }
+    
+    /// One recommendation cycle: refresh market data and protocol positions, then score and
+    /// recommend every position; the span parents the cycle's scoring and fetches
+    #[instrument(name = "recommendation_cycle", skip(self), fields(positions = self.positions.len()))]
     async fn recommend_positions(&mut self) -> Result<Vec<PositionRecommendation>> {
         info!("Analyzing positions and generating recommendations");
         
-        // In a real implementation, this would:
-        // 1. Fetch current positions from the blockchain
-        // 2. Analyze market data
-        // 3. Calculate risk metrics
-        // 4. Generate recommendations
-        
         let mut recommendations = Vec::new();
+        self.refresh_market_data().await;
+        self.refresh_protocol_positions().await;
         
-        // Simulate position analysis
-        for position in &mut self.positions {
-            position.calculate_risk_score(&self.market_data);
-            position.calculate_liquidity_score(&self.market_data);
+        {
+            let mut market_data = self.market_data.write().expect("market data lock poisoned");
+            market_data.tracked_tokens = self.positions.iter().map(|p| p.token_address.to_lowercase()).collect();
+            for position in &mut self.positions {
+                position.calculate_risk_score(&market_data);
+                position.calculate_liquidity_score(&market_data);
+            }
         }
         
-        for position in &self.positions {
-            let recommendation = self.analyze_position(position).await?;
+        // What each liquidity position earns while in range; a rebalance earns it again
+        let in_range_aprs: HashMap<String, f64> = self
+            .positions
+            .iter()
+            .filter_map(|p| Some((p.id.clone(), self.in_range_fee_apr(p)?)))
+            .collect();
+        let rebalances = match &self.rebalance {
+            Some(advisor) => advisor.advise(&self.positions, &in_range_aprs).await,
+            None => HashMap::new(),
+        };
+        for position in self.positions.iter().filter(|p| self.filter.allows_position(p)) {
+            let fee_apr = match in_range_aprs.get(&position.id) {
+                Some(apr) => Some(apr * self.in_range_share(position).await),
+                None => None,
+            };
+            let recommendation = self.analyze_position(position, fee_apr, rebalances.get(&position.id)).await?;
             recommendations.push(recommendation);
         }
         
@@ -77,28 +449,126 @@ impl PositionRecommender {
         Ok(recommendations)
     }
     
-    async fn analyze_position(&self, position: &Position) -> Result<PositionRecommendation> {
-        let recommendation_score = self.calculate_recommendation_score(position);
-        let (suggested_action, reasoning) = self.determine_action(position, recommendation_score);
+    /// Fee APR a liquidity position earns while in range: its share of its pool's active
+    /// liquidity of a day's fees, else the pool's TVL-wide APR while that share is unknown
+    fn in_range_fee_apr(&self, position: &Position) -> Option<f64> {
+        let range = position.liquidity.as_deref()?;
+        let stats = self.pool_stats.get(&range.pool.to_lowercase())?;
+        let value_usd = position.value_usd.to_f64().unwrap_or(0.0);
+        match (stats.daily_volume_usd, stats.active_liquidity, range.liquidity.parse::<u128>()) {
+            (Some(volume), Some(active), Ok(liquidity)) if active > 0 => {
+                metrics::position_fee_apr(stats.fee_tier, volume, liquidity, active, value_usd, true)
+            }
+            _ => stats.fee_apr,
+        }
+    }
+
+    /// Share of the last day a liquidity position spent in range, from its stored range
+    /// alerts and current tick
+    async fn in_range_share(&self, position: &Position) -> f64 {
+        let Some(range) = position.liquidity.as_deref() else {
+            return 1.0;
+        };
+        let now = chrono::Utc::now();
+        let since = now - chrono::Duration::hours(IN_RANGE_WINDOW_HOURS);
+        let query = AlertQuery {
+            position_id: Some(position.id.clone()),
+            since: Some(since),
+            limit: metrics::MAX_RANGE_ALERTS,
+            ..Default::default()
+        };
+        let alerts = self.state.storage().alerts(&query).await.unwrap_or_else(|e| {
+            warn!(position = %position.id, "Failed to read range alerts: {:#}", e);
+            Vec::new()
+        });
+        metrics::in_range_share(range.in_range(), &alerts, since, now)
+    }
+
+    /// `fee_apr`: the position's estimated fee APR; `rebalance`: set when the position is a
+    /// tracked Uniswap position out of range
+    async fn analyze_position(&self, position: &Position, fee_apr: Option<f64>, rebalance: Option<&RebalanceEstimate>) -> Result<PositionRecommendation> {
+        // Liquidity positions lose to holding as prices move, more so in a narrow range;
+        // lending and perp positions don't
+        let projected_il = (position.lending.is_none() && position.perp.is_none()).then(|| {
+            let volatility = self.market_data.read().expect("market data lock poisoned").get_volatility(&position.token_address);
+            position
+                .liquidity
+                .as_deref()
+                .and_then(|range| il::projected_range_il(volatility, range.current_tick, range.tick_lower, range.tick_upper))
+                .unwrap_or_else(|| il::projected_il(volatility))
+        });
+        let net_return = fee_apr.unwrap_or(0.0) + projected_il.unwrap_or(0.0);
+        let recommendation_score = self.calculate_recommendation_score(position, net_return);
+        let (mut suggested_action, mut reasoning) = self.determine_action(position, recommendation_score);
+        if let Some(estimate) = rebalance {
+            let move_summary = format!(
+                "out of range at tick {} ({} to {}): moving to {} to {} costs {} (gas {}, swap {}) against {} in fees over {} days",
+                estimate.current_tick,
+                estimate.tick_lower,
+                estimate.tick_upper,
+                estimate.new_tick_lower,
+                estimate.new_tick_upper,
+                format_usd_compact(estimate.cost_usd()),
+                format_usd_compact(estimate.gas_usd),
+                format_usd_compact(estimate.swap_usd),
+                format_usd_compact(estimate.extra_fees_usd),
+                estimate.horizon_days
+            );
+            if estimate.net_usd() > 0.0 {
+                suggested_action = Action::Rebalance {
+                    new_tick_lower: estimate.new_tick_lower,
+                    new_tick_upper: estimate.new_tick_upper,
+                };
+                reasoning = format!("Position {}", move_summary);
+            } else {
+                reasoning = format!("{}; {}, which doesn't pay", reasoning, move_summary);
+            }
+        }
         
         Ok(PositionRecommendation {
             position: position.clone(),
             recommendation_score,
             reasoning,
             suggested_action,
+            fee_apr,
+            projected_il,
+            rebalance: rebalance.cloned(),
         })
     }
     
-    fn calculate_recommendation_score(&self, position: &Position) -> f64 {
+    /// `net_return`: the position's yearly return net of impermanent loss (fee APR plus
+    /// projected IL)
+    fn calculate_recommendation_score(&self, position: &Position, net_return: f64) -> f64 {
         // Simple scoring algorithm
         let risk_factor = 1.0 - position.risk_score;
         let liquidity_factor = position.liquidity_score;
         let value_factor = position.value_usd.to_f64().unwrap_or(0.0) / 1000.0; // Normalize value
+        let return_factor = net_return.clamp(-1.0, 1.0);
         
-        (risk_factor * 0.4 + liquidity_factor * 0.4 + value_factor * 0.2).min(1.0)
+        (risk_factor * 0.4 + liquidity_factor * 0.4 + value_factor * 0.2 + return_factor * 0.2).min(1.0)
     }
     
-    fn determine_action(&self, _position: &Position, score: f64) -> (Action, String) {
+    fn determine_action(&self, position: &Position, score: f64) -> (Action, String) {
+        // Debt close to liquidation is repaid whatever the score
+        let min_health_factor = self.config.aave.as_ref().map(|a| a.min_health_factor);
+        if let (Some(lending), Some(min)) = (&position.lending, min_health_factor) {
+            if let Some(hf) = lending.health_factor.filter(|hf| *hf < min) {
+                return match lending.side {
+                    LendingSide::Borrow => (Action::Decrease, format!("Health factor {:.2} below {:.2}: repay debt to avoid liquidation", hf, min)),
+                    LendingSide::Supply => (Action::Hold, format!("Health factor {:.2} below {:.2}: keep collateral until debt is repaid", hf, min)),
+                };
+            }
+        }
+        // A short hedging more than the portfolio holds adds exposure instead of removing it
+        if let Some(perp) = position.perp.as_ref().filter(|p| p.side == PerpSide::Short) {
+            let net = self.net_exposure(&position.token_address);
+            if net < 0.0 {
+                return (
+                    Action::Decrease,
+                    format!("Short hedge exceeds the portfolio's exposure by {:.4} tokens ({:.4} shorted)", -net, perp.size_in_tokens),
+                );
+            }
+        }
         if score > 0.8 {
             (Action::Increase, "Strong fundamentals and low risk".to_string())
         } else if score > 0.6 {
@@ -117,22 +587,54 @@ impl PositionRecommender {
             info!(
                 "Recommendation {}: {} {} (Score: {:.2})",
                 i + 1,
-                format!("{:?}", rec.suggested_action),
+                rec.suggested_action,
                 rec.position.token_address,
                 rec.recommendation_score
             );
             info!("Reasoning: {}", rec.reasoning);
-            info!("Value: ${:.2}", rec.position.value_usd);
+            info!("Value: {}", format_usd(&rec.position.value_usd));
             info!("---");
         }
     }
     
+    /// Publish an event for each recommendation that is new or whose action changed
+    fn publish_changes(&mut self, recommendations: &[PositionRecommendation]) {
+        for rec in recommendations {
+            self.last_scores.insert(rec.position.id.clone(), rec.recommendation_score);
+            let previous_action = self.last_actions.insert(rec.position.id.clone(), rec.suggested_action.clone());
+            // A rebalance's target range follows the price; only a new kind of action is news
+            if previous_action.as_ref().map(Action::name) == Some(rec.suggested_action.name()) {
+                continue;
+            }
+            self.events.publish(Event::RecommendationChanged {
+                timestamp: chrono::Utc::now(),
+                previous_action,
+                recommendation: rec.clone(),
+            });
+        }
+    }
+    
     pub fn add_position(&mut self, position: Position) {
         let position_id = position.id.clone();
+        if !self.filter.allows_position(&position) {
+            warn!(position_id = %position_id, token = %position.token_address, "Position not added: token excluded by [filters]");
+            return;
+        }
+        // Positions restored from a snapshot are replaced rather than duplicated
+        self.positions.retain(|p| p.id != position_id);
         self.positions.push(position);
         info!("Added position: {}", position_id);
     }
     
+    /// Net exposure to `token` across positions, in tokens (see [`Position::exposure`])
+    fn net_exposure(&self, token: &str) -> f64 {
+        self.positions
+            .iter()
+            .filter(|p| p.token_address.eq_ignore_ascii_case(token))
+            .map(Position::exposure)
+            .sum()
+    }
+    
     pub fn get_position_metrics(&self) -> PositionMetrics {
         let total_value: Decimal = self.positions.iter()
             .map(|p| p.value_usd)
@@ -140,9 +642,11 @@ impl PositionRecommender {
         
         let mut risk_distribution = HashMap::new();
         let mut liquidity_distribution = HashMap::new();
+        let mut net_exposure = HashMap::new();
         
         for position in &self.positions {
             let token = position.token_address.clone();
+            *net_exposure.entry(token.to_lowercase()).or_insert(0.0) += position.exposure();
             *risk_distribution.entry(token.clone()).or_insert(0.0) += position.risk_score;
             *liquidity_distribution.entry(token).or_insert(0.0) += position.liquidity_score;
         }
@@ -162,6 +666,246 @@ impl PositionRecommender {
             risk_distribution,
             liquidity_distribution,
             concentration_risk,
+            net_exposure,
         }
     }
 }
+
+/// Next snapshot tick; never resolves when snapshots only follow cycles
+async fn next_snapshot(snapshots: &mut Option<Interval>) {
+    match snapshots {
+        Some(ticker) => {
+            ticker.tick().await;
+        }
+        None => std::future::pending().await,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{ApiConfig, UniswapConfig};
+    use crate::events::RangeStatus;
+    use crate::position::{LendingMetrics, LiquidityMetrics};
+    use serde_json::{json, Value};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    /// A local HTTP endpoint answering every POST with `respond(body)`, standing in for both
+    /// the RPC and the subgraph; returns its URL
+    async fn stub_endpoint(respond: fn(&str) -> Value) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                tokio::spawn(async move {
+                    let mut request = Vec::new();
+                    let mut buf = [0u8; 4096];
+                    let body = loop {
+                        let n = stream.read(&mut buf).await.unwrap_or(0);
+                        if n == 0 {
+                            return;
+                        }
+                        request.extend_from_slice(&buf[..n]);
+                        let text = String::from_utf8_lossy(&request).to_string();
+                        if let Some((head, body)) = text.split_once("\r\n\r\n") {
+                            let length = head
+                                .lines()
+                                .find_map(|l| l.to_ascii_lowercase().strip_prefix("content-length:").map(|v| v.trim().parse().unwrap_or(0)))
+                                .unwrap_or(0);
+                            if body.len() >= length {
+                                break body.to_string();
+                            }
+                        }
+                    };
+                    let response = respond(&body).to_string();
+                    let reply = format!(
+                        "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}",
+                        response.len(),
+                        response
+                    );
+                    let _ = stream.write_all(reply.as_bytes()).await;
+                });
+            }
+        });
+        url
+    }
+
+    /// 1 gwei gas and ETH at $2000; anything else (e.g. reading the NFT) fails
+    fn gas_and_eth_price(body: &str) -> Value {
+        if body.contains("eth_gasPrice") {
+            json!({ "jsonrpc": "2.0", "id": 1, "result": "0x3b9aca00" })
+        } else if body.contains("ethPriceUSD") {
+            json!({ "data": { "bundle": { "ethPriceUSD": "2000" }, "tokens": [] } })
+        } else {
+            json!({ "jsonrpc": "2.0", "id": 1, "error": { "code": -32000, "message": "not stubbed" } })
+        }
+    }
+
+    /// Two days of the pool's history ($1m and $3m volume), on top of [`gas_and_eth_price`]
+    fn pool_history(body: &str) -> Value {
+        if !body.contains("poolDayDatas") {
+            return gas_and_eth_price(body);
+        }
+        let day = |date: i64, volume: &str, close: &str| {
+            json!({ "date": date, "open": "1", "high": "1", "low": "1", "close": close, "volumeUSD": volume, "tvlUSD": "10000000", "feesUSD": "3000" })
+        };
+        json!({ "data": { "poolDayDatas": [day(1_700_000_000, "1000000", "1.0"), day(1_700_086_400, "3000000", "1.1")] } })
+    }
+
+    fn test_config(endpoint: &str) -> Config {
+        Config {
+            rpc_url: endpoint.to_string(),
+            api: Some(ApiConfig {
+                coingecko_api_url: endpoint.to_string(),
+                coingecko_api_key: None,
+                coinmarketcap_api_url: None,
+                coinmarketcap_api_key: None,
+                defipulse_api_url: None,
+                thegraph_api_url: Some(endpoint.to_string()),
+                thegraph_api_key: None,
+            }),
+            uniswap: Some(UniswapConfig {
+                pool_ids: Vec::new(),
+                quote_interval_secs: 60,
+                position_ids: vec!["42".to_string()],
+            }),
+            ..Config::default()
+        }
+    }
+
+    /// Tracked position 42: $10k of WETH/USDC (0.3%) in ticks -600 to 600, with the pool at `current_tick`
+    fn tracked_position(current_tick: i32) -> Position {
+        let mut position = Position::new("42".to_string(), "0xowner".to_string(), "0xweth".to_string(), Decimal::new(5, 0), Decimal::new(10_000, 0));
+        position.liquidity = Some(Box::new(LiquidityMetrics {
+            protocol: "uniswap_v3".to_string(),
+            pool: "0xpool".to_string(),
+            token0: "0xweth".to_string(),
+            token1: "0xusdc".to_string(),
+            token0_symbol: "WETH".to_string(),
+            token1_symbol: "USDC".to_string(),
+            fee: 3000,
+            tick_lower: -600,
+            tick_upper: 600,
+            current_tick,
+            liquidity: "1000000000000".to_string(),
+        }));
+        position
+    }
+
+    #[tokio::test]
+    async fn test_out_of_range_position_is_rebalanced() {
+        let endpoint = stub_endpoint(gas_and_eth_price).await;
+        let mut recommender = PositionRecommender::new(test_config(&endpoint)).await.unwrap();
+        // The NFT read fails against the stub, so the added position is scored as last read
+        recommender.add_position(tracked_position(1_234));
+        let stats = PoolStats { fee_tier: 3000, fee_apr: Some(0.5), ..Default::default() };
+        recommender.pool_stats.insert("0xpool".to_string(), stats);
+
+        let recommendations = recommender.recommend_positions().await.unwrap();
+        assert_eq!(recommendations.len(), 1);
+        let recommendation = &recommendations[0];
+        assert_eq!(recommendation.suggested_action, Action::Rebalance { new_tick_lower: 600, new_tick_upper: 1_800 });
+        let estimate = recommendation.rebalance.as_ref().unwrap();
+        // 700k gas at 1 gwei and $2000 per ETH
+        assert!((estimate.gas_usd - 1.4).abs() < 1e-9);
+        assert!(estimate.net_usd() > 0.0);
+    }
+
+    /// A quote of the tracked position's pool, with 4x the position's liquidity active
+    fn pool_quote() -> QuoteEvent {
+        QuoteEvent {
+            quote: PoolQuote {
+                pool_id: "0xPOOL".to_string(),
+                timestamp: chrono::Utc::now(),
+                token0_symbol: "WETH".to_string(),
+                token1_symbol: "USDC".to_string(),
+                fee_tier: 3000,
+                tvl_usd: 10_000_000.0,
+                volume_usd: 500_000_000.0,
+            },
+            token0: "0xweth".to_string(),
+            token1: "0xusdc".to_string(),
+            position_id: Some("42".to_string()),
+            active_liquidity: Some(4_000_000_000_000),
+            price: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_fee_apr_is_positions_share_while_in_range() {
+        let endpoint = stub_endpoint(gas_and_eth_price).await;
+        let mut recommender = PositionRecommender::new(test_config(&endpoint)).await.unwrap();
+        recommender.add_position(tracked_position(0));
+        let stats = PoolStats {
+            fee_tier: 3000,
+            daily_volume_usd: Some(1_000_000.0),
+            active_liquidity: Some(4_000_000_000_000),
+            fee_apr: Some(0.1),
+        };
+        recommender.pool_stats.insert("0xpool".to_string(), stats);
+        // Out of range for 3 of the last 24 hours
+        let now = chrono::Utc::now();
+        let range = |current_tick| RangeStatus {
+            position_id: "42".to_string(),
+            owner: "0xowner".to_string(),
+            pool: "0xpool".to_string(),
+            pair: "WETH/USDC".to_string(),
+            tick_lower: -600,
+            tick_upper: 600,
+            current_tick,
+        };
+        let storage = recommender.state.storage();
+        let out = Event::PositionOutOfRange { timestamp: now - chrono::Duration::hours(6), range: range(700) };
+        let back = Event::PositionBackInRange { timestamp: now - chrono::Duration::hours(3), range: range(0) };
+        storage.save_alert(&out).await.unwrap();
+        storage.save_alert(&back).await.unwrap();
+
+        let recommendations = recommender.recommend_positions().await.unwrap();
+        let fee_apr = recommendations[0].fee_apr.unwrap();
+        // A quarter of the active liquidity, not the pool's TVL-wide 10%
+        let in_range = metrics::position_fee_apr(3000, 1_000_000.0, 1_000_000_000_000, 4_000_000_000_000, 10_000.0, true).unwrap();
+        assert!((fee_apr - in_range * 21.0 / 24.0).abs() < 1e-3, "{fee_apr} vs {in_range}");
+    }
+
+    #[tokio::test]
+    async fn test_seeded_fee_apr_on_first_cycle() {
+        let endpoint = stub_endpoint(pool_history).await;
+        let mut recommender = PositionRecommender::new(test_config(&endpoint)).await.unwrap();
+        recommender.add_position(tracked_position(0));
+        recommender.quotes.insert(pool_quote()).await;
+
+        // One quote gives no volume change yet, so the pool's history stands in
+        let recommendations = recommender.recommend_positions().await.unwrap();
+        let fee_apr = recommendations[0].fee_apr.unwrap();
+        let seeded = metrics::position_fee_apr(3000, 2_000_000.0, 1_000_000_000_000, 4_000_000_000_000, 10_000.0, true).unwrap();
+        assert!((fee_apr - seeded).abs() < 1e-9, "{fee_apr} vs {seeded}");
+    }
+
+    #[tokio::test]
+    async fn test_projected_il_follows_the_range() {
+        let endpoint = stub_endpoint(gas_and_eth_price).await;
+        let mut recommender = PositionRecommender::new(test_config(&endpoint)).await.unwrap();
+        recommender.add_position(tracked_position(0));
+        let mut supply = Position::new("aave:0xowner:0xusdc".to_string(), "0xowner".to_string(), "0xusdc".to_string(), Decimal::new(1_000, 0), Decimal::new(1_000, 0));
+        supply.lending = Some(LendingMetrics {
+            protocol: "aave_v3".to_string(),
+            side: LendingSide::Supply,
+            health_factor: None,
+            apr: 0.05,
+            liquidation_threshold: 0.8,
+        });
+        recommender.add_position(supply);
+        let weth = TokenData { volatility: 0.5, market_cap: 1e9, volume: 1e8, depth: 1e7, price_usd: None };
+        recommender.market_data.write().expect("market data lock poisoned").token_data.insert("0xweth".to_string(), weth);
+
+        let recommendations = recommender.recommend_positions().await.unwrap();
+        let ranged = recommendations.iter().find(|r| r.position.id == "42").unwrap();
+        let projected_il = ranged.projected_il.unwrap();
+        // ±6% concentrates the loss well past a full-range position's
+        assert_eq!(Some(projected_il), il::projected_range_il(0.5, 0, -600, 600));
+        assert!(projected_il < il::projected_il(0.5), "{projected_il}");
+        let lending = recommendations.iter().find(|r| r.position.id.starts_with("aave:")).unwrap();
+        assert_eq!(lending.projected_il, None);
+    }
+}
@@ -0,0 +1,445 @@
+//! Scheduled portfolio reports: tracked positions, PnL and fees from the ledger, and the
+//! recommendations issued during the period, exported as JSON and/or CSV to a local
+//! directory and/or an S3 bucket.
+
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Datelike, Duration, NaiveTime, Utc};
+use reqwest::{Client, Url};
+use rust_decimal::prelude::ToPrimitive;
+use serde::Serialize;
+use std::path::PathBuf;
+use std::sync::Arc;
+use tracing::{info, warn};
+
+use crate::aws::{sha256_hex, AwsCredentials, SignedRequest};
+use crate::config::{ReportFormat, ReportPeriod, ReportS3Config, ReportsConfig};
+use crate::pnl::{by_position, LedgerKind, LedgerQuery, PositionPnl};
+use crate::position::{Action, PositionRecommendation};
+use crate::scheduler::Schedule;
+use crate::storage::{HistoryQuery, RecommendationRecord, Storage};
+use crate::utils::sum_decimal;
+
+/// Recommendation history rows read per storage query
+const HISTORY_PAGE: usize = 1_000;
+
+/// A tracked position and its latest recommendation
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PositionLine {
+    pub position_id: String,
+    pub owner: String,
+    pub token_address: String,
+    pub value_usd: f64,
+    #[serde(serialize_with = "action_text")]
+    pub action: Action,
+    pub score: f64,
+}
+
+/// A recommendation recorded during the period
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RecommendationLine {
+    pub timestamp: DateTime<Utc>,
+    pub cycle: u64,
+    pub position_id: String,
+    pub owner: String,
+    #[serde(serialize_with = "action_text")]
+    pub action: Action,
+    pub score: f64,
+    /// Transactions sent while it was the position's latest recommendation
+    pub transactions: usize,
+    pub reasoning: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct ReportTotals {
+    pub positions: usize,
+    pub value_usd: f64,
+    /// Ledger positions, to date
+    pub pnl_usd: f64,
+    pub fees_collected_usd: f64,
+    /// Ledger entries within the period
+    pub fees_collected_in_period_usd: f64,
+    pub gas_in_period_usd: f64,
+    pub recommendations: usize,
+    pub hold: usize,
+    pub increase: usize,
+    pub decrease: usize,
+    pub exit: usize,
+    pub rebalance: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PortfolioReport {
+    pub period: ReportPeriod,
+    /// Inclusive
+    pub start: DateTime<Utc>,
+    /// Exclusive
+    pub end: DateTime<Utc>,
+    pub generated_at: DateTime<Utc>,
+    pub totals: ReportTotals,
+    /// As of `generated_at`
+    pub positions: Vec<PositionLine>,
+    /// Ledger entries up to `end`, replayed per position
+    pub pnl: Vec<PositionPnl>,
+    /// Oldest first
+    pub recommendations: Vec<RecommendationLine>,
+}
+
+/// The last complete period before `now`: days end at UTC midnight, weeks on Monday
+pub fn period_bounds(period: ReportPeriod, now: DateTime<Utc>) -> (DateTime<Utc>, DateTime<Utc>) {
+    let midnight = now.date_naive().and_time(NaiveTime::MIN).and_utc();
+    match period {
+        ReportPeriod::Daily => (midnight - Duration::days(1), midnight),
+        ReportPeriod::Weekly => {
+            let end = midnight - Duration::days(now.weekday().num_days_from_monday().into());
+            (end - Duration::days(7), end)
+        }
+    }
+}
+
+impl PortfolioReport {
+    /// Assemble the report for the last complete period before `now`
+    pub async fn build(storage: &dyn Storage, period: ReportPeriod, now: DateTime<Utc>) -> Result<Self> {
+        let (start, end) = period_bounds(period, now);
+        let latest = storage.latest().await?;
+        let ledger = storage.ledger(&LedgerQuery { until: Some(end), ..Default::default() }).await?;
+        let mut history = Vec::new();
+        loop {
+            let query = HistoryQuery {
+                since: Some(start),
+                until: Some(end),
+                limit: HISTORY_PAGE,
+                offset: history.len(),
+                ..Default::default()
+            };
+            let page = storage.history(&query).await?;
+            let done = page.len() < HISTORY_PAGE;
+            history.extend(page);
+            if done {
+                break;
+            }
+        }
+
+        let positions: Vec<PositionLine> = latest.iter().map(position_line).collect();
+        let pnl: Vec<PositionPnl> = by_position(ledger.clone()).values().map(|e| PositionPnl::from_entries(e)).collect();
+        history.reverse();
+        let recommendations: Vec<RecommendationLine> = history.iter().map(recommendation_line).collect();
+
+        let in_period = ledger.iter().filter(|e| e.timestamp >= start);
+        let mut totals = ReportTotals {
+            positions: positions.len(),
+            value_usd: sum_decimal(positions.iter().map(|p| p.value_usd)),
+            pnl_usd: sum_decimal(pnl.iter().map(|p| p.pnl_usd)),
+            fees_collected_usd: sum_decimal(pnl.iter().map(|p| p.fees_collected_usd)),
+            recommendations: recommendations.len(),
+            ..Default::default()
+        };
+        totals.gas_in_period_usd = sum_decimal(in_period.clone().map(|e| e.gas_usd));
+        totals.fees_collected_in_period_usd =
+            sum_decimal(in_period.filter(|e| e.kind == LedgerKind::FeeCollection).map(|e| e.value_usd));
+        for line in &recommendations {
+            match line.action {
+                Action::Hold => totals.hold += 1,
+                Action::Increase => totals.increase += 1,
+                Action::Decrease => totals.decrease += 1,
+                Action::Exit => totals.exit += 1,
+                Action::Rebalance { .. } => totals.rebalance += 1,
+            }
+        }
+        Ok(Self { period, start, end, generated_at: now, totals, positions, pnl, recommendations })
+    }
+
+    /// File stem, e.g. `portfolio-weekly-2026-10-12` (the first day covered)
+    pub fn name(&self) -> String {
+        let period = match self.period {
+            ReportPeriod::Daily => "daily",
+            ReportPeriod::Weekly => "weekly",
+        };
+        format!("portfolio-{}-{}", period, self.start.format("%Y-%m-%d"))
+    }
+
+    /// File names and contents for each format. CSV has one file per section.
+    pub fn render(&self, formats: &[ReportFormat]) -> Result<Vec<(String, Vec<u8>)>> {
+        let name = self.name();
+        let mut files = Vec::new();
+        for format in formats {
+            match format {
+                ReportFormat::Json => files.push((format!("{}.json", name), serde_json::to_vec_pretty(self)?)),
+                ReportFormat::Csv => {
+                    files.push((format!("{}-summary.csv", name), csv_rows(std::slice::from_ref(&self.totals))?));
+                    files.push((format!("{}-positions.csv", name), csv_rows(&self.positions)?));
+                    files.push((format!("{}-pnl.csv", name), csv_rows(&self.pnl)?));
+                    files.push((format!("{}-recommendations.csv", name), csv_rows(&self.recommendations)?));
+                }
+            }
+        }
+        Ok(files)
+    }
+}
+
+fn position_line(rec: &PositionRecommendation) -> PositionLine {
+    PositionLine {
+        position_id: rec.position.id.clone(),
+        owner: rec.position.user_address.clone(),
+        token_address: rec.position.token_address.clone(),
+        value_usd: rec.position.value_usd.to_f64().unwrap_or(0.0),
+        action: rec.suggested_action.clone(),
+        score: rec.recommendation_score,
+    }
+}
+
+fn recommendation_line(record: &RecommendationRecord) -> RecommendationLine {
+    let rec = &record.recommendation;
+    RecommendationLine {
+        timestamp: record.timestamp,
+        cycle: record.cycle,
+        position_id: rec.position.id.clone(),
+        owner: rec.position.user_address.clone(),
+        action: rec.suggested_action.clone(),
+        score: rec.recommendation_score,
+        transactions: record.executions.len(),
+        reasoning: rec.reasoning.clone(),
+    }
+}
+
+/// Actions as text, e.g. `Rebalance to ticks -600 to 600`: CSV rows can't hold a variant's fields
+fn action_text<S: serde::Serializer>(action: &Action, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+    serializer.collect_str(action)
+}
+
+fn csv_rows<T: Serialize>(rows: &[T]) -> Result<Vec<u8>> {
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    for row in rows {
+        writer.serialize(row)?;
+    }
+    writer.into_inner().context("flushing CSV")
+}
+
+/// Uploads objects to an S3 (or S3-compatible) bucket with SigV4
+pub struct S3Bucket {
+    http: Client,
+    config: ReportS3Config,
+}
+
+impl S3Bucket {
+    pub fn new(config: &ReportS3Config) -> Self {
+        Self {
+            http: Client::builder()
+                .timeout(std::time::Duration::from_secs(60))
+                .build()
+                .expect("failed to build reqwest client"),
+            config: config.clone(),
+        }
+    }
+
+    /// Host header and URL for `key`: virtual-hosted on AWS, path-style on a custom endpoint
+    fn locate(&self, key: &str) -> Result<(String, String)> {
+        let path = format!("/{}", uri_encode_path(key));
+        match &self.config.endpoint {
+            Some(endpoint) => {
+                let url = Url::parse(endpoint).with_context(|| format!("invalid S3 endpoint {}", endpoint))?;
+                let host = match (url.host_str(), url.port()) {
+                    (Some(host), Some(port)) => format!("{}:{}", host, port),
+                    (Some(host), None) => host.to_string(),
+                    (None, _) => bail!("S3 endpoint {} has no host", endpoint),
+                };
+                let path = format!("/{}{}", uri_encode_path(&self.config.bucket), path);
+                Ok((host, path))
+            }
+            None => Ok((format!("{}.s3.{}.amazonaws.com", self.config.bucket, self.config.region), path)),
+        }
+    }
+
+    pub async fn put(&self, key: &str, body: Vec<u8>, content_type: &str) -> Result<()> {
+        let credentials = AwsCredentials::from_env()?;
+        let (host, path) = self.locate(key)?;
+        let scheme = match &self.config.endpoint {
+            Some(endpoint) if endpoint.starts_with("http://") => "http",
+            _ => "https",
+        };
+        let now = Utc::now();
+        let payload_hash = sha256_hex(&body);
+        let mut headers = vec![
+            ("content-type", content_type.to_string()),
+            ("host", host.clone()),
+            ("x-amz-content-sha256", payload_hash.clone()),
+            ("x-amz-date", now.format("%Y%m%dT%H%M%SZ").to_string()),
+        ];
+        if let Some(token) = &credentials.session_token {
+            headers.push(("x-amz-security-token", token.clone()));
+        }
+        let request = SignedRequest { method: "PUT", path: &path, headers: &headers, payload_hash: &payload_hash };
+        let authorization = credentials.authorization(&self.config.region, "s3", &now, &request);
+
+        let mut put = self.http.put(format!("{}://{}{}", scheme, host, path));
+        for (name, value) in headers.iter().filter(|(name, _)| *name != "host") {
+            put = put.header(*name, value);
+        }
+        let resp = put.header("authorization", authorization).body(body).send().await?;
+        let status = resp.status();
+        if !status.is_success() {
+            bail!("S3 PUT {} failed ({}): {}", key, status, resp.text().await.unwrap_or_default());
+        }
+        Ok(())
+    }
+}
+
+/// Percent-encode everything but unreserved characters and `/`, as S3 canonical URIs expect
+fn uri_encode_path(path: &str) -> String {
+    path.bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' | b'/' => (b as char).to_string(),
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+/// Builds and writes a report after each period
+pub struct ReportExporter {
+    storage: Arc<dyn Storage>,
+    config: ReportsConfig,
+    schedule: Schedule,
+    s3: Option<S3Bucket>,
+}
+
+impl ReportExporter {
+    pub fn new(storage: Arc<dyn Storage>, config: &ReportsConfig, schedule: Schedule) -> Self {
+        Self {
+            storage,
+            config: config.clone(),
+            schedule,
+            s3: config.s3.as_ref().map(S3Bucket::new),
+        }
+    }
+
+    pub async fn run(self) {
+        loop {
+            self.schedule.tick().await;
+            if let Err(e) = self.export(Utc::now()).await {
+                warn!(target: "reports", "report export failed: {:#}", e);
+            }
+        }
+    }
+
+    /// Export the report for the last complete period before `now`; returns its name
+    pub async fn export(&self, now: DateTime<Utc>) -> Result<String> {
+        if self.config.dir.is_none() && self.s3.is_none() {
+            bail!("[reports] needs a dir or an [reports.s3] bucket");
+        }
+        let report = PortfolioReport::build(self.storage.as_ref(), self.config.period, now).await?;
+        let files = report.render(&self.config.formats)?;
+        if let Some(dir) = &self.config.dir {
+            let dir = PathBuf::from(dir);
+            tokio::fs::create_dir_all(&dir).await.with_context(|| format!("creating {}", dir.display()))?;
+            for (name, body) in &files {
+                let tmp = dir.join(format!(".{}.tmp", name));
+                tokio::fs::write(&tmp, body).await.with_context(|| format!("writing {}", tmp.display()))?;
+                tokio::fs::rename(&tmp, dir.join(name)).await.with_context(|| format!("renaming {}", tmp.display()))?;
+            }
+        }
+        if let Some(s3) = &self.s3 {
+            for (name, body) in files.iter() {
+                let content_type = if name.ends_with(".csv") { "text/csv" } else { "application/json" };
+                s3.put(&format!("{}{}", s3.config.prefix, name), body.clone(), content_type).await?;
+            }
+        }
+        info!(
+            target: "reports",
+            report = %report.name(),
+            files = files.len(),
+            recommendations = report.totals.recommendations,
+            "portfolio report exported"
+        );
+        Ok(report.name())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pnl::LedgerEntry;
+    use crate::position::Position;
+    use crate::storage::MemoryStorage;
+    use chrono::TimeZone;
+    use rust_decimal::Decimal;
+
+    #[test]
+    fn test_period_bounds() {
+        // A Wednesday afternoon
+        let now = Utc.with_ymd_and_hms(2026, 10, 14, 15, 30, 0).unwrap();
+        let day = |d| Utc.with_ymd_and_hms(2026, 10, d, 0, 0, 0).unwrap();
+        assert_eq!(period_bounds(ReportPeriod::Daily, now), (day(13), day(14)));
+        assert_eq!(period_bounds(ReportPeriod::Weekly, now), (day(5), day(12)));
+        assert_eq!(period_bounds(ReportPeriod::Weekly, day(12)), (day(5), day(12)));
+    }
+
+    #[tokio::test]
+    async fn test_report_contents() {
+        let storage = MemoryStorage::new();
+        let recommendation = |action: Action| PositionRecommendation {
+            position: Position::new("p1".to_string(), "0xAA".to_string(), "0xtoken".to_string(), Decimal::ONE, Decimal::new(2500, 0)),
+            recommendation_score: 0.4,
+            reasoning: "range is drifting, \"tight\"".to_string(),
+            suggested_action: action,
+            fee_apr: None,
+            projected_il: None,
+            rebalance: None,
+        };
+        storage.record_cycle(&[], &[recommendation(Action::Hold)]).await.unwrap();
+        storage.record_cycle(&[], &[recommendation(Action::Exit)]).await.unwrap();
+
+        let now = Utc::now();
+        let entry = |kind, days_ago: i64, value_usd| LedgerEntry {
+            position_id: "42".to_string(),
+            owner: "0xaa".to_string(),
+            kind,
+            timestamp: now - Duration::days(days_ago),
+            amount0: 0.0,
+            amount1: 0.0,
+            value_usd,
+            liquidity: 1.0,
+            uncollected_fees_usd: 0.0,
+            gas_usd: 2.0,
+            tx_hash: None,
+            note: None,
+        };
+        storage.save_ledger_entry(&entry(LedgerKind::Deposit, 10, 1000.0)).await.unwrap();
+        storage.save_ledger_entry(&entry(LedgerKind::FeeCollection, 3, 30.0)).await.unwrap();
+
+        // Everything was recorded just now: report on the week that includes it
+        let report = PortfolioReport::build(&storage, ReportPeriod::Weekly, now + Duration::days(7)).await.unwrap();
+        assert_eq!(report.positions.len(), 1);
+        assert_eq!(report.positions[0].action, Action::Exit);
+        assert_eq!(report.recommendations.iter().map(|r| r.action.clone()).collect::<Vec<_>>(), [Action::Hold, Action::Exit]);
+        assert_eq!((report.totals.hold, report.totals.exit, report.totals.value_usd), (1, 1, 2500.0));
+        assert_eq!(report.pnl.len(), 1);
+        assert_eq!(report.totals.fees_collected_usd, 30.0);
+
+        let files = report.render(&[ReportFormat::Json, ReportFormat::Csv]).unwrap();
+        assert_eq!(files.len(), 5);
+        assert!(files[0].0.starts_with("portfolio-weekly-") && files[0].0.ends_with(".json"));
+        let recommendations = String::from_utf8(files[4].1.clone()).unwrap();
+        let mut lines = recommendations.lines();
+        assert_eq!(lines.next().unwrap(), "timestamp,cycle,position_id,owner,action,score,transactions,reasoning");
+        assert!(lines.next().unwrap().ends_with(",1,p1,0xAA,Hold,0.4,0,\"range is drifting, \"\"tight\"\"\""));
+    }
+
+    #[test]
+    fn test_s3_locations() {
+        let mut config = ReportS3Config {
+            bucket: "reports".to_string(),
+            region: "eu-west-1".to_string(),
+            prefix: String::new(),
+            endpoint: None,
+        };
+        let key = "daily/portfolio daily.json";
+        assert_eq!(
+            S3Bucket::new(&config).locate(key).unwrap(),
+            ("reports.s3.eu-west-1.amazonaws.com".to_string(), "/daily/portfolio%20daily.json".to_string())
+        );
+        config.endpoint = Some("http://127.0.0.1:9000".to_string());
+        assert_eq!(
+            S3Bucket::new(&config).locate(key).unwrap(),
+            ("127.0.0.1:9000".to_string(), "/reports/daily/portfolio%20daily.json".to_string())
+        );
+    }
+}
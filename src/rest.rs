@@ -0,0 +1,286 @@
+use axum::extract::{Query, State};
+use axum::http::StatusCode;
+use axum::{Extension, Json};
+use chrono::{DateTime, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+
+use crate::api_keys::ApiKey;
+use crate::metrics::{MetricKind, MetricPoint, MetricQuery};
+use crate::pnl::{by_position, LedgerEntry, LedgerQuery, PositionPnl, TaxReport};
+use crate::position::{Action, PositionRecommendation};
+use crate::server::AppState;
+use crate::storage::{HistoryQuery, PoolQuote, RecommendationRecord};
+
+/// Largest page a caller may request
+pub(crate) const MAX_PAGE_SIZE: usize = 500;
+
+fn default_limit() -> usize {
+    100
+}
+
+/// Query parameters shared by the recommendation and history endpoints
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct RecommendationParams {
+    pub position_id: Option<String>,
+    /// Only this wallet's positions
+    pub wallet: Option<String>,
+    /// Hold, Increase, Decrease, Exit or Rebalance
+    #[param(value_type = Option<String>)]
+    #[serde(default, deserialize_with = "action_param")]
+    pub action: Option<Action>,
+    pub token_address: Option<String>,
+    /// RFC 3339, inclusive (history only)
+    pub since: Option<DateTime<Utc>>,
+    /// RFC 3339, exclusive (history only)
+    pub until: Option<DateTime<Utc>>,
+    /// Page size, at most 500
+    #[serde(default = "default_limit")]
+    pub limit: usize,
+    #[serde(default)]
+    pub offset: usize,
+}
+
+/// An action by name, case-insensitive
+fn action_param<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<Option<Action>, D::Error> {
+    Option::<String>::deserialize(deserializer)?
+        .map(|name| name.parse().map_err(serde::de::Error::custom))
+        .transpose()
+}
+
+impl RecommendationParams {
+    fn into_query(self, caller: Option<&ApiKey>) -> HistoryQuery {
+        HistoryQuery {
+            position_id: self.position_id,
+            wallet: self.wallet,
+            wallets: caller.map(|k| k.wallets.clone()).unwrap_or_default(),
+            action: self.action,
+            token_address: self.token_address,
+            since: self.since,
+            until: self.until,
+            limit: self.limit.min(MAX_PAGE_SIZE),
+            offset: self.offset,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct RecommendationPage {
+    pub items: Vec<PositionRecommendation>,
+    pub limit: usize,
+    pub offset: usize,
+    /// Offset of the next page, absent on the last page
+    pub next_offset: Option<usize>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct HistoryPage {
+    pub items: Vec<RecommendationRecord>,
+    pub limit: usize,
+    pub offset: usize,
+    /// Offset of the next page, absent on the last page
+    pub next_offset: Option<usize>,
+}
+
+fn next_offset(returned: usize, query: &HistoryQuery) -> Option<usize> {
+    (returned == query.limit && returned > 0).then_some(query.offset + query.limit)
+}
+
+/// Recommendations from the latest cycle
+#[utoipa::path(
+    get,
+    path = "/recommendations",
+    tag = "query",
+    params(RecommendationParams),
+    responses((status = 200, description = "One page of recommendations", body = RecommendationPage)),
+    security((), ("api_key" = []), ("bearer" = []))
+)]
+pub async fn recommendations(
+    State(state): State<AppState>,
+    caller: Option<Extension<ApiKey>>,
+    Query(params): Query<RecommendationParams>,
+) -> Result<Json<RecommendationPage>, (StatusCode, String)> {
+    let query = params.into_query(caller.as_ref().map(|Extension(k)| k));
+    let latest = state.recommender.latest().await.map_err(internal)?;
+    let items: Vec<PositionRecommendation> = latest
+        .into_iter()
+        .filter(|r| query.matches_recommendation(r))
+        .skip(query.offset)
+        .take(query.limit)
+        .collect();
+    Ok(Json(RecommendationPage {
+        next_offset: next_offset(items.len(), &query),
+        limit: query.limit,
+        offset: query.offset,
+        items,
+    }))
+}
+
+/// Past recommendations, newest first
+#[utoipa::path(
+    get,
+    path = "/history",
+    tag = "query",
+    params(RecommendationParams),
+    responses((status = 200, description = "One page of recommendation history", body = HistoryPage)),
+    security((), ("api_key" = []), ("bearer" = []))
+)]
+pub async fn history(
+    State(state): State<AppState>,
+    caller: Option<Extension<ApiKey>>,
+    Query(params): Query<RecommendationParams>,
+) -> Result<Json<HistoryPage>, (StatusCode, String)> {
+    let query = params.into_query(caller.as_ref().map(|Extension(k)| k));
+    let items = state.recommender.history(&query).await.map_err(internal)?;
+    Ok(Json(HistoryPage {
+        next_offset: next_offset(items.len(), &query),
+        limit: query.limit,
+        offset: query.offset,
+        items,
+    }))
+}
+
+/// Query parameters for the PnL endpoint
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct PnlParams {
+    pub position_id: Option<String>,
+    /// Only this wallet's positions
+    pub wallet: Option<String>,
+}
+
+/// Query parameters for the tax report
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct TaxReportParams {
+    /// Calendar year (UTC)
+    pub year: i32,
+    /// Only this wallet's positions
+    pub wallet: Option<String>,
+}
+
+/// Ledger entries visible to the caller, oldest first
+async fn visible_ledger(
+    state: &AppState,
+    caller: Option<&ApiKey>,
+    query: LedgerQuery,
+) -> Result<Vec<LedgerEntry>, (StatusCode, String)> {
+    let entries = state.recommender.storage().ledger(&query).await.map_err(internal)?;
+    Ok(entries.into_iter().filter(|e| caller.is_none_or(|k| k.allows_wallet(&e.owner))).collect())
+}
+
+/// PnL per position, replayed from the ledger
+#[utoipa::path(
+    get,
+    path = "/pnl",
+    tag = "query",
+    params(PnlParams),
+    responses((status = 200, description = "PnL of each position with ledger entries", body = Vec<PositionPnl>)),
+    security((), ("api_key" = []), ("bearer" = []))
+)]
+pub async fn pnl(
+    State(state): State<AppState>,
+    caller: Option<Extension<ApiKey>>,
+    Query(params): Query<PnlParams>,
+) -> Result<Json<Vec<PositionPnl>>, (StatusCode, String)> {
+    let query = LedgerQuery { position_id: params.position_id, owner: params.wallet, ..Default::default() };
+    let entries = visible_ledger(&state, caller.as_ref().map(|Extension(k)| k), query).await?;
+    Ok(Json(by_position(entries).values().map(|e| PositionPnl::from_entries(e)).collect()))
+}
+
+/// Fee income and realized gains for a calendar year, from the ledger
+#[utoipa::path(
+    get,
+    path = "/reports/tax",
+    tag = "query",
+    params(TaxReportParams),
+    responses(
+        (status = 200, description = "Tax report", body = TaxReport),
+        (status = 400, description = "Invalid year")
+    ),
+    security((), ("api_key" = []), ("bearer" = []))
+)]
+pub async fn tax_report(
+    State(state): State<AppState>,
+    caller: Option<Extension<ApiKey>>,
+    Query(params): Query<TaxReportParams>,
+) -> Result<Json<TaxReport>, (StatusCode, String)> {
+    // Earlier years are needed for the cost basis; later ones are not
+    let until = Utc
+        .with_ymd_and_hms(params.year + 1, 1, 1, 0, 0, 0)
+        .single()
+        .ok_or((StatusCode::BAD_REQUEST, format!("invalid year {}", params.year)))?;
+    let query = LedgerQuery { owner: params.wallet, until: Some(until), ..Default::default() };
+    let entries = visible_ledger(&state, caller.as_ref().map(|Extension(k)| k), query).await?;
+    Ok(Json(TaxReport::from_entries(params.year, entries)))
+}
+
+/// Query parameters for the metrics endpoint
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct MetricParams {
+    /// pool_fee_apr, realized_vol, position_value, in_range_pct or rpc_score
+    #[param(value_type = Option<String>)]
+    pub metric: Option<MetricKind>,
+    /// Pool address or position id
+    pub subject: Option<String>,
+    /// RFC 3339, inclusive
+    pub since: Option<DateTime<Utc>>,
+    /// RFC 3339, exclusive
+    pub until: Option<DateTime<Utc>>,
+    /// Points returned, at most 500
+    #[serde(default = "default_limit")]
+    pub limit: usize,
+}
+
+/// Stored metric time series, oldest first
+#[utoipa::path(
+    get,
+    path = "/metrics",
+    tag = "query",
+    params(MetricParams),
+    responses((status = 200, description = "Metric points", body = Vec<MetricPoint>)),
+    security((), ("api_key" = []), ("bearer" = []))
+)]
+pub async fn metrics(
+    State(state): State<AppState>,
+    caller: Option<Extension<ApiKey>>,
+    Query(params): Query<MetricParams>,
+) -> Result<Json<Vec<MetricPoint>>, (StatusCode, String)> {
+    let query = MetricQuery {
+        metric: params.metric,
+        subject: params.subject,
+        since: params.since,
+        until: params.until,
+        limit: params.limit.min(MAX_PAGE_SIZE),
+    };
+    let points = state.recommender.storage().metrics(&query).await.map_err(internal)?;
+    let caller = caller.as_ref().map(|Extension(k)| k);
+    if caller.is_none_or(|k| k.wallets.is_empty()) {
+        return Ok(Json(points));
+    }
+    // Wallet-scoped keys see pool metrics, and position metrics of positions in their ledger
+    let visible: Vec<String> = visible_ledger(&state, caller, LedgerQuery::default())
+        .await?
+        .into_iter()
+        .map(|e| e.position_id)
+        .collect();
+    Ok(Json(points.into_iter().filter(|p| !p.metric.per_position() || visible.contains(&p.subject)).collect()))
+}
+
+/// Latest quote of every pool the quote pipeline tracks, ordered by pool id
+#[utoipa::path(
+    get,
+    path = "/quotes",
+    tag = "query",
+    responses((status = 200, description = "Latest pool quotes", body = Vec<PoolQuote>)),
+    security((), ("api_key" = []), ("bearer" = []))
+)]
+pub async fn quotes(State(state): State<AppState>) -> Json<Vec<PoolQuote>> {
+    Json(state.quotes.all().await.into_iter().map(|event| event.quote).collect())
+}
+
+fn internal(e: anyhow::Error) -> (StatusCode, String) {
+    (StatusCode::INTERNAL_SERVER_ERROR, format!("{:#}", e))
+}
@@ -0,0 +1,78 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use std::str::FromStr;
+use std::time::Duration;
+
+/// When a periodic task should next run: a fixed interval or a cron expression
+#[derive(Debug, Clone)]
+pub enum Schedule {
+    Every(Duration),
+    Cron(Box<cron::Schedule>),
+}
+
+impl Schedule {
+    pub fn every_secs(secs: u64) -> Self {
+        Schedule::Every(Duration::from_secs(secs.max(1)))
+    }
+
+    /// Parse a cron expression (UTC).
+    ///
+    /// Accepts standard 5-field expressions (`min hour dom month dow`) as well as the
+    /// 6/7-field forms with leading seconds and trailing year.
+    pub fn parse_cron(expr: &str) -> Result<Self> {
+        let fields = expr.split_whitespace().count();
+        let normalized = if fields == 5 {
+            format!("0 {}", expr.trim())
+        } else {
+            expr.trim().to_string()
+        };
+        let schedule = cron::Schedule::from_str(&normalized)
+            .with_context(|| format!("invalid cron expression '{}'", expr))?;
+        Ok(Schedule::Cron(Box::new(schedule)))
+    }
+
+    /// Use the cron expression when configured, otherwise the fixed interval
+    pub fn from_cron_or_interval(cron_expr: Option<&str>, interval_secs: u64) -> Result<Self> {
+        match cron_expr.map(str::trim).filter(|e| !e.is_empty()) {
+            Some(expr) => Self::parse_cron(expr),
+            None => Ok(Self::every_secs(interval_secs)),
+        }
+    }
+
+    /// Delay from `now` until the next scheduled run
+    pub fn delay_after(&self, now: DateTime<Utc>) -> Duration {
+        match self {
+            Schedule::Every(interval) => *interval,
+            Schedule::Cron(schedule) => schedule
+                .after(&now)
+                .next()
+                .and_then(|next| (next - now).to_std().ok())
+                .unwrap_or(Duration::from_secs(60)),
+        }
+    }
+
+    /// Sleep until the next scheduled run
+    pub async fn tick(&self) {
+        tokio::time::sleep(self.delay_after(Utc::now())).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_five_field_cron() {
+        let schedule = Schedule::parse_cron("*/15 * * * *").unwrap();
+        let now = Utc.with_ymd_and_hms(2024, 1, 1, 12, 7, 30).unwrap();
+        assert_eq!(schedule.delay_after(now), Duration::from_secs(7 * 60 + 30));
+    }
+
+    #[test]
+    fn test_interval_fallback() {
+        let schedule = Schedule::from_cron_or_interval(None, 300).unwrap();
+        assert_eq!(schedule.delay_after(Utc::now()), Duration::from_secs(300));
+        assert!(Schedule::from_cron_or_interval(Some("not a cron"), 300).is_err());
+    }
+}
@@ -0,0 +1,336 @@
+//! Secret references in config values, resolved once at startup so secrets never have to be
+//! written to disk:
+//!
+//! - `vault:<mount>/<path>#<key>`: a field of a HashiCorp Vault KV v2 secret
+//! - `aws-sm:<secret id>[#<key>]`: an AWS Secrets Manager secret string, or one field of it
+//!   when it holds JSON
+//! - `keyring:<name>`: an entry in the OS keyring (see [`crate::credentials`])
+
+use anyhow::{anyhow, bail, Context, Result};
+use chrono::Utc;
+use reqwest::Client;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::time::Duration;
+use tracing::info;
+
+use crate::aws::{sha256_hex, AwsCredentials, SignedRequest};
+use crate::config::{Config, SecretsConfig};
+use crate::credentials;
+
+/// Where a secret-valued field points
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SecretRef {
+    Vault { mount: String, path: String, key: String },
+    AwsSecretsManager { secret_id: String, key: Option<String> },
+    Keyring { name: String },
+}
+
+impl SecretRef {
+    /// `None` for a plain value; an error for a malformed reference
+    pub fn parse(value: &str) -> Result<Option<Self>> {
+        if let Some(rest) = value.strip_prefix("vault:") {
+            let (location, key) = rest
+                .split_once('#')
+                .ok_or_else(|| anyhow!("vault reference '{}' needs a #key", value))?;
+            let (mount, path) = location
+                .split_once('/')
+                .filter(|(mount, path)| !mount.is_empty() && !path.is_empty())
+                .ok_or_else(|| anyhow!("vault reference '{}' needs a <mount>/<path>", value))?;
+            if key.is_empty() {
+                bail!("vault reference '{}' has an empty key", value);
+            }
+            return Ok(Some(Self::Vault { mount: mount.to_string(), path: path.to_string(), key: key.to_string() }));
+        }
+        if let Some(rest) = value.strip_prefix("aws-sm:") {
+            let (secret_id, key) = match rest.split_once('#') {
+                Some((id, key)) => (id, Some(key.to_string()).filter(|k| !k.is_empty())),
+                None => (rest, None),
+            };
+            if secret_id.is_empty() {
+                bail!("aws-sm reference '{}' has an empty secret id", value);
+            }
+            return Ok(Some(Self::AwsSecretsManager { secret_id: secret_id.to_string(), key }));
+        }
+        if let Some(name) = value.strip_prefix("keyring:") {
+            if name.is_empty() {
+                bail!("keyring reference '{}' has an empty entry name", value);
+            }
+            return Ok(Some(Self::Keyring { name: name.to_string() }));
+        }
+        Ok(None)
+    }
+}
+
+/// Fetches referenced secrets, reading each Vault path or Secrets Manager secret once
+pub struct SecretResolver {
+    http: Client,
+    config: SecretsConfig,
+    fetched: HashMap<String, Value>,
+}
+
+impl SecretResolver {
+    pub fn new(config: SecretsConfig) -> Self {
+        Self {
+            http: Client::builder()
+                .timeout(Duration::from_secs(15))
+                .build()
+                .expect("reqwest client"),
+            config,
+            fetched: HashMap::new(),
+        }
+    }
+
+    /// The secret `value` refers to, or `None` when it is a plain value
+    pub async fn resolve(&mut self, value: &str) -> Result<Option<String>> {
+        let Some(reference) = SecretRef::parse(value)? else {
+            return Ok(None);
+        };
+        let secret = match &reference {
+            SecretRef::Vault { mount, path, key } => {
+                let cache_key = format!("vault:{}/{}", mount, path);
+                if !self.fetched.contains_key(&cache_key) {
+                    let data = self.vault(mount, path).await?;
+                    self.fetched.insert(cache_key.clone(), data);
+                }
+                field(&self.fetched[&cache_key], key)?
+            }
+            SecretRef::AwsSecretsManager { secret_id, key } => {
+                let cache_key = format!("aws-sm:{}", secret_id);
+                if !self.fetched.contains_key(&cache_key) {
+                    let secret = self.aws(secret_id).await?;
+                    self.fetched.insert(cache_key.clone(), Value::String(secret));
+                }
+                let secret = self.fetched[&cache_key].as_str().unwrap_or_default();
+                match key {
+                    Some(key) => {
+                        let json: Value = serde_json::from_str(secret)
+                            .with_context(|| format!("secret '{}' is not JSON, so it has no '{}' field", secret_id, key))?;
+                        field(&json, key)?
+                    }
+                    None => secret.to_string(),
+                }
+            }
+            SecretRef::Keyring { name } => credentials::get(name)?.ok_or_else(|| {
+                anyhow!("no keyring entry '{}' (store it with `keyring set {}`)", name, name)
+            })?,
+        };
+        Ok(Some(secret))
+    }
+
+    /// `data.data` of a KV v2 secret
+    async fn vault(&self, mount: &str, path: &str) -> Result<Value> {
+        let addr = match &self.config.vault_addr {
+            Some(addr) => addr.clone(),
+            None => std::env::var("VAULT_ADDR").context("secrets.vault_addr and VAULT_ADDR are both unset")?,
+        };
+        let token = std::env::var(&self.config.vault_token_env)
+            .with_context(|| format!("{} not set", self.config.vault_token_env))?;
+        let mut request = self
+            .http
+            .get(format!("{}/v1/{}/data/{}", addr.trim_end_matches('/'), mount, path))
+            .header("X-Vault-Token", token);
+        if let Some(namespace) = &self.config.vault_namespace {
+            request = request.header("X-Vault-Namespace", namespace);
+        }
+        let resp: Value = request
+            .send()
+            .await?
+            .error_for_status()
+            .with_context(|| format!("reading {}/{} from Vault", mount, path))?
+            .json()
+            .await?;
+        resp.pointer("/data/data")
+            .cloned()
+            .ok_or_else(|| anyhow!("Vault returned no data for {}/{} (is it a KV v2 mount?)", mount, path))
+    }
+
+    /// `SecretString` of a Secrets Manager secret
+    async fn aws(&self, secret_id: &str) -> Result<String> {
+        let region = match &self.config.aws_region {
+            Some(region) => region.clone(),
+            None => std::env::var("AWS_REGION").context("secrets.aws_region and AWS_REGION are both unset")?,
+        };
+        let credentials = AwsCredentials::from_env()?;
+        let host = format!("secretsmanager.{}.amazonaws.com", region);
+        let body = json!({ "SecretId": secret_id }).to_string();
+        let now = Utc::now();
+
+        let mut headers = vec![
+            ("content-type", "application/x-amz-json-1.1".to_string()),
+            ("host", host.clone()),
+            ("x-amz-date", now.format("%Y%m%dT%H%M%SZ").to_string()),
+        ];
+        if let Some(token) = &credentials.session_token {
+            headers.push(("x-amz-security-token", token.clone()));
+        }
+        headers.push(("x-amz-target", "secretsmanager.GetSecretValue".to_string()));
+        let payload_hash = sha256_hex(body.as_bytes());
+        let request = SignedRequest { method: "POST", path: "/", headers: &headers, payload_hash: &payload_hash };
+        let authorization = credentials.authorization(&region, "secretsmanager", &now, &request);
+
+        let mut request = self.http.post(format!("https://{}/", host));
+        for (name, value) in headers.iter().filter(|(name, _)| *name != "host") {
+            request = request.header(*name, value);
+        }
+        let resp = request.header("authorization", authorization).body(body).send().await?;
+        let status = resp.status();
+        let json: Value = resp.json().await.unwrap_or(Value::Null);
+        if !status.is_success() {
+            bail!("Secrets Manager GetSecretValue for '{}' failed ({}): {}", secret_id, status, json);
+        }
+        json["SecretString"]
+            .as_str()
+            .map(str::to_string)
+            .ok_or_else(|| anyhow!("secret '{}' has no SecretString (binary secrets are not supported)", secret_id))
+    }
+}
+
+fn field(data: &Value, key: &str) -> Result<String> {
+    match data.get(key) {
+        Some(Value::String(s)) => Ok(s.clone()),
+        Some(Value::Number(n)) => Ok(n.to_string()),
+        Some(_) => bail!("secret field '{}' is not a string", key),
+        None => bail!("secret has no field '{}'", key),
+    }
+}
+
+/// Secret-valued fields that may hold a reference, with their TOML paths
+fn secret_fields(config: &mut Config) -> Vec<(&'static str, &mut String)> {
+    let mut fields = Vec::new();
+    if let Some(key) = config.private_key.as_mut() {
+        fields.push(("private_key", key));
+    }
+    if let Some(key) = config.security.as_mut().and_then(|s| s.private_key.as_mut()) {
+        fields.push(("security.private_key", key));
+    }
+    if let Some(api) = config.api.as_mut() {
+        if let Some(key) = api.coinmarketcap_api_key.as_mut() {
+            fields.push(("api.coinmarketcap_api_key", key));
+        }
+        if let Some(key) = api.thegraph_api_key.as_mut() {
+            fields.push(("api.thegraph_api_key", key));
+        }
+        if let Some(key) = api.coingecko_api_key.as_mut() {
+            fields.push(("api.coingecko_api_key", key));
+        }
+    }
+    if let Some(channels) = config.notifications.as_mut().and_then(|n| n.notification_channels.as_mut()) {
+        if let Some(email) = channels.email.as_mut() {
+            fields.push(("notifications.notification_channels.email.password", &mut email.password));
+        }
+        if let Some(slack) = channels.slack.as_mut() {
+            fields.push(("notifications.notification_channels.slack.bot_token", &mut slack.bot_token));
+        }
+        if let Some(telegram) = channels.telegram.as_mut() {
+            fields.push(("notifications.notification_channels.telegram.bot_token", &mut telegram.bot_token));
+        }
+    }
+    if let Some(secret) = config.webhooks.as_mut().and_then(|w| w.secret.as_mut()) {
+        fields.push(("webhooks.secret", secret));
+    }
+    if let Some(ops) = config.ops_alerts.as_mut() {
+        fields.push(("ops_alerts.api_key", &mut ops.api_key));
+    }
+    if let Some(jwt) = config.server.as_mut().and_then(|s| s.jwt.as_mut()) {
+        fields.push(("server.jwt.secret", &mut jwt.secret));
+    }
+    fields
+}
+
+/// Replace every secret reference in `config` with the secret it points to
+pub async fn resolve_config(config: &mut Config) -> Result<()> {
+    let mut resolver = SecretResolver::new(config.secrets.clone().unwrap_or_default());
+    let mut resolved = 0;
+    for (path, value) in secret_fields(config) {
+        if let Some(secret) = resolver.resolve(value).await.with_context(|| format!("resolving {}", path))? {
+            *value = secret;
+            resolved += 1;
+        }
+    }
+    if resolved > 0 {
+        info!("Resolved {} secret reference(s)", resolved);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    /// A local Vault answering every KV v2 read with `data`; returns its address
+    async fn stub_vault(data: Value) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = format!("http://{}", listener.local_addr().unwrap());
+        let body = json!({ "data": { "data": data } }).to_string();
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let mut buf = [0u8; 4096];
+                let _ = stream.read(&mut buf).await;
+                let reply = format!(
+                    "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}",
+                    body.len(),
+                    body
+                );
+                let _ = stream.write_all(reply.as_bytes()).await;
+            }
+        });
+        addr
+    }
+
+    #[test]
+    fn test_parse_references() {
+        assert_eq!(SecretRef::parse("plain-value").unwrap(), None);
+        assert_eq!(
+            SecretRef::parse("vault:kv/origins#graph_key").unwrap(),
+            Some(SecretRef::Vault { mount: "kv".into(), path: "origins".into(), key: "graph_key".into() })
+        );
+        assert_eq!(
+            SecretRef::parse("vault:secret/team/origins#smtp").unwrap(),
+            Some(SecretRef::Vault { mount: "secret".into(), path: "team/origins".into(), key: "smtp".into() })
+        );
+        assert_eq!(
+            SecretRef::parse("aws-sm:prod/origins#private_key").unwrap(),
+            Some(SecretRef::AwsSecretsManager { secret_id: "prod/origins".into(), key: Some("private_key".into()) })
+        );
+        assert_eq!(
+            SecretRef::parse("aws-sm:graph-key").unwrap(),
+            Some(SecretRef::AwsSecretsManager { secret_id: "graph-key".into(), key: None })
+        );
+        assert_eq!(
+            SecretRef::parse("keyring:graph-key").unwrap(),
+            Some(SecretRef::Keyring { name: "graph-key".into() })
+        );
+        assert!(SecretRef::parse("vault:kv/origins").is_err());
+        assert!(SecretRef::parse("vault:origins#key").is_err());
+        assert!(SecretRef::parse("aws-sm:#key").is_err());
+        assert!(SecretRef::parse("keyring:").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_plain_values_are_left_alone() {
+        let mut config = Config::default();
+        config.api.as_mut().unwrap().thegraph_api_key = Some("literal-key".to_string());
+        resolve_config(&mut config).await.unwrap();
+        assert_eq!(config.api.unwrap().thegraph_api_key.as_deref(), Some("literal-key"));
+    }
+
+    #[tokio::test]
+    async fn test_coingecko_key_is_resolved() {
+        let token_env = format!("ORIGINS_TEST_VAULT_TOKEN_{}", std::process::id());
+        std::env::set_var(&token_env, "test-token");
+        let mut config = Config {
+            secrets: Some(SecretsConfig {
+                vault_addr: Some(stub_vault(json!({ "coingecko": "cg-key" })).await),
+                vault_token_env: token_env,
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        config.api.as_mut().unwrap().coingecko_api_key = Some("vault:kv/origins#coingecko".to_string());
+        resolve_config(&mut config).await.unwrap();
+        assert_eq!(config.api.unwrap().coingecko_api_key.as_deref(), Some("cg-key"));
+    }
+}
@@ -0,0 +1,596 @@
+use anyhow::{Context, Result};
+use axum::extract::{DefaultBodyLimit, Path, Query, State};
+use axum::http::StatusCode;
+use axum::response::sse::{self, KeepAlive, Sse};
+use axum::response::{Html, IntoResponse};
+use axum::routing::{delete, get, post, put};
+use axum::{middleware, Extension, Json, Router};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+use std::sync::Arc;
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::time::Duration;
+use tokio::sync::Notify;
+use tokio_stream::wrappers::BroadcastStream;
+use tokio_stream::{Stream, StreamExt};
+use tracing::info;
+
+use crate::alerts::PositionMutes;
+use crate::api_keys::{self, ApiKey, ApiKeyStore};
+use crate::audit::AuditRecord;
+use crate::cache::Cache;
+use crate::config::{Config, ServerConfig};
+use crate::events::{Event, EventBus};
+use crate::executor::{PaperWallet, RebalanceHandle, RebalanceQuery, RebalanceWorkflow, WorkflowStatus};
+use crate::graphql::{self, ApiSchema};
+use crate::health::HealthState;
+use crate::jwt::JwtVerifier;
+use crate::openapi;
+use crate::quotes::LatestQuotes;
+use crate::rate_limit::{self, RateLimiter};
+use crate::rest;
+use crate::state::RecommenderState;
+use crate::uniswap::UniswapClient;
+
+/// Timeout applied to each upstream check performed by /readyz
+const PROBE_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// Shared state handed to every HTTP handler
+#[derive(Clone)]
+pub struct AppState {
+    pub config: Arc<Config>,
+    pub health: HealthState,
+    pub recommender: RecommenderState,
+    pub uniswap: UniswapClient,
+    pub schema: ApiSchema,
+    pub api_keys: ApiKeyStore,
+    pub jwt: Option<JwtVerifier>,
+    pub events: EventBus,
+    pub cycle_trigger: Arc<Notify>,
+    pub client_limiter: RateLimiter,
+    pub mutes: PositionMutes,
+    /// Set when the rebalance executor runs
+    pub rebalancer: Option<RebalanceHandle>,
+    /// Latest quote per pool from the quote pipeline
+    pub quotes: LatestQuotes,
+}
+
+impl AppState {
+    pub fn new(
+        config: Arc<Config>,
+        health: HealthState,
+        recommender: RecommenderState,
+        events: EventBus,
+        cache: Cache,
+        cycle_trigger: Arc<Notify>,
+        mutes: PositionMutes,
+    ) -> Result<Self> {
+        let uniswap = UniswapClient::from_config(&config).with_cache(cache);
+        let schema = graphql::build_schema(recommender.clone(), uniswap.clone());
+        let api_keys = match &config.server {
+            Some(server) => ApiKeyStore::from_config(&server.api_keys, server.require_api_key)?,
+            None => ApiKeyStore::default(),
+        };
+        let jwt = config.server.as_ref().and_then(|s| s.jwt.as_ref()).map(JwtVerifier::new);
+        Ok(Self {
+            config,
+            health,
+            recommender,
+            uniswap,
+            schema,
+            api_keys,
+            jwt,
+            events,
+            cycle_trigger,
+            client_limiter: RateLimiter::new(),
+            mutes,
+            rebalancer: None,
+            quotes: LatestQuotes::default(),
+        })
+    }
+
+    /// Accept rebalance requests via the admin API
+    pub fn with_rebalancer(mut self, handle: RebalanceHandle) -> Self {
+        self.rebalancer = Some(handle);
+        self
+    }
+
+    /// Serve the quote pipeline's latest quotes on /quotes
+    pub fn with_quotes(mut self, quotes: LatestQuotes) -> Self {
+        self.quotes = quotes;
+        self
+    }
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub(crate) struct Liveness {
+    status: &'static str,
+    uptime_secs: i64,
+    last_successful_cycle: Option<DateTime<Utc>>,
+    consecutive_cycle_failures: u32,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub(crate) struct CheckResult {
+    ok: bool,
+    detail: String,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub(crate) struct ReadinessReport {
+    ready: bool,
+    rpc: CheckResult,
+    subgraph: CheckResult,
+    models: CheckResult,
+    last_cycle: CheckResult,
+}
+
+pub fn router(state: AppState) -> Router {
+    let read_routes = Router::new()
+        .route("/graphql", post(graphql_handler))
+        .route("/events", get(events_sse))
+        .route("/recommendations", get(rest::recommendations))
+        .route("/history", get(rest::history))
+        .route("/pnl", get(rest::pnl))
+        .route("/reports/tax", get(rest::tax_report))
+        .route("/metrics", get(rest::metrics))
+        .route("/quotes", get(rest::quotes))
+        .route_layer(middleware::from_fn_with_state(state.clone(), api_keys::require_read));
+
+    let admin_routes = Router::new()
+        .route("/admin/api-keys", get(api_keys::list_keys).post(api_keys::create_key))
+        .route("/admin/api-keys/:id", delete(api_keys::revoke_key))
+        .route("/admin/trigger-cycle", post(trigger_cycle))
+        .route("/admin/mutes", get(list_mutes))
+        .route("/admin/mutes/:position_id", put(mute_position).delete(unmute_position))
+        .route("/admin/rebalances", get(list_rebalances))
+        .route("/admin/rebalances/:position_id", post(request_rebalance))
+        .route("/admin/paper", get(paper_wallet))
+        .route("/admin/audit", get(list_audit))
+        .route_layer(middleware::from_fn_with_state(state.clone(), api_keys::require_admin));
+
+    let max_body_bytes = state
+        .config
+        .server
+        .as_ref()
+        .map(|s| s.max_body_bytes)
+        .unwrap_or(64 * 1024);
+
+    // Probes stay outside the per-client limit so kubelet checks are never throttled
+    let api_routes = Router::new()
+        .route("/graphql", get(graphiql))
+        .merge(read_routes)
+        .merge(admin_routes)
+        .layer(middleware::from_fn_with_state(state.clone(), rate_limit::limit_clients))
+        .layer(DefaultBodyLimit::max(max_body_bytes));
+
+    Router::new()
+        .route("/healthz", get(healthz))
+        .route("/readyz", get(readyz))
+        .route("/openapi.json", get(openapi::openapi_json))
+        .route("/docs", get(openapi::swagger_ui))
+        .merge(api_routes)
+        .with_state(state)
+}
+
+/// Bind and serve the HTTP API until the process exits
+pub async fn serve(server_cfg: &ServerConfig, state: AppState) -> Result<()> {
+    let listener = tokio::net::TcpListener::bind(&server_cfg.bind_address)
+        .await
+        .with_context(|| format!("binding HTTP server to {}", server_cfg.bind_address))?;
+    info!(target: "server", address = %server_cfg.bind_address, "HTTP server listening");
+    axum::serve(listener, router(state).into_make_service_with_connect_info::<SocketAddr>()).await?;
+    Ok(())
+}
+
+/// Start a recommendation cycle now instead of waiting for the schedule
+#[utoipa::path(
+    post,
+    path = "/admin/trigger-cycle",
+    tag = "admin",
+    responses((status = 202, description = "Cycle scheduled")),
+    security(("api_key" = []), ("bearer" = []))
+)]
+pub(crate) async fn trigger_cycle(State(state): State<AppState>) -> StatusCode {
+    state.cycle_trigger.notify_one();
+    info!(target: "server", "recommendation cycle triggered via API");
+    StatusCode::ACCEPTED
+}
+
+/// Positions whose alerts are muted
+#[utoipa::path(
+    get,
+    path = "/admin/mutes",
+    tag = "admin",
+    responses((status = 200, description = "Muted position ids", body = Vec<String>)),
+    security(("api_key" = []), ("bearer" = []))
+)]
+pub(crate) async fn list_mutes(State(state): State<AppState>) -> Json<Vec<String>> {
+    Json(state.mutes.list())
+}
+
+/// Stop publishing alerts for a position
+#[utoipa::path(
+    put,
+    path = "/admin/mutes/{position_id}",
+    tag = "admin",
+    params(("position_id" = String, Path, description = "Position id")),
+    responses((status = 204, description = "Position muted")),
+    security(("api_key" = []), ("bearer" = []))
+)]
+pub(crate) async fn mute_position(State(state): State<AppState>, Path(position_id): Path<String>) -> StatusCode {
+    if state.mutes.mute(&position_id) {
+        info!(target: "server", position = %position_id, "position alerts muted");
+    }
+    StatusCode::NO_CONTENT
+}
+
+/// Resume alerts for a position
+#[utoipa::path(
+    delete,
+    path = "/admin/mutes/{position_id}",
+    tag = "admin",
+    params(("position_id" = String, Path, description = "Position id")),
+    responses(
+        (status = 204, description = "Position unmuted"),
+        (status = 404, description = "Position was not muted")
+    ),
+    security(("api_key" = []), ("bearer" = []))
+)]
+pub(crate) async fn unmute_position(State(state): State<AppState>, Path(position_id): Path<String>) -> StatusCode {
+    if state.mutes.unmute(&position_id) {
+        info!(target: "server", position = %position_id, "position alerts unmuted");
+        StatusCode::NO_CONTENT
+    } else {
+        StatusCode::NOT_FOUND
+    }
+}
+
+fn default_rebalance_limit() -> usize {
+    50
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub(crate) struct RebalanceParams {
+    position_id: Option<String>,
+    /// running, completed or failed
+    #[param(value_type = Option<String>)]
+    status: Option<WorkflowStatus>,
+    /// At most 500
+    #[serde(default = "default_rebalance_limit")]
+    limit: usize,
+}
+
+/// Rebalance workflows with their per-step audit trail, newest first
+#[utoipa::path(
+    get,
+    path = "/admin/rebalances",
+    tag = "admin",
+    params(RebalanceParams),
+    responses((status = 200, description = "Rebalance workflows", body = Vec<RebalanceWorkflow>)),
+    security(("api_key" = []), ("bearer" = []))
+)]
+pub(crate) async fn list_rebalances(
+    State(state): State<AppState>,
+    Query(params): Query<RebalanceParams>,
+) -> Result<Json<Vec<RebalanceWorkflow>>, StatusCode> {
+    let query = RebalanceQuery {
+        position_id: params.position_id,
+        status: params.status,
+        limit: params.limit.min(500),
+    };
+    state
+        .recommender
+        .storage()
+        .rebalances(&query)
+        .await
+        .map(Json)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)
+}
+
+/// Rebalance a position into a new range around the current price, or retry its failed rebalance
+#[utoipa::path(
+    post,
+    path = "/admin/rebalances/{position_id}",
+    tag = "admin",
+    params(("position_id" = String, Path, description = "Position id")),
+    responses(
+        (status = 202, description = "Rebalance queued"),
+        (status = 429, description = "Too many queued rebalances"),
+        (status = 503, description = "Rebalance executor not enabled")
+    ),
+    security(("api_key" = []), ("bearer" = []))
+)]
+pub(crate) async fn request_rebalance(State(state): State<AppState>, Path(position_id): Path<String>) -> StatusCode {
+    let Some(rebalancer) = &state.rebalancer else {
+        return StatusCode::SERVICE_UNAVAILABLE;
+    };
+    if rebalancer.request(&position_id) {
+        info!(target: "server", position = %position_id, "rebalance requested via API");
+        StatusCode::ACCEPTED
+    } else {
+        StatusCode::TOO_MANY_REQUESTS
+    }
+}
+
+/// The paper-trading wallet: cash, holdings with IL and fees, gas spent and recent trades
+#[utoipa::path(
+    get,
+    path = "/admin/paper",
+    tag = "admin",
+    responses(
+        (status = 200, description = "Paper wallet", body = PaperWallet),
+        (status = 404, description = "No trades or marks yet"),
+        (status = 503, description = "Paper trading not enabled")
+    ),
+    security(("api_key" = []), ("bearer" = []))
+)]
+pub(crate) async fn paper_wallet(State(state): State<AppState>) -> Result<Json<PaperWallet>, StatusCode> {
+    let Some(paper) = state.config.execution.as_ref().and_then(|e| e.paper.as_ref()) else {
+        return Err(StatusCode::SERVICE_UNAVAILABLE);
+    };
+    match state.recommender.storage().paper_wallet(&paper.wallet).await {
+        Ok(Some(wallet)) => Ok(Json(wallet)),
+        Ok(None) => Err(StatusCode::NOT_FOUND),
+        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
+    }
+}
+
+fn default_audit_limit() -> usize {
+    100
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub(crate) struct AuditParams {
+    /// Only records after this sequence number
+    #[serde(default)]
+    after: u64,
+    /// At most 1000
+    #[serde(default = "default_audit_limit")]
+    limit: usize,
+}
+
+/// Audit log records in chain order, for export and independent verification
+#[utoipa::path(
+    get,
+    path = "/admin/audit",
+    tag = "admin",
+    params(AuditParams),
+    responses(
+        (status = 200, description = "Audit records, oldest first", body = Vec<AuditRecord>),
+        (status = 503, description = "Audit log not enabled")
+    ),
+    security(("api_key" = []), ("bearer" = []))
+)]
+pub(crate) async fn list_audit(
+    State(state): State<AppState>,
+    Query(params): Query<AuditParams>,
+) -> Result<Json<Vec<AuditRecord>>, StatusCode> {
+    if state.config.audit.is_none() {
+        return Err(StatusCode::SERVICE_UNAVAILABLE);
+    }
+    state
+        .recommender
+        .storage()
+        .audit_records(params.after, params.limit.min(1_000))
+        .await
+        .map(Json)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)
+}
+
+/// Execute a GraphQL request against the recommender state (schema via introspection)
+#[utoipa::path(
+    post,
+    path = "/graphql",
+    tag = "query",
+    request_body(content = serde_json::Value, description = "GraphQL request: query, variables, operationName"),
+    responses((status = 200, description = "GraphQL response", body = serde_json::Value)),
+    security((), ("api_key" = []), ("bearer" = []))
+)]
+pub(crate) async fn graphql_handler(
+    State(state): State<AppState>,
+    caller: Option<Extension<ApiKey>>,
+    Json(request): Json<async_graphql::Request>,
+) -> Json<async_graphql::Response> {
+    // Resolvers restrict results to the caller's wallets
+    let request = match caller {
+        Some(Extension(key)) => request.data(key),
+        None => request,
+    };
+    Json(state.schema.execute(request).await)
+}
+
+/// Server-sent events feed of recommendation events, filtered to the caller's wallets
+#[utoipa::path(
+    get,
+    path = "/events",
+    tag = "events",
+    responses((status = 200, description = "text/event-stream; each event's data is one JSON event", body = Event, content_type = "text/event-stream")),
+    security((), ("api_key" = []), ("bearer" = []))
+)]
+pub(crate) async fn events_sse(
+    State(state): State<AppState>,
+    caller: Option<Extension<ApiKey>>,
+) -> Sse<impl Stream<Item = Result<sse::Event, Infallible>>> {
+    let caller = caller.map(|Extension(key)| key);
+    let stream = BroadcastStream::new(state.events.subscribe()).filter_map(move |msg| {
+        // Lagged receivers skip the missed events rather than closing the stream
+        let event = msg.ok()?;
+        if let (Some(key), Some(wallet)) = (&caller, event.wallet()) {
+            if !key.allows_wallet(wallet) {
+                return None;
+            }
+        }
+        let data = serde_json::to_string(&event).ok()?;
+        Some(Ok(sse::Event::default().event(event.kind()).data(data)))
+    });
+    Sse::new(stream).keep_alive(KeepAlive::default())
+}
+
+/// Interactive GraphiQL explorer
+async fn graphiql() -> impl IntoResponse {
+    Html(async_graphql::http::GraphiQLSource::build().endpoint("/graphql").finish())
+}
+
+/// Liveness: the process is up and serving requests
+#[utoipa::path(
+    get,
+    path = "/healthz",
+    tag = "probes",
+    responses((status = 200, description = "Process is alive", body = Liveness))
+)]
+pub(crate) async fn healthz(State(state): State<AppState>) -> Json<Liveness> {
+    let health = state.health.snapshot().await;
+    let uptime_secs = health
+        .started_at
+        .map(|t| (Utc::now() - t).num_seconds())
+        .unwrap_or(0);
+    Json(Liveness {
+        status: "ok",
+        uptime_secs,
+        last_successful_cycle: health.last_successful_cycle,
+        consecutive_cycle_failures: health.consecutive_cycle_failures,
+    })
+}
+
+/// Readiness: upstreams are reachable and the recommendation loop is making progress
+#[utoipa::path(
+    get,
+    path = "/readyz",
+    tag = "probes",
+    responses(
+        (status = 200, description = "Ready", body = ReadinessReport),
+        (status = 503, description = "Not ready", body = ReadinessReport)
+    )
+)]
+pub(crate) async fn readyz(State(state): State<AppState>) -> impl IntoResponse {
+    let server_cfg = state.config.server.clone();
+    let max_subgraph_lag = server_cfg.as_ref().map(|s| s.max_subgraph_lag_secs).unwrap_or(600);
+    let max_missed_cycles = server_cfg.as_ref().map(|s| s.max_missed_cycles).unwrap_or(3);
+
+    let rpc = match tokio::time::timeout(PROBE_TIMEOUT, state.uniswap.block_number(&state.config.active_chain().rpc_url)).await {
+        Ok(Ok(block)) => CheckResult { ok: true, detail: format!("head block {}", block) },
+        Ok(Err(e)) => CheckResult { ok: false, detail: format!("rpc error: {}", e) },
+        Err(_) => CheckResult { ok: false, detail: "rpc probe timed out".to_string() },
+    };
+
+    let subgraph = match tokio::time::timeout(PROBE_TIMEOUT, state.uniswap.subgraph_meta()).await {
+        Ok(Ok(meta)) => match meta.timestamp {
+            Some(ts) => {
+                let lag = (Utc::now().timestamp() as u64).saturating_sub(ts);
+                CheckResult {
+                    ok: lag <= max_subgraph_lag,
+                    detail: format!("indexed block {} is {}s behind", meta.number, lag),
+                }
+            }
+            None => CheckResult { ok: true, detail: format!("indexed block {} (no timestamp)", meta.number) },
+        },
+        Ok(Err(e)) => CheckResult { ok: false, detail: format!("subgraph error: {}", e) },
+        Err(_) => CheckResult { ok: false, detail: "subgraph probe timed out".to_string() },
+    };
+
+    let health = state.health.snapshot().await;
+    // Models are optional: report their status without gating readiness on it
+    let models = CheckResult {
+        ok: true,
+        detail: if health.models_loaded { "loaded".to_string() } else { "not loaded, using heuristic scoring".to_string() },
+    };
+
+    let max_cycle_age = state.config.get_recommendation_interval() * max_missed_cycles;
+    let last_cycle = match health.last_successful_cycle {
+        Some(t) => {
+            let age = (Utc::now() - t).num_seconds().max(0) as u64;
+            CheckResult { ok: age <= max_cycle_age, detail: format!("last successful cycle {}s ago", age) }
+        }
+        None => CheckResult { ok: false, detail: "no successful cycle yet".to_string() },
+    };
+
+    let ready = rpc.ok && subgraph.ok && last_cycle.ok;
+    let status = if ready { StatusCode::OK } else { StatusCode::SERVICE_UNAVAILABLE };
+    (status, Json(ReadinessReport { ready, rpc, subgraph, models, last_cycle }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Serve the API for `config` on a local port; returns its base URL
+    async fn serve_local(config: Config) -> String {
+        let state = AppState::new(
+            Arc::new(config),
+            HealthState::new(),
+            RecommenderState::new(),
+            EventBus::new(),
+            Cache::default(),
+            Arc::new(Notify::new()),
+            PositionMutes::default(),
+        )
+        .unwrap();
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            let _ = axum::serve(listener, router(state).into_make_service_with_connect_info::<SocketAddr>()).await;
+        });
+        url
+    }
+
+    #[tokio::test]
+    async fn test_admin_routes_closed_without_auth() {
+        let url = serve_local(Config::default()).await;
+        let client = reqwest::Client::new();
+        let trigger = client.post(format!("{}/admin/trigger-cycle", url)).send().await.unwrap();
+        assert_eq!(trigger.status(), reqwest::StatusCode::SERVICE_UNAVAILABLE);
+        let keys = client.get(format!("{}/admin/api-keys", url)).send().await.unwrap();
+        assert_eq!(keys.status(), reqwest::StatusCode::SERVICE_UNAVAILABLE);
+        // Reads stay open
+        let quotes = client.get(format!("{}/quotes", url)).send().await.unwrap();
+        assert_eq!(quotes.status(), reqwest::StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_jwt_is_the_only_way_in_when_enabled() {
+        use crate::config::{ApiKeyConfig, JwtConfig, Scope};
+        use jsonwebtoken::{encode, EncodingKey, Header};
+        use sha2::Digest;
+
+        // A configured key, but API keys aren't required, so only JWT is enabled
+        let key = "ork_test";
+        let server = ServerConfig {
+            enabled: true,
+            bind_address: "127.0.0.1:0".to_string(),
+            max_subgraph_lag_secs: 600,
+            max_missed_cycles: 3,
+            require_api_key: false,
+            api_keys: vec![ApiKeyConfig {
+                name: "desk".to_string(),
+                key_sha256: hex::encode(sha2::Sha256::digest(key.as_bytes())),
+                scopes: vec![Scope::Admin],
+                rate_limit_per_minute: 0,
+                wallets: Vec::new(),
+            }],
+            jwt: Some(JwtConfig { secret: "secret".to_string(), issuer: None, audience: None, rate_limit_per_minute: 0 }),
+            client_rate_limit_per_minute: 0,
+            trust_forwarded_for: false,
+            max_body_bytes: 64 * 1024,
+        };
+        let url = serve_local(Config { server: Some(server), ..Config::default() }).await;
+        let token = |role: Scope| {
+            let claims = crate::jwt::Claims { sub: "ops".to_string(), exp: (Utc::now().timestamp() + 300) as u64, role, wallets: Vec::new() };
+            encode(&Header::default(), &claims, &EncodingKey::from_secret(b"secret")).unwrap()
+        };
+        let client = reqwest::Client::new();
+        let trigger = || client.post(format!("{}/admin/trigger-cycle", url));
+
+        assert_eq!(trigger().send().await.unwrap().status(), reqwest::StatusCode::UNAUTHORIZED);
+        let with_key = trigger().header(api_keys::API_KEY_HEADER, key).send().await.unwrap();
+        assert_eq!(with_key.status(), reqwest::StatusCode::UNAUTHORIZED);
+        let reader = trigger().bearer_auth(token(Scope::Read)).send().await.unwrap();
+        assert_eq!(reader.status(), reqwest::StatusCode::FORBIDDEN);
+        let admin = trigger().bearer_auth(token(Scope::Admin)).send().await.unwrap();
+        assert_eq!(admin.status(), reqwest::StatusCode::ACCEPTED);
+    }
+}
@@ -0,0 +1,264 @@
+use anyhow::{anyhow, bail, Context, Result};
+use async_trait::async_trait;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use chrono::{DateTime, Utc};
+use ethereum_types::{Address, H256};
+use k256::ecdsa::{RecoveryId, Signature as EcdsaSignature, VerifyingKey};
+use reqwest::Client;
+use serde_json::{json, Value};
+use sha3::{Digest, Keccak256};
+use std::time::Duration;
+use tokio::sync::Mutex;
+use tracing::info;
+
+use super::{Signature, Signer};
+use crate::aws::{sha256_hex, AwsCredentials, SignedRequest};
+use crate::config::KmsConfig;
+
+const GCP_KMS_API: &str = "https://cloudkms.googleapis.com/v1";
+const GCP_METADATA_TOKEN: &str = "http://metadata.google.internal/computeMetadata/v1/instance/service-accounts/default/token";
+/// Refresh metadata-server tokens this long before they expire
+const TOKEN_REFRESH_MARGIN_SECS: i64 = 60;
+
+/// Signs with a key that never leaves AWS KMS or GCP Cloud KMS. The KMS signs the keccak256
+/// digest directly; the signature is normalized to low-s and its recovery id found by
+/// matching the key's public key.
+pub struct KmsSigner {
+    api: KmsApi,
+    public_key: VerifyingKey,
+    address: Address,
+}
+
+impl KmsSigner {
+    /// Fetch the key's public key and derive the account address
+    pub async fn connect(config: &KmsConfig) -> Result<Self> {
+        let api = KmsApi {
+            http: Client::builder()
+                .timeout(Duration::from_secs(15))
+                .build()
+                .expect("failed to build reqwest client"),
+            config: config.clone(),
+            token: Mutex::new(None),
+        };
+        let public_key = public_key_from_spki(&api.public_key_der().await?)?;
+        let signer = Self {
+            address: address_of(&public_key),
+            api,
+            public_key,
+        };
+        info!(target: "signer", provider = signer.name(), address = ?signer.address, "using KMS signing key");
+        Ok(signer)
+    }
+}
+
+struct KmsApi {
+    http: Client,
+    config: KmsConfig,
+    /// Cached GCP access token and its expiry
+    token: Mutex<Option<(String, DateTime<Utc>)>>,
+}
+
+impl KmsApi {
+    /// DER SubjectPublicKeyInfo of the signing key
+    async fn public_key_der(&self) -> Result<Vec<u8>> {
+        match &self.config {
+            KmsConfig::Aws { key_id, .. } => {
+                let resp = self.aws("GetPublicKey", json!({ "KeyId": key_id })).await?;
+                Ok(BASE64.decode(resp["PublicKey"].as_str().ok_or_else(|| anyhow!("GetPublicKey returned no key"))?)?)
+            }
+            KmsConfig::Gcp { key_name, .. } => {
+                let resp = self.gcp_get(&format!("{}/{}/publicKey", GCP_KMS_API, key_name)).await?;
+                let pem = resp["pem"].as_str().ok_or_else(|| anyhow!("publicKey returned no PEM"))?;
+                let body: String = pem.lines().filter(|l| !l.starts_with("-----")).collect();
+                Ok(BASE64.decode(body)?)
+            }
+        }
+    }
+
+    async fn sign_digest(&self, digest: &[u8; 32]) -> Result<Vec<u8>> {
+        match &self.config {
+            KmsConfig::Aws { key_id, .. } => {
+                let resp = self
+                    .aws(
+                        "Sign",
+                        json!({
+                            "KeyId": key_id,
+                            "Message": BASE64.encode(digest),
+                            "MessageType": "DIGEST",
+                            "SigningAlgorithm": "ECDSA_SHA_256",
+                        }),
+                    )
+                    .await?;
+                Ok(BASE64.decode(resp["Signature"].as_str().ok_or_else(|| anyhow!("Sign returned no signature"))?)?)
+            }
+            KmsConfig::Gcp { key_name, .. } => {
+                let token = self.gcp_token().await?;
+                let resp: Value = self
+                    .http
+                    .post(format!("{}/{}:asymmetricSign", GCP_KMS_API, key_name))
+                    .bearer_auth(token)
+                    .json(&json!({ "digest": { "sha256": BASE64.encode(digest) } }))
+                    .send()
+                    .await?
+                    .error_for_status()
+                    .context("Cloud KMS asymmetricSign")?
+                    .json()
+                    .await?;
+                Ok(BASE64.decode(resp["signature"].as_str().ok_or_else(|| anyhow!("asymmetricSign returned no signature"))?)?)
+            }
+        }
+    }
+
+    /// AWS KMS JSON API call signed with SigV4
+    async fn aws(&self, action: &str, body: Value) -> Result<Value> {
+        let KmsConfig::Aws { region, .. } = &self.config else {
+            bail!("not an AWS KMS key");
+        };
+        let credentials = AwsCredentials::from_env()?;
+        let host = format!("kms.{}.amazonaws.com", region);
+        let body = body.to_string();
+        let now = Utc::now();
+        let amz_date = now.format("%Y%m%dT%H%M%SZ").to_string();
+        let target = format!("TrentService.{}", action);
+
+        let mut headers = vec![
+            ("content-type", "application/x-amz-json-1.1".to_string()),
+            ("host", host.clone()),
+            ("x-amz-date", amz_date.clone()),
+        ];
+        if let Some(token) = &credentials.session_token {
+            headers.push(("x-amz-security-token", token.clone()));
+        }
+        headers.push(("x-amz-target", target.clone()));
+        let payload_hash = sha256_hex(body.as_bytes());
+        let request = SignedRequest { method: "POST", path: "/", headers: &headers, payload_hash: &payload_hash };
+        let authorization = credentials.authorization(region, "kms", &now, &request);
+
+        let mut request = self.http.post(format!("https://{}/", host));
+        for (name, value) in headers.iter().filter(|(name, _)| *name != "host") {
+            request = request.header(*name, value);
+        }
+        let resp = request.header("authorization", authorization).body(body).send().await?;
+        let status = resp.status();
+        let json: Value = resp.json().await.unwrap_or(Value::Null);
+        if !status.is_success() {
+            bail!("AWS KMS {} failed ({}): {}", action, status, json);
+        }
+        Ok(json)
+    }
+
+    async fn gcp_get(&self, url: &str) -> Result<Value> {
+        let token = self.gcp_token().await?;
+        Ok(self.http.get(url).bearer_auth(token).send().await?.error_for_status()?.json().await?)
+    }
+
+    async fn gcp_token(&self) -> Result<String> {
+        let KmsConfig::Gcp { access_token_env, .. } = &self.config else {
+            bail!("not a GCP KMS key");
+        };
+        if let Some(var) = access_token_env {
+            return std::env::var(var).with_context(|| format!("{} not set", var));
+        }
+        let mut cached = self.token.lock().await;
+        if let Some((token, expires)) = cached.as_ref() {
+            if *expires > Utc::now() {
+                return Ok(token.clone());
+            }
+        }
+        let resp: Value = self
+            .http
+            .get(GCP_METADATA_TOKEN)
+            .header("Metadata-Flavor", "Google")
+            .send()
+            .await
+            .context("fetching an access token from the GCP metadata server")?
+            .error_for_status()?
+            .json()
+            .await?;
+        let token = resp["access_token"].as_str().ok_or_else(|| anyhow!("metadata server returned no token"))?.to_string();
+        let expires_in = resp["expires_in"].as_i64().unwrap_or(300);
+        *cached = Some((token.clone(), Utc::now() + chrono::Duration::seconds(expires_in - TOKEN_REFRESH_MARGIN_SECS)));
+        Ok(token)
+    }
+}
+
+#[async_trait]
+impl Signer for KmsSigner {
+    fn name(&self) -> &'static str {
+        match self.api.config {
+            KmsConfig::Aws { .. } => "aws-kms",
+            KmsConfig::Gcp { .. } => "gcp-kms",
+        }
+    }
+
+    fn address(&self) -> Address {
+        self.address
+    }
+
+    async fn sign_transaction(&self, payload: &[u8]) -> Result<Signature> {
+        let digest: [u8; 32] = Keccak256::digest(payload).into();
+        let der = self.api.sign_digest(&digest).await?;
+        recoverable_signature(&der, &digest, &self.public_key)
+    }
+}
+
+/// Low-s signature with the recovery id that yields `public_key`
+fn recoverable_signature(der: &[u8], digest: &[u8; 32], public_key: &VerifyingKey) -> Result<Signature> {
+    let signature = EcdsaSignature::from_der(der).context("KMS returned an invalid DER signature")?;
+    let signature = signature.normalize_s().unwrap_or(signature);
+    for id in 0..2 {
+        let recovery_id = RecoveryId::from_byte(id).expect("recovery id 0 or 1");
+        if VerifyingKey::recover_from_prehash(digest, &signature, recovery_id).is_ok_and(|key| &key == public_key) {
+            let (r, s) = signature.split_bytes();
+            return Ok(Signature {
+                r: H256::from_slice(&r),
+                s: H256::from_slice(&s),
+                y_parity: id,
+            });
+        }
+    }
+    bail!("KMS signature does not recover to the key's public key")
+}
+
+/// Uncompressed secp256k1 point at the end of a DER SubjectPublicKeyInfo
+fn public_key_from_spki(spki: &[u8]) -> Result<VerifyingKey> {
+    if spki.len() < 65 || spki[spki.len() - 65] != 0x04 {
+        bail!("KMS public key is not an uncompressed secp256k1 key");
+    }
+    VerifyingKey::from_sec1_bytes(&spki[spki.len() - 65..]).context("KMS key is not on secp256k1")
+}
+
+fn address_of(key: &VerifyingKey) -> Address {
+    let point = key.to_encoded_point(false);
+    Address::from_slice(&Keccak256::digest(&point.as_bytes()[1..])[12..])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use k256::ecdsa::SigningKey;
+
+    #[test]
+    fn test_der_signature_recovery() {
+        let key = SigningKey::from_slice(&hex::decode("7a28b5ba57c53603b0b07b56bba752f7784bf506fa95edc395f5cf6c7514fe9d").unwrap()).unwrap();
+        let public_key = *key.verifying_key();
+        let spki = [
+            hex::decode("3056301006072a8648ce3d020106052b8104000a034200").unwrap(),
+            public_key.to_encoded_point(false).as_bytes().to_vec(),
+        ]
+        .concat();
+        assert_eq!(public_key_from_spki(&spki).unwrap(), public_key);
+        assert_eq!(format!("{:?}", address_of(&public_key)), "0x008aeeda4d805471df9b2a5b0f38a0c3bcba786b");
+
+        let digest: [u8; 32] = Keccak256::digest(b"payload").into();
+        let (signature, _): (EcdsaSignature, RecoveryId) = key.sign_prehash_recoverable(&digest).unwrap();
+        // KMS may return either s; a high-s signature must be normalized
+        let (r, s) = signature.split_scalars();
+        let high = EcdsaSignature::from_scalars(r, -*s).unwrap();
+        for der in [signature.to_der(), high.to_der()] {
+            let recovered = recoverable_signature(der.as_bytes(), &digest, &public_key).unwrap();
+            assert_eq!(recovered.s, H256::from_slice(&signature.split_bytes().1));
+        }
+    }
+}
@@ -0,0 +1,277 @@
+//! Ledger Ethereum app over Linux hidraw: transactions are shown on the device and only
+//! signed once approved there.
+
+use anyhow::{anyhow, bail, Context, Result};
+use async_trait::async_trait;
+use ethereum_types::{Address, H256};
+use std::fs::{self, File, OpenOptions};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::time::timeout;
+use tracing::info;
+
+use super::{Signature, Signer};
+use crate::config::LedgerConfig;
+
+const LEDGER_VENDOR_ID: &str = "00002C97";
+/// Report descriptor prefix of the generic (non-FIDO) interface: usage page 0xFFA0
+const GENERIC_USAGE_PAGE: [u8; 3] = [0x06, 0xa0, 0xff];
+const HID_PACKET_SIZE: usize = 64;
+const HID_CHANNEL: u16 = 0x0101;
+const HID_TAG_APDU: u8 = 0x05;
+
+const CLA_ETH: u8 = 0xe0;
+const INS_GET_ADDRESS: u8 = 0x02;
+const INS_SIGN_TRANSACTION: u8 = 0x04;
+const INS_SIGN_EIP712_HASHED: u8 = 0x0c;
+const P1_FIRST_CHUNK: u8 = 0x00;
+const P1_MORE_CHUNKS: u8 = 0x80;
+const MAX_APDU_DATA: usize = 255;
+
+/// Signs on a Ledger running the Ethereum app
+pub struct LedgerSigner {
+    device_path: PathBuf,
+    derivation_path: Vec<u32>,
+    address: Address,
+    approval_timeout: Duration,
+}
+
+impl LedgerSigner {
+    /// Locate the device and read the account address (blocking)
+    pub fn connect(config: &LedgerConfig) -> Result<Self> {
+        let device_path = match &config.device_path {
+            Some(path) => PathBuf::from(path),
+            None => find_device()?,
+        };
+        let derivation_path = parse_derivation_path(&config.derivation_path)?;
+        let response = exchange(&device_path, &apdu(INS_GET_ADDRESS, 0, &encode_path(&derivation_path)))?;
+        // pubkey length, pubkey, address length, ASCII hex address
+        let pubkey_len = *response.first().ok_or_else(|| anyhow!("empty address response"))? as usize;
+        let address_len = *response.get(1 + pubkey_len).ok_or_else(|| anyhow!("truncated address response"))? as usize;
+        let ascii = response
+            .get(2 + pubkey_len..2 + pubkey_len + address_len)
+            .ok_or_else(|| anyhow!("truncated address response"))?;
+        let address_hex = std::str::from_utf8(ascii).context("address is not ASCII")?;
+        let address = Address::from_slice(&hex::decode(address_hex).context("address is not hex")?);
+        info!(target: "signer", device = %device_path.display(), path = %config.derivation_path, "connected to Ledger");
+        Ok(Self {
+            device_path,
+            derivation_path,
+            address,
+            approval_timeout: Duration::from_secs(config.approval_timeout_secs),
+        })
+    }
+
+    fn sign_blocking(device_path: &Path, ins: u8, derivation_path: &[u32], payload: &[u8]) -> Result<Signature> {
+        let mut data = encode_path(derivation_path);
+        data.extend_from_slice(payload);
+        let mut response = Vec::new();
+        for (i, chunk) in data.chunks(MAX_APDU_DATA).enumerate() {
+            let p1 = if i == 0 { P1_FIRST_CHUNK } else { P1_MORE_CHUNKS };
+            response = exchange(device_path, &apdu(ins, p1, chunk))?;
+        }
+        if response.len() < 65 {
+            bail!("unexpected signature length {}", response.len());
+        }
+        // Typed transactions come back with v as the y-parity; older apps add 27
+        let y_parity = match response[0] {
+            v @ (0 | 1) => v,
+            v @ (27 | 28) => v - 27,
+            v => bail!("unexpected signature v={} (legacy transactions are not supported)", v),
+        };
+        Ok(Signature {
+            r: H256::from_slice(&response[1..33]),
+            s: H256::from_slice(&response[33..65]),
+            y_parity,
+        })
+    }
+}
+
+#[async_trait]
+impl Signer for LedgerSigner {
+    fn name(&self) -> &'static str {
+        "ledger"
+    }
+
+    fn address(&self) -> Address {
+        self.address
+    }
+
+    async fn sign_transaction(&self, payload: &[u8]) -> Result<Signature> {
+        self.sign(INS_SIGN_TRANSACTION, payload.to_vec()).await
+    }
+
+    async fn sign_typed_data(&self, domain_separator: H256, struct_hash: H256) -> Result<Signature> {
+        self.sign(INS_SIGN_EIP712_HASHED, [domain_separator.as_bytes(), struct_hash.as_bytes()].concat())
+            .await
+    }
+}
+
+impl LedgerSigner {
+    /// Run a signing APDU on a blocking thread, bounded by the approval timeout
+    async fn sign(&self, ins: u8, payload: Vec<u8>) -> Result<Signature> {
+        info!(target: "signer", address = ?self.address, "waiting for approval on the Ledger");
+        let device_path = self.device_path.clone();
+        let derivation_path = self.derivation_path.clone();
+        let task = tokio::task::spawn_blocking(move || Self::sign_blocking(&device_path, ins, &derivation_path, &payload));
+        match timeout(self.approval_timeout, task).await {
+            Ok(joined) => joined.context("Ledger signing task panicked")?,
+            Err(_) => bail!(
+                "no approval on the Ledger within {:?}; reject the pending request on the device",
+                self.approval_timeout
+            ),
+        }
+    }
+}
+
+/// First hidraw node exposing a Ledger's generic interface
+fn find_device() -> Result<PathBuf> {
+    for entry in fs::read_dir("/sys/class/hidraw").context("listing /sys/class/hidraw")? {
+        let entry = entry?;
+        let device = entry.path().join("device");
+        let uevent = fs::read_to_string(device.join("uevent")).unwrap_or_default();
+        if !uevent.lines().any(|l| l.starts_with("HID_ID=") && l.to_uppercase().contains(LEDGER_VENDOR_ID)) {
+            continue;
+        }
+        let descriptor = fs::read(device.join("report_descriptor")).unwrap_or_default();
+        if descriptor.starts_with(&GENERIC_USAGE_PAGE) {
+            return Ok(PathBuf::from("/dev").join(entry.file_name()));
+        }
+    }
+    bail!("no Ledger found; connect and unlock it, or set security.ledger.device_path")
+}
+
+/// Parse `m/44'/60'/0'/0/0` into BIP-32 indexes
+fn parse_derivation_path(path: &str) -> Result<Vec<u32>> {
+    path.trim_start_matches("m/")
+        .split('/')
+        .map(|part| {
+            let (index, hardened) = match part.strip_suffix('\'').or_else(|| part.strip_suffix('h')) {
+                Some(index) => (index, true),
+                None => (part, false),
+            };
+            let index: u32 = index.parse().with_context(|| format!("invalid derivation path {}", path))?;
+            Ok(if hardened { index | 0x8000_0000 } else { index })
+        })
+        .collect()
+}
+
+fn encode_path(path: &[u32]) -> Vec<u8> {
+    let mut out = vec![path.len() as u8];
+    for index in path {
+        out.extend_from_slice(&index.to_be_bytes());
+    }
+    out
+}
+
+fn apdu(ins: u8, p1: u8, data: &[u8]) -> Vec<u8> {
+    let mut out = vec![CLA_ETH, ins, p1, 0x00, data.len() as u8];
+    out.extend_from_slice(data);
+    out
+}
+
+/// Send one APDU and return the response data, mapping status words to errors
+fn exchange(device_path: &Path, apdu: &[u8]) -> Result<Vec<u8>> {
+    let mut device = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(device_path)
+        .with_context(|| format!("opening {} (check udev permissions)", device_path.display()))?;
+    for packet in frame(apdu) {
+        // hidraw expects a leading report id, 0 for unnumbered reports
+        let mut report = Vec::with_capacity(HID_PACKET_SIZE + 1);
+        report.push(0);
+        report.extend_from_slice(&packet);
+        device.write_all(&report).context("writing to Ledger")?;
+    }
+    let mut response = read_response(&mut device)?;
+    if response.len() < 2 {
+        bail!("short response from Ledger");
+    }
+    let status = u16::from_be_bytes([response[response.len() - 2], response[response.len() - 1]]);
+    response.truncate(response.len() - 2);
+    match status {
+        0x9000 => Ok(response),
+        0x6985 => bail!("transaction rejected on the Ledger"),
+        0x6a80 => bail!("Ledger refused the data; enable blind signing in the Ethereum app settings"),
+        0x6d00 | 0x6e00 | 0x6e01 | 0x6511 => bail!("open the Ethereum app on the Ledger"),
+        0x5515 | 0x6b0c => bail!("Ledger is locked"),
+        other => bail!("Ledger returned status {:#06x}", other),
+    }
+}
+
+/// Split an APDU into 64-byte HID packets (channel, tag, sequence, length on the first)
+fn frame(apdu: &[u8]) -> Vec<[u8; HID_PACKET_SIZE]> {
+    let mut payload = (apdu.len() as u16).to_be_bytes().to_vec();
+    payload.extend_from_slice(apdu);
+    payload
+        .chunks(HID_PACKET_SIZE - 5)
+        .enumerate()
+        .map(|(seq, chunk)| {
+            let mut packet = [0u8; HID_PACKET_SIZE];
+            packet[..2].copy_from_slice(&HID_CHANNEL.to_be_bytes());
+            packet[2] = HID_TAG_APDU;
+            packet[3..5].copy_from_slice(&(seq as u16).to_be_bytes());
+            packet[5..5 + chunk.len()].copy_from_slice(chunk);
+            packet
+        })
+        .collect()
+}
+
+fn read_response(device: &mut File) -> Result<Vec<u8>> {
+    let mut packets = Vec::new();
+    let mut expected: Option<usize> = None;
+    let mut received = 0;
+    loop {
+        let mut packet = [0u8; HID_PACKET_SIZE];
+        device.read_exact(&mut packet).context("reading from Ledger")?;
+        received += HID_PACKET_SIZE - 5;
+        if expected.is_none() {
+            expected = Some(u16::from_be_bytes([packet[5], packet[6]]) as usize);
+        }
+        packets.push(packet);
+        if received >= expected.unwrap_or(0) + 2 {
+            return unframe(&packets);
+        }
+    }
+}
+
+/// Reassemble an APDU response from HID packets
+fn unframe(packets: &[[u8; HID_PACKET_SIZE]]) -> Result<Vec<u8>> {
+    let mut payload = Vec::new();
+    for (seq, packet) in packets.iter().enumerate() {
+        if u16::from_be_bytes([packet[0], packet[1]]) != HID_CHANNEL || packet[2] != HID_TAG_APDU {
+            bail!("unexpected HID packet header");
+        }
+        if u16::from_be_bytes([packet[3], packet[4]]) as usize != seq {
+            bail!("HID packet out of sequence");
+        }
+        payload.extend_from_slice(&packet[5..]);
+    }
+    let len = u16::from_be_bytes([payload[0], payload[1]]) as usize;
+    payload
+        .get(2..2 + len)
+        .map(<[u8]>::to_vec)
+        .ok_or_else(|| anyhow!("truncated Ledger response"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_apdu_framing() {
+        let path = parse_derivation_path("m/44'/60'/0'/0/0").unwrap();
+        assert_eq!(path, vec![0x8000_002c, 0x8000_003c, 0x8000_0000, 0, 0]);
+
+        let mut data = encode_path(&path);
+        data.extend_from_slice(&[0xab; 200]);
+        let command = apdu(INS_SIGN_TRANSACTION, P1_FIRST_CHUNK, &data);
+        let packets = frame(&command);
+        // 2 length bytes + 5 header bytes + 221 data bytes over 59-byte packet bodies
+        assert_eq!(packets.len(), 4);
+        assert_eq!(&packets[0][..7], &[0x01, 0x01, 0x05, 0x00, 0x00, 0x00, command.len() as u8]);
+        assert_eq!(unframe(&packets).unwrap(), command);
+    }
+}
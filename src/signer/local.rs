@@ -0,0 +1,84 @@
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use ethereum_types::{Address, H256};
+use k256::ecdsa::SigningKey;
+use sha3::{Digest, Keccak256};
+
+use super::{Signature, Signer};
+
+/// Signs with a secp256k1 key held in memory
+pub struct LocalSigner {
+    key: SigningKey,
+    address: Address,
+}
+
+impl LocalSigner {
+    pub fn from_bytes(secret: &[u8]) -> Result<Self> {
+        let key = SigningKey::from_slice(secret).context("invalid secp256k1 private key")?;
+        let point = key.verifying_key().to_encoded_point(false);
+        // Address = last 20 bytes of keccak256 over the uncompressed public key without its 0x04 prefix
+        let hash = Keccak256::digest(&point.as_bytes()[1..]);
+        let address = Address::from_slice(&hash[12..]);
+        Ok(Self { key, address })
+    }
+
+    /// Parse a 0x-prefixed or bare hex private key
+    pub fn from_hex(private_key: &str) -> Result<Self> {
+        let bytes = hex::decode(private_key.trim().trim_start_matches("0x")).context("private key is not valid hex")?;
+        Self::from_bytes(&bytes)
+    }
+
+    /// Decrypt a Web3 Secret Storage (v3) keystore file
+    pub fn from_keystore(path: &str, passphrase: &str) -> Result<Self> {
+        let secret = eth_keystore::decrypt_key(path, passphrase)
+            .with_context(|| format!("decrypting keystore {}", path))?;
+        Self::from_bytes(&secret)
+    }
+}
+
+#[async_trait]
+impl Signer for LocalSigner {
+    fn name(&self) -> &'static str {
+        "local"
+    }
+
+    fn address(&self) -> Address {
+        self.address
+    }
+
+    async fn sign_transaction(&self, payload: &[u8]) -> Result<Signature> {
+        let hash = Keccak256::digest(payload);
+        let (signature, recovery_id) = self.key.sign_prehash_recoverable(&hash).context("signing transaction")?;
+        let (r, s) = signature.split_bytes();
+        Ok(Signature {
+            r: H256::from_slice(&r),
+            s: H256::from_slice(&s),
+            y_parity: recovery_id.to_byte(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Test vector from the Web3 Secret Storage definition (pbkdf2, password "testpassword")
+    const KEYSTORE: &str = r#"{"crypto":{"cipher":"aes-128-ctr","cipherparams":{"iv":"6087dab2f9fdbbfaddc31a909735c1e6"},"ciphertext":"5318b4d5bcd28de64ee5559e671353e16f075ecae9f99c7a79a38af5f869aa46","kdf":"pbkdf2","kdfparams":{"c":262144,"dklen":32,"prf":"hmac-sha256","salt":"ae3cd4e7013836a3df6bd7241b12db061dbe2c6785853cce422d148a624ce0bd"},"mac":"517ead924a9d0dc3124507e3393d175ce3ff7c1e96529c6c555ce9e51205e9b2"},"id":"3198bc9c-6672-5ab3-d995-4942343ae5b6","version":3}"#;
+
+    #[tokio::test]
+    async fn test_keystore_matches_raw_key() {
+        let path = std::env::temp_dir().join(format!("origins-keystore-{}.json", std::process::id()));
+        std::fs::write(&path, KEYSTORE).unwrap();
+        let path = path.to_str().unwrap();
+
+        let from_keystore = LocalSigner::from_keystore(path, "testpassword").unwrap();
+        let from_hex = LocalSigner::from_hex("0x7a28b5ba57c53603b0b07b56bba752f7784bf506fa95edc395f5cf6c7514fe9d").unwrap();
+        assert_eq!(from_keystore.address(), from_hex.address());
+        assert_eq!(format!("{:?}", from_hex.address()), "0x008aeeda4d805471df9b2a5b0f38a0c3bcba786b");
+        assert!(LocalSigner::from_keystore(path, "wrong").is_err());
+        std::fs::remove_file(path).unwrap();
+
+        let signature = from_hex.sign_transaction(b"payload").await.unwrap();
+        assert!(signature.y_parity <= 1);
+    }
+}
@@ -0,0 +1,99 @@
+//! Transaction signers: a local secp256k1 key (raw hex or encrypted keystore), a Ledger or a
+//! cloud KMS key. The key-based signers need the `execution` feature; the [`Signer`] trait
+//! is always available for embedders bringing their own.
+
+#[cfg(feature = "execution")]
+mod kms;
+mod ledger;
+#[cfg(feature = "execution")]
+mod local;
+
+#[cfg(feature = "execution")]
+pub use kms::KmsSigner;
+pub use ledger::LedgerSigner;
+#[cfg(feature = "execution")]
+pub use local::LocalSigner;
+
+use anyhow::{bail, Result};
+use async_trait::async_trait;
+use ethereum_types::{Address, H256};
+use std::sync::Arc;
+
+use crate::config::{Config, SecurityConfig};
+
+/// Secp256k1 signature with the recovery id as y-parity (0 or 1)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Signature {
+    pub r: H256,
+    pub s: H256,
+    pub y_parity: u8,
+}
+
+/// Signs transactions for one account
+#[async_trait]
+pub trait Signer: Send + Sync {
+    fn name(&self) -> &'static str;
+    fn address(&self) -> Address;
+    /// Sign an unsigned transaction encoding; the signature covers `keccak256(payload)`
+    async fn sign_transaction(&self, payload: &[u8]) -> Result<Signature>;
+    /// EIP-712 signature over `keccak256(0x1901 || domain_separator || struct_hash)`
+    async fn sign_typed_data(&self, domain_separator: H256, struct_hash: H256) -> Result<Signature> {
+        self.sign_transaction(&[&[0x19, 0x01][..], domain_separator.as_bytes(), struct_hash.as_bytes()].concat())
+            .await
+    }
+}
+
+/// Build the configured signer, or `None` when transaction signing is disabled
+pub async fn from_config(config: &Config) -> Result<Option<Arc<dyn Signer>>> {
+    let Some(security) = config.security.as_ref().filter(|s| s.enable_transaction_signing) else {
+        return Ok(None);
+    };
+    if let Some(ledger) = &security.ledger {
+        return Ok(Some(Arc::new(LedgerSigner::connect(ledger)?)));
+    }
+    key_signer(config, security).await.map(Some)
+}
+
+/// Cloud KMS, encrypted keystore or raw private key signer
+#[cfg(feature = "execution")]
+async fn key_signer(config: &Config, security: &SecurityConfig) -> Result<Arc<dyn Signer>> {
+    if let Some(kms) = &security.kms {
+        return Ok(Arc::new(KmsSigner::connect(kms).await?));
+    }
+    if let Some(path) = security.keystore_path.as_deref() {
+        let passphrase = keystore_passphrase(security)?;
+        return Ok(Arc::new(LocalSigner::from_keystore(path, &passphrase)?));
+    }
+    match security.private_key.as_deref().or(config.private_key.as_deref()) {
+        Some(key) => Ok(Arc::new(LocalSigner::from_hex(key)?)),
+        None => bail!("transaction signing enabled but neither security.keystore_path nor a private_key is set"),
+    }
+}
+
+#[cfg(not(feature = "execution"))]
+async fn key_signer(_config: &Config, _security: &SecurityConfig) -> Result<Arc<dyn Signer>> {
+    bail!("KMS, keystore and private key signing need the `execution` feature; only security.ledger is available in this build")
+}
+
+/// Passphrase from the configured env var, else the OS keyring (when enabled), else an
+/// interactive prompt
+#[cfg(feature = "execution")]
+fn keystore_passphrase(security: &SecurityConfig) -> Result<String> {
+    use anyhow::Context;
+    use std::io::IsTerminal;
+
+    use crate::credentials;
+
+    if let Ok(passphrase) = std::env::var(&security.keystore_password_env) {
+        return Ok(passphrase);
+    }
+    if security.keystore_password_keyring {
+        if let Some(passphrase) = credentials::get(credentials::KEYSTORE_PASSPHRASE)? {
+            return Ok(passphrase);
+        }
+    }
+    if !std::io::stdin().is_terminal() {
+        bail!("keystore passphrase not found: set {} or run interactively", security.keystore_password_env);
+    }
+    rpassword::prompt_password("Keystore passphrase: ").context("reading keystore passphrase")
+}
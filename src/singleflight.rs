@@ -0,0 +1,164 @@
+//! Coalescing of identical in-flight requests: while one caller (the leader) runs the work
+//! for a key, everyone else asking for the same key waits for its result instead of sending
+//! the same request upstream again.
+
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::Mutex;
+
+use anyhow::{anyhow, Result};
+use tokio::sync::broadcast;
+use tracing::debug;
+
+/// Result handed to waiters; errors travel as their rendered message
+type Shared<T> = std::result::Result<T, String>;
+
+pub struct Singleflight<T> {
+    inflight: Mutex<HashMap<String, broadcast::Sender<Shared<T>>>>,
+}
+
+impl<T> Default for Singleflight<T> {
+    fn default() -> Self {
+        Self {
+            inflight: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+impl<T: Clone> Singleflight<T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Run `work` for `key`, or wait for the run already in flight for it. If that leader is
+    /// cancelled before finishing, waiters fall back to running `work` themselves.
+    pub async fn run<F, Fut>(&self, key: &str, work: F) -> Result<T>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<T>>,
+    {
+        let waiting = {
+            let mut inflight = self.inflight.lock().expect("singleflight lock poisoned");
+            match inflight.get(key) {
+                Some(leader) => Some(leader.subscribe()),
+                None => {
+                    inflight.insert(key.to_string(), broadcast::channel(1).0);
+                    None
+                }
+            }
+        };
+        if let Some(mut rx) = waiting {
+            debug!(target: "singleflight", key, "joining in-flight request");
+            return match rx.recv().await {
+                Ok(shared) => shared.map_err(|e| anyhow!(e)),
+                Err(_) => work().await,
+            };
+        }
+
+        let flight = Flight { inflight: &self.inflight, key, done: false };
+        let result = work().await;
+        if let Some(leader) = flight.finish() {
+            let _ = leader.send(result.as_ref().map(T::clone).map_err(|e| format!("{:#}", e)));
+        }
+        result
+    }
+
+    /// Keys with a request in flight
+    pub fn in_flight(&self) -> usize {
+        self.inflight.lock().expect("singleflight lock poisoned").len()
+    }
+}
+
+/// The leader's claim on a key; released on drop so a cancelled leader doesn't strand waiters
+struct Flight<'a, T> {
+    inflight: &'a Mutex<HashMap<String, broadcast::Sender<Shared<T>>>>,
+    key: &'a str,
+    done: bool,
+}
+
+impl<T> Flight<'_, T> {
+    fn finish(mut self) -> Option<broadcast::Sender<Shared<T>>> {
+        self.done = true;
+        self.inflight.lock().expect("singleflight lock poisoned").remove(self.key)
+    }
+}
+
+impl<T> Drop for Flight<'_, T> {
+    fn drop(&mut self) {
+        if !self.done {
+            if let Ok(mut inflight) = self.inflight.lock() {
+                inflight.remove(self.key);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_concurrent_callers_share_one_run() {
+        let flights = Arc::new(Singleflight::<u64>::new());
+        let runs = Arc::new(AtomicUsize::new(0));
+        let calls: Vec<_> = (0..8)
+            .map(|_| {
+                let (flights, runs) = (flights.clone(), runs.clone());
+                tokio::spawn(async move {
+                    flights
+                        .run("pool:0x01", || async {
+                            runs.fetch_add(1, Ordering::SeqCst);
+                            tokio::time::sleep(Duration::from_millis(50)).await;
+                            Ok(42)
+                        })
+                        .await
+                })
+            })
+            .collect();
+        for call in calls {
+            assert_eq!(call.await.unwrap().unwrap(), 42);
+        }
+        assert_eq!(runs.load(Ordering::SeqCst), 1);
+        assert_eq!(flights.in_flight(), 0);
+
+        // Errors reach every waiter, and the next call runs afresh
+        let failing = || async {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            Err::<u64, _>(anyhow!("upstream 502"))
+        };
+        let (a, b) = tokio::join!(flights.run("pool:0x01", failing), flights.run("pool:0x01", failing));
+        assert_eq!(a.unwrap_err().to_string(), "upstream 502");
+        assert_eq!(b.unwrap_err().to_string(), "upstream 502");
+        assert_eq!(flights.run("pool:0x01", || async { Ok(7) }).await.unwrap(), 7);
+    }
+
+    #[tokio::test]
+    async fn test_waiters_recover_from_a_cancelled_leader() {
+        let flights = Arc::new(Singleflight::<u64>::new());
+        let leader = {
+            let flights = flights.clone();
+            tokio::spawn(async move {
+                flights
+                    .run("slow", || async {
+                        tokio::time::sleep(Duration::from_secs(60)).await;
+                        Ok(1)
+                    })
+                    .await
+            })
+        };
+        while flights.in_flight() == 0 {
+            tokio::task::yield_now().await;
+        }
+        let waiter = {
+            let flights = flights.clone();
+            tokio::spawn(async move { flights.run("slow", || async { Ok(2) }).await })
+        };
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        leader.abort();
+        assert_eq!(waiter.await.unwrap().unwrap(), 2);
+        assert_eq!(flights.in_flight(), 0);
+    }
+}
@@ -0,0 +1,52 @@
+use anyhow::Result;
+use std::sync::Arc;
+
+use crate::position::{Position, PositionRecommendation};
+use crate::storage::{HistoryQuery, MemoryStorage, Storage};
+
+pub use crate::storage::RecommendationRecord;
+
+/// Recommender output shared with the HTTP/GraphQL API, backed by the configured storage
+#[derive(Clone)]
+pub struct RecommenderState {
+    storage: Arc<dyn Storage>,
+}
+
+impl Default for RecommenderState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RecommenderState {
+    /// In-memory state (lost on restart)
+    pub fn new() -> Self {
+        Self::with_storage(Arc::new(MemoryStorage::new()))
+    }
+
+    pub fn with_storage(storage: Arc<dyn Storage>) -> Self {
+        Self { storage }
+    }
+
+    pub fn storage(&self) -> Arc<dyn Storage> {
+        self.storage.clone()
+    }
+
+    /// Replace the current positions/recommendations with a finished cycle's output
+    pub async fn record_cycle(&self, positions: &[Position], recommendations: &[PositionRecommendation]) -> Result<u64> {
+        self.storage.record_cycle(positions, recommendations).await
+    }
+
+    pub async fn positions(&self) -> Result<Vec<Position>> {
+        self.storage.positions().await
+    }
+
+    pub async fn latest(&self) -> Result<Vec<PositionRecommendation>> {
+        self.storage.latest().await
+    }
+
+    /// History, newest first
+    pub async fn history(&self, query: &HistoryQuery) -> Result<Vec<RecommendationRecord>> {
+        self.storage.history(query).await
+    }
+}
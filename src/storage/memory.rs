@@ -0,0 +1,404 @@
+use anyhow::{bail, Result};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use tokio::sync::RwLock;
+
+use super::{AlertQuery, Candle, HistoryQuery, PoolQuote, RecommendationRecord, StateSnapshot, Storage};
+use crate::archive::SwapRecord;
+use crate::audit::AuditRecord;
+use crate::backfill::{BackfillProgress, PositionSnapshotRecord};
+use crate::events::Event;
+use crate::executor::{ExecutionQuery, PaperWallet, PolicyState, RebalanceQuery, RebalanceWorkflow, TrackedTransaction};
+use crate::metrics::{MetricPoint, MetricQuery};
+use crate::pnl::{LedgerEntry, LedgerQuery};
+use crate::position::{Position, PositionRecommendation};
+
+/// Number of past recommendations retained in memory
+const HISTORY_CAPACITY: usize = 5_000;
+/// Quotes retained per pool
+const QUOTE_CAPACITY: usize = 2_000;
+const ALERT_CAPACITY: usize = 5_000;
+
+#[derive(Default)]
+struct Inner {
+    cycle: u64,
+    positions: Vec<Position>,
+    latest: Vec<PositionRecommendation>,
+    history: VecDeque<RecommendationRecord>,
+    candles: HashMap<(String, u32), BTreeMap<DateTime<Utc>, Candle>>,
+    /// Per pool, by time and id
+    swaps: HashMap<String, BTreeMap<(DateTime<Utc>, String), SwapRecord>>,
+    /// Per pool, by time and id
+    position_snapshots: HashMap<String, BTreeMap<(DateTime<Utc>, String), PositionSnapshotRecord>>,
+    backfill: HashMap<(String, String), BackfillProgress>,
+    /// Per pool, oldest first
+    quotes: HashMap<String, VecDeque<PoolQuote>>,
+    /// Oldest first
+    alerts: VecDeque<Event>,
+    /// By time, metric and subject
+    metrics: BTreeMap<(DateTime<Utc>, &'static str, String), MetricPoint>,
+    /// Oldest first
+    rebalances: Vec<RebalanceWorkflow>,
+    /// Oldest first
+    executions: Vec<TrackedTransaction>,
+    paper_wallets: HashMap<String, PaperWallet>,
+    policy: Option<PolicyState>,
+    snapshot: Option<StateSnapshot>,
+    /// Oldest first
+    ledger: Vec<LedgerEntry>,
+    /// Oldest first; never trimmed
+    audit: Vec<AuditRecord>,
+}
+
+/// In-process storage with a bounded recommendation history
+#[derive(Default)]
+pub struct MemoryStorage {
+    inner: RwLock<Inner>,
+}
+
+impl MemoryStorage {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl Storage for MemoryStorage {
+    fn backend_name(&self) -> &'static str {
+        "memory"
+    }
+
+    async fn record_cycle(&self, positions: &[Position], recommendations: &[PositionRecommendation]) -> Result<u64> {
+        let mut inner = self.inner.write().await;
+        inner.cycle += 1;
+        let cycle = inner.cycle;
+        let timestamp = Utc::now();
+        inner.positions = positions.to_vec();
+        inner.latest = recommendations.to_vec();
+        for rec in recommendations {
+            if inner.history.len() == HISTORY_CAPACITY {
+                inner.history.pop_front();
+            }
+            inner.history.push_back(RecommendationRecord {
+                cycle,
+                timestamp,
+                recommendation: rec.clone(),
+                executions: Vec::new(),
+            });
+        }
+        Ok(cycle)
+    }
+
+    async fn positions(&self) -> Result<Vec<Position>> {
+        Ok(self.inner.read().await.positions.clone())
+    }
+
+    async fn latest(&self) -> Result<Vec<PositionRecommendation>> {
+        Ok(self.inner.read().await.latest.clone())
+    }
+
+    async fn history(&self, query: &HistoryQuery) -> Result<Vec<RecommendationRecord>> {
+        let inner = self.inner.read().await;
+        Ok(inner
+            .history
+            .iter()
+            .rev()
+            .filter(|r| query.matches(r))
+            .skip(query.offset)
+            .take(query.limit)
+            .map(|r| RecommendationRecord {
+                executions: inner
+                    .executions
+                    .iter()
+                    .filter(|tx| tx.cycle == Some(r.cycle) && tx.position_id.as_ref() == Some(&r.recommendation.position.id))
+                    .cloned()
+                    .collect(),
+                ..r.clone()
+            })
+            .collect())
+    }
+
+    async fn save_candles(&self, candles: &[Candle]) -> Result<()> {
+        let mut inner = self.inner.write().await;
+        for candle in candles {
+            inner
+                .candles
+                .entry((candle.pool_id.clone(), candle.interval_secs))
+                .or_default()
+                .insert(candle.start, candle.clone());
+        }
+        Ok(())
+    }
+
+    async fn candles(&self, pool_id: &str, interval_secs: u32, since: DateTime<Utc>) -> Result<Vec<Candle>> {
+        Ok(self
+            .inner
+            .read()
+            .await
+            .candles
+            .get(&(pool_id.to_string(), interval_secs))
+            .map(|series| series.range(since..).map(|(_, c)| c.clone()).collect())
+            .unwrap_or_default())
+    }
+
+    async fn save_swaps(&self, swaps: &[SwapRecord]) -> Result<()> {
+        let mut inner = self.inner.write().await;
+        for swap in swaps {
+            inner.swaps.entry(swap.pool_id.clone()).or_default().entry((swap.timestamp, swap.id.clone())).or_insert_with(|| swap.clone());
+        }
+        Ok(())
+    }
+
+    async fn swaps(&self, pool_id: &str, since: DateTime<Utc>, limit: usize) -> Result<Vec<SwapRecord>> {
+        let inner = self.inner.read().await;
+        Ok(inner
+            .swaps
+            .get(pool_id)
+            .map(|swaps| swaps.range((since, String::new())..).take(limit).map(|(_, s)| s.clone()).collect())
+            .unwrap_or_default())
+    }
+
+    async fn save_position_snapshots(&self, snapshots: &[PositionSnapshotRecord]) -> Result<()> {
+        let mut inner = self.inner.write().await;
+        for snapshot in snapshots {
+            inner
+                .position_snapshots
+                .entry(snapshot.pool_id.clone())
+                .or_default()
+                .entry((snapshot.timestamp, snapshot.id.clone()))
+                .or_insert_with(|| snapshot.clone());
+        }
+        Ok(())
+    }
+
+    async fn position_snapshots(&self, pool_id: &str, since: DateTime<Utc>, limit: usize) -> Result<Vec<PositionSnapshotRecord>> {
+        let inner = self.inner.read().await;
+        Ok(inner
+            .position_snapshots
+            .get(pool_id)
+            .map(|snapshots| snapshots.range((since, String::new())..).take(limit).map(|(_, s)| s.clone()).collect())
+            .unwrap_or_default())
+    }
+
+    async fn save_backfill_progress(&self, progress: &BackfillProgress) -> Result<()> {
+        let key = (progress.pool_id.clone(), progress.dataset.clone());
+        self.inner.write().await.backfill.insert(key, progress.clone());
+        Ok(())
+    }
+
+    async fn backfill_progress(&self, pool_id: &str) -> Result<Vec<BackfillProgress>> {
+        let inner = self.inner.read().await;
+        Ok(inner.backfill.values().filter(|p| p.pool_id == pool_id).cloned().collect())
+    }
+
+    async fn save_quote(&self, quote: &PoolQuote) -> Result<()> {
+        let mut inner = self.inner.write().await;
+        let quotes = inner.quotes.entry(quote.pool_id.clone()).or_default();
+        if quotes.len() == QUOTE_CAPACITY {
+            quotes.pop_front();
+        }
+        quotes.push_back(quote.clone());
+        Ok(())
+    }
+
+    async fn quotes(&self, pool_id: &str, since: DateTime<Utc>) -> Result<Vec<PoolQuote>> {
+        Ok(self
+            .inner
+            .read()
+            .await
+            .quotes
+            .get(pool_id)
+            .map(|quotes| quotes.iter().filter(|q| q.timestamp >= since).cloned().collect())
+            .unwrap_or_default())
+    }
+
+    async fn save_alert(&self, event: &Event) -> Result<()> {
+        let mut inner = self.inner.write().await;
+        if inner.alerts.len() == ALERT_CAPACITY {
+            inner.alerts.pop_front();
+        }
+        inner.alerts.push_back(event.clone());
+        Ok(())
+    }
+
+    async fn alerts(&self, query: &AlertQuery) -> Result<Vec<Event>> {
+        Ok(self
+            .inner
+            .read()
+            .await
+            .alerts
+            .iter()
+            .rev()
+            .filter(|e| query.matches(e))
+            .take(query.limit)
+            .cloned()
+            .collect())
+    }
+
+    async fn save_metrics(&self, points: &[MetricPoint]) -> Result<()> {
+        let mut inner = self.inner.write().await;
+        for point in points {
+            inner.metrics.insert((point.timestamp, point.metric.as_str(), point.subject.clone()), point.clone());
+        }
+        Ok(())
+    }
+
+    async fn metrics(&self, query: &MetricQuery) -> Result<Vec<MetricPoint>> {
+        Ok(self
+            .inner
+            .read()
+            .await
+            .metrics
+            .values()
+            .filter(|p| query.matches(p))
+            .take(query.limit)
+            .cloned()
+            .collect())
+    }
+
+    async fn save_rebalance(&self, workflow: &RebalanceWorkflow) -> Result<()> {
+        let mut inner = self.inner.write().await;
+        match inner.rebalances.iter_mut().find(|w| w.id == workflow.id) {
+            Some(existing) => *existing = workflow.clone(),
+            None => inner.rebalances.push(workflow.clone()),
+        }
+        Ok(())
+    }
+
+    async fn rebalances(&self, query: &RebalanceQuery) -> Result<Vec<RebalanceWorkflow>> {
+        Ok(self
+            .inner
+            .read()
+            .await
+            .rebalances
+            .iter()
+            .rev()
+            .filter(|w| query.matches(w))
+            .take(query.limit)
+            .cloned()
+            .collect())
+    }
+
+    async fn save_execution(&self, tx: &TrackedTransaction) -> Result<()> {
+        let mut inner = self.inner.write().await;
+        match inner.executions.iter_mut().find(|t| t.tx_hash == tx.tx_hash) {
+            Some(existing) => *existing = tx.clone(),
+            None => inner.executions.push(tx.clone()),
+        }
+        Ok(())
+    }
+
+    async fn executions(&self, query: &ExecutionQuery) -> Result<Vec<TrackedTransaction>> {
+        Ok(self
+            .inner
+            .read()
+            .await
+            .executions
+            .iter()
+            .rev()
+            .filter(|t| query.matches(t))
+            .take(query.limit)
+            .cloned()
+            .collect())
+    }
+
+    async fn save_paper_wallet(&self, wallet: &PaperWallet) -> Result<()> {
+        self.inner.write().await.paper_wallets.insert(wallet.name.clone(), wallet.clone());
+        Ok(())
+    }
+
+    async fn paper_wallet(&self, name: &str) -> Result<Option<PaperWallet>> {
+        Ok(self.inner.read().await.paper_wallets.get(name).cloned())
+    }
+
+    async fn save_policy_state(&self, state: &PolicyState) -> Result<()> {
+        self.inner.write().await.policy = Some(state.clone());
+        Ok(())
+    }
+
+    async fn policy_state(&self) -> Result<Option<PolicyState>> {
+        Ok(self.inner.read().await.policy.clone())
+    }
+
+    async fn save_ledger_entry(&self, entry: &LedgerEntry) -> Result<()> {
+        self.inner.write().await.ledger.push(entry.clone());
+        Ok(())
+    }
+
+    async fn ledger(&self, query: &LedgerQuery) -> Result<Vec<LedgerEntry>> {
+        Ok(self.inner.read().await.ledger.iter().filter(|e| query.matches(e)).cloned().collect())
+    }
+
+    async fn save_snapshot(&self, snapshot: &StateSnapshot) -> Result<()> {
+        self.inner.write().await.snapshot = Some(snapshot.clone());
+        Ok(())
+    }
+
+    async fn latest_snapshot(&self) -> Result<Option<StateSnapshot>> {
+        Ok(self.inner.read().await.snapshot.clone())
+    }
+
+    async fn append_audit(&self, record: &AuditRecord) -> Result<()> {
+        let mut inner = self.inner.write().await;
+        let next = inner.audit.last().map_or(1, |r| r.seq + 1);
+        if record.seq != next {
+            bail!("audit record {} out of sequence (next is {})", record.seq, next);
+        }
+        inner.audit.push(record.clone());
+        Ok(())
+    }
+
+    async fn audit_records(&self, after_seq: u64, limit: usize) -> Result<Vec<AuditRecord>> {
+        let inner = self.inner.read().await;
+        Ok(inner.audit.iter().filter(|r| r.seq > after_seq).take(limit).cloned().collect())
+    }
+
+    async fn latest_audit(&self) -> Result<Option<AuditRecord>> {
+        Ok(self.inner.read().await.audit.last().cloned())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::position::Action;
+    use rust_decimal::Decimal;
+
+    fn recommendation(id: &str, wallet: &str) -> PositionRecommendation {
+        PositionRecommendation {
+            position: Position::new(id.to_string(), wallet.to_string(), "0xtoken".to_string(), Decimal::ONE, Decimal::ONE),
+            recommendation_score: 0.5,
+            reasoning: String::new(),
+            suggested_action: Action::Hold,
+            fee_apr: None,
+            projected_il: None,
+            rebalance: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_history_filters_and_orders_newest_first() {
+        let storage = MemoryStorage::new();
+        storage.record_cycle(&[], &[recommendation("a", "0xAA"), recommendation("b", "0xBB")]).await.unwrap();
+        let cycle = storage.record_cycle(&[], &[recommendation("a", "0xAA")]).await.unwrap();
+        assert_eq!(cycle, 2);
+
+        let all = storage.history(&HistoryQuery { limit: 10, ..Default::default() }).await.unwrap();
+        assert_eq!(all.len(), 3);
+        assert_eq!(all[0].cycle, 2);
+
+        let wallet = HistoryQuery { wallets: vec!["0xbb".to_string()], limit: 10, ..Default::default() };
+        let filtered = storage.history(&wallet).await.unwrap();
+        assert_eq!(filtered.len(), 1);
+        assert_eq!(filtered[0].recommendation.position.id, "b");
+
+        let limited = HistoryQuery { position_id: Some("a".to_string()), limit: 1, ..Default::default() };
+        assert_eq!(storage.history(&limited).await.unwrap()[0].cycle, 2);
+        let next_page = HistoryQuery { offset: 1, ..limited };
+        assert_eq!(storage.history(&next_page).await.unwrap()[0].cycle, 1);
+
+        let future = HistoryQuery { since: Some(Utc::now() + chrono::Duration::hours(1)), limit: 10, ..Default::default() };
+        assert!(storage.history(&future).await.unwrap().is_empty());
+    }
+}
@@ -0,0 +1,60 @@
+//! Versioned schema migrations shared by the SQL backends.
+//!
+//! Each backend keeps its own ordered list; applied versions are recorded in
+//! `schema_migrations`, and on connect every migration not yet recorded runs in order,
+//! in one transaction. Migrations are append-only: never edit one that has shipped,
+//! add a new version instead.
+
+use anyhow::{bail, Result};
+
+#[derive(Debug, Clone, Copy)]
+pub(super) struct Migration {
+    pub version: i64,
+    pub description: &'static str,
+    pub sql: &'static str,
+}
+
+/// Migrations from `migrations` not in `applied`, in order. Fails when the database
+/// has versions this build doesn't know, i.e. it was migrated by a newer build.
+pub(super) fn pending(migrations: &[Migration], applied: &[i64]) -> Result<Vec<Migration>> {
+    if let Some(pair) = migrations.windows(2).find(|w| w[1].version <= w[0].version) {
+        bail!("schema migrations out of order at version {}", pair[1].version);
+    }
+    let latest = migrations.last().map_or(0, |m| m.version);
+    if let Some(unknown) = applied.iter().find(|v| !migrations.iter().any(|m| m.version == **v)) {
+        bail!(
+            "database has schema version {} which this build does not know (latest known is {}); upgrade the binary",
+            unknown,
+            latest
+        );
+    }
+    Ok(migrations.iter().filter(|m| !applied.contains(&m.version)).copied().collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MIGRATIONS: &[Migration] = &[
+        Migration { version: 1, description: "initial schema", sql: "" },
+        Migration { version: 2, description: "add column", sql: "" },
+        Migration { version: 3, description: "add index", sql: "" },
+    ];
+
+    fn versions(pending: Vec<Migration>) -> Vec<i64> {
+        pending.iter().map(|m| m.version).collect()
+    }
+
+    #[test]
+    fn test_pending() {
+        assert_eq!(versions(pending(MIGRATIONS, &[]).unwrap()), [1, 2, 3]);
+        assert_eq!(versions(pending(MIGRATIONS, &[1]).unwrap()), [2, 3]);
+        assert!(pending(MIGRATIONS, &[1, 2, 3]).unwrap().is_empty());
+
+        let err = pending(MIGRATIONS, &[1, 2, 3, 4]).unwrap_err().to_string();
+        assert!(err.contains("schema version 4") && err.contains("latest known is 3"));
+
+        let shuffled = [MIGRATIONS[0], MIGRATIONS[2], MIGRATIONS[1]];
+        assert!(pending(&shuffled, &[]).is_err());
+    }
+}
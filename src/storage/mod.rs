@@ -0,0 +1,305 @@
+//! Persistence for positions, recommendation history, pool quotes, price candles,
+//! backfilled swaps and position snapshots, alerts, derived metric time series,
+//! the rebalance audit trail, sent transactions, the execution policy's spend log, the PnL
+//! ledger and the hash-chained audit log.
+//!
+//! `MemoryStorage` keeps everything in-process (lost on restart); `SqliteStorage` keeps it
+//! in a local file for a single instance; `PostgresStorage` lets several instances share
+//! state. The SQL backends upgrade existing databases on connect through versioned
+//! migrations (see `migrations`).
+
+mod memory;
+mod migrations;
+mod postgres;
+mod sqlite;
+
+pub use memory::MemoryStorage;
+pub use postgres::PostgresStorage;
+pub use sqlite::SqliteStorage;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use tokio::sync::broadcast::error::RecvError;
+use tracing::{info, warn};
+
+use crate::alerts::AlertMarks;
+use crate::archive::SwapRecord;
+use crate::audit::AuditRecord;
+use crate::backfill::{BackfillProgress, PositionSnapshotRecord};
+use crate::config::{StorageBackendKind, StorageConfig};
+use crate::events::{Event, EventBus};
+use crate::executor::{ExecutionQuery, PaperWallet, PolicyState, RebalanceQuery, RebalanceWorkflow, TrackedTransaction};
+use crate::metrics::{MetricPoint, MetricQuery};
+use crate::pnl::{LedgerEntry, LedgerQuery};
+use crate::position::{Action, Position, PositionRecommendation};
+use crate::uniswap::Pool;
+
+/// SQLite database file when `sqlite_path` is unset
+const DEFAULT_SQLITE_PATH: &str = "origins.db";
+
+/// A recommendation together with the cycle that produced it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "server", derive(utoipa::ToSchema))]
+pub struct RecommendationRecord {
+    pub cycle: u64,
+    pub timestamp: DateTime<Utc>,
+    pub recommendation: PositionRecommendation,
+    /// Transactions sent for this position while this was its latest recommendation
+    #[serde(default)]
+    pub executions: Vec<TrackedTransaction>,
+}
+
+/// OHLCV bar for a pool over `interval_secs` starting at `start`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Candle {
+    pub pool_id: String,
+    pub interval_secs: u32,
+    pub start: DateTime<Utc>,
+    pub open: f64,
+    pub high: f64,
+    pub low: f64,
+    pub close: f64,
+    pub volume: f64,
+}
+
+/// The recommender's working state, saved periodically and restored on startup
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StateSnapshot {
+    pub taken_at: DateTime<Utc>,
+    /// Positions with their latest risk and liquidity scores
+    pub positions: Vec<Position>,
+    /// Latest action and score per position, so unchanged recommendations aren't re-announced
+    pub recommendations: BTreeMap<String, RecommendationMark>,
+    #[serde(default)]
+    pub alerts: AlertMarks,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RecommendationMark {
+    pub action: Action,
+    pub score: f64,
+}
+
+/// A pool's TVL and volume as quoted from the subgraph
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "server", derive(utoipa::ToSchema))]
+pub struct PoolQuote {
+    pub pool_id: String,
+    pub timestamp: DateTime<Utc>,
+    pub token0_symbol: String,
+    pub token1_symbol: String,
+    pub fee_tier: u32,
+    pub tvl_usd: f64,
+    pub volume_usd: f64,
+}
+
+impl PoolQuote {
+    pub fn from_pool(pool: &Pool, timestamp: DateTime<Utc>) -> Self {
+        Self {
+            pool_id: pool.id.clone(),
+            timestamp,
+            token0_symbol: pool.token0.symbol.clone(),
+            token1_symbol: pool.token1.symbol.clone(),
+            fee_tier: pool.fee_tier.parse().unwrap_or(0),
+            tvl_usd: pool.total_value_locked_usd.parse().unwrap_or(0.0),
+            volume_usd: pool.volume_usd.parse().unwrap_or(0.0),
+        }
+    }
+}
+
+/// Filters for stored alerts
+#[derive(Debug, Clone, Default)]
+pub struct AlertQuery {
+    pub position_id: Option<String>,
+    /// Event type, e.g. `position_out_of_range`
+    pub kind: Option<String>,
+    /// Inclusive lower bound on the event time
+    pub since: Option<DateTime<Utc>>,
+    pub limit: usize,
+}
+
+impl AlertQuery {
+    pub fn matches(&self, event: &Event) -> bool {
+        self.position_id.as_deref().is_none_or(|id| event.position_id() == id)
+            && self.kind.as_deref().is_none_or(|kind| event.kind() == kind)
+            && self.since.is_none_or(|since| event.timestamp() >= since)
+    }
+}
+
+/// Filters and pagination for recommendation queries
+#[derive(Debug, Clone, Default)]
+pub struct HistoryQuery {
+    pub position_id: Option<String>,
+    /// Only this wallet's positions
+    pub wallet: Option<String>,
+    /// Wallets the caller may see; empty means all
+    pub wallets: Vec<String>,
+    pub action: Option<Action>,
+    pub token_address: Option<String>,
+    /// Inclusive lower bound on the recording time
+    pub since: Option<DateTime<Utc>>,
+    /// Exclusive upper bound on the recording time
+    pub until: Option<DateTime<Utc>>,
+    pub limit: usize,
+    pub offset: usize,
+}
+
+impl HistoryQuery {
+    /// Whether a recommendation passes the field filters (time range excluded)
+    pub fn matches_recommendation(&self, rec: &PositionRecommendation) -> bool {
+        let position = &rec.position;
+        self.position_id.as_ref().is_none_or(|id| &position.id == id)
+            && self.wallet.as_ref().is_none_or(|w| w.eq_ignore_ascii_case(&position.user_address))
+            && (self.wallets.is_empty() || self.wallets.iter().any(|w| w.eq_ignore_ascii_case(&position.user_address)))
+            && self.action.as_ref().is_none_or(|a| a.name() == rec.suggested_action.name())
+            && self.token_address.as_ref().is_none_or(|t| t.eq_ignore_ascii_case(&position.token_address))
+    }
+
+    fn matches(&self, record: &RecommendationRecord) -> bool {
+        self.since.is_none_or(|since| record.timestamp >= since)
+            && self.until.is_none_or(|until| record.timestamp < until)
+            && self.matches_recommendation(&record.recommendation)
+    }
+}
+
+#[async_trait]
+pub trait Storage: Send + Sync {
+    fn backend_name(&self) -> &'static str;
+
+    /// Persist a finished cycle's positions and recommendations; returns the cycle number
+    async fn record_cycle(&self, positions: &[Position], recommendations: &[PositionRecommendation]) -> Result<u64>;
+
+    /// Positions analyzed in the latest cycle
+    async fn positions(&self) -> Result<Vec<Position>>;
+
+    /// Recommendations from the latest cycle
+    async fn latest(&self) -> Result<Vec<PositionRecommendation>>;
+
+    /// Past recommendations matching `query`, newest first, after skipping `query.offset`
+    async fn history(&self, query: &HistoryQuery) -> Result<Vec<RecommendationRecord>>;
+
+    /// Insert or replace candles (keyed by pool, interval and start)
+    async fn save_candles(&self, candles: &[Candle]) -> Result<()>;
+
+    /// Candles for a pool/interval starting at or after `since`, oldest first
+    async fn candles(&self, pool_id: &str, interval_secs: u32, since: DateTime<Utc>) -> Result<Vec<Candle>>;
+
+    /// Insert swaps, skipping ids already stored
+    async fn save_swaps(&self, swaps: &[SwapRecord]) -> Result<()>;
+
+    /// Up to `limit` swaps of a pool at or after `since`, oldest first
+    async fn swaps(&self, pool_id: &str, since: DateTime<Utc>, limit: usize) -> Result<Vec<SwapRecord>>;
+
+    /// Insert position snapshots, skipping ids already stored
+    async fn save_position_snapshots(&self, snapshots: &[PositionSnapshotRecord]) -> Result<()>;
+
+    /// Up to `limit` snapshots of a pool's positions at or after `since`, oldest first
+    async fn position_snapshots(&self, pool_id: &str, since: DateTime<Utc>, limit: usize) -> Result<Vec<PositionSnapshotRecord>>;
+
+    /// Insert or replace backfill progress (keyed by pool and dataset)
+    async fn save_backfill_progress(&self, progress: &BackfillProgress) -> Result<()>;
+
+    async fn backfill_progress(&self, pool_id: &str) -> Result<Vec<BackfillProgress>>;
+
+    async fn save_quote(&self, quote: &PoolQuote) -> Result<()>;
+
+    /// Quotes for a pool taken at or after `since`, oldest first
+    async fn quotes(&self, pool_id: &str, since: DateTime<Utc>) -> Result<Vec<PoolQuote>>;
+
+    /// Append an alert event (any event other than a recommendation change)
+    async fn save_alert(&self, event: &Event) -> Result<()>;
+
+    /// Alerts matching `query`, newest first
+    async fn alerts(&self, query: &AlertQuery) -> Result<Vec<Event>>;
+
+    /// Insert or replace metric points (keyed by metric, subject and time)
+    async fn save_metrics(&self, points: &[MetricPoint]) -> Result<()>;
+
+    /// Up to `query.limit` metric points matching `query`, oldest first
+    async fn metrics(&self, query: &MetricQuery) -> Result<Vec<MetricPoint>>;
+
+    /// Insert or replace a rebalance workflow (keyed by id)
+    async fn save_rebalance(&self, workflow: &RebalanceWorkflow) -> Result<()>;
+
+    /// Rebalance workflows matching `query`, newest first
+    async fn rebalances(&self, query: &RebalanceQuery) -> Result<Vec<RebalanceWorkflow>>;
+
+    /// Insert or replace a tracked transaction (keyed by hash)
+    async fn save_execution(&self, tx: &TrackedTransaction) -> Result<()>;
+
+    /// Tracked transactions matching `query`, newest first
+    async fn executions(&self, query: &ExecutionQuery) -> Result<Vec<TrackedTransaction>>;
+
+    /// Insert or replace a paper-trading wallet (keyed by name)
+    async fn save_paper_wallet(&self, wallet: &PaperWallet) -> Result<()>;
+
+    async fn paper_wallet(&self, name: &str) -> Result<Option<PaperWallet>>;
+
+    /// Replace the execution policy's spend log and first-start time
+    async fn save_policy_state(&self, state: &PolicyState) -> Result<()>;
+
+    async fn policy_state(&self) -> Result<Option<PolicyState>>;
+
+    /// Append a PnL ledger entry
+    async fn save_ledger_entry(&self, entry: &LedgerEntry) -> Result<()>;
+
+    /// Ledger entries matching `query`, oldest first
+    async fn ledger(&self, query: &LedgerQuery) -> Result<Vec<LedgerEntry>>;
+
+    /// Replace the saved recommender state
+    async fn save_snapshot(&self, snapshot: &StateSnapshot) -> Result<()>;
+
+    async fn latest_snapshot(&self) -> Result<Option<StateSnapshot>>;
+
+    /// Append an audit record; fails if a record with the same `seq` already exists
+    async fn append_audit(&self, record: &AuditRecord) -> Result<()>;
+
+    /// Up to `limit` audit records with `seq > after_seq`, oldest first
+    async fn audit_records(&self, after_seq: u64, limit: usize) -> Result<Vec<AuditRecord>>;
+
+    /// The audit record with the highest `seq`
+    async fn latest_audit(&self) -> Result<Option<AuditRecord>>;
+}
+
+/// Open the configured storage backend (in-memory when unset)
+pub async fn connect(config: Option<&StorageConfig>) -> Result<Arc<dyn Storage>> {
+    let storage: Arc<dyn Storage> = match config {
+        Some(cfg) if cfg.backend == StorageBackendKind::Postgres => {
+            let url = cfg
+                .postgres_url
+                .as_deref()
+                .ok_or_else(|| anyhow::anyhow!("storage backend 'postgres' requires postgres_url"))?;
+            Arc::new(PostgresStorage::connect(url, cfg.max_connections, &cfg.instance).await?)
+        }
+        Some(cfg) if cfg.backend == StorageBackendKind::Sqlite => {
+            let path = cfg.sqlite_path.as_deref().unwrap_or(DEFAULT_SQLITE_PATH);
+            Arc::new(SqliteStorage::connect(path, cfg.max_connections).await?)
+        }
+        _ => Arc::new(MemoryStorage::new()),
+    };
+    info!(target: "storage", backend = storage.backend_name(), "storage initialized");
+    Ok(storage)
+}
+
+/// Persist every alert published on the bus until it closes
+pub async fn record_alerts(storage: Arc<dyn Storage>, bus: EventBus) {
+    let mut rx = bus.subscribe();
+    loop {
+        match rx.recv().await {
+            Ok(Event::RecommendationChanged { .. }) => {}
+            Ok(event) => {
+                if let Err(e) = storage.save_alert(&event).await {
+                    warn!(target: "storage", kind = event.kind(), position = event.position_id(), "alert not saved: {:#}", e);
+                }
+            }
+            Err(RecvError::Lagged(skipped)) => {
+                warn!(target: "storage", skipped, "alert recorder lagged, alerts dropped");
+            }
+            Err(RecvError::Closed) => break,
+        }
+    }
+}
@@ -0,0 +1,807 @@
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use sqlx::postgres::{PgPool, PgPoolOptions};
+use sqlx::types::Json;
+use sqlx::{Executor, Row};
+use tracing::info;
+
+use super::migrations::{pending, Migration};
+use super::{AlertQuery, Candle, HistoryQuery, PoolQuote, RecommendationRecord, StateSnapshot, Storage};
+use crate::archive::SwapRecord;
+use crate::audit::AuditRecord;
+use crate::backfill::{BackfillProgress, PositionSnapshotRecord};
+use crate::events::Event;
+use crate::executor::{ExecutionQuery, PaperWallet, PolicyState, RebalanceQuery, RebalanceWorkflow, TrackedTransaction};
+use crate::metrics::{MetricPoint, MetricQuery};
+use crate::pnl::{LedgerEntry, LedgerQuery};
+use crate::position::{Action, Position, PositionRecommendation};
+
+const MIGRATIONS_TABLE: &str = r#"
+CREATE TABLE IF NOT EXISTS schema_migrations (
+    version     BIGINT PRIMARY KEY,
+    description TEXT NOT NULL,
+    applied_at  TIMESTAMPTZ NOT NULL
+);
+"#;
+
+/// `pg_advisory_xact_lock` key serializing migrations across instances
+const MIGRATION_LOCK_KEY: i64 = 0x006f_7269_6769_6e73; // "origins"
+
+/// Append-only; see `migrations`. Version 1 uses `IF NOT EXISTS` so databases created
+/// before migrations were tracked are adopted as-is.
+const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        description: "initial schema",
+        sql: r#"
+CREATE TABLE IF NOT EXISTS cycles (
+    id          BIGSERIAL PRIMARY KEY,
+    recorded_at TIMESTAMPTZ NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS positions (
+    id           TEXT PRIMARY KEY,
+    user_address TEXT NOT NULL,
+    cycle_id     BIGINT NOT NULL REFERENCES cycles (id),
+    data         JSONB NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS recommendations (
+    id           BIGSERIAL PRIMARY KEY,
+    cycle_id     BIGINT NOT NULL REFERENCES cycles (id),
+    position_id  TEXT NOT NULL,
+    user_address TEXT NOT NULL,
+    action       TEXT NOT NULL,
+    recorded_at  TIMESTAMPTZ NOT NULL,
+    data         JSONB NOT NULL
+);
+CREATE INDEX IF NOT EXISTS recommendations_cycle_idx ON recommendations (cycle_id);
+CREATE INDEX IF NOT EXISTS recommendations_position_idx ON recommendations (position_id, id DESC);
+
+CREATE TABLE IF NOT EXISTS candles (
+    pool_id       TEXT NOT NULL,
+    interval_secs INTEGER NOT NULL,
+    start_time    TIMESTAMPTZ NOT NULL,
+    open          DOUBLE PRECISION NOT NULL,
+    high          DOUBLE PRECISION NOT NULL,
+    low           DOUBLE PRECISION NOT NULL,
+    close         DOUBLE PRECISION NOT NULL,
+    volume        DOUBLE PRECISION NOT NULL,
+    PRIMARY KEY (pool_id, interval_secs, start_time)
+);
+
+CREATE TABLE IF NOT EXISTS quotes (
+    pool_id   TEXT NOT NULL,
+    quoted_at TIMESTAMPTZ NOT NULL,
+    data      JSONB NOT NULL,
+    PRIMARY KEY (pool_id, quoted_at)
+);
+
+CREATE TABLE IF NOT EXISTS alerts (
+    id          BIGSERIAL PRIMARY KEY,
+    kind        TEXT NOT NULL,
+    position_id TEXT NOT NULL,
+    created_at  TIMESTAMPTZ NOT NULL,
+    data        JSONB NOT NULL
+);
+CREATE INDEX IF NOT EXISTS alerts_position_idx ON alerts (position_id, id DESC);
+
+CREATE TABLE IF NOT EXISTS rebalances (
+    id          TEXT PRIMARY KEY,
+    position_id TEXT NOT NULL,
+    status      TEXT NOT NULL,
+    created_at  TIMESTAMPTZ NOT NULL,
+    updated_at  TIMESTAMPTZ NOT NULL,
+    data        JSONB NOT NULL
+);
+CREATE INDEX IF NOT EXISTS rebalances_position_idx ON rebalances (position_id, created_at DESC);
+
+CREATE TABLE IF NOT EXISTS executions (
+    tx_hash      TEXT PRIMARY KEY,
+    position_id  TEXT,
+    cycle_id     BIGINT,
+    finalized    BOOLEAN NOT NULL,
+    submitted_at TIMESTAMPTZ NOT NULL,
+    data         JSONB NOT NULL
+);
+CREATE INDEX IF NOT EXISTS executions_recommendation_idx ON executions (position_id, cycle_id);
+CREATE INDEX IF NOT EXISTS executions_unsettled_idx ON executions (submitted_at) WHERE NOT finalized;
+
+CREATE TABLE IF NOT EXISTS paper_wallets (
+    name       TEXT PRIMARY KEY,
+    updated_at TIMESTAMPTZ NOT NULL,
+    data       JSONB NOT NULL
+);
+
+-- A single row holding the latest recommender state snapshot
+CREATE TABLE IF NOT EXISTS snapshots (
+    id       SMALLINT PRIMARY KEY CHECK (id = 1),
+    taken_at TIMESTAMPTZ NOT NULL,
+    data     JSONB NOT NULL
+);
+"#,
+    },
+    Migration {
+        version: 2,
+        description: "pnl ledger",
+        sql: r#"
+CREATE TABLE pnl_ledger (
+    id          BIGSERIAL PRIMARY KEY,
+    position_id TEXT NOT NULL,
+    owner       TEXT NOT NULL,
+    kind        TEXT NOT NULL,
+    recorded_at TIMESTAMPTZ NOT NULL,
+    data        JSONB NOT NULL
+);
+CREATE INDEX pnl_ledger_position_idx ON pnl_ledger (position_id, recorded_at);
+CREATE INDEX pnl_ledger_owner_idx ON pnl_ledger (owner, recorded_at);
+"#,
+    },
+    Migration {
+        version: 3,
+        description: "audit log",
+        // `data` holds the record as serialized text so it reads back byte for byte
+        sql: r#"
+CREATE TABLE audit_log (
+    seq         BIGINT PRIMARY KEY,
+    recorded_at TIMESTAMPTZ NOT NULL,
+    kind        TEXT NOT NULL,
+    position_id TEXT,
+    hash        TEXT NOT NULL,
+    data        TEXT NOT NULL
+);
+CREATE FUNCTION audit_log_append_only() RETURNS TRIGGER AS $fn$
+BEGIN
+    RAISE EXCEPTION 'audit_log is append-only';
+END
+$fn$ LANGUAGE plpgsql;
+CREATE TRIGGER audit_log_no_update BEFORE UPDATE OR DELETE ON audit_log
+    FOR EACH ROW EXECUTE FUNCTION audit_log_append_only();
+CREATE TRIGGER audit_log_no_truncate BEFORE TRUNCATE ON audit_log
+    FOR EACH STATEMENT EXECUTE FUNCTION audit_log_append_only();
+"#,
+    },
+    Migration {
+        version: 4,
+        description: "backfilled swaps and position snapshots",
+        sql: r#"
+CREATE TABLE swaps (
+    id         TEXT PRIMARY KEY,
+    pool_id    TEXT NOT NULL,
+    swapped_at TIMESTAMPTZ NOT NULL,
+    data       JSONB NOT NULL
+);
+CREATE INDEX swaps_pool_idx ON swaps (pool_id, swapped_at);
+CREATE TABLE position_snapshots (
+    id          TEXT PRIMARY KEY,
+    pool_id     TEXT NOT NULL,
+    position_id TEXT NOT NULL,
+    taken_at    TIMESTAMPTZ NOT NULL,
+    data        JSONB NOT NULL
+);
+CREATE INDEX position_snapshots_pool_idx ON position_snapshots (pool_id, taken_at);
+CREATE TABLE backfill_progress (
+    pool_id TEXT NOT NULL,
+    dataset TEXT NOT NULL,
+    data    JSONB NOT NULL,
+    PRIMARY KEY (pool_id, dataset)
+);
+"#,
+    },
+    Migration {
+        version: 5,
+        description: "derived metric time series",
+        sql: r#"
+CREATE TABLE metrics (
+    metric      TEXT NOT NULL,
+    subject     TEXT NOT NULL,
+    recorded_at TIMESTAMPTZ NOT NULL,
+    value       DOUBLE PRECISION NOT NULL,
+    PRIMARY KEY (metric, subject, recorded_at)
+);
+CREATE INDEX metrics_time_idx ON metrics (recorded_at);
+"#,
+    },
+    Migration {
+        version: 6,
+        description: "execution policy state",
+        sql: r#"
+-- A single row holding the execution policy's spend log and first-start time
+CREATE TABLE policy_state (
+    id         SMALLINT PRIMARY KEY CHECK (id = 1),
+    updated_at TIMESTAMPTZ NOT NULL,
+    data       JSONB NOT NULL
+);
+"#,
+    },
+    Migration {
+        version: 7,
+        description: "per-instance cycles, positions and snapshots",
+        sql: r#"
+-- Instances sharing the database keep their own positions, latest cycle and state
+-- snapshot; existing rows belong to the default instance
+ALTER TABLE cycles ADD COLUMN instance TEXT NOT NULL DEFAULT 'default';
+CREATE INDEX cycles_instance_idx ON cycles (instance, id DESC);
+
+ALTER TABLE positions ADD COLUMN instance TEXT NOT NULL DEFAULT 'default';
+ALTER TABLE positions DROP CONSTRAINT positions_pkey;
+ALTER TABLE positions ADD PRIMARY KEY (instance, id);
+
+ALTER TABLE snapshots ADD COLUMN instance TEXT NOT NULL DEFAULT 'default';
+ALTER TABLE snapshots DROP CONSTRAINT snapshots_pkey;
+ALTER TABLE snapshots DROP CONSTRAINT snapshots_id_check;
+ALTER TABLE snapshots ADD PRIMARY KEY (instance);
+"#,
+    },
+];
+
+/// Postgres storage shared between recommender instances
+pub struct PostgresStorage {
+    pool: PgPool,
+    /// Scopes the current positions, latest cycle and snapshot to this instance
+    instance: String,
+}
+
+impl PostgresStorage {
+    /// Connect and create the tables if they don't exist yet
+    pub async fn connect(url: &str, max_connections: u32, instance: &str) -> Result<Self> {
+        let pool = PgPoolOptions::new()
+            .max_connections(max_connections.max(1))
+            .connect(url)
+            .await
+            .context("connecting to postgres")?;
+        migrate(&pool, MIGRATIONS).await.context("migrating postgres schema")?;
+        Ok(Self {
+            pool,
+            instance: instance.to_string(),
+        })
+    }
+}
+
+/// Apply pending migrations in one transaction, holding an advisory lock so instances
+/// starting together don't race
+async fn migrate(pool: &PgPool, migrations: &[Migration]) -> Result<()> {
+    let mut tx = pool.begin().await?;
+    sqlx::query("SELECT pg_advisory_xact_lock($1)")
+        .bind(MIGRATION_LOCK_KEY)
+        .execute(&mut *tx)
+        .await?;
+    tx.execute(MIGRATIONS_TABLE).await?;
+    let applied: Vec<i64> = sqlx::query_scalar("SELECT version FROM schema_migrations")
+        .fetch_all(&mut *tx)
+        .await?;
+    for migration in pending(migrations, &applied)? {
+        tx.execute(migration.sql)
+            .await
+            .with_context(|| format!("applying migration {} ({})", migration.version, migration.description))?;
+        sqlx::query("INSERT INTO schema_migrations (version, description, applied_at) VALUES ($1, $2, $3)")
+            .bind(migration.version)
+            .bind(migration.description)
+            .bind(Utc::now())
+            .execute(&mut *tx)
+            .await?;
+        info!(target: "storage", backend = "postgres", version = migration.version, description = migration.description, "applied schema migration");
+    }
+    tx.commit().await?;
+    Ok(())
+}
+
+#[async_trait]
+impl Storage for PostgresStorage {
+    fn backend_name(&self) -> &'static str {
+        "postgres"
+    }
+
+    async fn record_cycle(&self, positions: &[Position], recommendations: &[PositionRecommendation]) -> Result<u64> {
+        let timestamp = Utc::now();
+        let mut tx = self.pool.begin().await?;
+        let cycle: i64 = sqlx::query_scalar("INSERT INTO cycles (instance, recorded_at) VALUES ($1, $2) RETURNING id")
+            .bind(&self.instance)
+            .bind(timestamp)
+            .fetch_one(&mut *tx)
+            .await?;
+
+        sqlx::query("DELETE FROM positions WHERE instance = $1")
+            .bind(&self.instance)
+            .execute(&mut *tx)
+            .await?;
+        for position in positions {
+            sqlx::query("INSERT INTO positions (instance, id, user_address, cycle_id, data) VALUES ($1, $2, $3, $4, $5)")
+                .bind(&self.instance)
+                .bind(&position.id)
+                .bind(&position.user_address)
+                .bind(cycle)
+                .bind(Json(position))
+                .execute(&mut *tx)
+                .await?;
+        }
+
+        for rec in recommendations {
+            sqlx::query(
+                "INSERT INTO recommendations (cycle_id, position_id, user_address, action, recorded_at, data) \
+                 VALUES ($1, $2, $3, $4, $5, $6)",
+            )
+            .bind(cycle)
+            .bind(&rec.position.id)
+            .bind(&rec.position.user_address)
+            .bind(rec.suggested_action.name())
+            .bind(timestamp)
+            .bind(Json(rec))
+            .execute(&mut *tx)
+            .await?;
+        }
+        tx.commit().await?;
+        Ok(cycle as u64)
+    }
+
+    async fn positions(&self) -> Result<Vec<Position>> {
+        let rows: Vec<Json<Position>> = sqlx::query_scalar("SELECT data FROM positions WHERE instance = $1 ORDER BY id")
+            .bind(&self.instance)
+            .fetch_all(&self.pool)
+            .await?;
+        Ok(rows.into_iter().map(|Json(p)| p).collect())
+    }
+
+    async fn latest(&self) -> Result<Vec<PositionRecommendation>> {
+        let rows: Vec<Json<PositionRecommendation>> = sqlx::query_scalar(
+            "SELECT data FROM recommendations WHERE cycle_id = (SELECT MAX(id) FROM cycles WHERE instance = $1) ORDER BY id",
+        )
+        .bind(&self.instance)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(rows.into_iter().map(|Json(r)| r).collect())
+    }
+
+    async fn history(&self, query: &HistoryQuery) -> Result<Vec<RecommendationRecord>> {
+        let wallets: Vec<String> = query.wallets.iter().map(|w| w.to_lowercase()).collect();
+        let rows = sqlx::query(
+            "SELECT cycle_id, recorded_at, data, \
+                    COALESCE((SELECT jsonb_agg(e.data ORDER BY e.submitted_at) FROM executions e \
+                              WHERE e.position_id = recommendations.position_id \
+                                AND e.cycle_id = recommendations.cycle_id), '[]'::jsonb) AS executions \
+             FROM recommendations \
+             WHERE ($1::TEXT IS NULL OR position_id = $1) \
+               AND (cardinality($2::TEXT[]) = 0 OR lower(user_address) = ANY($2)) \
+               AND ($3::TEXT IS NULL OR lower(user_address) = lower($3)) \
+               AND ($4::TEXT IS NULL OR action = $4) \
+               AND ($5::TEXT IS NULL OR lower(data->'position'->>'token_address') = lower($5)) \
+               AND ($6::TIMESTAMPTZ IS NULL OR recorded_at >= $6) \
+               AND ($7::TIMESTAMPTZ IS NULL OR recorded_at < $7) \
+             ORDER BY id DESC LIMIT $8 OFFSET $9",
+        )
+        .bind(query.position_id.as_deref())
+        .bind(&wallets)
+        .bind(query.wallet.as_deref())
+        .bind(query.action.as_ref().map(Action::name))
+        .bind(query.token_address.as_deref())
+        .bind(query.since)
+        .bind(query.until)
+        .bind(query.limit as i64)
+        .bind(query.offset as i64)
+        .fetch_all(&self.pool)
+        .await?;
+        rows.into_iter()
+            .map(|row| {
+                let Json(recommendation): Json<PositionRecommendation> = row.try_get("data")?;
+                let Json(executions): Json<Vec<TrackedTransaction>> = row.try_get("executions")?;
+                Ok(RecommendationRecord {
+                    cycle: row.try_get::<i64, _>("cycle_id")? as u64,
+                    timestamp: row.try_get("recorded_at")?,
+                    recommendation,
+                    executions,
+                })
+            })
+            .collect()
+    }
+
+    async fn save_candles(&self, candles: &[Candle]) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+        for candle in candles {
+            sqlx::query(
+                "INSERT INTO candles (pool_id, interval_secs, start_time, open, high, low, close, volume) \
+                 VALUES ($1, $2, $3, $4, $5, $6, $7, $8) \
+                 ON CONFLICT (pool_id, interval_secs, start_time) DO UPDATE SET \
+                 open = EXCLUDED.open, high = EXCLUDED.high, low = EXCLUDED.low, \
+                 close = EXCLUDED.close, volume = EXCLUDED.volume",
+            )
+            .bind(&candle.pool_id)
+            .bind(candle.interval_secs as i32)
+            .bind(candle.start)
+            .bind(candle.open)
+            .bind(candle.high)
+            .bind(candle.low)
+            .bind(candle.close)
+            .bind(candle.volume)
+            .execute(&mut *tx)
+            .await?;
+        }
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn candles(&self, pool_id: &str, interval_secs: u32, since: DateTime<Utc>) -> Result<Vec<Candle>> {
+        let rows = sqlx::query(
+            "SELECT start_time, open, high, low, close, volume FROM candles \
+             WHERE pool_id = $1 AND interval_secs = $2 AND start_time >= $3 ORDER BY start_time",
+        )
+        .bind(pool_id)
+        .bind(interval_secs as i32)
+        .bind(since)
+        .fetch_all(&self.pool)
+        .await?;
+        rows.into_iter()
+            .map(|row| {
+                Ok(Candle {
+                    pool_id: pool_id.to_string(),
+                    interval_secs,
+                    start: row.try_get("start_time")?,
+                    open: row.try_get("open")?,
+                    high: row.try_get("high")?,
+                    low: row.try_get("low")?,
+                    close: row.try_get("close")?,
+                    volume: row.try_get("volume")?,
+                })
+            })
+            .collect()
+    }
+
+    async fn save_swaps(&self, swaps: &[SwapRecord]) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+        for swap in swaps {
+            sqlx::query("INSERT INTO swaps (id, pool_id, swapped_at, data) VALUES ($1, $2, $3, $4) ON CONFLICT (id) DO NOTHING")
+                .bind(&swap.id)
+                .bind(&swap.pool_id)
+                .bind(swap.timestamp)
+                .bind(Json(swap))
+                .execute(&mut *tx)
+                .await?;
+        }
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn swaps(&self, pool_id: &str, since: DateTime<Utc>, limit: usize) -> Result<Vec<SwapRecord>> {
+        let rows: Vec<Json<SwapRecord>> =
+            sqlx::query_scalar("SELECT data FROM swaps WHERE pool_id = $1 AND swapped_at >= $2 ORDER BY swapped_at, id LIMIT $3")
+                .bind(pool_id)
+                .bind(since)
+                .bind(i64::try_from(limit)?)
+                .fetch_all(&self.pool)
+                .await?;
+        Ok(rows.into_iter().map(|Json(s)| s).collect())
+    }
+
+    async fn save_position_snapshots(&self, snapshots: &[PositionSnapshotRecord]) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+        for snapshot in snapshots {
+            sqlx::query(
+                "INSERT INTO position_snapshots (id, pool_id, position_id, taken_at, data) VALUES ($1, $2, $3, $4, $5) \
+                 ON CONFLICT (id) DO NOTHING",
+            )
+            .bind(&snapshot.id)
+            .bind(&snapshot.pool_id)
+            .bind(&snapshot.position_id)
+            .bind(snapshot.timestamp)
+            .bind(Json(snapshot))
+            .execute(&mut *tx)
+            .await?;
+        }
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn position_snapshots(&self, pool_id: &str, since: DateTime<Utc>, limit: usize) -> Result<Vec<PositionSnapshotRecord>> {
+        let rows: Vec<Json<PositionSnapshotRecord>> = sqlx::query_scalar(
+            "SELECT data FROM position_snapshots WHERE pool_id = $1 AND taken_at >= $2 ORDER BY taken_at, id LIMIT $3",
+        )
+        .bind(pool_id)
+        .bind(since)
+        .bind(i64::try_from(limit)?)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(rows.into_iter().map(|Json(s)| s).collect())
+    }
+
+    async fn save_backfill_progress(&self, progress: &BackfillProgress) -> Result<()> {
+        sqlx::query(
+            "INSERT INTO backfill_progress (pool_id, dataset, data) VALUES ($1, $2, $3) \
+             ON CONFLICT (pool_id, dataset) DO UPDATE SET data = EXCLUDED.data",
+        )
+        .bind(&progress.pool_id)
+        .bind(&progress.dataset)
+        .bind(Json(progress))
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn backfill_progress(&self, pool_id: &str) -> Result<Vec<BackfillProgress>> {
+        let rows: Vec<Json<BackfillProgress>> = sqlx::query_scalar("SELECT data FROM backfill_progress WHERE pool_id = $1")
+            .bind(pool_id)
+            .fetch_all(&self.pool)
+            .await?;
+        Ok(rows.into_iter().map(|Json(p)| p).collect())
+    }
+
+    async fn save_quote(&self, quote: &PoolQuote) -> Result<()> {
+        sqlx::query(
+            "INSERT INTO quotes (pool_id, quoted_at, data) VALUES ($1, $2, $3) \
+             ON CONFLICT (pool_id, quoted_at) DO UPDATE SET data = EXCLUDED.data",
+        )
+        .bind(&quote.pool_id)
+        .bind(quote.timestamp)
+        .bind(Json(quote))
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn quotes(&self, pool_id: &str, since: DateTime<Utc>) -> Result<Vec<PoolQuote>> {
+        let rows: Vec<Json<PoolQuote>> = sqlx::query_scalar(
+            "SELECT data FROM quotes WHERE pool_id = $1 AND quoted_at >= $2 ORDER BY quoted_at",
+        )
+        .bind(pool_id)
+        .bind(since)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(rows.into_iter().map(|Json(q)| q).collect())
+    }
+
+    async fn save_alert(&self, event: &Event) -> Result<()> {
+        sqlx::query("INSERT INTO alerts (kind, position_id, created_at, data) VALUES ($1, $2, $3, $4)")
+            .bind(event.kind())
+            .bind(event.position_id())
+            .bind(event.timestamp())
+            .bind(Json(event))
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    async fn alerts(&self, query: &AlertQuery) -> Result<Vec<Event>> {
+        let rows: Vec<Json<Event>> = sqlx::query_scalar(
+            "SELECT data FROM alerts \
+             WHERE ($1::TEXT IS NULL OR position_id = $1) AND ($2::TEXT IS NULL OR kind = $2) \
+               AND ($3::TIMESTAMPTZ IS NULL OR created_at >= $3) \
+             ORDER BY id DESC LIMIT $4",
+        )
+        .bind(query.position_id.as_deref())
+        .bind(query.kind.as_deref())
+        .bind(query.since)
+        .bind(query.limit as i64)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(rows.into_iter().map(|Json(e)| e).collect())
+    }
+
+    async fn save_metrics(&self, points: &[MetricPoint]) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+        for point in points {
+            sqlx::query(
+                "INSERT INTO metrics (metric, subject, recorded_at, value) VALUES ($1, $2, $3, $4) \
+                 ON CONFLICT (metric, subject, recorded_at) DO UPDATE SET value = EXCLUDED.value",
+            )
+            .bind(point.metric.as_str())
+            .bind(point.subject.to_lowercase())
+            .bind(point.timestamp)
+            .bind(point.value)
+            .execute(&mut *tx)
+            .await?;
+        }
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn metrics(&self, query: &MetricQuery) -> Result<Vec<MetricPoint>> {
+        let rows = sqlx::query(
+            "SELECT metric, subject, recorded_at, value FROM metrics \
+             WHERE ($1::TEXT IS NULL OR metric = $1) AND ($2::TEXT IS NULL OR subject = $2) \
+               AND ($3::TIMESTAMPTZ IS NULL OR recorded_at >= $3) AND ($4::TIMESTAMPTZ IS NULL OR recorded_at < $4) \
+             ORDER BY recorded_at, metric, subject LIMIT $5",
+        )
+        .bind(query.metric.map(|m| m.as_str()))
+        .bind(query.subject.as_deref().map(str::to_lowercase))
+        .bind(query.since)
+        .bind(query.until)
+        .bind(i64::try_from(query.limit)?)
+        .fetch_all(&self.pool)
+        .await?;
+        rows.into_iter()
+            .map(|row| {
+                let metric: String = row.try_get("metric")?;
+                Ok(MetricPoint {
+                    metric: metric.parse()?,
+                    subject: row.try_get("subject")?,
+                    timestamp: row.try_get("recorded_at")?,
+                    value: row.try_get("value")?,
+                })
+            })
+            .collect()
+    }
+
+    async fn save_rebalance(&self, workflow: &RebalanceWorkflow) -> Result<()> {
+        sqlx::query(
+            "INSERT INTO rebalances (id, position_id, status, created_at, updated_at, data) \
+             VALUES ($1, $2, $3, $4, $5, $6) \
+             ON CONFLICT (id) DO UPDATE SET \
+             status = EXCLUDED.status, updated_at = EXCLUDED.updated_at, data = EXCLUDED.data",
+        )
+        .bind(&workflow.id)
+        .bind(&workflow.position_id)
+        .bind(workflow.status.as_str())
+        .bind(workflow.created_at)
+        .bind(workflow.updated_at)
+        .bind(Json(workflow))
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn rebalances(&self, query: &RebalanceQuery) -> Result<Vec<RebalanceWorkflow>> {
+        let rows: Vec<Json<RebalanceWorkflow>> = sqlx::query_scalar(
+            "SELECT data FROM rebalances \
+             WHERE ($1::TEXT IS NULL OR position_id = $1) AND ($2::TEXT IS NULL OR status = $2) \
+             ORDER BY created_at DESC LIMIT $3",
+        )
+        .bind(query.position_id.as_deref())
+        .bind(query.status.map(|s| s.as_str()))
+        .bind(query.limit as i64)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(rows.into_iter().map(|Json(w)| w).collect())
+    }
+
+    async fn save_execution(&self, tx: &TrackedTransaction) -> Result<()> {
+        sqlx::query(
+            "INSERT INTO executions (tx_hash, position_id, cycle_id, finalized, submitted_at, data) \
+             VALUES ($1, $2, $3, $4, $5, $6) \
+             ON CONFLICT (tx_hash) DO UPDATE SET finalized = EXCLUDED.finalized, data = EXCLUDED.data",
+        )
+        .bind(&tx.tx_hash)
+        .bind(tx.position_id.as_deref())
+        .bind(tx.cycle.map(|c| c as i64))
+        .bind(tx.finalized)
+        .bind(tx.submitted_at)
+        .bind(Json(tx))
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn executions(&self, query: &ExecutionQuery) -> Result<Vec<TrackedTransaction>> {
+        let rows: Vec<Json<TrackedTransaction>> = sqlx::query_scalar(
+            "SELECT data FROM executions \
+             WHERE ($1::TEXT IS NULL OR position_id = $1) AND (NOT $2 OR NOT finalized) \
+             ORDER BY submitted_at DESC LIMIT $3",
+        )
+        .bind(query.position_id.as_deref())
+        .bind(query.unsettled)
+        .bind(query.limit as i64)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(rows.into_iter().map(|Json(t)| t).collect())
+    }
+
+    async fn save_paper_wallet(&self, wallet: &PaperWallet) -> Result<()> {
+        sqlx::query(
+            "INSERT INTO paper_wallets (name, updated_at, data) VALUES ($1, $2, $3) \
+             ON CONFLICT (name) DO UPDATE SET updated_at = EXCLUDED.updated_at, data = EXCLUDED.data",
+        )
+        .bind(&wallet.name)
+        .bind(wallet.updated_at)
+        .bind(Json(wallet))
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn paper_wallet(&self, name: &str) -> Result<Option<PaperWallet>> {
+        let row: Option<Json<PaperWallet>> = sqlx::query_scalar("SELECT data FROM paper_wallets WHERE name = $1")
+            .bind(name)
+            .fetch_optional(&self.pool)
+            .await?;
+        Ok(row.map(|Json(w)| w))
+    }
+
+    async fn save_policy_state(&self, state: &PolicyState) -> Result<()> {
+        sqlx::query(
+            "INSERT INTO policy_state (id, updated_at, data) VALUES (1, $1, $2) \
+             ON CONFLICT (id) DO UPDATE SET updated_at = EXCLUDED.updated_at, data = EXCLUDED.data",
+        )
+        .bind(Utc::now())
+        .bind(Json(state))
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn policy_state(&self) -> Result<Option<PolicyState>> {
+        let row: Option<Json<PolicyState>> = sqlx::query_scalar("SELECT data FROM policy_state WHERE id = 1")
+            .fetch_optional(&self.pool)
+            .await?;
+        Ok(row.map(|Json(s)| s))
+    }
+
+    async fn save_ledger_entry(&self, entry: &LedgerEntry) -> Result<()> {
+        sqlx::query("INSERT INTO pnl_ledger (position_id, owner, kind, recorded_at, data) VALUES ($1, $2, $3, $4, $5)")
+            .bind(&entry.position_id)
+            .bind(entry.owner.to_lowercase())
+            .bind(entry.kind.as_str())
+            .bind(entry.timestamp)
+            .bind(Json(entry))
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    async fn ledger(&self, query: &LedgerQuery) -> Result<Vec<LedgerEntry>> {
+        let rows: Vec<Json<LedgerEntry>> = sqlx::query_scalar(
+            "SELECT data FROM pnl_ledger \
+             WHERE ($1::TEXT IS NULL OR position_id = $1) AND ($2::TEXT IS NULL OR owner = $2) \
+               AND ($3::TEXT IS NULL OR kind = $3) AND ($4::TIMESTAMPTZ IS NULL OR recorded_at >= $4) \
+               AND ($5::TIMESTAMPTZ IS NULL OR recorded_at < $5) \
+             ORDER BY recorded_at, id",
+        )
+        .bind(query.position_id.as_deref())
+        .bind(query.owner.as_deref().map(str::to_lowercase))
+        .bind(query.kind.map(|k| k.as_str()))
+        .bind(query.since)
+        .bind(query.until)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(rows.into_iter().map(|Json(e)| e).collect())
+    }
+
+    async fn save_snapshot(&self, snapshot: &StateSnapshot) -> Result<()> {
+        sqlx::query(
+            "INSERT INTO snapshots (id, instance, taken_at, data) VALUES (1, $1, $2, $3) \
+             ON CONFLICT (instance) DO UPDATE SET taken_at = EXCLUDED.taken_at, data = EXCLUDED.data",
+        )
+        .bind(&self.instance)
+        .bind(snapshot.taken_at)
+        .bind(Json(snapshot))
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn latest_snapshot(&self) -> Result<Option<StateSnapshot>> {
+        let row: Option<Json<StateSnapshot>> = sqlx::query_scalar("SELECT data FROM snapshots WHERE instance = $1")
+            .bind(&self.instance)
+            .fetch_optional(&self.pool)
+            .await?;
+        Ok(row.map(|Json(s)| s))
+    }
+
+    async fn append_audit(&self, record: &AuditRecord) -> Result<()> {
+        sqlx::query(
+            "INSERT INTO audit_log (seq, recorded_at, kind, position_id, hash, data) VALUES ($1, $2, $3, $4, $5, $6)",
+        )
+        .bind(i64::try_from(record.seq)?)
+        .bind(record.timestamp)
+        .bind(&record.kind)
+        .bind(record.position_id.as_deref())
+        .bind(&record.hash)
+        .bind(serde_json::to_string(record)?)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn audit_records(&self, after_seq: u64, limit: usize) -> Result<Vec<AuditRecord>> {
+        let rows: Vec<String> = sqlx::query_scalar("SELECT data FROM audit_log WHERE seq > $1 ORDER BY seq LIMIT $2")
+            .bind(i64::try_from(after_seq)?)
+            .bind(i64::try_from(limit)?)
+            .fetch_all(&self.pool)
+            .await?;
+        rows.iter().map(|data| serde_json::from_str(data).context("decoding audit record")).collect()
+    }
+
+    async fn latest_audit(&self) -> Result<Option<AuditRecord>> {
+        let row: Option<String> = sqlx::query_scalar("SELECT data FROM audit_log ORDER BY seq DESC LIMIT 1")
+            .fetch_optional(&self.pool)
+            .await?;
+        row.map(|data| serde_json::from_str(&data).context("decoding audit record")).transpose()
+    }
+}
@@ -0,0 +1,1032 @@
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use sqlx::sqlite::{SqliteConnectOptions, SqliteJournalMode, SqlitePool, SqlitePoolOptions};
+use sqlx::types::Json;
+use sqlx::{Executor, Row};
+use std::time::Duration;
+use tracing::info;
+
+use super::migrations::{pending, Migration};
+use super::{AlertQuery, Candle, HistoryQuery, PoolQuote, RecommendationRecord, StateSnapshot, Storage};
+use crate::archive::SwapRecord;
+use crate::audit::AuditRecord;
+use crate::backfill::{BackfillProgress, PositionSnapshotRecord};
+use crate::events::Event;
+use crate::executor::{ExecutionQuery, PaperWallet, PolicyState, RebalanceQuery, RebalanceWorkflow, TrackedTransaction};
+use crate::metrics::{MetricPoint, MetricQuery};
+use crate::pnl::{LedgerEntry, LedgerQuery};
+use crate::position::{Action, Position, PositionRecommendation};
+
+// Timestamps are stored as RFC 3339 text in UTC, which sorts chronologically
+const MIGRATIONS_TABLE: &str = r#"
+CREATE TABLE IF NOT EXISTS schema_migrations (
+    version     BIGINT PRIMARY KEY,
+    description TEXT NOT NULL,
+    applied_at  TEXT NOT NULL
+);
+"#;
+
+/// Append-only; see `migrations`. Version 1 uses `IF NOT EXISTS` so databases created
+/// before migrations were tracked are adopted as-is.
+const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        description: "initial schema",
+        sql: r#"
+CREATE TABLE IF NOT EXISTS cycles (
+    id          INTEGER PRIMARY KEY AUTOINCREMENT,
+    recorded_at TEXT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS positions (
+    id           TEXT PRIMARY KEY,
+    user_address TEXT NOT NULL,
+    cycle_id     INTEGER NOT NULL REFERENCES cycles (id),
+    data         TEXT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS recommendations (
+    id           INTEGER PRIMARY KEY AUTOINCREMENT,
+    cycle_id     INTEGER NOT NULL REFERENCES cycles (id),
+    position_id  TEXT NOT NULL,
+    user_address TEXT NOT NULL,
+    action       TEXT NOT NULL,
+    recorded_at  TEXT NOT NULL,
+    data         TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS recommendations_cycle_idx ON recommendations (cycle_id);
+CREATE INDEX IF NOT EXISTS recommendations_position_idx ON recommendations (position_id, id DESC);
+
+CREATE TABLE IF NOT EXISTS quotes (
+    pool_id   TEXT NOT NULL,
+    quoted_at TEXT NOT NULL,
+    data      TEXT NOT NULL,
+    PRIMARY KEY (pool_id, quoted_at)
+);
+
+CREATE TABLE IF NOT EXISTS candles (
+    pool_id       TEXT NOT NULL,
+    interval_secs INTEGER NOT NULL,
+    start_time    TEXT NOT NULL,
+    open          REAL NOT NULL,
+    high          REAL NOT NULL,
+    low           REAL NOT NULL,
+    close         REAL NOT NULL,
+    volume        REAL NOT NULL,
+    PRIMARY KEY (pool_id, interval_secs, start_time)
+);
+
+CREATE TABLE IF NOT EXISTS alerts (
+    id          INTEGER PRIMARY KEY AUTOINCREMENT,
+    kind        TEXT NOT NULL,
+    position_id TEXT NOT NULL,
+    created_at  TEXT NOT NULL,
+    data        TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS alerts_position_idx ON alerts (position_id, id DESC);
+
+CREATE TABLE IF NOT EXISTS rebalances (
+    id          TEXT PRIMARY KEY,
+    position_id TEXT NOT NULL,
+    status      TEXT NOT NULL,
+    created_at  TEXT NOT NULL,
+    updated_at  TEXT NOT NULL,
+    data        TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS rebalances_position_idx ON rebalances (position_id, created_at DESC);
+
+CREATE TABLE IF NOT EXISTS executions (
+    tx_hash      TEXT PRIMARY KEY,
+    position_id  TEXT,
+    cycle_id     INTEGER,
+    finalized    INTEGER NOT NULL,
+    submitted_at TEXT NOT NULL,
+    data         TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS executions_recommendation_idx ON executions (position_id, cycle_id);
+CREATE INDEX IF NOT EXISTS executions_unsettled_idx ON executions (submitted_at) WHERE NOT finalized;
+
+CREATE TABLE IF NOT EXISTS paper_wallets (
+    name       TEXT PRIMARY KEY,
+    updated_at TEXT NOT NULL,
+    data       TEXT NOT NULL
+);
+
+-- A single row holding the latest recommender state snapshot
+CREATE TABLE IF NOT EXISTS snapshots (
+    id       INTEGER PRIMARY KEY CHECK (id = 1),
+    taken_at TEXT NOT NULL,
+    data     TEXT NOT NULL
+);
+"#,
+    },
+    Migration {
+        version: 2,
+        description: "pnl ledger",
+        sql: r#"
+CREATE TABLE pnl_ledger (
+    id          INTEGER PRIMARY KEY AUTOINCREMENT,
+    position_id TEXT NOT NULL,
+    owner       TEXT NOT NULL,
+    kind        TEXT NOT NULL,
+    recorded_at TEXT NOT NULL,
+    data        TEXT NOT NULL
+);
+CREATE INDEX pnl_ledger_position_idx ON pnl_ledger (position_id, recorded_at);
+CREATE INDEX pnl_ledger_owner_idx ON pnl_ledger (owner, recorded_at);
+"#,
+    },
+    Migration {
+        version: 3,
+        description: "audit log",
+        // `data` holds the record as serialized text so it reads back byte for byte
+        sql: r#"
+CREATE TABLE audit_log (
+    seq         BIGINT PRIMARY KEY,
+    recorded_at TEXT NOT NULL,
+    kind        TEXT NOT NULL,
+    position_id TEXT,
+    hash        TEXT NOT NULL,
+    data        TEXT NOT NULL
+);
+CREATE TRIGGER audit_log_no_update BEFORE UPDATE ON audit_log
+BEGIN
+    SELECT RAISE(ABORT, 'audit_log is append-only');
+END;
+CREATE TRIGGER audit_log_no_delete BEFORE DELETE ON audit_log
+BEGIN
+    SELECT RAISE(ABORT, 'audit_log is append-only');
+END;
+"#,
+    },
+    Migration {
+        version: 4,
+        description: "backfilled swaps and position snapshots",
+        sql: r#"
+CREATE TABLE swaps (
+    id         TEXT PRIMARY KEY,
+    pool_id    TEXT NOT NULL,
+    swapped_at TEXT NOT NULL,
+    data       TEXT NOT NULL
+);
+CREATE INDEX swaps_pool_idx ON swaps (pool_id, swapped_at);
+CREATE TABLE position_snapshots (
+    id          TEXT PRIMARY KEY,
+    pool_id     TEXT NOT NULL,
+    position_id TEXT NOT NULL,
+    taken_at    TEXT NOT NULL,
+    data        TEXT NOT NULL
+);
+CREATE INDEX position_snapshots_pool_idx ON position_snapshots (pool_id, taken_at);
+CREATE TABLE backfill_progress (
+    pool_id TEXT NOT NULL,
+    dataset TEXT NOT NULL,
+    data    TEXT NOT NULL,
+    PRIMARY KEY (pool_id, dataset)
+);
+"#,
+    },
+    Migration {
+        version: 5,
+        description: "derived metric time series",
+        sql: r#"
+CREATE TABLE metrics (
+    metric      TEXT NOT NULL,
+    subject     TEXT NOT NULL,
+    recorded_at TEXT NOT NULL,
+    value       REAL NOT NULL,
+    PRIMARY KEY (metric, subject, recorded_at)
+);
+CREATE INDEX metrics_time_idx ON metrics (recorded_at);
+"#,
+    },
+    Migration {
+        version: 6,
+        description: "execution policy state",
+        sql: r#"
+-- A single row holding the execution policy's spend log and first-start time
+CREATE TABLE policy_state (
+    id         SMALLINT PRIMARY KEY CHECK (id = 1),
+    updated_at TEXT NOT NULL,
+    data       TEXT NOT NULL
+);
+"#,
+    },
+];
+
+/// Embedded SQLite storage for a single instance; state survives restarts
+pub struct SqliteStorage {
+    pool: SqlitePool,
+}
+
+impl SqliteStorage {
+    /// Open (creating if missing) the database file and its tables
+    pub async fn connect(path: &str, max_connections: u32) -> Result<Self> {
+        let options = SqliteConnectOptions::new()
+            .filename(path)
+            .create_if_missing(true)
+            .journal_mode(SqliteJournalMode::Wal)
+            .busy_timeout(Duration::from_secs(5))
+            .foreign_keys(true);
+        let pool = SqlitePoolOptions::new()
+            .max_connections(max_connections.max(1))
+            .connect_with(options)
+            .await
+            .with_context(|| format!("opening sqlite database {}", path))?;
+        migrate(&pool, MIGRATIONS).await.context("migrating sqlite schema")?;
+        Ok(Self { pool })
+    }
+}
+
+/// Apply pending migrations in one transaction
+async fn migrate(pool: &SqlitePool, migrations: &[Migration]) -> Result<()> {
+    let mut tx = pool.begin().await?;
+    tx.execute(MIGRATIONS_TABLE).await?;
+    let applied: Vec<i64> = sqlx::query_scalar("SELECT version FROM schema_migrations")
+        .fetch_all(&mut *tx)
+        .await?;
+    for migration in pending(migrations, &applied)? {
+        tx.execute(migration.sql)
+            .await
+            .with_context(|| format!("applying migration {} ({})", migration.version, migration.description))?;
+        sqlx::query("INSERT INTO schema_migrations (version, description, applied_at) VALUES (?1, ?2, ?3)")
+            .bind(migration.version)
+            .bind(migration.description)
+            .bind(Utc::now())
+            .execute(&mut *tx)
+            .await?;
+        info!(target: "storage", backend = "sqlite", version = migration.version, description = migration.description, "applied schema migration");
+    }
+    tx.commit().await?;
+    Ok(())
+}
+
+#[async_trait]
+impl Storage for SqliteStorage {
+    fn backend_name(&self) -> &'static str {
+        "sqlite"
+    }
+
+    async fn record_cycle(&self, positions: &[Position], recommendations: &[PositionRecommendation]) -> Result<u64> {
+        let timestamp = Utc::now();
+        let mut tx = self.pool.begin().await?;
+        let cycle: i64 = sqlx::query_scalar("INSERT INTO cycles (recorded_at) VALUES (?1) RETURNING id")
+            .bind(timestamp)
+            .fetch_one(&mut *tx)
+            .await?;
+
+        sqlx::query("DELETE FROM positions").execute(&mut *tx).await?;
+        for position in positions {
+            sqlx::query("INSERT INTO positions (id, user_address, cycle_id, data) VALUES (?1, ?2, ?3, ?4)")
+                .bind(&position.id)
+                .bind(&position.user_address)
+                .bind(cycle)
+                .bind(Json(position))
+                .execute(&mut *tx)
+                .await?;
+        }
+
+        for rec in recommendations {
+            sqlx::query(
+                "INSERT INTO recommendations (cycle_id, position_id, user_address, action, recorded_at, data) \
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            )
+            .bind(cycle)
+            .bind(&rec.position.id)
+            .bind(&rec.position.user_address)
+            .bind(rec.suggested_action.name())
+            .bind(timestamp)
+            .bind(Json(rec))
+            .execute(&mut *tx)
+            .await?;
+        }
+        tx.commit().await?;
+        Ok(cycle as u64)
+    }
+
+    async fn positions(&self) -> Result<Vec<Position>> {
+        let rows: Vec<Json<Position>> = sqlx::query_scalar("SELECT data FROM positions ORDER BY id")
+            .fetch_all(&self.pool)
+            .await?;
+        Ok(rows.into_iter().map(|Json(p)| p).collect())
+    }
+
+    async fn latest(&self) -> Result<Vec<PositionRecommendation>> {
+        let rows: Vec<Json<PositionRecommendation>> = sqlx::query_scalar(
+            "SELECT data FROM recommendations WHERE cycle_id = (SELECT MAX(id) FROM cycles) ORDER BY id",
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(rows.into_iter().map(|Json(r)| r).collect())
+    }
+
+    async fn history(&self, query: &HistoryQuery) -> Result<Vec<RecommendationRecord>> {
+        let wallets: Vec<String> = query.wallets.iter().map(|w| w.to_lowercase()).collect();
+        let rows = sqlx::query(
+            "SELECT cycle_id, recorded_at, data, \
+                    (SELECT json_group_array(json(e.data)) FROM \
+                       (SELECT data FROM executions \
+                        WHERE position_id = recommendations.position_id \
+                          AND cycle_id = recommendations.cycle_id \
+                        ORDER BY submitted_at) e) AS executions \
+             FROM recommendations \
+             WHERE (?1 IS NULL OR position_id = ?1) \
+               AND (json_array_length(?2) = 0 OR lower(user_address) IN (SELECT value FROM json_each(?2))) \
+               AND (?3 IS NULL OR lower(user_address) = lower(?3)) \
+               AND (?4 IS NULL OR action = ?4) \
+               AND (?5 IS NULL OR lower(json_extract(data, '$.position.token_address')) = lower(?5)) \
+               AND (?6 IS NULL OR recorded_at >= ?6) \
+               AND (?7 IS NULL OR recorded_at < ?7) \
+             ORDER BY id DESC LIMIT ?8 OFFSET ?9",
+        )
+        .bind(query.position_id.as_deref())
+        .bind(Json(&wallets))
+        .bind(query.wallet.as_deref())
+        .bind(query.action.as_ref().map(Action::name))
+        .bind(query.token_address.as_deref())
+        .bind(query.since)
+        .bind(query.until)
+        .bind(query.limit as i64)
+        .bind(query.offset as i64)
+        .fetch_all(&self.pool)
+        .await?;
+        rows.into_iter()
+            .map(|row| {
+                let Json(recommendation): Json<PositionRecommendation> = row.try_get("data")?;
+                let Json(executions): Json<Vec<TrackedTransaction>> = row.try_get("executions")?;
+                Ok(RecommendationRecord {
+                    cycle: row.try_get::<i64, _>("cycle_id")? as u64,
+                    timestamp: row.try_get("recorded_at")?,
+                    recommendation,
+                    executions,
+                })
+            })
+            .collect()
+    }
+
+    async fn save_candles(&self, candles: &[Candle]) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+        for candle in candles {
+            sqlx::query(
+                "INSERT INTO candles (pool_id, interval_secs, start_time, open, high, low, close, volume) \
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8) \
+                 ON CONFLICT (pool_id, interval_secs, start_time) DO UPDATE SET \
+                 open = excluded.open, high = excluded.high, low = excluded.low, \
+                 close = excluded.close, volume = excluded.volume",
+            )
+            .bind(&candle.pool_id)
+            .bind(candle.interval_secs as i64)
+            .bind(candle.start)
+            .bind(candle.open)
+            .bind(candle.high)
+            .bind(candle.low)
+            .bind(candle.close)
+            .bind(candle.volume)
+            .execute(&mut *tx)
+            .await?;
+        }
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn candles(&self, pool_id: &str, interval_secs: u32, since: DateTime<Utc>) -> Result<Vec<Candle>> {
+        let rows = sqlx::query(
+            "SELECT start_time, open, high, low, close, volume FROM candles \
+             WHERE pool_id = ?1 AND interval_secs = ?2 AND start_time >= ?3 ORDER BY start_time",
+        )
+        .bind(pool_id)
+        .bind(interval_secs as i64)
+        .bind(since)
+        .fetch_all(&self.pool)
+        .await?;
+        rows.into_iter()
+            .map(|row| {
+                Ok(Candle {
+                    pool_id: pool_id.to_string(),
+                    interval_secs,
+                    start: row.try_get("start_time")?,
+                    open: row.try_get("open")?,
+                    high: row.try_get("high")?,
+                    low: row.try_get("low")?,
+                    close: row.try_get("close")?,
+                    volume: row.try_get("volume")?,
+                })
+            })
+            .collect()
+    }
+
+    async fn save_swaps(&self, swaps: &[SwapRecord]) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+        for swap in swaps {
+            sqlx::query("INSERT INTO swaps (id, pool_id, swapped_at, data) VALUES (?1, ?2, ?3, ?4) ON CONFLICT (id) DO NOTHING")
+                .bind(&swap.id)
+                .bind(&swap.pool_id)
+                .bind(swap.timestamp)
+                .bind(Json(swap))
+                .execute(&mut *tx)
+                .await?;
+        }
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn swaps(&self, pool_id: &str, since: DateTime<Utc>, limit: usize) -> Result<Vec<SwapRecord>> {
+        let rows: Vec<Json<SwapRecord>> =
+            sqlx::query_scalar("SELECT data FROM swaps WHERE pool_id = ?1 AND swapped_at >= ?2 ORDER BY swapped_at, id LIMIT ?3")
+                .bind(pool_id)
+                .bind(since)
+                .bind(i64::try_from(limit)?)
+                .fetch_all(&self.pool)
+                .await?;
+        Ok(rows.into_iter().map(|Json(s)| s).collect())
+    }
+
+    async fn save_position_snapshots(&self, snapshots: &[PositionSnapshotRecord]) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+        for snapshot in snapshots {
+            sqlx::query(
+                "INSERT INTO position_snapshots (id, pool_id, position_id, taken_at, data) VALUES (?1, ?2, ?3, ?4, ?5) \
+                 ON CONFLICT (id) DO NOTHING",
+            )
+            .bind(&snapshot.id)
+            .bind(&snapshot.pool_id)
+            .bind(&snapshot.position_id)
+            .bind(snapshot.timestamp)
+            .bind(Json(snapshot))
+            .execute(&mut *tx)
+            .await?;
+        }
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn position_snapshots(&self, pool_id: &str, since: DateTime<Utc>, limit: usize) -> Result<Vec<PositionSnapshotRecord>> {
+        let rows: Vec<Json<PositionSnapshotRecord>> = sqlx::query_scalar(
+            "SELECT data FROM position_snapshots WHERE pool_id = ?1 AND taken_at >= ?2 ORDER BY taken_at, id LIMIT ?3",
+        )
+        .bind(pool_id)
+        .bind(since)
+        .bind(i64::try_from(limit)?)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(rows.into_iter().map(|Json(s)| s).collect())
+    }
+
+    async fn save_backfill_progress(&self, progress: &BackfillProgress) -> Result<()> {
+        sqlx::query(
+            "INSERT INTO backfill_progress (pool_id, dataset, data) VALUES (?1, ?2, ?3) \
+             ON CONFLICT (pool_id, dataset) DO UPDATE SET data = excluded.data",
+        )
+        .bind(&progress.pool_id)
+        .bind(&progress.dataset)
+        .bind(Json(progress))
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn backfill_progress(&self, pool_id: &str) -> Result<Vec<BackfillProgress>> {
+        let rows: Vec<Json<BackfillProgress>> = sqlx::query_scalar("SELECT data FROM backfill_progress WHERE pool_id = ?1")
+            .bind(pool_id)
+            .fetch_all(&self.pool)
+            .await?;
+        Ok(rows.into_iter().map(|Json(p)| p).collect())
+    }
+
+    async fn save_quote(&self, quote: &PoolQuote) -> Result<()> {
+        sqlx::query(
+            "INSERT INTO quotes (pool_id, quoted_at, data) VALUES (?1, ?2, ?3) \
+             ON CONFLICT (pool_id, quoted_at) DO UPDATE SET data = excluded.data",
+        )
+        .bind(&quote.pool_id)
+        .bind(quote.timestamp)
+        .bind(Json(quote))
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn quotes(&self, pool_id: &str, since: DateTime<Utc>) -> Result<Vec<PoolQuote>> {
+        let rows: Vec<Json<PoolQuote>> = sqlx::query_scalar(
+            "SELECT data FROM quotes WHERE pool_id = ?1 AND quoted_at >= ?2 ORDER BY quoted_at",
+        )
+        .bind(pool_id)
+        .bind(since)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(rows.into_iter().map(|Json(q)| q).collect())
+    }
+
+    async fn save_alert(&self, event: &Event) -> Result<()> {
+        sqlx::query("INSERT INTO alerts (kind, position_id, created_at, data) VALUES (?1, ?2, ?3, ?4)")
+            .bind(event.kind())
+            .bind(event.position_id())
+            .bind(event.timestamp())
+            .bind(Json(event))
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    async fn alerts(&self, query: &AlertQuery) -> Result<Vec<Event>> {
+        let rows: Vec<Json<Event>> = sqlx::query_scalar(
+            "SELECT data FROM alerts \
+             WHERE (?1 IS NULL OR position_id = ?1) AND (?2 IS NULL OR kind = ?2) \
+               AND (?3 IS NULL OR created_at >= ?3) \
+             ORDER BY id DESC LIMIT ?4",
+        )
+        .bind(query.position_id.as_deref())
+        .bind(query.kind.as_deref())
+        .bind(query.since)
+        .bind(query.limit as i64)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(rows.into_iter().map(|Json(e)| e).collect())
+    }
+
+    async fn save_metrics(&self, points: &[MetricPoint]) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+        for point in points {
+            sqlx::query(
+                "INSERT INTO metrics (metric, subject, recorded_at, value) VALUES (?1, ?2, ?3, ?4) \
+                 ON CONFLICT (metric, subject, recorded_at) DO UPDATE SET value = excluded.value",
+            )
+            .bind(point.metric.as_str())
+            .bind(point.subject.to_lowercase())
+            .bind(point.timestamp)
+            .bind(point.value)
+            .execute(&mut *tx)
+            .await?;
+        }
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn metrics(&self, query: &MetricQuery) -> Result<Vec<MetricPoint>> {
+        let rows = sqlx::query(
+            "SELECT metric, subject, recorded_at, value FROM metrics \
+             WHERE (?1 IS NULL OR metric = ?1) AND (?2 IS NULL OR subject = ?2) \
+               AND (?3 IS NULL OR recorded_at >= ?3) AND (?4 IS NULL OR recorded_at < ?4) \
+             ORDER BY recorded_at, metric, subject LIMIT ?5",
+        )
+        .bind(query.metric.map(|m| m.as_str()))
+        .bind(query.subject.as_deref().map(str::to_lowercase))
+        .bind(query.since)
+        .bind(query.until)
+        .bind(i64::try_from(query.limit)?)
+        .fetch_all(&self.pool)
+        .await?;
+        rows.into_iter()
+            .map(|row| {
+                let metric: String = row.try_get("metric")?;
+                Ok(MetricPoint {
+                    metric: metric.parse()?,
+                    subject: row.try_get("subject")?,
+                    timestamp: row.try_get("recorded_at")?,
+                    value: row.try_get("value")?,
+                })
+            })
+            .collect()
+    }
+
+    async fn save_rebalance(&self, workflow: &RebalanceWorkflow) -> Result<()> {
+        sqlx::query(
+            "INSERT INTO rebalances (id, position_id, status, created_at, updated_at, data) \
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6) \
+             ON CONFLICT (id) DO UPDATE SET \
+             status = excluded.status, updated_at = excluded.updated_at, data = excluded.data",
+        )
+        .bind(&workflow.id)
+        .bind(&workflow.position_id)
+        .bind(workflow.status.as_str())
+        .bind(workflow.created_at)
+        .bind(workflow.updated_at)
+        .bind(Json(workflow))
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn rebalances(&self, query: &RebalanceQuery) -> Result<Vec<RebalanceWorkflow>> {
+        let rows: Vec<Json<RebalanceWorkflow>> = sqlx::query_scalar(
+            "SELECT data FROM rebalances \
+             WHERE (?1 IS NULL OR position_id = ?1) AND (?2 IS NULL OR status = ?2) \
+             ORDER BY created_at DESC LIMIT ?3",
+        )
+        .bind(query.position_id.as_deref())
+        .bind(query.status.map(|s| s.as_str()))
+        .bind(query.limit as i64)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(rows.into_iter().map(|Json(w)| w).collect())
+    }
+
+    async fn save_execution(&self, tx: &TrackedTransaction) -> Result<()> {
+        sqlx::query(
+            "INSERT INTO executions (tx_hash, position_id, cycle_id, finalized, submitted_at, data) \
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6) \
+             ON CONFLICT (tx_hash) DO UPDATE SET finalized = excluded.finalized, data = excluded.data",
+        )
+        .bind(&tx.tx_hash)
+        .bind(tx.position_id.as_deref())
+        .bind(tx.cycle.map(|c| c as i64))
+        .bind(tx.finalized)
+        .bind(tx.submitted_at)
+        .bind(Json(tx))
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn executions(&self, query: &ExecutionQuery) -> Result<Vec<TrackedTransaction>> {
+        let rows: Vec<Json<TrackedTransaction>> = sqlx::query_scalar(
+            "SELECT data FROM executions \
+             WHERE (?1 IS NULL OR position_id = ?1) AND (NOT ?2 OR NOT finalized) \
+             ORDER BY submitted_at DESC LIMIT ?3",
+        )
+        .bind(query.position_id.as_deref())
+        .bind(query.unsettled)
+        .bind(query.limit as i64)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(rows.into_iter().map(|Json(t)| t).collect())
+    }
+
+    async fn save_paper_wallet(&self, wallet: &PaperWallet) -> Result<()> {
+        sqlx::query(
+            "INSERT INTO paper_wallets (name, updated_at, data) VALUES (?1, ?2, ?3) \
+             ON CONFLICT (name) DO UPDATE SET updated_at = excluded.updated_at, data = excluded.data",
+        )
+        .bind(&wallet.name)
+        .bind(wallet.updated_at)
+        .bind(Json(wallet))
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn paper_wallet(&self, name: &str) -> Result<Option<PaperWallet>> {
+        let row: Option<Json<PaperWallet>> = sqlx::query_scalar("SELECT data FROM paper_wallets WHERE name = ?1")
+            .bind(name)
+            .fetch_optional(&self.pool)
+            .await?;
+        Ok(row.map(|Json(w)| w))
+    }
+
+    async fn save_policy_state(&self, state: &PolicyState) -> Result<()> {
+        sqlx::query(
+            "INSERT INTO policy_state (id, updated_at, data) VALUES (1, ?1, ?2) \
+             ON CONFLICT (id) DO UPDATE SET updated_at = excluded.updated_at, data = excluded.data",
+        )
+        .bind(Utc::now())
+        .bind(Json(state))
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn policy_state(&self) -> Result<Option<PolicyState>> {
+        let row: Option<Json<PolicyState>> = sqlx::query_scalar("SELECT data FROM policy_state WHERE id = 1")
+            .fetch_optional(&self.pool)
+            .await?;
+        Ok(row.map(|Json(s)| s))
+    }
+
+    async fn save_ledger_entry(&self, entry: &LedgerEntry) -> Result<()> {
+        sqlx::query("INSERT INTO pnl_ledger (position_id, owner, kind, recorded_at, data) VALUES (?1, ?2, ?3, ?4, ?5)")
+            .bind(&entry.position_id)
+            .bind(entry.owner.to_lowercase())
+            .bind(entry.kind.as_str())
+            .bind(entry.timestamp)
+            .bind(Json(entry))
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    async fn ledger(&self, query: &LedgerQuery) -> Result<Vec<LedgerEntry>> {
+        let rows: Vec<Json<LedgerEntry>> = sqlx::query_scalar(
+            "SELECT data FROM pnl_ledger \
+             WHERE (?1 IS NULL OR position_id = ?1) AND (?2 IS NULL OR owner = ?2) \
+               AND (?3 IS NULL OR kind = ?3) AND (?4 IS NULL OR recorded_at >= ?4) \
+               AND (?5 IS NULL OR recorded_at < ?5) \
+             ORDER BY recorded_at, id",
+        )
+        .bind(query.position_id.as_deref())
+        .bind(query.owner.as_deref().map(str::to_lowercase))
+        .bind(query.kind.map(|k| k.as_str()))
+        .bind(query.since)
+        .bind(query.until)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(rows.into_iter().map(|Json(e)| e).collect())
+    }
+
+    async fn save_snapshot(&self, snapshot: &StateSnapshot) -> Result<()> {
+        sqlx::query(
+            "INSERT INTO snapshots (id, taken_at, data) VALUES (1, ?1, ?2) \
+             ON CONFLICT (id) DO UPDATE SET taken_at = excluded.taken_at, data = excluded.data",
+        )
+        .bind(snapshot.taken_at)
+        .bind(Json(snapshot))
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn latest_snapshot(&self) -> Result<Option<StateSnapshot>> {
+        let row: Option<Json<StateSnapshot>> = sqlx::query_scalar("SELECT data FROM snapshots WHERE id = 1")
+            .fetch_optional(&self.pool)
+            .await?;
+        Ok(row.map(|Json(s)| s))
+    }
+
+    async fn append_audit(&self, record: &AuditRecord) -> Result<()> {
+        sqlx::query(
+            "INSERT INTO audit_log (seq, recorded_at, kind, position_id, hash, data) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        )
+        .bind(i64::try_from(record.seq)?)
+        .bind(record.timestamp)
+        .bind(&record.kind)
+        .bind(record.position_id.as_deref())
+        .bind(&record.hash)
+        .bind(serde_json::to_string(record)?)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn audit_records(&self, after_seq: u64, limit: usize) -> Result<Vec<AuditRecord>> {
+        let rows: Vec<String> = sqlx::query_scalar("SELECT data FROM audit_log WHERE seq > ?1 ORDER BY seq LIMIT ?2")
+            .bind(i64::try_from(after_seq)?)
+            .bind(i64::try_from(limit)?)
+            .fetch_all(&self.pool)
+            .await?;
+        rows.iter().map(|data| serde_json::from_str(data).context("decoding audit record")).collect()
+    }
+
+    async fn latest_audit(&self) -> Result<Option<AuditRecord>> {
+        let row: Option<String> = sqlx::query_scalar("SELECT data FROM audit_log ORDER BY seq DESC LIMIT 1")
+            .fetch_optional(&self.pool)
+            .await?;
+        row.map(|data| serde_json::from_str(&data).context("decoding audit record")).transpose()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::alerts::AlertState;
+    use crate::events::RangeStatus;
+    use crate::storage::RecommendationMark;
+    use crate::metrics::MetricKind;
+    use crate::position::Action;
+    use rust_decimal::Decimal;
+
+    fn recommendation(id: &str, wallet: &str) -> PositionRecommendation {
+        PositionRecommendation {
+            position: Position::new(id.to_string(), wallet.to_string(), "0xtoken".to_string(), Decimal::ONE, Decimal::ONE),
+            recommendation_score: 0.5,
+            reasoning: String::new(),
+            suggested_action: Action::Hold,
+            fee_apr: None,
+            projected_il: None,
+            rebalance: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_state_survives_reopening() {
+        let path = std::env::temp_dir().join(format!("origins-sqlite-test-{}.db", std::process::id()));
+        let path = path.to_str().unwrap().to_string();
+        let _ = std::fs::remove_file(&path);
+
+        let storage = SqliteStorage::connect(&path, 2).await.unwrap();
+        let positions = vec![recommendation("a", "0xAA").position];
+        storage.record_cycle(&positions, &[recommendation("a", "0xAA"), recommendation("b", "0xBB")]).await.unwrap();
+        let now = Utc::now();
+        storage
+            .save_quote(&PoolQuote {
+                pool_id: "0xpool".to_string(),
+                timestamp: now,
+                token0_symbol: "WETH".to_string(),
+                token1_symbol: "USDC".to_string(),
+                fee_tier: 500,
+                tvl_usd: 1e6,
+                volume_usd: 2e5,
+            })
+            .await
+            .unwrap();
+        let range = RangeStatus {
+            position_id: "a".to_string(),
+            owner: "0xAA".to_string(),
+            pool: "0xpool".to_string(),
+            pair: "WETH/USDC".to_string(),
+            current_tick: 10,
+            tick_lower: -5,
+            tick_upper: 5,
+        };
+        storage.save_alert(&Event::PositionOutOfRange { timestamp: now, range }).await.unwrap();
+        let state = AlertState::default();
+        state.set_in_range("a", false);
+        state.set_fees_alerted("a", true);
+        let mut snapshot = StateSnapshot {
+            taken_at: now,
+            positions: positions.clone(),
+            recommendations: [("a".to_string(), RecommendationMark { action: Action::Exit, score: 0.3 })].into(),
+            alerts: state.marks(),
+        };
+        storage.save_snapshot(&snapshot).await.unwrap();
+        snapshot.recommendations.get_mut("a").unwrap().action = Action::Hold;
+        storage.save_snapshot(&snapshot).await.unwrap();
+        drop(storage);
+
+        let storage = SqliteStorage::connect(&path, 2).await.unwrap();
+        assert_eq!(storage.positions().await.unwrap().len(), 1);
+        assert_eq!(storage.latest().await.unwrap().len(), 2);
+        let cycle = storage.record_cycle(&positions, &[recommendation("a", "0xAA")]).await.unwrap();
+        assert_eq!(cycle, 2);
+
+        let query = HistoryQuery { wallets: vec!["0xbb".to_string()], limit: 10, ..Default::default() };
+        let history = storage.history(&query).await.unwrap();
+        assert_eq!(history.len(), 1);
+        assert_eq!(history[0].recommendation.position.id, "b");
+        assert!(history[0].executions.is_empty());
+        let since = HistoryQuery { since: Some(now), limit: 10, ..Default::default() };
+        assert_eq!(storage.history(&since).await.unwrap()[0].cycle, 2);
+
+        let quotes = storage.quotes("0xpool", now - chrono::Duration::minutes(1)).await.unwrap();
+        assert_eq!(quotes.len(), 1);
+        assert_eq!(quotes[0].fee_tier, 500);
+        let alerts = storage.alerts(&AlertQuery { kind: Some("position_out_of_range".to_string()), limit: 10, ..Default::default() }).await.unwrap();
+        assert_eq!(alerts.len(), 1);
+        assert_eq!(alerts[0].position_id(), "a");
+
+        let restored = storage.latest_snapshot().await.unwrap().unwrap();
+        assert_eq!(restored.positions.len(), 1);
+        assert_eq!(restored.recommendations["a"], RecommendationMark { action: Action::Hold, score: 0.3 });
+        let state = AlertState::new(restored.alerts);
+        assert_eq!(state.in_range("a"), Some(false));
+        assert!(state.fees_alerted("a"));
+        assert_eq!(state.in_range("b"), None);
+
+        drop(storage);
+        for suffix in ["", "-wal", "-shm"] {
+            let _ = std::fs::remove_file(format!("{}{}", path, suffix));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_migrations_upgrade_existing_database() {
+        let path = std::env::temp_dir().join(format!("origins-sqlite-migrate-{}.db", std::process::id()));
+        let path = path.to_str().unwrap().to_string();
+        let _ = std::fs::remove_file(&path);
+
+        // A database created before migrations were tracked
+        let pool = SqlitePool::connect_with(SqliteConnectOptions::new().filename(&path).create_if_missing(true)).await.unwrap();
+        sqlx::raw_sql(MIGRATIONS[0].sql).execute(&pool).await.unwrap();
+        sqlx::query("INSERT INTO paper_wallets (name, updated_at, data) VALUES ('paper', '2026-01-01T00:00:00Z', '{}')")
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        let mut upgraded = MIGRATIONS.to_vec();
+        upgraded.push(Migration {
+            version: MIGRATIONS.len() as i64 + 1,
+            description: "add paper wallet owner",
+            sql: "ALTER TABLE paper_wallets ADD COLUMN owner TEXT NOT NULL DEFAULT ''",
+        });
+        migrate(&pool, &upgraded).await.unwrap();
+        let versions: Vec<i64> = sqlx::query_scalar("SELECT version FROM schema_migrations ORDER BY version")
+            .fetch_all(&pool)
+            .await
+            .unwrap();
+        assert_eq!(versions, upgraded.iter().map(|m| m.version).collect::<Vec<_>>());
+        let owner: String = sqlx::query_scalar("SELECT owner FROM paper_wallets WHERE name = 'paper'").fetch_one(&pool).await.unwrap();
+        assert_eq!(owner, "");
+
+        // Re-running is a no-op; an older build refuses the newer schema
+        migrate(&pool, &upgraded).await.unwrap();
+        let err = migrate(&pool, MIGRATIONS).await.unwrap_err().to_string();
+        assert!(err.contains(&format!("schema version {}", upgraded.len())), "{}", err);
+
+        pool.close().await;
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[tokio::test]
+    async fn test_backfilled_history() {
+        let path = std::env::temp_dir().join(format!("origins-sqlite-backfill-{}.db", std::process::id()));
+        let path = path.to_str().unwrap().to_string();
+        let _ = std::fs::remove_file(&path);
+        let storage = SqliteStorage::connect(&path, 1).await.unwrap();
+
+        let start = DateTime::from_timestamp(1_700_000_000, 0).unwrap();
+        let swap = |id: &str, secs: i64| SwapRecord {
+            id: id.to_string(),
+            pool_id: "0xpool".to_string(),
+            timestamp: start + chrono::Duration::seconds(secs),
+            tx_hash: "0xtx".to_string(),
+            amount0: 1.0,
+            amount1: -2000.0,
+            amount_usd: 2000.0,
+            sqrt_price_x96: "1".to_string(),
+            tick: 0,
+        };
+        storage.save_swaps(&[swap("b", 10), swap("a", 0)]).await.unwrap();
+        let mut duplicate = swap("a", 0);
+        duplicate.amount_usd = 1.0;
+        storage.save_swaps(&[duplicate, swap("c", 20)]).await.unwrap();
+        let swaps = storage.swaps("0xpool", start, 10).await.unwrap();
+        assert_eq!(swaps.iter().map(|s| s.id.as_str()).collect::<Vec<_>>(), ["a", "b", "c"]);
+        assert_eq!(swaps[0].amount_usd, 2000.0);
+        assert_eq!(storage.swaps("0xpool", start + chrono::Duration::seconds(5), 1).await.unwrap()[0].id, "b");
+
+        let mut progress = BackfillProgress {
+            pool_id: "0xpool".to_string(),
+            dataset: "swaps".to_string(),
+            from: start,
+            cursor: start,
+            rows: 0,
+            updated_at: start,
+        };
+        storage.save_backfill_progress(&progress).await.unwrap();
+        progress.rows = 3;
+        storage.save_backfill_progress(&progress).await.unwrap();
+        assert_eq!(storage.backfill_progress("0xpool").await.unwrap(), [progress]);
+
+        drop(storage);
+        for suffix in ["", "-wal", "-shm"] {
+            let _ = std::fs::remove_file(format!("{}{}", path, suffix));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_metrics_time_series() {
+        let path = std::env::temp_dir().join(format!("origins-sqlite-metrics-{}.db", std::process::id()));
+        let path = path.to_str().unwrap().to_string();
+        let _ = std::fs::remove_file(&path);
+        let storage = SqliteStorage::connect(&path, 1).await.unwrap();
+
+        let start = DateTime::from_timestamp(1_700_000_000, 0).unwrap();
+        let point = |metric: MetricKind, subject: &str, hours: i64, value: f64| MetricPoint {
+            metric,
+            subject: subject.to_string(),
+            timestamp: start + chrono::Duration::hours(hours),
+            value,
+        };
+        storage
+            .save_metrics(&[
+                point(MetricKind::PoolFeeApr, "0xPool", 0, 0.10),
+                point(MetricKind::PoolFeeApr, "0xpool", 1, 0.12),
+                point(MetricKind::InRangePct, "7", 1, 1.0),
+            ])
+            .await
+            .unwrap();
+        // Re-deriving the same point replaces it
+        storage.save_metrics(&[point(MetricKind::PoolFeeApr, "0xpool", 1, 0.11)]).await.unwrap();
+
+        let query = MetricQuery { metric: Some(MetricKind::PoolFeeApr), subject: Some("0xPOOL".to_string()), limit: 10, ..Default::default() };
+        let values: Vec<f64> = storage.metrics(&query).await.unwrap().iter().map(|p| p.value).collect();
+        assert_eq!(values, [0.10, 0.11]);
+        let recent = MetricQuery { since: Some(start + chrono::Duration::hours(1)), limit: 10, ..Default::default() };
+        assert_eq!(storage.metrics(&recent).await.unwrap().len(), 2);
+        assert_eq!(storage.metrics(&MetricQuery { limit: 1, ..Default::default() }).await.unwrap()[0].subject, "0xpool");
+
+        drop(storage);
+        for suffix in ["", "-wal", "-shm"] {
+            let _ = std::fs::remove_file(format!("{}{}", path, suffix));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_audit_log_is_append_only() {
+        let path = std::env::temp_dir().join(format!("origins-sqlite-audit-{}.db", std::process::id()));
+        let path = path.to_str().unwrap().to_string();
+        let _ = std::fs::remove_file(&path);
+
+        let storage: std::sync::Arc<dyn Storage> = std::sync::Arc::new(SqliteStorage::connect(&path, 2).await.unwrap());
+        let first = crate::audit::AuditLog::new(storage.clone());
+        let second = crate::audit::AuditLog::new(storage.clone());
+        first.append("transaction_sent", None, &serde_json::json!({})).await.unwrap();
+        for score in [0.2, 0.7] {
+            second.append("recommendation_changed", Some("a"), &serde_json::json!({ "score": score })).await.unwrap();
+        }
+        // `first` still holds record 1 as its head: it conflicts, reloads and appends record 4
+        assert_eq!(first.append("transaction_sent", None, &serde_json::json!({})).await.unwrap().seq, 4);
+        assert_eq!(crate::audit::verify_storage(storage.as_ref()).await.unwrap().0, 4);
+
+        let sqlite = SqliteStorage::connect(&path, 1).await.unwrap();
+        for sql in ["UPDATE audit_log SET kind = 'x' WHERE seq = 1", "DELETE FROM audit_log WHERE seq = 2"] {
+            let err = sqlx::query(sql).execute(&sqlite.pool).await.unwrap_err().to_string();
+            assert!(err.contains("append-only"), "{}", err);
+        }
+        assert_eq!(storage.audit_records(2, 10).await.unwrap().len(), 2);
+
+        drop((storage, sqlite));
+        for suffix in ["", "-wal", "-shm"] {
+            let _ = std::fs::remove_file(format!("{}{}", path, suffix));
+        }
+    }
+}
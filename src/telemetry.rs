@@ -0,0 +1,64 @@
+use anyhow::{Context, Result};
+use opentelemetry::trace::TracerProvider as _;
+use opentelemetry::KeyValue;
+use opentelemetry_otlp::{SpanExporter, WithExportConfig};
+use opentelemetry_sdk::trace::TracerProvider;
+use opentelemetry_sdk::{runtime, Resource};
+use tracing::Level;
+use tracing_subscriber::filter::LevelFilter;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+
+use crate::config::TelemetryConfig;
+
+/// Keeps the OTLP tracer provider alive and flushes pending spans on drop
+pub struct TelemetryGuard {
+    provider: Option<TracerProvider>,
+}
+
+impl Drop for TelemetryGuard {
+    fn drop(&mut self) {
+        if let Some(provider) = self.provider.take() {
+            if let Err(e) = provider.shutdown() {
+                eprintln!("failed to flush OTLP spans: {}", e);
+            }
+        }
+    }
+}
+
+/// Initialize logging, and OTLP span export when telemetry is configured.
+///
+/// Must be called from within the tokio runtime: the batch exporter runs on it.
+pub fn init(level: Level, telemetry: Option<&TelemetryConfig>) -> Result<TelemetryGuard> {
+    let fmt_layer = tracing_subscriber::fmt::layer();
+    let registry = tracing_subscriber::registry().with(LevelFilter::from_level(level));
+
+    let Some(cfg) = telemetry else {
+        registry.with(fmt_layer).init();
+        return Ok(TelemetryGuard { provider: None });
+    };
+
+    let exporter = SpanExporter::builder()
+        .with_http()
+        .with_endpoint(cfg.otlp_endpoint.clone())
+        .build()
+        .with_context(|| format!("building OTLP exporter for {}", cfg.otlp_endpoint))?;
+
+    let provider = TracerProvider::builder()
+        .with_batch_exporter(exporter, runtime::Tokio)
+        .with_resource(Resource::new(vec![KeyValue::new(
+            "service.name",
+            cfg.service_name.clone(),
+        )]))
+        .build();
+    let tracer = provider.tracer("origins-position-recommender");
+
+    registry
+        .with(fmt_layer)
+        .with(tracing_opentelemetry::layer().with_tracer(tracer))
+        .init();
+
+    Ok(TelemetryGuard {
+        provider: Some(provider),
+    })
+}
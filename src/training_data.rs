@@ -0,0 +1,348 @@
+//! Training data for the AI predictor. Every `ai.sample_interval_secs` the latest cycle's
+//! positions are recorded with their feature vectors and token price (USD value per unit);
+//! once `ai.label_horizon_secs` have passed, each is labeled with the realized return of
+//! that price, read from the stored recommendation history, and appended to the training
+//! set in `ai.data_dir` that retraining reads.
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Duration as ChronoDuration, Utc};
+use rust_decimal::prelude::ToPrimitive;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::time::{interval, MissedTickBehavior};
+use tracing::{debug, info, warn};
+
+use crate::ai_predictor::{self, AIPredictor};
+use crate::config::AiConfig;
+use crate::market_data::SharedMarketData;
+use crate::position::Position;
+use crate::scheduler::Schedule;
+use crate::storage::{HistoryQuery, Storage};
+
+/// Slope of [`return_score`] at a flat return
+const RETURN_SCALE: f64 = 10.0;
+/// History records read when looking for a position's price after its horizon
+const MAX_PRICE_RECORDS: usize = 1_000;
+
+/// Recommendation score a realized return maps to: 0.5 when flat, about 0.73 at +10% and
+/// 0.27 at -10%, approaching 1 and 0 for large gains and losses
+pub fn return_score(realized_return: f64) -> f64 {
+    1.0 / (1.0 + (-RETURN_SCALE * realized_return).exp())
+}
+
+/// USD value per unit of the position's token; `None` for empty or unpriced positions
+fn unit_price(position: &Position) -> Option<f64> {
+    let amount = position.amount.to_f64().filter(|a| *a > 0.0)?;
+    Some(position.value_usd.to_f64()? / amount).filter(|p| p.is_finite() && *p > 0.0)
+}
+
+/// A position's features at `recorded_at`, waiting for its label
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Observation {
+    pub position_id: String,
+    pub token_address: String,
+    pub recorded_at: DateTime<Utc>,
+    pub price_usd: f64,
+    pub features: Vec<f64>,
+}
+
+/// An observation labeled with the realized return of its token price over the horizon
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TrainingSample {
+    pub position_id: String,
+    pub token_address: String,
+    pub recorded_at: DateTime<Utc>,
+    pub features: Vec<f64>,
+    /// Price return from `recorded_at` to the end of the horizon (0.05 = +5%)
+    pub realized_return: f64,
+}
+
+impl TrainingSample {
+    /// What the models are trained to predict
+    pub fn target(&self) -> f64 {
+        return_score(self.realized_return)
+    }
+}
+
+/// The on-disk training set: labeled samples (`samples.jsonl`, append-only) and
+/// observations waiting for their label (`pending.jsonl`)
+#[derive(Debug, Clone)]
+pub struct TrainingSet {
+    dir: PathBuf,
+}
+
+impl TrainingSet {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    /// Every labeled sample, oldest first
+    pub fn samples(&self) -> Result<Vec<TrainingSample>> {
+        read_lines(&self.dir.join("samples.jsonl"))
+    }
+
+    /// Observations not labeled yet
+    pub fn pending(&self) -> Result<Vec<Observation>> {
+        read_lines(&self.dir.join("pending.jsonl"))
+    }
+
+    pub fn append_samples(&self, samples: &[TrainingSample]) -> Result<()> {
+        if samples.is_empty() {
+            return Ok(());
+        }
+        fs::create_dir_all(&self.dir).with_context(|| format!("creating {}", self.dir.display()))?;
+        let path = self.dir.join("samples.jsonl");
+        let mut lines = String::new();
+        for sample in samples {
+            lines.push_str(&serde_json::to_string(sample)?);
+            lines.push('\n');
+        }
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .with_context(|| format!("opening {}", path.display()))?;
+        file.write_all(lines.as_bytes()).with_context(|| format!("writing {}", path.display()))
+    }
+
+    /// Replace the pending observations
+    pub fn save_pending(&self, pending: &[Observation]) -> Result<()> {
+        fs::create_dir_all(&self.dir).with_context(|| format!("creating {}", self.dir.display()))?;
+        let path = self.dir.join("pending.jsonl");
+        let tmp = path.with_extension("jsonl.tmp");
+        let mut lines = String::new();
+        for observation in pending {
+            lines.push_str(&serde_json::to_string(observation)?);
+            lines.push('\n');
+        }
+        fs::write(&tmp, lines).with_context(|| format!("writing {}", tmp.display()))?;
+        fs::rename(&tmp, &path).with_context(|| format!("renaming {}", tmp.display()))
+    }
+}
+
+/// JSON lines of `path`, skipping malformed ones; none when the file doesn't exist
+fn read_lines<T: for<'de> Deserialize<'de>>(path: &Path) -> Result<Vec<T>> {
+    let text = match fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e).with_context(|| format!("reading {}", path.display())),
+    };
+    Ok(text
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .filter_map(|(i, line)| match serde_json::from_str(line) {
+            Ok(item) => Some(item),
+            Err(e) => {
+                warn!(target: "training", path = %path.display(), line = i + 1, "skipping malformed training record: {}", e);
+                None
+            }
+        })
+        .collect())
+}
+
+/// Records observations and labels them as their horizon passes
+pub struct TrainingCollector {
+    storage: Arc<dyn Storage>,
+    market_data: SharedMarketData,
+    set: TrainingSet,
+    interval: Duration,
+    horizon: ChronoDuration,
+}
+
+impl TrainingCollector {
+    pub fn new(storage: Arc<dyn Storage>, market_data: SharedMarketData, config: &AiConfig) -> Self {
+        Self {
+            storage,
+            market_data,
+            set: TrainingSet::new(&config.data_dir),
+            interval: Duration::from_secs(config.sample_interval_secs.max(1)),
+            horizon: ChronoDuration::seconds(config.label_horizon_secs.max(1) as i64),
+        }
+    }
+
+    pub async fn run(self) {
+        let mut ticker = interval(self.interval);
+        ticker.set_missed_tick_behavior(MissedTickBehavior::Skip);
+        loop {
+            ticker.tick().await;
+            match self.collect(Utc::now()).await {
+                Ok((observed, labeled)) => debug!(target: "training", observed, labeled, "collected training data"),
+                Err(e) => warn!(target: "training", "training data not collected: {:#}", e),
+            }
+        }
+    }
+
+    /// Record the latest cycle's positions and label the observations whose horizon has
+    /// passed by `now`; returns how many were recorded and labeled
+    pub async fn collect(&self, now: DateTime<Utc>) -> Result<(usize, usize)> {
+        let mut pending = self.set.pending()?;
+        let positions = self.storage.positions().await?;
+        let observed = {
+            let market_data = self.market_data.read().expect("market data lock poisoned");
+            let before = pending.len();
+            pending.extend(positions.iter().filter_map(|position| {
+                Some(Observation {
+                    position_id: position.id.clone(),
+                    token_address: position.token_address.clone(),
+                    recorded_at: now,
+                    price_usd: unit_price(position)?,
+                    features: ai_predictor::features(&market_data, position),
+                })
+            }));
+            pending.len() - before
+        };
+
+        let mut samples = Vec::new();
+        let mut waiting = Vec::new();
+        for observation in pending {
+            let due = observation.recorded_at + self.horizon;
+            if due > now {
+                waiting.push(observation);
+                continue;
+            }
+            match self.price_at(&observation.position_id, due).await? {
+                Some(price) => samples.push(TrainingSample {
+                    realized_return: price / observation.price_usd - 1.0,
+                    position_id: observation.position_id,
+                    token_address: observation.token_address,
+                    recorded_at: observation.recorded_at,
+                    features: observation.features,
+                }),
+                // No cycle recorded the position in time (closed, or the process was down)
+                None if now >= due + self.tolerance() => {
+                    debug!(target: "training", position = %observation.position_id, "dropping observation without a price after its horizon");
+                }
+                None => waiting.push(observation),
+            }
+        }
+        self.set.append_samples(&samples)?;
+        self.set.save_pending(&waiting)?;
+        Ok((observed, samples.len()))
+    }
+
+    /// How late after its horizon a price may be recorded and still label an observation
+    fn tolerance(&self) -> ChronoDuration {
+        ChronoDuration::from_std(self.interval).unwrap_or(self.horizon)
+    }
+
+    /// The position's first recorded price at or after `at`, within the tolerance
+    async fn price_at(&self, position_id: &str, at: DateTime<Utc>) -> Result<Option<f64>> {
+        let query = HistoryQuery {
+            position_id: Some(position_id.to_string()),
+            since: Some(at),
+            until: Some(at + self.tolerance()),
+            limit: MAX_PRICE_RECORDS,
+            ..Default::default()
+        };
+        // Newest first; the oldest is the one closest to the horizon
+        let records = self.storage.history(&query).await?;
+        Ok(records.iter().rev().find_map(|r| unit_price(&r.recommendation.position)))
+    }
+}
+
+/// Retrains the predictor on the training set on the `retraining` schedule
+pub struct Retrainer {
+    predictor: AIPredictor,
+    set: TrainingSet,
+    schedule: Schedule,
+    min_samples: usize,
+}
+
+impl Retrainer {
+    pub fn new(predictor: AIPredictor, config: &AiConfig, schedule: Schedule) -> Self {
+        Self {
+            predictor,
+            set: TrainingSet::new(&config.data_dir),
+            schedule,
+            min_samples: config.min_training_samples,
+        }
+    }
+
+    pub async fn run(mut self) {
+        loop {
+            self.schedule.tick().await;
+            if let Err(e) = self.retrain().await {
+                warn!(target: "training", "AI models not retrained: {:#}", e);
+            }
+        }
+    }
+
+    /// Train on every labeled sample, if there are enough; returns how many were used
+    pub async fn retrain(&mut self) -> Result<usize> {
+        let samples = self.set.samples()?;
+        if samples.len() < self.min_samples {
+            info!(target: "training", samples = samples.len(), needed = self.min_samples, "too few training samples to retrain");
+            return Ok(0);
+        }
+        self.predictor.train_models(&samples).await?;
+        Ok(samples.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::position::{Action, PositionRecommendation};
+    use crate::storage::MemoryStorage;
+    use rust_decimal::Decimal;
+
+    fn position(value_usd: i64) -> Position {
+        Position::new("p1".to_string(), "0xuser".to_string(), "0xtoken".to_string(), Decimal::from(10), Decimal::from(value_usd))
+    }
+
+    fn recommendation(position: Position) -> PositionRecommendation {
+        PositionRecommendation {
+            position,
+            recommendation_score: 0.5,
+            reasoning: String::new(),
+            suggested_action: Action::Hold,
+            fee_apr: None,
+            projected_il: None,
+            rebalance: None,
+        }
+    }
+
+    #[test]
+    fn test_return_score() {
+        assert_eq!(return_score(0.0), 0.5);
+        assert!((return_score(0.1) - 0.731).abs() < 1e-3);
+        assert!((return_score(0.1) + return_score(-0.1) - 1.0).abs() < 1e-12);
+    }
+
+    #[tokio::test]
+    async fn test_collect_labels_after_horizon() {
+        let dir = std::env::temp_dir().join(format!("origins-training-test-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let config = AiConfig {
+            data_dir: dir.to_string_lossy().into_owned(),
+            sample_interval_secs: 3600,
+            label_horizon_secs: 1800,
+            ..AiConfig::default()
+        };
+        let storage: Arc<dyn Storage> = Arc::new(MemoryStorage::new());
+        let collector = TrainingCollector::new(storage.clone(), SharedMarketData::default(), &config);
+
+        // Observed an hour ago at $100 per unit
+        storage.record_cycle(&[position(1_000)], &[]).await.unwrap();
+        let observed_at = Utc::now() - ChronoDuration::hours(1);
+        assert_eq!(collector.collect(observed_at).await.unwrap(), (1, 0));
+        assert_eq!(collector.set.pending().unwrap().len(), 1);
+
+        // A cycle recorded half an hour past its horizon prices it at $110
+        storage.record_cycle(&[position(1_100)], &[recommendation(position(1_100))]).await.unwrap();
+        assert_eq!(collector.collect(Utc::now()).await.unwrap(), (1, 1));
+        let samples = collector.set.samples().unwrap();
+        assert_eq!(samples.len(), 1);
+        assert!((samples[0].realized_return - 0.1).abs() < 1e-9);
+        assert_eq!(samples[0].features.len(), ai_predictor::FEATURE_COUNT);
+        // The new observation waits for its own horizon
+        assert_eq!(collector.set.pending().unwrap().len(), 1);
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
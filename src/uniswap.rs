@@ -3,18 +3,321 @@ use ethabi::{ParamType, Token as AbiToken};
 use ethereum_types::{Address, U256};
 use reqwest::{header::HeaderMap, Client, StatusCode};
 use serde::{Deserialize, Serialize};
-use std::str::FromStr;
-use std::sync::Arc;
+use std::collections::HashMap;
+use std::sync::LazyLock;
 use std::time::Duration;
 use tokio::time::sleep;
-use tracing::info;
+use tracing::{debug, info, instrument, warn};
 
-use crate::config::Config;
+use crate::cache::Cache;
+use crate::config::{ChainSettings, Config};
+use crate::filters::AssetFilter;
+use crate::fixtures::Fixtures;
+use crate::rpc_health::{endpoint_label, RpcEndpoints};
+use crate::singleflight::Singleflight;
+use crate::utils::fee_growth::{self, FeeGrowth};
+use crate::utils::{format_significant_decimal, uniswap_v3};
+
+pub mod chainlink;
+pub mod positions;
+
+/// Uniswap v3 NonfungiblePositionManager (same address on mainnet and Arbitrum)
+pub const POSITION_MANAGER: &str = "0xC36442b4a4522E871399CD717aBDD847Ab11FE88";
+/// Uniswap v3 factory (same address on mainnet and Arbitrum)
+pub const FACTORY: &str = "0x1F98431c8aD98523631AE4a59f267346ea31F984";
+/// Uniswap SwapRouter02 (same address on mainnet and Arbitrum)
+pub const SWAP_ROUTER: &str = "0x68b3465833fb72A70ecDF485E0e4C7bD8665Fc45";
+/// Subgraph queried when neither the chain nor `[api]` names one
+const DEFAULT_SUBGRAPH_URL: &str = "https://api.thegraph.com/subgraphs/name/uniswap/uniswap-v3";
+/// Calls per JSON-RPC batch; providers commonly cap batches at 100
+const MAX_RPC_BATCH: usize = 100;
+
+/// Graph and RPC requests in flight, shared by every client so components asking for the
+/// same pool or price at once send it upstream only once
+static IN_FLIGHT: LazyLock<Singleflight<serde_json::Value>> = LazyLock::new(Singleflight::new);
+
+/// Uniswap v3 contracts on one network
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Contracts {
+    pub position_manager: Address,
+    pub factory: Address,
+    pub swap_router: Address,
+}
+
+impl Default for Contracts {
+    fn default() -> Self {
+        Self {
+            position_manager: POSITION_MANAGER.parse().expect("valid position manager address"),
+            factory: FACTORY.parse().expect("valid factory address"),
+            swap_router: SWAP_ROUTER.parse().expect("valid swap router address"),
+        }
+    }
+}
+
+impl Contracts {
+    /// The chain's overrides on top of the canonical deployment
+    pub fn for_chain(chain: &ChainSettings) -> Result<Self> {
+        let parse = |name: &str, value: &Option<String>, default: Address| -> Result<Address> {
+            match value {
+                Some(address) => address.parse().with_context(|| format!("chains.{}.{}: invalid address {}", chain.name, name, address)),
+                None => Ok(default),
+            }
+        };
+        let defaults = Self::default();
+        Ok(Self {
+            position_manager: parse("position_manager", &chain.position_manager, defaults.position_manager)?,
+            factory: parse("factory", &chain.factory, defaults.factory)?,
+            swap_router: parse("swap_router", &chain.swap_router, defaults.swap_router)?,
+        })
+    }
+}
+
+/// Call data for `signature` with ABI-encoded arguments
+pub(crate) fn encode_call(signature: &str, args: &[AbiToken]) -> Vec<u8> {
+    use sha3::{Digest, Keccak256};
+    let hash = Keccak256::digest(signature.as_bytes());
+    let mut data = hash[..4].to_vec();
+    data.extend_from_slice(&ethabi::encode(args));
+    data
+}
+
+/// `eth_call` request against the latest block, without an id
+fn eth_call_request(to: &str, data: &[u8]) -> serde_json::Value {
+    serde_json::json!({
+        "jsonrpc": "2.0",
+        "method": "eth_call",
+        "params": [{ "to": to, "data": format!("0x{}", hex::encode(data)) }, "latest"]
+    })
+}
+
+/// Returned bytes of one `eth_call` response
+fn call_result(response: &serde_json::Value) -> Result<Vec<u8>> {
+    if let Some(error) = response.get("error") {
+        return Err(anyhow::anyhow!("eth_call failed: {}", error));
+    }
+    let result_hex = response.get("result").and_then(|v| v.as_str()).unwrap_or("");
+    if result_hex.is_empty() {
+        return Err(anyhow::anyhow!("empty eth_call result"));
+    }
+    Ok(hex::decode(result_hex.trim_start_matches("0x"))?)
+}
+
+/// Responses of a batch of `len` requests with ids `0..len`, put back in request order
+/// (servers may answer in any order); a missing one becomes an error response. `None` when
+/// the endpoint didn't answer with an array, i.e. doesn't support batches.
+fn split_batch_response(len: usize, response: serde_json::Value) -> Option<Vec<serde_json::Value>> {
+    let serde_json::Value::Array(responses) = response else {
+        return None;
+    };
+    let mut ordered = vec![serde_json::json!({ "error": "no response in batch" }); len];
+    for response in responses {
+        if let Some(id) = response.get("id").and_then(|id| id.as_u64()).filter(|id| (*id as usize) < len) {
+            ordered[id as usize] = response;
+        }
+    }
+    Some(ordered)
+}
+
+/// `symbol()` output: a string, or a bytes32 for older tokens
+fn decode_symbol(bytes: &[u8]) -> Option<String> {
+    if let Ok(tokens) = ethabi::decode(&[ParamType::String], bytes) {
+        if let Some(AbiToken::String(s)) = tokens.first().cloned() {
+            if !s.is_empty() {
+                return Some(s);
+            }
+        }
+    }
+    if let Ok(tokens) = ethabi::decode(&[ParamType::FixedBytes(32)], bytes) {
+        if let Some(AbiToken::FixedBytes(raw)) = tokens.first().cloned() {
+            let trimmed = String::from_utf8(raw).unwrap_or_default().trim_matches(char::from(0)).to_string();
+            if !trimmed.is_empty() {
+                return Some(trimmed);
+            }
+        }
+    }
+    None
+}
+
+fn decode_decimals(bytes: &[u8]) -> Option<u8> {
+    match ethabi::decode(&[ParamType::Uint(8)], bytes).ok()?.first()? {
+        AbiToken::Uint(v) => Some(v.low_u32() as u8),
+        _ => None,
+    }
+}
+
+/// `positions(uint256)` call data for a decimal token id
+fn positions_call(token_id: &str) -> Result<Vec<u8>> {
+    let id = U256::from_dec_str(token_id).with_context(|| format!("invalid position id {}", token_id))?;
+    Ok(encode_call("positions(uint256)", &[AbiToken::Uint(id)]))
+}
+
+/// A pool's current price, from `slot0()`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Slot0 {
+    pub sqrt_price_x96: U256,
+    pub tick: i32,
+}
+
+/// A pool's price and the fee growth around one range, read together so they're consistent
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RangeFeeState {
+    pub slot0: Slot0,
+    pub growth0: FeeGrowth,
+    pub growth1: FeeGrowth,
+}
+
+fn decode_slot0(bytes: &[u8]) -> Result<Slot0> {
+    let output_types = [
+        ParamType::Uint(160), // sqrtPriceX96
+        ParamType::Int(24),   // tick
+        ParamType::Uint(16),  // observationIndex
+        ParamType::Uint(16),  // observationCardinality
+        ParamType::Uint(16),  // observationCardinalityNext
+        ParamType::Uint(8),   // feeProtocol
+        ParamType::Bool,      // unlocked
+    ];
+    let tokens = ethabi::decode(&output_types, bytes)?;
+    let sqrt_price_x96 = tokens[0].clone().into_uint().ok_or_else(|| anyhow::anyhow!("slot0 returned no sqrtPriceX96"))?;
+    let tick = tokens[1].clone().into_int().ok_or_else(|| anyhow::anyhow!("slot0 returned no tick"))?;
+    Ok(Slot0 {
+        sqrt_price_x96,
+        tick: uniswap_v3::decode_int24(tick)?,
+    })
+}
+
+fn decode_uint(bytes: &[u8]) -> Result<U256> {
+    ethabi::decode(&[ParamType::Uint(256)], bytes)?
+        .remove(0)
+        .into_uint()
+        .ok_or_else(|| anyhow::anyhow!("expected a uint"))
+}
+
+/// `feeGrowthOutside{0,1}X128` from `ticks(int24)` output
+fn decode_tick_fee_growth(bytes: &[u8]) -> Result<(U256, U256)> {
+    let output_types = [
+        ParamType::Uint(128), // liquidityGross
+        ParamType::Int(128),  // liquidityNet
+        ParamType::Uint(256), // feeGrowthOutside0X128
+        ParamType::Uint(256), // feeGrowthOutside1X128
+        ParamType::Int(56),   // tickCumulativeOutside
+        ParamType::Uint(160), // secondsPerLiquidityOutsideX128
+        ParamType::Uint(32),  // secondsOutside
+        ParamType::Bool,      // initialized
+    ];
+    let tokens = ethabi::decode(&output_types, bytes)?;
+    let growth = |i: usize| tokens[i].clone().into_uint().ok_or_else(|| anyhow::anyhow!("ticks returned no fee growth"));
+    Ok((growth(2)?, growth(3)?))
+}
+
+/// A price to 6 significant figures, or `n/a` when it couldn't be computed
+fn display_price(price: Result<rust_decimal::Decimal>) -> String {
+    price.map_or_else(|_| "n/a".to_string(), |p| format_significant_decimal(p, 6))
+}
+
+/// ABI `int24` (sign-extended to 256 bits)
+fn int24(value: i32) -> AbiToken {
+    AbiToken::Int(uniswap_v3::int24_word(value))
+}
+
+/// Arguments of `NonfungiblePositionManager.mint`
+#[derive(Debug, Clone)]
+pub struct MintParams {
+    pub token0: Address,
+    pub token1: Address,
+    pub fee: u32,
+    pub tick_lower: i32,
+    pub tick_upper: i32,
+    pub amount0_desired: U256,
+    pub amount1_desired: U256,
+    pub amount0_min: U256,
+    pub amount1_min: U256,
+    pub recipient: Address,
+    pub deadline: u64,
+}
+
+/// `mint` call data for a new position
+pub fn mint_call(params: &MintParams) -> Vec<u8> {
+    encode_call(
+        "mint((address,address,uint24,int24,int24,uint256,uint256,uint256,uint256,address,uint256))",
+        &[AbiToken::Tuple(vec![
+            AbiToken::Address(params.token0),
+            AbiToken::Address(params.token1),
+            AbiToken::Uint(U256::from(params.fee)),
+            int24(params.tick_lower),
+            int24(params.tick_upper),
+            AbiToken::Uint(params.amount0_desired),
+            AbiToken::Uint(params.amount1_desired),
+            AbiToken::Uint(params.amount0_min),
+            AbiToken::Uint(params.amount1_min),
+            AbiToken::Address(params.recipient),
+            AbiToken::Uint(U256::from(params.deadline)),
+        ])],
+    )
+}
+
+/// `decreaseLiquidity` call data
+pub fn decrease_liquidity_call(token_id: &str, liquidity: u128, amount0_min: U256, amount1_min: U256, deadline: u64) -> Result<Vec<u8>> {
+    Ok(encode_call(
+        "decreaseLiquidity((uint256,uint128,uint256,uint256,uint256))",
+        &[AbiToken::Tuple(vec![
+            AbiToken::Uint(U256::from_dec_str(token_id)?),
+            AbiToken::Uint(U256::from(liquidity)),
+            AbiToken::Uint(amount0_min),
+            AbiToken::Uint(amount1_min),
+            AbiToken::Uint(U256::from(deadline)),
+        ])],
+    ))
+}
+
+/// ERC20 `approve(spender, amount)` call data
+pub fn approve_call(spender: Address, amount: U256) -> Vec<u8> {
+    encode_call("approve(address,uint256)", &[AbiToken::Address(spender), AbiToken::Uint(amount)])
+}
+
+/// SwapRouter02 `exactInputSingle` call data (no price limit)
+pub fn exact_input_single_call(
+    token_in: Address,
+    token_out: Address,
+    fee: u32,
+    recipient: Address,
+    amount_in: U256,
+    amount_out_minimum: U256,
+) -> Vec<u8> {
+    encode_call(
+        "exactInputSingle((address,address,uint24,address,uint256,uint256,uint160))",
+        &[AbiToken::Tuple(vec![
+            AbiToken::Address(token_in),
+            AbiToken::Address(token_out),
+            AbiToken::Uint(U256::from(fee)),
+            AbiToken::Address(recipient),
+            AbiToken::Uint(amount_in),
+            AbiToken::Uint(amount_out_minimum),
+            AbiToken::Uint(U256::zero()),
+        ])],
+    )
+}
 
 #[derive(Clone)]
 pub struct UniswapClient {
+    /// The process-wide pooled client (see `crate::http`)
     http: Client,
     graph_endpoint: String,
+    /// Graph API key headers, sent to the subgraph only
+    graph_headers: HeaderMap,
+    /// The chain's RPC endpoints; calls to any of them go to the best-scoring one
+    endpoints: RpcEndpoints,
+    cache: Cache,
+    /// Chain name, keeps cached token metadata apart per network
+    chain: String,
+    contracts: Contracts,
+    /// `[filters]` applied to top-pool listings
+    filter: AssetFilter,
+    /// `[development.fixtures]`: Graph and RPC responses recorded to or replayed from disk
+    fixtures: Option<Fixtures>,
+    /// `[chainlink]` feeds token prices fall back to without the subgraph (active chain only)
+    chainlink: Vec<chainlink::Feed>,
+    /// Per-attempt RPC timeout, so a hanging endpoint fails over instead of stalling the call
+    rpc_timeout: Duration,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -27,6 +330,14 @@ pub struct Pool {
     pub liquidity: String,
     pub volume_usd: String,
     pub total_value_locked_usd: String,
+    /// Tokens locked, in whole units; only fetched by `get_pool_by_id`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub total_value_locked_token0: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub total_value_locked_token1: Option<String>,
+    /// Price in token1 per token0; only fetched by `get_pool_by_id`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token1_price: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -37,6 +348,86 @@ pub struct Token {
     pub decimals: String,
 }
 
+/// A swap in a pool, as indexed by the subgraph
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Swap {
+    pub id: String,
+    /// Unix seconds
+    pub timestamp: String,
+    /// Signed token amounts from the pool's perspective
+    pub amount0: String,
+    pub amount1: String,
+    #[serde(rename = "amountUSD")]
+    pub amount_usd: String,
+    pub sqrt_price_x96: String,
+    pub tick: String,
+    pub transaction: SwapTransaction,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SwapTransaction {
+    pub id: String,
+}
+
+/// One hour of a pool's price (token0 in token1) and volume, as indexed by the subgraph
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PoolHourData {
+    pub period_start_unix: i64,
+    pub open: String,
+    pub high: String,
+    pub low: String,
+    pub close: String,
+    #[serde(rename = "volumeUSD")]
+    pub volume_usd: String,
+}
+
+/// One UTC day of a pool's price (token0 in token1), volume, TVL and fees, as indexed by the
+/// subgraph
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PoolDayData {
+    /// Unix seconds at the start of the day
+    pub date: i64,
+    pub open: String,
+    pub high: String,
+    pub low: String,
+    pub close: String,
+    #[serde(rename = "volumeUSD")]
+    pub volume_usd: String,
+    #[serde(rename = "tvlUSD")]
+    pub tvl_usd: String,
+    #[serde(rename = "feesUSD")]
+    pub fees_usd: String,
+}
+
+/// A position's cumulative deposits, withdrawals and fees after one of its transactions
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PositionSnapshot {
+    pub id: String,
+    pub owner: String,
+    pub position: EntityRef,
+    /// Unix seconds
+    pub timestamp: String,
+    pub block_number: String,
+    pub liquidity: String,
+    pub deposited_token0: String,
+    pub deposited_token1: String,
+    pub withdrawn_token0: String,
+    pub withdrawn_token1: String,
+    pub collected_fees_token0: String,
+    pub collected_fees_token1: String,
+    pub transaction: EntityRef,
+}
+
+/// A related entity, by id
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EntityRef {
+    pub id: String,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 struct GraphRequest {
     query: String,
@@ -91,13 +482,26 @@ impl UniswapClient {
             other => other.to_string(),
         }
     }
+    /// Client for the network the configuration selects (see `Config::active_chain`)
     pub fn from_config(config: &Config) -> Self {
-        // Prefer config.api.thegraph_api_url if present
-        let endpoint = config
-            .api
-            .as_ref()
-            .and_then(|a| a.thegraph_api_url.clone())
-            .unwrap_or_else(|| "https://api.thegraph.com/subgraphs/name/uniswap/uniswap-v3".to_string());
+        let chain = config.active_chain();
+        Self::for_chain(config, &chain).unwrap_or_else(|e| {
+            warn!(target: "uniswap", chain = %chain.name, "{:#}; using the canonical Uniswap contracts", e);
+            Self::build(config, &chain, Contracts::default())
+        })
+    }
+
+    /// Client for one network: its subgraph (else `[api]`'s) and contract addresses
+    pub fn for_chain(config: &Config, chain: &ChainSettings) -> Result<Self> {
+        Ok(Self::build(config, chain, Contracts::for_chain(chain)?))
+    }
+
+    fn build(config: &Config, chain: &ChainSettings, contracts: Contracts) -> Self {
+        let endpoint = chain
+            .subgraph_url
+            .clone()
+            .or_else(|| config.api.as_ref().and_then(|a| a.thegraph_api_url.clone()))
+            .unwrap_or_else(|| DEFAULT_SUBGRAPH_URL.to_string());
 
         // Optional Graph API key support (Graph Gateway requires Authorization header)
         let mut headers = HeaderMap::new();
@@ -115,27 +519,99 @@ impl UniswapClient {
             }
         }
 
-        let http = Client::builder()
-            .default_headers(headers)
-            .user_agent("origins-uniswap-client/0.1")
-            .timeout(Duration::from_secs(15))
-            .build()
-            .expect("failed to build reqwest client");
-
         Self {
-            http,
+            http: crate::http::shared(),
             graph_endpoint: endpoint,
+            graph_headers: headers,
+            endpoints: RpcEndpoints::for_chain(chain),
+            cache: Cache::default(),
+            chain: chain.name.clone(),
+            contracts,
+            filter: AssetFilter::from_config(config.filters.as_ref()),
+            fixtures: config.fixtures().map(Fixtures::from_config),
+            chainlink: match &config.chainlink {
+                Some(chainlink) if chain.name == config.active_chain().name => chainlink::Feed::from_config(chainlink),
+                _ => Vec::new(),
+            },
+            rpc_timeout: Duration::from_secs(config.rpc_health.clone().unwrap_or_default().call_timeout_secs),
+        }
+    }
+
+    /// Uniswap contracts of this client's network
+    pub fn contracts(&self) -> Contracts {
+        self.contracts
+    }
+
+    /// Token and pool allow/deny lists this client applies
+    pub fn filter(&self) -> &AssetFilter {
+        &self.filter
+    }
+
+    fn drop_filtered(&self, pools: Vec<Pool>) -> Vec<Pool> {
+        let before = pools.len();
+        let pools: Vec<Pool> = pools.into_iter().filter(|p| self.filter.allows_pool(p)).collect();
+        if pools.len() < before {
+            info!(target: "uniswap.fetch", dropped = before - pools.len(), "filtered pools by [filters]");
+        }
+        pools
+    }
+
+    /// Serve Graph responses and token metadata from `cache` when possible
+    pub fn with_cache(mut self, cache: Cache) -> Self {
+        self.cache = cache;
+        self
+    }
+
+    fn graph_cache_key(&self, req: &GraphRequest) -> String {
+        use sha2::{Digest, Sha256};
+        let mut hasher = Sha256::new();
+        hasher.update(self.graph_endpoint.as_bytes());
+        hasher.update(req.query.as_bytes());
+        hasher.update(req.variables.to_string().as_bytes());
+        format!("graph:{}", hex::encode(hasher.finalize()))
+    }
+
+    /// Like `post_with_retry`, but answers from the cache when a fresh response is stored
+    async fn post_cached<T: Serialize + for<'de> Deserialize<'de>>(&self, req: &GraphRequest) -> Result<T> {
+        let key = self.graph_cache_key(req);
+        if let Some(data) = self.cache.get_json::<T>(&key).await {
+            return Ok(data);
         }
+        let data: T = self.post_with_retry(req).await?;
+        self.cache.set_json(&key, &data, self.cache.graph_ttl_for(&req.query)).await;
+        Ok(data)
     }
 
+    /// Graph request; identical requests in flight anywhere in the process share one round trip
     async fn post_with_retry<T: for<'de> Deserialize<'de>>(&self, req: &GraphRequest) -> Result<T> {
+        let key = self.graph_cache_key(req);
+        let request = serde_json::to_value(req)?;
+        let data = IN_FLIGHT.run(&key, || self.through_fixtures("graph", &request, self.send_graph(req))).await?;
+        serde_json::from_value(data).with_context(|| "decoding graph response data")
+    }
+
+    /// Run `fetch`, unless `[development.fixtures]` replays the response from disk; in record
+    /// mode the response is saved
+    async fn through_fixtures<F>(&self, kind: &str, request: &serde_json::Value, fetch: F) -> Result<serde_json::Value>
+    where
+        F: std::future::Future<Output = Result<serde_json::Value>>,
+    {
+        match &self.fixtures {
+            Some(fixtures) => fixtures.through(kind, &self.chain, request, fetch).await,
+            None => fetch.await,
+        }
+    }
+
+    #[instrument(name = "graph_request", skip_all, fields(endpoint = %self.graph_endpoint))]
+    async fn send_graph(&self, req: &GraphRequest) -> Result<serde_json::Value> {
         let mut attempt: u32 = 0;
         let max_attempts: u32 = 3;
-        let mut last_status: Option<StatusCode> = None;
+        let last_status: Option<StatusCode>;
         loop {
             info!(target: "uniswap.fetch", endpoint = %self.graph_endpoint, attempt = attempt + 1, "sending request to The Graph");
             let resp = self.http
                 .post(&self.graph_endpoint)
+                .headers(self.graph_headers.clone())
                 .json(req)
                 .send()
                 .await
@@ -144,7 +620,7 @@ impl UniswapClient {
             let status = resp.status();
             if status.is_success() {
                 let text = resp.text().await.with_context(|| "reading graph response text")?;
-                let envelope: GraphResponse<T> = serde_json::from_str(&text)
+                let envelope: GraphResponse<serde_json::Value> = serde_json::from_str(&text)
                     .with_context(|| format!("decoding graph response JSON: {}", text))?;
 
                 if let Some(errors) = envelope.errors {
@@ -161,13 +637,13 @@ impl UniswapClient {
                 }
             }
 
-            last_status = Some(status);
             attempt += 1;
             if attempt >= max_attempts || status == StatusCode::BAD_REQUEST {
+                last_status = Some(status);
                 break;
             }
             // Exponential backoff: 300ms, 900ms, 2700ms
-            let backoff_ms = 300u64 * 3u64.pow((attempt - 1) as u32);
+            let backoff_ms = 300u64 * 3u64.pow(attempt - 1);
             info!(target: "uniswap.fetch", endpoint = %self.graph_endpoint, attempt = attempt + 1, status = %status, backoff_ms, "graph request failed, backing off and retrying");
             sleep(Duration::from_millis(backoff_ms)).await;
         }
@@ -195,9 +671,9 @@ impl UniswapClient {
             variables: serde_json::json!({ "first": first as i64 }),
         };
 
-        let body: PoolsData = self.post_with_retry(&req).await?;
+        let body: PoolsData = self.post_cached(&req).await?;
         info!(target: "uniswap.fetch", endpoint = %self.graph_endpoint, count = body.pools.len(), "fetched top pools");
-        Ok(body.pools)
+        Ok(self.drop_filtered(body.pools))
     }
 
     pub async fn top_pools_paginated(&self, total: usize, page_size: usize) -> Result<Vec<Pool>> {
@@ -210,7 +686,7 @@ impl UniswapClient {
             if batch.is_empty() {
                 break;
             }
-            all.extend(batch);
+            all.extend(self.drop_filtered(batch));
             skip += page;
         }
         all.truncate(total);
@@ -239,7 +715,7 @@ impl UniswapClient {
             variables: serde_json::json!({ "first": first as i64, "skip": skip as i64 }),
         };
 
-        let body: PoolsData = self.post_with_retry(&req).await?;
+        let body: PoolsData = self.post_cached(&req).await?;
         info!(target: "uniswap.fetch", endpoint = %self.graph_endpoint, first, skip, count = body.pools.len(), "fetched top pools page");
         Ok(body.pools)
     }
@@ -254,6 +730,9 @@ impl UniswapClient {
             liquidity
             volumeUSD
             totalValueLockedUSD
+            totalValueLockedToken0
+            totalValueLockedToken1
+            token1Price
             token0 { id symbol name decimals }
             token1 { id symbol name decimals }
           }
@@ -265,13 +744,201 @@ impl UniswapClient {
             variables: serde_json::json!({ "id": pool_id }),
         };
 
-        #[derive(Deserialize)]
+        #[derive(Serialize, Deserialize)]
         struct PoolData { pool: Option<Pool> }
-        let body: PoolData = self.post_with_retry(&req).await?;
+        let body: PoolData = self.post_cached(&req).await?;
         info!(target: "uniswap.fetch", endpoint = %self.graph_endpoint, pool_id = pool_id, found = body.pool.is_some(), "fetched pool by id");
         Ok(body.pool)
     }
 
+    /// Up to `first` swaps in `pool_id` at or after `since` (unix seconds), oldest first.
+    /// Not cached: callers page through history with a moving cursor.
+    pub async fn swaps_since(&self, pool_id: &str, since: i64, first: usize) -> Result<Vec<Swap>> {
+        info!(target: "uniswap.fetch", endpoint = %self.graph_endpoint, pool_id, since, first, "fetching swaps");
+        let query = r#"
+        query Swaps($pool: String!, $since: BigInt!, $first: Int!) {
+          swaps(first: $first, orderBy: timestamp, orderDirection: asc, where: { pool: $pool, timestamp_gte: $since }) {
+            id
+            timestamp
+            amount0
+            amount1
+            amountUSD
+            sqrtPriceX96
+            tick
+            transaction { id }
+          }
+        }
+        "#;
+
+        let req = GraphRequest {
+            query: query.to_string(),
+            variables: serde_json::json!({ "pool": pool_id.to_lowercase(), "since": since.to_string(), "first": first as i64 }),
+        };
+
+        #[derive(Serialize, Deserialize)]
+        struct SwapsData { swaps: Vec<Swap> }
+        let body: SwapsData = self.post_with_retry(&req).await?;
+        info!(target: "uniswap.fetch", endpoint = %self.graph_endpoint, pool_id, count = body.swaps.len(), "fetched swaps");
+        Ok(body.swaps)
+    }
+
+    /// Up to `first` hourly candles of `pool_id` starting at or after `since` (unix
+    /// seconds), oldest first. Not cached.
+    pub async fn pool_hour_data_since(&self, pool_id: &str, since: i64, first: usize) -> Result<Vec<PoolHourData>> {
+        info!(target: "uniswap.fetch", endpoint = %self.graph_endpoint, pool_id, since, first, "fetching pool hour data");
+        let query = r#"
+        query PoolHourData($pool: String!, $since: Int!, $first: Int!) {
+          poolHourDatas(first: $first, orderBy: periodStartUnix, orderDirection: asc, where: { pool: $pool, periodStartUnix_gte: $since }) {
+            periodStartUnix
+            open
+            high
+            low
+            close
+            volumeUSD
+          }
+        }
+        "#;
+
+        let req = GraphRequest {
+            query: query.to_string(),
+            variables: serde_json::json!({ "pool": pool_id.to_lowercase(), "since": since, "first": first as i64 }),
+        };
+
+        #[derive(Serialize, Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct HourData { pool_hour_datas: Vec<PoolHourData> }
+        let body: HourData = self.post_cached(&req).await?;
+        info!(target: "uniswap.fetch", endpoint = %self.graph_endpoint, pool_id, count = body.pool_hour_datas.len(), "fetched pool hour data");
+        Ok(body.pool_hour_datas)
+    }
+
+    /// `pool_id`'s hourly candles over the last `hours` hours, oldest first
+    pub async fn pool_hour_data(&self, pool_id: &str, hours: usize) -> Result<Vec<PoolHourData>> {
+        // Candles start on the hour; rounding keeps the request (and its cache key) stable
+        let hour = chrono::Utc::now().timestamp() / 3600 * 3600;
+        self.pool_hour_data_since(pool_id, hour - hours as i64 * 3600, hours.min(1000)).await
+    }
+
+    /// Up to `first` daily candles of `pool_id` starting at or after `since` (unix seconds),
+    /// oldest first
+    pub async fn pool_day_data_since(&self, pool_id: &str, since: i64, first: usize) -> Result<Vec<PoolDayData>> {
+        info!(target: "uniswap.fetch", endpoint = %self.graph_endpoint, pool_id, since, first, "fetching pool day data");
+        let query = r#"
+        query PoolDayData($pool: String!, $since: Int!, $first: Int!) {
+          poolDayDatas(first: $first, orderBy: date, orderDirection: asc, where: { pool: $pool, date_gte: $since }) {
+            date
+            open
+            high
+            low
+            close
+            volumeUSD
+            tvlUSD
+            feesUSD
+          }
+        }
+        "#;
+
+        let req = GraphRequest {
+            query: query.to_string(),
+            variables: serde_json::json!({ "pool": pool_id.to_lowercase(), "since": since, "first": first as i64 }),
+        };
+
+        #[derive(Serialize, Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct DayData { pool_day_datas: Vec<PoolDayData> }
+        let body: DayData = self.post_cached(&req).await?;
+        info!(target: "uniswap.fetch", endpoint = %self.graph_endpoint, pool_id, count = body.pool_day_datas.len(), "fetched pool day data");
+        Ok(body.pool_day_datas)
+    }
+
+    /// `pool_id`'s daily candles for the last `days` complete UTC days, oldest first
+    pub async fn pool_day_data(&self, pool_id: &str, days: usize) -> Result<Vec<PoolDayData>> {
+        let today = chrono::Utc::now().timestamp() / 86_400 * 86_400;
+        let mut day_data = self.pool_day_data_since(pool_id, today - days as i64 * 86_400, (days + 1).min(1000)).await?;
+        day_data.retain(|day| day.date < today);
+        Ok(day_data)
+    }
+
+    /// Volume of each pool over the last 24 hours (its last 24 hourly candles), by lowercase
+    /// pool id; pools without candles are missing
+    pub async fn volumes_24h(&self, pool_ids: &[&str]) -> Result<HashMap<String, f64>> {
+        // 24 candles per pool (25 when the window straddles an hour) within one page of 1000
+        const POOLS_PER_QUERY: usize = 40;
+        let query = r#"
+        query PoolVolumes($pools: [String!]!, $since: Int!) {
+          poolHourDatas(first: 1000, where: { pool_in: $pools, periodStartUnix_gt: $since }) {
+            pool { id }
+            volumeUSD
+          }
+        }
+        "#;
+        #[derive(Serialize, Deserialize)]
+        struct PoolRef { id: String }
+        #[derive(Serialize, Deserialize)]
+        struct HourVolume {
+            pool: PoolRef,
+            #[serde(rename = "volumeUSD")]
+            volume_usd: String,
+        }
+        #[derive(Serialize, Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct VolumesData { pool_hour_datas: Vec<HourVolume> }
+
+        // Candles start on the hour; rounding keeps the request (and its cache key) stable
+        let hour = chrono::Utc::now().timestamp() / 3600 * 3600;
+        let mut volumes: HashMap<String, f64> = HashMap::new();
+        for chunk in pool_ids.chunks(POOLS_PER_QUERY) {
+            let pools: Vec<String> = chunk.iter().map(|id| id.to_lowercase()).collect();
+            let req = GraphRequest {
+                query: query.to_string(),
+                variables: serde_json::json!({ "pools": pools, "since": hour - 86_400 }),
+            };
+            let body: VolumesData = self.post_cached(&req).await?;
+            for candle in body.pool_hour_datas {
+                *volumes.entry(candle.pool.id.to_lowercase()).or_default() += candle.volume_usd.parse::<f64>().unwrap_or(0.0);
+            }
+        }
+        info!(target: "uniswap.fetch", endpoint = %self.graph_endpoint, pools = pool_ids.len(), "fetched 24h pool volumes");
+        Ok(volumes)
+    }
+
+    /// Up to `first` snapshots of positions in `pool_id` taken at or after `since` (unix
+    /// seconds), oldest first. Not cached.
+    pub async fn position_snapshots_since(&self, pool_id: &str, since: i64, first: usize) -> Result<Vec<PositionSnapshot>> {
+        info!(target: "uniswap.fetch", endpoint = %self.graph_endpoint, pool_id, since, first, "fetching position snapshots");
+        let query = r#"
+        query PositionSnapshots($pool: String!, $since: BigInt!, $first: Int!) {
+          positionSnapshots(first: $first, orderBy: timestamp, orderDirection: asc, where: { pool: $pool, timestamp_gte: $since }) {
+            id
+            owner
+            position { id }
+            timestamp
+            blockNumber
+            liquidity
+            depositedToken0
+            depositedToken1
+            withdrawnToken0
+            withdrawnToken1
+            collectedFeesToken0
+            collectedFeesToken1
+            transaction { id }
+          }
+        }
+        "#;
+
+        let req = GraphRequest {
+            query: query.to_string(),
+            variables: serde_json::json!({ "pool": pool_id.to_lowercase(), "since": since.to_string(), "first": first as i64 }),
+        };
+
+        #[derive(Serialize, Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct SnapshotsData { position_snapshots: Vec<PositionSnapshot> }
+        let body: SnapshotsData = self.post_cached(&req).await?;
+        info!(target: "uniswap.fetch", endpoint = %self.graph_endpoint, pool_id, count = body.position_snapshots.len(), "fetched position snapshots");
+        Ok(body.position_snapshots)
+    }
+
     /// Resolve a Uniswap v3 position NFT id to its pool id, then fetch the pool
     pub async fn get_pool_by_position_id(&self, position_id: &str) -> Result<Option<Pool>> {
         info!(target: "uniswap.fetch", endpoint = %self.graph_endpoint, position_id = position_id, "resolving pool by position id");
@@ -284,11 +951,11 @@ impl UniswapClient {
         }
         "#;
 
-        #[derive(Deserialize)]
+        #[derive(Serialize, Deserialize)]
         struct PositionResp { position: Option<PositionMin> }
-        #[derive(Deserialize)]
+        #[derive(Serialize, Deserialize)]
         struct PositionMin { pool: PoolRef }
-        #[derive(Deserialize)]
+        #[derive(Serialize, Deserialize)]
         struct PoolRef { id: String }
 
         let req = GraphRequest {
@@ -296,7 +963,7 @@ impl UniswapClient {
             variables: serde_json::json!({ "id": position_id }),
         };
 
-        let body: PositionResp = self.post_with_retry(&req).await?;
+        let body: PositionResp = self.post_cached(&req).await?;
         if let Some(pos) = body.position {
             info!(target: "uniswap.fetch", endpoint = %self.graph_endpoint, position_id = position_id, pool_id = %pos.pool.id, "resolved position to pool, fetching");
             self.get_pool_by_id(&pos.pool.id).await
@@ -306,9 +973,44 @@ impl UniswapClient {
         }
     }
 
+    /// Fetch the subgraph's indexing status (latest indexed block and its timestamp)
+    pub async fn subgraph_meta(&self) -> Result<SubgraphMeta> {
+        let query = r#"
+        query Meta {
+          _meta { block { number timestamp } }
+        }
+        "#;
+
+        let req = GraphRequest {
+            query: query.to_string(),
+            variables: serde_json::json!({}),
+        };
+
+        #[derive(Deserialize)]
+        struct MetaData { _meta: MetaInner }
+        #[derive(Deserialize)]
+        struct MetaInner { block: SubgraphMeta }
+
+        let body: MetaData = self.post_with_retry(&req).await?;
+        Ok(body._meta.block)
+    }
+
 // ================= On-chain Position Manager fetcher =================
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SubgraphMeta {
+    pub number: u64,
+    /// Unix timestamp of the latest indexed block (absent on some graph-node versions)
+    pub timestamp: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct TokenMetadata {
+    symbol: String,
+    decimals: u8,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct OnchainPosition {
     pub token_id: String,
@@ -317,117 +1019,274 @@ pub struct OnchainPosition {
     pub token1: String,
     pub token0_symbol: String,
     pub token1_symbol: String,
+    pub token0_decimals: u8,
+    pub token1_decimals: u8,
     pub fee: u32,
     pub tick_lower: i32,
     pub tick_upper: i32,
     pub liquidity: String,
+    /// Token0 the liquidity holds at the current price, in raw units (fees excluded)
+    pub amount0: String,
+    pub amount1: String,
     pub tokens_owed0: String,
     pub tokens_owed1: String,
+    /// Fees `collect` would pay out in raw token0 units: `tokens_owed0` plus fees accrued
+    /// since the position was last touched
+    pub uncollected_fees0: String,
+    pub uncollected_fees1: String,
+    /// Range bounds and mid price, token1 per token0; `n/a` where the price doesn't fit a
+    /// Decimal, e.g. the upper bound of a full-range position
     pub price_lower_quote_per_base: String,
     pub price_upper_quote_per_base: String,
     pub mid_price_quote_per_base: String,
+    /// The position's pool
+    pub pool: String,
+    /// Pool tick when the position was read
+    pub current_tick: i32,
+    /// Pool price when the position was read, token1 per token0 like the range
+    pub current_price: String,
+    /// Whether the range contains the current tick, i.e. the position earns fees
+    pub in_range: bool,
+}
+
+impl OnchainPosition {
+    /// USD value of the tokens the liquidity holds (uncollected fees excluded); `None` when
+    /// either token has no price
+    pub fn value_usd(&self, prices: &TokenPrices) -> Option<f64> {
+        let amount = |raw: &str, decimals: u8| U256::from_dec_str(raw).ok().map(|raw| crate::utils::to_units(raw, decimals));
+        let value0 = amount(&self.amount0, self.token0_decimals)? * prices.usd(&self.token0)?;
+        let value1 = amount(&self.amount1, self.token1_decimals)? * prices.usd(&self.token1)?;
+        Some(value0 + value1)
+    }
+}
+
+/// USD prices derived from the subgraph (token price in ETH times the ETH price)
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TokenPrices {
+    pub eth_usd: f64,
+    /// Lowercase token address -> USD price
+    pub tokens: std::collections::HashMap<String, f64>,
+}
+
+impl TokenPrices {
+    pub fn usd(&self, token: &str) -> Option<f64> {
+        self.tokens.get(&token.to_lowercase()).copied()
+    }
 }
 
 impl UniswapClient {
-    async fn eth_call_raw(&self, rpc_url: &str, to_addr: &str, data: &[u8]) -> Result<Vec<u8>> {
-        let params = serde_json::json!({
-            "to": to_addr,
-            "data": format!("0x{}", hex::encode(data)),
+    /// POST a JSON-RPC body; identical calls in flight share one round trip. A call to one of
+    /// the chain's endpoints goes to the best-scoring endpoint, failing over down the ranking.
+    async fn rpc_post(&self, rpc_url: &str, body: &serde_json::Value) -> Result<serde_json::Value> {
+        let key = format!("rpc:{}:{}", rpc_url, body);
+        IN_FLIGHT
+            .run(&key, || self.through_fixtures("rpc", body, self.send_ranked(rpc_url, body)))
+            .await
+    }
+
+    /// Send to the best-scoring of the chain's endpoints, failing over down the ranking
+    async fn send_ranked(&self, rpc_url: &str, body: &serde_json::Value) -> Result<serde_json::Value> {
+        if !self.endpoints.contains(rpc_url) {
+            return self.send_rpc(rpc_url, body).await;
+        }
+        let mut last_error = None;
+        for url in self.endpoints.ranked() {
+            let started = std::time::Instant::now();
+            match self.send_rpc(&url, body).await {
+                Ok(json) => {
+                    self.endpoints.record_success(&url, started.elapsed());
+                    return Ok(json);
+                }
+                Err(e) => {
+                    self.endpoints.record_failure(&url);
+                    warn!(target: "uniswap.onchain", endpoint = %endpoint_label(&url), "RPC call failed, trying the next endpoint: {:#}", e);
+                    last_error = Some(e);
+                }
+            }
+        }
+        Err(last_error.unwrap_or_else(|| anyhow::anyhow!("no RPC endpoint configured")))
+    }
+
+    async fn send_rpc(&self, url: &str, body: &serde_json::Value) -> Result<serde_json::Value> {
+        Ok(self.http.post(url).timeout(self.rpc_timeout).json(body).send().await?.error_for_status()?.json().await?)
+    }
+
+    /// Health scores of the chain's RPC endpoints
+    pub fn rpc_endpoints(&self) -> RpcEndpoints {
+        self.endpoints.clone()
+    }
+
+    /// JSON-RPC call returning a hex quantity (`eth_gasPrice`, `eth_estimateGas`, ...)
+    #[instrument(name = "rpc_call", skip(self, rpc_url, params))]
+    async fn rpc_quantity(&self, rpc_url: &str, method: &str, params: serde_json::Value) -> Result<U256> {
+        let body = serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": method,
+            "params": params
         });
+        let json = self.rpc_post(rpc_url, &body).await?;
+        if let Some(error) = json.get("error") {
+            return Err(anyhow::anyhow!("{} failed: {}", method, error));
+        }
+        let result_hex = json.get("result").and_then(|v| v.as_str()).unwrap_or("");
+        if result_hex.is_empty() {
+            return Err(anyhow::anyhow!("empty {} result", method));
+        }
+        Ok(U256::from_str_radix(result_hex.trim_start_matches("0x"), 16)?)
+    }
+
+    /// `eth_call` sent as `from` (for calls gated on the caller, like `collect`)
+    #[instrument(name = "rpc_call", skip(self, rpc_url, data), fields(method = "eth_call"))]
+    async fn eth_call_from(&self, rpc_url: &str, from: &str, to_addr: &str, data: &[u8]) -> Result<Vec<u8>> {
         let body = serde_json::json!({
             "jsonrpc": "2.0",
             "id": 1,
             "method": "eth_call",
-            "params": [params, "latest"]
+            "params": [{ "from": from, "to": to_addr, "data": format!("0x{}", hex::encode(data)) }, "latest"]
         });
-        let resp = self.http.post(rpc_url).json(&body).send().await?.error_for_status()?;
-        let json: serde_json::Value = resp.json().await?;
+        let json = self.rpc_post(rpc_url, &body).await?;
+        if let Some(error) = json.get("error") {
+            return Err(anyhow::anyhow!("eth_call reverted: {}", error));
+        }
         let result_hex = json.get("result").and_then(|v| v.as_str()).unwrap_or("");
         if result_hex.is_empty() {
             return Err(anyhow::anyhow!("empty eth_call result"));
         }
-        let bytes = hex::decode(result_hex.trim_start_matches("0x"))?;
-        Ok(bytes)
+        Ok(hex::decode(result_hex.trim_start_matches("0x"))?)
     }
 
-    async fn resolve_erc20_symbol(&self, rpc_url: &str, token_address_hex: &str) -> Result<String> {
-        use sha3::{Digest, Keccak256};
-        // Try symbol() -> string
-        let symbol_selector = {
-            let mut h = Keccak256::new();
-            h.update(b"symbol()");
-            let out = h.finalize();
-            [out[0], out[1], out[2], out[3]]
-        };
-        let mut data = Vec::with_capacity(4);
-        data.extend_from_slice(&symbol_selector);
-        if let Ok(bytes) = self.eth_call_raw(rpc_url, token_address_hex, &data).await {
-            if let Ok(tokens) = ethabi::decode(&[ParamType::String], &bytes) {
-                if let Some(AbiToken::String(s)) = tokens.get(0).cloned() {
-                    if !s.is_empty() { return Ok(s); }
+    #[instrument(name = "rpc_call", skip(self, rpc_url, data), fields(method = "eth_call"))]
+    async fn eth_call_raw(&self, rpc_url: &str, to_addr: &str, data: &[u8]) -> Result<Vec<u8>> {
+        let mut body = eth_call_request(to_addr, data);
+        body["id"] = serde_json::json!(1);
+        call_result(&self.rpc_post(rpc_url, &body).await?)
+    }
+
+    /// `eth_call`s sent as JSON-RPC batches of up to [`MAX_RPC_BATCH`], one result per call
+    /// in order. Endpoints that reject batches get the calls one by one instead.
+    #[instrument(name = "rpc_batch", skip_all, fields(method = "eth_call", calls = calls.len()))]
+    pub async fn eth_call_batch(&self, rpc_url: &str, calls: &[(String, Vec<u8>)]) -> Result<Vec<Result<Vec<u8>>>> {
+        let mut results = Vec::with_capacity(calls.len());
+        for chunk in calls.chunks(MAX_RPC_BATCH) {
+            let batch: Vec<serde_json::Value> = chunk
+                .iter()
+                .enumerate()
+                .map(|(id, (to, data))| {
+                    let mut request = eth_call_request(to, data);
+                    request["id"] = serde_json::json!(id);
+                    request
+                })
+                .collect();
+            let body = serde_json::Value::Array(batch);
+            let response = self.rpc_post(rpc_url, &body).await?;
+            match split_batch_response(chunk.len(), response) {
+                Some(responses) => results.extend(responses.iter().map(call_result)),
+                None => {
+                    debug!(target: "uniswap.onchain", rpc = rpc_url, "endpoint answered a batch with a single response; sending calls individually");
+                    for (to, data) in chunk {
+                        results.push(self.eth_call_raw(rpc_url, to, data).await);
+                    }
                 }
             }
         }
-        // Fallback to bytes32
-        let mut data = Vec::with_capacity(4);
-        let bytes32_selector = {
-            let mut h = Keccak256::new();
-            h.update(b"symbol()bytes32"); // not standard; keep original selector
-            let out = h.finalize();
-            [out[0], out[1], out[2], out[3]]
-        };
-        data.extend_from_slice(&symbol_selector); // many tokens still use same selector but return bytes32
-        if let Ok(bytes) = self.eth_call_raw(rpc_url, token_address_hex, &data).await {
-            if let Ok(tokens) = ethabi::decode(&[ParamType::FixedBytes(32)], &bytes) {
-                if let Some(AbiToken::FixedBytes(raw)) = tokens.get(0).cloned() {
-                    let trimmed = String::from_utf8(raw.clone()).unwrap_or_default().trim_matches(char::from(0)).to_string();
-                    if !trimmed.is_empty() { return Ok(trimmed); }
-                }
-            }
+        Ok(results)
+    }
+
+    /// Latest block number reported by the RPC endpoint (used as a reachability probe)
+    #[instrument(name = "rpc_call", skip(self, rpc_url), fields(method = "eth_blockNumber"))]
+    pub async fn block_number(&self, rpc_url: &str) -> Result<u64> {
+        let body = serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "eth_blockNumber",
+            "params": []
+        });
+        let json = self.rpc_post(rpc_url, &body).await?;
+        let result_hex = json.get("result").and_then(|v| v.as_str()).unwrap_or("");
+        if result_hex.is_empty() {
+            return Err(anyhow::anyhow!("empty eth_blockNumber result"));
         }
-        Ok(token_address_hex.to_string())
+        Ok(u64::from_str_radix(result_hex.trim_start_matches("0x"), 16)?)
     }
 
-    async fn resolve_erc20_decimals(&self, rpc_url: &str, token_address_hex: &str) -> u8 {
-        use sha3::{Digest, Keccak256};
-        let selector = {
-            let mut h = Keccak256::new();
-            h.update(b"decimals()");
-            let out = h.finalize();
-            [out[0], out[1], out[2], out[3]]
+    /// Metadata of each token, in order; the uncached ones are looked up in one RPC batch.
+    /// A symbol that can't be read falls back to the address, but decimals that can't be
+    /// read are an error: amounts scaled by a guess are off by orders of magnitude. A token
+    /// is only cached once both resolve.
+    async fn token_metadata_many(&self, rpc_url: &str, tokens: &[&str]) -> Vec<Result<TokenMetadata>> {
+        let key = |token: &str| format!("token:{}:{}", self.chain, token.to_lowercase());
+        let mut found = Vec::with_capacity(tokens.len());
+        for token in tokens {
+            found.push(self.cache.get_json::<TokenMetadata>(&key(token)).await);
+        }
+        let missing: Vec<&str> = tokens.iter().zip(&found).filter(|(_, meta)| meta.is_none()).map(|(t, _)| *t).collect();
+        if missing.is_empty() {
+            return found.into_iter().flatten().map(Ok).collect();
+        }
+
+        let (symbol, decimals) = (encode_call("symbol()", &[]), encode_call("decimals()", &[]));
+        let calls: Vec<(String, Vec<u8>)> = missing
+            .iter()
+            .flat_map(|token| [(token.to_string(), symbol.clone()), (token.to_string(), decimals.clone())])
+            .collect();
+        let (results, batch_error) = match self.eth_call_batch(rpc_url, &calls).await {
+            Ok(results) => (results, None),
+            Err(e) => {
+                warn!(target: "uniswap.onchain", tokens = missing.len(), "token metadata lookup failed: {:#}", e);
+                (Vec::new(), Some(format!("{:#}", e)))
+            }
         };
-        let mut data = Vec::with_capacity(4);
-        data.extend_from_slice(&selector);
-        if let Ok(bytes) = self.eth_call_raw(rpc_url, token_address_hex, &data).await {
-            if let Ok(tokens) = ethabi::decode(&[ParamType::Uint(8)], &bytes) {
-                if let Some(AbiToken::Uint(v)) = tokens.get(0).cloned() {
-                    return v.low_u32() as u8;
-                }
+        let mut results = results.into_iter();
+        let mut resolved = Vec::with_capacity(missing.len());
+        for token in &missing {
+            let symbol = results.next().and_then(Result::ok).and_then(|bytes| decode_symbol(&bytes));
+            let decimals = match results.next() {
+                Some(Ok(bytes)) => decode_decimals(&bytes).ok_or_else(|| anyhow::anyhow!("invalid decimals() output of {}", token)),
+                Some(Err(e)) => Err(e.context(format!("reading decimals() of {}", token))),
+                None => Err(anyhow::anyhow!(
+                    "reading decimals() of {}: {}",
+                    token,
+                    batch_error.as_deref().unwrap_or("no result")
+                )),
+            };
+            let meta = decimals.map(|decimals| TokenMetadata {
+                symbol: symbol.clone().unwrap_or_else(|| token.to_string()),
+                decimals,
+            });
+            // Don't cache the fallback symbol of a failed call
+            if let (Some(_), Ok(meta)) = (&symbol, &meta) {
+                self.cache.set_json(&key(token), meta, self.cache.token_ttl()).await;
             }
+            resolved.push(meta);
         }
-        18
+        let mut resolved = resolved.into_iter();
+        found
+            .into_iter()
+            .map(|meta| meta.map(Ok).unwrap_or_else(|| resolved.next().expect("one lookup per missing token")))
+            .collect()
     }
 
-    pub async fn get_onchain_position(&self, rpc_url: &str, token_id: &str) -> Result<OnchainPosition> {
-        // Encode call data for positions(uint256)
-        let fn_selector = {
-            // keccak256("positions(uint256)")[0..4]
-            use sha3::{Digest, Keccak256};
-            let mut hasher = Keccak256::new();
-            hasher.update(b"positions(uint256)");
-            let hash = hasher.finalize();
-            [hash[0], hash[1], hash[2], hash[3]]
-        };
-        let id = U256::from_dec_str(token_id)?;
-        let encoded_args = ethabi::encode(&[AbiToken::Uint(id.into())]);
-        let mut data = Vec::with_capacity(4 + encoded_args.len());
-        data.extend_from_slice(&fn_selector);
-        data.extend_from_slice(&encoded_args);
+    /// ERC-20 decimals; an error when the call fails, never a guess
+    pub async fn token_decimals(&self, rpc_url: &str, token: &str) -> Result<u8> {
+        self.tokens_decimals(rpc_url, &[token]).await.remove(0)
+    }
 
+    /// Decimals of each token, in order, read in one RPC batch; an error for each token
+    /// whose call fails, never a guess
+    pub async fn tokens_decimals(&self, rpc_url: &str, tokens: &[&str]) -> Vec<Result<u8>> {
+        self.token_metadata_many(rpc_url, tokens).await.into_iter().map(|meta| meta.map(|meta| meta.decimals)).collect()
+    }
+
+    pub async fn get_onchain_position(&self, rpc_url: &str, token_id: &str) -> Result<OnchainPosition> {
         info!(target: "uniswap.onchain", token_id, "fetching on-chain position");
-        let to_addr = "0xC36442b4a4522E871399CD717aBDD847Ab11FE88";
-        let bytes = self.eth_call_raw(rpc_url, to_addr, &data).await?;
+        let bytes = self.eth_call_raw(rpc_url, &format!("{:?}", self.contracts.position_manager), &positions_call(token_id)?).await?;
+        self.onchain_position_from(rpc_url, token_id, &bytes).await
+    }
 
+    /// Complete a position from its `positions(uint256)` output: token metadata, pool state,
+    /// fees and amounts
+    async fn onchain_position_from(&self, rpc_url: &str, token_id: &str, bytes: &[u8]) -> Result<OnchainPosition> {
         // Decode tuple per ABI
         let output_types = vec![
             ParamType::Uint(96),               // nonce
@@ -443,34 +1302,50 @@ impl UniswapClient {
             ParamType::Uint(128),              // tokensOwed0
             ParamType::Uint(128),              // tokensOwed1
         ];
-        let tokens = ethabi::decode(&output_types, &bytes)?;
+        let tokens = ethabi::decode(&output_types, bytes)?;
 
         let operator = tokens[1].clone().into_address().unwrap();
         let token0 = tokens[2].clone().into_address().unwrap();
         let token1 = tokens[3].clone().into_address().unwrap();
         let fee_u256 = tokens[4].clone().into_uint().unwrap();
-        let tick_lower_i256 = tokens[5].clone().into_int().unwrap();
-        let tick_upper_i256 = tokens[6].clone().into_int().unwrap();
+        let tick_lower = uniswap_v3::decode_int24(tokens[5].clone().into_int().unwrap()).context("tickLower")?;
+        let tick_upper = uniswap_v3::decode_int24(tokens[6].clone().into_int().unwrap()).context("tickUpper")?;
         let liquidity = tokens[7].clone().into_uint().unwrap();
+        let inside_last0 = tokens[8].clone().into_uint().unwrap();
+        let inside_last1 = tokens[9].clone().into_uint().unwrap();
         let owed0 = tokens[10].clone().into_uint().unwrap();
         let owed1 = tokens[11].clone().into_uint().unwrap();
 
         // Resolve token symbols
         let token0_hex = format!("0x{:x}", token0);
         let token1_hex = format!("0x{:x}", token1);
-        let sym0_raw = self.resolve_erc20_symbol(rpc_url, &token0_hex).await.unwrap_or(token0_hex.clone());
-        let sym1_raw = self.resolve_erc20_symbol(rpc_url, &token1_hex).await.unwrap_or(token1_hex.clone());
-        let sym0 = self.alias_symbol(&token0_hex, &sym0_raw);
-        let sym1 = self.alias_symbol(&token1_hex, &sym1_raw);
-
-        // Decimals and price range (token1 per token0)
-        let dec0 = self.resolve_erc20_decimals(rpc_url, &token0_hex).await as i32;
-        let dec1 = self.resolve_erc20_decimals(rpc_url, &token1_hex).await as i32;
-        // Price of token1 quoted in token0 units: 1.0001^tick * 10^(dec0 - dec1)
-        let scale = 10f64.powi(dec0 - dec1);
-        let price_lower = 1.0001f64.powi(tick_lower_i256.low_u32() as i32) * scale;
-        let price_upper = 1.0001f64.powi(tick_upper_i256.low_u32() as i32) * scale;
-        let mid_price = (price_lower * price_upper).sqrt();
+        let mut metas = self.token_metadata_many(rpc_url, &[&token0_hex, &token1_hex]).await;
+        let (meta1, meta0) = (metas.remove(1)?, metas.remove(0)?);
+        let sym0 = self.alias_symbol(&token0_hex, &meta0.symbol);
+        let sym1 = self.alias_symbol(&token1_hex, &meta1.symbol);
+
+        // Price range, token1 per token0: 1.0001^tick * 10^(dec0 - dec1). Display only, so a
+        // bound that doesn't fit a Decimal (full range) shows as n/a rather than failing
+        let price_lower = display_price(uniswap_v3::price_at_tick(tick_lower, meta0.decimals, meta1.decimals));
+        let price_upper = display_price(uniswap_v3::price_at_tick(tick_upper, meta0.decimals, meta1.decimals));
+        let mid_price = display_price(uniswap_v3::mid_price(tick_lower, tick_upper, meta0.decimals, meta1.decimals));
+
+        let pool = self.pool_address(rpc_url, &token0_hex, &token1_hex, fee_u256.low_u32()).await?;
+        let fees = self
+            .range_fee_state(rpc_url, &pool, tick_lower, tick_upper)
+            .await
+            .with_context(|| format!("reading slot0 and fee growth of pool {}", pool))?;
+        let slot0 = fees.slot0;
+        let liquidity_u128 = liquidity.low_u128();
+        let uncollected0 = fee_growth::uncollected_fees(liquidity_u128, owed0, &fees.growth0, inside_last0, tick_lower, tick_upper, slot0.tick);
+        let uncollected1 = fee_growth::uncollected_fees(liquidity_u128, owed1, &fees.growth1, inside_last1, tick_lower, tick_upper, slot0.tick);
+        let (amount0, amount1) = uniswap_v3::amounts_for_liquidity(
+            slot0.sqrt_price_x96,
+            uniswap_v3::sqrt_ratio_at_tick(tick_lower)?,
+            uniswap_v3::sqrt_ratio_at_tick(tick_upper)?,
+            liquidity_u128,
+        );
+        let current_price = display_price(uniswap_v3::price_at_sqrt_ratio(slot0.sqrt_price_x96, meta0.decimals, meta1.decimals));
 
         let pos = OnchainPosition {
             token_id: token_id.to_string(),
@@ -479,19 +1354,479 @@ impl UniswapClient {
             token1: token1_hex,
             token0_symbol: sym0,
             token1_symbol: sym1,
+            token0_decimals: meta0.decimals,
+            token1_decimals: meta1.decimals,
             fee: fee_u256.low_u32(),
-            tick_lower: tick_lower_i256.low_u32() as i32,
-            tick_upper: tick_upper_i256.low_u32() as i32,
+            tick_lower,
+            tick_upper,
             liquidity: liquidity.to_string(),
+            amount0: amount0.to_string(),
+            amount1: amount1.to_string(),
             tokens_owed0: owed0.to_string(),
             tokens_owed1: owed1.to_string(),
-            price_lower_quote_per_base: format!("{:.2}", price_lower),
-            price_upper_quote_per_base: format!("{:.2}", price_upper),
-            mid_price_quote_per_base: format!("{:.2}", mid_price),
+            uncollected_fees0: uncollected0.to_string(),
+            uncollected_fees1: uncollected1.to_string(),
+            price_lower_quote_per_base: price_lower,
+            price_upper_quote_per_base: price_upper,
+            mid_price_quote_per_base: mid_price,
+            pool,
+            current_tick: slot0.tick,
+            current_price,
+            // Uniswap's convention: the lower tick is inclusive, the upper exclusive
+            in_range: tick_lower <= slot0.tick && slot0.tick < tick_upper,
         };
-        info!(target: "uniswap.onchain", token_id, liquidity = %pos.liquidity, fee = pos.fee, "fetched on-chain position");
+        info!(target: "uniswap.onchain", token_id, liquidity = %pos.liquidity, fee = pos.fee, in_range = pos.in_range, "fetched on-chain position");
         Ok(pos)
     }
+
+    /// Current owner of a position NFT (`ownerOf(uint256)` on the position manager)
+    pub async fn position_owner(&self, rpc_url: &str, token_id: &str) -> Result<String> {
+        let id = U256::from_dec_str(token_id)?;
+        let data = encode_call("ownerOf(uint256)", &[AbiToken::Uint(id)]);
+        let bytes = self.eth_call_raw(rpc_url, &format!("{:?}", self.contracts.position_manager), &data).await?;
+        let owner = ethabi::decode(&[ParamType::Address], &bytes)?
+            .remove(0)
+            .into_address()
+            .ok_or_else(|| anyhow::anyhow!("ownerOf returned no address"))?;
+        Ok(format!("0x{:x}", owner))
+    }
+
+    /// Token ids of every position NFT held by `owner`, enumerated on the position manager
+    /// (`balanceOf`, then `tokenOfOwnerByIndex` for each index in one RPC batch)
+    pub async fn owned_position_ids(&self, rpc_url: &str, owner: &str) -> Result<Vec<String>> {
+        let owner: Address = owner.parse().with_context(|| format!("invalid owner address {}", owner))?;
+        let manager = format!("{:?}", self.contracts.position_manager);
+        let data = encode_call("balanceOf(address)", &[AbiToken::Address(owner)]);
+        let bytes = self.eth_call_raw(rpc_url, &manager, &data).await?;
+        let count = ethabi::decode(&[ParamType::Uint(256)], &bytes)?
+            .remove(0)
+            .into_uint()
+            .ok_or_else(|| anyhow::anyhow!("balanceOf returned no amount"))?;
+        if count > U256::from(u16::MAX) {
+            return Err(anyhow::anyhow!("owner holds {} positions, too many to enumerate", count));
+        }
+        let calls: Vec<(String, Vec<u8>)> = (0..count.as_u64())
+            .map(|i| {
+                let args = [AbiToken::Address(owner), AbiToken::Uint(U256::from(i))];
+                (manager.clone(), encode_call("tokenOfOwnerByIndex(address,uint256)", &args))
+            })
+            .collect();
+        let mut ids = Vec::with_capacity(calls.len());
+        for (i, result) in self.eth_call_batch(rpc_url, &calls).await?.into_iter().enumerate() {
+            let bytes = result.with_context(|| format!("tokenOfOwnerByIndex({})", i))?;
+            let id = ethabi::decode(&[ParamType::Uint(256)], &bytes)?
+                .remove(0)
+                .into_uint()
+                .ok_or_else(|| anyhow::anyhow!("tokenOfOwnerByIndex returned no id"))?;
+            ids.push(id.to_string());
+        }
+        debug!(target: "uniswap.onchain", owner = ?owner, positions = ids.len(), "enumerated owned positions");
+        Ok(ids)
+    }
+
+    /// Every position NFT held by `owner`, closed (zero-liquidity) ones included. The
+    /// positions themselves are read in one RPC batch.
+    pub async fn get_positions_by_owner(&self, rpc_url: &str, owner: &str) -> Result<Vec<OnchainPosition>> {
+        let ids = self.owned_position_ids(rpc_url, owner).await?;
+        let manager = format!("{:?}", self.contracts.position_manager);
+        let calls = ids
+            .iter()
+            .map(|id| Ok((manager.clone(), positions_call(id)?)))
+            .collect::<Result<Vec<_>>>()?;
+        let mut positions = Vec::with_capacity(ids.len());
+        for (token_id, result) in ids.iter().zip(self.eth_call_batch(rpc_url, &calls).await?) {
+            let bytes = result.with_context(|| format!("positions({})", token_id))?;
+            positions.push(self.onchain_position_from(rpc_url, token_id, &bytes).await?);
+        }
+        Ok(positions)
+    }
+
+    /// Pool address for a token pair and fee tier (`getPool` on the v3 factory)
+    pub async fn pool_address(&self, rpc_url: &str, token0: &str, token1: &str, fee: u32) -> Result<String> {
+        let data = encode_call(
+            "getPool(address,address,uint24)",
+            &[
+                AbiToken::Address(token0.parse().context("invalid token0 address")?),
+                AbiToken::Address(token1.parse().context("invalid token1 address")?),
+                AbiToken::Uint(U256::from(fee)),
+            ],
+        );
+        let bytes = self.eth_call_raw(rpc_url, &format!("{:?}", self.contracts.factory), &data).await?;
+        let pool = ethabi::decode(&[ParamType::Address], &bytes)?
+            .remove(0)
+            .into_address()
+            .ok_or_else(|| anyhow::anyhow!("getPool returned no address"))?;
+        if pool.is_zero() {
+            return Err(anyhow::anyhow!("no pool for {}/{} fee {}", token0, token1, fee));
+        }
+        Ok(format!("0x{:x}", pool))
+    }
+
+    /// `collect` call data sweeping everything owed to the position into `owner`
+    pub fn collect_call(token_id: &str, owner: &str) -> Result<Vec<u8>> {
+        let max = U256::from(u128::MAX);
+        Ok(encode_call(
+            "collect((uint256,address,uint128,uint128))",
+            &[AbiToken::Tuple(vec![
+                AbiToken::Uint(U256::from_dec_str(token_id)?),
+                AbiToken::Address(owner.parse().context("invalid owner address")?),
+                AbiToken::Uint(max),
+                AbiToken::Uint(max),
+            ])],
+        ))
+    }
+
+    /// Fees a position could collect right now, in raw token units.
+    ///
+    /// `tokensOwed` only updates when the position is touched, so this simulates `collect`
+    /// from the owner, which also accrues fees earned since then.
+    pub async fn uncollected_fees(&self, rpc_url: &str, token_id: &str, owner: &str) -> Result<(U256, U256)> {
+        let data = Self::collect_call(token_id, owner)?;
+        let bytes = self.eth_call_from(rpc_url, owner, &format!("{:?}", self.contracts.position_manager), &data).await?;
+        let tokens = ethabi::decode(&[ParamType::Uint(256), ParamType::Uint(256)], &bytes)?;
+        let amount0 = tokens[0].clone().into_uint().unwrap_or_default();
+        let amount1 = tokens[1].clone().into_uint().unwrap_or_default();
+        Ok((amount0, amount1))
+    }
+
+    /// Amounts `decreaseLiquidity` would release right now, simulated from the owner
+    pub async fn decrease_liquidity_amounts(&self, rpc_url: &str, token_id: &str, owner: &str, liquidity: u128) -> Result<(U256, U256)> {
+        let deadline = u64::MAX >> 1;
+        let data = decrease_liquidity_call(token_id, liquidity, U256::zero(), U256::zero(), deadline)?;
+        let bytes = self.eth_call_from(rpc_url, owner, &format!("{:?}", self.contracts.position_manager), &data).await?;
+        let tokens = ethabi::decode(&[ParamType::Uint(256), ParamType::Uint(256)], &bytes)?;
+        Ok((tokens[0].clone().into_uint().unwrap_or_default(), tokens[1].clone().into_uint().unwrap_or_default()))
+    }
+
+    /// ERC20 `balanceOf(owner)`
+    pub async fn token_balance(&self, rpc_url: &str, token: &str, owner: &str) -> Result<U256> {
+        let data = encode_call(
+            "balanceOf(address)",
+            &[AbiToken::Address(owner.parse().context("invalid owner address")?)],
+        );
+        let bytes = self.eth_call_raw(rpc_url, token, &data).await?;
+        ethabi::decode(&[ParamType::Uint(256)], &bytes)?
+            .remove(0)
+            .into_uint()
+            .ok_or_else(|| anyhow::anyhow!("balanceOf returned no amount"))
+    }
+
+    /// ERC20 `allowance(owner, spender)`
+    pub async fn token_allowance(&self, rpc_url: &str, token: &str, owner: &str, spender: Address) -> Result<U256> {
+        let data = encode_call(
+            "allowance(address,address)",
+            &[
+                AbiToken::Address(owner.parse().context("invalid owner address")?),
+                AbiToken::Address(spender),
+            ],
+        );
+        let bytes = self.eth_call_raw(rpc_url, token, &data).await?;
+        ethabi::decode(&[ParamType::Uint(256)], &bytes)?
+            .remove(0)
+            .into_uint()
+            .ok_or_else(|| anyhow::anyhow!("allowance returned no amount"))
+    }
+
+    /// Estimated cost of collecting a position's fees, in wei
+    pub async fn collect_gas_cost_wei(&self, rpc_url: &str, token_id: &str, owner: &str) -> Result<U256> {
+        let data = Self::collect_call(token_id, owner)?;
+        let tx = serde_json::json!({ "from": owner, "to": format!("{:?}", self.contracts.position_manager), "data": format!("0x{}", hex::encode(&data)) });
+        let gas = self.rpc_quantity(rpc_url, "eth_estimateGas", serde_json::json!([tx])).await?;
+        let gas_price = self.rpc_quantity(rpc_url, "eth_gasPrice", serde_json::json!([])).await?;
+        Ok(gas.saturating_mul(gas_price))
+    }
+
+    /// Current `eth_gasPrice`, in wei
+    pub async fn gas_price_wei(&self, rpc_url: &str) -> Result<U256> {
+        self.rpc_quantity(rpc_url, "eth_gasPrice", serde_json::json!([])).await
+    }
+
+    /// USD prices for `tokens` from the subgraph's ETH-denominated prices, or from the
+    /// `[chainlink]` feeds when the subgraph can't be reached
+    pub async fn token_prices_usd(&self, tokens: &[&str]) -> Result<TokenPrices> {
+        match self.subgraph_token_prices(tokens).await {
+            Err(e) if !self.chainlink.is_empty() => {
+                warn!(target: "uniswap.chainlink", "subgraph prices unavailable, using Chainlink feeds: {:#}", e);
+                self.oracle_token_prices(tokens).await
+            }
+            prices => prices,
+        }
+    }
+
+    async fn subgraph_token_prices(&self, tokens: &[&str]) -> Result<TokenPrices> {
+        let query = r#"
+        query TokenPrices($ids: [ID!]!) {
+          bundle(id: "1") { ethPriceUSD }
+          tokens(where: { id_in: $ids }) { id derivedETH }
+        }
+        "#;
+        let ids: Vec<String> = tokens.iter().map(|t| t.to_lowercase()).collect();
+        let req = GraphRequest {
+            query: query.to_string(),
+            variables: serde_json::json!({ "ids": ids }),
+        };
+
+        // camelCase would give ethPriceUsd / derivedEth; the subgraph spells the acronyms out
+        #[derive(Serialize, Deserialize)]
+        struct Bundle { #[serde(rename = "ethPriceUSD")] eth_price_usd: String }
+        #[derive(Serialize, Deserialize)]
+        struct TokenPrice { id: String, #[serde(rename = "derivedETH")] derived_eth: String }
+        #[derive(Serialize, Deserialize)]
+        struct PricesData { bundle: Option<Bundle>, tokens: Vec<TokenPrice> }
+
+        let body: PricesData = self.post_cached(&req).await?;
+        let eth_usd: f64 = body
+            .bundle
+            .ok_or_else(|| anyhow::anyhow!("subgraph has no ETH price bundle"))?
+            .eth_price_usd
+            .parse()?;
+        let tokens = body
+            .tokens
+            .into_iter()
+            .filter_map(|t| Some((t.id.to_lowercase(), t.derived_eth.parse::<f64>().ok()? * eth_usd)))
+            .collect();
+        Ok(TokenPrices { eth_usd, tokens })
+    }
+
+    /// Current sqrt price and tick of a pool
+    pub async fn get_slot0(&self, rpc_url: &str, pool: &str) -> Result<Slot0> {
+        decode_slot0(&self.eth_call_raw(rpc_url, pool, &encode_call("slot0()", &[])).await?)
+    }
+
+    /// The pool's `slot0`, global fee growth and the fee growth outside a range's ticks, in
+    /// one RPC batch
+    pub async fn range_fee_state(&self, rpc_url: &str, pool: &str, tick_lower: i32, tick_upper: i32) -> Result<RangeFeeState> {
+        let calls: Vec<(String, Vec<u8>)> = [
+            encode_call("slot0()", &[]),
+            encode_call("feeGrowthGlobal0X128()", &[]),
+            encode_call("feeGrowthGlobal1X128()", &[]),
+            encode_call("ticks(int24)", &[int24(tick_lower)]),
+            encode_call("ticks(int24)", &[int24(tick_upper)]),
+        ]
+        .into_iter()
+        .map(|data| (pool.to_string(), data))
+        .collect();
+        let mut results = self.eth_call_batch(rpc_url, &calls).await?.into_iter();
+        let mut next = || results.next().ok_or_else(|| anyhow::anyhow!("missing result in batch"))?;
+        let slot0 = decode_slot0(&next()?).context("slot0")?;
+        let global0 = decode_uint(&next()?).context("feeGrowthGlobal0X128")?;
+        let global1 = decode_uint(&next()?).context("feeGrowthGlobal1X128")?;
+        let (lower0, lower1) = decode_tick_fee_growth(&next()?).context("ticks(tickLower)")?;
+        let (upper0, upper1) = decode_tick_fee_growth(&next()?).context("ticks(tickUpper)")?;
+        Ok(RangeFeeState {
+            slot0,
+            growth0: FeeGrowth {
+                global_x128: global0,
+                lower_outside_x128: lower0,
+                upper_outside_x128: upper0,
+            },
+            growth1: FeeGrowth {
+                global_x128: global1,
+                lower_outside_x128: lower1,
+                upper_outside_x128: upper1,
+            },
+        })
+    }
+
+    /// Current tick of a pool (from `slot0()`)
+    pub async fn pool_tick(&self, rpc_url: &str, pool: &str) -> Result<i32> {
+        Ok(self.get_slot0(rpc_url, pool).await?.tick)
+    }
+
+    /// Current tick of each pool, in order, read in one RPC batch
+    pub async fn pool_ticks(&self, rpc_url: &str, pools: &[&str]) -> Result<Vec<Result<i32>>> {
+        let data = encode_call("slot0()", &[]);
+        let calls: Vec<(String, Vec<u8>)> = pools.iter().map(|pool| (pool.to_string(), data.clone())).collect();
+        Ok(self
+            .eth_call_batch(rpc_url, &calls)
+            .await?
+            .into_iter()
+            .map(|result| Ok(decode_slot0(&result?)?.tick))
+            .collect())
+    }
 }
 
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_batch_responses_are_matched_by_id() {
+        let response = serde_json::json!([
+            { "jsonrpc": "2.0", "id": 2, "result": "0x12" },
+            { "jsonrpc": "2.0", "id": 0, "result": "0x" },
+            { "jsonrpc": "2.0", "id": 7, "result": "0x01" }
+        ]);
+        let responses = split_batch_response(3, response).unwrap();
+        assert_eq!(call_result(&responses[2]).unwrap(), vec![0x12]);
+        assert!(call_result(&responses[0]).unwrap().is_empty());
+        // id 1 never came back; id 7 wasn't asked for
+        assert!(call_result(&responses[1]).unwrap_err().to_string().contains("no response in batch"));
+
+        let single = serde_json::json!({ "jsonrpc": "2.0", "id": null, "error": { "code": -32600, "message": "batch not supported" } });
+        assert!(split_batch_response(3, single).is_none());
+    }
+
+    #[test]
+    fn test_full_range_prices_display() {
+        // WETH/USDC full range: the upper bound's price doesn't fit a Decimal
+        let (lower, upper) = (uniswap_v3::MIN_TICK / 60 * 60, uniswap_v3::MAX_TICK / 60 * 60);
+        assert_eq!(display_price(uniswap_v3::price_at_tick(upper, 18, 6)), "n/a");
+        assert_ne!(display_price(uniswap_v3::price_at_tick(lower, 18, 6)), "n/a");
+        assert_ne!(display_price(uniswap_v3::mid_price(lower, upper, 18, 6)), "n/a");
+        assert_eq!(display_price(uniswap_v3::price_at_tick(0, 18, 18)), "1");
+    }
+
+    #[test]
+    fn test_symbol_decoding() {
+        let string = ethabi::encode(&[AbiToken::String("WETH".to_string())]);
+        assert_eq!(decode_symbol(&string).as_deref(), Some("WETH"));
+        let mut raw = b"MKR".to_vec();
+        raw.resize(32, 0);
+        assert_eq!(decode_symbol(&ethabi::encode(&[AbiToken::FixedBytes(raw)])).as_deref(), Some("MKR"));
+        assert_eq!(decode_symbol(&[]), None);
+        assert_eq!(decode_decimals(&ethabi::encode(&[AbiToken::Uint(U256::from(6))])), Some(6));
+    }
+
+    #[test]
+    fn test_slot0_and_tick_decoding() {
+        let sqrt_price_x96 = U256::from_dec_str("1987654321987654321987654").unwrap();
+        let bytes = ethabi::encode(&[
+            AbiToken::Uint(sqrt_price_x96),
+            int24(-198_080),
+            AbiToken::Uint(U256::from(3)),
+            AbiToken::Uint(U256::from(10)),
+            AbiToken::Uint(U256::from(10)),
+            AbiToken::Uint(U256::zero()),
+            AbiToken::Bool(true),
+        ]);
+        assert_eq!(decode_slot0(&bytes).unwrap(), Slot0 { sqrt_price_x96, tick: -198_080 });
+        assert!(decode_slot0(&bytes[..64]).is_err());
+
+        let outside0 = U256::MAX - U256::from(7);
+        let tick = ethabi::encode(&[
+            AbiToken::Uint(U256::from(5)),
+            AbiToken::Int(U256::MAX),
+            AbiToken::Uint(outside0),
+            AbiToken::Uint(U256::from(9)),
+            AbiToken::Int(U256::zero()),
+            AbiToken::Uint(U256::zero()),
+            AbiToken::Uint(U256::zero()),
+            AbiToken::Bool(true),
+        ]);
+        assert_eq!(decode_tick_fee_growth(&tick).unwrap(), (outside0, U256::from(9)));
+    }
+
+    #[tokio::test]
+    async fn test_replay_answers_rpc_calls_from_fixtures() {
+        let dir = std::env::temp_dir().join(format!("uniswap-fixtures-{}", std::process::id()));
+        let mut config = Config::default();
+        let development = config.development.as_mut().unwrap();
+        development.test_mode = true;
+        development.fixtures = Some(crate::config::FixturesConfig {
+            mode: crate::config::FixtureMode::Replay,
+            dir: dir.display().to_string(),
+        });
+        let chain = config.active_chain();
+        let request = serde_json::json!({ "jsonrpc": "2.0", "id": 1, "method": "eth_gasPrice", "params": [] });
+        Fixtures::new(&dir, crate::config::FixtureMode::Record)
+            .through("rpc", &chain.name, &request, async { Ok(serde_json::json!({ "jsonrpc": "2.0", "id": 1, "result": "0x3b9aca00" })) })
+            .await
+            .unwrap();
+
+        // The endpoint is unreachable; only the fixture can answer
+        let client = UniswapClient::from_config(&config);
+        assert_eq!(client.gas_price_wei("http://127.0.0.1:9").await.unwrap(), U256::from(1_000_000_000u64));
+
+        // Outside test mode the fixtures are ignored
+        config.development.as_mut().unwrap().test_mode = false;
+        assert!(UniswapClient::from_config(&config).gas_price_wei("http://127.0.0.1:9").await.is_err());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_owned_position_ids_enumerate_the_position_manager() {
+        let dir = std::env::temp_dir().join(format!("uniswap-owner-fixtures-{}", std::process::id()));
+        let mut config = Config::default();
+        let development = config.development.as_mut().unwrap();
+        development.test_mode = true;
+        development.fixtures = Some(crate::config::FixturesConfig {
+            mode: crate::config::FixtureMode::Replay,
+            dir: dir.display().to_string(),
+        });
+        let chain = config.active_chain();
+        let owner: Address = "0x00000000000000000000000000000000000000aa".parse().unwrap();
+        let manager = POSITION_MANAGER.to_lowercase();
+        let uint = |n: u64| format!("0x{}", hex::encode(ethabi::encode(&[AbiToken::Uint(U256::from(n))])));
+
+        let recorder = Fixtures::new(&dir, crate::config::FixtureMode::Record);
+        let mut balance = eth_call_request(&manager, &encode_call("balanceOf(address)", &[AbiToken::Address(owner)]));
+        balance["id"] = serde_json::json!(1);
+        let answer = serde_json::json!({ "jsonrpc": "2.0", "id": 1, "result": uint(2) });
+        recorder.through("rpc", &chain.name, &balance, async { Ok(answer) }).await.unwrap();
+        let batch: Vec<serde_json::Value> = (0..2u64)
+            .map(|i| {
+                let args = [AbiToken::Address(owner), AbiToken::Uint(U256::from(i))];
+                let mut request = eth_call_request(&manager, &encode_call("tokenOfOwnerByIndex(address,uint256)", &args));
+                request["id"] = serde_json::json!(i);
+                request
+            })
+            .collect();
+        let answers = serde_json::json!([
+            { "jsonrpc": "2.0", "id": 1, "result": uint(4242) },
+            { "jsonrpc": "2.0", "id": 0, "result": uint(17) }
+        ]);
+        recorder.through("rpc", &chain.name, &serde_json::Value::Array(batch), async { Ok(answers) }).await.unwrap();
+
+        let client = UniswapClient::from_config(&config);
+        let ids = client.owned_position_ids("http://127.0.0.1:9", &format!("{:?}", owner)).await.unwrap();
+        assert_eq!(ids, vec!["17".to_string(), "4242".to_string()]);
+        assert!(client.owned_position_ids("http://127.0.0.1:9", "not-an-address").await.is_err());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_unreadable_decimals_are_an_error() {
+        let dir = std::env::temp_dir().join(format!("uniswap-decimals-fixtures-{}", std::process::id()));
+        let mut config = Config::default();
+        let development = config.development.as_mut().unwrap();
+        development.test_mode = true;
+        development.fixtures = Some(crate::config::FixturesConfig {
+            mode: crate::config::FixtureMode::Replay,
+            dir: dir.display().to_string(),
+        });
+        let chain = config.active_chain();
+        let (usdc, flaky) = ("0x00000000000000000000000000000000000000c1", "0x00000000000000000000000000000000000000c2");
+        let batch: Vec<serde_json::Value> = [usdc, flaky]
+            .iter()
+            .flat_map(|token| [(token, "symbol()"), (token, "decimals()")])
+            .enumerate()
+            .map(|(id, (token, call))| {
+                let mut request = eth_call_request(token, &encode_call(call, &[]));
+                request["id"] = serde_json::json!(id);
+                request
+            })
+            .collect();
+        let string = |s: &str| format!("0x{}", hex::encode(ethabi::encode(&[AbiToken::String(s.to_string())])));
+        let uint = |n: u64| format!("0x{}", hex::encode(ethabi::encode(&[AbiToken::Uint(U256::from(n))])));
+        let answers = serde_json::json!([
+            { "jsonrpc": "2.0", "id": 0, "result": string("USDC") },
+            { "jsonrpc": "2.0", "id": 1, "result": uint(6) },
+            { "jsonrpc": "2.0", "id": 2, "result": string("USDT") },
+            { "jsonrpc": "2.0", "id": 3, "error": { "code": -32000, "message": "header not found" } }
+        ]);
+        Fixtures::new(&dir, crate::config::FixtureMode::Record)
+            .through("rpc", &chain.name, &serde_json::Value::Array(batch), async { Ok(answers) })
+            .await
+            .unwrap();
+
+        // A failed decimals() call is an error, not 18 decimals
+        let client = UniswapClient::from_config(&config);
+        let decimals = client.tokens_decimals("http://127.0.0.1:9", &[usdc, flaky]).await;
+        assert_eq!(decimals[0].as_ref().unwrap(), &6);
+        assert!(format!("{:#}", decimals[1].as_ref().unwrap_err()).contains("decimals()"));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,189 @@
+//! Chainlink USD price feeds, read with `latestRoundData()` over `eth_call`. An answer is
+//! only used when it's positive, from a completed round and updated within the feed's
+//! maximum age; anything else is stale and the token goes unpriced.
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+use anyhow::{anyhow, bail, Context, Result};
+use chrono::{DateTime, TimeZone, Utc};
+use ethabi::ParamType;
+use ethereum_types::U256;
+use tracing::{debug, warn};
+
+use super::{decode_decimals, encode_call, TokenPrices, UniswapClient};
+use crate::config::ChainlinkConfig;
+use crate::utils::to_units;
+
+/// Token name of the feed that prices the native token
+const NATIVE: &str = "eth";
+
+/// One configured feed
+#[derive(Debug, Clone, PartialEq)]
+pub struct Feed {
+    /// Lowercase token address, or `eth`
+    pub token: String,
+    pub aggregator: String,
+    pub max_age: Duration,
+}
+
+impl Feed {
+    pub fn from_config(config: &ChainlinkConfig) -> Vec<Self> {
+        config
+            .feeds
+            .iter()
+            .map(|feed| Self {
+                token: feed.token.to_lowercase(),
+                aggregator: feed.aggregator.clone(),
+                max_age: Duration::from_secs(feed.max_age_secs.unwrap_or(config.max_age_secs)),
+            })
+            .collect()
+    }
+}
+
+/// `latestRoundData()` output
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RoundData {
+    pub round_id: U256,
+    /// Two's complement `int256`
+    pub answer: U256,
+    pub updated_at: u64,
+    pub answered_in_round: U256,
+}
+
+/// A fresh feed answer
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct OraclePrice {
+    pub usd: f64,
+    pub updated_at: DateTime<Utc>,
+}
+
+fn decode_round_data(bytes: &[u8]) -> Result<RoundData> {
+    let output_types = [
+        ParamType::Uint(80),  // roundId
+        ParamType::Int(256),  // answer
+        ParamType::Uint(256), // startedAt
+        ParamType::Uint(256), // updatedAt
+        ParamType::Uint(80),  // answeredInRound
+    ];
+    let tokens = ethabi::decode(&output_types, bytes)?;
+    let word = |i: usize| tokens[i].clone().into_uint().or_else(|| tokens[i].clone().into_int());
+    let missing = || anyhow!("latestRoundData returned too few values");
+    let updated_at = word(3).ok_or_else(missing)?;
+    Ok(RoundData {
+        round_id: word(0).ok_or_else(missing)?,
+        answer: word(1).ok_or_else(missing)?,
+        updated_at: if updated_at > U256::from(u64::MAX) { u64::MAX } else { updated_at.as_u64() },
+        answered_in_round: word(4).ok_or_else(missing)?,
+    })
+}
+
+/// The USD price in `round` scaled by the feed's `decimals`, if the answer can be trusted at `now`
+fn fresh_price(round: &RoundData, decimals: u8, max_age: Duration, now: DateTime<Utc>) -> Result<OraclePrice> {
+    if round.answer.is_zero() || round.answer.bit(255) {
+        bail!("non-positive answer");
+    }
+    if round.updated_at == 0 || round.answered_in_round < round.round_id {
+        bail!("round {} is incomplete", round.round_id);
+    }
+    let updated_at = Utc
+        .timestamp_opt(i64::try_from(round.updated_at).unwrap_or(i64::MAX), 0)
+        .single()
+        .ok_or_else(|| anyhow!("invalid updatedAt {}", round.updated_at))?;
+    let age = (now - updated_at).to_std().unwrap_or_default();
+    if age > max_age {
+        bail!("last updated {}s ago, over the {}s limit", age.as_secs(), max_age.as_secs());
+    }
+    Ok(OraclePrice {
+        usd: to_units(round.answer, decimals),
+        updated_at,
+    })
+}
+
+impl UniswapClient {
+    /// Fresh prices of `feeds`, keyed by their token; a feed whose call fails or whose answer
+    /// is stale is left out
+    pub async fn chainlink_prices(&self, rpc_url: &str, feeds: &[Feed]) -> Result<HashMap<String, OraclePrice>> {
+        let (latest, decimals) = (encode_call("latestRoundData()", &[]), encode_call("decimals()", &[]));
+        let calls: Vec<(String, Vec<u8>)> = feeds
+            .iter()
+            .flat_map(|feed| [(feed.aggregator.clone(), latest.clone()), (feed.aggregator.clone(), decimals.clone())])
+            .collect();
+        let mut results = self.eth_call_batch(rpc_url, &calls).await?.into_iter();
+        let now = Utc::now();
+        let mut prices = HashMap::with_capacity(feeds.len());
+        for feed in feeds {
+            let (round, feed_decimals) = (results.next(), results.next());
+            let price = round
+                .ok_or_else(|| anyhow!("missing latestRoundData result"))?
+                .and_then(|bytes| decode_round_data(&bytes))
+                .and_then(|round| {
+                    let bytes = feed_decimals.ok_or_else(|| anyhow!("missing decimals result"))??;
+                    let decimals = decode_decimals(&bytes).ok_or_else(|| anyhow!("invalid decimals"))?;
+                    fresh_price(&round, decimals, feed.max_age, now)
+                });
+            match price {
+                Ok(price) => {
+                    debug!(target: "uniswap.chainlink", token = %feed.token, usd = price.usd, updated_at = %price.updated_at, "Chainlink price");
+                    prices.insert(feed.token.clone(), price);
+                }
+                Err(e) => warn!(target: "uniswap.chainlink", token = %feed.token, aggregator = %feed.aggregator, "skipping Chainlink feed: {:#}", e),
+            }
+        }
+        Ok(prices)
+    }
+
+    /// `tokens` priced from the configured feeds, for when the subgraph is unreachable. Needs
+    /// a fresh `ETH` feed for `eth_usd`; tokens without a fresh feed are left out.
+    pub(super) async fn oracle_token_prices(&self, tokens: &[&str]) -> Result<TokenPrices> {
+        let wanted: Vec<String> = tokens.iter().map(|t| t.to_lowercase()).collect();
+        let feeds: Vec<Feed> = self
+            .chainlink
+            .iter()
+            .filter(|feed| feed.token == NATIVE || wanted.contains(&feed.token))
+            .cloned()
+            .collect();
+        let rpc_url = self.endpoints.primary().context("no RPC endpoint to read Chainlink feeds from")?;
+        let mut prices = self.chainlink_prices(rpc_url, &feeds).await?;
+        let eth_usd = prices.remove(NATIVE).context("no fresh Chainlink ETH/USD price")?.usd;
+        Ok(TokenPrices {
+            eth_usd,
+            tokens: prices.into_iter().map(|(token, price)| (token, price.usd)).collect(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethabi::Token as AbiToken;
+
+    fn round(answer: U256, updated_at: u64, round_id: u64, answered_in_round: u64) -> Vec<u8> {
+        ethabi::encode(&[
+            AbiToken::Uint(U256::from(round_id)),
+            AbiToken::Int(answer),
+            AbiToken::Uint(U256::from(updated_at)),
+            AbiToken::Uint(U256::from(updated_at)),
+            AbiToken::Uint(U256::from(answered_in_round)),
+        ])
+    }
+
+    #[test]
+    fn test_round_data_staleness() {
+        let now = Utc.timestamp_opt(1_700_003_600, 0).unwrap();
+        let hour = Duration::from_secs(3600);
+        // ETH/USD at $2,012.34567 with 8 decimals, updated an hour ago
+        let fresh = decode_round_data(&round(U256::from(201_234_567_000u64), 1_700_000_000, 7, 7)).unwrap();
+        let price = fresh_price(&fresh, 8, hour, now).unwrap();
+        assert!((price.usd - 2012.34567).abs() < 1e-9);
+        assert_eq!(price.updated_at.timestamp(), 1_700_000_000);
+
+        let stale = fresh_price(&fresh, 8, Duration::from_secs(3599), now).unwrap_err();
+        assert!(stale.to_string().contains("over the 3599s limit"), "{}", stale);
+        let incomplete = decode_round_data(&round(U256::from(1), 1_700_000_000, 8, 7)).unwrap();
+        assert!(fresh_price(&incomplete, 8, hour, now).is_err());
+        let negative = decode_round_data(&round(U256::MAX, 1_700_000_000, 7, 7)).unwrap();
+        assert!(fresh_price(&negative, 8, hour, now).unwrap_err().to_string().contains("non-positive"));
+        assert!(decode_round_data(&[0u8; 64]).is_err());
+    }
+}
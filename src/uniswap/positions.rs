@@ -0,0 +1,143 @@
+//! Tracked Uniswap V3 positions (`uniswap.position_ids`) as [`Position`]s: each NFT read
+//! on-chain, valued at the subgraph's (or Chainlink's) token prices, and carrying its pool
+//! and range so the recommender can price fees, impermanent loss and rebalances.
+
+use anyhow::{Context, Result};
+use ethereum_types::U256;
+use tracing::{debug, info, warn};
+
+use super::{OnchainPosition, TokenPrices, UniswapClient};
+use crate::position::{LiquidityMetrics, Position};
+use crate::utils::{from_base_units, to_decimal};
+
+pub const PROTOCOL: &str = "uniswap_v3";
+
+pub struct UniswapAdapter {
+    client: UniswapClient,
+    rpc_url: String,
+    position_ids: Vec<String>,
+}
+
+impl UniswapAdapter {
+    pub fn new(client: UniswapClient, rpc_url: &str, position_ids: &[String]) -> Self {
+        Self {
+            client,
+            rpc_url: rpc_url.to_string(),
+            position_ids: position_ids.to_vec(),
+        }
+    }
+
+    /// One position per tracked token id, keyed by the token id
+    pub async fn positions(&self) -> Result<Vec<Position>> {
+        let mut onchain = Vec::with_capacity(self.position_ids.len());
+        for token_id in &self.position_ids {
+            let position = self
+                .client
+                .get_onchain_position(&self.rpc_url, token_id)
+                .await
+                .with_context(|| format!("reading Uniswap position {}", token_id))?;
+            let owner = self
+                .client
+                .position_owner(&self.rpc_url, token_id)
+                .await
+                .with_context(|| format!("reading the owner of Uniswap position {}", token_id))?;
+            onchain.push((position, owner));
+        }
+        let tokens: Vec<&str> = onchain.iter().flat_map(|(p, _)| [p.token0.as_str(), p.token1.as_str()]).collect();
+        let prices = self.client.token_prices_usd(&tokens).await.context("pricing Uniswap positions")?;
+        let positions = onchain
+            .iter()
+            .map(|(position, owner)| position_from(position, owner, &prices))
+            .collect::<Result<Vec<_>>>()?;
+        info!(target: "uniswap", positions = positions.len(), "fetched Uniswap positions");
+        Ok(positions)
+    }
+}
+
+/// `onchain` as a position of `owner`: token0 holdings, valued at both tokens' prices
+/// (unpriced positions are worth 0)
+fn position_from(onchain: &OnchainPosition, owner: &str, prices: &TokenPrices) -> Result<Position> {
+    let amount0 = U256::from_dec_str(&onchain.amount0).with_context(|| format!("invalid amount0 {}", onchain.amount0))?;
+    let value_usd = onchain.value_usd(prices).unwrap_or_else(|| {
+        warn!(target: "uniswap", token_id = %onchain.token_id, "no USD price for the position's tokens");
+        0.0
+    });
+    let mut position = Position::new(
+        onchain.token_id.clone(),
+        owner.to_lowercase(),
+        onchain.token0.clone(),
+        from_base_units(amount0, onchain.token0_decimals)?,
+        to_decimal(value_usd).round_dp(2),
+    );
+    position.liquidity = Some(Box::new(LiquidityMetrics {
+        protocol: PROTOCOL.to_string(),
+        pool: onchain.pool.to_lowercase(),
+        token0: onchain.token0.clone(),
+        token1: onchain.token1.clone(),
+        token0_symbol: onchain.token0_symbol.clone(),
+        token1_symbol: onchain.token1_symbol.clone(),
+        fee: onchain.fee,
+        tick_lower: onchain.tick_lower,
+        tick_upper: onchain.tick_upper,
+        current_tick: onchain.current_tick,
+        liquidity: onchain.liquidity.clone(),
+    }));
+    debug!(target: "uniswap", token_id = %onchain.token_id, value_usd, in_range = onchain.in_range, "liquidity position");
+    Ok(position)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal::Decimal;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_position_from_onchain() {
+        let onchain = OnchainPosition {
+            token_id: "42".to_string(),
+            operator: "0x0000000000000000000000000000000000000000".to_string(),
+            token0: "0xweth".to_string(),
+            token1: "0xusdc".to_string(),
+            token0_symbol: "WETH".to_string(),
+            token1_symbol: "USDC".to_string(),
+            token0_decimals: 18,
+            token1_decimals: 6,
+            fee: 500,
+            tick_lower: -200_000,
+            tick_upper: -190_000,
+            liquidity: "123456789".to_string(),
+            amount0: "1500000000000000000".to_string(),
+            amount1: "1000000000".to_string(),
+            tokens_owed0: "0".to_string(),
+            tokens_owed1: "0".to_string(),
+            uncollected_fees0: "0".to_string(),
+            uncollected_fees1: "0".to_string(),
+            price_lower_quote_per_base: "2061.15".to_string(),
+            price_upper_quote_per_base: "5602.68".to_string(),
+            mid_price_quote_per_base: "3398.25".to_string(),
+            pool: "0xPOOL".to_string(),
+            current_tick: -189_000,
+            current_price: "6190.1".to_string(),
+            in_range: false,
+        };
+        let prices = TokenPrices {
+            eth_usd: 2000.0,
+            tokens: HashMap::from([("0xweth".to_string(), 2000.0), ("0xusdc".to_string(), 1.0)]),
+        };
+        let position = position_from(&onchain, "0xOWNER", &prices).unwrap();
+        assert_eq!(position.id, "42");
+        assert_eq!(position.user_address, "0xowner");
+        assert_eq!(position.amount, Decimal::new(15, 1));
+        // 1.5 WETH at $2000 plus 1000 USDC
+        assert_eq!(position.value_usd, Decimal::new(4000, 0));
+        let liquidity = position.liquidity.as_deref().unwrap();
+        assert_eq!(liquidity.pool, "0xpool");
+        assert_eq!(liquidity.pair(), "WETH/USDC");
+        assert!(!liquidity.in_range());
+
+        // Unpriced tokens leave the value at 0 rather than failing the read
+        let unpriced = position_from(&onchain, "0xowner", &TokenPrices::default()).unwrap();
+        assert_eq!(unpriced.value_usd, Decimal::ZERO);
+    }
+}
@@ -1,9 +1,20 @@
+//! Utility functions for the position recommender
+
 use anyhow::Result;
-use rust_decimal::Decimal;
-use rust_decimal::prelude::ToPrimitive;
+use ethereum_types::{Address, U256};
+use rust_decimal::{Decimal, RoundingStrategy};
+use rust_decimal::prelude::{FromPrimitive, ToPrimitive};
+use sha3::{Digest, Keccak256};
 use std::str::FromStr;
 
-/// Utility functions for the position recommender
+use crate::storage::Candle;
+
+pub mod fee_growth;
+pub mod rolling;
+pub mod timeseries;
+pub mod uniswap_v3;
+
+use rolling::{rolling_stats, RollingWindow};
 
 /// Parse a decimal from string with proper error handling
 pub fn parse_decimal(s: &str) -> Result<Decimal> {
@@ -11,9 +22,88 @@ pub fn parse_decimal(s: &str) -> Result<Decimal> {
         .map_err(|e| anyhow::anyhow!("Failed to parse decimal '{}': {}", s, e))
 }
 
-/// Format a decimal as USD currency
+/// Format a decimal as USD currency with thousands separators, e.g. $1,234.56
 pub fn format_usd(decimal: &Decimal) -> String {
-    format!("${:.2}", decimal.to_f64().unwrap_or(0.0))
+    let value = decimal.to_f64().unwrap_or(0.0);
+    let sign = if value < 0.0 { "-" } else { "" };
+    format!("{}${}", sign, format_thousands(value.abs(), 2))
+}
+
+/// Format with thousands separators and `decimals` fraction digits, e.g. 1,234,567.89
+pub fn format_thousands(value: f64, decimals: usize) -> String {
+    let formatted = format!("{:.*}", decimals, value.abs());
+    let (whole, fraction) = formatted.split_once('.').unwrap_or((&formatted, ""));
+    let mut grouped = String::with_capacity(formatted.len() + whole.len() / 3);
+    for (i, digit) in whole.chars().enumerate() {
+        if i > 0 && (whole.len() - i) % 3 == 0 {
+            grouped.push(',');
+        }
+        grouped.push(digit);
+    }
+    let sign = if value < 0.0 && formatted.chars().any(|c| c.is_ascii_digit() && c != '0') { "-" } else { "" };
+    if fraction.is_empty() {
+        format!("{}{}", sign, grouped)
+    } else {
+        format!("{}{}.{}", sign, grouped, fraction)
+    }
+}
+
+fn round_significant(value: f64, figures: usize) -> f64 {
+    if value == 0.0 || !value.is_finite() {
+        return value;
+    }
+    let magnitude = value.abs().log10().floor() as i32;
+    let factor = 10f64.powi(magnitude + 1 - figures.max(1) as i32);
+    (value / factor).round() * factor
+}
+
+/// Round to `figures` significant figures, dropping trailing zeros, e.g. 0.000123456 with 3
+/// figures is 0.000123 and 123456 is 123000
+pub fn format_significant(value: f64, figures: usize) -> String {
+    let rounded = round_significant(value, figures);
+    if rounded == 0.0 || !rounded.is_finite() {
+        return if rounded.is_finite() { "0".to_string() } else { rounded.to_string() };
+    }
+    let magnitude = rounded.abs().log10().floor() as i32;
+    let decimals = (figures.max(1) as i32 - 1 - magnitude).max(0) as usize;
+    let formatted = format!("{:.*}", decimals, rounded);
+    if formatted.contains('.') {
+        formatted.trim_end_matches('0').trim_end_matches('.').to_string()
+    } else {
+        formatted
+    }
+}
+
+/// A decimal rounded to `figures` significant figures, without trailing zeros, e.g. 2499.63
+pub fn format_significant_decimal(value: Decimal, figures: u32) -> String {
+    value.round_sf(figures.max(1)).unwrap_or(value).normalize().to_string()
+}
+
+/// Compact notation with 3 significant figures, e.g. 1.23K, 45.6M, 4.5B; values under a
+/// thousand keep 2 decimals
+pub fn format_compact(value: f64) -> String {
+    const UNITS: [(f64, &str); 4] = [(1e12, "T"), (1e9, "B"), (1e6, "M"), (1e3, "K")];
+    let rounded = round_significant(value.abs(), 3);
+    let sign = if value < 0.0 { "-" } else { "" };
+    match UNITS.iter().find(|(scale, _)| rounded >= *scale) {
+        Some((scale, suffix)) => format!("{}{}{}", sign, format_significant(rounded / scale, 3), suffix),
+        None => format!("{}{:.2}", sign, value.abs()),
+    }
+}
+
+/// Compact USD amount, e.g. $1.23M
+pub fn format_usd_compact(value: f64) -> String {
+    let compact = format_compact(value);
+    match compact.strip_prefix('-') {
+        Some(abs) => format!("-${}", abs),
+        None => format!("${}", compact),
+    }
+}
+
+/// [`format_usd_compact`] of a numeric string such as a subgraph USD field; the string
+/// itself when it isn't a number
+pub fn format_usd_compact_str(raw: &str) -> String {
+    raw.parse::<f64>().map_or_else(|_| raw.to_string(), format_usd_compact)
 }
 
 /// Calculate percentage change between two values
@@ -25,16 +115,50 @@ pub fn calculate_percentage_change(old_value: f64, new_value: f64) -> f64 {
     }
 }
 
+/// Decimal variant of [`calculate_percentage_change`]
+pub fn calculate_percentage_change_decimal(old_value: Decimal, new_value: Decimal) -> Decimal {
+    if old_value.is_zero() {
+        Decimal::ZERO
+    } else {
+        (new_value - old_value) / old_value * Decimal::ONE_HUNDRED
+    }
+}
+
+/// Convert an f64 to a Decimal at its shortest round-tripping representation (0.1 stays 0.1);
+/// 0 for NaN and infinities
+pub fn to_decimal(value: f64) -> Decimal {
+    Decimal::from_f64(value).unwrap_or_default()
+}
+
+/// Sum f64 USD values in Decimal, so long ledgers don't accumulate binary rounding error
+pub fn sum_decimal(values: impl IntoIterator<Item = f64>) -> f64 {
+    values.into_iter().map(to_decimal).sum::<Decimal>().to_f64().unwrap_or(0.0)
+}
+
 /// Validate Ethereum address format
 pub fn is_valid_ethereum_address(address: &str) -> bool {
     address.starts_with("0x") && address.len() == 42 && address[2..].chars().all(|c| c.is_ascii_hexdigit())
 }
 
+/// EIP-55 mixed-case checksum encoding of an address
+pub fn to_checksum_address(address: &Address) -> String {
+    let lower = hex::encode(address.as_bytes());
+    let hash = Keccak256::digest(lower.as_bytes());
+    let checksummed: String = lower
+        .chars()
+        .enumerate()
+        .map(|(i, c)| {
+            let nibble = (hash[i / 2] >> if i % 2 == 0 { 4 } else { 0 }) & 0x0f;
+            if nibble >= 8 { c.to_ascii_uppercase() } else { c }
+        })
+        .collect();
+    format!("0x{}", checksummed)
+}
+
 /// Convert wei to ether
 pub fn wei_to_ether(wei: &str) -> Result<Decimal> {
-    let wei_decimal = parse_decimal(wei)?;
-    let ether = wei_decimal / Decimal::from(1_000_000_000_000_000_000u64);
-    Ok(ether)
+    let wei = U256::from_dec_str(wei).map_err(|e| anyhow::anyhow!("Failed to parse wei amount '{}': {:?}", wei, e))?;
+    from_base_units(wei, 18)
 }
 
 /// Convert ether to wei
@@ -42,19 +166,38 @@ pub fn ether_to_wei(ether: &Decimal) -> Decimal {
     ether * Decimal::from(1_000_000_000_000_000_000u64)
 }
 
+/// Convert a raw on-chain amount to whole token units, e.g. 1_500_000 with 6 decimals
+/// (USDC) is 1.5. Fails when the result doesn't fit a Decimal (about 7.9e28)
+pub fn from_base_units(raw: U256, decimals: u8) -> Result<Decimal> {
+    let digits = raw.to_string();
+    let decimals = decimals as usize;
+    let (whole, fraction) = if digits.len() > decimals {
+        digits.split_at(digits.len() - decimals)
+    } else {
+        ("0", digits.as_str())
+    };
+    let amount = format!("{}.{:0>width$}", whole, fraction, width = decimals);
+    Decimal::from_str(amount.trim_end_matches('.'))
+        .map_err(|e| anyhow::anyhow!("{} base units with {} decimals is out of range: {}", raw, decimals, e))
+}
+
+/// Convert whole token units to the raw on-chain amount, truncating digits the token
+/// can't represent, e.g. 0.123456789 WBTC (8 decimals) is 12_345_678
+pub fn to_base_units(amount: &Decimal, decimals: u8) -> Result<U256> {
+    if amount.is_sign_negative() && !amount.is_zero() {
+        anyhow::bail!("cannot convert negative amount {} to base units", amount);
+    }
+    let truncated = amount.round_dp_with_strategy(decimals as u32, RoundingStrategy::ToZero);
+    let mantissa = U256::from(truncated.mantissa().unsigned_abs());
+    let exponent = decimals as u32 - truncated.scale();
+    mantissa
+        .checked_mul(U256::exp10(exponent as usize))
+        .ok_or_else(|| anyhow::anyhow!("{} with {} decimals overflows uint256", amount, decimals))
+}
+
 /// Calculate simple moving average
 pub fn calculate_sma(values: &[f64], period: usize) -> Vec<f64> {
-    if values.len() < period {
-        return vec![];
-    }
-    
-    let mut sma = Vec::new();
-    for i in (period - 1)..values.len() {
-        let sum: f64 = values[(i - period + 1)..=i].iter().sum();
-        sma.push(sum / period as f64);
-    }
-    
-    sma
+    rolling_stats(values, period).map(|stats| stats.mean).collect()
 }
 
 /// Calculate exponential moving average
@@ -75,6 +218,127 @@ pub fn calculate_ema(values: &[f64], period: usize) -> Vec<f64> {
     ema
 }
 
+/// Calculate the relative strength index with Wilder's smoothing; the first value is for
+/// `values[period]`
+pub fn calculate_rsi(values: &[f64], period: usize) -> Vec<f64> {
+    if period == 0 || values.len() <= period {
+        return vec![];
+    }
+
+    let changes: Vec<f64> = values.windows(2).map(|w| w[1] - w[0]).collect();
+    let mut avg_gain = changes[..period].iter().map(|c| c.max(0.0)).sum::<f64>() / period as f64;
+    let mut avg_loss = changes[..period].iter().map(|c| (-c).max(0.0)).sum::<f64>() / period as f64;
+    let rsi = |gain: f64, loss: f64| {
+        if loss == 0.0 {
+            if gain == 0.0 { 50.0 } else { 100.0 }
+        } else {
+            100.0 - 100.0 / (1.0 + gain / loss)
+        }
+    };
+
+    let mut result = vec![rsi(avg_gain, avg_loss)];
+    for &change in &changes[period..] {
+        avg_gain = (avg_gain * (period - 1) as f64 + change.max(0.0)) / period as f64;
+        avg_loss = (avg_loss * (period - 1) as f64 + (-change).max(0.0)) / period as f64;
+        result.push(rsi(avg_gain, avg_loss));
+    }
+
+    result
+}
+
+/// MACD line, its signal line and their difference, one value per input
+#[derive(Debug, Clone, PartialEq)]
+pub struct Macd {
+    pub macd: Vec<f64>,
+    pub signal: Vec<f64>,
+    pub histogram: Vec<f64>,
+}
+
+/// Calculate MACD from fast/slow EMAs (commonly 12/26 with a 9-period signal)
+pub fn calculate_macd(values: &[f64], fast: usize, slow: usize, signal: usize) -> Macd {
+    let fast_ema = calculate_ema(values, fast);
+    let slow_ema = calculate_ema(values, slow);
+    let macd: Vec<f64> = fast_ema.iter().zip(&slow_ema).map(|(f, s)| f - s).collect();
+    let signal = calculate_ema(&macd, signal);
+    let histogram = macd.iter().zip(&signal).map(|(m, s)| m - s).collect();
+
+    Macd { macd, signal, histogram }
+}
+
+/// Bollinger bands: SMA plus/minus `width` standard deviations; the first value is for
+/// `values[period - 1]`
+#[derive(Debug, Clone, PartialEq)]
+pub struct BollingerBands {
+    pub middle: Vec<f64>,
+    pub upper: Vec<f64>,
+    pub lower: Vec<f64>,
+}
+
+/// Calculate Bollinger bands using the population standard deviation of each window
+pub fn calculate_bollinger_bands(values: &[f64], period: usize, width: f64) -> BollingerBands {
+    let stats: Vec<_> = rolling_stats(values, period).collect();
+    let middle = stats.iter().map(|s| s.mean).collect();
+    let upper = stats.iter().map(|s| s.mean + width * s.population_std).collect();
+    let lower = stats.iter().map(|s| s.mean - width * s.population_std).collect();
+
+    BollingerBands { middle, upper, lower }
+}
+
+/// Calculate the average true range with Wilder's smoothing; the first value is for
+/// `candles[period]`, as the first candle has no previous close
+pub fn calculate_atr(candles: &[Candle], period: usize) -> Vec<f64> {
+    if period == 0 || candles.len() <= period {
+        return vec![];
+    }
+
+    let true_ranges: Vec<f64> = candles
+        .windows(2)
+        .map(|w| {
+            let (prev_close, candle) = (w[0].close, &w[1]);
+            (candle.high - candle.low)
+                .max((candle.high - prev_close).abs())
+                .max((candle.low - prev_close).abs())
+        })
+        .collect();
+    let mut atr = true_ranges[..period].iter().sum::<f64>() / period as f64;
+
+    let mut result = vec![atr];
+    for &true_range in &true_ranges[period..] {
+        atr = (atr * (period - 1) as f64 + true_range) / period as f64;
+        result.push(atr);
+    }
+
+    result
+}
+
+/// Stochastic oscillator: %K and its `d_period` SMA %D, both in 0-100
+#[derive(Debug, Clone, PartialEq)]
+pub struct Stochastic {
+    /// One value per candle from `candles[k_period - 1]`
+    pub k: Vec<f64>,
+    /// One value per %K from `k[d_period - 1]`
+    pub d: Vec<f64>,
+}
+
+/// Calculate the stochastic oscillator; a flat window reads 50
+pub fn calculate_stochastic(candles: &[Candle], k_period: usize, d_period: usize) -> Stochastic {
+    if k_period == 0 || d_period == 0 {
+        return Stochastic { k: vec![], d: vec![] };
+    }
+
+    let (mut highs, mut lows) = (RollingWindow::new(k_period), RollingWindow::new(k_period));
+    let k: Vec<f64> = candles
+        .iter()
+        .filter_map(|candle| {
+            let (high, low) = (highs.push(candle.high), lows.push(candle.low));
+            Some(normalize(candle.close, low?.min, high?.max) * 100.0)
+        })
+        .collect();
+    let d = calculate_sma(&k, d_period);
+
+    Stochastic { k, d }
+}
+
 /// Calculate volatility (standard deviation)
 pub fn calculate_volatility(values: &[f64]) -> f64 {
     if values.len() < 2 {
@@ -89,6 +353,169 @@ pub fn calculate_volatility(values: &[f64]) -> f64 {
     variance.sqrt()
 }
 
+/// Quantiles `qs` (each in 0..=1) of `values`, interpolating linearly between the closest
+/// ranks; NaNs are ignored and an empty input gives no quantiles
+pub fn calculate_quantiles(values: &[f64], qs: &[f64]) -> Vec<f64> {
+    let mut sorted: Vec<f64> = values.iter().copied().filter(|v| !v.is_nan()).collect();
+    if sorted.is_empty() {
+        return vec![];
+    }
+    sorted.sort_by(f64::total_cmp);
+
+    qs.iter()
+        .map(|q| {
+            let rank = q.clamp(0.0, 1.0) * (sorted.len() - 1) as f64;
+            let (below, above) = (rank.floor() as usize, rank.ceil() as usize);
+            sorted[below] + (sorted[above] - sorted[below]) * (rank - below as f64)
+        })
+        .collect()
+}
+
+/// Quantile `q` (0 to 1) of `values`; see [`calculate_quantiles`]
+pub fn calculate_quantile(values: &[f64], q: f64) -> Option<f64> {
+    calculate_quantiles(values, &[q]).first().copied()
+}
+
+/// Percentile `p` (0 to 100) of `values`, e.g. 95 for the 95th
+pub fn calculate_percentile(values: &[f64], p: f64) -> Option<f64> {
+    calculate_quantile(values, p / 100.0)
+}
+
+/// Standard scores of `values` against their mean and sample standard deviation; all 0
+/// when the values don't vary
+pub fn calculate_z_scores(values: &[f64]) -> Vec<f64> {
+    let std = calculate_volatility(values);
+    if std == 0.0 {
+        return vec![0.0; values.len()];
+    }
+    let mean = values.iter().sum::<f64>() / values.len() as f64;
+    values.iter().map(|v| (v - mean) / std).collect()
+}
+
+/// Indices of values more than `threshold` standard deviations from the mean
+pub fn z_score_anomalies(values: &[f64], threshold: f64) -> Vec<usize> {
+    calculate_z_scores(values)
+        .iter()
+        .enumerate()
+        .filter(|(_, z)| z.abs() > threshold)
+        .map(|(i, _)| i)
+        .collect()
+}
+
+/// Sample covariance of two aligned series; 0 when shorter than 2 or of different lengths
+pub fn calculate_covariance(x: &[f64], y: &[f64]) -> f64 {
+    if x.len() != y.len() || x.len() < 2 {
+        return 0.0;
+    }
+
+    let mean_x = x.iter().sum::<f64>() / x.len() as f64;
+    let mean_y = y.iter().sum::<f64>() / y.len() as f64;
+    x.iter().zip(y).map(|(a, b)| (a - mean_x) * (b - mean_y)).sum::<f64>() / (x.len() - 1) as f64
+}
+
+/// Pearson correlation of two aligned series in -1..=1; 0 when either is constant
+pub fn calculate_pearson_correlation(x: &[f64], y: &[f64]) -> f64 {
+    let denominator = calculate_volatility(x) * calculate_volatility(y);
+    clamp(safe_divide(calculate_covariance(x, y), denominator), -1.0, 1.0)
+}
+
+/// Spearman rank correlation of two aligned series; ties share their average rank
+pub fn calculate_spearman_correlation(x: &[f64], y: &[f64]) -> f64 {
+    calculate_pearson_correlation(&ranks(x), &ranks(y))
+}
+
+/// 1-based ranks of `values`, averaged over ties
+fn ranks(values: &[f64]) -> Vec<f64> {
+    let mut order: Vec<usize> = (0..values.len()).collect();
+    order.sort_by(|&a, &b| values[a].total_cmp(&values[b]));
+
+    let mut ranks = vec![0.0; values.len()];
+    let mut start = 0;
+    while start < order.len() {
+        let mut end = start + 1;
+        while end < order.len() && values[order[end]] == values[order[start]] {
+            end += 1;
+        }
+        let rank = (start + end + 1) as f64 / 2.0;
+        for &i in &order[start..end] {
+            ranks[i] = rank;
+        }
+        start = end;
+    }
+
+    ranks
+}
+
+/// Sample covariance matrix of aligned return series: `matrix[i][j]` is the covariance of
+/// `series[i]` and `series[j]`
+pub fn calculate_covariance_matrix(series: &[Vec<f64>]) -> Vec<Vec<f64>> {
+    series
+        .iter()
+        .map(|a| series.iter().map(|b| calculate_covariance(a, b)).collect())
+        .collect()
+}
+
+/// Mean of Decimal values; 0 when empty
+pub fn calculate_mean_decimal(values: &[Decimal]) -> Decimal {
+    if values.is_empty() {
+        return Decimal::ZERO;
+    }
+    values.iter().sum::<Decimal>() / Decimal::from(values.len())
+}
+
+/// Sample variance of Decimal values; 0 for fewer than 2
+pub fn calculate_variance_decimal(values: &[Decimal]) -> Decimal {
+    if values.len() < 2 {
+        return Decimal::ZERO;
+    }
+    let mean = calculate_mean_decimal(values);
+    values.iter().map(|x| (x - mean) * (x - mean)).sum::<Decimal>() / Decimal::from(values.len() - 1)
+}
+
+/// Seconds in the 365-day year yields are annualized over
+pub const SECS_PER_YEAR: f64 = 365.0 * 86_400.0;
+
+/// How often an APY assumes earnings are reinvested
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Compounding {
+    /// `n` times a year, e.g. 365 for daily
+    PerYear(u32),
+    Continuous,
+}
+
+/// Simple annualization of a yield earned over `period_secs` (0.001 over a day is 0.365)
+pub fn periodic_yield_to_apr(periodic_yield: f64, period_secs: f64) -> f64 {
+    safe_divide(periodic_yield * SECS_PER_YEAR, period_secs)
+}
+
+/// Yield over `period_secs` at a simple `apr`
+pub fn apr_to_periodic_yield(apr: f64, period_secs: f64) -> f64 {
+    apr * period_secs / SECS_PER_YEAR
+}
+
+/// APY of `apr` reinvested at `compounding`
+pub fn apr_to_apy(apr: f64, compounding: Compounding) -> f64 {
+    match compounding {
+        Compounding::PerYear(0) => apr,
+        Compounding::PerYear(n) => (1.0 + apr / n as f64).powf(n as f64) - 1.0,
+        Compounding::Continuous => apr.exp_m1(),
+    }
+}
+
+/// APR that gives `apy` when reinvested at `compounding`; the inverse of [`apr_to_apy`]
+pub fn apy_to_apr(apy: f64, compounding: Compounding) -> f64 {
+    match compounding {
+        Compounding::PerYear(0) => apy,
+        Compounding::PerYear(n) => ((1.0 + apy).powf(1.0 / n as f64) - 1.0) * n as f64,
+        Compounding::Continuous => apy.ln_1p(),
+    }
+}
+
+/// Convert a raw token amount to whole units given the token's decimals
+pub fn to_units(raw: U256, decimals: u8) -> f64 {
+    raw.to_string().parse::<f64>().unwrap_or(0.0) / 10f64.powi(decimals as i32)
+}
+
 /// Safe division that handles zero division
 pub fn safe_divide(numerator: f64, denominator: f64) -> f64 {
     if denominator == 0.0 {
@@ -129,6 +556,12 @@ mod tests {
         assert!(!is_valid_ethereum_address("742d35Cc6634C0532925a3b8D0C4C5C5C5C5C5C5"));
     }
 
+    #[test]
+    fn test_checksum_address() {
+        let address: Address = "0x5aaeb6053f3e94c9b9a09f33669435e7ef1beaed".parse().unwrap();
+        assert_eq!(to_checksum_address(&address), "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed");
+    }
+
     #[test]
     fn test_calculate_percentage_change() {
         assert_eq!(calculate_percentage_change(100.0, 110.0), 10.0);
@@ -143,6 +576,187 @@ mod tests {
         assert_eq!(sma, vec![2.0, 3.0, 4.0]);
     }
 
+    #[test]
+    fn test_rsi_calculation() {
+        let rising: Vec<f64> = (1..=20).map(f64::from).collect();
+        assert!(calculate_rsi(&rising, 14).iter().all(|&v| v == 100.0));
+        assert!(calculate_rsi(&rising[..14], 14).is_empty());
+
+        let values = vec![1.0, 2.0, 1.0, 2.0, 1.0];
+        let rsi = calculate_rsi(&values, 2);
+        assert_eq!(rsi.len(), 3);
+        assert!((rsi[0] - 50.0).abs() < 1e-9);
+        // Wilder smoothing: gains/losses (0.75, 0.25) -> 75, then (0.375, 0.625) -> 37.5
+        assert!((rsi[1] - 75.0).abs() < 1e-9, "{:?}", rsi);
+        assert!((rsi[2] - 37.5).abs() < 1e-9, "{:?}", rsi);
+    }
+
+    #[test]
+    fn test_macd_calculation() {
+        let flat = vec![3.0; 10];
+        let macd = calculate_macd(&flat, 3, 6, 2);
+        assert_eq!(macd.macd.len(), 10);
+        assert!(macd.macd.iter().chain(&macd.histogram).all(|v| v.abs() < 1e-12));
+
+        let rising: Vec<f64> = (1..=30).map(f64::from).collect();
+        let macd = calculate_macd(&rising, 12, 26, 9);
+        assert!(macd.macd.last().unwrap() > &0.0, "fast EMA leads in an uptrend");
+        assert_eq!(macd.histogram.len(), 30);
+    }
+
+    #[test]
+    fn test_bollinger_bands() {
+        let bands = calculate_bollinger_bands(&[2.0, 4.0, 4.0, 4.0, 5.0, 5.0, 7.0, 9.0], 8, 2.0);
+        assert_eq!(bands.middle, vec![5.0]);
+        assert_eq!(bands.upper, vec![9.0]);
+        assert_eq!(bands.lower, vec![1.0]);
+        assert!(calculate_bollinger_bands(&[1.0, 2.0], 3, 2.0).middle.is_empty());
+    }
+
+    fn candle(high: f64, low: f64, close: f64) -> Candle {
+        Candle {
+            pool_id: "0xpool".to_string(),
+            interval_secs: 3600,
+            start: chrono::Utc::now(),
+            open: close,
+            high,
+            low,
+            close,
+            volume: 0.0,
+        }
+    }
+
+    #[test]
+    fn test_atr_calculation() {
+        let candles = vec![candle(10.0, 8.0, 9.0), candle(11.0, 9.0, 10.0), candle(14.0, 12.0, 13.0), candle(13.0, 12.0, 12.5)];
+        // True ranges: 2, max(2, 4, 2) = 4 (gap up), 1
+        let atr = calculate_atr(&candles, 2);
+        assert_eq!(atr, vec![3.0, 2.0]);
+        assert!(calculate_atr(&candles[..2], 2).is_empty());
+    }
+
+    #[test]
+    fn test_stochastic_oscillator() {
+        let candles = vec![candle(10.0, 0.0, 5.0), candle(10.0, 0.0, 10.0), candle(10.0, 0.0, 0.0), candle(5.0, 5.0, 5.0)];
+        let stochastic = calculate_stochastic(&candles, 1, 2);
+        assert_eq!(stochastic.k, vec![50.0, 100.0, 0.0, 50.0]);
+        assert_eq!(stochastic.d, vec![75.0, 50.0, 25.0]);
+
+        let stochastic = calculate_stochastic(&candles, 3, 1);
+        assert_eq!(stochastic.k, vec![0.0, 50.0]);
+    }
+
+    #[test]
+    fn test_correlation_and_covariance() {
+        let x = vec![1.0, 2.0, 3.0, 4.0, 5.0];
+        let doubled: Vec<f64> = x.iter().map(|v| v * 2.0).collect();
+        let inverted: Vec<f64> = x.iter().map(|v| -v).collect();
+        assert_eq!(calculate_covariance(&x, &x), 2.5);
+        assert!((calculate_pearson_correlation(&x, &doubled) - 1.0).abs() < 1e-12);
+        assert!((calculate_pearson_correlation(&x, &inverted) + 1.0).abs() < 1e-12);
+        assert_eq!(calculate_pearson_correlation(&x, &[1.0; 5]), 0.0);
+
+        // Monotonic but not linear: rank correlation is exact where Pearson is not
+        let cubed: Vec<f64> = x.iter().map(|v| v.powi(3)).collect();
+        assert!(calculate_pearson_correlation(&x, &cubed) < 1.0);
+        assert!((calculate_spearman_correlation(&x, &cubed) - 1.0).abs() < 1e-12);
+        assert_eq!(ranks(&[3.0, 1.0, 3.0, 2.0]), vec![3.5, 1.0, 3.5, 2.0]);
+
+        let matrix = calculate_covariance_matrix(&[x.clone(), doubled]);
+        assert_eq!(matrix, vec![vec![2.5, 5.0], vec![5.0, 10.0]]);
+    }
+
+    #[test]
+    fn test_decimal_stats() {
+        let values: Vec<Decimal> = ["0.1", "0.2", "0.3"].iter().map(|v| parse_decimal(v).unwrap()).collect();
+        assert_eq!(calculate_mean_decimal(&values), parse_decimal("0.2").unwrap());
+        assert_eq!(calculate_variance_decimal(&values), parse_decimal("0.01").unwrap());
+        assert_eq!(calculate_mean_decimal(&[]), Decimal::ZERO);
+        assert_eq!(
+            calculate_percentage_change_decimal(Decimal::from(100), Decimal::from(110)),
+            Decimal::from(10)
+        );
+        assert_eq!(calculate_percentage_change_decimal(Decimal::ZERO, Decimal::ONE), Decimal::ZERO);
+
+        // 0.1 + 0.2 is 0.30000000000000004 in f64
+        assert_eq!(sum_decimal([0.1, 0.2]), 0.3);
+        assert_eq!(to_decimal(f64::NAN), Decimal::ZERO);
+    }
+
+    #[test]
+    fn test_base_unit_conversion() {
+        let usdc = from_base_units(U256::from(1_500_000u64), 6).unwrap();
+        assert_eq!(usdc, parse_decimal("1.5").unwrap());
+        assert_eq!(from_base_units(U256::from(5u64), 8).unwrap(), parse_decimal("0.00000005").unwrap());
+        assert_eq!(from_base_units(U256::from(42u64), 0).unwrap(), Decimal::from(42));
+        assert_eq!(wei_to_ether("2500000000000000000").unwrap(), parse_decimal("2.5").unwrap());
+        assert!(from_base_units(U256::MAX, 18).is_err());
+
+        assert_eq!(to_base_units(&usdc, 6).unwrap(), U256::from(1_500_000u64));
+        assert_eq!(to_base_units(&parse_decimal("0.123456789").unwrap(), 8).unwrap(), U256::from(12_345_678u64));
+        assert_eq!(to_base_units(&Decimal::from(3), 18).unwrap(), U256::from(3_000_000_000_000_000_000u128));
+        assert!(to_base_units(&Decimal::from(-1), 6).is_err());
+    }
+
+    #[test]
+    fn test_number_formatting() {
+        assert_eq!(format_thousands(1_234_567.891, 2), "1,234,567.89");
+        assert_eq!(format_thousands(999.0, 0), "999");
+        assert_eq!(format_thousands(-1_000.5, 1), "-1,000.5");
+        assert_eq!(format_usd(&parse_decimal("-98765.4321").unwrap()), "-$98,765.43");
+
+        assert_eq!(format_significant(0.000123456, 3), "0.000123");
+        assert_eq!(format_significant(123_456.0, 3), "123000");
+        assert_eq!(format_significant(2.5, 4), "2.5");
+        assert_eq!(format_significant(0.0, 3), "0");
+        assert_eq!(format_significant_decimal(Decimal::new(2_499_631_234, 6), 6), "2499.63");
+        assert_eq!(format_significant_decimal(Decimal::new(123_456, 0), 3), "123000");
+        assert_eq!(format_significant_decimal(Decimal::new(1, 12), 6), "0.000000000001");
+
+        assert_eq!(format_usd_compact(1_234_567.0), "$1.23M");
+        assert_eq!(format_usd_compact(4_500_000_000.0), "$4.5B");
+        assert_eq!(format_usd_compact(999_999.0), "$1M");
+        assert_eq!(format_usd_compact(-12_345.0), "-$12.3K");
+        assert_eq!(format_usd_compact(950.126), "$950.13");
+        assert_eq!(format_usd_compact_str("183524719.128374651928374651"), "$184M");
+        assert_eq!(format_usd_compact_str("n/a"), "n/a");
+    }
+
+    #[test]
+    fn test_yield_conversions() {
+        let apr = periodic_yield_to_apr(0.001, 86_400.0);
+        assert!((apr - 0.365).abs() < 1e-12);
+        assert!((apr_to_periodic_yield(apr, 86_400.0) - 0.001).abs() < 1e-12);
+        assert_eq!(periodic_yield_to_apr(0.001, 0.0), 0.0);
+
+        assert!((apr_to_apy(0.12, Compounding::PerYear(12)) - 0.126825).abs() < 1e-6);
+        assert!((apr_to_apy(0.12, Compounding::Continuous) - 0.127497).abs() < 1e-6);
+        assert!((apr_to_apy(0.12, Compounding::PerYear(1)) - 0.12).abs() < 1e-12);
+        for compounding in [Compounding::PerYear(365), Compounding::Continuous, Compounding::PerYear(0)] {
+            let apy = apr_to_apy(0.25, compounding);
+            assert!((apy_to_apr(apy, compounding) - 0.25).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn test_quantiles_and_z_scores() {
+        let values = vec![15.0, 20.0, 35.0, 40.0, 50.0];
+        assert_eq!(calculate_quantile(&values, 0.5), Some(35.0));
+        assert_eq!(calculate_percentile(&values, 40.0), Some(29.0));
+        assert_eq!(calculate_quantiles(&[5.0, 1.0, f64::NAN, 3.0], &[0.0, 0.25, 1.0]), vec![1.0, 2.0, 5.0]);
+        assert_eq!(calculate_quantile(&[], 0.5), None);
+        assert_eq!(calculate_quantile(&[7.0], 0.9), Some(7.0));
+
+        let z = calculate_z_scores(&[2.0, 4.0, 6.0]);
+        assert_eq!(z, vec![-1.0, 0.0, 1.0]);
+        assert_eq!(calculate_z_scores(&[3.0; 4]), vec![0.0; 4]);
+
+        let mut gas = vec![20.0; 30];
+        gas[7] = 21.0;
+        gas[12] = 300.0;
+        assert_eq!(z_score_anomalies(&gas, 3.0), vec![12]);
+    }
+
     #[test]
     fn test_normalize() {
         assert_eq!(normalize(5.0, 0.0, 10.0), 0.5);
@@ -0,0 +1,100 @@
+//! Uncollected fee accounting as the core pool and position manager do it: a position's fees
+//! are its liquidity times the growth in fees per unit of liquidity inside its range since
+//! the position was last touched, on top of the `tokensOwed` already credited to it.
+//!
+//! Fee growth values are Q128.128 and overflow by design, so differences wrap like the
+//! contracts' unchecked arithmetic.
+
+use ethereum_types::U256;
+
+use super::uniswap_v3::mul_div;
+
+/// Fee growth per unit of liquidity of one token, pool-wide and outside the range's ticks
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct FeeGrowth {
+    pub global_x128: U256,
+    /// `feeGrowthOutsideX128` of the range's lower tick
+    pub lower_outside_x128: U256,
+    /// `feeGrowthOutsideX128` of the range's upper tick
+    pub upper_outside_x128: U256,
+}
+
+impl FeeGrowth {
+    /// Fee growth inside `[tick_lower, tick_upper)` with the pool at `tick_current`, as
+    /// `Tick.getFeeGrowthInside`
+    pub fn inside(&self, tick_lower: i32, tick_upper: i32, tick_current: i32) -> U256 {
+        let below = if tick_current >= tick_lower {
+            self.lower_outside_x128
+        } else {
+            self.global_x128.overflowing_sub(self.lower_outside_x128).0
+        };
+        let above = if tick_current < tick_upper {
+            self.upper_outside_x128
+        } else {
+            self.global_x128.overflowing_sub(self.upper_outside_x128).0
+        };
+        self.global_x128.overflowing_sub(below).0.overflowing_sub(above).0
+    }
+}
+
+/// Fees earned by `liquidity` since the position's fee growth checkpoint `inside_last_x128`
+pub fn fees_accrued(liquidity: u128, inside_x128: U256, inside_last_x128: U256) -> U256 {
+    let growth = inside_x128.overflowing_sub(inside_last_x128).0;
+    // liquidity * growth / 2^128 < 2^256, so it always fits
+    mul_div(U256::from(liquidity), growth, U256::one() << 128).expect("bounded by 2^256")
+}
+
+/// Everything `collect` would pay out: fees credited as `tokens_owed` plus those accrued since
+pub fn uncollected_fees(
+    liquidity: u128,
+    tokens_owed: U256,
+    growth: &FeeGrowth,
+    inside_last_x128: U256,
+    tick_lower: i32,
+    tick_upper: i32,
+    tick_current: i32,
+) -> U256 {
+    let inside = growth.inside(tick_lower, tick_upper, tick_current);
+    tokens_owed.saturating_add(fees_accrued(liquidity, inside, inside_last_x128))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn x128(value: u64) -> U256 {
+        U256::from(value) << 128
+    }
+
+    #[test]
+    fn test_fee_growth_inside_by_current_tick() {
+        let growth = |lower, upper| FeeGrowth {
+            global_x128: x128(100),
+            lower_outside_x128: x128(lower),
+            upper_outside_x128: x128(upper),
+        };
+        // In range, outside values are the growth below the lower and above the upper tick
+        assert_eq!(growth(10, 30).inside(-60, 60, 0), x128(60));
+        // Below the range both ticks' outside values are the growth above them
+        assert_eq!(growth(50, 20).inside(-60, 60, -120), x128(30));
+        // Above the range both are the growth below them
+        assert_eq!(growth(10, 70).inside(-60, 60, 60), x128(60));
+    }
+
+    #[test]
+    fn test_uncollected_fees_wrap_like_the_contracts() {
+        // The global accumulator overflowed past the checkpoint
+        let last = U256::MAX - x128(5) + 1;
+        let growth = FeeGrowth {
+            global_x128: x128(3),
+            ..Default::default()
+        };
+        let inside = growth.inside(-60, 60, 0);
+        assert_eq!(inside, x128(3));
+        assert_eq!(fees_accrued(1_000, inside, last), U256::from(8_000));
+        assert_eq!(uncollected_fees(1_000, U256::from(42), &growth, last, -60, 60, 0), U256::from(8_042));
+        // Nothing accrues without growth since the checkpoint, or without liquidity
+        assert_eq!(uncollected_fees(1_000, U256::from(42), &growth, inside, -60, 60, 0), U256::from(42));
+        assert_eq!(fees_accrued(0, inside, last), U256::zero());
+    }
+}
@@ -0,0 +1,158 @@
+//! Rolling-window statistics updated in O(1) amortized per value, so indicators and
+//! volatility over long histories don't rescan every window.
+
+use std::collections::VecDeque;
+
+/// Statistics of one full window
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RollingStats {
+    pub sum: f64,
+    pub mean: f64,
+    /// Sample standard deviation (n - 1); 0 for a window of one
+    pub std: f64,
+    /// Population standard deviation (n)
+    pub population_std: f64,
+    pub min: f64,
+    pub max: f64,
+}
+
+/// Fixed-size window over a stream of values. The mean and squared deviations are updated
+/// with Welford's method rather than running sums, which keeps them stable over long series.
+#[derive(Debug, Clone)]
+pub struct RollingWindow {
+    period: usize,
+    values: VecDeque<f64>,
+    mean: f64,
+    m2: f64,
+    /// Indices and values of candidate minimums/maximums, monotonic from the front
+    mins: VecDeque<(usize, f64)>,
+    maxs: VecDeque<(usize, f64)>,
+    pushed: usize,
+}
+
+impl RollingWindow {
+    /// `period` must be at least 1
+    pub fn new(period: usize) -> Self {
+        assert!(period > 0, "rolling window period must be at least 1");
+        Self {
+            period,
+            values: VecDeque::with_capacity(period + 1),
+            mean: 0.0,
+            m2: 0.0,
+            mins: VecDeque::new(),
+            maxs: VecDeque::new(),
+            pushed: 0,
+        }
+    }
+
+    /// Add a value, evicting the oldest once full; the window's stats when it is full
+    pub fn push(&mut self, value: f64) -> Option<RollingStats> {
+        let index = self.pushed;
+        self.pushed += 1;
+        self.values.push_back(value);
+
+        if self.values.len() > self.period {
+            let old = self.values.pop_front().expect("window is non-empty");
+            let old_mean = self.mean;
+            self.mean += (value - old) / self.period as f64;
+            self.m2 = (self.m2 + (value - old) * (value - self.mean + old - old_mean)).max(0.0);
+        } else {
+            let delta = value - self.mean;
+            self.mean += delta / self.values.len() as f64;
+            self.m2 += delta * (value - self.mean);
+        }
+
+        let oldest = index + 1 - self.values.len();
+        while self.mins.back().is_some_and(|&(_, v)| v >= value) {
+            self.mins.pop_back();
+        }
+        self.mins.push_back((index, value));
+        while self.mins.front().is_some_and(|&(i, _)| i < oldest) {
+            self.mins.pop_front();
+        }
+        while self.maxs.back().is_some_and(|&(_, v)| v <= value) {
+            self.maxs.pop_back();
+        }
+        self.maxs.push_back((index, value));
+        while self.maxs.front().is_some_and(|&(i, _)| i < oldest) {
+            self.maxs.pop_front();
+        }
+
+        self.is_full().then(|| self.stats())
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.values.len() == self.period
+    }
+
+    fn stats(&self) -> RollingStats {
+        let n = self.values.len() as f64;
+        RollingStats {
+            sum: self.mean * n,
+            mean: self.mean,
+            std: if n > 1.0 { (self.m2 / (n - 1.0)).sqrt() } else { 0.0 },
+            population_std: (self.m2 / n).sqrt(),
+            min: self.mins.front().map_or(f64::NAN, |&(_, v)| v),
+            max: self.maxs.front().map_or(f64::NAN, |&(_, v)| v),
+        }
+    }
+}
+
+/// Stats of every full window of `period` values, the first ending at `values[period - 1]`;
+/// nothing when `period` is 0
+pub fn rolling_stats(values: &[f64], period: usize) -> impl Iterator<Item = RollingStats> + '_ {
+    let mut window = (period > 0).then(|| RollingWindow::new(period));
+    values.iter().filter_map(move |&value| window.as_mut()?.push(value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Recomputes each window from scratch
+    fn naive(values: &[f64], period: usize) -> Vec<RollingStats> {
+        values
+            .windows(period)
+            .map(|w| {
+                let n = w.len() as f64;
+                let mean = w.iter().sum::<f64>() / n;
+                let ss = w.iter().map(|x| (x - mean).powi(2)).sum::<f64>();
+                RollingStats {
+                    sum: w.iter().sum(),
+                    mean,
+                    std: if n > 1.0 { (ss / (n - 1.0)).sqrt() } else { 0.0 },
+                    population_std: (ss / n).sqrt(),
+                    min: w.iter().cloned().fold(f64::INFINITY, f64::min),
+                    max: w.iter().cloned().fold(f64::NEG_INFINITY, f64::max),
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_matches_recomputation() {
+        // Deterministic pseudo-random values at a large level, where running sums lose precision
+        let mut state = 42u64;
+        let values: Vec<f64> = (0..5_000)
+            .map(|_| {
+                state = state.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+                1e6 + (state >> 40) as f64 / 1e3
+            })
+            .collect();
+
+        for period in [1, 2, 7, 50] {
+            let rolling: Vec<RollingStats> = rolling_stats(&values, period).collect();
+            let expected = naive(&values, period);
+            assert_eq!(rolling.len(), expected.len());
+            for (got, want) in rolling.iter().zip(&expected) {
+                assert_eq!((got.min, got.max), (want.min, want.max));
+                assert!((got.mean - want.mean).abs() < 1e-6, "{:?} vs {:?}", got, want);
+                assert!((got.sum - want.sum).abs() < 1e-3);
+                assert!((got.std - want.std).abs() < 1e-4, "{:?} vs {:?}", got, want);
+                assert!((got.population_std - want.population_std).abs() < 1e-4);
+            }
+        }
+        assert_eq!(rolling_stats(&values, 0).count(), 0);
+        assert_eq!(rolling_stats(&values[..3], 4).count(), 0);
+    }
+}
@@ -0,0 +1,125 @@
+//! Resampling of irregular timestamped observations onto a fixed interval, and alignment of
+//! several series on one index, so they can be compared point by point.
+
+use chrono::{DateTime, TimeZone, Utc};
+
+/// How a grid point between two observations gets its value
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Fill {
+    /// The latest observation at or before the point
+    Forward,
+    /// Linear between the observations either side of the point
+    Interpolate,
+}
+
+/// Series resampled onto a shared index: `values[i][j]` is series `i` at `index[j]`
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AlignedSeries {
+    pub index: Vec<DateTime<Utc>>,
+    pub values: Vec<Vec<f64>>,
+}
+
+/// Grid of multiples of `interval_secs` (since the epoch) within `[start, end]`
+fn grid(start: DateTime<Utc>, end: DateTime<Utc>, interval_secs: u32) -> Vec<DateTime<Utc>> {
+    if interval_secs == 0 || start > end {
+        return vec![];
+    }
+    let interval = interval_secs as i64;
+    let first = start.timestamp() + (interval - start.timestamp().rem_euclid(interval)) % interval;
+    (first..=end.timestamp())
+        .step_by(interval as usize)
+        .filter_map(|secs| Utc.timestamp_opt(secs, 0).single())
+        .collect()
+}
+
+/// Value of time-sorted `points` at `at`; `None` before the first observation
+fn value_at(points: &[(DateTime<Utc>, f64)], at: DateTime<Utc>, fill: Fill) -> Option<f64> {
+    let after = points.partition_point(|(t, _)| *t <= at);
+    let (prev_t, prev) = *points.get(after.checked_sub(1)?)?;
+    match (fill, points.get(after)) {
+        (Fill::Interpolate, Some(&(next_t, next))) if prev_t < at => {
+            let share = (at - prev_t).num_milliseconds() as f64 / (next_t - prev_t).num_milliseconds() as f64;
+            Some(prev + (next - prev) * share)
+        }
+        _ => Some(prev),
+    }
+}
+
+/// Resample observations onto every `interval_secs` boundary from the first observation to
+/// the last. Unsorted input is sorted; of observations sharing a timestamp the one given
+/// last wins.
+pub fn resample(points: &[(DateTime<Utc>, f64)], interval_secs: u32, fill: Fill) -> Vec<(DateTime<Utc>, f64)> {
+    let mut sorted = points.to_vec();
+    sorted.sort_by_key(|(t, _)| *t);
+    let (Some(&(start, _)), Some(&(end, _))) = (sorted.first(), sorted.last()) else {
+        return vec![];
+    };
+    grid(start, end, interval_secs)
+        .into_iter()
+        .filter_map(|at| Some((at, value_at(&sorted, at, fill)?)))
+        .collect()
+}
+
+/// Resample every series onto the `interval_secs` boundaries they all cover (the latest
+/// start to the earliest end); empty if any series is empty or they don't overlap
+pub fn align(series: &[Vec<(DateTime<Utc>, f64)>], interval_secs: u32, fill: Fill) -> AlignedSeries {
+    let sorted: Vec<Vec<(DateTime<Utc>, f64)>> = series
+        .iter()
+        .map(|points| {
+            let mut points = points.clone();
+            points.sort_by_key(|(t, _)| *t);
+            points
+        })
+        .collect();
+    if sorted.is_empty() || sorted.iter().any(Vec::is_empty) {
+        return AlignedSeries::default();
+    }
+    let start = sorted.iter().map(|s| s[0].0).max().expect("series is non-empty");
+    let end = sorted.iter().map(|s| s[s.len() - 1].0).min().expect("series is non-empty");
+
+    let index = grid(start, end, interval_secs);
+    let values = sorted
+        .iter()
+        .map(|points| index.iter().map(|at| value_at(points, *at, fill).expect("index is within every series")).collect())
+        .collect();
+    AlignedSeries { index, values }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(secs: i64) -> DateTime<Utc> {
+        Utc.timestamp_opt(secs, 0).unwrap()
+    }
+
+    #[test]
+    fn test_resample_irregular_points() {
+        let points = vec![(at(3_700), 30.0), (at(50), 10.0), (at(1_900), 20.0)];
+        let forward = resample(&points, 600, Fill::Forward);
+        let times: Vec<i64> = forward.iter().map(|(t, _)| t.timestamp()).collect();
+        assert_eq!(times, vec![600, 1_200, 1_800, 2_400, 3_000, 3_600]);
+        assert_eq!(forward.iter().map(|(_, v)| *v).collect::<Vec<_>>(), vec![10.0, 10.0, 10.0, 20.0, 20.0, 20.0]);
+
+        let interpolated = resample(&points, 1_800, Fill::Interpolate);
+        // 1800 lies between (50, 10) and (1900, 20); 3600 between (1900, 20) and (3700, 30)
+        assert!((interpolated[0].1 - (10.0 + 10.0 * 1_750.0 / 1_850.0)).abs() < 1e-9);
+        assert!((interpolated[1].1 - (20.0 + 10.0 * 1_700.0 / 1_800.0)).abs() < 1e-9);
+
+        assert!(resample(&[], 60, Fill::Forward).is_empty());
+        assert_eq!(resample(&[(at(120), 1.0)], 60, Fill::Interpolate), vec![(at(120), 1.0)]);
+    }
+
+    #[test]
+    fn test_align_on_the_common_range() {
+        let a = vec![(at(0), 1.0), (at(300), 2.0), (at(900), 3.0)];
+        let b = vec![(at(200), 10.0), (at(700), 20.0), (at(1_500), 30.0)];
+        let aligned = align(&[a.clone(), b], 300, Fill::Forward);
+        assert_eq!(aligned.index, vec![at(300), at(600), at(900)]);
+        assert_eq!(aligned.values, vec![vec![2.0, 2.0, 3.0], vec![10.0, 10.0, 20.0]]);
+
+        let disjoint = vec![(at(5_000), 1.0)];
+        assert!(align(&[a.clone(), disjoint], 300, Fill::Forward).index.is_empty());
+        assert!(align(&[a, vec![]], 300, Fill::Forward).index.is_empty());
+    }
+}
@@ -0,0 +1,497 @@
+//! Uniswap V3 fixed-point math, ported from the core `TickMath`/`FullMath` and periphery
+//! `LiquidityAmounts` libraries so results match the contracts to the wei.
+//!
+//! Square-root prices are Q64.96 (`sqrtPriceX96`); amounts round down like
+//! `LiquidityAmounts` does. Prices in whole token units are computed from them with integer
+//! math into a `Decimal`, never through `1.0001^tick` in floating point.
+
+use anyhow::{bail, Result};
+use ethereum_types::{U256, U512};
+use rust_decimal::prelude::FromPrimitive;
+use rust_decimal::Decimal;
+
+pub const MIN_TICK: i32 = -887_272;
+pub const MAX_TICK: i32 = 887_272;
+
+/// `getSqrtRatioAtTick(MIN_TICK)`
+pub const MIN_SQRT_RATIO: U256 = U256([4_295_128_739, 0, 0, 0]);
+/// `getSqrtRatioAtTick(MAX_TICK)`
+pub const MAX_SQRT_RATIO: U256 = U256([0x5d95_1d52_6398_8d26, 0xefd1_fc6a_5064_8849, 0xfffd_8963, 0]);
+
+const RESOLUTION: usize = 96;
+/// Decimal places kept by exact prices (the most a `Decimal` holds)
+const PRICE_SCALE: u32 = 28;
+/// Bound on the decimal exponents of price conversions, far beyond real token decimals
+const MAX_PRICE_EXPONENT: u32 = 120;
+
+fn q96() -> U256 {
+    U256::one() << RESOLUTION
+}
+
+/// `a * b / denominator` with a 512-bit intermediate, rounding down
+pub fn mul_div(a: U256, b: U256, denominator: U256) -> Result<U256> {
+    if denominator.is_zero() {
+        bail!("mul_div by zero");
+    }
+    let result = U512::from(a) * U512::from(b) / U512::from(denominator);
+    U256::try_from(result).map_err(|_| anyhow::anyhow!("mul_div overflows 256 bits"))
+}
+
+/// `a * b / denominator`, rounding up
+pub fn mul_div_rounding_up(a: U256, b: U256, denominator: U256) -> Result<U256> {
+    let result = mul_div(a, b, denominator)?;
+    if (U512::from(a) * U512::from(b) % U512::from(denominator)).is_zero() {
+        return Ok(result);
+    }
+    result
+        .checked_add(U256::one())
+        .ok_or_else(|| anyhow::anyhow!("mul_div_rounding_up overflows 256 bits"))
+}
+
+/// ABI word of an `int24` (or any `i32`), sign-extended to 256 bits
+pub fn int24_word(value: i32) -> U256 {
+    if value < 0 {
+        U256::MAX - U256::from((-(value as i64) - 1) as u64)
+    } else {
+        U256::from(value as u64)
+    }
+}
+
+/// Decode an ABI `int24` word such as a tick. Words that aren't a sign-extended 24-bit value
+/// are rejected rather than truncated.
+pub fn decode_int24(word: U256) -> Result<i32> {
+    // Sign-extend bit 23 of the low 24 bits
+    let value = ((word.low_u32() << 8) as i32) >> 8;
+    if int24_word(value) != word {
+        bail!("{:#x} is not a sign-extended int24", word);
+    }
+    Ok(value)
+}
+
+/// `sqrt(1.0001^tick) * 2^96`, exactly as `TickMath.getSqrtRatioAtTick`
+pub fn sqrt_ratio_at_tick(tick: i32) -> Result<U256> {
+    if !(MIN_TICK..=MAX_TICK).contains(&tick) {
+        bail!("tick {} outside [{}, {}]", tick, MIN_TICK, MAX_TICK);
+    }
+    // 2^128 / sqrt(1.0001)^(2^i), for each bit i of |tick|
+    const FACTORS: [u128; 19] = [
+        0xfff9_7272_373d_4132_59a4_6990_580e_213a,
+        0xfff2_e50f_5f65_6932_ef12_357c_f3c7_fdcc,
+        0xffe5_caca_7e10_e4e6_1c36_24ea_a094_1cd0,
+        0xffcb_9843_d60f_6159_c9db_5883_5c92_6644,
+        0xff97_3b41_fa98_c081_472e_6896_dfb2_54c0,
+        0xff2e_a164_66c9_6a38_43ec_78b3_26b5_2861,
+        0xfe5d_ee04_6a99_a2a8_11c4_61f1_969c_3053,
+        0xfcbe_86c7_900a_88ae_dcff_c83b_479a_a3a4,
+        0xf987_a725_3ac4_1317_6f2b_074c_f781_5e54,
+        0xf339_2b08_22b7_0005_940c_7a39_8e4b_70f3,
+        0xe715_9475_a2c2_9b74_43b2_9c7f_a6e8_89d9,
+        0xd097_f3bd_fd20_22b8_845a_d8f7_92aa_5825,
+        0xa9f7_4646_2d87_0fdf_8a65_dc1f_90e0_61e5,
+        0x70d8_69a1_56d2_a1b8_90bb_3df6_2baf_32f7,
+        0x31be_135f_97d0_8fd9_8123_1505_542f_cfa6,
+        0x9aa_508b_5b7a_84e1_c677_de54_f3e9_9bc9,
+        0x5d_6af8_dedb_8119_6699_c329_225e_e604,
+        0x2216_e584_f5fa_1ea9_2604_1bed_fe98,
+        0x48a_1703_91f7_dc42_444e_8fa2,
+    ];
+    let abs_tick = tick.unsigned_abs();
+    let mut ratio = if abs_tick & 1 != 0 {
+        U256::from(0xfffc_b933_bd6f_ad37_aa2d_162d_1a59_4001u128)
+    } else {
+        U256::one() << 128
+    };
+    for (bit, factor) in FACTORS.iter().enumerate() {
+        if abs_tick & (2 << bit) != 0 {
+            ratio = (ratio * U256::from(*factor)) >> 128;
+        }
+    }
+    if tick > 0 {
+        ratio = U256::MAX / ratio;
+    }
+    // Q128.128 to Q64.96, rounding up so that tick_at_sqrt_ratio round-trips
+    let round_up = if (ratio & U256::from(u32::MAX)).is_zero() { 0 } else { 1 };
+    Ok((ratio >> 32) + round_up)
+}
+
+/// Greatest tick whose sqrt ratio is at most `sqrt_price_x96`, as `TickMath.getTickAtSqrtRatio`
+pub fn tick_at_sqrt_ratio(sqrt_price_x96: U256) -> Result<i32> {
+    if sqrt_price_x96 < MIN_SQRT_RATIO || sqrt_price_x96 >= MAX_SQRT_RATIO {
+        bail!("sqrt price {} outside [MIN_SQRT_RATIO, MAX_SQRT_RATIO)", sqrt_price_x96);
+    }
+    // Binary search over the exact forward mapping, which is strictly increasing
+    let (mut low, mut high) = (MIN_TICK, MAX_TICK);
+    while low < high {
+        let mid = low + (high - low + 1) / 2;
+        if sqrt_ratio_at_tick(mid)? <= sqrt_price_x96 {
+            low = mid;
+        } else {
+            high = mid - 1;
+        }
+    }
+    Ok(low)
+}
+
+/// Tick spacing of a fee tier (in hundredths of a basis point) as enabled on the factory;
+/// `None` for tiers it doesn't know
+pub fn tick_spacing(fee: u32) -> Option<i32> {
+    match fee {
+        100 => Some(1),
+        500 => Some(10),
+        3_000 => Some(60),
+        10_000 => Some(200),
+        _ => None,
+    }
+}
+
+/// Lowest tick a position can use with `spacing`
+pub fn min_usable_tick(spacing: i32) -> i32 {
+    MIN_TICK / spacing * spacing
+}
+
+/// Highest tick a position can use with `spacing`
+pub fn max_usable_tick(spacing: i32) -> i32 {
+    MAX_TICK / spacing * spacing
+}
+
+/// Direction to snap a tick that isn't a multiple of the spacing
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TickRounding {
+    Down,
+    Up,
+    Nearest,
+}
+
+/// Snap `tick` to an initializable tick for `spacing`, within the usable bounds
+pub fn align_tick(tick: i32, spacing: i32, rounding: TickRounding) -> i32 {
+    let down = tick.div_euclid(spacing) * spacing;
+    let aligned = match rounding {
+        TickRounding::Down => down,
+        TickRounding::Up if down == tick => down,
+        TickRounding::Up => down + spacing,
+        TickRounding::Nearest if tick - down >= spacing - (tick - down) => down + spacing,
+        TickRounding::Nearest => down,
+    };
+    aligned.clamp(min_usable_tick(spacing), max_usable_tick(spacing))
+}
+
+fn exp10(exponent: u32) -> Result<U512> {
+    if exponent > MAX_PRICE_EXPONENT {
+        bail!("decimal exponent {} out of range", exponent);
+    }
+    Ok(U512::exp10(exponent as usize))
+}
+
+/// `ratio_x192 / 2^192` (a raw token1 per token0 ratio) in whole units, rounded down to 28
+/// decimal places, or fewer when the integer part needs the precision
+fn price_from_ratio_x192(ratio_x192: U512, decimals0: u8, decimals1: u8) -> Result<Decimal> {
+    let exponent = decimals0 as i32 - decimals1 as i32 + PRICE_SCALE as i32;
+    let raw = ratio_x192 >> (2 * RESOLUTION);
+    let mut mantissa = if exponent >= 0 {
+        // Multiply before shifting out the fraction so it contributes digits
+        let scaled = ratio_x192
+            .checked_mul(exp10(exponent as u32)?)
+            .ok_or_else(|| anyhow::anyhow!("price overflows"))?;
+        scaled >> (2 * RESOLUTION)
+    } else {
+        raw / exp10(exponent.unsigned_abs())?
+    };
+    let mut scale = PRICE_SCALE;
+    let max_mantissa = U512::one() << 96;
+    while mantissa >= max_mantissa {
+        if scale == 0 {
+            bail!("price overflows a Decimal");
+        }
+        mantissa /= U512::from(10u8);
+        scale -= 1;
+    }
+    Ok(Decimal::from_i128_with_scale(mantissa.low_u128() as i128, scale).normalize())
+}
+
+/// Price at `sqrt_price_x96` as token1 per token0 in whole units
+pub fn price_at_sqrt_ratio(sqrt_price_x96: U256, decimals0: u8, decimals1: u8) -> Result<Decimal> {
+    price_from_ratio_x192(U512::from(sqrt_price_x96) * U512::from(sqrt_price_x96), decimals0, decimals1)
+}
+
+/// Price at `tick` as token1 per token0 in whole units; the inverse of [`tick_at_price`]
+pub fn price_at_tick(tick: i32, decimals0: u8, decimals1: u8) -> Result<Decimal> {
+    price_at_sqrt_ratio(sqrt_ratio_at_tick(tick)?, decimals0, decimals1)
+}
+
+/// Geometric mean of the prices at the range's bounds: the price halfway through it in ticks
+pub fn mid_price(tick_lower: i32, tick_upper: i32, decimals0: u8, decimals1: u8) -> Result<Decimal> {
+    let ratio_x192 = U512::from(sqrt_ratio_at_tick(tick_lower)?) * U512::from(sqrt_ratio_at_tick(tick_upper)?);
+    price_from_ratio_x192(ratio_x192, decimals0, decimals1)
+}
+
+/// Greatest tick at or below `price`, quoted as token1 per token0 in whole units
+pub fn tick_at_price(price: f64, decimals0: u8, decimals1: u8) -> Result<i32> {
+    if !(price.is_finite() && price > 0.0) {
+        bail!("price {} must be positive and finite", price);
+    }
+    let price = Decimal::from_f64(price)
+        .filter(|p| !p.is_zero())
+        .ok_or_else(|| anyhow::anyhow!("price {} is outside the tick range", price))?;
+    // sqrtPriceX96 = floor(sqrt(mantissa / 10^scale * 10^(decimals1 - decimals0) * 2^192))
+    let exponent = decimals1 as i32 - decimals0 as i32 - price.scale() as i32;
+    let scaled = U512::from(price.mantissa().unsigned_abs()) << (2 * RESOLUTION);
+    let ratio_x192 = if exponent >= 0 {
+        scaled.checked_mul(exp10(exponent as u32)?).unwrap_or(U512::MAX)
+    } else {
+        scaled / exp10(exponent.unsigned_abs())?
+    };
+    let sqrt_price_x96 = ratio_x192.integer_sqrt();
+    if sqrt_price_x96 < U512::from(MIN_SQRT_RATIO) || sqrt_price_x96 >= U512::from(MAX_SQRT_RATIO) {
+        bail!("price {} is outside the tick range", price);
+    }
+    tick_at_sqrt_ratio(U256::try_from(sqrt_price_x96).expect("below MAX_SQRT_RATIO"))
+}
+
+/// Mintable `(tick_lower, tick_upper)` covering `[price_lower, price_upper]`: the bounds are
+/// widened outwards to the spacing, and to one spacing when they collapse
+pub fn range_for_prices(price_lower: f64, price_upper: f64, decimals0: u8, decimals1: u8, spacing: i32) -> Result<(i32, i32)> {
+    if price_lower >= price_upper {
+        bail!("price range [{}, {}] is empty", price_lower, price_upper);
+    }
+    let lower = align_tick(tick_at_price(price_lower, decimals0, decimals1)?, spacing, TickRounding::Down);
+    let upper = align_tick(tick_at_price(price_upper, decimals0, decimals1)?, spacing, TickRounding::Up);
+    if lower < upper {
+        Ok((lower, upper))
+    } else if upper + spacing <= max_usable_tick(spacing) {
+        Ok((lower, upper + spacing))
+    } else {
+        Ok((lower - spacing, upper))
+    }
+}
+
+fn sorted(a: U256, b: U256) -> (U256, U256) {
+    if a > b {
+        (b, a)
+    } else {
+        (a, b)
+    }
+}
+
+/// Token0 held by `liquidity` across a price range
+pub fn amount0_for_liquidity(sqrt_ratio_a: U256, sqrt_ratio_b: U256, liquidity: u128) -> U256 {
+    let (a, b) = sorted(sqrt_ratio_a, sqrt_ratio_b);
+    if a.is_zero() {
+        return U256::zero();
+    }
+    // (liquidity << 96) * (b - a) / b <= liquidity << 96, so it always fits
+    mul_div(U256::from(liquidity) << RESOLUTION, b - a, b).expect("bounded by liquidity << 96") / a
+}
+
+/// Token1 held by `liquidity` across a price range
+pub fn amount1_for_liquidity(sqrt_ratio_a: U256, sqrt_ratio_b: U256, liquidity: u128) -> U256 {
+    let (a, b) = sorted(sqrt_ratio_a, sqrt_ratio_b);
+    // liquidity * (b - a) / 2^96 < 2^128 * 2^160 / 2^96
+    mul_div(U256::from(liquidity), b - a, q96()).expect("bounded by 2^192")
+}
+
+/// Token amounts of `liquidity` in the range `[sqrt_ratio_a, sqrt_ratio_b]` at the current price
+pub fn amounts_for_liquidity(sqrt_price_x96: U256, sqrt_ratio_a: U256, sqrt_ratio_b: U256, liquidity: u128) -> (U256, U256) {
+    let (a, b) = sorted(sqrt_ratio_a, sqrt_ratio_b);
+    if sqrt_price_x96 <= a {
+        (amount0_for_liquidity(a, b, liquidity), U256::zero())
+    } else if sqrt_price_x96 < b {
+        (amount0_for_liquidity(sqrt_price_x96, b, liquidity), amount1_for_liquidity(a, sqrt_price_x96, liquidity))
+    } else {
+        (U256::zero(), amount1_for_liquidity(a, b, liquidity))
+    }
+}
+
+fn to_u128(value: U256) -> Result<u128> {
+    if value > U256::from(u128::MAX) {
+        bail!("liquidity {} overflows uint128", value);
+    }
+    Ok(value.as_u128())
+}
+
+/// Liquidity `amount0` buys across a price range
+pub fn liquidity_for_amount0(sqrt_ratio_a: U256, sqrt_ratio_b: U256, amount0: U256) -> Result<u128> {
+    let (a, b) = sorted(sqrt_ratio_a, sqrt_ratio_b);
+    let intermediate = mul_div(a, b, q96())?;
+    to_u128(mul_div(amount0, intermediate, b - a)?)
+}
+
+/// Liquidity `amount1` buys across a price range
+pub fn liquidity_for_amount1(sqrt_ratio_a: U256, sqrt_ratio_b: U256, amount1: U256) -> Result<u128> {
+    let (a, b) = sorted(sqrt_ratio_a, sqrt_ratio_b);
+    to_u128(mul_div(amount1, q96(), b - a)?)
+}
+
+/// Most liquidity `amount0` and `amount1` can mint in the range at the current price
+pub fn liquidity_for_amounts(
+    sqrt_price_x96: U256,
+    sqrt_ratio_a: U256,
+    sqrt_ratio_b: U256,
+    amount0: U256,
+    amount1: U256,
+) -> Result<u128> {
+    let (a, b) = sorted(sqrt_ratio_a, sqrt_ratio_b);
+    if a == b {
+        bail!("empty price range");
+    }
+    if sqrt_price_x96 <= a {
+        liquidity_for_amount0(a, b, amount0)
+    } else if sqrt_price_x96 < b {
+        Ok(liquidity_for_amount0(sqrt_price_x96, b, amount0)?.min(liquidity_for_amount1(a, sqrt_price_x96, amount1)?))
+    } else {
+        liquidity_for_amount1(a, b, amount1)
+    }
+}
+
+/// `sqrt_price_x96 / 2^96` as a float, for estimates that don't need exact amounts
+pub fn sqrt_ratio_to_f64(sqrt_price_x96: U256) -> f64 {
+    sqrt_price_x96.to_string().parse::<f64>().unwrap_or(0.0) / 2f64.powi(RESOLUTION as i32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn u(value: &str) -> U256 {
+        U256::from_dec_str(value).unwrap()
+    }
+
+    #[test]
+    fn test_sqrt_ratio_at_tick_matches_tick_math() {
+        assert_eq!(sqrt_ratio_at_tick(MIN_TICK).unwrap(), MIN_SQRT_RATIO);
+        assert_eq!(sqrt_ratio_at_tick(MAX_TICK).unwrap(), MAX_SQRT_RATIO);
+        assert_eq!(sqrt_ratio_at_tick(0).unwrap(), q96());
+        assert_eq!(sqrt_ratio_at_tick(50).unwrap(), u("79426470787362580746886972461"));
+        assert_eq!(sqrt_ratio_at_tick(-50).unwrap(), u("79030349367926598376800521322"));
+        assert_eq!(sqrt_ratio_at_tick(200_000).unwrap(), u("1744244129640337381386292603617838"));
+        assert_eq!(sqrt_ratio_at_tick(-200_000).unwrap(), u("3598751819609688046946419"));
+        assert!(sqrt_ratio_at_tick(MAX_TICK + 1).is_err());
+        assert!(sqrt_ratio_at_tick(MIN_TICK - 1).is_err());
+    }
+
+    #[test]
+    fn test_tick_at_sqrt_ratio_round_trips() {
+        for tick in [MIN_TICK, -200_000, -50, -1, 0, 1, 50, 200_000, MAX_TICK - 1] {
+            let ratio = sqrt_ratio_at_tick(tick).unwrap();
+            assert_eq!(tick_at_sqrt_ratio(ratio).unwrap(), tick);
+            if tick > MIN_TICK {
+                assert_eq!(tick_at_sqrt_ratio(ratio - 1).unwrap(), tick - 1);
+            }
+        }
+        assert_eq!(tick_at_sqrt_ratio(MAX_SQRT_RATIO - 1).unwrap(), MAX_TICK - 1);
+        assert!(tick_at_sqrt_ratio(MAX_SQRT_RATIO).is_err());
+        assert!(tick_at_sqrt_ratio(MIN_SQRT_RATIO - 1).is_err());
+    }
+
+    // Vectors from the periphery LiquidityAmounts tests; prices are encodePriceSqrt(reserve1, reserve0)
+    const PRICE_1_1: &str = "79228162514264337593543950336";
+    const PRICE_100_110: &str = "75541088972021052632782079082";
+    const PRICE_110_100: &str = "83095197869223157896060286990";
+    const PRICE_99_110: &str = "75162434512514379355924140470";
+    const PRICE_111_100: &str = "83472048772503575395058907992";
+
+    #[test]
+    fn test_liquidity_for_amounts() {
+        let (a, b) = (u(PRICE_100_110), u(PRICE_110_100));
+        let (amount0, amount1) = (U256::from(100), U256::from(200));
+        assert_eq!(liquidity_for_amounts(u(PRICE_1_1), a, b, amount0, amount1).unwrap(), 2148);
+        assert_eq!(liquidity_for_amounts(u(PRICE_99_110), a, b, amount0, amount1).unwrap(), 1048);
+        assert_eq!(liquidity_for_amounts(u(PRICE_111_100), a, b, amount0, amount1).unwrap(), 2097);
+        // Range bounds in either order
+        assert_eq!(liquidity_for_amounts(u(PRICE_1_1), b, a, amount0, amount1).unwrap(), 2148);
+        assert!(liquidity_for_amounts(u(PRICE_1_1), a, a, amount0, amount1).is_err());
+    }
+
+    #[test]
+    fn test_amounts_for_liquidity() {
+        let (a, b) = (u(PRICE_100_110), u(PRICE_110_100));
+        assert_eq!(amounts_for_liquidity(u(PRICE_1_1), a, b, 2148), (U256::from(99), U256::from(99)));
+        assert_eq!(amounts_for_liquidity(u(PRICE_99_110), a, b, 1048), (U256::from(99), U256::zero()));
+        assert_eq!(amounts_for_liquidity(u(PRICE_111_100), a, b, 2097), (U256::zero(), U256::from(199)));
+        // On the lower bound everything is token0, on the upper bound token1
+        assert_eq!(amounts_for_liquidity(a, a, b, 2148).1, U256::zero());
+        assert_eq!(amounts_for_liquidity(b, a, b, 2148).0, U256::zero());
+    }
+
+    #[test]
+    fn test_mul_div() {
+        let max = U256::MAX;
+        assert_eq!(mul_div(max, max, max).unwrap(), max);
+        assert_eq!(mul_div(U256::from(7), U256::from(3), U256::from(2)).unwrap(), U256::from(10));
+        assert_eq!(mul_div_rounding_up(U256::from(7), U256::from(3), U256::from(2)).unwrap(), U256::from(11));
+        assert_eq!(mul_div_rounding_up(U256::from(6), U256::from(3), U256::from(2)).unwrap(), U256::from(9));
+        assert!(mul_div(max, max, U256::one()).is_err());
+        assert!(mul_div(max, max, U256::zero()).is_err());
+    }
+
+    #[test]
+    fn test_tick_alignment() {
+        assert_eq!(tick_spacing(3_000), Some(60));
+        assert_eq!(tick_spacing(2_500), None);
+        assert_eq!(min_usable_tick(60), -887_220);
+        assert_eq!(max_usable_tick(200), 887_200);
+
+        assert_eq!(align_tick(-201_234, 60, TickRounding::Down), -201_240);
+        assert_eq!(align_tick(-201_234, 60, TickRounding::Up), -201_180);
+        assert_eq!(align_tick(-201_234, 60, TickRounding::Nearest), -201_240);
+        assert_eq!(align_tick(-201_205, 60, TickRounding::Nearest), -201_180);
+        assert_eq!(align_tick(120, 60, TickRounding::Up), 120);
+        assert_eq!(align_tick(MIN_TICK, 60, TickRounding::Down), -887_220);
+    }
+
+    #[test]
+    fn test_int24_decoding() {
+        for tick in [MIN_TICK, -198_080, -1, 0, 1, MAX_TICK, -(1 << 23), (1 << 23) - 1] {
+            assert_eq!(decode_int24(int24_word(tick)).unwrap(), tick);
+        }
+        // A negative tick is sign-extended across the whole word
+        assert_eq!(int24_word(-1), U256::MAX);
+        // Bit 23 set without the extension, or bits beyond int24, are malformed
+        assert!(decode_int24(U256::from(0x80_0000u32)).is_err());
+        assert!(decode_int24(U256::from(1u64 << 24)).is_err());
+    }
+
+    #[test]
+    fn test_exact_prices() {
+        assert_eq!(price_at_tick(0, 18, 18).unwrap(), Decimal::ONE);
+        assert_eq!(price_at_sqrt_ratio(u(PRICE_1_1), 6, 6).unwrap(), Decimal::ONE);
+        // The same raw ratio with 18/6 decimals is 10^12 in whole units
+        assert_eq!(price_at_sqrt_ratio(u(PRICE_1_1), 18, 6).unwrap(), Decimal::new(1_000_000_000_000, 0));
+        assert_eq!(price_at_sqrt_ratio(u(PRICE_1_1), 6, 18).unwrap(), Decimal::new(1, 12));
+        // 1.0001^100 = 1.0100496620928...
+        let price = price_at_tick(100, 18, 18).unwrap();
+        assert!((price - Decimal::new(10_100_496_620_928, 13)).abs() < Decimal::new(1, 12), "{}", price);
+        // Inverse ticks give reciprocal prices
+        let product = price * price_at_tick(-100, 18, 18).unwrap();
+        assert!((product - Decimal::ONE).abs() < Decimal::new(1, 20), "{}", product);
+        // Near the extremes the price leaves what a Decimal holds: an error above, zero below
+        assert!(price_at_tick(MAX_TICK, 18, 18).is_err());
+        assert_eq!(price_at_tick(MIN_TICK, 18, 18).unwrap(), Decimal::ZERO);
+
+        let mid = mid_price(-60, 60, 18, 18).unwrap();
+        assert!((mid - Decimal::ONE).abs() < Decimal::new(1, 20), "{}", mid);
+    }
+
+    #[test]
+    fn test_price_ticks() {
+        // WETH (18) / USDC (6) at 2,500 USDC per WETH
+        let tick = tick_at_price(2_500.0, 18, 6).unwrap();
+        assert_eq!(tick, -198_080);
+        let price = price_at_tick(tick, 18, 6).unwrap();
+        let target = Decimal::new(2_500, 0);
+        assert!(price <= target && price > target / Decimal::new(10_001, 4), "{}", price);
+        assert_eq!(tick_at_price(1.0, 18, 18).unwrap(), 0);
+        assert_eq!(tick_at_price(1.00015, 18, 18).unwrap(), 1);
+        assert_eq!(tick_at_price(0.99995, 18, 18).unwrap(), -1);
+        assert!(tick_at_price(0.0, 18, 6).is_err());
+        assert!(tick_at_price(1e60, 18, 18).is_err());
+
+        let (lower, upper) = range_for_prices(2_000.0, 3_000.0, 18, 6, 10).unwrap();
+        assert!(lower % 10 == 0 && upper % 10 == 0);
+        assert!(price_at_tick(lower, 18, 6).unwrap() <= Decimal::new(2_000, 0));
+        assert!(price_at_tick(upper, 18, 6).unwrap() >= Decimal::new(3_000, 0));
+        // A range inside one spacing still gets a mintable width
+        let (lower, upper) = range_for_prices(2_500.0, 2_500.1, 18, 6, 200).unwrap();
+        assert_eq!(upper - lower, 200);
+        assert!(range_for_prices(3_000.0, 2_000.0, 18, 6, 10).is_err());
+    }
+}
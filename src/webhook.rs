@@ -0,0 +1,125 @@
+use anyhow::{Context, Result};
+use hmac::{Hmac, Mac};
+use reqwest::Client;
+use sha2::Sha256;
+use std::time::Duration;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::time::sleep;
+use tracing::{info, warn};
+
+use crate::config::WebhookConfig;
+use crate::events::{Event, EventBus};
+
+/// Header carrying the hex HMAC-SHA256 of `"{timestamp}.{body}"`
+pub const SIGNATURE_HEADER: &str = "X-Origins-Signature";
+/// Header carrying the unix timestamp included in the signature
+pub const TIMESTAMP_HEADER: &str = "X-Origins-Timestamp";
+
+/// Generic webhook sink: POSTs each recommendation event as signed JSON
+pub struct WebhookSink {
+    http: Client,
+    config: WebhookConfig,
+}
+
+/// Compute the `sha256=<hex>` signature for a payload
+pub fn sign_payload(secret: &str, timestamp: i64, body: &str) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes())
+        .expect("HMAC accepts keys of any length");
+    mac.update(timestamp.to_string().as_bytes());
+    mac.update(b".");
+    mac.update(body.as_bytes());
+    format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+}
+
+impl WebhookSink {
+    pub fn new(config: WebhookConfig) -> Self {
+        let http = Client::builder()
+            .user_agent("origins-webhook/0.1")
+            .timeout(Duration::from_secs(10))
+            .build()
+            .expect("failed to build reqwest client");
+        Self { http, config }
+    }
+
+    /// Deliver events from the bus until it closes
+    pub async fn run(self, bus: EventBus) {
+        let mut rx = bus.subscribe();
+        loop {
+            match rx.recv().await {
+                Ok(event) => self.deliver(&event).await,
+                Err(RecvError::Lagged(skipped)) => {
+                    warn!(target: "webhook", skipped, "webhook sink lagged, events dropped");
+                }
+                Err(RecvError::Closed) => break,
+            }
+        }
+    }
+
+    async fn deliver(&self, event: &Event) {
+        let body = match serde_json::to_string(event) {
+            Ok(body) => body,
+            Err(e) => {
+                warn!(target: "webhook", "failed to encode event: {}", e);
+                return;
+            }
+        };
+        for url in &self.config.urls {
+            if let Err(e) = self.post_with_retry(url, &body).await {
+                warn!(target: "webhook", %url, "webhook delivery failed: {:#}", e);
+            }
+        }
+    }
+
+    async fn post_with_retry(&self, url: &str, body: &str) -> Result<()> {
+        let max_attempts = self.config.max_attempts.max(1);
+        let mut attempt: u32 = 0;
+        loop {
+            let timestamp = chrono::Utc::now().timestamp();
+            let mut req = self
+                .http
+                .post(url)
+                .header("Content-Type", "application/json")
+                .header(TIMESTAMP_HEADER, timestamp.to_string())
+                .body(body.to_string());
+            if let Some(secret) = self.config.secret.as_deref().filter(|s| !s.is_empty()) {
+                req = req.header(SIGNATURE_HEADER, sign_payload(secret, timestamp, body));
+            }
+
+            attempt += 1;
+            let result = req.send().await.with_context(|| format!("posting webhook to {}", url));
+            match result {
+                Ok(resp) if resp.status().is_success() => {
+                    info!(target: "webhook", %url, attempt, "webhook delivered");
+                    return Ok(());
+                }
+                // Client errors (other than rate limiting) will not succeed on retry
+                Ok(resp) if resp.status().is_client_error() && resp.status().as_u16() != 429 => {
+                    return Err(anyhow::anyhow!("webhook rejected, status={}", resp.status()));
+                }
+                Ok(resp) if attempt >= max_attempts => {
+                    return Err(anyhow::anyhow!("webhook failed after {} attempts, status={}", attempt, resp.status()));
+                }
+                Err(e) if attempt >= max_attempts => return Err(e),
+                _ => {}
+            }
+            // Exponential backoff: 500ms, 1500ms, 4500ms, ...
+            let backoff_ms = 500u64 * 3u64.pow(attempt - 1);
+            info!(target: "webhook", %url, attempt, backoff_ms, "webhook delivery failed, backing off and retrying");
+            sleep(Duration::from_millis(backoff_ms)).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sign_payload() {
+        // HMAC-SHA256("secret", "1700000000.{}")
+        let sig = sign_payload("secret", 1_700_000_000, "{}");
+        assert_eq!(sig, "sha256=b8569b78799ff9e3cbff0fc2d63a33a2b57f3282abd07c37ae5e8e7d79a5f163");
+        assert_ne!(sig, sign_payload("other", 1_700_000_000, "{}"));
+        assert_ne!(sig, sign_payload("secret", 1_700_000_001, "{}"));
+    }
+}